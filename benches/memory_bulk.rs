@@ -0,0 +1,87 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wasmer::*;
+
+/// The lengths of the `memory.copy` and `memory.fill` operations, from
+/// ones that are cheaper inline to ones that are cheaper through the
+/// host's `memmove` and `memset`.
+static LENGTHS: &[u32] = &[8, 32, 128, 1024, 16384];
+
+/// The number of operations done by each call, so the call overhead
+/// doesn't hide the cost of the operations themselves.
+const ITERATIONS: i32 = 1000;
+
+fn bulk_memory_wat() -> String {
+    let mut wat = String::from("(module\n    (memory 1)\n");
+    for len in LENGTHS {
+        wat.push_str(&format!(
+            r#"    (func (export "copy_{len}") (param $n i32)
+        (loop $continue
+            (memory.copy (i32.const 32768) (i32.const 0) (i32.const {len}))
+            (br_if $continue (local.tee $n (i32.sub (local.get $n) (i32.const 1))))))
+    (func (export "fill_{len}") (param $n i32)
+        (loop $continue
+            (memory.fill (i32.const 0) (local.get $n) (i32.const {len}))
+            (br_if $continue (local.tee $n (i32.sub (local.get $n) (i32.const 1))))))
+"#,
+            len = len
+        ));
+    }
+    wat.push(')');
+    wat
+}
+
+pub fn run_bulk_memory_operations(store: &Store, compiler_name: &str, c: &mut Criterion) {
+    let module = Module::new(store, bulk_memory_wat()).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+
+    for operation in &["copy", "fill"] {
+        for len in LENGTHS {
+            let f: TypedFunction<i32, ()> = instance
+                .exports
+                .get_native_function(&format!("{}_{}", operation, len))
+                .unwrap();
+
+            c.bench_function(
+                &format!("memory.{} {} bytes {}", operation, len, compiler_name),
+                |b| b.iter(|| f.call(black_box(ITERATIONS)).unwrap()),
+            );
+        }
+    }
+}
+
+fn run_bulk_memory_benchmarks(_c: &mut Criterion) {
+    #[cfg(feature = "cranelift")]
+    {
+        let store = Store::new_with_engine(
+            &Universal::new(wasmer_compiler_cranelift::Cranelift::new()).engine(),
+        );
+        run_bulk_memory_operations(&store, "cranelift (runtime)", _c);
+
+        let store = Store::new_with_engine(
+            &Universal::new(wasmer_compiler_cranelift::Cranelift::new())
+                .bulk_memory_inline_threshold(128)
+                .engine(),
+        );
+        run_bulk_memory_operations(&store, "cranelift (inline up to 128 bytes)", _c);
+    }
+
+    #[cfg(feature = "singlepass")]
+    {
+        let store = Store::new_with_engine(
+            &Universal::new(wasmer_compiler_singlepass::Singlepass::new()).engine(),
+        );
+        run_bulk_memory_operations(&store, "singlepass", _c);
+    }
+
+    #[cfg(feature = "llvm")]
+    {
+        let store =
+            Store::new_with_engine(&Universal::new(wasmer_compiler_llvm::LLVM::new()).engine());
+        run_bulk_memory_operations(&store, "llvm", _c);
+    }
+}
+
+criterion_group!(benches, run_bulk_memory_benchmarks);
+
+criterion_main!(benches);
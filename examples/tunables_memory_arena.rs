@@ -0,0 +1,373 @@
+//! Custom tunables can decide where the memories of the instances live.
+//!
+//! This example allocates them from an arena, reserved once up front and
+//! split into slots of a fixed size, e.g. to keep them in memory the
+//! embedder manages itself: huge pages, NUMA-pinned memory, a memfd…
+//! Tables and globals are still created by the base tunables.
+//!
+//! You can run the example directly by executing in Wasmer root:
+//!
+//! ```shell
+//! cargo run --example tunables-memory-arena --release --features "cranelift"
+//! ```
+//!
+//! Ready?
+
+use std::alloc::{self, Layout};
+use std::cell::UnsafeCell;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+
+use wasmer::{
+    imports,
+    vm::{self, MemoryError, MemoryStyle, TableStyle, VMMemoryDefinition, VMTableDefinition},
+    wat2wasm, BaseTunables, Instance, MemoryType, Module, Pages, Store, TableType, Target,
+    Tunables, WASM_PAGE_SIZE,
+};
+use wasmer_compiler::Universal;
+use wasmer_compiler_cranelift::Cranelift;
+
+/// A block of memory split into slots of `slot_pages` pages, each
+/// holding the memory of one instance.
+struct Arena {
+    base: NonNull<u8>,
+    layout: Layout,
+    slot_pages: Pages,
+    free_slots: Mutex<Vec<usize>>,
+}
+
+// The arena only hands out disjoint slots.
+unsafe impl Send for Arena {}
+unsafe impl Sync for Arena {}
+
+impl Arena {
+    fn new(slots: usize, slot_pages: Pages) -> Self {
+        let layout = Layout::from_size_align(slots * slot_pages.bytes().0, WASM_PAGE_SIZE)
+            .expect("valid arena layout");
+        let base = NonNull::new(unsafe { alloc::alloc_zeroed(layout) })
+            .unwrap_or_else(|| alloc::handle_alloc_error(layout));
+
+        Self {
+            base,
+            layout,
+            slot_pages,
+            free_slots: Mutex::new((0..slots).rev().collect()),
+        }
+    }
+
+    fn slot_base(&self, slot: usize) -> *mut u8 {
+        unsafe { self.base.as_ptr().add(slot * self.slot_pages.bytes().0) }
+    }
+
+    fn free_slots(&self) -> usize {
+        self.free_slots.lock().unwrap().len()
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.base.as_ptr(), self.layout) }
+    }
+}
+
+/// Where the compiled code finds the base and the length of a memory.
+enum Definition {
+    /// The memory is owned by the host, and so is its definition.
+    Host(Box<UnsafeCell<VMMemoryDefinition>>),
+    /// The memory is owned by an instance, which keeps its definition.
+    Vm(NonNull<VMMemoryDefinition>),
+}
+
+/// A memory living in a slot of an arena. It never moves and can't
+/// grow beyond its slot.
+#[derive(Debug)]
+struct ArenaMemory {
+    arena: Arc<Arena>,
+    slot: usize,
+    ty: MemoryType,
+    style: MemoryStyle,
+    definition: Definition,
+    grow_lock: Mutex<()>,
+}
+
+// The definition is only written while holding `grow_lock`.
+unsafe impl Send for ArenaMemory {}
+unsafe impl Sync for ArenaMemory {}
+
+impl ArenaMemory {
+    /// Takes a slot of `arena` for a memory of type `ty`.
+    ///
+    /// # Safety
+    /// - `vm_definition_location`, if any, must be valid for the lifetime
+    ///   of the memory.
+    unsafe fn new(
+        arena: &Arc<Arena>,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: Option<NonNull<VMMemoryDefinition>>,
+    ) -> Result<Self, MemoryError> {
+        if ty.minimum > arena.slot_pages {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: ty.minimum,
+                max_allowed: arena.slot_pages,
+            });
+        }
+        let slot = arena
+            .free_slots
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or_else(|| MemoryError::Generic("the arena is full".to_string()))?;
+
+        let initial = VMMemoryDefinition {
+            base: arena.slot_base(slot),
+            current_length: ty.minimum.bytes().0,
+        };
+        let definition = match vm_definition_location {
+            Some(location) => {
+                location.as_ptr().write(initial);
+                Definition::Vm(location)
+            }
+            None => Definition::Host(Box::new(UnsafeCell::new(initial))),
+        };
+
+        Ok(Self {
+            arena: arena.clone(),
+            slot,
+            ty: *ty,
+            style: style.clone(),
+            definition,
+            grow_lock: Mutex::new(()),
+        })
+    }
+
+    fn definition(&self) -> *mut VMMemoryDefinition {
+        match &self.definition {
+            Definition::Host(definition) => definition.get(),
+            Definition::Vm(location) => location.as_ptr(),
+        }
+    }
+
+    /// The largest size the memory can grow to.
+    fn limit(&self) -> Pages {
+        self.ty.maximum.map_or(self.arena.slot_pages, |maximum| {
+            maximum.min(self.arena.slot_pages)
+        })
+    }
+}
+
+impl vm::Memory for ArenaMemory {
+    fn ty(&self) -> MemoryType {
+        let mut ty = self.ty;
+        ty.minimum = self.size();
+        ty
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        &self.style
+    }
+
+    fn size(&self) -> Pages {
+        let current_length = unsafe { (*self.definition()).current_length };
+        Pages((current_length / WASM_PAGE_SIZE) as u32)
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let _guard = self.grow_lock.lock().unwrap();
+        let current = self.size();
+        let new_size = current
+            .0
+            .checked_add(delta.0)
+            .filter(|&pages| Pages(pages) <= self.limit())
+            .ok_or(MemoryError::CouldNotGrow {
+                current,
+                attempted_delta: delta,
+            })?;
+
+        // The slot is already allocated, and zeroed: growing only makes
+        // more of it accessible.
+        unsafe { (*self.definition()).current_length = Pages(new_size).bytes().0 };
+
+        Ok(current)
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        NonNull::new(self.definition()).unwrap()
+    }
+}
+
+impl Drop for ArenaMemory {
+    fn drop(&mut self) {
+        // Zero the slot before handing it to the next memory.
+        unsafe {
+            self.arena
+                .slot_base(self.slot)
+                .write_bytes(0, self.arena.slot_pages.bytes().0)
+        };
+        self.arena.free_slots.lock().unwrap().push(self.slot);
+    }
+}
+
+impl std::fmt::Debug for Arena {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Arena")
+            .field("slot_pages", &self.slot_pages)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for Definition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Host(_) => "Host",
+            Self::Vm(_) => "Vm",
+        })
+    }
+}
+
+/// Tunables allocating the memories from an arena, and delegating
+/// everything else to the base tunables.
+pub struct ArenaTunables<T: Tunables> {
+    arena: Arc<Arena>,
+    base: T,
+}
+
+impl<T: Tunables> ArenaTunables<T> {
+    pub fn new(base: T, slots: usize, slot_pages: Pages) -> Self {
+        Self {
+            arena: Arc::new(Arena::new(slots, slot_pages)),
+            base,
+        }
+    }
+}
+
+impl<T: Tunables> Tunables for ArenaTunables<T> {
+    /// The slots are not followed by guard pages, so the compiled code
+    /// must check the bounds of every access: the memories are dynamic,
+    /// without offset guard.
+    fn memory_style(&self, _memory: &MemoryType) -> MemoryStyle {
+        MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+        }
+    }
+
+    /// Delegated to base.
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    /// Create a memory owned by the host in a slot of the arena.
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        Ok(Arc::new(unsafe {
+            ArenaMemory::new(&self.arena, ty, style, None)?
+        }))
+    }
+
+    /// Create a memory owned by the VM in a slot of the arena.
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        Ok(Arc::new(ArenaMemory::new(
+            &self.arena,
+            ty,
+            style,
+            Some(vm_definition_location),
+        )?))
+    }
+
+    /// Delegated to base.
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    /// Delegated to base.
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base.create_vm_table(ty, style, vm_definition_location)
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // A Wasm module with a memory of 1 page, which it can grow, and read
+    // and write.
+    let wasm_bytes = wat2wasm(
+        br#"
+(module
+  (memory (export "memory") 1)
+  (func (export "grow") (param i32) (result i32)
+    (memory.grow (local.get 0)))
+  (func (export "store") (param i32 i32)
+    (i32.store (local.get 0) (local.get 1)))
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0))))
+"#,
+    )?;
+
+    // Any compiler and any engine do the job here
+    let compiler = Cranelift::default();
+    let engine = Universal::new(compiler).engine();
+
+    // An arena of 2 slots of 4 pages.
+    let base = BaseTunables::for_target(&Target::default());
+    let tunables = ArenaTunables::new(base, 2, Pages(4));
+    let arena = tunables.arena.clone();
+
+    // Create a store, that holds the engine and our custom tunables
+    let store = Store::new_with_tunables(&engine, tunables);
+
+    println!("Compiling module...");
+    let module = Module::new(&store, wasm_bytes)?;
+
+    println!("Instantiating module...");
+    let instance = Instance::new(&module, &imports! {})?;
+    assert_eq!(arena.free_slots(), 1);
+
+    let grow = instance.exports.get_native_function::<i32, i32>("grow")?;
+    let store_ = instance
+        .exports
+        .get_native_function::<(i32, i32), ()>("store")?;
+    let load = instance.exports.get_native_function::<i32, i32>("load")?;
+
+    println!("Using the memory...");
+    store_.call(0x1_0000 - 4, 42)?;
+    assert_eq!(load.call(0x1_0000 - 4)?, 42);
+    // The accesses are bounds checked…
+    assert!(load.call(0x1_0000).is_err());
+    // …until the memory grows, within its slot.
+    assert_eq!(grow.call(3)?, 1);
+    assert_eq!(load.call(0x1_0000)?, 0);
+    assert_eq!(grow.call(1)?, -1);
+
+    let memory = instance.exports.get_memory("memory")?;
+    println!("Memory of this instance: {:?}", memory);
+    assert_eq!(memory.size(), Pages(4));
+
+    // The slot goes back to the arena with the instance.
+    drop(instance);
+    drop(grow);
+    drop(store_);
+    drop(load);
+    assert_eq!(arena.free_slots(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_tunables_memory_arena() -> Result<(), Box<dyn std::error::Error>> {
+    main()
+}
@@ -0,0 +1,176 @@
+#![no_main]
+
+//! Feeds adversarial iovec arrays, path lengths and directory cookies
+//! to the WASI syscalls, over an in-memory file system. The syscalls
+//! must fail with an errno, never panic.
+
+use libfuzzer_sys::{arbitrary, arbitrary::Arbitrary, fuzz_target};
+use wasmer::{Instance, Memory, Module, Store, WasmerEnv};
+use wasmer_wasi::testing::WasiTestFixture;
+
+const WAT: &str = r#"
+(module
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_read"
+    (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_readdir"
+    (func $fd_readdir (param i32 i32 i32 i64 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_prestat_dir_name"
+    (func $fd_prestat_dir_name (param i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_open"
+    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (export "fd_write" (func $fd_write))
+  (export "fd_read" (func $fd_read))
+  (export "fd_readdir" (func $fd_readdir))
+  (export "fd_prestat_dir_name" (func $fd_prestat_dir_name))
+  (export "path_open" (func $path_open)))
+"#;
+
+thread_local! {
+    // Compiled once, instantiated for each input.
+    static MODULE: Module = Module::new(&Store::default(), WAT).unwrap();
+}
+
+/// An iovec, written to the memory before the call.
+#[derive(Arbitrary, Debug)]
+struct Iovec {
+    buf: u32,
+    buf_len: u32,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Syscall {
+    FdWrite {
+        fd: u32,
+        iovs: Vec<Iovec>,
+        iovs_ptr: u32,
+        // May disagree with the iovecs written.
+        iovs_len: u32,
+        nwritten: u32,
+    },
+    FdRead {
+        fd: u32,
+        iovs: Vec<Iovec>,
+        iovs_ptr: u32,
+        iovs_len: u32,
+        nread: u32,
+    },
+    FdReaddir {
+        fd: u32,
+        buf: u32,
+        buf_len: u32,
+        cookie: u64,
+        bufused: u32,
+    },
+    FdPrestatDirName {
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    },
+    PathOpen {
+        fd: u32,
+        path: Vec<u8>,
+        path_ptr: u32,
+        path_len: u32,
+        oflags: u32,
+        fd_out: u32,
+    },
+}
+
+/// Writes `bytes` at `offset`, if they fit in the memory.
+fn poke(memory: &Memory, offset: u32, bytes: &[u8]) {
+    let _ = memory.write(offset as u64, bytes);
+}
+
+fn write_iovecs(memory: &Memory, ptr: u32, iovs: &[Iovec]) {
+    let bytes = iovs
+        .iter()
+        .flat_map(|iov| [iov.buf.to_le_bytes(), iov.buf_len.to_le_bytes()])
+        .flatten()
+        .collect::<Vec<_>>();
+    poke(memory, ptr, &bytes);
+}
+
+fuzz_target!(|syscalls: Vec<Syscall>| {
+    let mut fixture = WasiTestFixture::new("fuzz");
+    fixture
+        .file("/data/a.txt", "hello")
+        .unwrap()
+        .file("/data/b/c.txt", "world")
+        .unwrap()
+        .stdin("input");
+    let mut test_env = fixture.finalize().unwrap();
+
+    let module = MODULE.with(Module::clone);
+    let import_object = test_env.env_mut().import_object(&module).unwrap();
+    let instance = Instance::new(&module, &import_object).unwrap();
+    test_env.env_mut().init_with_instance(&instance).unwrap();
+
+    let exports = &instance.exports;
+    let memory = exports.get_memory("memory").unwrap();
+    let fd_write = exports
+        .get_native_function::<(u32, u32, u32, u32), u32>("fd_write")
+        .unwrap();
+    let fd_read = exports
+        .get_native_function::<(u32, u32, u32, u32), u32>("fd_read")
+        .unwrap();
+    let fd_readdir = exports
+        .get_native_function::<(u32, u32, u32, u64, u32), u32>("fd_readdir")
+        .unwrap();
+    let fd_prestat_dir_name = exports
+        .get_native_function::<(u32, u32, u32), u32>("fd_prestat_dir_name")
+        .unwrap();
+    let path_open = exports
+        .get_native_function::<(u32, u32, u32, u32, u32, u64, u64, u32, u32), u32>("path_open")
+        .unwrap();
+
+    // The syscalls may trap, e.g. on a pointer out of the memory, but
+    // must not panic.
+    for syscall in syscalls {
+        let _ = match syscall {
+            Syscall::FdWrite {
+                fd,
+                iovs,
+                iovs_ptr,
+                iovs_len,
+                nwritten,
+            } => {
+                write_iovecs(memory, iovs_ptr, &iovs);
+                fd_write.call(fd, iovs_ptr, iovs_len, nwritten)
+            }
+            Syscall::FdRead {
+                fd,
+                iovs,
+                iovs_ptr,
+                iovs_len,
+                nread,
+            } => {
+                write_iovecs(memory, iovs_ptr, &iovs);
+                fd_read.call(fd, iovs_ptr, iovs_len, nread)
+            }
+            Syscall::FdReaddir {
+                fd,
+                buf,
+                buf_len,
+                cookie,
+                bufused,
+            } => fd_readdir.call(fd, buf, buf_len, cookie, bufused),
+            Syscall::FdPrestatDirName { fd, path, path_len } => {
+                fd_prestat_dir_name.call(fd, path, path_len)
+            }
+            Syscall::PathOpen {
+                fd,
+                path,
+                path_ptr,
+                path_len,
+                oflags,
+                fd_out,
+            } => {
+                poke(memory, path_ptr, &path);
+                path_open.call(fd, 0, path_ptr, path_len, oflags, !0, !0, 0, fd_out)
+            }
+        };
+    }
+});
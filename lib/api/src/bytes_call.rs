@@ -0,0 +1,164 @@
+//! Calling the functions taking and returning bytes through the memory
+//! of the instance, see [`TypedFunction::call_with_bytes`].
+
+use crate::{ExportError, Instance, RuntimeError, TypedFunction};
+
+/// The exports the host calls a function taking and returning bytes
+/// with, see [`TypedFunction::call_with_bytes_using`].
+///
+/// The allocator is expected to have the signature `(len: i32) -> i32`
+/// of `malloc`, and the deallocator either `(ptr: i32)` like `free`,
+/// or `(ptr: i32, len: i32)` like the deallocators of the Rust guests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BytesCallConvention {
+    /// The name of the memory holding the bytes.
+    pub memory: String,
+    /// The name of the allocator of the guest.
+    pub alloc: String,
+    /// The name of the deallocator of the guest.
+    pub free: String,
+}
+
+impl Default for BytesCallConvention {
+    fn default() -> Self {
+        Self {
+            memory: "memory".to_string(),
+            alloc: "malloc".to_string(),
+            free: "free".to_string(),
+        }
+    }
+}
+
+impl TypedFunction<(i32, i32), (i32, i32)> {
+    /// Calls the function with `input`, and returns the bytes it
+    /// returns, using the exports `memory`, `malloc` and `free` of
+    /// `instance`.
+    ///
+    /// The function takes the pointer and the length of its input, and
+    /// returns the pointer and the length of its output, allocated with
+    /// `malloc`. The host allocates the input with `malloc` and copies
+    /// it, then copies the output, and frees both of them with `free`:
+    /// the function must not free its input.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Instance, Module, Store, TypedFunction};
+    /// # let store = Store::default();
+    /// # let module = Module::new(&store, r#"
+    /// # (module
+    /// #     (memory (export "memory") 1)
+    /// #     (global $next (mut i32) (i32.const 1024))
+    /// #     (func (export "malloc") (param $len i32) (result i32)
+    /// #         (global.get $next)
+    /// #         (global.set $next (i32.add (global.get $next) (local.get $len))))
+    /// #     (func (export "free") (param i32))
+    /// #     (func (export "echo") (param i32 i32) (result i32 i32)
+    /// #         (local.get 0) (local.get 1)))
+    /// # "#)?;
+    /// # let instance = Instance::new(&module, &imports! {})?;
+    /// let echo: TypedFunction<(i32, i32), (i32, i32)> =
+    ///     instance.exports.get_native_function("echo")?;
+    /// assert_eq!(echo.call_with_bytes(&instance, b"hello")?, b"hello");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn call_with_bytes(
+        &self,
+        instance: &Instance,
+        input: &[u8],
+    ) -> Result<Vec<u8>, RuntimeError> {
+        self.call_with_bytes_using(instance, &BytesCallConvention::default(), input)
+    }
+
+    /// Like [`TypedFunction::call_with_bytes`], with a string whose
+    /// output must be valid UTF-8.
+    pub fn call_with_str(&self, instance: &Instance, input: &str) -> Result<String, RuntimeError> {
+        let output = self.call_with_bytes(instance, input.as_bytes())?;
+
+        String::from_utf8(output)
+            .map_err(|e| RuntimeError::new(format!("the output isn't valid UTF-8: {}", e)))
+    }
+
+    /// Like [`TypedFunction::call_with_bytes`], with the exports named
+    /// by `convention`.
+    pub fn call_with_bytes_using(
+        &self,
+        instance: &Instance,
+        convention: &BytesCallConvention,
+        input: &[u8],
+    ) -> Result<Vec<u8>, RuntimeError> {
+        let export_error = |e: ExportError| RuntimeError::new(format!("{}", e));
+        let memory = instance
+            .exports
+            .get_memory(&convention.memory)
+            .map_err(export_error)?;
+        let alloc = instance
+            .exports
+            .get_native_function::<i32, i32>(&convention.alloc)
+            .map_err(export_error)?;
+        let free = Deallocator::new(instance, &convention.free)?;
+
+        let len = i32_len(input.len())?;
+        let ptr = alloc.call(len)?;
+        let result = memory
+            .write(ptr as u32 as u64, input)
+            .map_err(RuntimeError::from)
+            .and_then(|()| self.call(ptr, len));
+        free.call(ptr, len)?;
+
+        let (output_ptr, output_len) = result?;
+        let result = if output_len as u32 as u64 > memory.data_size() {
+            // Checked before allocating the buffer.
+            Err(RuntimeError::new(format!(
+                "the output of {} bytes doesn't fit in the memory",
+                output_len as u32
+            )))
+        } else {
+            let mut output = vec![0; output_len as u32 as usize];
+            memory
+                .read(output_ptr as u32 as u64, &mut output)
+                .map(|()| output)
+                .map_err(RuntimeError::from)
+        };
+        free.call(output_ptr, output_len)?;
+
+        result
+    }
+}
+
+/// The deallocator of a [`BytesCallConvention`], with or without the
+/// length of the allocation.
+enum Deallocator {
+    Ptr(TypedFunction<i32, ()>),
+    PtrLen(TypedFunction<(i32, i32), ()>),
+}
+
+impl Deallocator {
+    fn new(instance: &Instance, name: &str) -> Result<Self, RuntimeError> {
+        if let Ok(free) = instance.exports.get_native_function(name) {
+            return Ok(Self::Ptr(free));
+        }
+
+        instance
+            .exports
+            .get_native_function(name)
+            .map(Self::PtrLen)
+            .map_err(|e: ExportError| RuntimeError::new(format!("{}", e)))
+    }
+
+    fn call(&self, ptr: i32, len: i32) -> Result<(), RuntimeError> {
+        match self {
+            Self::Ptr(free) => free.call(ptr),
+            Self::PtrLen(free) => free.call(ptr, len),
+        }
+    }
+}
+
+fn i32_len(len: usize) -> Result<i32, RuntimeError> {
+    if len > i32::MAX as usize {
+        return Err(RuntimeError::new(format!(
+            "the input of {} bytes doesn't fit in the memory",
+            len
+        )));
+    }
+
+    Ok(len as i32)
+}
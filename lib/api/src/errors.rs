@@ -0,0 +1,66 @@
+//! The errors shared by the backends, re-exported the same way by every
+//! backend, so that code generic over the backend can match them.
+//!
+//! [`CompileError`], [`crate::LinkError`] and the other errors of
+//! `wasmer_types` are also the same on every backend, and
+//! [`RuntimeError::kind`] tells what a runtime error comes from.
+
+use crate::{CompileError, HostEnvInitError, LinkError, RuntimeError};
+#[cfg(any(feature = "sys", feature = "std"))]
+use thiserror::Error;
+
+pub use wasmer_types::{RuntimeErrorKind, TrapCode};
+
+/// An error while instantiating a module.
+///
+/// This is not a common WebAssembly error, however
+/// we need to differentiate from a `LinkError` (an error
+/// that happens while linking, on instantiation), a
+/// Trap that occurs when calling the WebAssembly module
+/// start function, and an error when initializing the user's
+/// host environments.
+#[derive(Debug)]
+#[cfg_attr(any(feature = "sys", feature = "std"), derive(Error))]
+pub enum InstantiationError {
+    /// A linking ocurred during instantiation.
+    #[cfg_attr(any(feature = "sys", feature = "std"), error(transparent))]
+    Link(LinkError),
+
+    /// A runtime error occured while invoking the start function
+    #[cfg_attr(any(feature = "sys", feature = "std"), error(transparent))]
+    Start(RuntimeError),
+
+    /// The module was compiled with a CPU feature that is not available on
+    /// the current host.
+    ///
+    /// This is never returned by the `js` backend.
+    #[cfg_attr(
+        any(feature = "sys", feature = "std"),
+        error("missing requires CPU features: {0:?}")
+    )]
+    CpuFeature(String),
+
+    /// The module couldn't be compiled, with an engine compiling the
+    /// modules when they are first instantiated.
+    ///
+    /// This is never returned by the `js` backend.
+    #[cfg_attr(any(feature = "sys", feature = "std"), error(transparent))]
+    Compile(CompileError),
+
+    /// Error occurred when initializing the host environment.
+    #[cfg_attr(any(feature = "sys", feature = "std"), error(transparent))]
+    HostEnvInitialization(HostEnvInitError),
+}
+
+#[cfg(all(feature = "core", not(feature = "sys"), not(feature = "std")))]
+impl std::fmt::Display for InstantiationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "InstantiationError")
+    }
+}
+
+impl From<HostEnvInitError> for InstantiationError {
+    fn from(other: HostEnvInitError) -> Self {
+        Self::HostEnvInitialization(other)
+    }
+}
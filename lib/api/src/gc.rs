@@ -0,0 +1,12 @@
+//! The statistics of [`Store::gc`](crate::Store::gc), the same on every
+//! backend.
+
+/// What [`Store::gc`](crate::Store::gc) freed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreGcStats {
+    /// The number of function references freed, whose instance or host
+    /// function was dropped.
+    pub reclaimed_functions: usize,
+    /// The number of function references still alive.
+    pub retained_functions: usize,
+}
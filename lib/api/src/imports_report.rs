@@ -0,0 +1,124 @@
+//! Reports of all the imports of a module which an [`Imports`] doesn't
+//! satisfy, see [`Imports::report`] and [`Module::check_imports`].
+
+use crate::{ImportError, Imports, ImportsReport, LinkError, LinkIssue, Module};
+
+impl Imports {
+    /// Compares the imports of `module` with the ones defined in `self`,
+    /// and reports all the missing imports and all the imports whose
+    /// type is incompatible, with the expected and the provided types.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Function, ImportError, Module, Store};
+    /// # let store = Store::default();
+    /// let module = Module::new(
+    ///     &store,
+    ///     r#"
+    ///     (module
+    ///         (import "env" "log" (func (param i32)))
+    ///         (import "env" "abort" (func)))
+    ///     "#,
+    /// )?;
+    /// let imports = imports! {
+    ///     "env" => {
+    ///         "log" => Function::new_native(&store, || {}),
+    ///     },
+    /// };
+    ///
+    /// let report = imports.report(&module);
+    /// assert!(matches!(
+    ///     &report.errors[..],
+    ///     [
+    ///         (_, _, ImportError::IncompatibleType(..)),
+    ///         (_, _, ImportError::UnknownImport(_)),
+    ///     ]
+    /// ));
+    /// println!("{}", report);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn report(&self, module: &Module) -> ImportsReport {
+        let errors = module
+            .imports()
+            .filter_map(|import| {
+                let expected = import.ty().clone();
+                let error = match self.get_export(import.module(), import.name()) {
+                    None => ImportError::UnknownImport(expected),
+                    Some(provided) => {
+                        let provided = provided.ty();
+                        if provided.is_compatible_with(&expected) {
+                            return None;
+                        }
+                        ImportError::IncompatibleType(expected, provided)
+                    }
+                };
+
+                Some((
+                    import.module().to_string(),
+                    import.name().to_string(),
+                    error,
+                ))
+            })
+            .collect();
+
+        ImportsReport { errors }
+    }
+}
+
+impl Module {
+    /// Checks that `imports` satisfies all the imports of the module,
+    /// with compatible types, without instantiating it, so that no start
+    /// function runs and no memory or table gets allocated.
+    ///
+    /// This is a dry run of the linking done by [`crate::Instance::new`],
+    /// returning all the missing and incompatible imports at once.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Function, ImportError, Module, Store};
+    /// # let store = Store::default();
+    /// let module = Module::new(
+    ///     &store,
+    ///     r#"
+    ///     (module
+    ///         (import "env" "log" (func (param i32)))
+    ///         (start $main)
+    ///         (func $main unreachable))
+    ///     "#,
+    /// )?;
+    ///
+    /// let issues = module.check_imports(&imports! {}).unwrap_err();
+    /// assert_eq!(issues.len(), 1);
+    /// assert_eq!(issues[0].name, "log");
+    /// assert!(matches!(issues[0].error, ImportError::UnknownImport(_)));
+    ///
+    /// let imports = imports! {
+    ///     "env" => {
+    ///         "log" => Function::new_native(&store, |_: i32| {}),
+    ///     },
+    /// };
+    /// assert!(module.check_imports(&imports).is_ok());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn check_imports(&self, imports: &Imports) -> Result<(), Vec<LinkIssue>> {
+        let report = imports.report(self);
+        if report.is_empty() {
+            Ok(())
+        } else {
+            Err(report.into())
+        }
+    }
+}
+
+/// Turns the error about one of the imports of `module` into a report
+/// of all its unsatisfied imports, when there are several of them.
+pub(crate) fn report_link_error(module: &Module, imports: &Imports, error: LinkError) -> LinkError {
+    if !matches!(error, LinkError::Import(..)) {
+        return error;
+    }
+
+    let report = imports.report(module);
+    if report.errors.len() > 1 {
+        LinkError::Imports(report)
+    } else {
+        error
+    }
+}
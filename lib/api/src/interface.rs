@@ -0,0 +1,160 @@
+//! Typed interfaces over the exports of an instance, generated with the
+//! [`instance_interface!`] macro.
+
+use crate::{Extern, Instance, TypedFunction, WasmTypeList};
+use thiserror::Error;
+
+/// The error returned when an instance doesn't provide the exports an
+/// interface generated with [`instance_interface!`] expects.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceError {
+    /// The instance has no export with the expected name.
+    #[error("`{interface}` expects an export named `{export}`, which is missing")]
+    Missing {
+        /// The name of the interface.
+        interface: &'static str,
+        /// The name of the missing export.
+        export: String,
+    },
+    /// The export doesn't have the expected signature.
+    #[error("`{interface}` expects `{export}` to be `{expected}`, but it is {found}")]
+    IncompatibleType {
+        /// The name of the interface.
+        interface: &'static str,
+        /// The name of the export.
+        export: String,
+        /// The signature expected by the interface.
+        expected: &'static str,
+        /// A description of the export of the instance.
+        found: String,
+    },
+}
+
+/// Binds the function exported as `export`, used by the code generated
+/// by [`instance_interface!`].
+#[doc(hidden)]
+pub fn __bind_function<Args, Rets>(
+    instance: &Instance,
+    interface: &'static str,
+    export: &str,
+    expected: &'static str,
+) -> Result<TypedFunction<Args, Rets>, InterfaceError>
+where
+    Args: WasmTypeList,
+    Rets: WasmTypeList,
+{
+    let incompatible = |found: String| InterfaceError::IncompatibleType {
+        interface,
+        export: export.to_string(),
+        expected,
+        found,
+    };
+    match instance.exports.get_extern(export) {
+        None => Err(InterfaceError::Missing {
+            interface,
+            export: export.to_string(),
+        }),
+        Some(Extern::Function(function)) => function
+            .native()
+            .map_err(|_| incompatible(format!("a function of type `{}`", function.ty()))),
+        Some(Extern::Global(_)) => Err(incompatible("a global".to_string())),
+        Some(Extern::Memory(_)) => Err(incompatible("a memory".to_string())),
+        Some(Extern::Table(_)) => Err(incompatible("a table".to_string())),
+        #[cfg(feature = "sys")]
+        Some(Extern::Tag(_)) => Err(incompatible("a tag".to_string())),
+    }
+}
+
+/// Generates a struct holding typed functions bound to the exports of an
+/// instance, so they don't have to be looked up by name one by one.
+///
+/// Each field is a [`TypedFunction`] named after the export it binds,
+/// unless an `#[export_name = "..."]` attribute gives another name. The
+/// generated `bind` constructor checks all the exports, and reports the
+/// first missing or mistyped one with an [`InterfaceError`].
+///
+/// # Usage
+///
+/// ```
+/// # use wasmer::{imports, wat2wasm, Instance, Module, Store};
+/// # let store = Store::default();
+/// # let wasm_bytes = wat2wasm(r#"
+/// # (module
+/// #   (global $value (mut i32) (i32.const 0))
+/// #   (func (export "add") (param i32) (result i32)
+/// #     (global.set $value (i32.add (global.get $value) (local.get 0)))
+/// #     (global.get $value))
+/// #   (func (export "reset-value")
+/// #     (global.set $value (i32.const 0))))
+/// # "#.as_bytes()).unwrap();
+/// # let module = Module::new(&store, wasm_bytes).unwrap();
+/// # let instance = Instance::new(&module, &imports! {}).unwrap();
+/// use wasmer::instance_interface;
+///
+/// instance_interface! {
+///     /// An accumulator implemented by the guest.
+///     pub struct Accumulator {
+///         add: fn(i32) -> i32,
+///         #[export_name = "reset-value"]
+///         reset: fn(),
+///     }
+/// }
+///
+/// let accumulator = Accumulator::bind(&instance).unwrap();
+/// assert_eq!(accumulator.add.call(2).unwrap(), 2);
+/// assert_eq!(accumulator.add.call(3).unwrap(), 5);
+/// accumulator.reset.call().unwrap();
+/// assert_eq!(accumulator.add.call(1).unwrap(), 1);
+/// ```
+#[macro_export]
+macro_rules! instance_interface {
+    (
+        $( #[$attr:meta] )*
+        $vis:vis struct $name:ident {
+            $(
+                $( #[doc = $doc:expr] )*
+                $( #[export_name = $export:literal] )?
+                $field_vis:vis $field:ident : fn( $( $arg:ty ),* $(,)? ) $( -> $ret:ty )?
+            ),* $(,)?
+        }
+    ) => {
+        $( #[$attr] )*
+        #[derive(Clone)]
+        #[allow(unused_parens)]
+        $vis struct $name {
+            $(
+                $( #[doc = $doc] )*
+                $field_vis $field: $crate::TypedFunction<( $( $arg ),* ), ( $( $ret )? )>,
+            )*
+        }
+
+        impl $name {
+            /// Binds the exports of `instance`.
+            #[allow(dead_code)]
+            $vis fn bind(instance: &$crate::Instance) -> ::std::result::Result<Self, $crate::InterfaceError> {
+                ::std::result::Result::Ok(Self {
+                    $(
+                        $field: $crate::__bind_function(
+                            instance,
+                            stringify!($name),
+                            $crate::__export_name!($field $( $export )?),
+                            stringify!(fn( $( $arg ),* ) $( -> $ret )?),
+                        )?,
+                    )*
+                })
+            }
+        }
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __export_name {
+    ( $field:ident ) => {
+        stringify!($field)
+    };
+
+    ( $field:ident $export:literal ) => {
+        $export
+    };
+}
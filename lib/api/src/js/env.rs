@@ -1,4 +1,5 @@
-use crate::js::{ExportError, Instance};
+use crate::js::{ExportError, Instance, Memory};
+use std::sync::{Arc, Mutex, MutexGuard};
 use thiserror::Error;
 
 /// An error while initializing the user supplied host env with the `WasmerEnv` trait.
@@ -217,3 +218,98 @@ impl<T> Default for LazyInit<T> {
 unsafe impl<T: Send> Send for LazyInit<T> {}
 // I thought we could opt out of sync..., look into this
 // unsafe impl<T> !Sync for InitWithInstance<T> {}
+
+/// An environment giving the host functions mutable access to some host
+/// data, along with the memory of the instance.
+///
+/// The data is shared by all the clones of the environment, so every
+/// host function created with it sees the same data. The memory is the
+/// one exported as `memory` by the instance, and is set when the instance
+/// is created.
+///
+/// ```
+/// # use wasmer::{Function, HostState, Store, WasmPtr};
+/// # let store = Store::default();
+/// struct Counter {
+///     calls: u32,
+/// }
+///
+/// fn count(env: &HostState<Counter>, ptr: WasmPtr<u32>) {
+///     let (mut counter, memory) = env.data_and_memory_mut();
+///     counter.calls += 1;
+///     ptr.write(memory, counter.calls).unwrap();
+/// }
+///
+/// let state = HostState::new(Counter { calls: 0 });
+/// let count = Function::new_native_with_env(&store, state.clone(), count);
+/// ```
+///
+/// The data is locked while it's borrowed: a host function must release
+/// it before calling back into the instance, if the instance can call a
+/// host function borrowing it again.
+pub struct HostState<T> {
+    data: Arc<Mutex<T>>,
+    memory: LazyInit<Memory>,
+}
+
+impl<T> HostState<T> {
+    /// Creates an environment holding `data`.
+    pub fn new(data: T) -> Self {
+        Self {
+            data: Arc::new(Mutex::new(data)),
+            memory: LazyInit::new(),
+        }
+    }
+
+    /// Borrows the host data mutably.
+    pub fn data_mut(&self) -> MutexGuard<'_, T> {
+        self.data.lock().unwrap()
+    }
+
+    /// Returns the memory of the instance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instance doesn't export a memory named `memory`, or
+    /// if it's called before the instance is created.
+    pub fn memory(&self) -> &Memory {
+        self.memory
+            .get_ref()
+            .expect("the instance must export a memory named `memory`")
+    }
+
+    /// Borrows the host data mutably and the memory of the instance at
+    /// the same time, see [`HostState::data_mut`] and [`HostState::memory`].
+    pub fn data_and_memory_mut(&self) -> (MutexGuard<'_, T>, &Memory) {
+        (self.data_mut(), self.memory())
+    }
+}
+
+impl<T> Clone for HostState<T> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            memory: self.memory.clone(),
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for HostState<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("HostState")
+            .field("data", &self.data)
+            .field("memory", &self.memory)
+            .finish()
+    }
+}
+
+impl<T> WasmerEnv for HostState<T> {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        // The memory is optional, the host functions may only use the data.
+        let memory: Result<Memory, _> = instance.exports.get_with_generics_weak("memory");
+        if let Ok(memory) = memory {
+            self.memory.initialize(memory);
+        }
+        Ok(())
+    }
+}
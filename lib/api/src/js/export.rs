@@ -2,8 +2,8 @@ use crate::js::instance::Instance;
 use crate::js::wasm_bindgen_polyfill::Global;
 use crate::js::HostEnvInitError;
 use crate::js::WasmerEnv;
-use js_sys::Function;
 use js_sys::WebAssembly::{Memory, Table};
+use js_sys::{Array, Function};
 use std::cell::RefCell;
 use std::fmt;
 use std::sync::Arc;
@@ -60,6 +60,8 @@ pub struct VMFunction {
     pub(crate) function: Function,
     pub(crate) ty: FunctionType,
     pub(crate) environment: Option<Arc<RefCell<Box<dyn WasmerEnv>>>>,
+    /// The argument buffer reused across calls, created on warm-up.
+    args: Arc<RefCell<Option<Array>>>,
 }
 
 unsafe impl Send for VMFunction {}
@@ -75,9 +77,57 @@ impl VMFunction {
             function,
             ty,
             environment: environment.map(|env| Arc::new(RefCell::new(env))),
+            args: Arc::new(RefCell::new(None)),
         }
     }
 
+    /// Creates the argument buffer used to call this function, so the
+    /// first call doesn't pay for it.
+    ///
+    /// Functions with up to two arguments don't use the buffer, see
+    /// [`Self::call_with_args`], so there's nothing to create for them.
+    /// Nothing else is cached: converting the arguments and the results
+    /// only matches on their types.
+    pub(crate) fn warm_up(&self) {
+        if self.ty.params().len() > 2 {
+            self.args_buffer();
+        }
+    }
+
+    fn args_buffer(&self) -> Array {
+        self.args
+            .borrow_mut()
+            .get_or_insert_with(|| Array::new_with_length(self.ty.params().len() as u32))
+            .clone()
+    }
+
+    /// Calls the function with the given arguments.
+    ///
+    /// Functions with up to two arguments, the common case for hot
+    /// functions, are called directly with `Function.call` so neither an
+    /// argument array nor `Reflect.apply` is involved. The other ones reuse
+    /// the cached argument buffer instead of allocating a new `Array` per
+    /// call.
+    ///
+    /// The buffer is only read by the JS engine when the call starts, so
+    /// reentrant calls into the same function can safely overwrite it.
+    pub(crate) fn call_with_args(&self, params: &[JsValue]) -> Result<JsValue, JsValue> {
+        match params {
+            [] => return self.function.call0(&JsValue::NULL),
+            [a1] => return self.function.call1(&JsValue::NULL, a1),
+            [a1, a2] => return self.function.call2(&JsValue::NULL, a1, a2),
+            _ => {}
+        }
+        let args = self.args_buffer();
+        // Truncate the buffer, so a call with fewer arguments doesn't pass
+        // the trailing ones of the previous call.
+        args.set_length(params.len() as u32);
+        for (i, param) in params.iter().enumerate() {
+            args.set(i as u32, param.clone());
+        }
+        js_sys::Reflect::apply(&self.function, &JsValue::NULL, &args)
+    }
+
     pub(crate) fn init_envs(&self, instance: &Instance) -> Result<(), HostEnvInitError> {
         if let Some(env) = &self.environment {
             let mut borrowed_env = env.borrow_mut();
@@ -170,6 +220,7 @@ impl From<(JsValue, ExternType)> for Export {
                     panic!("Extern type doesn't match js value type");
                 }
             }
+            ExternType::Tag(_) => unimplemented!("tags are not supported by the js API"),
         }
     }
 }
@@ -15,6 +15,14 @@ use wasm_bindgen::JsCast;
 use crate::js::export::{Export, VMFunction};
 use std::fmt;
 
+fn format_types_for_error_message(items: &[Val]) -> String {
+    items
+        .iter()
+        .map(|param| param.ty().to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 #[repr(C)]
 pub struct VMFunctionBody(u8);
 
@@ -455,13 +463,19 @@ impl Function {
     /// assert_eq!(sum.call(&[Value::I32(1), Value::I32(2)]).unwrap().to_vec(), vec![Value::I32(3)]);
     /// ```
     pub fn call(&self, params: &[Val]) -> Result<Box<[Val]>, RuntimeError> {
-        let arr = js_sys::Array::new_with_length(params.len() as u32);
-        for (i, param) in params.iter().enumerate() {
-            let js_value = param.as_jsvalue();
-            arr.set(i as u32, js_value);
+        let signature = self.ty();
+        if signature.params().len() != params.len() {
+            return Err(RuntimeError::new(format!(
+                "Parameters of type [{}] did not match signature {}",
+                format_types_for_error_message(params),
+                &signature
+            )));
         }
-        let result =
-            js_sys::Reflect::apply(&self.exported.function, &wasm_bindgen::JsValue::NULL, &arr)?;
+        let params = params
+            .iter()
+            .map(|param| param.as_jsvalue())
+            .collect::<Vec<_>>();
+        let result = self.exported.call_with_args(&params)?;
 
         let result_types = self.exported.ty.results();
         match result_types.len() {
@@ -482,6 +496,17 @@ impl Function {
         }
     }
 
+    /// Calls the `Function` once per element of `batch` and returns the
+    /// results of every call, in order.
+    ///
+    /// The JS engine doesn't offer a way to enter WebAssembly once for
+    /// several calls, so this is equivalent to calling [`Function::call`]
+    /// in a loop. If a call traps, the batch stops and the trap is
+    /// returned.
+    pub fn call_batched(&self, batch: &[&[Val]]) -> Result<Vec<Box<[Val]>>, RuntimeError> {
+        batch.iter().map(|params| self.call(params)).collect()
+    }
+
     pub(crate) fn from_vm_export(store: &Store, wasmer_export: VMFunction) -> Self {
         Self {
             store: store.clone(),
@@ -6,11 +6,12 @@ use crate::js::{MemoryAccessError, MemoryType};
 use std::convert::TryInto;
 use std::mem::MaybeUninit;
 use std::slice;
+use std::time::Duration;
 use thiserror::Error;
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use wasmer_types::{Bytes, Pages};
+use wasmer_types::{AtomicRmwOp, Bytes, Pages, WaitResult};
 
 /// Error type describing things that can go wrong when operating on Wasm Memories.
 #[derive(Error, Debug, Clone, PartialEq, Hash)]
@@ -349,6 +350,111 @@ impl Memory {
         view.subarray(offset, end).copy_from(data);
         Ok(())
     }
+
+    /// Atomically reads the `u32` at the given offset, which must be
+    /// aligned, like `i32.atomic.load`.
+    pub fn atomic_load32(&self, offset: u64) -> Result<u32, MemoryAccessError> {
+        let (view, index) = self.atomic32(offset)?;
+        js_sys::Atomics::load(&view, index)
+            .map(|value| value as u32)
+            .map_err(|_| MemoryAccessError::HeapOutOfBounds)
+    }
+
+    /// Atomically writes `value` at the given offset, which must be
+    /// aligned, like `i32.atomic.store`.
+    pub fn atomic_store32(&self, offset: u64, value: u32) -> Result<(), MemoryAccessError> {
+        let (view, index) = self.atomic32(offset)?;
+        js_sys::Atomics::store(&view, index, value as i32)
+            .map(|_| ())
+            .map_err(|_| MemoryAccessError::HeapOutOfBounds)
+    }
+
+    /// Atomically applies `op` with `value` to the `u32` at the given
+    /// offset, which must be aligned, and returns its previous value,
+    /// like `i32.atomic.rmw.*`.
+    ///
+    /// The 64-bit atomic accesses are only available with the `sys`
+    /// feature.
+    pub fn atomic_rmw32(
+        &self,
+        offset: u64,
+        op: AtomicRmwOp,
+        value: u32,
+    ) -> Result<u32, MemoryAccessError> {
+        let (view, index) = self.atomic32(offset)?;
+        let value = value as i32;
+        let previous = match op {
+            AtomicRmwOp::Add => js_sys::Atomics::add(&view, index, value),
+            AtomicRmwOp::Sub => js_sys::Atomics::sub(&view, index, value),
+            AtomicRmwOp::And => js_sys::Atomics::and(&view, index, value),
+            AtomicRmwOp::Or => js_sys::Atomics::or(&view, index, value),
+            AtomicRmwOp::Xor => js_sys::Atomics::xor(&view, index, value),
+            AtomicRmwOp::Xchg => js_sys::Atomics::exchange(&view, index, value),
+        };
+        previous
+            .map(|previous| previous as u32)
+            .map_err(|_| MemoryAccessError::HeapOutOfBounds)
+    }
+
+    /// Blocks the current thread until the location at the given offset
+    /// is notified, or `timeout` elapses, if it holds `expected`, like
+    /// `memory.atomic.wait32`.
+    ///
+    /// Only shared memories can be waited on, and browsers don't allow
+    /// waiting on their main thread.
+    pub fn wait32(
+        &self,
+        offset: u64,
+        expected: u32,
+        timeout: Option<Duration>,
+    ) -> Result<WaitResult, MemoryAccessError> {
+        if !self.ty().shared {
+            return Err(MemoryAccessError::WaitNotAllowed);
+        }
+
+        let (view, index) = self.atomic32(offset)?;
+        let result = match timeout {
+            Some(timeout) => js_sys::Atomics::wait_with_timeout(
+                &view,
+                index,
+                expected as i32,
+                timeout.as_secs_f64() * 1000.0,
+            ),
+            None => js_sys::Atomics::wait(&view, index, expected as i32),
+        }
+        .map_err(|_| MemoryAccessError::WaitNotAllowed)?;
+        match String::from(result).as_str() {
+            "ok" => Ok(WaitResult::Ok),
+            "not-equal" => Ok(WaitResult::NotEqual),
+            _ => Ok(WaitResult::TimedOut),
+        }
+    }
+
+    /// Wakes up at most `count` of the threads waiting on the location
+    /// at the given offset, which must be aligned, and returns how many
+    /// were woken up, like `memory.atomic.notify`.
+    pub fn notify(&self, offset: u64, count: u32) -> Result<u32, MemoryAccessError> {
+        let (view, index) = self.atomic32(offset)?;
+        js_sys::Atomics::notify_with_count(&view, index, count)
+            .map_err(|_| MemoryAccessError::HeapOutOfBounds)
+    }
+
+    /// Checks that the `u32` at `offset` is in bounds and aligned, as the
+    /// atomic accesses require, and returns its index in an `Int32Array`
+    /// view of the memory.
+    fn atomic32(&self, offset: u64) -> Result<(js_sys::Int32Array, u32), MemoryAccessError> {
+        let view = js_sys::Int32Array::new(&self.vm_memory.memory.buffer());
+        let offset: u32 = offset.try_into().map_err(|_| MemoryAccessError::Overflow)?;
+        let end = offset.checked_add(4).ok_or(MemoryAccessError::Overflow)?;
+        if end / 4 > view.length() {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        if offset % 4 != 0 {
+            return Err(MemoryAccessError::UnalignedAtomic);
+        }
+
+        Ok((view, offset / 4))
+    }
 }
 
 impl<'a> Exportable<'a> for Memory {
@@ -0,0 +1,140 @@
+//! The frames of the traps, parsed from the stacks of the JS exceptions.
+//!
+//! The JS engines only tell the indices of the functions in the stacks,
+//! as `wasm-function[42]`, so the names of the functions come from the
+//! `name` sections of the modules, registered when they are created.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+/// The names of the functions of a module, by function index.
+pub(crate) type FunctionNames = HashMap<u32, String>;
+
+thread_local! {
+    /// The names of the functions of the last module created with each
+    /// name, while the module is alive.
+    static FUNCTION_NAMES: RefCell<HashMap<Option<String>, Weak<FunctionNames>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Registers the names of the functions of a module named `module_name`
+/// in its `name` section, to describe the frames of its traps.
+pub(crate) fn register(module_name: Option<&str>, names: &Arc<FunctionNames>) {
+    if names.is_empty() {
+        return;
+    }
+    FUNCTION_NAMES.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.retain(|_, names| names.strong_count() > 0);
+        registry.insert(module_name.map(str::to_string), Arc::downgrade(names));
+    });
+}
+
+fn function_name(module_name: Option<&str>, func_index: u32) -> Option<String> {
+    FUNCTION_NAMES.with(|registry| {
+        let names = registry
+            .borrow()
+            .get(&module_name.map(str::to_string))?
+            .upgrade()?;
+        names.get(&func_index).cloned()
+    })
+}
+
+/// Description of a frame in a backtrace for a [`RuntimeError::trace`](crate::RuntimeError::trace).
+///
+/// The frames are parsed from the stack of the JS exception, which
+/// tells less than the `sys` backend does: the offset of the
+/// instruction is only known on the engines telling it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameInfo {
+    module_name: Option<String>,
+    func_index: u32,
+    function_name: Option<String>,
+    module_offset: usize,
+}
+
+impl FrameInfo {
+    /// Parses a line of the stack of a JS exception, if it's a frame of
+    /// a WebAssembly function.
+    ///
+    /// The engines write the frames differently, but all of them write
+    /// `wasm-function[<index>]`, followed by `:0x<offset>` on V8 and
+    /// SpiderMonkey. V8 also writes the URL of the module, as
+    /// `wasm://wasm/<name>-<hash>`, the name coming from the `name`
+    /// section.
+    fn parse(line: &str) -> Option<Self> {
+        const FUNCTION: &str = "wasm-function[";
+        const URL: &str = "wasm://wasm/";
+
+        let start = line.find(FUNCTION)? + FUNCTION.len();
+        let end = start + line[start..].find(']')?;
+        let func_index = line[start..end].parse().ok()?;
+
+        let module_offset = line[end + 1..]
+            .strip_prefix(":0x")
+            .and_then(|offset| {
+                let len = offset
+                    .find(|c: char| !c.is_ascii_hexdigit())
+                    .unwrap_or_else(|| offset.len());
+                usize::from_str_radix(&offset[..len], 16).ok()
+            })
+            .unwrap_or(0);
+
+        let module_name = line.find(URL).and_then(|url| {
+            let url = &line[url + URL.len()..];
+            let url = &url[..url.find(':').unwrap_or_else(|| url.len())];
+            // Without a name, the URL is only the 8 digits of the hash.
+            let (name, hash) = url.rsplit_once('-')?;
+            if hash.len() == 8 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                Some(name.to_string())
+            } else {
+                None
+            }
+        });
+
+        let function_name = function_name(module_name.as_deref(), func_index);
+        Some(Self {
+            module_name,
+            func_index,
+            function_name,
+            module_offset,
+        })
+    }
+
+    /// Returns the WebAssembly function index for this frame.
+    ///
+    /// This function index is the index in the function index space of the
+    /// WebAssembly module that this frame comes from.
+    pub fn func_index(&self) -> u32 {
+        self.func_index
+    }
+
+    /// Returns the name of the module that this frame is for, from its
+    /// `name` section, or `<module>` when the engine doesn't tell it.
+    pub fn module_name(&self) -> &str {
+        self.module_name.as_deref().unwrap_or("<module>")
+    }
+
+    /// Returns the name of the function for this frame, from the `name`
+    /// section of its module, if one is available.
+    ///
+    /// The names are looked up in the last module created with the same
+    /// name, which is still alive, requiring the `wasm-types-polyfill`
+    /// feature to parse the `name` section.
+    pub fn function_name(&self) -> Option<&str> {
+        self.function_name.as_deref()
+    }
+
+    /// Returns the offset within the original wasm module this frame's
+    /// program counter was at, or `0` if the engine doesn't tell it.
+    pub fn module_offset(&self) -> usize {
+        self.module_offset
+    }
+}
+
+/// Parses the WebAssembly frames of the `stack` of a JS exception,
+/// innermost first.
+pub(crate) fn parse_stack(stack: &str) -> Vec<FrameInfo> {
+    stack.lines().filter_map(FrameInfo::parse).collect()
+}
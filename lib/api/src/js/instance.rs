@@ -1,15 +1,14 @@
-use crate::js::env::HostEnvInitError;
+use crate::imports_report::report_link_error;
+use crate::js::error::{ImportError, LinkError};
 use crate::js::export::Export;
 use crate::js::exports::{Exportable, Exports};
 use crate::js::externals::Extern;
 use crate::js::imports::Imports;
 use crate::js::module::Module;
 use crate::js::store::Store;
-use crate::js::trap::RuntimeError;
+use crate::js::InstantiationError;
 use js_sys::WebAssembly;
 use std::fmt;
-#[cfg(feature = "std")]
-use thiserror::Error;
 
 /// A WebAssembly Instance is a stateful, executable
 /// instance of a WebAssembly [`Module`].
@@ -29,37 +28,6 @@ pub struct Instance {
     pub exports: Exports,
 }
 
-/// An error while instantiating a module.
-///
-/// This is not a common WebAssembly error, however
-/// we need to differentiate from a `LinkError` (an error
-/// that happens while linking, on instantiation), a
-/// Trap that occurs when calling the WebAssembly module
-/// start function, and an error when initializing the user's
-/// host environments.
-#[derive(Debug)]
-#[cfg_attr(feature = "std", derive(Error))]
-pub enum InstantiationError {
-    /// A linking ocurred during instantiation.
-    #[cfg_attr(feature = "std", error("Link error: {0}"))]
-    Link(String),
-
-    /// A runtime error occured while invoking the start function
-    #[cfg_attr(feature = "std", error(transparent))]
-    Start(RuntimeError),
-
-    /// Error occurred when initializing the host environment.
-    #[cfg_attr(feature = "std", error(transparent))]
-    HostEnvInitialization(HostEnvInitError),
-}
-
-#[cfg(feature = "core")]
-impl std::fmt::Display for InstantiationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "InstantiationError")
-    }
-}
-
 impl Instance {
     /// Creates a new `Instance` from a WebAssembly [`Module`] and a
     /// set of imports resolved by the [`Resolver`].
@@ -95,6 +63,12 @@ impl Instance {
     ///  * Link errors that happen when plugging the imports into the instance
     ///  * Runtime errors that happen when running the module `start` function.
     pub fn new(module: &Module, imports: &Imports) -> Result<Self, InstantiationError> {
+        // Check the imports upfront, so missing imports are reported as
+        // link errors (as in the `sys` backend) rather than as a trap
+        // coming from the JS VM.
+        imports
+            .imports_for_module(module)
+            .map_err(|error| InstantiationError::Link(report_link_error(module, imports, error)))?;
         let import_copy = imports.clone();
         let (instance, imports): (WebAssembly::Instance, Vec<Extern>) = module
             .instantiate(imports)
@@ -128,9 +102,10 @@ impl Instance {
                 let extern_type = export_type.ty().clone();
                 let js_export =
                     js_sys::Reflect::get(&instance_exports, &name.into()).map_err(|_e| {
-                        InstantiationError::Link(format!(
-                            "Can't get {} from the instance exports",
-                            &name
+                        InstantiationError::Link(LinkError::Import(
+                            module.name().unwrap_or_default().to_string(),
+                            name.to_string(),
+                            ImportError::UnknownImport(extern_type.clone()),
                         ))
                     })?;
                 let export: Export = (js_export, extern_type).into();
@@ -139,12 +114,32 @@ impl Instance {
             })
             .collect::<Result<Exports, InstantiationError>>()?;
 
-        Ok(Self {
+        let self_instance = Self {
             instance,
             module: module.clone(),
             imports,
             exports,
-        })
+        };
+        self_instance.warm_up();
+        Ok(self_instance)
+    }
+
+    /// Prepares all the exported functions of this instance to be called.
+    ///
+    /// The argument buffer of every exported function taking more than two
+    /// arguments is created upfront, so the first call to each of them
+    /// doesn't allocate it. The arguments and results are still converted
+    /// on each call, from the function type, which doesn't allocate. This
+    /// is already done when the instance is created, so it only needs to
+    /// be called again after inserting new functions into the exports.
+    ///
+    /// *This method is only available when targeting JS environments*
+    pub fn warm_up(&self) {
+        for (_name, extern_) in self.exports.iter() {
+            if let Extern::Function(func) = extern_ {
+                func.exported.warm_up();
+            }
+        }
     }
 
     /// Initialize the given extern imports with the `Instance`.
@@ -25,6 +25,13 @@ pub enum MemoryAccessError {
     /// String is not valid UTF-8.
     #[error("string is not valid utf-8")]
     NonUtf8String,
+    /// Atomic access to an unaligned address.
+    #[error("unaligned atomic access")]
+    UnalignedAtomic,
+    /// Wait on a memory which isn't shared, or on a thread which can't
+    /// block.
+    #[error("waiting isn't allowed on this memory or thread")]
+    WaitNotAllowed,
 }
 
 impl From<MemoryAccessError> for RuntimeError {
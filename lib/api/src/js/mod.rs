@@ -28,6 +28,7 @@ mod error;
 mod export;
 mod exports;
 mod externals;
+mod frame_info;
 mod imports;
 mod instance;
 mod js_import_object;
@@ -40,31 +41,40 @@ mod ptr;
 mod store;
 mod trap;
 mod types;
+#[cfg(feature = "js-validate")]
+mod validate;
 mod wasm_bindgen_polyfill;
 
 /// Implement [`WasmerEnv`] for your type with `#[derive(WasmerEnv)]`.
 ///
 /// See the [`WasmerEnv`] trait for more information.
-pub use wasmer_derive::WasmerEnv;
+pub use wasmer_derive::{ValueType, WasmerEnv};
 
-pub use crate::js::env::{HostEnvInitError, LazyInit, WasmerEnv};
-pub use crate::js::error::{DeserializeError, SerializeError};
+pub use crate::errors::{InstantiationError, RuntimeErrorKind, TrapCode};
+pub use crate::js::env::{HostEnvInitError, HostState, LazyInit, WasmerEnv};
+pub use crate::js::error::{
+    CompileError, DeserializeError, ImportError, ImportsReport, LinkError, LinkIssue,
+    MiddlewareError, SerializeError, WasmError,
+};
 pub use crate::js::export::Export;
 pub use crate::js::exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use crate::js::externals::{
     Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, MemoryError, Table,
     WasmTypeList,
 };
+pub use crate::js::frame_info::FrameInfo;
 pub use crate::js::imports::Imports;
-pub use crate::js::instance::{Instance, InstantiationError};
+pub use crate::js::instance::Instance;
 pub use crate::js::js_import_object::JsImportObject;
 pub use crate::js::mem_access::{MemoryAccessError, WasmRef, WasmSlice, WasmSliceIter};
 pub use crate::js::module::{Module, ModuleTypeHints};
 pub use crate::js::native::TypedFunction;
+#[doc(hidden)]
+pub use crate::js::ptr::__wasm_ptr_field_of;
 pub use crate::js::ptr::{Memory32, Memory64, MemorySize, WasmPtr, WasmPtr64};
 pub use crate::js::trap::RuntimeError;
 
-pub use crate::js::store::{Store, StoreObject};
+pub use crate::js::store::{AdoptError, Store, StoreObject};
 pub use crate::js::types::{
     ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
     TableType, Val, ValType,
@@ -73,8 +83,8 @@ pub use crate::js::types::{Val as Value, ValType as Type};
 
 pub use wasmer_types::is_wasm;
 pub use wasmer_types::{
-    Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, Pages, ValueType, WASM_MAX_PAGES,
-    WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    ArtifactVersion, AtomicRmwOp, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, Pages,
+    ValueType, WaitResult, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 
 #[cfg(feature = "wat")]
@@ -1,20 +1,21 @@
 use crate::js::exports::Exportable;
 use crate::js::externals::Extern;
+use crate::js::frame_info::{self, FunctionNames};
 use crate::js::imports::Imports;
 use crate::js::store::Store;
 use crate::js::types::{ExportType, ImportType};
 // use crate::js::InstantiationError;
 #[cfg(feature = "wat")]
-use crate::js::error::WasmError;
-use crate::js::error::{CompileError, DeserializeError, SerializeError};
+use crate::js::error::{CompileError, DeserializeError, SerializeError, WasmError};
 use crate::js::RuntimeError;
 use js_sys::{Reflect, Uint8Array, WebAssembly};
 use std::fmt;
 use std::io;
 use std::path::Path;
+use std::sync::Arc;
 #[cfg(feature = "std")]
 use thiserror::Error;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{JsCast, JsValue};
 use wasmer_types::{
     ExportsIterator, ExternType, FunctionType, GlobalType, ImportsIterator, MemoryType, Mutability,
     Pages, TableType, Type,
@@ -63,6 +64,8 @@ pub struct Module {
     name: Option<String>,
     // WebAssembly type hints
     type_hints: Option<ModuleTypeHints>,
+    // The names of the functions, from the `name` section
+    function_names: Arc<FunctionNames>,
     #[cfg(feature = "js-serializable-module")]
     raw_bytes: Option<Vec<u8>>,
 }
@@ -149,9 +152,13 @@ impl Module {
     /// Opposed to [`Module::new`], this function is not compatible with
     /// the WebAssembly text format (if the "wat" feature is enabled for
     /// this crate).
+    ///
+    /// With the `js-validate` feature, the module is validated with
+    /// `wasmparser` first, so that its errors are the ones the `sys`
+    /// backend reports.
     pub fn from_binary(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
-        //
-        // Self::validate(store, binary)?;
+        #[cfg(feature = "js-validate")]
+        Self::validate(store, binary)?;
         unsafe { Self::from_binary_unchecked(store, binary) }
     }
 
@@ -166,12 +173,24 @@ impl Module {
         binary: &[u8],
     ) -> Result<Self, CompileError> {
         let js_bytes = Uint8Array::view(binary);
-        let module = WebAssembly::Module::new(&js_bytes.into()).unwrap();
+        let module = WebAssembly::Module::new(&js_bytes.into()).map_err(|e| {
+            CompileError::Validate(
+                e.dyn_into::<js_sys::Error>()
+                    .map(|e| String::from(e.message()))
+                    .unwrap_or_else(|_| "Invalid Wasm file".to_owned()),
+            )
+        })?;
 
         // The module is now validated, so we can safely parse it's types
         #[cfg(feature = "wasm-types-polyfill")]
-        let (type_hints, name) = {
+        let (type_hints, name, function_names) = {
             let info = crate::js::module_info_polyfill::translate_module(binary).unwrap();
+            let function_names = info
+                .info
+                .function_names
+                .iter()
+                .map(|(index, name)| (index.as_u32(), name.clone()))
+                .collect::<FunctionNames>();
 
             (
                 Some(ModuleTypeHints {
@@ -187,16 +206,19 @@ impl Module {
                         .collect::<Vec<_>>(),
                 }),
                 info.info.name,
+                Arc::new(function_names),
             )
         };
         #[cfg(not(feature = "wasm-types-polyfill"))]
-        let (type_hints, name) = (None, None);
+        let (type_hints, name, function_names) = (None, None, Arc::default());
+        frame_info::register(name.as_deref(), &function_names);
 
         Ok(Self {
             store: store.clone(),
             module,
             type_hints,
             name,
+            function_names,
             #[cfg(feature = "js-serializable-module")]
             raw_bytes: Some(binary.to_vec()),
         })
@@ -208,11 +230,22 @@ impl Module {
     /// This validation is normally pretty fast and checks the enabled
     /// WebAssembly features in the Store Engine to assure deterministic
     /// validation of the Module.
+    ///
+    /// With the `js-validate` feature, the module is validated with
+    /// `wasmparser`, and the errors are the ones the `sys` backend
+    /// reports, rather than with the JS engine.
     pub fn validate(_store: &Store, binary: &[u8]) -> Result<(), CompileError> {
-        let js_bytes = unsafe { Uint8Array::view(binary) };
-        match WebAssembly::validate(&js_bytes.into()) {
-            Ok(true) => Ok(()),
-            _ => Err(CompileError::Validate("Invalid Wasm file".to_owned())),
+        #[cfg(feature = "js-validate")]
+        {
+            crate::js::validate::validate_module(binary)
+        }
+        #[cfg(not(feature = "js-validate"))]
+        {
+            let js_bytes = unsafe { Uint8Array::view(binary) };
+            match WebAssembly::validate(&js_bytes.into()) {
+                Ok(true) => Ok(()),
+                _ => Err(CompileError::Validate("Invalid Wasm file".to_owned())),
+            }
         }
     }
 
@@ -281,6 +314,29 @@ impl Module {
         // self.artifact.module_ref().name.as_deref()
     }
 
+    /// Returns the name of the function at `index` in the function index
+    /// space of the module, from its `name` section, if it has one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = "(module (func $answer (result i32) (i32.const 42)))";
+    /// let module = Module::new(&store, wat)?;
+    /// assert_eq!(module.function_name(0), Some("answer"));
+    /// assert_eq!(module.function_name(1), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// The names are parsed with the `wasm-types-polyfill` feature, and
+    /// name the functions in the frames of [`RuntimeError::trace`].
+    pub fn function_name(&self, index: u32) -> Option<&str> {
+        self.function_names.get(&index).map(String::as_str)
+    }
+
     /// Serializes a module into a binary representation that the `Engine`
     /// can later process via [`Module::deserialize`].
     ///
@@ -426,6 +482,7 @@ impl Module {
                 ExternType::Global(_) => "global",
                 ExternType::Memory(_) => "memory",
                 ExternType::Table(_) => "table",
+                ExternType::Tag(_) => "tag",
             };
             if expected_kind != kind.as_str() {
                 return Err(format!("The provided type hint for the export {} is {} which doesn't match the expected kind: {}", i, kind.as_str(), expected_kind));
@@ -539,6 +596,7 @@ impl From<WebAssembly::Module> for Module {
             module,
             name: None,
             type_hints: None,
+            function_names: Arc::default(),
             #[cfg(feature = "js-serializable-module")]
             raw_bytes: None,
         }
@@ -6,6 +6,7 @@
 //!  
 //! https://github.com/WebAssembly/js-types/blob/master/proposals/js-types/Overview.md
 use core::convert::TryFrom;
+use std::collections::HashMap;
 use std::vec::Vec;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{
@@ -17,7 +18,7 @@ use wasmparser::{
     self, BinaryReaderError, Export, ExportSectionReader, ExternalKind, FuncType as WPFunctionType,
     FunctionSectionReader, GlobalSectionReader, GlobalType as WPGlobalType, ImportSectionEntryType,
     ImportSectionReader, MemorySectionReader, MemoryType as WPMemoryType, NameSectionReader,
-    Parser, Payload, TableSectionReader, TypeDef, TypeSectionReader,
+    Naming, NamingReader, Parser, Payload, TableSectionReader, TypeDef, TypeSectionReader,
 };
 
 pub type WasmResult<T> = Result<T, String>;
@@ -242,6 +243,17 @@ impl ModuleInfoPolyfill {
         self.info.name = Some(name.to_string());
         Ok(())
     }
+
+    pub(crate) fn declare_function_name(
+        &mut self,
+        func_index: FunctionIndex,
+        name: &str,
+    ) -> WasmResult<()> {
+        self.info
+            .function_names
+            .insert(func_index, name.to_string());
+        Ok(())
+    }
 }
 
 fn transform_err(err: BinaryReaderError) -> String {
@@ -372,7 +384,7 @@ pub fn parse_import_section<'data>(
                 unimplemented!("module linking not implemented yet")
             }
             ImportSectionEntryType::Tag(_) => {
-                unimplemented!("exception handling not implemented yet")
+                return Err("exception handling is not supported".to_string());
             }
             ImportSectionEntryType::Memory(WPMemoryType {
                 shared,
@@ -538,7 +550,7 @@ pub fn parse_export_section<'data>(
                 unimplemented!("module linking not implemented yet")
             }
             ExternalKind::Tag => {
-                unimplemented!("exception handling not implemented yet")
+                return Err("exception handling is not supported".to_string());
             }
         }
     }
@@ -558,16 +570,16 @@ pub fn parse_name_section<'data>(
 ) -> WasmResult<()> {
     while let Ok(subsection) = names.read() {
         match subsection {
-            wasmparser::Name::Function(_function_subsection) => {
-                // if let Some(function_names) = function_subsection
-                //     .get_map()
-                //     .ok()
-                //     .and_then(parse_function_name_subsection)
-                // {
-                //     for (index, name) in function_names {
-                //         module_info.declare_function_name(index, name)?;
-                //     }
-                // }
+            wasmparser::Name::Function(function_subsection) => {
+                if let Some(function_names) = function_subsection
+                    .get_map()
+                    .ok()
+                    .and_then(parse_function_name_subsection)
+                {
+                    for (index, name) in function_names {
+                        module_info.declare_function_name(index, name)?;
+                    }
+                }
             }
             wasmparser::Name::Module(module) => {
                 if let Ok(name) = module.get_name() {
@@ -588,26 +600,26 @@ pub fn parse_name_section<'data>(
     Ok(())
 }
 
-// fn parse_function_name_subsection(
-//     mut naming_reader: NamingReader<'_>,
-// ) -> Option<HashMap<FunctionIndex, &str>> {
-//     let mut function_names = HashMap::new();
-//     for _ in 0..naming_reader.get_count() {
-//         let Naming { index, name } = naming_reader.read().ok()?;
-//         if index == std::u32::MAX {
-//             // We reserve `u32::MAX` for our own use.
-//             return None;
-//         }
-
-//         if function_names
-//             .insert(FunctionIndex::from_u32(index), name)
-//             .is_some()
-//         {
-//             // If the function index has been previously seen, then we
-//             // break out of the loop and early return `None`, because these
-//             // should be unique.
-//             return None;
-//         }
-//     }
-//     Some(function_names)
-// }
+fn parse_function_name_subsection(
+    mut naming_reader: NamingReader<'_>,
+) -> Option<HashMap<FunctionIndex, &str>> {
+    let mut function_names = HashMap::new();
+    for _ in 0..naming_reader.get_count() {
+        let Naming { index, name } = naming_reader.read().ok()?;
+        if index == std::u32::MAX {
+            // We reserve `u32::MAX` for our own use.
+            return None;
+        }
+
+        if function_names
+            .insert(FunctionIndex::from_u32(index), name)
+            .is_some()
+        {
+            // If the function index has been previously seen, then we
+            // break out of the loop and early return `None`, because these
+            // should be unique.
+            return None;
+        }
+    }
+    Some(function_names)
+}
@@ -14,7 +14,6 @@ use crate::js::{FromToNativeWasmType, Function, RuntimeError, Store, WasmTypeLis
 use crate::js::export::VMFunction;
 use crate::js::types::param_from_js;
 use js_sys::Array;
-use std::iter::FromIterator;
 use wasm_bindgen::JsValue;
 use wasmer_types::NativeWasmType;
 
@@ -77,11 +76,8 @@ macro_rules! impl_native_traits {
         {
             /// Call the typed func and return results.
             pub fn call(&self, $( $x: $x, )* ) -> Result<Rets, RuntimeError> {
-                let params_list: Vec<JsValue> = vec![ $( JsValue::from_f64($x.to_native().to_binary() as f64) ),* ];
-                let results = self.exported.function.apply(
-                    &JsValue::UNDEFINED,
-                    &Array::from_iter(params_list.iter())
-                )?;
+                let params_list: &[JsValue] = &[ $( JsValue::from_f64($x.to_native().to_binary() as f64) ),* ];
+                let results = self.exported.call_with_args(params_list)?;
                 let mut rets_list_array = Rets::empty_array();
                 let mut_rets = rets_list_array.as_mut() as *mut [i128] as *mut i128;
                 match Rets::size() {
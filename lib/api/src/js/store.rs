@@ -1,4 +1,7 @@
+use crate::js::Extern;
+use crate::StoreGcStats;
 use std::fmt;
+use thiserror::Error;
 
 /// The store represents all global state that can be manipulated by
 /// WebAssembly programs. It consists of the runtime representation
@@ -25,6 +28,40 @@ impl Store {
     pub fn same(_a: &Self, _b: &Self) -> bool {
         true
     }
+
+    /// Registers an extern created in another store in this one, so it
+    /// can be imported by the instances of this store.
+    ///
+    /// All the stores share the JS engine, so every extern can be
+    /// adopted, and the adopted extern shares its state with the original
+    /// one.
+    pub fn adopt(&self, extern_: &Extern) -> Result<Extern, AdoptError> {
+        Ok(extern_.clone())
+    }
+
+    /// Frees the references to the functions of the dropped instances.
+    ///
+    /// The JS engine's garbage collector frees the objects no longer
+    /// referenced, so there's nothing to free here.
+    pub fn gc(&self) -> StoreGcStats {
+        StoreGcStats::default()
+    }
+}
+
+/// The error returned by [`Store::adopt`] when an extern can't be moved
+/// to another store.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AdoptError {
+    /// The function is defined in WebAssembly, and its code belongs to
+    /// the engine of its store.
+    #[error(
+        "a function defined in WebAssembly can only be adopted by a store with the same engine"
+    )]
+    CompiledFunction,
+    /// The global or the table holds function references, whose
+    /// signatures are registered in the engine of its store.
+    #[error("a funcref global or table can only be adopted by a store with the same engine")]
+    FuncRef,
 }
 
 impl PartialEq for Store {
@@ -1,10 +1,11 @@
-// use super::frame_info::{FrameInfo, GlobalFrameInfo, FRAME_INFO};
+use crate::js::frame_info::{parse_stack, FrameInfo};
 use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
 use wasm_bindgen::convert::FromWasmAbi;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{JsCast, JsValue};
+use wasmer_types::{RuntimeErrorKind, TrapCode};
 
 /// A struct representing an aborted instruction execution, with a message
 /// indicating the cause.
@@ -31,7 +32,11 @@ impl PartialEq for RuntimeError {
 enum RuntimeErrorSource {
     Generic(String),
     User(Box<dyn Error + Send + Sync>),
-    Js(JsValue),
+    Js {
+        value: JsValue,
+        /// The WebAssembly frames of the stack of the exception.
+        trace: Vec<FrameInfo>,
+    },
 }
 
 /// This is a hack to ensure the error type is Send+Sync
@@ -43,7 +48,7 @@ impl fmt::Display for RuntimeErrorSource {
         match self {
             Self::Generic(s) => write!(f, "{}", s),
             Self::User(s) => write!(f, "{}", s),
-            Self::Js(s) => write!(f, "{:?}", s),
+            Self::Js { value, .. } => write!(f, "{:?}", value),
         }
     }
 }
@@ -89,6 +94,18 @@ impl RuntimeError {
         format!("{}", self.inner)
     }
 
+    /// Returns a list of function frames in WebAssembly code that led to this
+    /// trap happening, parsed from the stack of the JS exception.
+    ///
+    /// The functions are named after the `name` section of their module,
+    /// rather than `wasm-function[<index>]` as the JS engines do.
+    pub fn trace(&self) -> &[FrameInfo] {
+        match self.inner.as_ref() {
+            RuntimeErrorSource::Js { trace, .. } => trace,
+            _ => &[],
+        }
+    }
+
     /// Attempts to downcast the `RuntimeError` to a concrete type.
     pub fn downcast<T: Error + 'static>(self) -> Result<T, Self> {
         match Arc::try_unwrap(self.inner) {
@@ -101,6 +118,42 @@ impl RuntimeError {
         }
     }
 
+    /// Attempts to downcast a reference to the `RuntimeError` to a
+    /// concrete type.
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        match self.inner.as_ref() {
+            // We only try to downcast user errors
+            RuntimeErrorSource::User(err) => err.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+
+    /// Returns what the error comes from.
+    ///
+    /// The exceptions of the JS engine are traps if their code can be
+    /// guessed from their message, see [`RuntimeError::to_trap`].
+    pub fn kind(&self) -> RuntimeErrorKind {
+        match self.inner.as_ref() {
+            RuntimeErrorSource::Generic(_) => RuntimeErrorKind::Generic,
+            RuntimeErrorSource::User(_) => RuntimeErrorKind::User,
+            RuntimeErrorSource::Js { value, .. } => match trap_code_of_js(value) {
+                Some(trap_code) => RuntimeErrorKind::Trap(trap_code),
+                None => RuntimeErrorKind::Js,
+            },
+        }
+    }
+
+    /// Returns trap code, if it's a Trap.
+    ///
+    /// The JS engines don't tell the trap codes, which are guessed from
+    /// the messages of their `WebAssembly.RuntimeError`s.
+    pub fn to_trap(self) -> Option<TrapCode> {
+        match self.kind() {
+            RuntimeErrorKind::Trap(trap_code) => Some(trap_code),
+            _ => None,
+        }
+    }
+
     /// Returns true if the `RuntimeError` is the same as T
     pub fn is<T: Error + 'static>(&self) -> bool {
         match self.inner.as_ref() {
@@ -110,6 +163,57 @@ impl RuntimeError {
     }
 }
 
+/// The trap code of an exception thrown by the JS engine, guessed from
+/// its message, which differs from an engine to another.
+fn trap_code_of_js(js: &JsValue) -> Option<TrapCode> {
+    use js_sys::{RangeError, WebAssembly};
+
+    if js.is_instance_of::<RangeError>() {
+        // The stack overflows are `RangeError`s, rather than traps.
+        let message = String::from(js.unchecked_ref::<RangeError>().message()).to_lowercase();
+        return if message.contains("call stack") || message.contains("recursion") {
+            Some(TrapCode::StackOverflow)
+        } else {
+            None
+        };
+    }
+    if !js.is_instance_of::<WebAssembly::RuntimeError>() {
+        return None;
+    }
+
+    let message = String::from(js.unchecked_ref::<js_sys::Error>().message()).to_lowercase();
+    let trap_code = if message.contains("unreachable") {
+        TrapCode::UnreachableCodeReached
+    } else if message.contains("divide by zero") || message.contains("division by zero") {
+        TrapCode::IntegerDivisionByZero
+    } else if message.contains("integer overflow")
+        || message.contains("divide result unrepresentable")
+    {
+        TrapCode::IntegerOverflow
+    } else if message.contains("unrepresentable") || message.contains("invalid conversion") {
+        TrapCode::BadConversionToInteger
+    } else if message.contains("unaligned") {
+        TrapCode::UnalignedAtomic
+    } else if message.contains("signature mismatch") || message.contains("type mismatch") {
+        TrapCode::BadSignature
+    } else if message.contains("null function")
+        || message.contains("uninitialized element")
+        || message.contains("indirect call to null")
+    {
+        TrapCode::IndirectCallToNull
+    } else if message.contains("table") {
+        TrapCode::TableAccessOutOfBounds
+    } else if message.contains("memory access") || message.contains("index out of bounds") {
+        TrapCode::HeapAccessOutOfBounds
+    } else if message.contains("out of bounds") {
+        TrapCode::OutOfBounds
+    } else {
+        return None;
+    };
+
+    Some(trap_code)
+}
+
 impl fmt::Debug for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("RuntimeError")
@@ -121,6 +225,17 @@ impl fmt::Debug for RuntimeError {
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "RuntimeError: {}", self.message())?;
+        for frame in self.trace() {
+            writeln!(f)?;
+            write!(
+                f,
+                "    at {} ({}[{}]:0x{:x})",
+                frame.function_name().unwrap_or("<unnamed>"),
+                frame.module_name(),
+                frame.func_index(),
+                frame.module_offset()
+            )?;
+        }
         Ok(())
     }
 }
@@ -162,8 +277,16 @@ impl From<JsValue> for RuntimeError {
         // We try to downcast the error and see if it's
         // an instance of RuntimeError instead, so we don't need
         // to re-wrap it.
-        generic_of_jsval(original, "WasmerRuntimeError").unwrap_or_else(|js| RuntimeError {
-            inner: Arc::new(RuntimeErrorSource::Js(js)),
+        generic_of_jsval(original, "WasmerRuntimeError").unwrap_or_else(|value| {
+            let trace = value
+                .dyn_ref::<js_sys::Error>()
+                .and_then(|error| js_sys::Reflect::get(error, &"stack".into()).ok())
+                .and_then(|stack| stack.as_string())
+                .map(|stack| parse_stack(&stack))
+                .unwrap_or_default();
+            RuntimeError {
+                inner: Arc::new(RuntimeErrorSource::Js { value, trace }),
+            }
         })
     }
 }
@@ -0,0 +1,35 @@
+//! Validation of the modules with `wasmparser`, as the `sys` backend
+//! does, rather than with the JS engine, whose exceptions don't tell
+//! what is wrong with the module.
+
+use crate::js::error::CompileError;
+use wasmer_types::Features;
+use wasmparser::{Validator, WasmFeatures};
+
+/// Validates `binary`, with the WebAssembly features enabled by
+/// default, whose errors are the ones the `sys` backend reports.
+pub(crate) fn validate_module(binary: &[u8]) -> Result<(), CompileError> {
+    let features = Features::default();
+    let mut validator = Validator::new();
+    validator.wasm_features(WasmFeatures {
+        bulk_memory: features.bulk_memory,
+        threads: features.threads,
+        reference_types: features.reference_types,
+        multi_value: features.multi_value,
+        simd: features.simd,
+        tail_call: features.tail_call,
+        module_linking: features.module_linking,
+        multi_memory: features.multi_memory,
+        memory64: features.memory64,
+        exceptions: features.exceptions,
+        deterministic_only: false,
+        extended_const: features.extended_const,
+        relaxed_simd: features.relaxed_simd,
+        mutable_global: true,
+        saturating_float_to_int: true,
+        sign_extension: true,
+    });
+    validator
+        .validate_all(binary)
+        .map_err(|e| CompileError::Validate(format!("{}", e)))
+}
@@ -91,7 +91,9 @@
 //!   is possible to serialize it in a file for example, and later execute
 //!   it with Wasmer with headless mode turned on. Headless Wasmer has no
 //!   compiler, which makes it more portable and faster to load. It's
-//!   ideal for constrainted environments.
+//!   ideal for constrainted environments. A headless store is made with
+//!   `UniversalEngine::headless`, and the `headless` feature builds
+//!   Wasmer without any compiler.
 //!   
 //! * **Cross-compilation** — Most compilers support cross-compilation. It
 //!   means it possible to pre-compile a WebAssembly module targetting a
@@ -318,7 +320,31 @@
 //! - `universal`
 #![cfg_attr(feature = "universal", doc = "(enabled),")]
 #![cfg_attr(not(feature = "universal"), doc = "(disabled),")]
-//!   enables [the Universal engine][`wasmer-engine-universal`].
+//!   enables [the Universal engine][`wasmer-engine-universal`],
+//! - `headless`
+#![cfg_attr(feature = "headless", doc = "(enabled),")]
+#![cfg_attr(not(feature = "headless"), doc = "(disabled),")]
+//!   enables the Universal engine without any compiler, which only
+//!   loads the modules compiled ahead of time with
+//!   [`Module::deserialize`]: `Store::default` then makes a headless
+//!   store, unless a default compiler is set,
+//! - `debug-symbols`
+#![cfg_attr(feature = "debug-symbols", doc = "(enabled),")]
+#![cfg_attr(not(feature = "debug-symbols"), doc = "(disabled),")]
+//!   adds the source locations found in the DWARF debug info of the
+//!   modules to the frames of [`RuntimeError::trace`],
+//! - `jit-debug`
+#![cfg_attr(feature = "jit-debug", doc = "(enabled),")]
+#![cfg_attr(not(feature = "jit-debug"), doc = "(disabled),")]
+//!   allows registering the compiled code with the native debuggers and
+//!   profilers, see `Universal::jit_debug`,
+//! - `tracing`
+#![cfg_attr(feature = "tracing", doc = "(enabled),")]
+#![cfg_attr(not(feature = "tracing"), doc = "(disabled),")]
+//!   reports the phases of compiling and instantiating the modules
+//!   (validation, compilation, import resolution, allocation, start
+//!   function…) with their durations, as [`tracing`] events, to find
+//!   where the cold start time goes.
 //!
 //! The features that set defaults come in sets that are mutually exclusive.
 //!
@@ -426,6 +452,7 @@
 //! [`wasmer-wasi`]: https://docs.rs/wasmer-wasi/
 //! [`wasm-pack`]: https://github.com/rustwasm/wasm-pack/
 //! [`wasm-bindgen`]: https://github.com/rustwasm/wasm-bindgen
+//! [`tracing`]: https://docs.rs/tracing/
 
 #[cfg(all(not(feature = "sys"), not(feature = "js")))]
 compile_error!("At least the `sys` or the `js` feature must be enabled. Please, pick one.");
@@ -454,3 +481,17 @@ mod js;
 
 #[cfg(feature = "js")]
 pub use js::*;
+
+mod bytes_call;
+mod errors;
+mod gc;
+mod imports_report;
+mod interface;
+mod linker;
+mod module_editor;
+
+pub use bytes_call::BytesCallConvention;
+pub use gc::StoreGcStats;
+pub use interface::*;
+pub use linker::{Linker, LinkerError};
+pub use module_editor::{ModuleEditError, ModuleEditor};
@@ -0,0 +1,187 @@
+//! Linking instances together, see [`Linker`].
+
+use crate::{Extern, Imports, Instance, InstantiationError, Module};
+use std::collections::HashMap;
+use thiserror::Error;
+
+impl Imports {
+    /// Creates the imports providing the exports of `instance` in the
+    /// namespace `namespace`, so that another module can import them.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Imports, Instance, Module, Store};
+    /// # let store = Store::default();
+    /// let lib = Module::new(&store, r#"(module (func (export "answer") (result i32) (i32.const 42)))"#)?;
+    /// let lib = Instance::new(&lib, &imports! {})?;
+    ///
+    /// let app = Module::new(&store, r#"(module (import "lib" "answer" (func (result i32))))"#)?;
+    /// let app = Instance::new(&app, &Imports::from_instance_exports("lib", &lib))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_instance_exports(namespace: &str, instance: &Instance) -> Self {
+        let mut imports = Self::new();
+        imports.register_namespace(namespace, instance.exports.clone());
+
+        imports
+    }
+}
+
+/// An error while instantiating a module with a [`Linker`].
+#[derive(Error, Debug)]
+pub enum LinkerError {
+    /// The registered modules import from each other, directly or
+    /// not. The names of the modules are listed in the order they
+    /// import from each other, from and to the same module.
+    #[error("the modules import from each other: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    /// The registered module `name` couldn't be instantiated.
+    #[error("failed to instantiate the module {name:?}: {error:?}")]
+    Dependency {
+        /// The name the module is registered with.
+        name: String,
+        /// Why it couldn't be instantiated.
+        error: InstantiationError,
+    },
+    /// The module couldn't be instantiated.
+    #[error("failed to instantiate the module: {0:?}")]
+    Instantiation(InstantiationError),
+}
+
+/// Links modules and instances together, by their names.
+///
+/// The instances registered with [`Linker::instance`] provide their
+/// exports to the modules importing from their name, as do host
+/// definitions registered with [`Linker::define`]. The modules
+/// registered with [`Linker::module`] are instantiated on demand,
+/// the first time a module importing from their name is instantiated,
+/// after the modules they import from themselves.
+///
+/// ```
+/// # use wasmer::{Linker, Module, Store};
+/// # let store = Store::default();
+/// let mut linker = Linker::new();
+/// linker.module(
+///     "lib",
+///     &Module::new(&store, r#"(module (func (export "answer") (result i32) (i32.const 42)))"#)?,
+/// );
+///
+/// let app = Module::new(
+///     &store,
+///     r#"
+///     (module
+///         (import "lib" "answer" (func $answer (result i32)))
+///         (func (export "main") (result i32) (call $answer)))
+///     "#,
+/// )?;
+/// let app = linker.instantiate(&app)?;
+/// let main = app.exports.get_native_function::<(), i32>("main")?;
+/// assert_eq!(main.call()?, 42);
+/// assert!(linker.get_instance("lib").is_some());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Linker {
+    imports: Imports,
+    instances: HashMap<String, Instance>,
+    modules: HashMap<String, Module>,
+}
+
+impl Linker {
+    /// Creates a linker without any definition.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines the import `name` of the namespace `namespace`, e.g. a
+    /// host function.
+    pub fn define(&mut self, namespace: &str, name: &str, value: impl Into<Extern>) -> &mut Self {
+        self.imports.define(namespace, name, value);
+        self
+    }
+
+    /// Registers `instance` as `name`, so that its exports are imported
+    /// by the modules importing from `name`.
+    pub fn instance(&mut self, name: &str, instance: &Instance) -> &mut Self {
+        self.imports
+            .extend(&Imports::from_instance_exports(name, instance));
+        self.instances.insert(name.to_string(), instance.clone());
+        self
+    }
+
+    /// Registers `module` as `name`, to be instantiated when a module
+    /// importing from `name` is.
+    pub fn module(&mut self, name: &str, module: &Module) -> &mut Self {
+        self.modules.insert(name.to_string(), module.clone());
+        self
+    }
+
+    /// Returns the instance registered as `name`, or instantiated from
+    /// the module registered as `name`, if any.
+    pub fn get_instance(&self, name: &str) -> Option<&Instance> {
+        self.instances.get(name)
+    }
+
+    /// The imports defined so far, including the exports of the
+    /// instances.
+    pub fn imports(&self) -> &Imports {
+        &self.imports
+    }
+
+    /// Instantiates `module`, after instantiating the registered
+    /// modules it depends on, directly or not.
+    pub fn instantiate(&mut self, module: &Module) -> Result<Instance, LinkerError> {
+        self.instantiate_dependencies(module, &mut Vec::new())?;
+
+        Instance::new(module, &self.imports).map_err(LinkerError::Instantiation)
+    }
+
+    /// Instantiates the registered modules `module` imports from, which
+    /// haven't been yet. `stack` holds the names of the modules whose
+    /// dependencies are being instantiated, to detect the cycles.
+    fn instantiate_dependencies(
+        &mut self,
+        module: &Module,
+        stack: &mut Vec<String>,
+    ) -> Result<(), LinkerError> {
+        let mut namespaces = Vec::<String>::new();
+        for import in module.imports() {
+            if !namespaces
+                .iter()
+                .any(|namespace| namespace == import.module())
+            {
+                namespaces.push(import.module().to_string());
+            }
+        }
+
+        for name in namespaces {
+            if self.instances.contains_key(&name) {
+                continue;
+            }
+            // Otherwise the imports are expected to be defined.
+            let dependency = match self.modules.get(&name) {
+                Some(dependency) => dependency.clone(),
+                None => continue,
+            };
+
+            if let Some(start) = stack.iter().position(|module| *module == name) {
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(name);
+                return Err(LinkerError::Cycle(cycle));
+            }
+
+            stack.push(name.clone());
+            self.instantiate_dependencies(&dependency, stack)?;
+            stack.pop();
+
+            let instance = Instance::new(&dependency, &self.imports).map_err(|error| {
+                LinkerError::Dependency {
+                    name: name.clone(),
+                    error,
+                }
+            })?;
+            self.instance(&name, &instance);
+        }
+
+        Ok(())
+    }
+}
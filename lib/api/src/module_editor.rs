@@ -0,0 +1,231 @@
+use thiserror::Error;
+
+/// The magic number and the version starting every WebAssembly module.
+const HEADER: [u8; 8] = *b"\0asm\x01\0\0\0";
+
+/// The id of the custom sections.
+const CUSTOM_SECTION_ID: u8 = 0;
+
+/// The custom sections holding debug information, removed by
+/// [`ModuleEditor::strip_debug_info`], besides the `.debug_*` ones.
+const DEBUG_SECTIONS: &[&str] = &["name", "sourceMappingURL", "external_debug_info"];
+
+/// An error while reading the bytes of a module in a [`ModuleEditor`].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleEditError {
+    /// The bytes don't start with the header of a WebAssembly module.
+    #[error("not a WebAssembly module: bad magic number or version")]
+    BadHeader,
+    /// A section ends past the end of the module, at `offset`.
+    #[error("the section at offset {offset} is truncated")]
+    Truncated {
+        /// The offset of the section in the module.
+        offset: usize,
+    },
+    /// The name of the custom section at `offset` isn't valid UTF-8.
+    #[error("the name of the custom section at offset {offset} isn't valid UTF-8")]
+    BadName {
+        /// The offset of the section in the module.
+        offset: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Section {
+    Custom { name: String, data: Vec<u8> },
+    // The other sections are kept as they are, without their size.
+    Other { id: u8, contents: Vec<u8> },
+}
+
+/// Rewrites the bytes of a WebAssembly module, to enumerate, remove,
+/// replace or add its custom sections, e.g. to strip its debug
+/// information or to embed metadata in it, without an external tool.
+///
+/// The other sections are kept untouched, in the same order, so the
+/// module produced is valid if the original one is. Only the structure
+/// of the sections is read, the module isn't validated.
+///
+/// ```
+/// # use wasmer::{ModuleEditor, wat2wasm};
+/// let wasm = wat2wasm(br#"(module $hello (func $answer (result i32) (i32.const 42)))"#).unwrap();
+///
+/// let mut editor = ModuleEditor::new(&wasm).unwrap();
+/// assert_eq!(editor.strip_debug_info(), 1);
+/// editor.add_custom_section("producers", b"ci".to_vec());
+///
+/// let stripped = editor.into_bytes();
+/// let editor = ModuleEditor::new(&stripped).unwrap();
+/// let names = editor.custom_sections().map(|(name, _)| name).collect::<Vec<_>>();
+/// assert_eq!(names, ["producers"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModuleEditor {
+    sections: Vec<Section>,
+}
+
+impl ModuleEditor {
+    /// Reads the sections of the module `bytes`.
+    pub fn new(bytes: &[u8]) -> Result<Self, ModuleEditError> {
+        if !bytes.starts_with(&HEADER) {
+            return Err(ModuleEditError::BadHeader);
+        }
+
+        let mut sections = Vec::new();
+        let mut offset = HEADER.len();
+        while offset < bytes.len() {
+            let truncated = ModuleEditError::Truncated { offset };
+            let id = bytes[offset];
+            let (size, size_len) = read_u32(&bytes[offset + 1..]).ok_or(truncated)?;
+            let start = offset + 1 + size_len;
+            let contents = start
+                .checked_add(size as usize)
+                .and_then(|end| bytes.get(start..end))
+                .ok_or(truncated)?;
+
+            sections.push(if id == CUSTOM_SECTION_ID {
+                let (name_len, name_len_len) = read_u32(contents).ok_or(truncated)?;
+                let name = contents
+                    .get(name_len_len..name_len_len + name_len as usize)
+                    .ok_or(truncated)?;
+                let name = std::str::from_utf8(name)
+                    .map_err(|_| ModuleEditError::BadName { offset })?
+                    .to_string();
+                let data = contents[name_len_len + name_len as usize..].to_vec();
+                Section::Custom { name, data }
+            } else {
+                Section::Other {
+                    id,
+                    contents: contents.to_vec(),
+                }
+            });
+            offset = start + contents.len();
+        }
+
+        Ok(Self { sections })
+    }
+
+    /// The custom sections, in the order they appear in the module,
+    /// with their names.
+    pub fn custom_sections(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.sections.iter().filter_map(|section| match section {
+            Section::Custom { name, data } => Some((name.as_str(), data.as_slice())),
+            Section::Other { .. } => None,
+        })
+    }
+
+    /// Removes the custom sections named `name`, and returns how many
+    /// there were.
+    pub fn remove_custom_sections(&mut self, name: &str) -> usize {
+        self.retain_custom_sections(|section| section != name)
+    }
+
+    /// Removes the custom sections holding debug information: the
+    /// DWARF `.debug_*` sections, the `name` section, and the source
+    /// map and external debug info references. Returns how many there
+    /// were.
+    pub fn strip_debug_info(&mut self) -> usize {
+        self.retain_custom_sections(|name| {
+            !name.starts_with(".debug_") && !DEBUG_SECTIONS.contains(&name)
+        })
+    }
+
+    /// Replaces the data of the first custom section named `name`, and
+    /// removes the others, or adds the section at the end of the module
+    /// if there's none.
+    pub fn replace_custom_section(&mut self, name: &str, data: Vec<u8>) {
+        let first = self
+            .sections
+            .iter()
+            .position(|section| matches!(section, Section::Custom { name: n, .. } if n == name));
+        match first {
+            Some(first) => {
+                self.sections[first] = Section::Custom {
+                    name: name.to_string(),
+                    data,
+                };
+                let mut index = 0;
+                self.sections.retain(|section| {
+                    index += 1;
+                    index - 1 == first
+                        || !matches!(section, Section::Custom { name: n, .. } if n == name)
+                });
+            }
+            None => self.add_custom_section(name, data),
+        }
+    }
+
+    /// Adds a custom section named `name` at the end of the module,
+    /// even if there are already sections of this name.
+    pub fn add_custom_section(&mut self, name: &str, data: Vec<u8>) {
+        self.sections.push(Section::Custom {
+            name: name.to_string(),
+            data,
+        });
+    }
+
+    /// The bytes of the edited module.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = HEADER.to_vec();
+        for section in &self.sections {
+            match section {
+                Section::Custom { name, data } => {
+                    let mut contents = Vec::with_capacity(5 + name.len() + data.len());
+                    write_u32(&mut contents, name.len() as u32);
+                    contents.extend_from_slice(name.as_bytes());
+                    contents.extend_from_slice(data);
+                    write_section(&mut bytes, CUSTOM_SECTION_ID, &contents);
+                }
+                Section::Other { id, contents } => write_section(&mut bytes, *id, contents),
+            }
+        }
+        bytes
+    }
+
+    /// The bytes of the edited module.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn retain_custom_sections(&mut self, mut keep: impl FnMut(&str) -> bool) -> usize {
+        let before = self.sections.len();
+        self.sections.retain(|section| match section {
+            Section::Custom { name, .. } => keep(name),
+            Section::Other { .. } => true,
+        });
+        before - self.sections.len()
+    }
+}
+
+/// Reads an unsigned LEB128 `u32`, and returns it with its length.
+fn read_u32(bytes: &[u8]) -> Option<(u32, usize)> {
+    let mut value = 0u32;
+    for (i, byte) in bytes.iter().take(5).enumerate() {
+        let bits = (byte & 0x7f) as u32;
+        if i == 4 && bits > 0x0f {
+            return None;
+        }
+        value |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn write_u32(bytes: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn write_section(bytes: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    bytes.push(id);
+    write_u32(bytes, contents.len() as u32);
+    bytes.extend_from_slice(contents);
+}
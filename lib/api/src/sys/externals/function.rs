@@ -14,11 +14,20 @@ use std::fmt;
 use std::sync::Arc;
 use wasmer_compiler::{Export, ExportFunction, ExportFunctionMetadata};
 use wasmer_vm::{
-    on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline, ImportInitializerFuncPtr,
-    VMCallerCheckedAnyfunc, VMDynamicFunctionContext, VMFuncRef, VMFunction, VMFunctionBody,
-    VMFunctionEnvironment, VMFunctionKind, VMTrampoline,
+    catch_traps, exception_pending, on_host_stack, raise_user_trap, resume_panic,
+    wasmer_call_trampoline, FuncDataOwner, ImportInitializerFuncPtr, VMCallerCheckedAnyfunc,
+    VMDynamicFunctionContext, VMFuncRef, VMFunction, VMFunctionBody, VMFunctionEnvironment,
+    VMFunctionKind, VMTrampoline,
 };
 
+fn format_types_for_error_message(items: &[Val]) -> String {
+    items
+        .iter()
+        .map(|param| param.ty().to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 /// A WebAssembly `function` instance.
 ///
 /// A function instance is the runtime representation of a function.
@@ -380,19 +389,8 @@ impl Function {
         &self.store
     }
 
-    fn call_wasm(
-        &self,
-        trampoline: VMTrampoline,
-        params: &[Val],
-        results: &mut [Val],
-    ) -> Result<(), RuntimeError> {
-        let format_types_for_error_message = |items: &[Val]| {
-            items
-                .iter()
-                .map(|param| param.ty().to_string())
-                .collect::<Vec<String>>()
-                .join(", ")
-        };
+    /// Checks `params` against the signature and stores them into `values`.
+    fn write_params(&self, params: &[Val], values: &mut [i128]) -> Result<(), RuntimeError> {
         let signature = self.ty();
         if signature.params().len() != params.len() {
             return Err(RuntimeError::new(format!(
@@ -401,19 +399,9 @@ impl Function {
                 &signature
             )));
         }
-        if signature.results().len() != results.len() {
-            return Err(RuntimeError::new(format!(
-                "Results of type [{}] did not match signature {}",
-                format_types_for_error_message(results),
-                &signature,
-            )));
-        }
-
-        let mut values_vec = vec![0; max(params.len(), results.len())];
 
-        // Store the argument values into `values_vec`.
         let param_tys = signature.params().iter();
-        for ((arg, slot), ty) in params.iter().zip(&mut values_vec).zip(param_tys) {
+        for ((arg, slot), ty) in params.iter().zip(values).zip(param_tys) {
             if arg.ty() != *ty {
                 let param_types = format_types_for_error_message(params);
                 return Err(RuntimeError::new(format!(
@@ -425,6 +413,38 @@ impl Function {
                 arg.write_value_to(slot);
             }
         }
+        Ok(())
+    }
+
+    /// Loads the results out of `values`.
+    fn read_results(&self, values: &[i128], results: &mut [Val]) {
+        for (index, &value_type) in self.ty().results().iter().enumerate() {
+            unsafe {
+                let ptr = values.as_ptr().add(index);
+                results[index] = Val::read_value_from(&self.store, ptr, value_type);
+            }
+        }
+    }
+
+    fn call_wasm(
+        &self,
+        trampoline: VMTrampoline,
+        params: &[Val],
+        results: &mut [Val],
+    ) -> Result<(), RuntimeError> {
+        let signature = self.ty();
+        if signature.results().len() != results.len() {
+            return Err(RuntimeError::new(format!(
+                "Results of type [{}] did not match signature {}",
+                format_types_for_error_message(results),
+                &signature,
+            )));
+        }
+
+        let mut values_vec = vec![0; max(params.len(), results.len())];
+
+        // Store the argument values into `values_vec`.
+        self.write_params(params, &mut values_vec)?;
 
         // Call the trampoline.
         if let Err(error) = unsafe {
@@ -440,14 +460,59 @@ impl Function {
         }
 
         // Load the return values out of `values_vec`.
-        for (index, &value_type) in signature.results().iter().enumerate() {
-            unsafe {
-                let ptr = values_vec.as_ptr().add(index);
-                results[index] = Val::read_value_from(&self.store, ptr, value_type);
+        self.read_results(&values_vec, results);
+
+        Ok(())
+    }
+
+    fn call_wasm_batched(
+        &self,
+        trampoline: VMTrampoline,
+        batch: &[&[Val]],
+    ) -> Result<Vec<Box<[Val]>>, RuntimeError> {
+        let stride = max(self.param_arity(), self.result_arity());
+        let mut values_vec = vec![0; stride * batch.len()];
+
+        // All the arguments are checked before calling anything, so a
+        // mismatch doesn't leave the batch half run.
+        if stride > 0 {
+            for (params, values) in batch.iter().zip(values_vec.chunks_mut(stride)) {
+                self.write_params(params, values)?;
             }
+        } else if let Some(params) = batch.iter().find(|params| !params.is_empty()) {
+            self.write_params(params, &mut [])?;
         }
 
-        Ok(())
+        // Enter the VM once for the whole batch.
+        let vmctx = self.exported.vm_function.vmctx;
+        let callee = self.exported.vm_function.address;
+        let values_ptr = values_vec.as_mut_ptr();
+        let calls = batch.len();
+        if let Err(error) = unsafe {
+            catch_traps(&self.store, || {
+                let trampoline = std::mem::transmute::<
+                    _,
+                    extern "C" fn(VMFunctionEnvironment, *const VMFunctionBody, *mut u8),
+                >(trampoline);
+                for call in 0..calls {
+                    trampoline(vmctx, callee, values_ptr.add(call * stride) as *mut u8);
+                    // An uncaught exception ends the batch, as a trap does.
+                    if exception_pending() {
+                        break;
+                    }
+                }
+            })
+        } {
+            return Err(RuntimeError::from_trap(error));
+        }
+
+        Ok((0..calls)
+            .map(|call| {
+                let mut results = vec![Val::null(); self.result_arity()];
+                self.read_results(&values_vec[call * stride..], &mut results);
+                results.into_boxed_slice()
+            })
+            .collect())
     }
 
     /// Returns the number of parameters that this function takes.
@@ -542,6 +607,59 @@ impl Function {
         }
     }
 
+    /// Calls the `Function` once per element of `batch` and returns the
+    /// results of every call, in order.
+    ///
+    /// This is meant for the host invoking a guest callback a large number
+    /// of times, e.g. once per pixel: a function defined in WebAssembly is
+    /// entered only once for the whole batch rather than once per call,
+    /// which removes most of the overhead of crossing the boundary. All the
+    /// arguments are checked before any call is made. If a call traps, the
+    /// batch stops and the trap is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{imports, wat2wasm, Function, Instance, Module, Store, Type, Value};
+    /// # let store = Store::default();
+    /// # let wasm_bytes = wat2wasm(r#"
+    /// # (module
+    /// #   (func (export "sum") (param $x i32) (param $y i32) (result i32)
+    /// #     local.get $x
+    /// #     local.get $y
+    /// #     i32.add
+    /// #   ))
+    /// # "#.as_bytes()).unwrap();
+    /// # let module = Module::new(&store, wasm_bytes).unwrap();
+    /// # let import_object = imports! {};
+    /// # let instance = Instance::new(&module, &import_object).unwrap();
+    /// #
+    /// let sum = instance.exports.get_function("sum").unwrap();
+    ///
+    /// let results = sum.call_batched(&[
+    ///     &[Value::I32(1), Value::I32(2)],
+    ///     &[Value::I32(3), Value::I32(4)],
+    /// ]).unwrap();
+    /// assert_eq!(results[0].to_vec(), vec![Value::I32(3)]);
+    /// assert_eq!(results[1].to_vec(), vec![Value::I32(7)]);
+    /// ```
+    pub fn call_batched(&self, batch: &[&[Val]]) -> Result<Vec<Box<[Val]>>, RuntimeError> {
+        if let Some(trampoline) = self.exported.vm_function.call_trampoline {
+            return self.call_wasm_batched(trampoline, batch);
+        }
+
+        // Host functions are called directly, so there's no boundary to
+        // amortize.
+        batch.iter().map(|params| self.call(params)).collect()
+    }
+
+    /// Returns the same function, registered in `store`.
+    pub(crate) fn with_store(&self, store: &Store) -> Self {
+        let mut adopted = self.clone();
+        adopted.store = store.clone();
+        adopted
+    }
+
     pub(crate) fn from_vm_export(store: &Store, wasmer_export: ExportFunction) -> Self {
         Self {
             store: store.clone(),
@@ -552,11 +670,24 @@ impl Function {
     pub(crate) fn vm_funcref(&self) -> VMFuncRef {
         let engine = self.store.engine();
         let vmsignature = engine.register_signature(&self.exported.vm_function.signature);
-        engine.register_function_metadata(VMCallerCheckedAnyfunc {
-            func_ptr: self.exported.vm_function.address,
-            type_index: vmsignature,
-            vmctx: self.exported.vm_function.vmctx,
-        })
+        // The registry frees the data once the instance or the host env
+        // of the function is gone.
+        let owner = if let Some(instance_ref) = &self.exported.vm_function.instance_ref {
+            FuncDataOwner::Instance(instance_ref.clone().into())
+        } else if let Some(metadata) = &self.exported.metadata {
+            let metadata: Arc<dyn std::any::Any + Send + Sync> = metadata.clone();
+            FuncDataOwner::Host(Arc::downgrade(&metadata))
+        } else {
+            FuncDataOwner::Static
+        };
+        engine.register_function_metadata(
+            VMCallerCheckedAnyfunc {
+                func_ptr: self.exported.vm_function.address,
+                type_index: vmsignature,
+                vmctx: self.exported.vm_function.vmctx,
+            },
+            owner,
+        )
     }
 
     /// Transform this WebAssembly function into a function with the
@@ -56,6 +56,45 @@ impl Global {
         Self::from_value(store, val, Mutability::Var).unwrap()
     }
 
+    /// Create a mutable `Global` with the initial value [`Val`], and
+    /// whose changes made by the Wasm code are passed to `observer`.
+    ///
+    /// The Wasm code writes the globals directly, so its changes are
+    /// detected, and `observer` invoked, when it calls a host function
+    /// or returns to the host: several writes in between are observed
+    /// once, with the last value. The writes of the host, with
+    /// [`Global::set`], aren't observed. When no global is observed,
+    /// the checks cost nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Global, Store, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let g = Global::new_mut_observed(&store, Value::I32(1), |value| {
+    ///     println!("the guest has set the global to {:?}", value);
+    /// });
+    ///
+    /// assert_eq!(g.get(), Value::I32(1));
+    /// ```
+    pub fn new_mut_observed(
+        store: &Store,
+        val: Val,
+        observer: impl Fn(Val) + Send + Sync + 'static,
+    ) -> Self {
+        let global = Self::new_mut(store, val);
+        let observer_store = store.clone();
+        global
+            .vm_global
+            .from
+            .observe(Arc::new(move |global: &RuntimeGlobal| {
+                observer(global.get(&observer_store))
+            }));
+
+        global
+    }
+
     /// Create a `Global` with the initial value [`Val`] and the provided [`Mutability`].
     fn from_value(store: &Store, val: Val, mutability: Mutability) -> Result<Self, RuntimeError> {
         if !val.comes_from_same_store(store) {
@@ -71,10 +110,13 @@ impl Global {
                 .map_err(|e| RuntimeError::new(format!("create global for {:?}: {}", val, e)))?;
         };
 
+        let global = Arc::new(global);
+        store.engine().register_global_root(&global);
+
         Ok(Self {
             store: store.clone(),
             vm_global: VMGlobal {
-                from: Arc::new(global),
+                from: global,
                 instance_ref: None,
             },
         })
@@ -184,6 +226,13 @@ impl Global {
         Ok(())
     }
 
+    /// Returns the same global, registered in `store`.
+    pub(crate) fn with_store(&self, store: &Store) -> Self {
+        let mut adopted = self.clone();
+        adopted.store = store.clone();
+        adopted
+    }
+
     pub(crate) fn from_vm_export(store: &Store, vm_global: VMGlobal) -> Self {
         Self {
             store: store.clone(),
@@ -3,14 +3,17 @@ use crate::sys::externals::Extern;
 use crate::sys::store::Store;
 use crate::sys::MemoryType;
 use crate::MemoryAccessError;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::slice;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use wasmer_compiler::Export;
-use wasmer_types::Pages;
-use wasmer_vm::{MemoryError, VMMemory};
+use wasmer_types::{AtomicRmwOp, Pages, WaitResult, WASM_PAGE_SIZE};
+use wasmer_vm::{FileMapping, InstanceRef, MemoryError, MemoryStats, MemoryStyle, VMMemory};
 
 /// A WebAssembly `memory` instance.
 ///
@@ -62,6 +65,124 @@ impl Memory {
         })
     }
 
+    /// Creates a new host `Memory` shared between threads, as the threads
+    /// proposal of WebAssembly defines it.
+    ///
+    /// A shared memory must have a maximum, and is never moved when it
+    /// grows, so the instances running on other threads can keep
+    /// accessing it. The maximum must fit in the static memory bound of
+    /// the tunables of `store`. See [`crate::threads`] to run an instance
+    /// on another thread with it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, Pages, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new_shared(&store, 1, 16).unwrap();
+    ///
+    /// assert!(m.ty().shared);
+    /// assert_eq!(m.ty().maximum, Some(Pages(16)));
+    /// ```
+    pub fn new_shared<IntoPages>(
+        store: &Store,
+        minimum: IntoPages,
+        maximum: IntoPages,
+    ) -> Result<Self, MemoryError>
+    where
+        IntoPages: Into<Pages>,
+    {
+        let ty = MemoryType::new(minimum, Some(maximum), true);
+        if let MemoryStyle::Dynamic { .. } = store.tunables().memory_style(&ty) {
+            return Err(MemoryError::InvalidMemory {
+                reason: "the maximum of a shared memory must fit in the static memory bound"
+                    .to_string(),
+            });
+        }
+        Self::new(store, ty)
+    }
+
+    /// Creates a new host `Memory` mapping `file`, so its contents are
+    /// readable by the guests without being copied into the memory.
+    ///
+    /// The memory is at least as large as the file, rounding up to a
+    /// whole page, and as the minimum of `ty`: the guests see zeros past
+    /// the end of the file. With [`FileMapping::CopyOnWrite`], the writes
+    /// to the memory stay private to it; with [`FileMapping::Shared`],
+    /// they go to the file, and [`Memory::flush`] waits for them to reach
+    /// it.
+    ///
+    /// The memory can't grow when that requires moving it while it
+    /// shares the file, e.g. beyond the bound of a dynamic memory. The
+    /// file must not shrink while the memory maps it.
+    ///
+    /// The memory is created by the tunables of the store, like the
+    /// other host memories. It fails if the memories they create can't
+    /// map files.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # use wasmer::{FileMapping, Memory, MemoryType, Pages, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let mut file = tempfile::tempfile().unwrap();
+    /// file.write_all(b"dataset").unwrap();
+    ///
+    /// let ty = MemoryType::new(0, None, false);
+    /// let m = Memory::new_from_file(&store, ty, &file, FileMapping::CopyOnWrite).unwrap();
+    ///
+    /// assert_eq!(m.size(), Pages(1));
+    /// let mut bytes = [0; 8];
+    /// m.read(0, &mut bytes).unwrap();
+    /// assert_eq!(&bytes, b"dataset\0");
+    /// ```
+    #[cfg(unix)]
+    pub fn new_from_file(
+        store: &Store,
+        mut ty: MemoryType,
+        file: &std::fs::File,
+        mapping: FileMapping,
+    ) -> Result<Self, MemoryError> {
+        let len = file
+            .metadata()
+            .map_err(|error| MemoryError::Generic(error.to_string()))?
+            .len();
+        let pages = (len + WASM_PAGE_SIZE as u64 - 1) / WASM_PAGE_SIZE as u64;
+        if pages > Pages::max_value().0 as u64 {
+            return Err(MemoryError::Generic(format!(
+                "the file is too large to be mapped in a memory: {} bytes",
+                len
+            )));
+        }
+        ty.minimum = ty.minimum.max(Pages(pages as u32));
+        if let Some(maximum) = ty.maximum {
+            if ty.minimum > maximum {
+                return Err(MemoryError::InvalidMemory {
+                    reason: format!(
+                        "the file needs {} pages but the maximum is {} pages",
+                        ty.minimum.0, maximum.0
+                    ),
+                });
+            }
+        }
+
+        let tunables = store.tunables();
+        let style = tunables.memory_style(&ty);
+        let memory = tunables.create_host_memory(&ty, &style)?;
+        memory.map_file(file, len as usize, mapping)?;
+
+        Ok(Self {
+            store: store.clone(),
+            vm_memory: VMMemory {
+                from: memory,
+                instance_ref: None,
+            },
+        })
+    }
+
     /// Returns the [`MemoryType`] of the `Memory`.
     ///
     /// # Example
@@ -166,6 +287,39 @@ impl Memory {
         self.vm_memory.from.grow(delta.into())
     }
 
+    /// Returns the usage statistics of this memory: its current size,
+    /// the largest size it has had, and how many times it grew.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Pages, Store, Type, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, Some(3), false)).unwrap();
+    /// m.grow(2).unwrap();
+    ///
+    /// let stats = m.stats();
+    /// assert_eq!(stats.peak, Pages(3));
+    /// assert_eq!(stats.grows, 1);
+    /// ```
+    pub fn stats(&self) -> MemoryStats {
+        self.vm_memory.from.stats()
+    }
+
+    /// Waits for the writes to the file this memory shares, if any, to
+    /// reach the file. See [`Memory::new_from_file`].
+    pub fn flush(&self) -> Result<(), MemoryError> {
+        self.vm_memory.from.flush()
+    }
+
+    /// Returns the same memory, registered in `store`.
+    pub(crate) fn with_store(&self, store: &Store) -> Self {
+        let mut adopted = self.clone();
+        adopted.store = store.clone();
+        adopted
+    }
+
     pub(crate) fn from_vm_export(store: &Store, vm_memory: VMMemory) -> Self {
         Self {
             store: store.clone(),
@@ -173,6 +327,12 @@ impl Memory {
         }
     }
 
+    /// The instance exporting the memory, unless it was created by the
+    /// host or the instance is gone.
+    pub(crate) fn instance_ref(&self) -> Option<InstanceRef> {
+        InstanceRef::try_from(self.vm_memory.instance_ref.clone()?).ok()
+    }
+
     /// Returns whether or not these two memories refer to the same data.
     ///
     /// # Example
@@ -189,6 +349,52 @@ impl Memory {
         Arc::ptr_eq(&self.vm_memory.from, &other.vm_memory.from)
     }
 
+    /// Leases the `len` bytes at `offset`, so the host can access them in
+    /// place instead of copying them out of the memory, e.g. to process an
+    /// image written by the guest.
+    ///
+    /// The memory is pinned while the lease, or any of its clones, is
+    /// alive: it can still grow, but the grows which would move it fail,
+    /// so the leased bytes stay where they are.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write(16, b"pixels").unwrap();
+    ///
+    /// let mut lease = m.lease(16, 6).unwrap();
+    /// // Safety: no WebAssembly code runs while the bytes are borrowed.
+    /// unsafe { lease.as_mut_slice().make_ascii_uppercase() };
+    /// drop(lease);
+    ///
+    /// let mut bytes = [0; 6];
+    /// m.read(16, &mut bytes).unwrap();
+    /// assert_eq!(&bytes, b"PIXELS");
+    /// ```
+    pub fn lease(&self, offset: u64, len: u64) -> Result<MemoryLease, MemoryAccessError> {
+        let end = offset.checked_add(len).ok_or(MemoryAccessError::Overflow)?;
+        // The memory can't shrink, so the region stays in bounds.
+        if end > self.data_size() {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        let offset = usize::try_from(offset).map_err(|_| MemoryAccessError::Overflow)?;
+        let len = usize::try_from(len).map_err(|_| MemoryAccessError::Overflow)?;
+        let memory = self.vm_memory.from.clone();
+        if !memory.pin() {
+            return Err(MemoryAccessError::NotPinnable);
+        }
+        // The memory doesn't move once pinned, so the pointer stays valid.
+        Ok(MemoryLease {
+            ptr: unsafe { self.data_ptr().add(offset) },
+            len,
+            memory,
+        })
+    }
+
     /// Get access to the backing VM value for this extern. This function is for
     /// tests it should not be called by users of the Wasmer API.
     ///
@@ -276,6 +482,130 @@ impl Memory {
         }
         Ok(())
     }
+
+    /// Atomically reads the `u32` at the given offset, which must be
+    /// aligned, like `i32.atomic.load`.
+    pub fn atomic_load32(&self, offset: u64) -> Result<u32, MemoryAccessError> {
+        Ok(self.atomic32(offset)?.load(Ordering::SeqCst))
+    }
+
+    /// Atomically reads the `u64` at the given offset, which must be
+    /// aligned, like `i64.atomic.load`.
+    pub fn atomic_load64(&self, offset: u64) -> Result<u64, MemoryAccessError> {
+        Ok(self.atomic64(offset)?.load(Ordering::SeqCst))
+    }
+
+    /// Atomically writes `value` at the given offset, which must be
+    /// aligned, like `i32.atomic.store`.
+    pub fn atomic_store32(&self, offset: u64, value: u32) -> Result<(), MemoryAccessError> {
+        self.atomic32(offset)?.store(value, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Atomically writes `value` at the given offset, which must be
+    /// aligned, like `i64.atomic.store`.
+    pub fn atomic_store64(&self, offset: u64, value: u64) -> Result<(), MemoryAccessError> {
+        self.atomic64(offset)?.store(value, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Atomically applies `op` with `value` to the `u32` at the given
+    /// offset, which must be aligned, and returns its previous value,
+    /// like `i32.atomic.rmw.*`.
+    pub fn atomic_rmw32(
+        &self,
+        offset: u64,
+        op: AtomicRmwOp,
+        value: u32,
+    ) -> Result<u32, MemoryAccessError> {
+        let location = self.atomic32(offset)?;
+        Ok(match op {
+            AtomicRmwOp::Add => location.fetch_add(value, Ordering::SeqCst),
+            AtomicRmwOp::Sub => location.fetch_sub(value, Ordering::SeqCst),
+            AtomicRmwOp::And => location.fetch_and(value, Ordering::SeqCst),
+            AtomicRmwOp::Or => location.fetch_or(value, Ordering::SeqCst),
+            AtomicRmwOp::Xor => location.fetch_xor(value, Ordering::SeqCst),
+            AtomicRmwOp::Xchg => location.swap(value, Ordering::SeqCst),
+        })
+    }
+
+    /// Atomically applies `op` with `value` to the `u64` at the given
+    /// offset, which must be aligned, and returns its previous value,
+    /// like `i64.atomic.rmw.*`.
+    pub fn atomic_rmw64(
+        &self,
+        offset: u64,
+        op: AtomicRmwOp,
+        value: u64,
+    ) -> Result<u64, MemoryAccessError> {
+        let location = self.atomic64(offset)?;
+        Ok(match op {
+            AtomicRmwOp::Add => location.fetch_add(value, Ordering::SeqCst),
+            AtomicRmwOp::Sub => location.fetch_sub(value, Ordering::SeqCst),
+            AtomicRmwOp::And => location.fetch_and(value, Ordering::SeqCst),
+            AtomicRmwOp::Or => location.fetch_or(value, Ordering::SeqCst),
+            AtomicRmwOp::Xor => location.fetch_xor(value, Ordering::SeqCst),
+            AtomicRmwOp::Xchg => location.swap(value, Ordering::SeqCst),
+        })
+    }
+
+    /// Blocks the current thread until the location at the given offset
+    /// is notified, or `timeout` elapses, if it holds `expected`, like
+    /// `memory.atomic.wait32`.
+    ///
+    /// Only shared memories can be waited on. The waiters are woken up
+    /// by [`Memory::notify`]: the compilers don't support the
+    /// `memory.atomic.notify` instruction yet.
+    pub fn wait32(
+        &self,
+        offset: u64,
+        expected: u32,
+        timeout: Option<Duration>,
+    ) -> Result<WaitResult, MemoryAccessError> {
+        if !self.ty().shared {
+            return Err(MemoryAccessError::WaitNotAllowed);
+        }
+
+        let location = self.atomic32(offset)?;
+        Ok(wasmer_vm::memory_wait32(location, expected, timeout))
+    }
+
+    /// Wakes up at most `count` of the threads waiting on the location
+    /// at the given offset, which must be aligned, and returns how many
+    /// were woken up, like `memory.atomic.notify`.
+    pub fn notify(&self, offset: u64, count: u32) -> Result<u32, MemoryAccessError> {
+        let location = self.atomic32(offset)?;
+        Ok(wasmer_vm::memory_notify(location, count))
+    }
+
+    fn atomic32(&self, offset: u64) -> Result<&AtomicU32, MemoryAccessError> {
+        let location = self.atomic_location(offset, mem::size_of::<u32>() as u64)?;
+        Ok(unsafe { &*(location as *const AtomicU32) })
+    }
+
+    fn atomic64(&self, offset: u64) -> Result<&AtomicU64, MemoryAccessError> {
+        let location = self.atomic_location(offset, mem::size_of::<u64>() as u64)?;
+        Ok(unsafe { &*(location as *const AtomicU64) })
+    }
+
+    /// Checks that the `size` bytes at `offset` are in bounds and
+    /// aligned, as the atomic accesses require, and returns their
+    /// address.
+    fn atomic_location(&self, offset: u64, size: u64) -> Result<*mut u8, MemoryAccessError> {
+        let definition = self.vm_memory.from.vmmemory();
+        let def = unsafe { definition.as_ref() };
+        let end = offset
+            .checked_add(size)
+            .ok_or(MemoryAccessError::Overflow)?;
+        if end > def.current_length.try_into().unwrap() {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        if offset % size != 0 {
+            return Err(MemoryAccessError::UnalignedAtomic);
+        }
+
+        Ok(unsafe { def.base.add(offset as usize) })
+    }
 }
 
 impl Clone for Memory {
@@ -290,6 +620,81 @@ impl Clone for Memory {
     }
 }
 
+/// A region of a [`Memory`] leased to the host, see [`Memory::lease`].
+///
+/// The memory can't move while the lease, or any of its clones, is alive.
+pub struct MemoryLease {
+    memory: Arc<dyn wasmer_vm::Memory>,
+    ptr: *mut u8,
+    len: usize,
+}
+
+// The lease is just a pointer into a memory which is already shared
+// between threads.
+unsafe impl Send for MemoryLease {}
+unsafe impl Sync for MemoryLease {}
+
+impl MemoryLease {
+    /// Returns the number of leased bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the lease is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the leased bytes.
+    ///
+    /// # Safety
+    ///
+    /// No WebAssembly code may write to the leased bytes while the slice
+    /// is borrowed: the host must not call into an instance using the
+    /// memory, and no other thread may run one, if the memory is shared.
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        slice::from_raw_parts(self.ptr, self.len)
+    }
+
+    /// Returns the leased bytes, mutably.
+    ///
+    /// # Safety
+    ///
+    /// No WebAssembly code may access the leased bytes while the slice is
+    /// borrowed, and no clone of this lease may be borrowed at the same
+    /// time: see [`MemoryLease::as_slice`].
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        slice::from_raw_parts_mut(self.ptr, self.len)
+    }
+}
+
+impl Clone for MemoryLease {
+    fn clone(&self) -> Self {
+        // Pinning an already pinned memory can't fail.
+        self.memory.pin();
+        Self {
+            memory: self.memory.clone(),
+            ptr: self.ptr,
+            len: self.len,
+        }
+    }
+}
+
+impl Drop for MemoryLease {
+    fn drop(&mut self) {
+        self.memory.unpin();
+    }
+}
+
+impl fmt::Debug for MemoryLease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryLease")
+            .field("ptr", &self.ptr)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
 impl<'a> Exportable<'a> for Memory {
     fn to_export(&self) -> Export {
         self.vm_memory.clone().into()
@@ -2,18 +2,20 @@ pub(crate) mod function;
 mod global;
 mod memory;
 mod table;
+mod tag;
 
 pub use self::function::{
     FromToNativeWasmType, Function, HostFunction, WasmTypeList, WithEnv, WithoutEnv,
 };
 
 pub use self::global::Global;
-pub use self::memory::Memory;
+pub use self::memory::{Memory, MemoryLease};
 pub use self::table::Table;
+pub use self::tag::Tag;
 
 use crate::sys::exports::{ExportError, Exportable};
-use crate::sys::store::{Store, StoreObject};
-use crate::sys::ExternType;
+use crate::sys::store::{AdoptError, Store, StoreObject};
+use crate::sys::{ExternType, Type};
 use std::fmt;
 use wasmer_compiler::Export;
 
@@ -31,6 +33,8 @@ pub enum Extern {
     Table(Table),
     /// A external [`Memory`].
     Memory(Memory),
+    /// A external [`Tag`].
+    Tag(Tag),
 }
 
 impl Extern {
@@ -41,6 +45,7 @@ impl Extern {
             Self::Memory(ft) => ExternType::Memory(ft.ty()),
             Self::Table(tt) => ExternType::Table(*tt.ty()),
             Self::Global(gt) => ExternType::Global(*gt.ty()),
+            Self::Tag(tt) => ExternType::Tag(tt.ty().clone()),
         }
     }
 
@@ -51,6 +56,7 @@ impl Extern {
             Export::Memory(m) => Self::Memory(Memory::from_vm_export(store, m)),
             Export::Global(g) => Self::Global(Global::from_vm_export(store, g)),
             Export::Table(t) => Self::Table(Table::from_vm_export(store, t)),
+            Export::Tag(t) => Self::Tag(Tag::from_vm_export(store, t)),
         }
     }
 }
@@ -62,6 +68,7 @@ impl<'a> Exportable<'a> for Extern {
             Self::Global(g) => g.to_export(),
             Self::Memory(m) => m.to_export(),
             Self::Table(t) => t.to_export(),
+            Self::Tag(t) => t.to_export(),
         }
     }
 
@@ -76,10 +83,38 @@ impl<'a> Exportable<'a> for Extern {
             Self::Global(g) => g.convert_to_weak_instance_ref(),
             Self::Memory(m) => m.convert_to_weak_instance_ref(),
             Self::Table(t) => t.convert_to_weak_instance_ref(),
+            Self::Tag(t) => t.convert_to_weak_instance_ref(),
         }
     }
 }
 
+impl Extern {
+    /// Registers the same extern in `store`, see [`Store::adopt`].
+    pub(crate) fn adopt(&self, store: &Store) -> Result<Self, AdoptError> {
+        if !self.comes_from_same_store(store) {
+            match self {
+                Self::Function(f) if f.exported.vm_function.call_trampoline.is_some() => {
+                    return Err(AdoptError::CompiledFunction);
+                }
+                Self::Global(g) if g.ty().ty == Type::FuncRef => {
+                    return Err(AdoptError::FuncRef);
+                }
+                Self::Table(t) if t.ty().ty == Type::FuncRef => {
+                    return Err(AdoptError::FuncRef);
+                }
+                _ => {}
+            }
+        }
+        Ok(match self {
+            Self::Function(f) => Self::Function(f.with_store(store)),
+            Self::Global(g) => Self::Global(g.with_store(store)),
+            Self::Memory(m) => Self::Memory(m.with_store(store)),
+            Self::Table(t) => Self::Table(t.with_store(store)),
+            Self::Tag(t) => Self::Tag(t.with_store(store)),
+        })
+    }
+}
+
 impl StoreObject for Extern {
     fn comes_from_same_store(&self, store: &Store) -> bool {
         let my_store = match self {
@@ -87,6 +122,7 @@ impl StoreObject for Extern {
             Self::Global(g) => g.store(),
             Self::Memory(m) => m.store(),
             Self::Table(t) => t.store(),
+            Self::Tag(t) => t.store(),
         };
         Store::same(my_store, store)
     }
@@ -102,6 +138,7 @@ impl fmt::Debug for Extern {
                 Self::Global(_) => "Global(...)",
                 Self::Memory(_) => "Memory(...)",
                 Self::Table(_) => "Table(...)",
+                Self::Tag(_) => "Tag(...)",
             }
         )
     }
@@ -130,3 +167,9 @@ impl From<Table> for Extern {
         Self::Table(r)
     }
 }
+
+impl From<Tag> for Extern {
+    fn from(r: Tag) -> Self {
+        Self::Tag(r)
+    }
+}
@@ -44,6 +44,7 @@ impl Table {
         let table = tunables
             .create_host_table(&ty, &style)
             .map_err(RuntimeError::new)?;
+        store.engine().register_table_root(&table);
 
         let num_elements = table.size();
         for i in 0..num_elements {
@@ -133,6 +134,13 @@ impl Table {
         Ok(())
     }
 
+    /// Returns the same table, registered in `store`.
+    pub(crate) fn with_store(&self, store: &Store) -> Self {
+        let mut adopted = self.clone();
+        adopted.store = store.clone();
+        adopted
+    }
+
     pub(crate) fn from_vm_export(store: &Store, vm_table: VMTable) -> Self {
         Self {
             store: store.clone(),
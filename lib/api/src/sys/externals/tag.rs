@@ -0,0 +1,122 @@
+use crate::sys::exports::{ExportError, Exportable};
+use crate::sys::externals::Extern;
+use crate::sys::store::Store;
+use crate::sys::TagType;
+use std::fmt;
+use std::sync::Arc;
+use wasmer_compiler::Export;
+use wasmer_vm::{Exception, Tag as RuntimeTag, VMTag};
+
+/// A WebAssembly `tag` instance, of the exception handling proposal.
+///
+/// The Wasm code throws exceptions with a tag and the values of its
+/// parameters, and catches them by tag. An exception the Wasm code
+/// doesn't catch is returned as a [`RuntimeError`] that can be
+/// downcast to an [`Exception`], whose tag can be compared with
+/// [`Tag::matches`].
+///
+/// [`RuntimeError`]: crate::sys::RuntimeError
+pub struct Tag {
+    store: Store,
+    vm_tag: VMTag,
+}
+
+impl Tag {
+    /// Creates a new `Tag` with the provided [`TagType`] definition.
+    ///
+    /// The tag is only equal to itself, whatever its type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Tag, TagType, Type};
+    /// # let store = Store::default();
+    /// #
+    /// let t = Tag::new(&store, TagType::new([Type::I32]));
+    ///
+    /// assert_eq!(t.ty().params(), &[Type::I32]);
+    /// ```
+    pub fn new(store: &Store, ty: TagType) -> Self {
+        Self {
+            store: store.clone(),
+            vm_tag: VMTag {
+                from: Arc::new(RuntimeTag::new(ty)),
+                instance_ref: None,
+            },
+        }
+    }
+
+    /// Returns the [`TagType`] of the `Tag`.
+    pub fn ty(&self) -> &TagType {
+        self.vm_tag.ty()
+    }
+
+    /// Returns the [`Store`] where the `Tag` belongs.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Returns whether `exception` was thrown with this tag.
+    pub fn matches(&self, exception: &Exception) -> bool {
+        Arc::ptr_eq(&self.vm_tag.from, exception.tag())
+    }
+
+    /// Returns the same tag, registered in `store`.
+    pub(crate) fn with_store(&self, store: &Store) -> Self {
+        let mut adopted = self.clone();
+        adopted.store = store.clone();
+        adopted
+    }
+
+    pub(crate) fn from_vm_export(store: &Store, vm_tag: VMTag) -> Self {
+        Self {
+            store: store.clone(),
+            vm_tag,
+        }
+    }
+
+    /// Returns whether or not these two tags are the same tag.
+    pub fn same(&self, other: &Self) -> bool {
+        self.vm_tag.same(&other.vm_tag)
+    }
+}
+
+impl Clone for Tag {
+    fn clone(&self) -> Self {
+        let mut vm_tag = self.vm_tag.clone();
+        vm_tag.upgrade_instance_ref().unwrap();
+
+        Self {
+            store: self.store.clone(),
+            vm_tag,
+        }
+    }
+}
+
+impl fmt::Debug for Tag {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Tag")
+            .field("ty", &self.ty())
+            .finish()
+    }
+}
+
+impl<'a> Exportable<'a> for Tag {
+    fn to_export(&self) -> Export {
+        self.vm_tag.clone().into()
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Tag(tag) => Ok(tag),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+
+    fn convert_to_weak_instance_ref(&mut self) {
+        if let Some(v) = self.vm_tag.instance_ref.as_mut() {
+            *v = v.downgrade();
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::sys::{MemoryType, Pages, TableType};
+use std::fmt;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use wasmer_compiler::Tunables;
+#[cfg(unix)]
+use wasmer_vm::FileMapping;
+use wasmer_vm::{
+    Memory, MemoryError, MemoryStats, MemoryStyle, Table, TableStyle, VMMemoryDefinition,
+    VMTableDefinition,
+};
+
+/// What happens to a growth of a memory, see [`MemoryGrowHook`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryGrowDecision {
+    /// The memory grows, if it can.
+    Allow,
+    /// The memory doesn't grow: `memory.grow` returns -1, and
+    /// [`Memory::grow`](crate::Memory::grow) fails.
+    Deny,
+    /// The memory doesn't grow, and the Wasm code growing it traps with
+    /// a [`MemoryError::OutOfMemory`], which
+    /// [`Memory::grow`](crate::Memory::grow) fails with when the host
+    /// grows it.
+    Trap,
+}
+
+/// Decides whether the memories of a store may grow, e.g. to keep
+/// many instances within one memory budget, see [`GrowHookTunables`].
+///
+/// It's implemented by the closures taking the current size of the
+/// memory and the number of pages it grows by.
+pub trait MemoryGrowHook: Send + Sync + 'static {
+    /// Called before a memory of `current` pages grows by `delta`
+    /// pages, whether the Wasm code or the host grows it.
+    fn on_grow(&self, current: Pages, delta: Pages) -> MemoryGrowDecision;
+}
+
+impl<F> MemoryGrowHook for F
+where
+    F: Fn(Pages, Pages) -> MemoryGrowDecision + Send + Sync + 'static,
+{
+    fn on_grow(&self, current: Pages, delta: Pages) -> MemoryGrowDecision {
+        self(current, delta)
+    }
+}
+
+/// The decisions of the hook of [`GrowHookTunables`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryGrowStats {
+    /// The number of growths allowed.
+    pub allowed: u64,
+    /// The number of growths denied.
+    pub denied: u64,
+    /// The number of growths denied with a trap.
+    pub trapped: u64,
+    /// The number of pages of the growths denied, with or without a
+    /// trap.
+    pub denied_pages: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    allowed: AtomicU64,
+    denied: AtomicU64,
+    trapped: AtomicU64,
+    denied_pages: AtomicU64,
+}
+
+/// Tunables asking a [`MemoryGrowHook`] before any memory they create
+/// grows.
+///
+/// The growths by 0 pages, which don't allocate, aren't submitted to
+/// the hook. The styles of the memories and tables, and the memories
+/// and tables themselves, come from the wrapped tunables.
+///
+/// ```
+/// # use wasmer::{BaseTunables, Engine, GrowHookTunables, MemoryGrowDecision, Pages, Store};
+/// # use std::sync::atomic::{AtomicU32, Ordering};
+/// # use std::sync::Arc;
+/// // The pages all the memories may have together.
+/// let budget = Arc::new(AtomicU32::new(1024));
+///
+/// let engine = Store::default().engine().clone();
+/// let tunables = GrowHookTunables::new(
+///     BaseTunables::for_target(engine.target()),
+///     move |_current: Pages, delta: Pages| {
+///         let granted = budget.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+///             left.checked_sub(delta.0)
+///         });
+///         match granted {
+///             Ok(_) => MemoryGrowDecision::Allow,
+///             Err(_) => MemoryGrowDecision::Trap,
+///         }
+///     },
+/// );
+/// let store = Store::new_with_tunables(&*engine, tunables);
+/// ```
+#[derive(Clone)]
+pub struct GrowHookTunables<T: Tunables> {
+    base: T,
+    hook: Arc<dyn MemoryGrowHook>,
+    counters: Arc<Counters>,
+}
+
+impl<T: Tunables> GrowHookTunables<T> {
+    /// Wraps `base`, asking `hook` before the memories grow.
+    pub fn new(base: T, hook: impl MemoryGrowHook) -> Self {
+        Self {
+            base,
+            hook: Arc::new(hook),
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    /// The decisions of the hook so far, for all the memories.
+    pub fn stats(&self) -> MemoryGrowStats {
+        let counters = &self.counters;
+        MemoryGrowStats {
+            allowed: counters.allowed.load(Ordering::Relaxed),
+            denied: counters.denied.load(Ordering::Relaxed),
+            trapped: counters.trapped.load(Ordering::Relaxed),
+            denied_pages: counters.denied_pages.load(Ordering::Relaxed),
+        }
+    }
+
+    fn hooked(&self, memory: Arc<dyn Memory>) -> Arc<dyn Memory> {
+        Arc::new(HookedMemory {
+            memory,
+            hook: self.hook.clone(),
+            counters: self.counters.clone(),
+        })
+    }
+}
+
+impl<T: Tunables> Tunables for GrowHookTunables<T> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(self.hooked(self.base.create_host_memory(ty, style)?))
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(self.hooked(
+            self.base
+                .create_vm_memory(ty, style, vm_definition_location)?,
+        ))
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_vm_table(ty, style, vm_definition_location)
+    }
+}
+
+/// A memory of [`GrowHookTunables`], submitting its growths to the hook.
+struct HookedMemory {
+    memory: Arc<dyn Memory>,
+    hook: Arc<dyn MemoryGrowHook>,
+    counters: Arc<Counters>,
+}
+
+impl fmt::Debug for HookedMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HookedMemory")
+            .field("memory", &self.memory)
+            .finish()
+    }
+}
+
+impl Memory for HookedMemory {
+    fn ty(&self) -> MemoryType {
+        self.memory.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.memory.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.memory.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        if delta.0 == 0 {
+            return self.memory.grow(delta);
+        }
+
+        let current = self.memory.size();
+        let counters = &self.counters;
+        match self.hook.on_grow(current, delta) {
+            MemoryGrowDecision::Allow => {
+                counters.allowed.fetch_add(1, Ordering::Relaxed);
+                self.memory.grow(delta)
+            }
+            MemoryGrowDecision::Deny => {
+                counters.denied.fetch_add(1, Ordering::Relaxed);
+                counters
+                    .denied_pages
+                    .fetch_add(delta.0 as u64, Ordering::Relaxed);
+                Err(MemoryError::CouldNotGrow {
+                    current,
+                    attempted_delta: delta,
+                })
+            }
+            MemoryGrowDecision::Trap => {
+                counters.trapped.fetch_add(1, Ordering::Relaxed);
+                counters
+                    .denied_pages
+                    .fetch_add(delta.0 as u64, Ordering::Relaxed);
+                Err(MemoryError::OutOfMemory {
+                    current,
+                    attempted_delta: delta,
+                })
+            }
+        }
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.memory.vmmemory()
+    }
+
+    fn is_mmap_backed(&self) -> bool {
+        self.memory.is_mmap_backed()
+    }
+
+    #[cfg(unix)]
+    fn map_file(
+        &self,
+        file: &std::fs::File,
+        len: usize,
+        mapping: FileMapping,
+    ) -> Result<(), MemoryError> {
+        self.memory.map_file(file, len, mapping)
+    }
+
+    fn flush(&self) -> Result<(), MemoryError> {
+        self.memory.flush()
+    }
+
+    fn pin(&self) -> bool {
+        self.memory.pin()
+    }
+
+    fn unpin(&self) {
+        self.memory.unpin()
+    }
+
+    fn stats(&self) -> MemoryStats {
+        self.memory.stats()
+    }
+}
@@ -1,13 +1,16 @@
+use crate::imports_report::report_link_error;
 use crate::sys::exports::Exports;
 use crate::sys::externals::Extern;
 use crate::sys::imports::Imports;
+use crate::sys::instrument;
 use crate::sys::module::Module;
 use crate::sys::store::Store;
-use crate::sys::{HostEnvInitError, LinkError, RuntimeError};
+#[cfg(feature = "experimental-reference-types-extern-ref")]
+use crate::sys::ExternRef;
+use crate::sys::{HostEnvInitError, InstantiationError};
 use std::fmt;
 use std::sync::{Arc, Mutex};
-use thiserror::Error;
-use wasmer_vm::{InstanceHandle, VMContext};
+use wasmer_vm::{InstanceHandle, MemoryStats, VMContext};
 
 /// A WebAssembly Instance is a stateful, executable
 /// instance of a WebAssembly [`Module`].
@@ -19,7 +22,7 @@ use wasmer_vm::{InstanceHandle, VMContext};
 /// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#module-instances>
 #[derive(Clone)]
 pub struct Instance {
-    handle: Arc<Mutex<InstanceHandle>>,
+    pub(crate) handle: Arc<Mutex<InstanceHandle>>,
     module: Module,
     #[allow(dead_code)]
     imports: Vec<Extern>,
@@ -41,50 +44,17 @@ mod send_test {
     }
 }
 
-/// An error while instantiating a module.
-///
-/// This is not a common WebAssembly error, however
-/// we need to differentiate from a `LinkError` (an error
-/// that happens while linking, on instantiation), a
-/// Trap that occurs when calling the WebAssembly module
-/// start function, and an error when initializing the user's
-/// host environments.
-#[derive(Error, Debug)]
-pub enum InstantiationError {
-    /// A linking ocurred during instantiation.
-    #[error(transparent)]
-    Link(LinkError),
-
-    /// A runtime error occured while invoking the start function
-    #[error(transparent)]
-    Start(RuntimeError),
-
-    /// The module was compiled with a CPU feature that is not available on
-    /// the current host.
-    #[error("missing requires CPU features: {0:?}")]
-    CpuFeature(String),
-
-    /// Error occurred when initializing the host environment.
-    #[error(transparent)]
-    HostEnvInitialization(HostEnvInitError),
-}
-
 impl From<wasmer_compiler::InstantiationError> for InstantiationError {
     fn from(other: wasmer_compiler::InstantiationError) -> Self {
         match other {
             wasmer_compiler::InstantiationError::Link(e) => Self::Link(e),
             wasmer_compiler::InstantiationError::Start(e) => Self::Start(e),
             wasmer_compiler::InstantiationError::CpuFeature(e) => Self::CpuFeature(e),
+            wasmer_compiler::InstantiationError::Compile(e) => Self::Compile(e),
         }
     }
 }
 
-impl From<HostEnvInitError> for InstantiationError {
-    fn from(other: HostEnvInitError) -> Self {
-        Self::HostEnvInitialization(other)
-    }
-}
-
 impl Instance {
     /// Creates a new `Instance` from a WebAssembly [`Module`] and a
     /// set of imports using [`Imports`] or the [`imports`] macro helper.
@@ -115,25 +85,24 @@ impl Instance {
     ///  * Link errors that happen when plugging the imports into the instance
     ///  * Runtime errors that happen when running the module `start` function.
     pub fn new(module: &Module, imports: &Imports) -> Result<Self, InstantiationError> {
-        let store = module.store();
-        let imports = imports
-            .imports_for_module(module)
-            .map_err(InstantiationError::Link)?;
-        let handle = module.instantiate(&imports)?;
-        let exports = module
-            .exports()
-            .map(|export| {
-                let name = export.name().to_string();
-                let export = handle.lookup(&name).expect("export");
-                let extern_ = Extern::from_vm_export(store, export.into());
-                (name, extern_)
+        let externs = instrument::phase("resolve_imports", || {
+            imports.imports_for_module(module).map_err(|error| {
+                InstantiationError::Link(report_link_error(module, imports, error))
             })
-            .collect::<Exports>();
+        })?;
+        // The types of the imports are checked while linking them.
+        let handle = module.instantiate(&externs).map_err(|error| match error {
+            InstantiationError::Link(error) => {
+                InstantiationError::Link(report_link_error(module, imports, error))
+            }
+            error => error,
+        })?;
+        let exports = Self::lookup_exports(module, &handle);
 
         let instance = Self {
             handle: Arc::new(Mutex::new(handle)),
             module: module.clone(),
-            imports,
+            imports: externs,
             exports,
         };
 
@@ -145,13 +114,13 @@ impl Instance {
         // This usage is correct because we pass a valid pointer to `instance` and the
         // correct error type returned by `WasmerEnv::init_with_instance` as a generic
         // parameter.
-        unsafe {
+        instrument::phase("init_host_envs", || unsafe {
             instance
                 .handle
                 .lock()
                 .unwrap()
-                .initialize_host_envs::<HostEnvInitError>(&instance as *const _ as *const _)?;
-        }
+                .initialize_host_envs::<HostEnvInitError>(&instance as *const _ as *const _)
+        })?;
 
         Ok(instance)
     }
@@ -167,18 +136,9 @@ impl Instance {
     ///  * Link errors that happen when plugging the imports into the instance
     ///  * Runtime errors that happen when running the module `start` function.
     pub fn new_by_index(module: &Module, externs: &[Extern]) -> Result<Self, InstantiationError> {
-        let store = module.store();
         let imports = externs.to_vec();
         let handle = module.instantiate(&imports)?;
-        let exports = module
-            .exports()
-            .map(|export| {
-                let name = export.name().to_string();
-                let export = handle.lookup(&name).expect("export");
-                let extern_ = Extern::from_vm_export(store, export.into());
-                (name, extern_)
-            })
-            .collect::<Exports>();
+        let exports = Self::lookup_exports(module, &handle);
 
         let instance = Self {
             handle: Arc::new(Mutex::new(handle)),
@@ -195,17 +155,110 @@ impl Instance {
         // This usage is correct because we pass a valid pointer to `instance` and the
         // correct error type returned by `WasmerEnv::init_with_instance` as a generic
         // parameter.
-        unsafe {
+        instrument::phase("init_host_envs", || unsafe {
             instance
                 .handle
                 .lock()
                 .unwrap()
-                .initialize_host_envs::<HostEnvInitError>(&instance as *const _ as *const _)?;
-        }
+                .initialize_host_envs::<HostEnvInitError>(&instance as *const _ as *const _)
+        })?;
 
         Ok(instance)
     }
 
+    fn lookup_exports(module: &Module, handle: &InstanceHandle) -> Exports {
+        module
+            .exports()
+            .map(|export| {
+                let name = export.name().to_string();
+                let export = handle.lookup(&name).expect("export");
+                let extern_ = Extern::from_vm_export(module.store(), export.into());
+                (name, extern_)
+            })
+            .collect::<Exports>()
+    }
+
+    /// Registers `callback`, run when the instance is torn down: when
+    /// it's [closed](Instance::close), or when the last of its clones
+    /// and of the externs exported by it is dropped.
+    ///
+    /// The callbacks run in the order they were registered, before the
+    /// memories, tables and globals of the instance are freed, e.g. to
+    /// release the host resources tied to the instance.
+    pub fn on_teardown(&self, callback: impl FnOnce() + Send + 'static) {
+        self.handle.lock().unwrap().on_teardown(Box::new(callback));
+    }
+
+    /// Tears the instance down now: runs its teardown callbacks, and
+    /// frees its functions, memories, tables and globals, and its
+    /// references to the imports, without waiting for the [`Store`]
+    /// to be dropped. This matters to the long-lived stores hosting
+    /// many short-lived instances.
+    ///
+    /// The instance is given back, and nothing is freed, while a clone
+    /// of it or an extern exported by it is still alive elsewhere, since
+    /// those keep it alive. Dropping the instance tears it down as well,
+    /// once nothing references it anymore; `close` tells whether it is.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Instance, Module, Store};
+    /// # use std::sync::atomic::{AtomicBool, Ordering};
+    /// # use std::sync::Arc;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let module = Module::new(&store, "(module (memory (export \"memory\") 1))")?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    ///
+    /// let torn_down = Arc::new(AtomicBool::new(false));
+    /// let flag = torn_down.clone();
+    /// instance.on_teardown(move || flag.store(true, Ordering::SeqCst));
+    ///
+    /// let memory = instance.exports.get_memory("memory")?.clone();
+    /// let instance = instance.close().unwrap_err();
+    /// assert!(!torn_down.load(Ordering::SeqCst));
+    ///
+    /// drop(memory);
+    /// assert!(instance.close().is_ok());
+    /// assert!(torn_down.load(Ordering::SeqCst));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn close(self) -> Result<(), Self> {
+        let Self {
+            handle,
+            module,
+            imports,
+            exports,
+        } = self;
+        let handle = match Arc::try_unwrap(handle) {
+            Ok(handle) => handle.into_inner().unwrap(),
+            Err(handle) => {
+                return Err(Self {
+                    handle,
+                    module,
+                    imports,
+                    exports,
+                })
+            }
+        };
+
+        // The exports hold references to the instance as well.
+        drop(exports);
+        if !handle.is_unique() {
+            let exports = Self::lookup_exports(&module, &handle);
+            return Err(Self {
+                handle: Arc::new(Mutex::new(handle)),
+                module,
+                imports,
+                exports,
+            });
+        }
+
+        drop(handle);
+        drop(imports);
+        Ok(())
+    }
+
     /// Gets the [`Module`] associated with this instance.
     pub fn module(&self) -> &Module {
         &self.module
@@ -216,6 +269,31 @@ impl Instance {
         self.module.store()
     }
 
+    /// Returns the usage statistics of the memories of this instance, by
+    /// memory index, imported memories first, so that hosts can bill and
+    /// monitor the memory of the guest.
+    ///
+    /// The statistics of an imported memory include its grows by the other
+    /// instances importing it.
+    pub fn memory_stats(&self) -> Vec<MemoryStats> {
+        self.handle.lock().unwrap().memory_stats()
+    }
+
+    /// Returns the non-null [`ExternRef`]s held by the tables and the
+    /// globals defined by this instance, for hosts tracking which of
+    /// their objects are still referenced from WebAssembly.
+    ///
+    /// The references held by imported tables and globals are returned by
+    /// the instance defining them, or held by the host. The references
+    /// only held on the stack of a running function aren't returned: the
+    /// compilers don't emit stack maps. Combined with
+    /// [`ExternRef::new_with_drop_callback`], which tells when a
+    /// reference is gone, this is enough outside of calls.
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    pub fn externref_roots(&self) -> Vec<ExternRef> {
+        self.handle.lock().unwrap().externref_roots()
+    }
+
     #[doc(hidden)]
     pub fn vmctx_ptr(&self) -> *mut VMContext {
         self.handle.lock().unwrap().vmctx_ptr()
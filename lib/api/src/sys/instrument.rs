@@ -0,0 +1,28 @@
+//! Instrumentation of the phases of compiling and instantiating the
+//! modules, to find where the cold start time goes.
+//!
+//! With the `tracing` feature, each phase runs in a `wasmer_phase` span,
+//! and emits an event with its duration when it ends. Without it, the
+//! instrumentation is compiled out.
+
+/// Runs `f` as `phase` of compiling or instantiating a module.
+#[cfg(feature = "tracing")]
+pub(crate) fn phase<T>(phase: &'static str, f: impl FnOnce() -> T) -> T {
+    let span = tracing::debug_span!("wasmer_phase", phase);
+    let _entered = span.enter();
+    let start = std::time::Instant::now();
+    let result = f();
+    tracing::debug!(
+        phase,
+        duration_us = start.elapsed().as_micros() as u64,
+        "phase finished"
+    );
+    result
+}
+
+/// Runs `f` as `phase` of compiling or instantiating a module.
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn phase<T>(_phase: &'static str, f: impl FnOnce() -> T) -> T {
+    f()
+}
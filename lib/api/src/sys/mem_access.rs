@@ -25,6 +25,16 @@ pub enum MemoryAccessError {
     /// String is not valid UTF-8.
     #[error("string is not valid utf-8")]
     NonUtf8String,
+    /// Atomic access to an unaligned address.
+    #[error("unaligned atomic access")]
+    UnalignedAtomic,
+    /// Wait on a memory which isn't shared, or on a thread which can't
+    /// block.
+    #[error("waiting isn't allowed on this memory or thread")]
+    WaitNotAllowed,
+    /// Leasing a memory which can't be pinned.
+    #[error("the memory can't be pinned")]
+    NotPinnable,
 }
 
 impl From<MemoryAccessError> for RuntimeError {
@@ -1,13 +1,20 @@
 mod env;
 mod exports;
 mod externals;
+mod grow_hook;
 mod imports;
 mod instance;
+mod instrument;
 mod mem_access;
 mod module;
 mod native;
+mod pooling;
+mod profiler;
 mod ptr;
+#[cfg(feature = "artifact-signing")]
+mod signing;
 mod store;
+pub mod threads;
 mod tunables;
 mod types;
 
@@ -26,45 +33,62 @@ pub mod internals {
     pub use crate::sys::externals::{WithEnv, WithoutEnv};
 }
 
-pub use crate::sys::env::{HostEnvInitError, LazyInit, WasmerEnv};
+pub use crate::errors::{InstantiationError, RuntimeErrorKind, TrapCode};
+pub use crate::sys::env::{HostEnvInitError, HostState, LazyInit, WasmerEnv};
 pub use crate::sys::exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use crate::sys::externals::{
-    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
+    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, MemoryLease, Table, Tag,
+    WasmTypeList,
+};
+pub use crate::sys::grow_hook::{
+    GrowHookTunables, MemoryGrowDecision, MemoryGrowHook, MemoryGrowStats,
 };
 pub use crate::sys::imports::Imports;
-pub use crate::sys::instance::{Instance, InstantiationError};
+pub use crate::sys::instance::Instance;
 pub use crate::sys::mem_access::{MemoryAccessError, WasmRef, WasmSlice, WasmSliceIter};
-pub use crate::sys::module::Module;
+pub use crate::sys::module::{Module, PreinitializeError};
 pub use crate::sys::native::TypedFunction;
+pub use crate::sys::pooling::{InstancePool, PoolingTunables};
+pub use crate::sys::profiler::{Profile, ProfileFrame, ProfileStack, Profiler, ProfilerError};
 
+#[doc(hidden)]
+pub use crate::sys::ptr::__wasm_ptr_field_of;
 pub use crate::sys::ptr::{Memory32, Memory64, MemorySize, WasmPtr, WasmPtr64};
-pub use crate::sys::store::{Store, StoreObject};
-pub use crate::sys::tunables::BaseTunables;
+#[cfg(feature = "artifact-signing")]
+pub use crate::sys::signing::{
+    ArtifactKeyProvider, ArtifactKeys, Keypair, PublicKey, SecretKey, Signature,
+};
+pub use crate::sys::store::{AdoptError, Store, StoreObject};
+pub use crate::sys::tunables::{BaseTunables, OverrideTunables};
 pub use crate::sys::types::{
     ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
-    TableType, Val, ValType,
+    TableType, TagType, Val, ValType,
 };
 pub use crate::sys::types::{Val as Value, ValType as Type};
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
-#[cfg(feature = "compiler")]
+#[cfg(feature = "debug-symbols")]
+pub use wasmer_compiler::SourceLocation;
 pub use wasmer_compiler::{
-    wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareReaderState, ModuleMiddleware,
+    lookup_jit_symbol, CpuFeature, Engine, Export, Features, FrameInfo, JitSymbol, LinkError,
+    RuntimeError, Target, Tunables,
 };
+#[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
-    CpuFeature, Engine, Export, Features, FrameInfo, LinkError, RuntimeError, Target, Tunables,
+    wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareReaderState, ModuleMiddleware,
 };
 pub use wasmer_derive::ValueType;
 pub use wasmer_types::is_wasm;
 #[cfg(feature = "experimental-reference-types-extern-ref")]
 pub use wasmer_types::ExternRef;
 pub use wasmer_types::{
-    Bytes, CompileError, DeserializeError, ExportIndex, GlobalInit, LocalFunctionIndex,
-    MiddlewareError, Pages, ParseCpuFeatureError, SerializeError, ValueType, WasmError, WasmResult,
+    ArtifactVersion, AtomicRmwOp, Bytes, CompileError, DeserializeError, ExportIndex, GlobalInit,
+    ImportError, ImportsReport, LinkIssue, LocalFunctionIndex, MiddlewareError, Pages,
+    ParseCpuFeatureError, SerializeError, ValueType, WaitResult, WasmError, WasmResult,
     WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 
 // TODO: should those be moved into wasmer::vm as well?
-pub use wasmer_vm::{raise_user_trap, MemoryError};
+pub use wasmer_vm::{raise_user_trap, Exception, FileMapping, MemoryError, MemoryStats};
 pub mod vm {
     //! The `vm` module re-exports wasmer-vm types.
 
@@ -1,7 +1,8 @@
 use crate::sys::exports::Exportable;
+use crate::sys::instrument;
 use crate::sys::store::Store;
 use crate::sys::types::{ExportType, ImportType};
-use crate::sys::InstantiationError;
+use crate::sys::{ExportError, Imports, Instance, InstantiationError, Memory, RuntimeError};
 use std::fmt;
 use std::io;
 use std::path::Path;
@@ -11,9 +12,10 @@ use wasmer_compiler::Artifact;
 #[cfg(feature = "wat")]
 use wasmer_types::WasmError;
 use wasmer_types::{
-    CompileError, DeserializeError, ExportsIterator, ImportsIterator, ModuleInfo, SerializeError,
+    CompileError, DeserializeError, ExportsIterator, FunctionIndex, ImportsIterator, ModuleInfo,
+    SerializeError,
 };
-use wasmer_vm::InstanceHandle;
+use wasmer_vm::{InstanceHandle, InstanceSnapshot};
 
 #[derive(Error, Debug)]
 pub enum IoCompileError {
@@ -25,6 +27,22 @@ pub enum IoCompileError {
     Compile(#[from] CompileError),
 }
 
+/// An error while pre-initializing a module with
+/// [`Module::preinitialize`].
+#[derive(Error, Debug)]
+pub enum PreinitializeError {
+    /// The module couldn't be instantiated.
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+    /// The initialization function isn't exported, or has another type
+    /// than `() -> ()`.
+    #[error(transparent)]
+    Export(#[from] ExportError),
+    /// The initialization function trapped.
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
 /// A WebAssembly Module contains stateless WebAssembly
 /// code that has already been compiled and can be instantiated
 /// multiple times.
@@ -51,6 +69,7 @@ pub struct Module {
     // ownership of the code and its metadata.
     artifact: Arc<dyn Artifact>,
     store: Store,
+    snapshot: Option<Arc<InstanceSnapshot>>,
 }
 
 impl Module {
@@ -171,11 +190,13 @@ impl Module {
     /// WebAssembly features in the Store Engine to assure deterministic
     /// validation of the Module.
     pub fn validate(store: &Store, binary: &[u8]) -> Result<(), CompileError> {
-        store.engine().validate(binary)
+        instrument::phase("validate", || store.engine().validate(binary))
     }
 
     fn compile(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
-        let artifact = store.engine().compile(binary, store.tunables())?;
+        let artifact = instrument::phase("compile", || {
+            store.engine().compile(binary, store.tunables())
+        })?;
         Ok(Self::from_artifact(store, artifact))
     }
 
@@ -239,7 +260,7 @@ impl Module {
     /// # }
     /// ```
     pub unsafe fn deserialize(store: &Store, bytes: &[u8]) -> Result<Self, DeserializeError> {
-        let artifact = store.engine().deserialize(bytes)?;
+        let artifact = instrument::phase("deserialize", || store.engine().deserialize(bytes))?;
         Ok(Self::from_artifact(store, artifact))
     }
 
@@ -264,14 +285,88 @@ impl Module {
         store: &Store,
         path: impl AsRef<Path>,
     ) -> Result<Self, DeserializeError> {
-        let artifact = store.engine().deserialize_from_file(path.as_ref())?;
+        let artifact = instrument::phase("deserialize", || {
+            store.engine().deserialize_from_file(path.as_ref())
+        })?;
         Ok(Self::from_artifact(store, artifact))
     }
 
+    /// Serializes a module into a binary signed by the key of the
+    /// given [`ArtifactKeyProvider`], that can later be processed via
+    /// [`Module::deserialize_signed`].
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// # let module = Module::from_file(&store, "path/to/foo.wasm")?;
+    /// let keys = ArtifactKeys::new().with_signing_keypair(keypair);
+    /// let signed = module.serialize_signed(&keys)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ArtifactKeyProvider`]: crate::ArtifactKeyProvider
+    #[cfg(feature = "artifact-signing")]
+    pub fn serialize_signed(
+        &self,
+        keys: &dyn crate::ArtifactKeyProvider,
+    ) -> Result<Vec<u8>, SerializeError> {
+        crate::sys::signing::sign(self.serialize()?, keys)
+    }
+
+    /// Deserializes a signed Module binary into a `Module`, after
+    /// checking it was signed by a key trusted by the given
+    /// [`ArtifactKeyProvider`].
+    /// > Note: the module has to be serialized before with the
+    /// > `serialize_signed` method.
+    ///
+    /// # Safety
+    ///
+    /// Please check [`Module::deserialize`]. The signature only
+    /// guarantees that the binary was produced by a trusted party.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let keys = ArtifactKeys::new().with_trusted_key(public_key);
+    /// let module = Module::deserialize_signed(&store, signed_data, &keys)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ArtifactKeyProvider`]: crate::ArtifactKeyProvider
+    #[cfg(feature = "artifact-signing")]
+    pub unsafe fn deserialize_signed(
+        store: &Store,
+        bytes: &[u8],
+        keys: &dyn crate::ArtifactKeyProvider,
+    ) -> Result<Self, DeserializeError> {
+        let bytes = crate::sys::signing::verify(bytes, keys)?;
+        Self::deserialize(store, bytes)
+    }
+
     fn from_artifact(store: &Store, artifact: Arc<dyn Artifact>) -> Self {
         Self {
             store: store.clone(),
             artifact,
+            snapshot: None,
+        }
+    }
+
+    /// Returns the same module, registered in `store`, which must use the
+    /// same engine.
+    pub(crate) fn with_store(&self, store: &Store) -> Self {
+        debug_assert!(Store::same(&self.store, store));
+        Self {
+            store: store.clone(),
+            artifact: self.artifact.clone(),
+            snapshot: self.snapshot.clone(),
         }
     }
 
@@ -280,27 +375,134 @@ impl Module {
         imports: &[crate::Extern],
     ) -> Result<InstanceHandle, InstantiationError> {
         unsafe {
-            let instance_handle = self.artifact.instantiate(
-                self.store.tunables(),
-                &imports
-                    .iter()
-                    .map(crate::Extern::to_export)
-                    .collect::<Vec<_>>(),
-                Box::new(self.clone()),
-            )?;
+            // Allocates the memories, tables and globals, and links the
+            // imports.
+            let instance_handle = instrument::phase("allocate", || {
+                self.artifact.instantiate(
+                    self.store.tunables(),
+                    &imports
+                        .iter()
+                        .map(crate::Extern::to_export)
+                        .collect::<Vec<_>>(),
+                    Box::new(self.clone()),
+                )
+            })?;
 
             // After the instance handle is created, we need to initialize
             // the data, call the start function and so. However, if any
             // of this steps traps, we still need to keep the instance alive
             // as some of the Instance elements may have placed in other
             // instance tables.
-            self.artifact
-                .finish_instantiation(&self.store, &instance_handle)?;
+            instrument::phase("start", || match &self.snapshot {
+                Some(snapshot) => instance_handle
+                    .finish_instantiation_from_snapshot(snapshot)
+                    .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap))),
+                None => self
+                    .artifact
+                    .finish_instantiation(&self.store, &instance_handle)
+                    .map_err(InstantiationError::from),
+            })?;
 
             Ok(instance_handle)
         }
     }
 
+    /// Pre-initializes the module: instantiates it with `imports`, calls
+    /// its exported `entrypoint` function once, and returns a module whose
+    /// instances start from the state the call left the instance in,
+    /// instead of running the data initializers and the start function.
+    ///
+    /// The contents of the memories defined by the module, and the values
+    /// of its mutable numeric globals, are captured. Tables, and imported
+    /// memories and globals, aren't: the instances initialize their tables
+    /// as usual, and see the imports they are given.
+    ///
+    /// On Linux, the memories of the instances are copy-on-write mappings
+    /// of the captured contents, so instantiating doesn't copy them; on
+    /// the other platforms, the contents are copied.
+    ///
+    /// The captured state isn't part of [`Module::serialize`]: a
+    /// deserialized module starts from scratch.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (global $ready (export "ready") (mut i32) (i32.const 0))
+    ///     (func (export "init") (global.set $ready (i32.const 1))))"#;
+    /// let module = Module::new(&store, wat)?.preinitialize(&imports! {}, "init")?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// assert_eq!(instance.exports.get_global("ready")?.get(), Value::I32(1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preinitialize(
+        &self,
+        imports: &Imports,
+        entrypoint: &str,
+    ) -> Result<Self, PreinitializeError> {
+        let instance = Instance::new(self, imports)?;
+        instance
+            .exports
+            .get_native_function::<(), ()>(entrypoint)?
+            .call()?;
+        let snapshot = instance.handle.lock().unwrap().snapshot();
+
+        Ok(Self {
+            snapshot: Some(Arc::new(snapshot)),
+            ..self.clone()
+        })
+    }
+
+    /// Returns a module whose instances start from the current state of
+    /// the instance of this module exporting `memory`, like the ones of
+    /// [`Module::preinitialize`]: they start with the contents of its
+    /// memories and the values of its mutable numeric globals, such as
+    /// the stack pointer, and run neither the data initializers nor the
+    /// start function.
+    ///
+    /// The memories are copied once, into the snapshot. On Linux, the
+    /// memories of the instances then map this copy copy-on-write.
+    ///
+    /// Returns `None` if `memory` isn't exported by a live instance of
+    /// this module.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (memory (export "memory") 1)
+    ///     (global $calls (export "calls") (mut i32) (i32.const 0))
+    ///     (func (export "call") (global.set $calls (i32.add (global.get $calls) (i32.const 1)))))"#;
+    /// let module = Module::new(&store, wat)?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// instance.exports.get_function("call")?.call(&[])?;
+    ///
+    /// let memory = instance.exports.get_memory("memory")?;
+    /// let snapshot = module.snapshot_of(memory).unwrap();
+    /// let copy = Instance::new(&snapshot, &imports! {})?;
+    /// assert_eq!(copy.exports.get_global("calls")?.get(), Value::I32(1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn snapshot_of(&self, memory: &Memory) -> Option<Self> {
+        let instance = memory.instance_ref()?;
+        if !Arc::ptr_eq(instance.module(), &self.artifact.module()) {
+            return None;
+        }
+
+        Some(Self {
+            snapshot: Some(Arc::new(instance.snapshot())),
+            ..self.clone()
+        })
+    }
+
     /// Returns the name of the current module.
     ///
     /// This name is normally set in the WebAssembly bytecode by some
@@ -322,6 +524,30 @@ impl Module {
         self.artifact.module_ref().name.as_deref()
     }
 
+    /// Returns the name of the function at `index` in the function index
+    /// space of the module, from its `name` section, if it has one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = "(module (func $answer (result i32) (i32.const 42)))";
+    /// let module = Module::new(&store, wat)?;
+    /// assert_eq!(module.function_name(0), Some("answer"));
+    /// assert_eq!(module.function_name(1), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn function_name(&self, index: u32) -> Option<&str> {
+        self.artifact
+            .module_ref()
+            .function_names
+            .get(&FunctionIndex::from_u32(index))
+            .map(String::as_str)
+    }
+
     /// Sets the name of the current module.
     /// This is normally useful for stacktraces and debugging.
     ///
@@ -0,0 +1,153 @@
+use crate::sys::{Imports, Instance, InstantiationError, MemoryType, Module, TableType};
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+use wasmer_compiler::Tunables;
+use wasmer_vm::{
+    LinearMemory, Memory, MemoryError, MemoryPool, MemoryStyle, Table, TableStyle,
+    VMMemoryDefinition, VMTableDefinition,
+};
+
+/// Tunables reusing the memories of dropped instances.
+///
+/// Reserving the address space of a memory is a large part of the cost
+/// of an instantiation. With these tunables, the memories of a dropped
+/// instance are zeroed and kept in a pool, and the next instances reuse
+/// them instead of reserving new ones.
+///
+/// The styles of the memories and tables, and the tables themselves,
+/// come from the wrapped tunables.
+///
+/// ```
+/// # use wasmer::{BaseTunables, Engine, PoolingTunables, Store};
+/// let engine = Store::default().engine().clone();
+/// let tunables = PoolingTunables::new(BaseTunables::for_target(engine.target()), 128);
+/// let store = Store::new_with_tunables(&*engine, tunables);
+/// ```
+#[derive(Clone)]
+pub struct PoolingTunables<T: Tunables> {
+    base: T,
+    pool: Arc<MemoryPool>,
+}
+
+impl<T: Tunables> PoolingTunables<T> {
+    /// Wraps `base`, keeping at most `max_idle_memories` idle memories of
+    /// every size.
+    pub fn new(base: T, max_idle_memories: usize) -> Self {
+        Self {
+            base,
+            pool: Arc::new(MemoryPool::new(max_idle_memories)),
+        }
+    }
+
+    /// The number of idle memories, waiting to be reused.
+    pub fn idle_memories(&self) -> usize {
+        self.pool.idle()
+    }
+}
+
+impl<T: Tunables> Tunables for PoolingTunables<T> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(Arc::new(LinearMemory::new_pooled(ty, style, &self.pool)?))
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(Arc::new(LinearMemory::from_definition_pooled(
+            ty,
+            style,
+            vm_definition_location,
+            &self.pool,
+        )?))
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_vm_table(ty, style, vm_definition_location)
+    }
+}
+
+/// Instances of a module, instantiated ahead of time.
+///
+/// [`InstancePool::prewarm`] instantiates the module in advance, e.g.
+/// while a server is idle, and [`InstancePool::get`] hands the
+/// instances out. Every instance is fresh: it went through the
+/// instantiation, start function included, as if created by
+/// [`Instance::new`], and is never handed out twice.
+///
+/// Combined with a store using [`PoolingTunables`], the instances reuse
+/// the memories of the dropped ones, zeroed.
+pub struct InstancePool {
+    module: Module,
+    imports: Imports,
+    ready: Mutex<Vec<Instance>>,
+}
+
+impl InstancePool {
+    /// Creates an empty pool of instances of `module`, instantiated
+    /// with `imports`.
+    pub fn new(module: &Module, imports: &Imports) -> Self {
+        Self {
+            module: module.clone(),
+            imports: imports.clone(),
+            ready: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Instantiates the module until `count` instances are ready.
+    pub fn prewarm(&self, count: usize) -> Result<(), InstantiationError> {
+        while self.ready() < count {
+            let instance = Instance::new(&self.module, &self.imports)?;
+            self.ready.lock().unwrap().push(instance);
+        }
+
+        Ok(())
+    }
+
+    /// Returns a ready instance, or instantiates the module if there is
+    /// none.
+    pub fn get(&self) -> Result<Instance, InstantiationError> {
+        let ready = self.ready.lock().unwrap().pop();
+        match ready {
+            Some(instance) => Ok(instance),
+            None => Instance::new(&self.module, &self.imports),
+        }
+    }
+
+    /// The number of instances ready to be handed out.
+    pub fn ready(&self) -> usize {
+        self.ready.lock().unwrap().len()
+    }
+
+    /// The module the instances are instances of.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+}
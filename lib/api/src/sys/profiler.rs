@@ -0,0 +1,476 @@
+use crate::sys::Store;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use wasmer_compiler::lookup_jit_symbol;
+use wasmer_vm::{Samples, SamplingTimer, TrapHandler};
+
+/// The default sampling frequency, in samples per second.
+const DEFAULT_FREQUENCY: u32 = 100;
+
+/// The default capacity of the sample buffer, in frames.
+const DEFAULT_CAPACITY: usize = 1 << 18;
+
+/// The name of the frames of the host code called by the Wasm code.
+const HOST_FRAME: &str = "[host]";
+
+/// A sampling profiler of the Wasm code run in a store.
+///
+/// While the profiler runs, the process is interrupted at a fixed
+/// frequency of its CPU time, and the stack of the Wasm code the thread
+/// was running for the store is recorded. The stacks are found by
+/// following the frame pointers, which the Cranelift and Singlepass
+/// compilers keep. Only one profiler can run at a time in a process.
+///
+/// This is only supported on Unix on x86-64 and AArch64.
+///
+/// ```no_run
+/// # use wasmer::{Profiler, Store};
+/// # fn run(store: &Store) {}
+/// # fn main() -> anyhow::Result<()> {
+/// # let store = Store::default();
+/// let mut profiler = Profiler::attach(&store).with_frequency(1000);
+/// profiler.start()?;
+/// run(&store);
+/// let profile = profiler.stop();
+/// profile.write_pprof(&mut std::fs::File::create("wasm.pb")?)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Profiler {
+    store: Store,
+    frequency: u32,
+    samples: Box<Samples>,
+    running: Option<(SamplingTimer, Instant)>,
+}
+
+/// An error starting a [`Profiler`].
+#[derive(Error, Debug)]
+pub enum ProfilerError {
+    /// Another profiler of the store is running.
+    #[error("the store is being profiled already")]
+    StoreProfiled,
+
+    /// The stacks can't be sampled: another profiler is running in the
+    /// process, or the platform is not supported.
+    #[error("unable to sample the stacks: {0}")]
+    Sampling(#[from] io::Error),
+}
+
+impl Profiler {
+    /// Creates a profiler of the Wasm code run in `store`, sampling 100
+    /// times per second. It must be started with [`start`](Self::start).
+    pub fn attach(store: &Store) -> Self {
+        Self {
+            store: store.clone(),
+            frequency: DEFAULT_FREQUENCY,
+            samples: Box::new(Samples::with_capacity(DEFAULT_CAPACITY)),
+            running: None,
+        }
+    }
+
+    /// Sets the sampling frequency, in samples per second of CPU time.
+    pub fn with_frequency(mut self, frequency: u32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Sets the number of frames the samples can hold in total, between
+    /// a start and a stop. The samples past it are dropped.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.samples = Box::new(Samples::with_capacity(capacity));
+        self
+    }
+
+    /// Returns whether the profiler is running.
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Starts sampling the stacks. Does nothing if the profiler is
+    /// running.
+    pub fn start(&mut self) -> Result<(), ProfilerError> {
+        if self.running.is_some() {
+            return Ok(());
+        }
+
+        let slot = self.store.samples_slot().unwrap();
+        // The samples are boxed, and only dropped after being detached.
+        if !unsafe { slot.attach(&self.samples) } {
+            return Err(ProfilerError::StoreProfiled);
+        }
+        match SamplingTimer::start(self.frequency) {
+            Ok(timer) => {
+                self.running = Some((timer, Instant::now()));
+                Ok(())
+            }
+            Err(error) => {
+                slot.detach();
+                Err(error.into())
+            }
+        }
+    }
+
+    /// Stops sampling the stacks, and returns the profile of the samples
+    /// taken since the start. It's empty if the profiler wasn't running.
+    pub fn stop(&mut self) -> Profile {
+        let duration = match self.running.take() {
+            Some((timer, start)) => {
+                drop(timer);
+                self.store.samples_slot().unwrap().detach();
+                start.elapsed()
+            }
+            None => Duration::default(),
+        };
+
+        let dropped_samples = self.samples.dropped();
+        let mut stacks = HashMap::<Vec<ProfileFrame>, u64>::new();
+        for pcs in self.samples.take() {
+            *stacks.entry(resolve(&pcs)).or_default() += 1;
+        }
+        let mut stacks = stacks
+            .into_iter()
+            .map(|(frames, samples)| ProfileStack { frames, samples })
+            .collect::<Vec<_>>();
+        stacks.sort_by(|a, b| b.samples.cmp(&a.samples).then(a.frames.cmp(&b.frames)));
+
+        Profile {
+            frequency: self.frequency,
+            duration,
+            stacks,
+            dropped_samples,
+        }
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        if let Some((timer, _)) = self.running.take() {
+            drop(timer);
+            self.store.samples_slot().unwrap().detach();
+        }
+    }
+}
+
+/// Names the frames of the program counters `pcs` of a sample, from the
+/// innermost one.
+fn resolve(pcs: &[usize]) -> Vec<ProfileFrame> {
+    let mut frames = pcs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &pc)| {
+            // The outer program counters are return addresses, which can
+            // be right after the end of their function.
+            let pc = if i == 0 { pc } else { pc - 1 };
+            let symbol = lookup_jit_symbol(pc)?;
+            Some(ProfileFrame {
+                name: symbol.name().to_string(),
+                module_name: Some(symbol.module_name().to_string()),
+            })
+        })
+        .collect::<Vec<_>>();
+    if pcs.first().and_then(|&pc| lookup_jit_symbol(pc)).is_none() {
+        frames.insert(
+            0,
+            ProfileFrame {
+                name: HOST_FRAME.to_string(),
+                module_name: None,
+            },
+        );
+    }
+    frames
+}
+
+/// The stacks sampled by a [`Profiler`].
+#[derive(Debug, Clone)]
+pub struct Profile {
+    frequency: u32,
+    duration: Duration,
+    stacks: Vec<ProfileStack>,
+    dropped_samples: usize,
+}
+
+/// A stack sampled by a [`Profiler`], and how many times it was.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileStack {
+    frames: Vec<ProfileFrame>,
+    samples: u64,
+}
+
+/// A frame of a [`ProfileStack`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProfileFrame {
+    name: String,
+    module_name: Option<String>,
+}
+
+impl ProfileFrame {
+    /// Returns the demangled name of the function, or `[host]` for the
+    /// host functions called by the Wasm code.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the module of the function, if it's a Wasm
+    /// function.
+    pub fn module_name(&self) -> Option<&str> {
+        self.module_name.as_deref()
+    }
+}
+
+impl ProfileStack {
+    /// Returns the frames of the stack, from the innermost one.
+    pub fn frames(&self) -> &[ProfileFrame] {
+        &self.frames
+    }
+
+    /// Returns the number of samples of the stack.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+}
+
+impl Profile {
+    /// Returns the sampled stacks, the most sampled first.
+    pub fn stacks(&self) -> &[ProfileStack] {
+        &self.stacks
+    }
+
+    /// Returns the number of samples.
+    pub fn samples(&self) -> u64 {
+        self.stacks.iter().map(|stack| stack.samples).sum()
+    }
+
+    /// Returns the number of samples dropped because the sample buffer
+    /// was full.
+    pub fn dropped_samples(&self) -> usize {
+        self.dropped_samples
+    }
+
+    /// Returns the time between the start and the stop of the profiler.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The CPU time a sample stands for, in nanoseconds.
+    fn period(&self) -> u64 {
+        1_000_000_000 / u64::from(self.frequency)
+    }
+
+    /// Returns the distinct frames of the stacks, and the index of the
+    /// frames of each stack in them.
+    fn frames(&self) -> (Vec<&ProfileFrame>, Vec<Vec<usize>>) {
+        let mut frames = vec![];
+        let mut indices = HashMap::new();
+        let stacks = self
+            .stacks
+            .iter()
+            .map(|stack| {
+                stack
+                    .frames
+                    .iter()
+                    .map(|frame| {
+                        *indices.entry(frame).or_insert_with(|| {
+                            frames.push(frame);
+                            frames.len() - 1
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        (frames, stacks)
+    }
+
+    /// Writes the profile in the protobuf format of pprof, uncompressed.
+    ///
+    /// See <https://github.com/google/pprof/blob/main/proto/profile.proto>.
+    pub fn write_pprof(&self, w: &mut impl Write) -> io::Result<()> {
+        let (frames, stacks) = self.frames();
+        let mut strings = StringTable::default();
+        let mut profile = Protobuf::default();
+
+        let value_type = |strings: &mut StringTable, ty: &str, unit: &str| {
+            let mut value_type = Protobuf::default();
+            value_type.int(1, strings.index(ty));
+            value_type.int(2, strings.index(unit));
+            value_type
+        };
+        let samples = value_type(&mut strings, "samples", "count");
+        profile.message(1, &samples);
+        let cpu = value_type(&mut strings, "cpu", "nanoseconds");
+        profile.message(1, &cpu);
+
+        for (stack, indices) in self.stacks.iter().zip(&stacks) {
+            let mut sample = Protobuf::default();
+            sample.packed(1, indices.iter().map(|&i| i as u64 + 1));
+            sample.packed(2, vec![stack.samples, stack.samples * self.period()]);
+            profile.message(2, &sample);
+        }
+
+        // A location and a function per frame, with the same ids.
+        for (i, frame) in frames.iter().enumerate() {
+            let id = i as u64 + 1;
+            let mut line = Protobuf::default();
+            line.int(1, id);
+            let mut location = Protobuf::default();
+            location.int(1, id);
+            location.message(4, &line);
+            profile.message(4, &location);
+
+            let mut function = Protobuf::default();
+            function.int(1, id);
+            function.int(2, strings.index(&frame.name));
+            function.int(3, strings.index(&frame.name));
+            function.int(5, strings.index(frame.module_name().unwrap_or("")));
+            profile.message(5, &function);
+        }
+
+        profile.int(10, self.duration.as_nanos() as u64);
+        profile.message(11, &cpu);
+        profile.int(12, self.period());
+        // The string table comes last, since the messages above add to it.
+        for string in &strings.strings {
+            profile.bytes(6, string.as_bytes());
+        }
+
+        w.write_all(&profile.buffer)
+    }
+
+    /// Writes the profile in the JSON format of speedscope.
+    ///
+    /// See <https://www.speedscope.app/file-format-schema.json>.
+    pub fn write_speedscope(&self, w: &mut impl Write) -> io::Result<()> {
+        let (frames, stacks) = self.frames();
+        let frames = frames
+            .iter()
+            .map(|frame| match frame.module_name() {
+                Some(module_name) => format!(
+                    r#"{{"name":{},"file":{}}}"#,
+                    json_string(&frame.name),
+                    json_string(module_name)
+                ),
+                None => format!(r#"{{"name":{}}}"#, json_string(&frame.name)),
+            })
+            .collect::<Vec<_>>();
+        // Speedscope wants the frames from the outermost one.
+        let samples = stacks
+            .iter()
+            .map(|indices| {
+                let indices = indices
+                    .iter()
+                    .rev()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>();
+                format!("[{}]", indices.join(","))
+            })
+            .collect::<Vec<_>>();
+        let weights = self
+            .stacks
+            .iter()
+            .map(|stack| (stack.samples * self.period()).to_string())
+            .collect::<Vec<_>>();
+        let total = self.samples() * self.period();
+
+        write!(
+            w,
+            concat!(
+                r#"{{"$schema":"https://www.speedscope.app/file-format-schema.json","#,
+                r#""exporter":"wasmer","name":"wasm","shared":{{"frames":[{}]}},"#,
+                r#""profiles":[{{"type":"sampled","name":"wasm","unit":"nanoseconds","#,
+                r#""startValue":0,"endValue":{},"samples":[{}],"weights":[{}]}}]}}"#
+            ),
+            frames.join(","),
+            total,
+            samples.join(","),
+            weights.join(",")
+        )
+    }
+}
+
+/// The string table of a pprof profile.
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u64>,
+}
+
+impl Default for StringTable {
+    fn default() -> Self {
+        // The first string must be empty.
+        Self {
+            strings: vec![String::new()],
+            indices: vec![(String::new(), 0)].into_iter().collect(),
+        }
+    }
+}
+
+impl StringTable {
+    fn index(&mut self, string: &str) -> u64 {
+        if let Some(&index) = self.indices.get(string) {
+            return index;
+        }
+        let index = self.strings.len() as u64;
+        self.strings.push(string.to_string());
+        self.indices.insert(string.to_string(), index);
+        index
+    }
+}
+
+/// A protobuf message being encoded.
+#[derive(Default)]
+struct Protobuf {
+    buffer: Vec<u8>,
+}
+
+impl Protobuf {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buffer.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    fn int(&mut self, field: u32, value: u64) {
+        self.key(field, 0);
+        self.varint(value);
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.key(field, 2);
+        self.varint(bytes.len() as u64);
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn message(&mut self, field: u32, message: &Self) {
+        self.bytes(field, &message.buffer);
+    }
+
+    fn packed(&mut self, field: u32, values: impl IntoIterator<Item = u64>) {
+        let mut packed = Self::default();
+        for value in values {
+            packed.varint(value);
+        }
+        self.bytes(field, &packed.buffer);
+    }
+}
+
+/// Quotes `string` as a JSON string.
+fn json_string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
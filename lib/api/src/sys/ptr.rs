@@ -133,6 +133,96 @@ impl<T, M: MemorySize> WasmPtr<T, M> {
         let address = M::Offset::try_from(address).map_err(|_| MemoryAccessError::Overflow)?;
         Ok(Self::new(address))
     }
+
+    /// Returns a pointer to the element `index` of the array starting at
+    /// this pointer, like [`WasmPtr::add_offset`], in units of `T`.
+    ///
+    /// This method returns an error if an address overflow occurs. The
+    /// element is checked to be within the memory when it's accessed.
+    #[inline]
+    pub fn index(self, index: u64) -> Result<Self, MemoryAccessError> {
+        let index = M::Offset::try_from(index).map_err(|_| MemoryAccessError::Overflow)?;
+        self.add_offset(index)
+    }
+
+    /// Returns a pointer to the field of `T` at `offset` bytes, of type `U`.
+    /// [`wasm_ptr_field!`](crate::wasm_ptr_field) computes the offset and
+    /// the type of the field from its name.
+    ///
+    /// This method returns an error if an address overflow occurs.
+    ///
+    /// # Safety
+    ///
+    /// `offset` must be the offset of a field of type `U` in `T`.
+    #[inline]
+    pub unsafe fn field<U>(self, offset: u64) -> Result<WasmPtr<U, M>, MemoryAccessError> {
+        let address = self
+            .offset
+            .into()
+            .checked_add(offset)
+            .ok_or(MemoryAccessError::Overflow)?;
+        let address = M::Offset::try_from(address).map_err(|_| MemoryAccessError::Overflow)?;
+        Ok(WasmPtr::new(address))
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn __uninit_pointee(&self) -> mem::MaybeUninit<T> {
+        mem::MaybeUninit::uninit()
+    }
+}
+
+/// Returns a pointer to a field of the struct a [`WasmPtr`] points to,
+/// with the type of the field, e.g. to read a field of a struct passed
+/// by the guest without the offsets computed by hand.
+///
+/// It returns an error if an address overflow occurs.
+///
+/// ```
+/// # use wasmer::{Memory, MemoryAccessError, ValueType, WasmPtr, wasm_ptr_field};
+/// #[derive(Copy, Clone, ValueType)]
+/// #[repr(C)]
+/// struct Iovec {
+///     buf: WasmPtr<u8>,
+///     len: u32,
+/// }
+///
+/// fn iovec_len(memory: &Memory, iovs: WasmPtr<Iovec>, i: u64) -> Result<u32, MemoryAccessError> {
+///     let iov = iovs.index(i)?;
+///     let len: WasmPtr<u32> = wasm_ptr_field!(iov, len)?;
+///     len.read(memory)
+/// }
+/// ```
+#[macro_export]
+macro_rules! wasm_ptr_field {
+    ($ptr:expr, $field:tt) => {{
+        let ptr = $ptr;
+        let uninit = ptr.__uninit_pointee();
+        let base = uninit.as_ptr();
+        // SAFETY: the field is projected without reading the uninitialized
+        // struct, and its offset and type are the ones of the struct.
+        unsafe {
+            let field = ::core::ptr::addr_of!((*base).$field);
+            let offset = (field as *const u8).offset_from(base as *const u8) as u64;
+            $crate::__wasm_ptr_field_of(ptr, offset, field)
+        }
+    }};
+}
+
+/// The pointer to the field `_field`, whose type is the one of the pointer
+/// returned, at `offset`, see [`wasm_ptr_field!`](crate::wasm_ptr_field).
+///
+/// # Safety
+///
+/// `offset` must be the offset of the field in `T`.
+#[doc(hidden)]
+#[inline]
+pub unsafe fn __wasm_ptr_field_of<T, U, M: MemorySize>(
+    ptr: WasmPtr<T, M>,
+    offset: u64,
+    _field: *const U,
+) -> Result<WasmPtr<U, M>, MemoryAccessError> {
+    ptr.field(offset)
 }
 
 impl<T: ValueType, M: MemorySize> WasmPtr<T, M> {
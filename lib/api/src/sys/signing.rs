@@ -0,0 +1,118 @@
+//! Signing of the serialized modules, so the hosts loading precompiled
+//! modules can check they were produced by a trusted party.
+//!
+//! A signed artifact is the serialized module followed by a trailer
+//! holding the ed25519 public key of the signer, the signature of the
+//! serialized module and a magic number. The trailer comes last so that
+//! the serialized module keeps the alignment of the buffer.
+
+use std::convert::TryFrom;
+use wasmer_types::{DeserializeError, SerializeError};
+
+pub use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature};
+use ed25519_dalek::{Signer, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+
+/// Magic number ending a signed artifact.
+const MAGIC: &[u8; 16] = b"wasmer-signature";
+
+/// Length of the trailer appended to signed artifacts.
+const TRAILER_LEN: usize = PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH + MAGIC.len();
+
+/// Provides the keys signing the serialized modules and the keys trusted
+/// when deserializing them.
+///
+/// Implement this trait to keep the keys in a dedicated store, e.g. a
+/// hardware security module or a key distribution service. See
+/// [`ArtifactKeys`] for an implementation holding the keys in memory.
+pub trait ArtifactKeyProvider: Send + Sync {
+    /// Signs a serialized module, returning the public key matching the
+    /// signing key together with the signature.
+    fn sign(&self, artifact: &[u8]) -> Result<(PublicKey, Signature), SerializeError>;
+
+    /// Returns whether artifacts signed by the given public key can be
+    /// deserialized.
+    fn is_trusted(&self, public_key: &PublicKey) -> bool;
+}
+
+/// An [`ArtifactKeyProvider`] holding the keys in memory.
+#[derive(Default)]
+pub struct ArtifactKeys {
+    signing: Option<Keypair>,
+    trusted: Vec<PublicKey>,
+}
+
+impl ArtifactKeys {
+    /// Creates a provider without any key: it can neither sign nor
+    /// verify artifacts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signs the artifacts with the given key pair. Its public key is
+    /// trusted as well.
+    pub fn with_signing_keypair(mut self, keypair: Keypair) -> Self {
+        self.trusted.push(keypair.public);
+        self.signing = Some(keypair);
+        self
+    }
+
+    /// Trusts the artifacts signed by the given public key.
+    pub fn with_trusted_key(mut self, public_key: PublicKey) -> Self {
+        self.trusted.push(public_key);
+        self
+    }
+}
+
+impl ArtifactKeyProvider for ArtifactKeys {
+    fn sign(&self, artifact: &[u8]) -> Result<(PublicKey, Signature), SerializeError> {
+        let keypair = self.signing.as_ref().ok_or_else(|| {
+            SerializeError::Generic("no key pair to sign the artifact".to_string())
+        })?;
+        Ok((keypair.public, keypair.sign(artifact)))
+    }
+
+    fn is_trusted(&self, public_key: &PublicKey) -> bool {
+        self.trusted.contains(public_key)
+    }
+}
+
+/// Appends the signature trailer to a serialized module.
+pub(crate) fn sign(
+    mut artifact: Vec<u8>,
+    keys: &dyn ArtifactKeyProvider,
+) -> Result<Vec<u8>, SerializeError> {
+    let (public_key, signature) = keys.sign(&artifact)?;
+    artifact.reserve(TRAILER_LEN);
+    artifact.extend_from_slice(public_key.as_bytes());
+    artifact.extend_from_slice(&signature.to_bytes());
+    artifact.extend_from_slice(MAGIC);
+    Ok(artifact)
+}
+
+/// Checks the signature trailer of a signed artifact and returns the
+/// serialized module it signs.
+pub(crate) fn verify<'a>(
+    bytes: &'a [u8],
+    keys: &dyn ArtifactKeyProvider,
+) -> Result<&'a [u8], DeserializeError> {
+    if bytes.len() < TRAILER_LEN || !bytes.ends_with(MAGIC) {
+        return Err(DeserializeError::InvalidSignature(
+            "the artifact is not signed".to_string(),
+        ));
+    }
+    let (artifact, trailer) = bytes.split_at(bytes.len() - TRAILER_LEN);
+    let (public_key, signature) = trailer.split_at(PUBLIC_KEY_LENGTH);
+    let public_key = PublicKey::from_bytes(public_key)
+        .map_err(|e| DeserializeError::InvalidSignature(e.to_string()))?;
+    let signature = Signature::try_from(&signature[..SIGNATURE_LENGTH])
+        .map_err(|e| DeserializeError::InvalidSignature(e.to_string()))?;
+    if !keys.is_trusted(&public_key) {
+        return Err(DeserializeError::InvalidSignature(
+            "the artifact is signed by an untrusted key".to_string(),
+        ));
+    }
+    public_key
+        .verify_strict(artifact, &signature)
+        .map_err(|e| DeserializeError::InvalidSignature(e.to_string()))?;
+    Ok(artifact)
+}
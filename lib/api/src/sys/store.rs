@@ -1,9 +1,13 @@
 use crate::sys::tunables::BaseTunables;
+use crate::sys::Extern;
+use crate::StoreGcStats;
 use std::fmt;
 use std::sync::{Arc, RwLock};
-use wasmer_compiler::CompilerConfig;
-use wasmer_compiler::{Engine, Tunables, Universal};
-use wasmer_vm::{init_traps, TrapHandler, TrapHandlerFn};
+use thiserror::Error;
+#[cfg(feature = "compiler")]
+use wasmer_compiler::{CompilerConfig, Universal};
+use wasmer_compiler::{Engine, Tunables};
+use wasmer_vm::{init_traps, SamplesSlot, TrapHandler, TrapHandlerFn};
 
 /// The store represents all global state that can be manipulated by
 /// WebAssembly programs. It consists of the runtime representation
@@ -20,10 +24,12 @@ pub struct Store {
     engine: Arc<dyn Engine + Send + Sync>,
     tunables: Arc<dyn Tunables + Send + Sync>,
     trap_handler: Arc<RwLock<Option<Box<TrapHandlerFn>>>>,
+    samples: Arc<SamplesSlot>,
 }
 
 impl Store {
     /// Creates a new `Store` with a specific [`CompilerConfig`].
+    #[cfg(feature = "compiler")]
     pub fn new(compiler_config: Box<dyn CompilerConfig>) -> Self {
         let engine = Universal::new(compiler_config).engine();
         Self::new_with_tunables(&engine, BaseTunables::for_target(engine.target()))
@@ -56,9 +62,22 @@ impl Store {
             engine: engine.cloned(),
             tunables: Arc::new(tunables),
             trap_handler: Arc::new(RwLock::new(None)),
+            samples: Arc::new(SamplesSlot::default()),
         }
     }
 
+    /// Replaces the [`Tunables`] creating the memories, tables and
+    /// globals of this store, e.g. to allocate the memories with a custom
+    /// allocator.
+    ///
+    /// The memory styles are part of the compiled code: the modules
+    /// compiled before must only be instantiated if the new tunables
+    /// choose the same styles. The clones of this store keep their
+    /// tunables.
+    pub fn set_tunables(&mut self, tunables: Arc<dyn Tunables + Send + Sync>) {
+        self.tunables = tunables;
+    }
+
     /// Returns the [`Tunables`].
     pub fn tunables(&self) -> &dyn Tunables {
         self.tunables.as_ref()
@@ -75,6 +94,83 @@ impl Store {
     pub fn same(a: &Self, b: &Self) -> bool {
         a.engine.id() == b.engine.id()
     }
+
+    /// Creates a new store with the same engine and tunables, but its own
+    /// trap handler and profiler samples.
+    pub(crate) fn new_sibling(&self) -> Self {
+        Self {
+            engine: self.engine.clone(),
+            tunables: self.tunables.clone(),
+            trap_handler: Arc::new(RwLock::new(None)),
+            samples: Arc::new(SamplesSlot::default()),
+        }
+    }
+
+    /// Registers an extern created in another store in this one, so it
+    /// can be imported by the instances of this store.
+    ///
+    /// The adopted extern shares its state with the original one: a
+    /// memory or a global written through either of them is seen by
+    /// both. Every extern keeps the objects it needs alive, so dropping
+    /// the original store, or the instances created in it, doesn't affect
+    /// the adopted externs. This is how a plugin host gives each plugin a
+    /// store of its own, while sharing some memories or host functions.
+    ///
+    /// Memories, host functions, and the globals and tables of `externref`
+    /// can be adopted by any store. Functions defined in WebAssembly, and
+    /// the globals and tables of `funcref`, are bound to the engine which
+    /// compiled them: they can only be adopted by the stores using the
+    /// same engine.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// let plugin_store = Store::default();
+    /// let memory = Memory::new(&plugin_store, MemoryType::new(1, None, false)).unwrap();
+    ///
+    /// let host_store = Store::default();
+    /// let shared = host_store.adopt(&memory.into()).unwrap();
+    /// drop(plugin_store);
+    /// assert!(matches!(shared, wasmer::Extern::Memory(_)));
+    /// ```
+    pub fn adopt(&self, extern_: &Extern) -> Result<Extern, AdoptError> {
+        extern_.adopt(self)
+    }
+
+    /// Frees the engine's references to the functions of the instances
+    /// and the host functions dropped since they were put in a table or
+    /// a global, or passed as a `funcref`.
+    ///
+    /// Those references are otherwise kept as long as the engine, and
+    /// grow with every instance of the long-lived stores hosting many
+    /// short-lived instances. The engine is shared with the stores
+    /// created with it, so they're all collected.
+    ///
+    /// The references still held by a table or a global alive are kept.
+    pub fn gc(&self) -> StoreGcStats {
+        let collection = self.engine.collect_function_metadata();
+        StoreGcStats {
+            reclaimed_functions: collection.reclaimed,
+            retained_functions: collection.retained,
+        }
+    }
+}
+
+/// The error returned by [`Store::adopt`] when an extern can't be moved
+/// to another store.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AdoptError {
+    /// The function is defined in WebAssembly, and its code belongs to
+    /// the engine of its store.
+    #[error(
+        "a function defined in WebAssembly can only be adopted by a store with the same engine"
+    )]
+    CompiledFunction,
+    /// The global or the table holds function references, whose
+    /// signatures are registered in the engine of its store.
+    #[error("a funcref global or table can only be adopted by a store with the same engine")]
+    FuncRef,
 }
 
 impl PartialEq for Store {
@@ -91,6 +187,10 @@ unsafe impl TrapHandler for Store {
             false
         }
     }
+
+    fn samples_slot(&self) -> Option<&SamplesSlot> {
+        Some(&self.samples)
+    }
 }
 
 // This is required to be able to set the trap_handler in the
@@ -139,6 +239,15 @@ impl Default for Store {
     }
 }
 
+// A headless store has no compiler: it only loads the modules compiled
+// ahead of time, with `Module::deserialize`.
+#[cfg(all(feature = "headless", not(feature = "default-compiler")))]
+impl Default for Store {
+    fn default() -> Self {
+        Self::new_with_engine(&wasmer_compiler::UniversalEngine::headless())
+    }
+}
+
 impl fmt::Debug for Store {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Store").finish()
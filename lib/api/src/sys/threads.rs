@@ -0,0 +1,94 @@
+//! Running the instances of a module on several threads, sharing a
+//! memory, as the threads proposal of WebAssembly expects.
+//!
+//! This is what emscripten pthreads and the WASIX threads need: every
+//! thread runs its own instance of the same module, and all of them
+//! import the same shared memory, created with [`Memory::new_shared`].
+
+use crate::sys::{Imports, Instance, InstantiationError, Memory, Module, Store};
+use std::io;
+use std::thread::{self, JoinHandle};
+use thiserror::Error;
+
+/// The error returned when a thread can't be spawned.
+#[derive(Error, Debug)]
+pub enum ThreadError {
+    /// The memory given to the thread isn't shared.
+    #[error("the memory isn't shared")]
+    NotShared,
+    /// The module and the memory don't belong to stores with the same
+    /// engine.
+    #[error("the module and the memory don't belong to stores with the same engine")]
+    IncompatibleStore,
+    /// The operating system couldn't spawn the thread.
+    #[error("failed to spawn the thread: {0}")]
+    Spawn(io::Error),
+}
+
+/// Instantiates `module` on a new thread, sharing `shared_memory` with it.
+///
+/// The instance of the new thread lives in a new store, which uses the
+/// engine and the tunables of the store of `module`, so each thread has its
+/// own trap handler. `imports` creates the imports of the instance in the
+/// store of the thread, given the shared memory registered in that store,
+/// and `run` is called with the instance once it's created.
+///
+/// The returned handle joins the thread, and gives the result of `run`, or
+/// the error of the instantiation.
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::{imports, Features, Memory, Module, Store, Universal};
+/// # #[cfg(feature = "cranelift")]
+/// # {
+/// # let mut features = Features::new();
+/// # features.threads(true);
+/// # let engine = Universal::new(wasmer::Cranelift::default()).features(features).engine();
+/// # let store = Store::new_with_engine(&engine);
+/// let module = Module::new(&store, r#"(module
+///   (import "env" "memory" (memory 1 1 shared))
+///   (func (export "run")
+///     (i32.atomic.store (i32.const 0) (i32.const 42))))"#).unwrap();
+/// let memory = Memory::new_shared(&store, 1, 1).unwrap();
+///
+/// let thread = wasmer::threads::spawn_in_new_store(
+///     &module,
+///     &memory,
+///     |_store, memory| imports! { "env" => { "memory" => memory.clone() } },
+///     |instance| instance.exports.get_function("run").unwrap().call(&[]).map(|_| ()),
+/// )
+/// .unwrap();
+/// thread.join().unwrap().unwrap().unwrap();
+/// assert_eq!(memory.atomic_load32(0).unwrap(), 42);
+/// # }
+/// ```
+pub fn spawn_in_new_store<I, F, R>(
+    module: &Module,
+    shared_memory: &Memory,
+    imports: I,
+    run: F,
+) -> Result<JoinHandle<Result<R, InstantiationError>>, ThreadError>
+where
+    I: FnOnce(&Store, &Memory) -> Imports + Send + 'static,
+    F: FnOnce(&Instance) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    if !shared_memory.ty().shared {
+        return Err(ThreadError::NotShared);
+    }
+    if !Store::same(module.store(), shared_memory.store()) {
+        return Err(ThreadError::IncompatibleStore);
+    }
+
+    let store = module.store().new_sibling();
+    let module = module.with_store(&store);
+    let memory = shared_memory.with_store(&store);
+    thread::Builder::new()
+        .spawn(move || {
+            let imports = imports(&store, &memory);
+            let instance = Instance::new(&module, &imports)?;
+            Ok(run(&instance))
+        })
+        .map_err(ThreadError::Spawn)
+}
@@ -146,6 +146,129 @@ impl Tunables for BaseTunables {
     }
 }
 
+/// A function choosing the style of a memory, given the style chosen by
+/// the wrapped tunables.
+type MemoryStyleFn = dyn Fn(&MemoryType, MemoryStyle) -> MemoryStyle + Send + Sync;
+
+/// Tunables overriding the memory styles of other tunables.
+///
+/// The style of a memory decides whether it is static, reserving its
+/// whole bound up front so the compiled code can skip most bounds
+/// checks, or dynamic, reserving only what it uses, and how large the
+/// guard region after it is. These tunables let a store choose them
+/// without a whole [`Tunables`] implementation: everything else comes
+/// from the wrapped tunables.
+///
+/// The styles are part of the compiled code, so a module must be
+/// instantiated in a store choosing the same styles as the store it
+/// was compiled in.
+///
+/// ```
+/// # use wasmer::{BaseTunables, Engine, OverrideTunables, Store};
+/// let engine = Store::default().engine().clone();
+/// // Keep the memories small, at the cost of bounds checks.
+/// let tunables =
+///     OverrideTunables::new(BaseTunables::for_target(engine.target())).dynamic_memories(0x1_0000);
+/// let store = Store::new_with_tunables(&*engine, tunables);
+/// ```
+#[derive(Clone)]
+pub struct OverrideTunables<T: Tunables> {
+    base: T,
+    memory_style: Option<Arc<MemoryStyleFn>>,
+}
+
+impl<T: Tunables> OverrideTunables<T> {
+    /// Wraps `base`, without overriding anything yet.
+    pub fn new(base: T) -> Self {
+        Self {
+            base,
+            memory_style: None,
+        }
+    }
+
+    /// Chooses the style of every memory with `memory_style`, which gets
+    /// the memory type and the style `base` chose for it.
+    pub fn with_memory_style<F>(mut self, memory_style: F) -> Self
+    where
+        F: Fn(&MemoryType, MemoryStyle) -> MemoryStyle + Send + Sync + 'static,
+    {
+        self.memory_style = Some(Arc::new(memory_style));
+        self
+    }
+
+    /// Makes every memory dynamic, followed by `offset_guard_size` bytes
+    /// of guard region.
+    pub fn dynamic_memories(self, offset_guard_size: u64) -> Self {
+        self.with_memory_style(move |_, _| MemoryStyle::Dynamic { offset_guard_size })
+    }
+
+    /// Makes every memory whose maximum fits in `bound` static, reserving
+    /// `bound` followed by `offset_guard_size` bytes of guard region.
+    ///
+    /// The other memories keep the style `base` chose for them.
+    pub fn static_memories(self, bound: Pages, offset_guard_size: u64) -> Self {
+        self.with_memory_style(move |memory, style| {
+            if memory.maximum.unwrap_or_else(Pages::max_value) <= bound {
+                MemoryStyle::Static {
+                    bound,
+                    offset_guard_size,
+                }
+            } else {
+                style
+            }
+        })
+    }
+}
+
+impl<T: Tunables> Tunables for OverrideTunables<T> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        let style = self.base.memory_style(memory);
+        match &self.memory_style {
+            Some(memory_style) => memory_style(memory, style),
+            None => style,
+        }
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        self.base.create_host_memory(ty, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        self.base
+            .create_vm_memory(ty, style, vm_definition_location)
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_vm_table(ty, style, vm_definition_location)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,4 +311,37 @@ mod tests {
             s => panic!("Unexpected memory style: {:?}", s),
         }
     }
+    #[test]
+    fn override_memory_style() {
+        let base = BaseTunables {
+            static_memory_bound: Pages(2048),
+            static_memory_offset_guard_size: 128,
+            dynamic_memory_offset_guard_size: 256,
+        };
+        let small = MemoryType::new(3, Some(16), true);
+        let large = MemoryType::new(3, Some(5_000_000), true);
+
+        let tunables = OverrideTunables::new(base.clone());
+        assert_eq!(tunables.memory_style(&small), base.memory_style(&small));
+
+        let tunables = OverrideTunables::new(base.clone()).dynamic_memories(512);
+        for requested in &[&small, &large] {
+            assert_eq!(
+                tunables.memory_style(requested),
+                MemoryStyle::Dynamic {
+                    offset_guard_size: 512
+                }
+            );
+        }
+
+        let tunables = OverrideTunables::new(base.clone()).static_memories(Pages(16), 64);
+        assert_eq!(
+            tunables.memory_style(&small),
+            MemoryStyle::Static {
+                bound: Pages(16),
+                offset_guard_size: 64
+            }
+        );
+        assert_eq!(tunables.memory_style(&large), base.memory_style(&large));
+    }
 }
@@ -614,6 +614,179 @@ mod js {
         assert_eq!(add_one.call(1), Ok(2));
     }
 
+    #[wasm_bindgen_test]
+    fn test_warm_up() {
+        let store = Store::default();
+        let mut module = Module::new(
+            &store,
+            br#"
+    (module
+        (func (export "add") (param i32 i32) (result i32)
+          (i32.add (local.get 0) (local.get 1))
+        )
+    )
+    "#,
+        )
+        .unwrap();
+        module
+            .set_type_hints(ModuleTypeHints {
+                imports: vec![],
+                exports: vec![ExternType::Function(FunctionType::new(
+                    vec![Type::I32, Type::I32],
+                    vec![Type::I32],
+                ))],
+            })
+            .unwrap();
+
+        let import_object = imports! {};
+        let instance = Instance::new(&module, &import_object).unwrap();
+        instance.warm_up();
+
+        let add = instance.exports.get_function("add").unwrap();
+        let expected = vec![Val::I32(3)].into_boxed_slice();
+        assert_eq!(add.call(&[Val::I32(1), Val::I32(2)]), Ok(expected));
+
+        // The argument buffer is reused, so subsequent calls must not
+        // observe the arguments of the previous ones.
+        let add_native: TypedFunction<(i32, i32), i32> = add.native().unwrap();
+        assert_eq!(add_native.call(10, 20), Ok(30));
+        assert_eq!(add_native.call(-1, 1), Ok(0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_call_with_fewer_arguments() {
+        let store = Store::default();
+        let mut module = Module::new(
+            &store,
+            br#"
+    (module
+        (func (export "sum") (param i32 i32 i32) (result i32)
+          (i32.add (i32.add (local.get 0) (local.get 1)) (local.get 2))
+        )
+    )
+    "#,
+        )
+        .unwrap();
+        module
+            .set_type_hints(ModuleTypeHints {
+                imports: vec![],
+                exports: vec![ExternType::Function(FunctionType::new(
+                    vec![Type::I32, Type::I32, Type::I32],
+                    vec![Type::I32],
+                ))],
+            })
+            .unwrap();
+
+        let import_object = imports! {};
+        let instance = Instance::new(&module, &import_object).unwrap();
+        instance.warm_up();
+
+        let sum = instance.exports.get_function("sum").unwrap();
+        let expected = vec![Val::I32(6)].into_boxed_slice();
+        assert_eq!(
+            sum.call(&[Val::I32(1), Val::I32(2), Val::I32(3)]),
+            Ok(expected)
+        );
+
+        // A short call is rejected instead of reusing the trailing
+        // argument of the previous call.
+        let err = sum.call(&[Val::I32(1), Val::I32(2)]).unwrap_err();
+        assert!(err.message().contains("did not match signature"));
+        let expected = vec![Val::I32(3)].into_boxed_slice();
+        assert_eq!(
+            sum.call(&[Val::I32(0), Val::I32(1), Val::I32(2)]),
+            Ok(expected)
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_hot_typed_calls() {
+        let store = Store::default();
+        let mut module = Module::new(
+            &store,
+            br#"
+    (module
+        (func (export "inc") (param i32) (result i32)
+          (i32.add (local.get 0) (i32.const 1))
+        )
+        (func (export "add") (param i32 i32) (result i32)
+          (i32.add (local.get 0) (local.get 1))
+        )
+        (func (export "mad") (param i32 i32 i32) (result i32)
+          (i32.add (i32.mul (local.get 0) (local.get 1)) (local.get 2))
+        )
+    )
+    "#,
+        )
+        .unwrap();
+        module
+            .set_type_hints(ModuleTypeHints {
+                imports: vec![],
+                exports: vec![
+                    ExternType::Function(FunctionType::new(vec![Type::I32], vec![Type::I32])),
+                    ExternType::Function(FunctionType::new(
+                        vec![Type::I32, Type::I32],
+                        vec![Type::I32],
+                    )),
+                    ExternType::Function(FunctionType::new(
+                        vec![Type::I32, Type::I32, Type::I32],
+                        vec![Type::I32],
+                    )),
+                ],
+            })
+            .unwrap();
+
+        let import_object = imports! {};
+        let instance = Instance::new(&module, &import_object).unwrap();
+        instance.warm_up();
+
+        const CALLS: i32 = 100_000;
+        let inc = instance.exports.get_function("inc").unwrap();
+        let add = instance.exports.get_function("add").unwrap();
+        let mad = instance.exports.get_function("mad").unwrap();
+        let inc_native: TypedFunction<i32, i32> = inc.native().unwrap();
+        let add_native: TypedFunction<(i32, i32), i32> = add.native().unwrap();
+        let mad_native: TypedFunction<(i32, i32, i32), i32> = mad.native().unwrap();
+
+        // Every call is checked, so a fast path returning wrong results
+        // fails the benchmark.
+        let start = js_sys::Date::now();
+        for i in 0..CALLS {
+            assert_eq!(
+                inc.call(&[Val::I32(i)]).unwrap().to_vec(),
+                vec![Val::I32(i + 1)]
+            );
+        }
+        let dynamic = js_sys::Date::now() - start;
+
+        let start = js_sys::Date::now();
+        for i in 0..CALLS {
+            assert_eq!(inc_native.call(i), Ok(i + 1));
+        }
+        let typed_one = js_sys::Date::now() - start;
+
+        let start = js_sys::Date::now();
+        for i in 0..CALLS {
+            assert_eq!(add_native.call(i, -2 * i), Ok(-i));
+        }
+        let typed_two = js_sys::Date::now() - start;
+
+        // Three arguments go through the reused argument buffer.
+        let start = js_sys::Date::now();
+        for i in 0..CALLS {
+            assert_eq!(mad_native.call(i, 3, i & 1), Ok(3 * i + (i & 1)));
+        }
+        let typed_three = js_sys::Date::now() - start;
+
+        console_log!(
+            "per call: dynamic {:.3}us, typed 1 arg {:.3}us, typed 2 args {:.3}us, typed 3 args {:.3}us",
+            dynamic * 1000.0 / CALLS as f64,
+            typed_one * 1000.0 / CALLS as f64,
+            typed_two * 1000.0 / CALLS as f64,
+            typed_three * 1000.0 / CALLS as f64,
+        );
+    }
+
     #[wasm_bindgen_test]
     fn test_panic() {
         let store = Store::default();
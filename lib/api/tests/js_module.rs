@@ -25,6 +25,20 @@ mod js {
         assert_eq!(module.name(), Some("new_name"));
     }
 
+    #[wasm_bindgen_test]
+    #[cfg(feature = "js-validate")]
+    fn module_validation_diagnostics() {
+        let store = Store::default();
+        let wat = br#"(module (func (result i32) (i64.const 0)))"#;
+        match Module::new(&store, wat) {
+            Err(CompileError::Validate(message)) => {
+                assert!(message.contains("type mismatch"), "{}", message);
+                assert!(message.contains("at offset"), "{}", message);
+            }
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
     #[wasm_bindgen_test]
     fn module_from_jsmodule() {
         let wat = br#"(module $name)"#;
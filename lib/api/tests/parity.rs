@@ -0,0 +1,626 @@
+//! Tests run against every backend from the very same source, see
+//! `wasmer-parity-tests`.
+//!
+//! The `sys` backend runs them with `cargo test`, the `js` backend
+//! with `wasm-pack test` (see `make test-js-api`).
+
+use wasmer::*;
+use wasmer_parity_tests::parity_tests;
+
+parity_tests! {
+    fn global_get_set() {
+        let store = Store::default();
+        let global = Global::new_mut(&store, Value::I32(10));
+        assert_eq!(global.get(), Value::I32(10));
+
+        global.set(Value::I32(20)).unwrap();
+        assert_eq!(global.get(), Value::I32(20));
+
+        let global = Global::new(&store, Value::I32(10));
+        assert!(global.set(Value::I32(20)).is_err());
+    }
+
+    fn memory_grow() {
+        let store = Store::default();
+        let memory_type = MemoryType::new(Pages(1), Some(Pages(3)), false);
+        let memory = Memory::new(&store, memory_type).unwrap();
+
+        assert_eq!(memory.size(), Pages(1));
+        assert_eq!(memory.grow(Pages(2)).unwrap(), Pages(1));
+        assert_eq!(memory.size(), Pages(3));
+        assert!(memory.grow(Pages(1)).is_err());
+    }
+
+    fn memory_access() {
+        let store = Store::default();
+        let memory_type = MemoryType::new(Pages(1), None, false);
+        let memory = Memory::new(&store, memory_type).unwrap();
+
+        let ptr: WasmPtr<u32> = WasmPtr::new(16);
+        ptr.write(&memory, 42).unwrap();
+        assert_eq!(ptr.read(&memory).unwrap(), 42);
+
+        let out_of_bounds: WasmPtr<u32> = WasmPtr::new(WASM_PAGE_SIZE as u32);
+        assert!(out_of_bounds.read(&memory).is_err());
+    }
+
+    #[known_failure("js", "Table::grow is not implemented")]
+    fn table_grow() {
+        let store = Store::default();
+        let table_type = TableType::new(Type::FuncRef, 0, Some(10));
+        let f = Function::new_native(&store, |num: i32| num + 1);
+        let table = Table::new(&store, table_type, Value::FuncRef(Some(f.clone()))).unwrap();
+
+        assert!(table.grow(12, Value::FuncRef(Some(f.clone()))).is_err());
+        assert_eq!(table.grow(5, Value::FuncRef(Some(f))).unwrap(), 0);
+        assert_eq!(table.size(), 5);
+    }
+
+    fn imported_native_function() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+              (import "env" "double" (func $double (param i32) (result i32)))
+              (func (export "quadruple") (param i32) (result i32)
+                (call $double (call $double (local.get 0)))))
+            "#,
+        )
+        .unwrap();
+        let imports = imports! {
+            "env" => {
+                "double" => Function::new_native(&store, |x: i32| x * 2),
+            },
+        };
+        let instance = Instance::new(&module, &imports).unwrap();
+
+        let quadruple = instance
+            .exports
+            .get_native_function::<i32, i32>("quadruple")
+            .unwrap();
+        assert_eq!(quadruple.call(3).unwrap(), 12);
+    }
+
+    #[known_failure("js", "multi-value results of host functions are not supported")]
+    fn imported_function_dynamic_multivalue() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+              (import "env" "swap" (func $swap (param i32 i32) (result i32 i32)))
+              (func (export "swap") (param i32 i32) (result i32 i32)
+                (call $swap (local.get 0) (local.get 1))))
+            "#,
+        )
+        .unwrap();
+        let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32, Type::I32]);
+        let imports = imports! {
+            "env" => {
+                "swap" => Function::new(&store, &signature, |args| {
+                    Ok(vec![args[1].clone(), args[0].clone()])
+                }),
+            },
+        };
+        let instance = Instance::new(&module, &imports).unwrap();
+
+        let swap = instance.exports.get_function("swap").unwrap();
+        assert_eq!(
+            swap.call(&[Value::I32(1), Value::I32(2)]).unwrap().to_vec(),
+            vec![Value::I32(2), Value::I32(1)],
+        );
+    }
+
+    fn trap() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+              (func (export "run")
+                unreachable))
+            "#,
+        )
+        .unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+
+        let run = instance.exports.get_function("run").unwrap();
+        assert!(run.call(&[]).is_err());
+    }
+
+    fn call_batched() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+              (func (export "sum") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1)))
+              (func (export "div") (param i32 i32) (result i32)
+                (i32.div_s (local.get 0) (local.get 1))))
+            "#,
+        )
+        .unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+
+        let sum = instance.exports.get_function("sum").unwrap();
+        let batch = (0..100)
+            .map(|i| vec![Value::I32(i), Value::I32(i * 2)])
+            .collect::<Vec<_>>();
+        let batch = batch.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let results = sum.call_batched(&batch).unwrap();
+        assert_eq!(results.len(), 100);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.to_vec(), vec![Value::I32(i as i32 * 3)]);
+        }
+        assert!(sum.call_batched(&[]).unwrap().is_empty());
+        assert!(sum
+            .call_batched(&[&[Value::I32(1), Value::I32(2)], &[Value::I64(1)]])
+            .is_err());
+
+        let div = instance.exports.get_function("div").unwrap();
+        assert!(div
+            .call_batched(&[&[Value::I32(4), Value::I32(2)], &[Value::I32(1), Value::I32(0)]])
+            .is_err());
+    }
+
+    fn instance_interface() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+              (global (export "counter") i32 (i32.const 0))
+              (func (export "sum") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1)))
+              (func (export "answer") (result i32)
+                (i32.const 42)))
+            "#,
+        )
+        .unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+
+        instance_interface! {
+            struct Calculator {
+                sum: fn(i32, i32) -> i32,
+                /// Renamed, as the name of the export isn't descriptive.
+                #[export_name = "answer"]
+                the_answer: fn() -> i32,
+            }
+        }
+        let calculator = Calculator::bind(&instance).unwrap();
+        assert_eq!(calculator.sum.call(1, 2).unwrap(), 3);
+        assert_eq!(calculator.the_answer.call().unwrap(), 42);
+
+        instance_interface! {
+            struct Missing {
+                product: fn(i32, i32) -> i32,
+            }
+        }
+        assert_eq!(
+            Missing::bind(&instance).err().unwrap().to_string(),
+            "`Missing` expects an export named `product`, which is missing",
+        );
+
+        instance_interface! {
+            struct Mistyped {
+                sum: fn(i64, i64) -> i64,
+            }
+        }
+        assert!(matches!(
+            Mistyped::bind(&instance),
+            Err(InterfaceError::IncompatibleType { expected: "fn(i64, i64) -> i64", .. })
+        ));
+
+        instance_interface! {
+            struct NotAFunction {
+                counter: fn() -> i32,
+            }
+        }
+        assert_eq!(
+            NotAFunction::bind(&instance).err().unwrap().to_string(),
+            "`NotAFunction` expects `counter` to be `fn() -> i32`, but it is a global",
+        );
+    }
+
+    fn start_function_trap() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+              (func $start
+                unreachable)
+              (start $start))
+            "#,
+        )
+        .unwrap();
+
+        match Instance::new(&module, &imports! {}) {
+            Err(InstantiationError::Start(_)) => {}
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    fn missing_import() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+              (import "env" "missing" (func)))
+            "#,
+        )
+        .unwrap();
+
+        match Instance::new(&module, &imports! {}) {
+            Err(InstantiationError::Link(LinkError::Import(module, name, _))) => {
+                assert_eq!(module, "env");
+                assert_eq!(name, "missing");
+            }
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    fn unsatisfied_imports_report() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+              (import "env" "missing" (func))
+              (import "env" "mistyped" (func (param i32)))
+              (import "env" "provided" (func)))
+            "#,
+        )
+        .unwrap();
+        let imports = imports! {
+            "env" => {
+                "mistyped" => Function::new_native(&store, || {}),
+                "provided" => Function::new_native(&store, || {}),
+            },
+        };
+
+        match Instance::new(&module, &imports) {
+            Err(InstantiationError::Link(LinkError::Imports(report))) => {
+                let errors = report
+                    .errors
+                    .iter()
+                    .map(|(module, name, error)| (module.as_str(), name.as_str(), error))
+                    .collect::<Vec<_>>();
+                assert!(matches!(
+                    &errors[..],
+                    [
+                        ("env", "missing", ImportError::UnknownImport(_)),
+                        ("env", "mistyped", ImportError::IncompatibleType(..)),
+                    ]
+                ));
+
+                let message = report.to_string();
+                assert!(message.contains("\"env\".\"missing\": missing"));
+                assert!(message.contains("expected a function of type `[I32] -> []`"));
+            }
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    fn linker_dependencies() {
+        let store = Store::default();
+        let mut linker = Linker::new();
+        linker
+            .define(
+                "host",
+                "double",
+                Function::new_native(&store, |x: i32| x * 2),
+            )
+            .module(
+                "lib",
+                &Module::new(
+                    &store,
+                    br#"
+                    (module
+                      (import "host" "double" (func $double (param i32) (result i32)))
+                      (func (export "answer") (result i32)
+                        (call $double (i32.const 21))))
+                    "#,
+                )
+                .unwrap(),
+            );
+
+        let app = Module::new(
+            &store,
+            br#"
+            (module
+              (import "lib" "answer" (func $answer (result i32)))
+              (func (export "main") (result i32) (call $answer)))
+            "#,
+        )
+        .unwrap();
+        let app = linker.instantiate(&app).unwrap();
+        let main = app
+            .exports
+            .get_native_function::<(), i32>("main")
+            .unwrap();
+        assert_eq!(main.call().unwrap(), 42);
+        assert!(linker.get_instance("lib").is_some());
+    }
+
+    fn linker_cycle() {
+        let store = Store::default();
+        let mut linker = Linker::new();
+        linker
+            .module(
+                "a",
+                &Module::new(&store, br#"(module (import "b" "f" (func)))"#).unwrap(),
+            )
+            .module(
+                "b",
+                &Module::new(&store, br#"(module (import "a" "f" (func)))"#).unwrap(),
+            );
+
+        let app = Module::new(&store, br#"(module (import "a" "f" (func)))"#).unwrap();
+        match linker.instantiate(&app) {
+            Err(LinkerError::Cycle(cycle)) => assert_eq!(cycle, ["a", "b", "a"]),
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    fn call_with_bytes() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 1024))
+                (global $freed (export "freed") (mut i32) (i32.const 0))
+                (func $malloc (export "my_malloc") (param $len i32) (result i32)
+                    (global.get $next)
+                    (global.set $next (i32.add (global.get $next) (local.get $len))))
+                (func (export "my_free") (param i32 i32)
+                    (global.set $freed (i32.add (global.get $freed) (local.get 1))))
+                ;; Returns a copy of its input, reversed.
+                (func (export "reverse") (param $ptr i32) (param $len i32) (result i32 i32)
+                    (local $out i32) (local $i i32)
+                    (local.set $out (call $malloc (local.get $len)))
+                    (block $done
+                        (loop $copy
+                            (br_if $done (i32.eq (local.get $i) (local.get $len)))
+                            (i32.store8
+                                (i32.sub
+                                    (i32.add (local.get $out) (local.get $len))
+                                    (i32.add (local.get $i) (i32.const 1)))
+                                (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+                            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                            (br $copy)))
+                    (local.get $out)
+                    (local.get $len)))
+            "#,
+        )
+        .unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let reverse: TypedFunction<(i32, i32), (i32, i32)> =
+            instance.exports.get_native_function("reverse").unwrap();
+
+        // The default names aren't exported.
+        assert!(reverse.call_with_bytes(&instance, b"abc").is_err());
+
+        let convention = BytesCallConvention {
+            alloc: "my_malloc".to_string(),
+            free: "my_free".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            reverse
+                .call_with_bytes_using(&instance, &convention, b"hello")
+                .unwrap(),
+            b"olleh"
+        );
+        // Both the input and the output are freed.
+        let freed = instance.exports.get_global("freed").unwrap();
+        assert_eq!(freed.get(), Value::I32(10));
+    }
+
+    fn module_validation_error() {
+        let store = Store::default();
+        let wat = br#"(module (func (result i32) (i64.const 0)))"#;
+        let binary = wat2wasm(wat).unwrap();
+
+        assert!(matches!(
+            Module::validate(&store, &binary),
+            Err(CompileError::Validate(_))
+        ));
+        assert!(matches!(
+            Module::new(&store, &binary),
+            Err(CompileError::Validate(_))
+        ));
+    }
+
+    fn runtime_error_kinds() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+                (func (export "unreachable") unreachable)
+                (func (export "div") (param i32) (result i32)
+                    (i32.div_u (i32.const 1) (local.get 0))))
+            "#,
+        )
+        .unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+
+        let unreachable = instance.exports.get_function("unreachable").unwrap();
+        let error = unreachable.call(&[]).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            RuntimeErrorKind::Trap(TrapCode::UnreachableCodeReached)
+        );
+        assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+
+        let div = instance.exports.get_function("div").unwrap();
+        let error = div.call(&[Value::I32(0)]).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            RuntimeErrorKind::Trap(TrapCode::IntegerDivisionByZero)
+        );
+
+        assert_eq!(RuntimeError::new("oops").kind(), RuntimeErrorKind::Generic);
+        let error = RuntimeError::user(Box::new(std::fmt::Error));
+        assert_eq!(error.kind(), RuntimeErrorKind::User);
+        assert!(error.downcast_ref::<std::fmt::Error>().is_some());
+    }
+
+    fn wasm_ptr_index_and_field() {
+        #[derive(Copy, Clone, ValueType)]
+        #[repr(C)]
+        struct Iovec {
+            buf: WasmPtr<u8>,
+            len: u32,
+        }
+
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(Pages(1), None, false)).unwrap();
+
+        let iovs: WasmPtr<Iovec> = WasmPtr::new(16);
+        for i in 0..2 {
+            let iov = Iovec {
+                buf: WasmPtr::new(64 * (i + 1)),
+                len: i + 10,
+            };
+            iovs.index(i as u64).unwrap().write(&memory, iov).unwrap();
+        }
+
+        let second = iovs.index(1).unwrap();
+        assert_eq!(second.offset(), 24);
+        let len: WasmPtr<u32> = wasm_ptr_field!(second, len).unwrap();
+        assert_eq!(len.offset(), 28);
+        assert_eq!(len.read(&memory).unwrap(), 11);
+        let buf = wasm_ptr_field!(second, buf).unwrap();
+        assert_eq!(buf.read(&memory).unwrap().offset(), 128);
+
+        assert!(matches!(
+            iovs.index(u32::MAX as u64),
+            Err(MemoryAccessError::Overflow)
+        ));
+        assert!(matches!(
+            iovs.index(u64::MAX),
+            Err(MemoryAccessError::Overflow)
+        ));
+    }
+
+    fn module_editor_rewrites_custom_sections() {
+        let store = Store::default();
+        let wasm = wat2wasm(
+            br#"(module $named (func (export "answer") (result i32) (i32.const 42)))"#,
+        )
+        .unwrap();
+
+        let mut editor = ModuleEditor::new(&wasm).unwrap();
+        editor.add_custom_section(".debug_info", vec![1, 2, 3]);
+        editor.add_custom_section("metadata", b"old".to_vec());
+        editor.add_custom_section("metadata", b"older".to_vec());
+        editor.replace_custom_section("metadata", b"new".to_vec());
+        let names = editor
+            .custom_sections()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["name", ".debug_info", "metadata"]);
+
+        assert_eq!(editor.strip_debug_info(), 2);
+        assert_eq!(editor.remove_custom_sections("missing"), 0);
+        let bytes = editor.into_bytes();
+
+        let edited = ModuleEditor::new(&bytes).unwrap();
+        assert_eq!(
+            edited.custom_sections().collect::<Vec<_>>(),
+            [("metadata", &b"new"[..])]
+        );
+        let module = Module::new(&store, &bytes).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let answer: TypedFunction<(), i32> =
+            instance.exports.get_native_function("answer").unwrap();
+        assert_eq!(answer.call().unwrap(), 42);
+
+        assert_eq!(
+            ModuleEditor::new(b"\0asm\x01\0\0\0\0\x05").unwrap_err(),
+            ModuleEditError::Truncated { offset: 8 }
+        );
+        assert_eq!(
+            ModuleEditor::new(b"not wasm").unwrap_err(),
+            ModuleEditError::BadHeader
+        );
+    }
+
+    fn function_names_in_traps() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+                (func $crash unreachable)
+                (func (export "run") call $crash))"#,
+        )
+        .unwrap();
+        assert_eq!(module.function_name(0), Some("crash"));
+        assert_eq!(module.function_name(1), None);
+
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let run = instance.exports.get_function("run").unwrap();
+        let error = run.call(&[]).unwrap_err();
+
+        let trace = error.trace();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0].func_index(), 0);
+        assert_eq!(trace[0].function_name(), Some("crash"));
+        assert_eq!(trace[1].func_index(), 1);
+        assert_eq!(trace[1].function_name(), None);
+        assert!(error.to_string().contains("at crash ("));
+    }
+
+    fn check_imports_without_instantiating() {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            br#"
+            (module
+              (import "env" "missing" (func))
+              (import "env" "mistyped" (global i32))
+              (import "env" "provided" (memory 1))
+              (start $main)
+              (func $main unreachable))
+            "#,
+        )
+        .unwrap();
+
+        let imports = imports! {
+            "env" => {
+                "mistyped" => Function::new_native(&store, || {}),
+                "provided" => Memory::new(&store, MemoryType::new(Pages(1), None, false)).unwrap(),
+            },
+        };
+        let issues = module.check_imports(&imports).unwrap_err();
+        assert_eq!(issues.len(), 2);
+        assert_eq!((issues[0].module.as_str(), issues[0].name.as_str()), ("env", "missing"));
+        assert!(matches!(issues[0].error, ImportError::UnknownImport(_)));
+        assert_eq!(issues[1].name, "mistyped");
+        assert!(matches!(issues[1].error, ImportError::IncompatibleType(..)));
+        assert!(issues[1]
+            .to_string()
+            .starts_with("\"env\".\"mistyped\": expected a global"));
+
+        // The start function only runs when instantiating.
+        let imports = imports! {
+            "env" => {
+                "missing" => Function::new_native(&store, || {}),
+                "mistyped" => Global::new(&store, Value::I32(0)),
+                "provided" => Memory::new(&store, MemoryType::new(Pages(1), None, false)).unwrap(),
+            },
+        };
+        module.check_imports(&imports).unwrap();
+        assert!(matches!(
+            Instance::new(&module, &imports),
+            Err(InstantiationError::Start(_))
+        ));
+    }
+}
@@ -1,6 +1,7 @@
 #[cfg(feature = "sys")]
 mod sys {
     use anyhow::Result;
+    use std::time::Duration;
     use wasmer::*;
 
     #[test]
@@ -60,6 +61,53 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn global_observed() -> Result<()> {
+        use std::sync::{Arc, Mutex};
+
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+            (module
+                (import "env" "signal" (global $signal (mut i32)))
+                (import "env" "host" (func $host))
+                (func (export "run")
+                    (global.set $signal (i32.const 1))
+                    (global.set $signal (i32.const 2))
+                    (call $host)
+                    (global.set $signal (i32.const 3)))
+                (func (export "idle")))
+            "#,
+        )?;
+
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let signal = {
+            let observed = observed.clone();
+            Global::new_mut_observed(&store, Value::I32(0), move |value| {
+                observed.lock().unwrap().push(value.unwrap_i32())
+            })
+        };
+        let import_object = imports! {
+            "env" => {
+                "signal" => signal.clone(),
+                "host" => Function::new_native(&store, || {}),
+            },
+        };
+        let instance = Instance::new(&module, &import_object)?;
+
+        instance.exports.get_function("run")?.call(&[])?;
+        assert_eq!(*observed.lock().unwrap(), vec![2, 3]);
+
+        // Neither the writes of the host nor the calls without writes
+        // are observed.
+        signal.set(Value::I32(4))?;
+        instance.exports.get_function("idle")?.call(&[])?;
+        assert_eq!(*observed.lock().unwrap(), vec![2, 3]);
+
+        Ok(())
+    }
+
     #[test]
     fn table_new() -> Result<()> {
         let store = Store::default();
@@ -179,6 +227,116 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn memory_atomics() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(Pages(1), Some(Pages(1)), true))?;
+
+        memory.atomic_store32(8, 5)?;
+        assert_eq!(memory.atomic_rmw32(8, AtomicRmwOp::Add, 3)?, 5);
+        assert_eq!(memory.atomic_rmw32(8, AtomicRmwOp::Xchg, 1)?, 8);
+        assert_eq!(memory.atomic_load32(8)?, 1);
+        memory.atomic_store64(16, u64::MAX)?;
+        assert_eq!(memory.atomic_rmw64(16, AtomicRmwOp::And, 0xff)?, u64::MAX);
+        assert_eq!(memory.atomic_load64(16)?, 0xff);
+
+        assert!(matches!(
+            memory.atomic_load32(6),
+            Err(MemoryAccessError::UnalignedAtomic)
+        ));
+        assert!(matches!(
+            memory.atomic_load64(WASM_PAGE_SIZE as u64),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        ));
+
+        let timeout = Some(Duration::from_millis(10));
+        assert_eq!(memory.wait32(8, 0, timeout)?, WaitResult::NotEqual);
+        assert_eq!(memory.wait32(8, 1, timeout)?, WaitResult::TimedOut);
+        assert_eq!(memory.notify(8, 1)?, 0);
+
+        let waiter = {
+            let memory = memory.clone();
+            std::thread::spawn(move || memory.wait32(8, 1, None))
+        };
+        while memory.notify(8, 1)? == 0 {
+            std::thread::yield_now();
+        }
+        assert_eq!(waiter.join().unwrap()?, WaitResult::Ok);
+
+        let unshared = Memory::new(&store, MemoryType::new(Pages(1), None, false))?;
+        assert!(matches!(
+            unshared.wait32(0, 0, timeout),
+            Err(MemoryAccessError::WaitNotAllowed)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_lease() -> Result<()> {
+        let engine = Store::default().engine().clone();
+        let mut tunables = BaseTunables::for_target(engine.target());
+        tunables.static_memory_bound = Pages(0);
+        let store = Store::new_with_tunables(&*engine, tunables);
+        // A dynamic memory moves when it grows past its allocation.
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        memory.write(8, &[1, 2, 3, 4])?;
+
+        let lease = memory.lease(8, 4)?;
+        assert_eq!(unsafe { lease.as_slice() }, &[1, 2, 3, 4]);
+        let clone = lease.clone();
+        drop(lease);
+        assert!(memory.grow(Pages(1000)).is_err());
+        assert_eq!(unsafe { clone.as_slice() }, &[1, 2, 3, 4]);
+        drop(clone);
+        memory.grow(Pages(1000))?;
+
+        assert!(matches!(
+            memory.lease(memory.data_size() - 2, 4),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        ));
+        assert!(matches!(
+            memory.lease(u64::MAX, 2),
+            Err(MemoryAccessError::Overflow)
+        ));
+        // A failed lease releases its pin.
+        memory.grow(Pages(1000))?;
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn memory_from_file_uses_the_tunables() -> Result<()> {
+        use std::io::Write;
+
+        let engine = Store::default().engine().clone();
+        let tunables = GrowHookTunables::new(
+            BaseTunables::for_target(engine.target()),
+            |_: Pages, _: Pages| MemoryGrowDecision::Deny,
+        );
+        let store = Store::new_with_tunables(&*engine, tunables.clone());
+        let mut file = tempfile::tempfile()?;
+        file.write_all(b"dataset")?;
+
+        let memory = Memory::new_from_file(
+            &store,
+            MemoryType::new(0, None, false),
+            &file,
+            FileMapping::CopyOnWrite,
+        )?;
+        let mut bytes = [0; 8];
+        memory.read(0, &mut bytes)?;
+        assert_eq!(&bytes, b"dataset\0");
+        assert!(matches!(
+            memory.grow(1),
+            Err(MemoryError::CouldNotGrow { .. })
+        ));
+        assert_eq!(tunables.stats().denied, 1);
+
+        Ok(())
+    }
+
     #[test]
     fn function_new() -> Result<()> {
         let store = Store::default();
@@ -464,4 +622,166 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn store_adopt() -> Result<()> {
+        let plugin_store = Store::default();
+        let memory = Memory::new(&plugin_store, MemoryType::new(1, None, false))?;
+        let double = Function::new_native(&plugin_store, |x: i32| x * 2);
+        let module = Module::new(
+            &plugin_store,
+            r#"(module
+  (func (export "one") (result i32)
+    i32.const 1))"#,
+        )?;
+        let plugin = Instance::new(&module, &imports! {})?;
+        let one = plugin.exports.get_function("one")?.clone();
+
+        let host_store = Store::default();
+        assert!(!Store::same(&plugin_store, &host_store));
+        let shared_memory = host_store.adopt(&memory.clone().into())?;
+        let shared_double = host_store.adopt(&double.into())?;
+        assert_eq!(
+            host_store.adopt(&one.into()).unwrap_err(),
+            AdoptError::CompiledFunction
+        );
+        // Tearing down the plugin doesn't affect the adopted externs.
+        drop(plugin);
+        drop(module);
+        drop(plugin_store);
+
+        let module = Module::new(
+            &host_store,
+            r#"(module
+  (import "env" "memory" (memory 1))
+  (import "env" "double" (func $double (param i32) (result i32)))
+  (func (export "run")
+    i32.const 0
+    i32.const 21
+    call $double
+    i32.store))"#,
+        )?;
+        let instance = Instance::new(
+            &module,
+            &imports! {
+                "env" => {
+                    "memory" => shared_memory,
+                    "double" => shared_double,
+                },
+            },
+        )?;
+        instance.exports.get_function("run")?.call(&[])?;
+
+        // The memory is shared with the original one.
+        let ptr: WasmPtr<u32> = WasmPtr::new(0);
+        assert_eq!(ptr.read(&memory)?, 42);
+
+        Ok(())
+    }
+
+    #[test]
+    fn host_state_data_and_memory() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+  (import "env" "count" (func $count (param i32)))
+  (memory (export "memory") 1)
+  (func (export "run")
+    i32.const 8
+    call $count
+    i32.const 16
+    call $count))"#,
+        )?;
+
+        fn count(env: &HostState<Vec<u32>>, ptr: WasmPtr<u32>) {
+            let (mut calls, memory) = env.data_and_memory_mut();
+            calls.push(ptr.offset());
+            ptr.write(memory, calls.len() as u32).unwrap();
+        }
+
+        let state = HostState::new(Vec::new());
+        let instance = Instance::new(
+            &module,
+            &imports! {
+                "env" => {
+                    "count" => Function::new_native_with_env(&store, state.clone(), count),
+                },
+            },
+        )?;
+        instance.exports.get_function("run")?.call(&[])?;
+
+        assert_eq!(*state.data_mut(), vec![8, 16]);
+        let memory = instance.exports.get_memory("memory")?;
+        assert_eq!(WasmPtr::<u32>::new(8).read(memory)?, 1);
+        assert_eq!(WasmPtr::<u32>::new(16).read(memory)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn store_gc() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, r#"(module (func (export "f")))"#)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let f = instance.exports.get_function("f")?.clone();
+        let host = Function::new_native_with_env(&store, 7, |x: &i32| *x);
+
+        let table_type = TableType {
+            ty: Type::FuncRef,
+            minimum: 1,
+            maximum: None,
+        };
+        let table = Table::new(&store, table_type, Value::FuncRef(Some(f.clone())))?;
+        table.grow(1, Value::FuncRef(Some(host.clone())))?;
+        assert_eq!(
+            store.gc(),
+            StoreGcStats {
+                reclaimed_functions: 0,
+                retained_functions: 2,
+            }
+        );
+
+        // The table still holds the function of the dropped instance.
+        drop(f);
+        drop(instance);
+        assert_eq!(
+            store.gc(),
+            StoreGcStats {
+                reclaimed_functions: 0,
+                retained_functions: 2,
+            }
+        );
+
+        drop(table);
+        assert_eq!(
+            store.gc(),
+            StoreGcStats {
+                reclaimed_functions: 1,
+                retained_functions: 1,
+            }
+        );
+
+        // So does a global with the dropped host function.
+        let global = Global::new(&store, Value::FuncRef(Some(host.clone())));
+        drop(host);
+        assert_eq!(
+            store.gc(),
+            StoreGcStats {
+                reclaimed_functions: 0,
+                retained_functions: 1,
+            }
+        );
+
+        drop(global);
+        assert_eq!(
+            store.gc(),
+            StoreGcStats {
+                reclaimed_functions: 1,
+                retained_functions: 0,
+            }
+        );
+
+        Ok(())
+    }
 }
@@ -67,4 +67,601 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn instance_pool_reuses_memories() -> Result<()> {
+        let engine = Store::default().engine().clone();
+        let tunables = PoolingTunables::new(BaseTunables::for_target(engine.target()), 2);
+        let store = Store::new_with_tunables(&*engine, tunables.clone());
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (memory (export "memory") 1)
+      (func (export "write")
+        (i32.store (i32.const 0) (i32.const 42))))
+"#,
+        )?;
+
+        let pool = InstancePool::new(&module, &imports! {});
+        pool.prewarm(2)?;
+        assert_eq!(pool.ready(), 2);
+
+        let instance = pool.get()?;
+        assert_eq!(pool.ready(), 1);
+        instance.exports.get_function("write")?.call(&[])?;
+        drop(instance);
+        assert_eq!(tunables.idle_memories(), 1);
+
+        drop(pool.get()?);
+        assert_eq!(tunables.idle_memories(), 2);
+
+        // No instance is ready anymore: a new one reuses a zeroed memory.
+        let instance = pool.get()?;
+        assert_eq!(tunables.idle_memories(), 1);
+        let mut bytes = [0; 4];
+        instance.exports.get_memory("memory")?.read(0, &mut bytes)?;
+        assert_eq!(bytes, [0; 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_grow_hook() -> Result<()> {
+        let engine = Store::default().engine().clone();
+        let tunables = GrowHookTunables::new(
+            BaseTunables::for_target(engine.target()),
+            |current: Pages, delta: Pages| {
+                if delta.0 >= 10 {
+                    MemoryGrowDecision::Trap
+                } else if current.0 + delta.0 <= 2 {
+                    MemoryGrowDecision::Allow
+                } else {
+                    MemoryGrowDecision::Deny
+                }
+            },
+        );
+        let store = Store::new_with_tunables(&*engine, tunables.clone());
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (memory (export "memory") 1)
+      (func (export "grow") (param i32) (result i32)
+        (memory.grow (local.get 0))))
+"#,
+        )?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let grow = instance.exports.get_native_function::<i32, i32>("grow")?;
+
+        assert_eq!(grow.call(1)?, 1);
+        assert_eq!(grow.call(1)?, -1);
+        assert_eq!(grow.call(0)?, 2);
+        let error = grow.call(10).unwrap_err();
+        assert!(matches!(
+            error.downcast::<MemoryError>(),
+            Ok(MemoryError::OutOfMemory { .. })
+        ));
+
+        // The host is asked too.
+        let memory = instance.exports.get_memory("memory")?;
+        assert!(matches!(
+            memory.grow(1),
+            Err(MemoryError::CouldNotGrow { .. })
+        ));
+        assert_eq!(memory.size(), Pages(2));
+
+        assert_eq!(
+            tunables.stats(),
+            MemoryGrowStats {
+                allowed: 1,
+                denied: 2,
+                trapped: 1,
+                denied_pages: 12,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_stats() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (memory (export \"memory\") 1 4)
+      (func (export \"grow\") (param $pages i32) (result i32)
+        local.get $pages
+        memory.grow))
+",
+        )?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let grow = instance.exports.get_native_function::<i32, i32>("grow")?;
+
+        assert_eq!(grow.call(2)?, 1);
+        assert_eq!(grow.call(0)?, 3);
+        assert_eq!(grow.call(2)?, -1);
+
+        let stats = instance.memory_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].current, Pages(3));
+        assert_eq!(stats[0].peak, Pages(3));
+        assert_eq!(stats[0].grows, 1);
+
+        // An imported memory reports the grows of all its users.
+        let memory = instance.exports.get_memory("memory")?;
+        let importer = Module::new(&store, "(module (import \"env\" \"memory\" (memory 1)))")?;
+        let importer = Instance::new(
+            &importer,
+            &imports! { "env" => { "memory" => memory.clone() } },
+        )?;
+        memory.grow(1)?;
+        assert_eq!(importer.memory_stats(), vec![memory.stats()]);
+        assert_eq!(memory.stats().grows, 2);
+
+        Ok(())
+    }
+
+    /// Appends the DWARF debug info of a made up `lib.c` to `wasm`, whose
+    /// every byte of code is on its own line, from line 100. Returns the
+    /// offset of the code section.
+    #[cfg(feature = "debug-symbols")]
+    fn append_debug_info(wasm: &mut Vec<u8>) -> Result<usize> {
+        use gimli::write::{
+            Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Sections,
+        };
+
+        fn write_u32_leb128(bytes: &mut Vec<u8>, mut value: u32) {
+            loop {
+                let byte = (value & 0x7f) as u8;
+                value >>= 7;
+                if value == 0 {
+                    bytes.push(byte);
+                    return;
+                }
+                bytes.push(byte | 0x80);
+            }
+        }
+        fn read_u32_leb128(bytes: &[u8], offset: &mut usize) -> u32 {
+            let mut value = 0;
+            for shift in (0..).step_by(7) {
+                let byte = bytes[*offset];
+                *offset += 1;
+                value |= ((byte & 0x7f) as u32) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            value
+        }
+
+        // Find the code section, after the magic and the version.
+        let mut offset = 8;
+        let (code_section_offset, code_len) = loop {
+            let id = wasm[offset];
+            offset += 1;
+            let size = read_u32_leb128(wasm, &mut offset) as usize;
+            if id == 10 {
+                break (offset, size as u64);
+            }
+            offset += size;
+        };
+
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        let mut program = LineProgram::new(
+            encoding,
+            gimli::LineEncoding::default(),
+            LineString::String(b"/src".to_vec()),
+            LineString::String(b"lib.c".to_vec()),
+            None,
+        );
+        let directory = program.default_directory();
+        let file = program.add_file(LineString::String(b"lib.c".to_vec()), directory, None);
+        program.begin_sequence(Some(Address::Constant(0)));
+        for address in 0..code_len {
+            program.row().address_offset = address;
+            program.row().file = file;
+            program.row().line = 100 + address;
+            program.generate_row();
+        }
+        program.end_sequence(code_len);
+        dwarf.unit.line_program = program;
+        let root = dwarf.unit.get_mut(dwarf.unit.root());
+        root.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(0)),
+        );
+        root.set(gimli::DW_AT_high_pc, AttributeValue::Udata(code_len));
+
+        let mut sections = Sections::new(EndianVec::new(gimli::LittleEndian));
+        dwarf.write(&mut sections)?;
+        sections.for_each(|id, data| -> Result<()> {
+            let data = data.slice();
+            if data.is_empty() {
+                return Ok(());
+            }
+            let mut contents = Vec::new();
+            write_u32_leb128(&mut contents, id.name().len() as u32);
+            contents.extend_from_slice(id.name().as_bytes());
+            contents.extend_from_slice(data);
+            wasm.push(0);
+            write_u32_leb128(wasm, contents.len() as u32);
+            wasm.extend(contents);
+            Ok(())
+        })?;
+
+        Ok(code_section_offset)
+    }
+
+    #[cfg(all(feature = "cranelift", feature = "debug-symbols"))]
+    #[test]
+    fn trace_source_locations() -> Result<()> {
+        let engine = Universal::new(Cranelift::default()).engine();
+        let store = Store::new_with_engine(&engine);
+        let mut wasm = wat2wasm(
+            br#"
+(module
+  (func (export "run") (call $fail))
+  (func $fail (unreachable)))
+"#,
+        )?
+        .into_owned();
+        let code_section_offset = append_debug_info(&mut wasm)?;
+
+        let module = Module::new(&store, wasm)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let run = instance.exports.get_function("run")?;
+        let error = run.call(&[]).unwrap_err();
+
+        let trace = error.trace();
+        assert_eq!(trace.len(), 2);
+        for frame in trace {
+            let location = frame.source_location().expect("a source location");
+            assert!(location.file().ends_with("lib.c"), "{}", location);
+            assert_eq!(
+                location.line(),
+                Some(100 + (frame.module_offset() - code_section_offset) as u32)
+            );
+        }
+        let message = error.to_string();
+        assert!(message.contains(&format!(" at {}", trace[0].source_location().unwrap())));
+
+        Ok(())
+    }
+
+    #[cfg(all(
+        feature = "cranelift",
+        unix,
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    fn profiler_samples_the_wasm_stacks() -> Result<()> {
+        let engine = Universal::new(Cranelift::default()).engine();
+        let store = Store::new_with_engine(&engine);
+        let module = Module::new(
+            &store,
+            r#"
+(module $profiled
+  (func $run (export "run") (param i64) (result i64)
+    (call $busy (local.get 0)))
+  (func $busy (param $n i64) (result i64)
+    (local $sum i64)
+    (loop $continue
+      (local.set $sum (i64.add (local.get $sum) (i64.mul (local.get $n) (local.get $n))))
+      (local.set $n (i64.sub (local.get $n) (i64.const 1)))
+      (br_if $continue (i64.ne (local.get $n) (i64.const 0))))
+    (local.get $sum)))
+"#,
+        )?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let run = instance.exports.get_native_function::<i64, i64>("run")?;
+
+        let mut profiler = Profiler::attach(&store).with_frequency(1000);
+        profiler.start()?;
+        assert!(matches!(
+            Profiler::attach(&store).start(),
+            Err(ProfilerError::StoreProfiled)
+        ));
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_millis(300) {
+            run.call(1_000_000)?;
+        }
+        let profile = profiler.stop();
+        assert!(!profiler.is_running());
+
+        assert!(profile.samples() > 0);
+        let stack = &profile.stacks()[0];
+        let names = stack
+            .frames()
+            .iter()
+            .map(|frame| frame.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["busy", "run"]);
+        assert_eq!(stack.frames()[0].module_name(), Some("profiled"));
+
+        let mut pprof = vec![];
+        profile.write_pprof(&mut pprof)?;
+        assert!(pprof.windows(4).any(|window| window == b"busy"));
+        let mut speedscope = vec![];
+        profile.write_speedscope(&mut speedscope)?;
+        let speedscope = String::from_utf8(speedscope)?;
+        assert!(speedscope.contains(r#"{"name":"busy","file":"profiled"}"#));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cranelift")]
+    #[test]
+    fn threads_share_memory() -> Result<()> {
+        let mut features = Features::new();
+        features.threads(true);
+        let engine = Universal::new(Cranelift::default())
+            .features(features)
+            .engine();
+        let store = Store::new_with_engine(&engine);
+        let module = Module::new(
+            &store,
+            r#"(module
+  (import "env" "memory" (memory 1 1 shared))
+  (import "env" "id" (func $id (result i32)))
+  (func (export "run")
+    (drop (i32.atomic.rmw.add (i32.const 0) (call $id)))))"#,
+        )?;
+        let memory = Memory::new_shared(&store, 1, 1)?;
+
+        let threads = (1..=4)
+            .map(|id| {
+                threads::spawn_in_new_store(
+                    &module,
+                    &memory,
+                    move |store, memory| {
+                        imports! {
+                            "env" => {
+                                "memory" => memory.clone(),
+                                "id" => Function::new_native_with_env(store, id, |id: &i32| *id),
+                            },
+                        }
+                    },
+                    |instance| {
+                        instance
+                            .exports
+                            .get_function("run")?
+                            .call(&[])
+                            .map(|_| ())
+                            .map_err(anyhow::Error::from)
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for thread in threads {
+            thread.join().unwrap()??;
+        }
+        assert_eq!(memory.atomic_load32(0)?, 1 + 2 + 3 + 4);
+
+        let unshared = Memory::new(&store, MemoryType::new(1, Some(1), false))?;
+        assert!(matches!(
+            threads::spawn_in_new_store(&module, &unshared, |_, _| imports! {}, |_| ()),
+            Err(threads::ThreadError::NotShared)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn close_tears_the_instance_down() -> Result<()> {
+        use std::sync::{Arc, Mutex};
+
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+  (memory (export "memory") 1)
+  (func (export "answer") (result i32) (i32.const 42)))"#,
+        )?;
+        let instance = Instance::new(&module, &imports! {})?;
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for id in 0..2 {
+            let log = log.clone();
+            instance.on_teardown(move || log.lock().unwrap().push(id));
+        }
+
+        // A clone of the instance keeps it alive.
+        let clone = instance.clone();
+        let instance = instance.close().unwrap_err();
+        drop(clone);
+
+        // So does an exported function.
+        let answer = instance.exports.get_function("answer")?.clone();
+        let instance = instance.close().unwrap_err();
+        assert_eq!(
+            instance.exports.get_function("answer")?.call(&[])?[0],
+            Value::I32(42)
+        );
+        assert!(log.lock().unwrap().is_empty());
+
+        drop(answer);
+        assert!(instance.close().is_ok());
+        assert_eq!(*log.lock().unwrap(), vec![0, 1]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cranelift")]
+    fn exceptions_instance(wat: &str) -> Result<Instance> {
+        let mut features = Features::default();
+        features.exceptions = true;
+        let engine = Universal::new(Cranelift::default())
+            .features(features)
+            .engine();
+        let store = Store::new_with_engine(&engine);
+        let module = Module::new(&store, wat)?;
+
+        Ok(Instance::new(&module, &imports! {})?)
+    }
+
+    #[cfg(feature = "cranelift")]
+    #[test]
+    fn exceptions_are_caught() -> Result<()> {
+        let instance = exceptions_instance(
+            r#"
+(module
+  (tag $t (param i32 i64))
+  (tag $u)
+  (func $throw (param i32)
+    local.get 0
+    i64.const 7
+    throw $t)
+  ;; Catches an exception thrown by a callee, with its values.
+  (func (export "catch") (param i32) (result i64) (local i64)
+    try (result i64)
+      local.get 0
+      call $throw
+      i64.const -1
+    catch $u
+      i64.const -2
+    catch $t
+      local.set 1
+      i64.extend_i32_s
+      local.get 1
+      i64.add
+    end)
+  (func (export "catch_all") (result i32)
+    try (result i32)
+      throw $u
+    catch $t
+      drop
+      drop
+      i32.const 1
+    catch_all
+      i32.const 2
+    end)
+  ;; Rethrows the exception to an outer `try` block.
+  (func (export "rethrow") (result i32)
+    try (result i32)
+      try
+        i32.const 3
+        call $throw
+      catch_all
+        rethrow 0
+      end
+      i32.const -1
+    catch $t
+      drop
+    end)
+  ;; Delegates the exception to the handler of an outer `try` block.
+  (func (export "delegate") (result i32)
+    try (result i32)
+      try
+        try
+          i32.const 4
+          call $throw
+        delegate 1
+      catch $t
+        drop
+        i32.const -1
+        return
+      end
+      i32.const -2
+    catch $t
+      drop
+    end)
+  (func (export "loop") (param i32) (result i64) (local i64)
+    loop
+      local.get 1
+      local.get 0
+      call 1
+      i64.add
+      local.set 1
+      local.get 0
+      i32.const 1
+      i32.sub
+      local.tee 0
+      br_if 0
+    end
+    local.get 1))
+"#,
+        )?;
+
+        let catch = instance.exports.get_function("catch")?;
+        assert_eq!(catch.call(&[Value::I32(35)])?[0], Value::I64(42));
+        assert_eq!(
+            instance.exports.get_function("catch_all")?.call(&[])?[0],
+            Value::I32(2)
+        );
+        assert_eq!(
+            instance.exports.get_function("rethrow")?.call(&[])?[0],
+            Value::I32(3)
+        );
+        assert_eq!(
+            instance.exports.get_function("delegate")?.call(&[])?[0],
+            Value::I32(4)
+        );
+        assert_eq!(
+            instance
+                .exports
+                .get_function("loop")?
+                .call(&[Value::I32(1000)])?[0],
+            Value::I64((1..=1000).map(|i| i + 7).sum())
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cranelift")]
+    #[test]
+    fn uncaught_exceptions() -> Result<()> {
+        let instance = exceptions_instance(
+            r#"
+(module
+  (tag $t (export "t") (param i32 f64))
+  (func $throw (export "throw") (param i32)
+    local.get 0
+    f64.const 1.5
+    throw $t)
+  (func (export "rethrow") (param i32) (result i32)
+    try
+      local.get 0
+      call $throw
+    catch $t
+      drop
+      drop
+      rethrow 0
+    end
+    i32.const 0))
+"#,
+        )?;
+        let tag = instance.exports.get_tag("t")?;
+        assert_eq!(tag.ty().params(), &[Type::I32, Type::F64]);
+
+        for name in ["throw", "rethrow"] {
+            let error = instance
+                .exports
+                .get_function(name)?
+                .call(&[Value::I32(42)])
+                .unwrap_err();
+            assert_eq!(error.kind(), RuntimeErrorKind::Exception);
+
+            let exception = error.downcast::<Exception>()?;
+            assert!(tag.matches(&exception));
+            assert_eq!(
+                exception.values::<Function>(),
+                vec![Value::I32(42), Value::F64(1.5)]
+            );
+        }
+
+        // The exceptions don't outlive the calls that threw them.
+        let throw = instance.exports.get_native_function::<i32, ()>("throw")?;
+        assert!(throw.call(1).is_err());
+        assert!(throw.call(2).is_err());
+
+        Ok(())
+    }
 }
@@ -248,4 +248,404 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn preinitialized_instances_start_from_the_snapshot() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+            (memory (export "memory") 1)
+            (global $counter (export "counter") (mut i32) (i32.const 0))
+            (data (i32.const 0) "\01")
+            (func (export "init")
+                (memory.grow (i32.const 1))
+                drop
+                (i32.store8 (i32.const 65536) (i32.const 42))
+                (global.set $counter (i32.const 7))))"#;
+        let module = Module::new(&store, wat)?.preinitialize(&imports! {}, "init")?;
+
+        let first = Instance::new(&module, &imports! {})?;
+        let second = Instance::new(&module, &imports! {})?;
+        for instance in &[&first, &second] {
+            let memory = instance.exports.get_memory("memory")?;
+            assert_eq!(memory.size(), Pages(2));
+            let mut byte = [0];
+            memory.read(0, &mut byte)?;
+            assert_eq!(byte, [1]);
+            memory.read(65536, &mut byte)?;
+            assert_eq!(byte, [42]);
+            let counter = instance.exports.get_global("counter")?;
+            assert_eq!(counter.get(), Value::I32(7));
+        }
+
+        first.exports.get_memory("memory")?.write(0, &[2])?;
+        first.exports.get_global("counter")?.set(Value::I32(8))?;
+        let mut byte = [0];
+        second.exports.get_memory("memory")?.read(0, &mut byte)?;
+        assert_eq!(byte, [1]);
+        assert_eq!(second.exports.get_global("counter")?.get(), Value::I32(7));
+
+        assert!(matches!(
+            module.preinitialize(&imports! {}, "missing"),
+            Err(PreinitializeError::Export(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn snapshots_of_instances() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+            (memory (export "memory") 1)
+            (global $starts (mut i32) (i32.const 0))
+            (func $start (global.set $starts (i32.add (global.get $starts) (i32.const 1))))
+            (start $start)
+            (func (export "starts") (result i32) (global.get $starts))
+            (func (export "bump")
+                (i32.store8 (i32.const 0) (i32.const 42))
+                (global.set $starts (i32.const 10))))"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        instance.exports.get_function("bump")?.call(&[])?;
+
+        let memory = instance.exports.get_memory("memory")?;
+        let snapshot = module.snapshot_of(memory).unwrap();
+        let copy = Instance::new(&snapshot, &imports! {})?;
+        let mut byte = [0];
+        copy.exports.get_memory("memory")?.read(0, &mut byte)?;
+        assert_eq!(byte, [42]);
+        // The global isn't exported, and the start function isn't called.
+        let starts = copy.exports.get_native_function::<(), i32>("starts")?;
+        assert_eq!(starts.call()?, 10);
+
+        let host_memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        assert!(module.snapshot_of(&host_memory).is_none());
+        let other = Module::new(&store, r#"(module (memory (export "memory") 1))"#)?;
+        assert!(other.snapshot_of(memory).is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cranelift")]
+    #[test]
+    fn lazy_modules_are_compiled_when_instantiated() -> Result<()> {
+        let engine = Universal::new(Cranelift::default()).lazy(true).engine();
+        let store = Store::new_with_engine(&engine);
+        let wat = r#"(module
+            (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1))))"#;
+        let module = Module::new(&store, wat)?;
+        assert_eq!(module.exports().count(), 1);
+
+        let instance = Instance::new(&module, &imports! {})?;
+        let add = instance
+            .exports
+            .get_native_function::<(i32, i32), i32>("add")?;
+        assert_eq!(add.call(1, 2)?, 3);
+
+        let module = unsafe { Module::deserialize(&store, &module.serialize()?)? };
+        let instance = Instance::new(&module, &imports! {})?;
+        let add = instance
+            .exports
+            .get_native_function::<(i32, i32), i32>("add")?;
+        assert_eq!(add.call(3, 4)?, 7);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cranelift")]
+    #[test]
+    fn headless_engines_load_precompiled_modules() -> Result<()> {
+        let store = Store::new_with_engine(&Universal::new(Cranelift::default()).engine());
+        let wat = r#"(module
+            (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1))))"#;
+        let serialized = Module::new(&store, wat)?.serialize()?;
+
+        let store = Store::new_with_engine(&UniversalEngine::headless());
+        assert!(Module::new(&store, wat).is_err());
+        let module = unsafe { Module::deserialize(&store, &serialized)? };
+        let instance = Instance::new(&module, &imports! {})?;
+        let add = instance
+            .exports
+            .get_native_function::<(i32, i32), i32>("add")?;
+        assert_eq!(add.call(1, 2)?, 3);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cranelift")]
+    #[test]
+    fn deterministic_engines() -> Result<()> {
+        let engine = Universal::new(Cranelift::default())
+            .deterministic(true)
+            .engine();
+        assert!(engine.is_deterministic());
+        let store = Store::new_with_engine(&engine);
+
+        let wat = r#"(module
+            (func (export "nan") (param f32) (result i32)
+                (i32.reinterpret_f32 (f32.div (local.get 0) (local.get 0)))))"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let nan = instance.exports.get_native_function::<f32, i32>("nan")?;
+        assert_eq!(nan.call(0.0)?, 0x7fc0_0000);
+
+        let wat = r#"(module
+            (import "wasi_snapshot_preview1" "random_get" (func (param i32 i32) (result i32))))"#;
+        match Module::new(&store, wat) {
+            Err(CompileError::Validate(message)) => {
+                assert!(message.contains("wasi_snapshot_preview1.random_get"))
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert!(Module::new(&store, "(module (memory 1 1 shared))").is_err());
+
+        // The deserialized modules must have been compiled deterministically.
+        let headless = Universal::headless().deterministic(true).engine();
+        let headless_store = Store::new_with_engine(&headless);
+        let serialized = module.serialize()?;
+        unsafe { Module::deserialize(&headless_store, &serialized)? };
+        let serialized = Module::new(&Store::default(), wat)?.serialize()?;
+        match unsafe { Module::deserialize(&headless_store, &serialized) } {
+            Err(DeserializeError::Incompatible(_)) => {}
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cranelift")]
+    #[test]
+    fn inline_bulk_memory_operations() -> Result<()> {
+        let engine = Universal::new(Cranelift::default())
+            .bulk_memory_inline_threshold(64)
+            .engine();
+        let store = Store::new_with_engine(&engine);
+        let wat = r#"(module
+            (memory (export "memory") 1)
+            (func (export "copy") (param i32 i32)
+                (memory.copy (local.get 0) (local.get 1) (i32.const 15)))
+            (func (export "fill") (param i32 i32)
+                (memory.fill (local.get 0) (local.get 1) (i32.const 13))))"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let copy = instance
+            .exports
+            .get_native_function::<(i32, i32), ()>("copy")?;
+        let fill = instance
+            .exports
+            .get_native_function::<(i32, i32), ()>("fill")?;
+
+        memory.write(0, &(0..20).collect::<Vec<u8>>())?;
+        // The ranges overlap.
+        copy.call(3, 0)?;
+        let mut bytes = [0; 20];
+        memory.read(0, &mut bytes)?;
+        assert_eq!(
+            bytes,
+            [0, 1, 2, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 18, 19]
+        );
+
+        fill.call(100, 0x1ab)?;
+        let mut bytes = [0; 15];
+        memory.read(99, &mut bytes)?;
+        assert_eq!(
+            bytes,
+            [0, 171, 171, 171, 171, 171, 171, 171, 171, 171, 171, 171, 171, 171, 0]
+        );
+
+        // Out-of-bounds operations trap without writing anything.
+        assert!(copy.call(65530, 0).is_err());
+        assert!(copy.call(0, 65530).is_err());
+        assert!(fill.call(65530, 7).is_err());
+        let mut bytes = [0xff; 6];
+        memory.read(65530, &mut bytes)?;
+        assert_eq!(bytes, [0; 6]);
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "cranelift", feature = "jit-debug"))]
+    #[test]
+    fn jit_debug_registers_the_functions() -> Result<()> {
+        #[repr(C)]
+        struct JitCodeEntry {
+            next_entry: *const JitCodeEntry,
+            prev_entry: *const JitCodeEntry,
+            symfile_addr: *const u8,
+            symfile_size: u64,
+        }
+        #[repr(C)]
+        struct JitDescriptor {
+            version: u32,
+            action_flag: u32,
+            relevant_entry: *const JitCodeEntry,
+            first_entry: *const JitCodeEntry,
+        }
+        extern "C" {
+            static __jit_debug_descriptor: JitDescriptor;
+        }
+
+        /// Returns whether an object registered with the GDB JIT
+        /// interface names `symbol`.
+        fn is_registered(symbol: &[u8]) -> bool {
+            let mut entry = unsafe { __jit_debug_descriptor.first_entry };
+            while !entry.is_null() {
+                let entry_ref = unsafe { &*entry };
+                let image = unsafe {
+                    std::slice::from_raw_parts(
+                        entry_ref.symfile_addr,
+                        entry_ref.symfile_size as usize,
+                    )
+                };
+                if image.windows(symbol.len()).any(|bytes| bytes == symbol) {
+                    return true;
+                }
+                entry = entry_ref.next_entry;
+            }
+            false
+        }
+
+        // Keep the jitdump file, on Linux, out of the source tree.
+        std::env::set_var("JITDUMPDIR", std::env::temp_dir());
+
+        let wat = r#"(module (func $jit_debug_registered_function))"#;
+        let store = Store::new_with_engine(&Universal::new(Cranelift::default()).engine());
+        Module::new(&store, wat)?;
+        assert!(!is_registered(b"jit_debug_registered_function\0"));
+
+        let engine = Universal::new(Cranelift::default())
+            .jit_debug(true)
+            .engine();
+        let store = Store::new_with_engine(&engine);
+        Module::new(&store, wat)?;
+        assert!(is_registered(b"jit_debug_registered_function\0"));
+
+        // The functions are unregistered with the engine holding their
+        // code.
+        drop(store);
+        drop(engine);
+        assert!(!is_registered(b"jit_debug_registered_function\0"));
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "cranelift", unix))]
+    #[test]
+    fn perf_map_and_symbol_lookup() -> Result<()> {
+        let engine = Universal::new(Cranelift::default()).perf_map(true).engine();
+        let store = Store::new_with_engine(&engine);
+        let module = Module::new(
+            &store,
+            r#"(module $profiled (func $perf_map_function) (func) (export "f" (func 1)))"#,
+        )?;
+        let _instance = Instance::new(&module, &imports! {})?;
+
+        let perf_map = std::fs::read_to_string(format!("/tmp/perf-{}.map", std::process::id()))?;
+        let function = |name: &str| -> (usize, usize) {
+            let line = perf_map
+                .lines()
+                .rev()
+                .find(|line| line.ends_with(&format!(" {}", name)))
+                .expect("the function in the perf map");
+            let mut fields = line.split(' ');
+            let mut hex = || usize::from_str_radix(fields.next().unwrap(), 16).unwrap();
+            (hex(), hex())
+        };
+
+        let (address, size) = function("perf_map_function");
+        let symbol = lookup_jit_symbol(address + size / 2).expect("a symbol");
+        assert_eq!(symbol.name(), "perf_map_function");
+        assert_eq!(symbol.module_name(), "profiled");
+        assert_eq!(symbol.func_index(), 0);
+        assert_eq!((symbol.address(), symbol.size()), (address, size));
+
+        let (address, _) = function("wasm-function[1]");
+        let symbol = lookup_jit_symbol(address).expect("a symbol");
+        assert_eq!(symbol.name(), "wasm-function[1]");
+        assert_eq!(symbol.func_index(), 1);
+        assert_eq!(lookup_jit_symbol(0), None);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "artifact-signing")]
+    #[test]
+    fn signed_serialization() -> Result<()> {
+        let keypair = |seed: u8| {
+            let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+            let public = PublicKey::from(&secret);
+            Keypair { secret, public }
+        };
+        let store = Store::default();
+        let module = Module::new(&store, "(module $signed)")?;
+
+        let signer = ArtifactKeys::new().with_signing_keypair(keypair(1));
+        let signed = module.serialize_signed(&signer)?;
+        let trusted = ArtifactKeys::new().with_trusted_key(keypair(1).public);
+        let deserialized = unsafe { Module::deserialize_signed(&store, &signed, &trusted)? };
+        assert_eq!(deserialized.name(), Some("signed"));
+
+        let untrusted = ArtifactKeys::new().with_trusted_key(keypair(2).public);
+        assert!(matches!(
+            unsafe { Module::deserialize_signed(&store, &signed, &untrusted) },
+            Err(DeserializeError::InvalidSignature(_))
+        ));
+
+        let mut tampered = signed.clone();
+        tampered[100] ^= 1;
+        assert!(matches!(
+            unsafe { Module::deserialize_signed(&store, &tampered, &trusted) },
+            Err(DeserializeError::InvalidSignature(_))
+        ));
+
+        let unsigned = module.serialize()?;
+        assert!(matches!(
+            unsafe { Module::deserialize_signed(&store, &unsigned, &trusted) },
+            Err(DeserializeError::InvalidSignature(_))
+        ));
+        assert!(module.serialize_signed(&trusted).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cranelift")]
+    #[test]
+    fn module_tags() -> Result<()> {
+        let mut features = Features::default();
+        features.exceptions = true;
+        let engine = Universal::new(Cranelift::default())
+            .features(features)
+            .engine();
+        let store = Store::new_with_engine(&engine);
+        let module = Module::new(
+            &store,
+            r#"
+(module
+  (import "host" "error" (tag (param i32)))
+  (tag $t (param i64 f32))
+  (export "t" (tag $t)))
+"#,
+        )?;
+
+        assert_eq!(
+            module.imports().collect::<Vec<_>>(),
+            vec![ImportType::new(
+                "host",
+                "error",
+                ExternType::Tag(TagType::new([Type::I32]))
+            )]
+        );
+        assert_eq!(
+            module.exports().collect::<Vec<_>>(),
+            vec![ExportType::new(
+                "t",
+                ExternType::Tag(TagType::new([Type::I64, Type::F32]))
+            )]
+        );
+
+        Ok(())
+    }
 }
@@ -498,4 +498,49 @@ mod sys {
 
         Ok(())
     }
+
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    #[test]
+    fn extern_ref_roots_and_drop_callbacks() -> Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let store = Store::default();
+        let wat = r#"(module
+    (global $global (export "global") (mut externref) (ref.null extern))
+    (table $table (export "table") 2 2 externref))"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        assert!(instance.externref_roots().is_empty());
+
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let new_extern_ref = |value: u32| {
+            let dropped = dropped.clone();
+            ExternRef::new_with_drop_callback(value, move |value| {
+                dropped.fetch_add(value as usize, Ordering::SeqCst);
+            })
+        };
+
+        let global: &Global = instance.exports.get_global("global")?;
+        global.set(Val::ExternRef(new_extern_ref(1)))?;
+        let table: &Table = instance.exports.get_table("table")?;
+        table.set(1, Val::ExternRef(new_extern_ref(2)))?;
+
+        let mut roots = instance
+            .externref_roots()
+            .iter()
+            .map(|root| *root.downcast::<u32>().unwrap())
+            .collect::<Vec<_>>();
+        roots.sort_unstable();
+        assert_eq!(roots, vec![1, 2]);
+        assert_eq!(dropped.load(Ordering::SeqCst), 0);
+
+        table.set(1, Val::ExternRef(ExternRef::null()))?;
+        assert_eq!(dropped.load(Ordering::SeqCst), 2);
+        global.set(Val::ExternRef(ExternRef::null()))?;
+        assert_eq!(dropped.load(Ordering::SeqCst), 3);
+        assert!(instance.externref_roots().is_empty());
+
+        Ok(())
+    }
 }
@@ -7,6 +7,10 @@
 //! error's length with [`wasmer_last_error_length`], and then reading
 //! the actual error with [`wasmer_last_error_message`].
 //!
+//! The kind of the error can be read with [`wasmer_last_error_code`],
+//! which returns a stable [`wasmer_error_code_t`], so that bindings
+//! can raise typed exceptions without parsing the message.
+//!
 //! # Example
 //!
 //! ```rust
@@ -27,6 +31,7 @@
 //!
 //!     // There is an error!
 //!     assert(error_length > 0);
+//!     assert(wasmer_last_error_code() == WASMER_ERROR_COMPILE);
 //!
 //!     char *error_message = malloc(error_length);
 //!     wasmer_last_error_message(error_message, error_length);
@@ -34,6 +39,7 @@
 //!
 //!     // Side note: The error has now been cleared on the Rust side!
 //!     assert(wasmer_last_error_length() == 0);
+//!     assert(wasmer_last_error_code() == WASMER_ERROR_NONE);
 //!
 //!     // Free everything.
 //!     free(error_message);
@@ -52,9 +58,94 @@ use std::cell::RefCell;
 use std::fmt::Display;
 use std::ptr::{self, NonNull};
 use std::slice;
+use wasmer_api::RuntimeError;
+use wasmer_types::TrapCode;
 
 thread_local! {
-    static LAST_ERROR: RefCell<Option<String>> = RefCell::new(None);
+    static LAST_ERROR: RefCell<Option<(wasmer_error_code_t, String)>> = RefCell::new(None);
+}
+
+/// The kind of the last error, see [`wasmer_last_error_code`].
+///
+/// The values are stable: new kinds get new values, and existing
+/// values are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub enum wasmer_error_code_t {
+    /// There is no error.
+    WASMER_ERROR_NONE = 0,
+    /// An error without a more specific kind.
+    WASMER_ERROR_GENERIC = 1,
+    /// A module failed to be parsed, validated, compiled or
+    /// deserialized.
+    WASMER_ERROR_COMPILE = 2,
+    /// The imports of a module failed to be linked.
+    WASMER_ERROR_LINK = 3,
+    /// An instance failed to be created, for another reason than a
+    /// link error or a trap.
+    WASMER_ERROR_INSTANTIATION = 4,
+    /// A host function raised an error.
+    WASMER_ERROR_RUNTIME = 5,
+    /// A WASI program exited.
+    WASMER_ERROR_WASI_EXIT = 6,
+    /// The call stack was exhausted.
+    WASMER_ERROR_TRAP_STACK_OVERFLOW = 100,
+    /// A memory was accessed out of bounds.
+    WASMER_ERROR_TRAP_HEAP_ACCESS_OUT_OF_BOUNDS = 101,
+    /// A misaligned memory access.
+    WASMER_ERROR_TRAP_HEAP_MISALIGNED = 102,
+    /// A table was accessed out of bounds.
+    WASMER_ERROR_TRAP_TABLE_ACCESS_OUT_OF_BOUNDS = 103,
+    /// A memory or a table was initialized or copied out of bounds.
+    WASMER_ERROR_TRAP_OUT_OF_BOUNDS = 104,
+    /// An indirect call to a null table entry.
+    WASMER_ERROR_TRAP_INDIRECT_CALL_TO_NULL = 105,
+    /// An indirect call with a mismatching signature.
+    WASMER_ERROR_TRAP_BAD_SIGNATURE = 106,
+    /// An integer arithmetic operation overflowed.
+    WASMER_ERROR_TRAP_INTEGER_OVERFLOW = 107,
+    /// An integer division by zero.
+    WASMER_ERROR_TRAP_INTEGER_DIVISION_BY_ZERO = 108,
+    /// A failed float-to-int conversion.
+    WASMER_ERROR_TRAP_BAD_CONVERSION_TO_INTEGER = 109,
+    /// An `unreachable` instruction was executed.
+    WASMER_ERROR_TRAP_UNREACHABLE_CODE_REACHED = 110,
+    /// An atomic memory access was misaligned.
+    WASMER_ERROR_TRAP_UNALIGNED_ATOMIC = 111,
+}
+
+impl From<TrapCode> for wasmer_error_code_t {
+    fn from(trap_code: TrapCode) -> Self {
+        match trap_code {
+            TrapCode::StackOverflow => Self::WASMER_ERROR_TRAP_STACK_OVERFLOW,
+            TrapCode::HeapAccessOutOfBounds => Self::WASMER_ERROR_TRAP_HEAP_ACCESS_OUT_OF_BOUNDS,
+            TrapCode::HeapMisaligned => Self::WASMER_ERROR_TRAP_HEAP_MISALIGNED,
+            TrapCode::TableAccessOutOfBounds => Self::WASMER_ERROR_TRAP_TABLE_ACCESS_OUT_OF_BOUNDS,
+            TrapCode::OutOfBounds => Self::WASMER_ERROR_TRAP_OUT_OF_BOUNDS,
+            TrapCode::IndirectCallToNull => Self::WASMER_ERROR_TRAP_INDIRECT_CALL_TO_NULL,
+            TrapCode::BadSignature => Self::WASMER_ERROR_TRAP_BAD_SIGNATURE,
+            TrapCode::IntegerOverflow => Self::WASMER_ERROR_TRAP_INTEGER_OVERFLOW,
+            TrapCode::IntegerDivisionByZero => Self::WASMER_ERROR_TRAP_INTEGER_DIVISION_BY_ZERO,
+            TrapCode::BadConversionToInteger => Self::WASMER_ERROR_TRAP_BAD_CONVERSION_TO_INTEGER,
+            TrapCode::UnreachableCodeReached => Self::WASMER_ERROR_TRAP_UNREACHABLE_CODE_REACHED,
+            TrapCode::UnalignedAtomic => Self::WASMER_ERROR_TRAP_UNALIGNED_ATOMIC,
+        }
+    }
+}
+
+impl From<&RuntimeError> for wasmer_error_code_t {
+    fn from(error: &RuntimeError) -> Self {
+        #[cfg(feature = "wasi")]
+        if let Some(wasmer_wasi::WasiError::Exit(_)) = error.downcast_ref() {
+            return Self::WASMER_ERROR_WASI_EXIT;
+        }
+
+        error
+            .clone()
+            .to_trap()
+            .map_or(Self::WASMER_ERROR_RUNTIME, Into::into)
+    }
 }
 
 /// Rust function to register a new error.
@@ -67,14 +158,43 @@ thread_local! {
 /// update_last_error("Hello, World!");
 /// ```
 pub fn update_last_error<E: Display>(err: E) {
+    update_last_error_with_code(wasmer_error_code_t::WASMER_ERROR_GENERIC, err);
+}
+
+/// Registers a new error of the given kind.
+pub(crate) fn update_last_error_with_code<E: Display>(code: wasmer_error_code_t, err: E) {
     LAST_ERROR.with(|prev| {
-        *prev.borrow_mut() = Some(err.to_string());
+        *prev.borrow_mut() = Some((code, err.to_string()));
     });
 }
 
+/// Registers a trap raised by WebAssembly or a host function as the
+/// last error, so that its kind can be read with
+/// [`wasmer_last_error_code`].
+pub(crate) fn update_last_error_with_runtime_error(error: &RuntimeError) {
+    update_last_error_with_code(error.into(), error);
+}
+
 /// Retrieve the most recent error, clearing it in the process.
 pub(crate) fn take_last_error() -> Option<String> {
-    LAST_ERROR.with(|prev| prev.borrow_mut().take())
+    LAST_ERROR.with(|prev| prev.borrow_mut().take().map(|(_code, message)| message))
+}
+
+/// Gets the kind of the last error if any, `WASMER_ERROR_NONE`
+/// otherwise.
+///
+/// The error isn't cleared, so the code must be read before the
+/// message is read with [`wasmer_last_error_message`].
+///
+/// # Example
+///
+/// See this module's documentation to get a complete example.
+#[no_mangle]
+pub extern "C" fn wasmer_last_error_code() -> wasmer_error_code_t {
+    LAST_ERROR.with(|prev| match *prev.borrow() {
+        Some((code, _)) => code,
+        None => wasmer_error_code_t::WASMER_ERROR_NONE,
+    })
 }
 
 /// Gets the length in bytes of the last error if any, zero otherwise. This
@@ -90,7 +210,7 @@ pub(crate) fn take_last_error() -> Option<String> {
 #[no_mangle]
 pub extern "C" fn wasmer_last_error_length() -> c_int {
     LAST_ERROR.with(|prev| match *prev.borrow() {
-        Some(ref err) => err.len() as c_int + 1,
+        Some((_, ref err)) => err.len() as c_int + 1,
         None => 0,
     })
 }
@@ -156,3 +276,92 @@ pub unsafe extern "C" fn wasmer_last_error_message(
 
     error_message.len() as c_int + 1
 }
+
+#[cfg(test)]
+mod tests {
+    use inline_c::assert_c;
+
+    #[test]
+    fn test_last_error_code() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            wasm_module_t* new_module(wasm_store_t* store, const char* source) {
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(&wat, source);
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+
+                return module;
+            }
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                assert(wasmer_last_error_code() == WASMER_ERROR_NONE);
+
+                // An invalid module.
+                wasm_byte_vec_t bytes;
+                wasmer_byte_vec_new_from_string(&bytes, "foobar");
+                assert(!wasm_module_new(store, &bytes));
+                assert(wasmer_last_error_code() == WASMER_ERROR_COMPILE);
+                wasm_byte_vec_delete(&bytes);
+
+                // Reading the message clears the error.
+                char message[512];
+                assert(wasmer_last_error_message(message, sizeof(message)) > 0);
+                assert(wasmer_last_error_code() == WASMER_ERROR_NONE);
+
+                // A missing import.
+                wasm_module_t* module = new_module(store, "(module (import \"env\" \"missing\" (func)))");
+                assert(module);
+
+                wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+                assert(!wasm_instance_new(store, module, &imports, NULL));
+                assert(wasmer_last_error_code() == WASMER_ERROR_LINK);
+                wasm_module_delete(module);
+
+                // A trap.
+                module = new_module(
+                    store,
+                    "(module\n"
+                    "  (func (export \"divide\") (param i32) (result i32)\n"
+                    "    (i32.div_u (i32.const 1) (local.get 0))))"
+                );
+                assert(module);
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                const wasm_func_t* divide = wasm_extern_as_func(exports.data[0]);
+
+                wasm_val_t arguments_data[1] = { WASM_I32_VAL(0) };
+                wasm_val_vec_t arguments = WASM_ARRAY_VEC(arguments_data);
+                wasm_val_t results_data[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_data);
+
+                wasm_trap_t* trap = wasm_func_call(divide, &arguments, &results);
+                assert(trap);
+                assert(wasmer_last_error_code() == WASMER_ERROR_TRAP_INTEGER_DIVISION_BY_ZERO);
+
+                wasm_trap_delete(trap);
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_module_delete(module);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+}
@@ -1,8 +1,11 @@
+use super::super::host_info::HostInfo;
+use super::super::instance::wasm_instance_t;
 use super::super::store::wasm_store_t;
 use super::super::trap::wasm_trap_t;
 use super::super::types::{wasm_functype_t, wasm_valkind_enum};
 use super::super::value::{wasm_val_inner, wasm_val_t, wasm_val_vec_t};
 use super::CApiExternTag;
+use crate::error::update_last_error_with_runtime_error;
 use std::convert::TryInto;
 use std::ffi::c_void;
 use std::mem::MaybeUninit;
@@ -15,6 +18,9 @@ use wasmer_api::{Function, RuntimeError, Val};
 pub struct wasm_func_t {
     pub(crate) tag: CApiExternTag,
     pub(crate) inner: Box<Function>,
+    pub(crate) host_info: HostInfo,
+    /// The instance which exported this extern, if any.
+    pub(crate) instance: Option<Box<wasm_instance_t>>,
 }
 
 impl wasm_func_t {
@@ -22,10 +28,14 @@ impl wasm_func_t {
         Self {
             tag: CApiExternTag::Function,
             inner: Box::new(function),
+            host_info: HostInfo::default(),
+            instance: None,
         }
     }
 }
 
+wasm_impl_host_info!(func);
+
 #[allow(non_camel_case_types)]
 pub type wasm_func_callback_t = unsafe extern "C" fn(
     args: &wasm_val_vec_t,
@@ -215,7 +225,11 @@ pub unsafe extern "C" fn wasm_func_call(
 
             None
         }
-        Err(e) => Some(Box::new(e.into())),
+        Err(e) => {
+            update_last_error_with_runtime_error(&e);
+
+            Some(Box::new(wasm_trap_t::new_in(e, func.instance.clone())))
+        }
     }
 }
 
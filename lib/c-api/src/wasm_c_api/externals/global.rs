@@ -1,3 +1,5 @@
+use super::super::host_info::HostInfo;
+use super::super::instance::wasm_instance_t;
 use super::super::store::wasm_store_t;
 use super::super::types::wasm_globaltype_t;
 use super::super::value::wasm_val_t;
@@ -12,6 +14,9 @@ use wasmer_api::{Global, Val};
 pub struct wasm_global_t {
     pub(crate) tag: CApiExternTag,
     pub(crate) inner: Box<Global>,
+    pub(crate) host_info: HostInfo,
+    /// The instance which exported this extern, if any.
+    pub(crate) instance: Option<Box<wasm_instance_t>>,
 }
 
 impl wasm_global_t {
@@ -19,10 +24,14 @@ impl wasm_global_t {
         Self {
             tag: CApiExternTag::Global,
             inner: Box::new(global),
+            host_info: HostInfo::default(),
+            instance: None,
         }
     }
 }
 
+wasm_impl_host_info!(global);
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_global_new(
     store: Option<&wasm_store_t>,
@@ -50,8 +59,8 @@ pub unsafe extern "C" fn wasm_global_delete(_global: Option<Box<wasm_global_t>>)
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_global_copy(global: &wasm_global_t) -> Box<wasm_global_t> {
-    // do shallow copy
-    Box::new(wasm_global_t::new((&*global.inner).clone()))
+    // do shallow copy, sharing the host info
+    Box::new(global.clone())
 }
 
 #[no_mangle]
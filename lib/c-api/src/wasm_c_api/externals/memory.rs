@@ -1,3 +1,5 @@
+use super::super::host_info::HostInfo;
+use super::super::instance::wasm_instance_t;
 use super::super::store::wasm_store_t;
 use super::super::types::wasm_memorytype_t;
 use super::CApiExternTag;
@@ -9,6 +11,9 @@ use wasmer_api::{Memory, Pages};
 pub struct wasm_memory_t {
     pub(crate) tag: CApiExternTag,
     pub(crate) inner: Box<Memory>,
+    pub(crate) host_info: HostInfo,
+    /// The instance which exported this extern, if any.
+    pub(crate) instance: Option<Box<wasm_instance_t>>,
 }
 
 impl wasm_memory_t {
@@ -16,10 +21,14 @@ impl wasm_memory_t {
         Self {
             tag: CApiExternTag::Memory,
             inner: Box::new(memory),
+            host_info: HostInfo::default(),
+            instance: None,
         }
     }
 }
 
+wasm_impl_host_info!(memory);
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_memory_new(
     store: Option<&wasm_store_t>,
@@ -39,8 +48,8 @@ pub unsafe extern "C" fn wasm_memory_delete(_memory: Option<Box<wasm_memory_t>>)
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_memory_copy(memory: &wasm_memory_t) -> Box<wasm_memory_t> {
-    // do shallow copy
-    Box::new(wasm_memory_t::new((&*memory.inner).clone()))
+    // do shallow copy, sharing the host info
+    Box::new(memory.clone())
 }
 
 #[no_mangle]
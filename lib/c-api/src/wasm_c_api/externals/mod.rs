@@ -3,6 +3,7 @@ mod global;
 mod memory;
 mod table;
 
+use super::instance::wasm_instance_t;
 pub use function::*;
 pub use global::*;
 pub use memory::*;
@@ -87,6 +88,18 @@ impl wasm_extern_t {
             CApiExternTag::Table => ExternType::Table(unsafe { *self.inner.table.inner.ty() }),
         }
     }
+
+    /// Records the instance which exported this extern.
+    pub(crate) fn set_instance(&mut self, instance: &wasm_instance_t) {
+        let instance = Some(Box::new(instance.clone()));
+
+        match self.get_tag() {
+            CApiExternTag::Function => unsafe { (*self.inner.function).instance = instance },
+            CApiExternTag::Memory => unsafe { (*self.inner.memory).instance = instance },
+            CApiExternTag::Global => unsafe { (*self.inner.global).instance = instance },
+            CApiExternTag::Table => unsafe { (*self.inner.table).instance = instance },
+        }
+    }
 }
 
 impl Clone for wasm_extern_t {
@@ -139,6 +152,7 @@ impl From<Extern> for wasm_extern_t {
                     global: mem::ManuallyDrop::new(wasm_global_t::new(global)),
                 },
             },
+            Extern::Tag(_) => unimplemented!("tags are not supported by the C API"),
         }
     }
 }
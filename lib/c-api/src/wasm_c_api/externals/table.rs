@@ -1,3 +1,5 @@
+use super::super::host_info::HostInfo;
+use super::super::instance::wasm_instance_t;
 use super::super::store::wasm_store_t;
 use super::super::types::{wasm_ref_t, wasm_table_size_t, wasm_tabletype_t};
 use super::CApiExternTag;
@@ -9,6 +11,9 @@ use wasmer_api::Table;
 pub struct wasm_table_t {
     pub(crate) tag: CApiExternTag,
     pub(crate) inner: Box<Table>,
+    pub(crate) host_info: HostInfo,
+    /// The instance which exported this extern, if any.
+    pub(crate) instance: Option<Box<wasm_instance_t>>,
 }
 
 impl wasm_table_t {
@@ -16,10 +21,14 @@ impl wasm_table_t {
         Self {
             tag: CApiExternTag::Table,
             inner: Box::new(table),
+            host_info: HostInfo::default(),
+            instance: None,
         }
     }
 }
 
+wasm_impl_host_info!(table);
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_table_new(
     _store: Option<&wasm_store_t>,
@@ -34,8 +43,8 @@ pub unsafe extern "C" fn wasm_table_delete(_table: Option<Box<wasm_table_t>>) {}
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_table_copy(table: &wasm_table_t) -> Box<wasm_table_t> {
-    // do shallow copy
-    Box::new(wasm_table_t::new((&*table.inner).clone()))
+    // do shallow copy, sharing the host info
+    Box::new(table.clone())
 }
 
 #[no_mangle]
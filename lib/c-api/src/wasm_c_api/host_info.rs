@@ -0,0 +1,65 @@
+use std::ffi::c_void;
+use std::fmt;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+#[allow(non_camel_case_types)]
+pub type wasm_host_info_finalizer_t = unsafe extern "C" fn(*mut c_void);
+
+/// The host data attached to a reference with
+/// `wasm_<name>_set_host_info{,_with_finalizer}`.
+///
+/// It is shared with the copies of the reference, and its finalizer
+/// runs when it is replaced, or when the reference and all its copies
+/// are deleted.
+#[derive(Clone, Default)]
+pub(crate) struct HostInfo {
+    data: Arc<Mutex<Option<HostInfoData>>>,
+}
+
+struct HostInfoData {
+    info: *mut c_void,
+    finalizer: Option<wasm_host_info_finalizer_t>,
+}
+
+/// The host data is only handed back to the host, which is responsible
+/// for its synchronization.
+unsafe impl Send for HostInfoData {}
+
+impl Drop for HostInfoData {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer {
+            unsafe { finalizer(self.info) }
+        }
+    }
+}
+
+impl HostInfo {
+    pub(crate) fn get(&self) -> *mut c_void {
+        self.data
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(ptr::null_mut(), |data| data.info)
+    }
+
+    pub(crate) fn set(&self, info: *mut c_void, finalizer: Option<wasm_host_info_finalizer_t>) {
+        let previous = self
+            .data
+            .lock()
+            .unwrap()
+            .replace(HostInfoData { info, finalizer });
+
+        // The previous finalizer runs without the lock held, so that it
+        // can use the reference.
+        drop(previous);
+    }
+}
+
+impl fmt::Debug for HostInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostInfo")
+            .field("info", &self.get())
+            .finish()
+    }
+}
@@ -1,42 +1,54 @@
-use super::externals::wasm_extern_vec_t;
+use super::externals::{wasm_extern_t, wasm_extern_vec_t};
+use super::host_info::HostInfo;
 use super::module::wasm_module_t;
 use super::store::wasm_store_t;
 use super::trap::wasm_trap_t;
+use crate::error::{
+    update_last_error_with_code, update_last_error_with_runtime_error, wasmer_error_code_t,
+};
 use std::sync::Arc;
-use wasmer_api::{Extern, Instance, InstantiationError};
+use wasmer_api::{Extern, Instance, InstantiationError, Store, StoreObject};
 
 /// Opaque type representing a WebAssembly instance.
 #[allow(non_camel_case_types)]
+#[derive(Clone, Debug)]
 pub struct wasm_instance_t {
     pub(crate) inner: Arc<Instance>,
+    pub(crate) host_info: HostInfo,
 }
 
+wasm_impl_host_info!(instance);
+
 /// Creates a new instance from a WebAssembly module and a
 /// set of imports.
 ///
 /// ## Errors
 ///
-/// The function can fail in 2 ways:
+/// The function can fail in 3 ways:
 ///
-/// 1. Link errors that happen when plugging the imports into the
+/// 1. The module or the imports come from a store with another
+///    engine, which is reported as a link error,
+/// 2. Link errors that happen when plugging the imports into the
 ///    instance,
-/// 2. Runtime errors that happen when running the module `start`
+/// 3. Runtime errors that happen when running the module `start`
 ///    function.
 ///
-/// The failure is stored in the `trap` argument; the program doesn't
-/// panic.
+/// The runtime errors are stored in the `trap` argument, and the other
+/// errors can be read with `wasmer_last_error_message`; the program
+/// doesn't panic.
 ///
 /// # Notes
 ///
-/// The `store` argument is ignored. The store from the given module
-/// will be used.
+/// The store from the given module is used, and `store`, if given, must
+/// have the same engine. A module can be shared with the stores of
+/// other threads with `wasm_module_share` and `wasm_module_obtain`.
 ///
 /// # Example
 ///
 /// See the module's documentation.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_instance_new(
-    _store: Option<&wasm_store_t>,
+    store: Option<&wasm_store_t>,
     module: Option<&wasm_module_t>,
     imports: Option<&wasm_extern_vec_t>,
     trap: Option<&mut *mut wasm_trap_t>,
@@ -45,6 +57,17 @@ pub unsafe extern "C" fn wasm_instance_new(
     let imports = imports?;
 
     let wasm_module = &module.inner;
+    if let Some(store) = store {
+        if !Store::same(&store.inner, wasm_module.store()) {
+            update_last_error_with_code(
+                wasmer_error_code_t::WASMER_ERROR_INSTANTIATION,
+                "the module comes from a store with another engine than the given store",
+            );
+
+            return None;
+        }
+    }
+
     let module_imports = wasm_module.imports();
     let module_import_count = module_imports.len();
     let externs = imports
@@ -54,16 +77,33 @@ pub unsafe extern "C" fn wasm_instance_new(
         .take(module_import_count)
         .collect::<Vec<Extern>>();
 
+    if let Some(index) = externs
+        .iter()
+        .position(|extern_| !extern_.comes_from_same_store(wasm_module.store()))
+    {
+        update_last_error_with_code(
+            wasmer_error_code_t::WASMER_ERROR_LINK,
+            format!(
+                "import #{} comes from a store with another engine than the module",
+                index
+            ),
+        );
+
+        return None;
+    }
+
     let instance = match Instance::new_by_index(wasm_module, &externs) {
         Ok(instance) => Arc::new(instance),
 
         Err(InstantiationError::Link(link_error)) => {
-            crate::error::update_last_error(link_error);
+            update_last_error_with_code(wasmer_error_code_t::WASMER_ERROR_LINK, link_error);
 
             return None;
         }
 
         Err(InstantiationError::Start(runtime_error)) => {
+            update_last_error_with_runtime_error(&runtime_error);
+
             if let Some(trap) = trap {
                 let this_trap: Box<wasm_trap_t> = Box::new(runtime_error.into());
                 *trap = Box::into_raw(this_trap);
@@ -72,20 +112,29 @@ pub unsafe extern "C" fn wasm_instance_new(
             return None;
         }
 
+        Err(e @ InstantiationError::Compile(_)) => {
+            update_last_error_with_code(wasmer_error_code_t::WASMER_ERROR_COMPILE, e);
+
+            return None;
+        }
+
         Err(e @ InstantiationError::CpuFeature(_)) => {
-            crate::error::update_last_error(e);
+            update_last_error_with_code(wasmer_error_code_t::WASMER_ERROR_INSTANTIATION, e);
 
             return None;
         }
 
         Err(InstantiationError::HostEnvInitialization(error)) => {
-            crate::error::update_last_error(error);
+            update_last_error_with_code(wasmer_error_code_t::WASMER_ERROR_INSTANTIATION, error);
 
             return None;
         }
     };
 
-    Some(Box::new(wasm_instance_t { inner: instance }))
+    Some(Box::new(wasm_instance_t {
+        inner: instance,
+        host_info: HostInfo::default(),
+    }))
 }
 
 /// Deletes an instance.
@@ -185,11 +234,16 @@ pub unsafe extern "C" fn wasm_instance_exports(
     // own
     out: &mut wasm_extern_vec_t,
 ) {
-    let instance = &instance.inner;
     let extern_vec = instance
+        .inner
         .exports
         .iter()
-        .map(|(_name, r#extern)| Some(Box::new(r#extern.clone().into())))
+        .map(|(_name, r#extern)| {
+            let mut r#extern: wasm_extern_t = r#extern.clone().into();
+            r#extern.set_instance(instance);
+
+            Some(Box::new(r#extern))
+        })
         .collect();
 
     out.set_buffer(extern_vec);
@@ -295,4 +349,118 @@ mod tests {
         })
         .success();
     }
+
+    #[test]
+    fn test_host_info_finalizers() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            static int finalized = 0;
+
+            void finalize(void* info) {
+                finalized += *(int*) info;
+            }
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(&wat, "(module (memory (export \"memory\") 1))");
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                wasm_memory_t* memory = wasm_extern_as_memory(exports.data[0]);
+
+                int one = 1, ten = 10, hundred = 100, thousand = 1000;
+                wasm_module_set_host_info_with_finalizer(module, &one, finalize);
+                wasm_instance_set_host_info_with_finalizer(instance, &ten, finalize);
+                wasm_memory_set_host_info_with_finalizer(memory, &hundred, finalize);
+                assert(wasm_memory_get_host_info(memory) == &hundred);
+
+                // Replacing the host info finalizes the previous one.
+                wasm_memory_set_host_info_with_finalizer(memory, &thousand, finalize);
+                assert(finalized == 100);
+
+                // The host info of a copy is the same.
+                wasm_memory_t* copy = wasm_memory_copy(memory);
+                assert(wasm_memory_get_host_info(copy) == &thousand);
+                wasm_extern_vec_delete(&exports);
+                assert(finalized == 100);
+                wasm_memory_delete(copy);
+                assert(finalized == 1100);
+
+                wasm_instance_delete(instance);
+                assert(finalized == 1110);
+                wasm_module_delete(module);
+                assert(finalized == 1111);
+
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_instance_new_from_another_engine() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+                wasm_engine_t* other_engine = wasm_engine_new();
+                wasm_store_t* other_store = wasm_store_new(other_engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(&wat, "(module)");
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                // The module can't be used with the store of another
+                // engine.
+                wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+                assert(wasm_instance_new(other_store, module, &imports, NULL) == NULL);
+                assert(wasmer_last_error_length() > 0);
+
+                // But it can be obtained for it once shared.
+                wasm_shared_module_t* shared_module = wasm_module_share(module);
+                wasm_module_t* other_module = wasm_module_obtain(other_store, shared_module);
+                assert(other_module);
+                wasm_instance_t* instance = wasm_instance_new(other_store, other_module, &imports, NULL);
+                assert(instance);
+
+                wasm_instance_delete(instance);
+                wasm_module_delete(other_module);
+                wasm_shared_module_delete(shared_module);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(other_store);
+                wasm_engine_delete(other_engine);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
 }
@@ -296,6 +296,36 @@ macro_rules! wasm_impl_copy_delete {
     };
 }
 
+macro_rules! wasm_impl_host_info {
+    ($name:ident) => {
+        paste::paste! {
+            #[no_mangle]
+            pub extern "C" fn [<wasm_ $name _get_host_info>](
+                object: &[<wasm_ $name _t>],
+            ) -> *mut std::ffi::c_void {
+                object.host_info.get()
+            }
+
+            #[no_mangle]
+            pub extern "C" fn [<wasm_ $name _set_host_info>](
+                object: &mut [<wasm_ $name _t>],
+                info: *mut std::ffi::c_void,
+            ) {
+                object.host_info.set(info, None);
+            }
+
+            #[no_mangle]
+            pub extern "C" fn [<wasm_ $name _set_host_info_with_finalizer>](
+                object: &mut [<wasm_ $name _t>],
+                info: *mut std::ffi::c_void,
+                finalizer: Option<crate::wasm_c_api::host_info::wasm_host_info_finalizer_t>,
+            ) {
+                object.host_info.set(info, finalizer);
+            }
+        }
+    };
+}
+
 macro_rules! c_try {
     ($expr:expr; otherwise $return:expr) => {{
         let res: Result<_, _> = $expr;
@@ -307,6 +337,19 @@ macro_rules! c_try {
             }
         }
     }};
+    ($expr:expr; code $code:ident) => {{
+        let res: Result<_, _> = $expr;
+        match res {
+            Ok(val) => val,
+            Err(err) => {
+                crate::error::update_last_error_with_code(
+                    crate::error::wasmer_error_code_t::$code,
+                    err,
+                );
+                return None;
+            }
+        }
+    }};
     ($expr:expr) => {{
         c_try!($expr; otherwise None)
     }};
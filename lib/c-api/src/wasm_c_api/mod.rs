@@ -64,6 +64,11 @@ pub mod engine;
 /// cbindgen:ignore
 pub mod externals;
 
+/// Host data attached to the references.
+///
+/// cbindgen:ignore
+mod host_info;
+
 /// A WebAssembly instance is a stateful, executable instance of a
 /// WebAssembly module.
 ///
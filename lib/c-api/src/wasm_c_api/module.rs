@@ -1,16 +1,20 @@
+use super::host_info::HostInfo;
 use super::store::wasm_store_t;
 use super::types::{wasm_byte_vec_t, wasm_exporttype_vec_t, wasm_importtype_vec_t};
-use crate::error::update_last_error;
+use crate::error::{update_last_error_with_code, wasmer_error_code_t};
 use std::ptr::NonNull;
 use std::sync::Arc;
-use wasmer_api::Module;
+use wasmer_api::{Module, Store};
 
 /// Opaque type representing a WebAssembly module.
 #[allow(non_camel_case_types)]
 pub struct wasm_module_t {
     pub(crate) inner: Arc<Module>,
+    pub(crate) host_info: HostInfo,
 }
 
+wasm_impl_host_info!(module);
+
 /// A WebAssembly module contains stateless WebAssembly code that has
 /// already been compiled and can be instantiated multiple times.
 ///
@@ -33,10 +37,12 @@ pub unsafe extern "C" fn wasm_module_new(
     let store = store?;
     let bytes = bytes?;
 
-    let module = c_try!(Module::from_binary(&store.inner, bytes.as_slice()));
+    let module =
+        c_try!(Module::from_binary(&store.inner, bytes.as_slice()); code WASMER_ERROR_COMPILE);
 
     Some(Box::new(wasm_module_t {
         inner: Arc::new(module),
+        host_info: HostInfo::default(),
     }))
 }
 
@@ -48,6 +54,137 @@ pub unsafe extern "C" fn wasm_module_new(
 #[no_mangle]
 pub unsafe extern "C" fn wasm_module_delete(_module: Option<Box<wasm_module_t>>) {}
 
+/// Opaque type representing a WebAssembly module which can be sent to
+/// other threads, see [`wasm_module_share`].
+#[allow(non_camel_case_types)]
+pub struct wasm_shared_module_t {
+    pub(crate) inner: Arc<Module>,
+}
+
+/// Deletes a shared WebAssembly module.
+///
+/// # Example
+///
+/// See [`wasm_module_share`].
+#[no_mangle]
+pub unsafe extern "C" fn wasm_shared_module_delete(_module: Option<Box<wasm_shared_module_t>>) {}
+
+/// Shares a WebAssembly module, so that it can be sent to another
+/// thread and obtained there with [`wasm_module_obtain`].
+///
+/// The module isn't copied.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Compile a module in a store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (func (export \"forty_two\") (result i32) i32.const 42))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     // Share it, and obtain it in the store of another engine, as
+///     // another thread would.
+///     wasm_shared_module_t* shared_module = wasm_module_share(module);
+///     assert(shared_module);
+///
+///     wasm_engine_t* other_engine = wasm_engine_new();
+///     wasm_store_t* other_store = wasm_store_new(other_engine);
+///     wasm_module_t* other_module = wasm_module_obtain(other_store, shared_module);
+///     assert(other_module);
+///
+///     // Instantiate it, and call its export.
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_instance_t* instance = wasm_instance_new(other_store, other_module, &imports, NULL);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     assert(exports.size == 1);
+///     const wasm_func_t* forty_two = wasm_extern_as_func(exports.data[0]);
+///
+///     wasm_val_t arguments[0] = {};
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///     assert(wasm_func_call(forty_two, &arguments_as_array, &results_as_array) == NULL);
+///     assert(results[0].of.i32 == 42);
+///
+///     // Free everything.
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(other_module);
+///     wasm_store_delete(other_store);
+///     wasm_engine_delete(other_engine);
+///     wasm_shared_module_delete(shared_module);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasm_module_share(
+    module: &wasm_module_t,
+) -> Option<Box<wasm_shared_module_t>> {
+    Some(Box::new(wasm_shared_module_t {
+        inner: module.inner.clone(),
+    }))
+}
+
+/// Obtains a WebAssembly module shared with [`wasm_module_share`], for
+/// `store`.
+///
+/// If the store has been created from the same engine as the store of
+/// the shared module, e.g. with a handle returned by
+/// `wasmer_engine_clone_handle`, the compiled module is reused.
+/// Otherwise, the module is serialized and deserialized in `store`,
+/// which fails if the engines aren't compatible; the error can then be
+/// read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See [`wasm_module_share`].
+#[no_mangle]
+pub unsafe extern "C" fn wasm_module_obtain(
+    store: Option<&wasm_store_t>,
+    shared_module: Option<&wasm_shared_module_t>,
+) -> Option<Box<wasm_module_t>> {
+    let store = store?;
+    let shared_module = shared_module?;
+
+    let module = if Store::same(&store.inner, shared_module.inner.store()) {
+        shared_module.inner.clone()
+    } else {
+        let artifact = c_try!(shared_module.inner.serialize());
+
+        Arc::new(c_try!(Module::deserialize(&store.inner, &artifact); code WASMER_ERROR_COMPILE))
+    };
+
+    Some(Box::new(wasm_module_t {
+        inner: module,
+        host_info: HostInfo::default(),
+    }))
+}
+
 /// Validates a new WebAssembly module given the configuration
 /// in the [store][super::store].
 ///
@@ -104,7 +241,7 @@ pub unsafe extern "C" fn wasm_module_validate(
     };
 
     if let Err(error) = Module::validate(&store.inner, bytes.as_slice()) {
-        update_last_error(error);
+        update_last_error_with_code(wasmer_error_code_t::WASMER_ERROR_COMPILE, error);
 
         false
     } else {
@@ -465,11 +602,13 @@ pub unsafe extern "C" fn wasm_module_deserialize(
 ) -> Option<NonNull<wasm_module_t>> {
     let bytes = bytes?;
 
-    let module = c_try!(Module::deserialize(&store.inner, bytes.as_slice()));
+    let module =
+        c_try!(Module::deserialize(&store.inner, bytes.as_slice()); code WASMER_ERROR_COMPILE);
 
     Some(NonNull::new_unchecked(Box::into_raw(Box::new(
         wasm_module_t {
             inner: Arc::new(module),
+            host_info: HostInfo::default(),
         },
     ))))
 }
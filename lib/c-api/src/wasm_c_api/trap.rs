@@ -1,17 +1,38 @@
+use super::instance::wasm_instance_t;
 use super::store::wasm_store_t;
 use super::types::{wasm_byte_vec_t, wasm_frame_t, wasm_frame_vec_t, wasm_message_t};
 use std::ffi::CString;
-use wasmer_api::RuntimeError;
+use wasmer_api::{FrameInfo, RuntimeError};
 
 // opaque type which is a `RuntimeError`
 #[allow(non_camel_case_types)]
 pub struct wasm_trap_t {
     pub(crate) inner: RuntimeError,
+    /// The instance of the function whose call raised the trap, if
+    /// known. It is attached to the frames of its module.
+    pub(crate) instance: Option<Box<wasm_instance_t>>,
+}
+
+impl wasm_trap_t {
+    pub(crate) fn new_in(error: RuntimeError, instance: Option<Box<wasm_instance_t>>) -> Self {
+        Self {
+            inner: error,
+            instance,
+        }
+    }
+
+    fn frame(&self, info: &FrameInfo) -> wasm_frame_t {
+        let instance = self.instance.as_ref().filter(|instance| {
+            instance.inner.module().name().unwrap_or("<module>") == info.module_name()
+        });
+
+        wasm_frame_t::new(info.clone(), instance.cloned())
+    }
 }
 
 impl From<RuntimeError> for wasm_trap_t {
     fn from(other: RuntimeError) -> Self {
-        Self { inner: other }
+        Self::new_in(other, None)
     }
 }
 
@@ -123,7 +144,10 @@ pub unsafe extern "C" fn wasm_trap_message(
 /// Gets the origin frame attached to the trap.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_trap_origin(trap: &wasm_trap_t) -> Option<Box<wasm_frame_t>> {
-    trap.inner.trace().first().map(Into::into).map(Box::new)
+    trap.inner
+        .trace()
+        .first()
+        .map(|info| Box::new(trap.frame(info)))
 }
 
 /// Gets the trace (as a list of frames) attached to the trap.
@@ -137,7 +161,7 @@ pub unsafe extern "C" fn wasm_trap_trace(
     out.set_buffer(
         frames
             .iter()
-            .map(|frame| Some(Box::new(frame.into())))
+            .map(|info| Some(Box::new(trap.frame(info))))
             .collect(),
     );
 }
@@ -209,4 +233,84 @@ mod tests {
         })
         .success();
     }
+
+    #[test]
+    fn test_trap_frames() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (func (export \"run\")\n"
+                    "    call $fail)\n"
+                    "  (func $fail\n"
+                    "    unreachable))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+                wasm_trap_t* trap = NULL;
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                assert(exports.size == 1);
+
+                const wasm_func_t* run = wasm_extern_as_func(exports.data[0]);
+                assert(run);
+
+                wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_EMPTY_VEC;
+                trap = wasm_func_call(run, &arguments, &results);
+                assert(trap);
+
+                // The trap happens in `$fail`, called by `run`.
+                wasm_frame_t* origin = wasm_trap_origin(trap);
+                assert(origin);
+                assert(wasm_frame_func_index(origin) == 1);
+                assert(wasm_frame_module_offset(origin) > wasm_frame_func_offset(origin));
+                assert(wasm_frame_instance(origin) != NULL);
+
+                wasm_frame_t* copy = wasm_frame_copy(origin);
+                assert(wasm_frame_func_index(copy) == 1);
+                assert(wasm_frame_func_offset(copy) == wasm_frame_func_offset(origin));
+                assert(wasm_frame_module_offset(copy) == wasm_frame_module_offset(origin));
+                assert(wasm_frame_instance(copy) != NULL);
+
+                wasm_frame_vec_t trace;
+                wasm_trap_trace(trap, &trace);
+                assert(trace.size == 2);
+                assert(wasm_frame_func_index(trace.data[0]) == 1);
+                assert(wasm_frame_func_index(trace.data[1]) == 0);
+                assert(wasm_frame_instance(trace.data[1]) != NULL);
+
+                wasm_frame_vec_delete(&trace);
+                wasm_frame_delete(copy);
+                wasm_frame_delete(origin);
+                wasm_trap_delete(trap);
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
 }
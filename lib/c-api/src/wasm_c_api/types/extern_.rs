@@ -32,6 +32,8 @@ impl From<&ExternType> for wasm_externkind_enum {
             ExternType::Global(_) => Self::WASM_EXTERN_GLOBAL,
             ExternType::Table(_) => Self::WASM_EXTERN_TABLE,
             ExternType::Memory(_) => Self::WASM_EXTERN_MEMORY,
+            // The C API doesn't enable the exception handling proposal.
+            ExternType::Tag(_) => unimplemented!("tags are not supported by the C API"),
         }
     }
 }
@@ -66,6 +68,7 @@ impl wasm_externtype_t {
                 ExternType::Memory(memory_type) => {
                     WasmExternType::Memory(WasmMemoryType::new(memory_type))
                 }
+                ExternType::Tag(_) => unimplemented!("tags are not supported by the C API"),
             },
         }
     }
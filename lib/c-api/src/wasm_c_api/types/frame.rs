@@ -5,6 +5,13 @@ use wasmer_api::FrameInfo;
 #[derive(Debug, Clone)]
 pub struct wasm_frame_t {
     info: FrameInfo,
+    instance: Option<Box<wasm_instance_t>>,
+}
+
+impl wasm_frame_t {
+    pub(crate) fn new(info: FrameInfo, instance: Option<Box<wasm_instance_t>>) -> Self {
+        Self { info, instance }
+    }
 }
 
 impl<'a> From<&'a FrameInfo> for wasm_frame_t {
@@ -15,7 +22,7 @@ impl<'a> From<&'a FrameInfo> for wasm_frame_t {
 
 impl From<FrameInfo> for wasm_frame_t {
     fn from(other: FrameInfo) -> Self {
-        Self { info: other }
+        Self::new(other, None)
     }
 }
 
@@ -28,8 +35,11 @@ pub unsafe extern "C" fn wasm_frame_copy(frame: &wasm_frame_t) -> Box<wasm_frame
 pub unsafe extern "C" fn wasm_frame_delete(_frame: Option<Box<wasm_frame_t>>) {}
 
 #[no_mangle]
-pub unsafe extern "C" fn wasm_frame_instance(_frame: &wasm_frame_t) -> *const wasm_instance_t {
-    std::ptr::null()
+pub unsafe extern "C" fn wasm_frame_instance(frame: &wasm_frame_t) -> *const wasm_instance_t {
+    frame
+        .instance
+        .as_deref()
+        .map_or(std::ptr::null(), |instance| instance as *const _)
 }
 
 #[no_mangle]
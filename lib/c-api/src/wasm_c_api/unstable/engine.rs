@@ -3,7 +3,7 @@
 
 #[cfg(feature = "compiler")]
 use super::super::engine::wasmer_compiler_t;
-use super::super::engine::{wasm_config_t, wasmer_engine_t};
+use super::super::engine::{wasm_config_t, wasm_engine_t, wasmer_engine_t};
 
 use super::features::wasmer_features_t;
 use super::target_lexicon::wasmer_target_t;
@@ -164,6 +164,79 @@ pub extern "C" fn wasmer_is_engine_available(engine: wasmer_engine_t) -> bool {
     matches!(engine, wasmer_engine_t::UNIVERSAL if cfg!(feature = "universal"))
 }
 
+/// Unstable non-standard Wasmer-specific API to get another handle to
+/// the same engine, which can be sent to another thread.
+///
+/// An engine is thread-safe: stores can be created from its handles in
+/// several threads, while a store, and the objects created in it, must
+/// be used by one thread at a time. A module compiled in a store can
+/// be instantiated in the stores of the same engine, see
+/// `wasm_module_share`.
+///
+/// The engine is freed when all its handles are deleted with
+/// `wasm_engine_delete`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine, and another handle to it.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_engine_t* handle = wasmer_engine_clone_handle(engine);
+///     assert(handle);
+///
+///     // Compile a module in a store of the first handle.
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module)");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///     wasm_shared_module_t* shared_module = wasm_module_share(module);
+///
+///     // Instantiate it in a store of the other handle, as another
+///     // thread would.
+///     wasm_store_t* other_store = wasm_store_new(handle);
+///     wasm_module_t* other_module = wasm_module_obtain(other_store, shared_module);
+///     assert(other_module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_instance_t* instance = wasm_instance_new(other_store, other_module, &imports, NULL);
+///     assert(instance);
+///
+///     // Free everything.
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(other_module);
+///     wasm_store_delete(other_store);
+///     wasm_shared_module_delete(shared_module);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(handle);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_engine_clone_handle(engine: &wasm_engine_t) -> Box<wasm_engine_t> {
+    Box::new(wasm_engine_t {
+        inner: engine.inner.clone(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use inline_c::assert_c;
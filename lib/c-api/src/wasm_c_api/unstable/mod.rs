@@ -6,5 +6,7 @@ pub mod module;
 #[cfg(feature = "compiler")]
 pub mod parser;
 pub mod target_lexicon;
+#[cfg(feature = "compiler")]
+pub mod validation;
 #[cfg(feature = "wasi")]
 pub mod wasi;
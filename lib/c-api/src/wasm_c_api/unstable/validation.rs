@@ -0,0 +1,270 @@
+//! Unstable non-standard Wasmer-specific API to validate a module
+//! with detailed diagnostics.
+
+use super::super::{store::wasm_store_t, types::wasm_byte_vec_t, types::wasm_name_t};
+use wasmer_api::wasmparser::{
+    BinaryReaderError, Parser, Payload, ValidPayload, Validator, WasmFeatures,
+};
+use wasmer_api::Module;
+
+/// Unstable non-standard type describing why a module is invalid,
+/// returned by `wasmer_module_validate_verbose`.
+///
+/// The `section` and `message` fields are owned by this type.
+#[allow(non_camel_case_types)]
+#[derive(Clone)]
+pub struct wasmer_diagnostic_t {
+    offset: usize,
+    section: wasm_name_t,
+    message: wasm_name_t,
+}
+
+wasm_declare_boxed_vec!(diagnostic, wasmer);
+
+/// See the `cbindgen_hack` module in `unstable/wasi.rs` to learn why
+/// the symbols generated by `wasm_declare_boxed_vec!` are declared
+/// again here.
+#[doc(hidden)]
+#[cfg(__cbindgen_hack__ = "yes")]
+mod __cbindgen_hack__ {
+    use super::*;
+
+    #[repr(C)]
+    pub struct wasmer_diagnostic_vec_t {
+        pub size: usize,
+        pub data: *mut *mut wasmer_diagnostic_t,
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_diagnostic_vec_new(
+        out: *mut wasmer_diagnostic_vec_t,
+        length: usize,
+        init: *const *mut wasmer_diagnostic_t,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_diagnostic_vec_new_uninitialized(
+        out: *mut wasmer_diagnostic_vec_t,
+        length: usize,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_diagnostic_vec_copy(
+        out_ptr: &mut wasmer_diagnostic_vec_t,
+        in_ptr: &wasmer_diagnostic_vec_t,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_diagnostic_vec_delete(
+        ptr: Option<&mut wasmer_diagnostic_vec_t>,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_diagnostic_vec_new_empty(out: *mut wasmer_diagnostic_vec_t) {
+        unimplemented!()
+    }
+}
+
+/// Non-standard function to get the offset in bytes, from the start
+/// of the module, where a `wasmer_diagnostic_t` has been found.
+#[no_mangle]
+pub extern "C" fn wasmer_diagnostic_offset(diagnostic: Option<&wasmer_diagnostic_t>) -> usize {
+    diagnostic.map_or(0, |diagnostic| diagnostic.offset)
+}
+
+/// Non-standard function to get the name of the section where a
+/// `wasmer_diagnostic_t` has been found, e.g. `type`, `code` or
+/// `data`. It is empty if the section is unknown.
+///
+/// The returned value isn't owned by the caller.
+#[no_mangle]
+pub extern "C" fn wasmer_diagnostic_section(
+    diagnostic: Option<&wasmer_diagnostic_t>,
+) -> Option<&wasm_name_t> {
+    Some(&diagnostic?.section)
+}
+
+/// Non-standard function to get the message of a
+/// `wasmer_diagnostic_t`.
+///
+/// The returned value isn't owned by the caller.
+#[no_mangle]
+pub extern "C" fn wasmer_diagnostic_message(
+    diagnostic: Option<&wasmer_diagnostic_t>,
+) -> Option<&wasm_name_t> {
+    Some(&diagnostic?.message)
+}
+
+/// Unstable non-standard Wasmer-specific API to validate a module
+/// like `wasm_module_validate`, and to describe why it's invalid.
+///
+/// The function returns `true` if the module is valid. Otherwise,
+/// it returns `false` and `out` receives the diagnostics. The
+/// validation stops at the first error, so there is one diagnostic
+/// for now.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition, whose
+///     // function returns nothing instead of an `i32`.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (func (result i32)))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Validate the module.
+///     wasmer_diagnostic_vec_t diagnostics;
+///     assert(!wasmer_module_validate_verbose(store, &wasm, &diagnostics));
+///     assert(diagnostics.size == 1);
+///
+///     // The error is in the code section.
+///     const wasmer_diagnostic_t* diagnostic = diagnostics.data[0];
+///     wasmer_assert_name(wasmer_diagnostic_section(diagnostic), "code");
+///     assert(wasmer_diagnostic_offset(diagnostic) > 0);
+///     assert(wasmer_diagnostic_message(diagnostic)->size > 0);
+///
+///     // Free everything.
+///     wasmer_diagnostic_vec_delete(&diagnostics);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_module_validate_verbose(
+    store: Option<&wasm_store_t>,
+    bytes: Option<&wasm_byte_vec_t>,
+    // own
+    out: &mut wasmer_diagnostic_vec_t,
+) -> bool {
+    let (store, bytes) = match (store, bytes) {
+        (Some(store), Some(bytes)) => (store, bytes.as_slice()),
+        _ => return false,
+    };
+
+    // The engine decides whether the module is valid, with its own
+    // features.
+    let error = match Module::validate(&store.inner, bytes) {
+        Ok(()) => {
+            out.set_buffer(Vec::new());
+
+            return true;
+        }
+        Err(error) => error,
+    };
+
+    // The engine reports the error as a string, so the module is
+    // validated again to locate it. Everything is enabled, so a
+    // module using a feature disabled by the engine is valid here,
+    // and the diagnostic isn't located.
+    let diagnostic = locate(bytes).unwrap_or_else(|| wasmer_diagnostic_t {
+        offset: 0,
+        section: String::new().into(),
+        message: error.to_string().into(),
+    });
+
+    out.set_buffer(vec![Some(Box::new(diagnostic))]);
+
+    false
+}
+
+/// Validates `bytes` with all the features enabled, and describes the
+/// first error.
+fn locate(bytes: &[u8]) -> Option<wasmer_diagnostic_t> {
+    let mut validator = Validator::new();
+    validator.wasm_features(WasmFeatures {
+        bulk_memory: true,
+        threads: true,
+        reference_types: true,
+        multi_value: true,
+        simd: true,
+        tail_call: true,
+        module_linking: true,
+        multi_memory: true,
+        memory64: true,
+        exceptions: true,
+        deterministic_only: false,
+        extended_const: true,
+        relaxed_simd: true,
+        mutable_global: true,
+        saturating_float_to_int: true,
+        sign_extension: true,
+    });
+
+    let mut section = "";
+    let diagnostic = |section: &str, error: BinaryReaderError| wasmer_diagnostic_t {
+        offset: error.offset(),
+        section: section.to_string().into(),
+        message: error.message().to_string().into(),
+    };
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        let payload = match payload {
+            Ok(payload) => payload,
+            Err(error) => return Some(diagnostic(section, error)),
+        };
+        section = section_name(&payload);
+
+        let result = match validator.payload(&payload) {
+            Ok(ValidPayload::Func(mut validator, body)) => validator.validate(&body),
+            Ok(_) => Ok(()),
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = result {
+            return Some(diagnostic(section, error));
+        }
+    }
+
+    None
+}
+
+fn section_name(payload: &Payload) -> &'static str {
+    match payload {
+        Payload::Version { .. } => "header",
+        Payload::TypeSection(_) => "type",
+        Payload::ImportSection(_) => "import",
+        Payload::FunctionSection(_) => "function",
+        Payload::TableSection(_) => "table",
+        Payload::MemorySection(_) => "memory",
+        Payload::TagSection(_) => "tag",
+        Payload::GlobalSection(_) => "global",
+        Payload::ExportSection(_) => "export",
+        Payload::StartSection { .. } => "start",
+        Payload::ElementSection(_) => "element",
+        Payload::DataCountSection { .. } => "datacount",
+        Payload::DataSection(_) => "data",
+        Payload::CodeSectionStart { .. } | Payload::CodeSectionEntry(_) => "code",
+        Payload::CustomSection { .. } => "custom",
+        Payload::InstanceSection(_)
+        | Payload::AliasSection(_)
+        | Payload::ModuleSectionStart { .. }
+        | Payload::ModuleSectionEntry { .. } => "module linking",
+        Payload::UnknownSection { .. } | Payload::End => "",
+    }
+}
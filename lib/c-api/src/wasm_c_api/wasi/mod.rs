@@ -4,7 +4,7 @@
 
 pub use super::unstable::wasi::wasi_get_unordered_imports;
 use super::{
-    externals::{wasm_extern_vec_t, wasm_func_t},
+    externals::{wasm_extern_vec_t, wasm_func_t, wasm_memory_t},
     instance::wasm_instance_t,
     module::wasm_module_t,
     store::wasm_store_t,
@@ -12,9 +12,13 @@ use super::{
 use crate::error::update_last_error;
 use std::convert::TryFrom;
 use std::ffi::CStr;
+use std::fs::File;
+use std::io::Write;
 use std::os::raw::c_char;
+use std::path::PathBuf;
 use std::slice;
-use wasmer_api::{Exportable, Extern};
+use wasmer_api::{Exportable, Extern, Store};
+use wasmer_vfs::host_fs;
 use wasmer_wasi::{
     generate_import_object_from_env, get_wasi_version, Pipe, WasiEnv, WasiFile, WasiState,
     WasiStateBuilder, WasiVersion,
@@ -26,6 +30,8 @@ pub struct wasi_config_t {
     inherit_stdout: bool,
     inherit_stderr: bool,
     inherit_stdin: bool,
+    stdout_file: Option<Box<dyn WasiFile + Send + Sync + 'static>>,
+    stdin_bytes: Option<Vec<u8>>,
     state_builder: WasiStateBuilder,
 }
 
@@ -42,6 +48,8 @@ pub unsafe extern "C" fn wasi_config_new(
         inherit_stdout: true,
         inherit_stderr: true,
         inherit_stdin: true,
+        stdout_file: None,
+        stdin_bytes: None,
         state_builder: WasiState::new(prog_name),
     }))
 }
@@ -130,14 +138,97 @@ pub unsafe extern "C" fn wasi_config_mapdir(
     true
 }
 
+/// Preopen the host directory `dir` as `alias`, with the given
+/// permissions, like [`wasi_config_mapdir`] does with all of them.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_preopen_dir_with_alias(
+    config: &mut wasi_config_t,
+    dir: *const c_char,
+    alias: *const c_char,
+    read: bool,
+    write: bool,
+    create: bool,
+) -> bool {
+    let dir_cstr = CStr::from_ptr(dir);
+    let dir_bytes = dir_cstr.to_bytes();
+    let dir_str = match std::str::from_utf8(dir_bytes) {
+        Ok(dir_str) => dir_str,
+        Err(e) => {
+            update_last_error(e);
+            return false;
+        }
+    };
+
+    let alias_cstr = CStr::from_ptr(alias);
+    let alias_bytes = alias_cstr.to_bytes();
+    let alias_str = match std::str::from_utf8(alias_bytes) {
+        Ok(alias_str) => alias_str,
+        Err(e) => {
+            update_last_error(e);
+            return false;
+        }
+    };
+
+    let result = config.state_builder.preopen(|p| {
+        p.directory(dir_str)
+            .alias(alias_str)
+            .read(read)
+            .write(write)
+            .create(create)
+    });
+    if let Err(e) = result {
+        update_last_error(e);
+        return false;
+    }
+
+    true
+}
+
 #[no_mangle]
 pub extern "C" fn wasi_config_capture_stdout(config: &mut wasi_config_t) {
     config.inherit_stdout = false;
+    config.stdout_file = None;
 }
 
 #[no_mangle]
 pub extern "C" fn wasi_config_inherit_stdout(config: &mut wasi_config_t) {
     config.inherit_stdout = true;
+    config.stdout_file = None;
+}
+
+/// Redirect the standard output to the host file at `path`, which is
+/// created, or truncated if it exists.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_set_stdout_file(
+    config: &mut wasi_config_t,
+    path: *const c_char,
+) -> bool {
+    let path_cstr = CStr::from_ptr(path);
+    let path_bytes = path_cstr.to_bytes();
+    let path_str = match std::str::from_utf8(path_bytes) {
+        Ok(path_str) => path_str,
+        Err(e) => {
+            update_last_error(e);
+            return false;
+        }
+    };
+
+    let file = match File::create(path_str) {
+        Ok(file) => file,
+        Err(e) => {
+            update_last_error(format!("failed to open `{}`: {}", path_str, e));
+            return false;
+        }
+    };
+    config.stdout_file = Some(Box::new(host_fs::File::new(
+        file,
+        PathBuf::from(path_str),
+        false,
+        true,
+        false,
+    )));
+
+    true
 }
 
 #[no_mangle]
@@ -158,6 +249,25 @@ pub extern "C" fn wasi_config_inherit_stderr(config: &mut wasi_config_t) {
 #[no_mangle]
 pub extern "C" fn wasi_config_inherit_stdin(config: &mut wasi_config_t) {
     config.inherit_stdin = true;
+    config.stdin_bytes = None;
+}
+
+/// Make the standard input read the `buffer_len` bytes of `buffer`,
+/// which are copied, then reach its end.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_set_stdin_bytes(
+    config: &mut wasi_config_t,
+    buffer: *const c_char,
+    buffer_len: usize,
+) {
+    let bytes: &[u8] = if buffer_len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(buffer as *const u8, buffer_len)
+    };
+
+    config.inherit_stdin = false;
+    config.stdin_bytes = Some(bytes.to_vec());
 }
 
 #[allow(non_camel_case_types)]
@@ -171,7 +281,9 @@ pub struct wasi_env_t {
 /// It take ownership over the `wasi_config_t`.
 #[no_mangle]
 pub extern "C" fn wasi_env_new(mut config: Box<wasi_config_t>) -> Option<Box<wasi_env_t>> {
-    if !config.inherit_stdout {
+    if let Some(stdout_file) = config.stdout_file.take() {
+        config.state_builder.stdout(stdout_file);
+    } else if !config.inherit_stdout {
         config.state_builder.stdout(Box::new(Pipe::new()));
     }
 
@@ -179,7 +291,11 @@ pub extern "C" fn wasi_env_new(mut config: Box<wasi_config_t>) -> Option<Box<was
         config.state_builder.stderr(Box::new(Pipe::new()));
     }
 
-    // TODO: impl capturer for stdin
+    if let Some(stdin_bytes) = config.stdin_bytes.take() {
+        let mut stdin = Pipe::new();
+        c_try!(stdin.write_all(&stdin_bytes));
+        config.state_builder.stdin(Box::new(stdin));
+    }
 
     let wasi_state = c_try!(config.state_builder.build());
 
@@ -192,6 +308,56 @@ pub extern "C" fn wasi_env_new(mut config: Box<wasi_config_t>) -> Option<Box<was
 #[no_mangle]
 pub extern "C" fn wasi_env_delete(_state: Option<Box<wasi_env_t>>) {}
 
+/// Set the memory of a [`wasi_env_t`], for modules which import their
+/// memory instead of exporting it.
+///
+/// It must be called before `wasi_get_imports`, so that the WASI
+/// imports use the memory too.
+#[no_mangle]
+pub extern "C" fn wasi_env_set_memory(env: &mut wasi_env_t, memory: &wasm_memory_t) {
+    env.inner.set_memory((*memory.inner).clone());
+}
+
+/// Initialize a [`wasi_env_t`] with an instance created with the
+/// imports returned by `wasi_get_imports`.
+///
+/// The memory exported by the instance as `memory` becomes the memory
+/// of the environment. If the instance exports no memory, it must
+/// have been set with `wasi_env_set_memory`.
+///
+/// Returns `false` and sets the last error if the instance doesn't
+/// belong to `store`, or if no memory can be found.
+#[no_mangle]
+pub extern "C" fn wasi_env_initialize_instance(
+    env: &mut wasi_env_t,
+    store: &wasm_store_t,
+    instance: &wasm_instance_t,
+) -> bool {
+    wasi_env_initialize_instance_inner(env, store, instance).is_some()
+}
+
+fn wasi_env_initialize_instance_inner(
+    env: &mut wasi_env_t,
+    store: &wasm_store_t,
+    instance: &wasm_instance_t,
+) -> Option<()> {
+    if !Store::same(&store.inner, instance.inner.store()) {
+        update_last_error("the instance doesn't belong to the given store");
+        return None;
+    }
+
+    match instance.inner.exports.get_memory("memory") {
+        Ok(memory) => env.inner.set_memory(memory.clone()),
+        Err(_) if env.inner.memory_ref().is_some() => (),
+        Err(err) => {
+            update_last_error(err);
+            return None;
+        }
+    }
+
+    Some(())
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wasi_env_read_stdout(
     env: &mut wasi_env_t,
@@ -472,4 +638,136 @@ mod tests {
         })
         .success();
     }
+
+    #[test]
+    fn test_wasi_stdin_bytes() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasi_config_t* config = wasi_config_new("example_program");
+                wasi_config_set_stdin_bytes(config, "hello", 5);
+                wasi_config_capture_stdout(config);
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                // Copies up to 32 bytes of its standard input to its
+                // standard output.
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_unstable\" \"fd_read\" (func $fd_read (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (import \"wasi_unstable\" \"fd_write\" (func $fd_write (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (func (export \"_start\")\n"
+                    "    (i32.store (i32.const 0) (i32.const 64))\n"
+                    "    (i32.store (i32.const 4) (i32.const 32))\n"
+                    "    (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))\n"
+                    "    (i32.store (i32.const 4) (i32.load (i32.const 8)))\n"
+                    "    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_func_t* start = wasi_get_start_function(instance);
+                assert(start);
+
+                wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_EMPTY_VEC;
+                assert(wasm_func_call(start, &arguments, &results) == NULL);
+
+                char buffer[32] = {0};
+                assert(wasi_env_read_stdout(wasi_env, buffer, sizeof(buffer)) == 5);
+                assert(strcmp(buffer, "hello") == 0);
+
+                wasm_func_delete(start);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasi_env_delete(wasi_env);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_env_initialize_instance() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasi_config_t* config = wasi_config_new("example_program");
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                // The module exports no memory.
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_unstable\" \"proc_exit\" (func (param i32))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                // There is no memory to initialize the environment with.
+                assert(!wasi_env_initialize_instance(wasi_env, store, instance));
+                assert(wasmer_last_error_length() > 0);
+
+                // Until one is set.
+                wasm_limits_t limits = { .min = 1, .max = wasm_limits_max_default };
+                wasm_memorytype_t* memory_type = wasm_memorytype_new(&limits);
+                wasm_memory_t* memory = wasm_memory_new(store, memory_type);
+                assert(memory);
+
+                wasi_env_set_memory(wasi_env, memory);
+                assert(wasi_env_initialize_instance(wasi_env, store, instance));
+
+                wasm_memory_delete(memory);
+                wasm_memorytype_delete(memory_type);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasi_env_delete(wasi_env);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
 }
@@ -17,7 +17,10 @@ pub unsafe extern "C" fn wat2wasm(wat: &wasm_byte_vec_t, out: &mut wasm_byte_vec
     match wasmer_api::wat2wasm(wat.as_slice()) {
         Ok(val) => out.set_buffer(val.into_owned()),
         Err(err) => {
-            crate::error::update_last_error(err);
+            crate::error::update_last_error_with_code(
+                crate::error::wasmer_error_code_t::WASMER_ERROR_COMPILE,
+                err,
+            );
             out.data = std::ptr::null_mut();
             out.size = 0;
         }
@@ -111,6 +111,12 @@ impl Run {
                 let mut emscripten_globals = EmscriptenGlobals::new(module.store(), &module)
                     .map_err(|e| anyhow!("{}", e))?;
                 let mut em_env = EmEnv::new(&emscripten_globals.data, Default::default());
+                // A shared memory can only be compiled with `--enable-threads`.
+                if emscripten_globals.memory.ty().shared {
+                    em_env
+                        .enable_threads(module.store(), &module, &emscripten_globals)
+                        .map_err(|e| anyhow!("{}", e))?;
+                }
                 let import_object =
                     generate_emscripten_env(module.store(), &mut emscripten_globals, &em_env);
                 let mut instance = match Instance::new(&module, &import_object) {
@@ -277,6 +283,10 @@ impl Run {
                     DeserializeError::Io(_) => {
                         // Do not notify on IO errors
                     }
+                    DeserializeError::IncompatibleVersion { .. } => {
+                        // The cache was written by another Wasmer version,
+                        // silently recompile and overwrite it
+                    }
                     err => {
                         warning!("cached module is corrupted: {}", err);
                     }
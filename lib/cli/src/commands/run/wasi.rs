@@ -117,6 +117,10 @@ impl Wasi {
                         // We should exit with the provided exit code
                         std::process::exit(exit_code as _);
                     }
+                    Ok(WasiError::Signaled(signal)) => {
+                        // Like shells, report the signal as 128 + its number
+                        std::process::exit(128 + signal as i32);
+                    }
                     Ok(err) => err.into(),
                     Err(err) => err.into(),
                 };
@@ -123,6 +123,8 @@ impl Compiler for CraneliftCompiler {
                     &signatures,
                     &memory_styles,
                     &table_styles,
+                    self.config().bulk_memory_inline_threshold,
+                    compile_info.features.exceptions,
                 );
                 context.func.name = get_function_name(func_index);
                 context.func.signature = signatures[module.functions[func_index]].clone();
@@ -224,6 +226,8 @@ impl Compiler for CraneliftCompiler {
                     &signatures,
                     memory_styles,
                     table_styles,
+                    self.config().bulk_memory_inline_threshold,
+                    compile_info.features.exceptions,
                 );
                 context.func.name = get_function_name(func_index);
                 context.func.signature = signatures[module.functions[func_index]].clone();
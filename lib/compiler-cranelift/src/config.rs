@@ -34,6 +34,9 @@ pub struct Cranelift {
     enable_verifier: bool,
     enable_pic: bool,
     opt_level: CraneliftOptLevel,
+    /// The largest constant length of the `memory.copy` and
+    /// `memory.fill` operations to emit inline.
+    pub(crate) bulk_memory_inline_threshold: u32,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
@@ -47,6 +50,7 @@ impl Cranelift {
             enable_verifier: false,
             opt_level: CraneliftOptLevel::Speed,
             enable_pic: false,
+            bulk_memory_inline_threshold: 0,
             middlewares: vec![],
         }
     }
@@ -60,6 +64,18 @@ impl Cranelift {
         self
     }
 
+    /// Emit the `memory.copy` and `memory.fill` operations of a
+    /// constant length of at most `threshold` bytes as inline loads
+    /// and stores.
+    ///
+    /// Longer, or non-constant, operations call into the runtime, which
+    /// uses the host's vectorized `memmove` and `memset`. The default
+    /// threshold is 0, which never inlines them.
+    pub fn bulk_memory_inline_threshold(&mut self, threshold: u32) -> &mut Self {
+        self.bulk_memory_inline_threshold = threshold;
+        self
+    }
+
     /// The optimization levels when optimizing the IR.
     pub fn opt_level(&mut self, opt_level: CraneliftOptLevel) -> &mut Self {
         self.opt_level = opt_level;
@@ -199,6 +215,10 @@ impl CompilerConfig for Cranelift {
         self.enable_nan_canonicalization = enable;
     }
 
+    fn bulk_memory_inline_threshold(&mut self, threshold: u32) {
+        self.bulk_memory_inline_threshold = threshold;
+    }
+
     /// Transform it into the compiler
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(CraneliftCompiler::new(*self))
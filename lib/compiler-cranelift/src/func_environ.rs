@@ -20,7 +20,7 @@ use wasmer_types::VMBuiltinFunctionIndex;
 use wasmer_types::VMOffsets;
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo,
-    SignatureIndex, TableIndex, Type as WasmerType,
+    SignatureIndex, TableIndex, TagIndex, Type as WasmerType,
 };
 use wasmer_types::{MemoryStyle, TableStyle};
 use wasmer_types::{WasmError, WasmResult};
@@ -109,6 +109,33 @@ pub struct FuncEnvironment<'module_environment> {
 
     /// The external function signature for implementing reference decrement for `extern.ref`.
     externref_dec_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `throw`.
+    throw_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `rethrow`.
+    rethrow_sig: Option<ir::SigRef>,
+
+    /// The external function signature for checking whether an exception
+    /// is being thrown, after a call.
+    exception_pending_sig: Option<ir::SigRef>,
+
+    /// The external function signature for checking the tag of the
+    /// exception being thrown, for wasm's `catch`.
+    exception_matches_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `catch` and
+    /// `catch_all`.
+    exception_catch_sig: Option<ir::SigRef>,
+
+    /// The external function signature for getting the number of caught
+    /// exceptions.
+    exception_slots_sig: Option<ir::SigRef>,
+
+    /// The external function signature for releasing the exceptions caught
+    /// by the function, when it returns.
+    exception_release_sig: Option<ir::SigRef>,
+
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -117,6 +144,13 @@ pub struct FuncEnvironment<'module_environment> {
 
     /// The table styles
     table_styles: &'module_environment PrimaryMap<TableIndex, TableStyle>,
+
+    /// The largest constant length of the `memory.copy` and `memory.fill`
+    /// operations to emit inline.
+    bulk_memory_inline_threshold: u32,
+
+    /// Whether the exception handling proposal is enabled.
+    exceptions: bool,
 }
 
 impl<'module_environment> FuncEnvironment<'module_environment> {
@@ -126,6 +160,8 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         signatures: &'module_environment PrimaryMap<SignatureIndex, ir::Signature>,
         memory_styles: &'module_environment PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: &'module_environment PrimaryMap<TableIndex, TableStyle>,
+        bulk_memory_inline_threshold: u32,
+        exceptions: bool,
     ) -> Self {
         Self {
             target_config,
@@ -150,12 +186,66 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             table_fill_sig: None,
             externref_inc_sig: None,
             externref_dec_sig: None,
+            throw_sig: None,
+            rethrow_sig: None,
+            exception_pending_sig: None,
+            exception_matches_sig: None,
+            exception_catch_sig: None,
+            exception_slots_sig: None,
+            exception_release_sig: None,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             memory_styles,
             table_styles,
+            bulk_memory_inline_threshold,
+            exceptions,
         }
     }
 
+    /// Returns the length of a `memory.copy` or `memory.fill` operation
+    /// if it is a constant small enough to emit the operation inline.
+    fn inline_bulk_memory_len(&self, func: &Function, len: ir::Value) -> Option<u32> {
+        let inst = match func.dfg.value_def(len) {
+            ir::ValueDef::Result(inst, _) => inst,
+            ir::ValueDef::Param(..) => return None,
+        };
+        match func.dfg[inst] {
+            ir::InstructionData::UnaryImm {
+                opcode: ir::Opcode::Iconst,
+                imm,
+            } => {
+                // A zero length must still trap when the address is out
+                // of bounds, which the runtime takes care of, and so do
+                // the lengths which don't fit in a `u32`.
+                let len = u32::try_from(imm.bits()).ok()?;
+                if len != 0 && len <= self.bulk_memory_inline_threshold {
+                    Some(len)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Splits `len` bytes into the offsets and types of the widest
+    /// accesses covering them, starting with the highest address.
+    ///
+    /// Storing the highest address first means that an out-of-bounds
+    /// operation traps before writing anything, as the wasm semantics
+    /// require, even when the bounds checks rely on the guard pages.
+    fn bulk_memory_accesses(len: u32) -> Vec<(i32, ir::Type)> {
+        let mut accesses = vec![];
+        let mut offset = 0;
+        for &(size, ty) in &[(8, I64), (4, I32), (2, I16), (1, I8)] {
+            while len - offset >= size {
+                accesses.push((offset as i32, ty));
+                offset += size;
+            }
+        }
+        accesses.reverse();
+        accesses
+    }
+
     fn pointer_type(&self) -> ir::Type {
         self.target_config.pointer_type()
     }
@@ -735,6 +825,191 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         (sig, VMBuiltinFunctionIndex::get_data_drop_index())
     }
 
+    fn get_throw_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.throw_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Tag index.
+                    AbiParam::new(I32),
+                    // Values.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.throw_sig = Some(sig);
+        sig
+    }
+
+    fn get_throw_func(&mut self, func: &mut Function) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_throw_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_throw_index())
+    }
+
+    fn get_rethrow_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.rethrow_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                // Slot.
+                params: vec![AbiParam::new(I32)],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.rethrow_sig = Some(sig);
+        sig
+    }
+
+    fn get_rethrow_func(&mut self, func: &mut Function) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_rethrow_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_rethrow_index())
+    }
+
+    fn get_exception_pending_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_pending_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_pending_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_pending_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_pending_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_exception_pending_index())
+    }
+
+    fn get_exception_matches_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_matches_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Tag index.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_matches_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_matches_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_matches_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_exception_matches_index())
+    }
+
+    fn get_exception_catch_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_catch_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    // Slot.
+                    AbiParam::new(I32),
+                    // Values.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_catch_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_catch_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_catch_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_exception_catch_index())
+    }
+
+    fn get_exception_slots_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_slots_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_slots_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_slots_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_slots_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_exception_slots_index())
+    }
+
+    fn get_exception_release_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_release_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                // Slots.
+                params: vec![AbiParam::new(I32)],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_release_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_release_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_release_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_exception_release_index())
+    }
+
+    /// Returns the Cranelift types of the values of the exceptions thrown
+    /// with the tag `tag_index`, which can only be numbers.
+    fn tag_value_types(&self, tag_index: TagIndex) -> WasmResult<Vec<ir::Type>> {
+        let sig_index = self.module.tags[tag_index];
+        self.module.signatures[sig_index]
+            .params()
+            .iter()
+            .map(|ty| match ty {
+                WasmerType::I32 => Ok(I32),
+                WasmerType::I64 => Ok(I64),
+                WasmerType::F32 => Ok(F32),
+                WasmerType::F64 => Ok(F64),
+                WasmerType::V128 => Ok(I8X16),
+                WasmerType::ExternRef | WasmerType::FuncRef => Err(WasmError::Unsupported(
+                    format!("exceptions with values of type {}", ty),
+                )),
+            })
+            .collect()
+    }
+
+    /// Creates a stack slot for the values of an exception, 16 bytes per
+    /// value, and returns its address, or a null pointer if there are no
+    /// values.
+    fn exception_values_addr(&self, pos: &mut FuncCursor<'_>, len: usize) -> ir::Value {
+        if len == 0 {
+            return pos.ins().iconst(self.pointer_type(), 0);
+        }
+        let slot = pos.func.create_stack_slot(ir::StackSlotData::new(
+            ir::StackSlotKind::ExplicitSlot,
+            16 * len as u32,
+        ));
+        pos.ins().stack_addr(self.pointer_type(), slot, 0)
+    }
+
     /// Translates load of builtin function and returns a pair of values `vmctx`
     /// and address of the loaded function.
     fn translate_load_builtin_function_address(
@@ -1297,13 +1572,31 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         &mut self,
         mut pos: FuncCursor,
         src_index: MemoryIndex,
-        _src_heap: ir::Heap,
+        src_heap: ir::Heap,
         _dst_index: MemoryIndex,
-        _dst_heap: ir::Heap,
+        dst_heap: ir::Heap,
         dst: ir::Value,
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        if let Some(len) = self.inline_bulk_memory_len(pos.func, len) {
+            let addr_ty = self.pointer_type();
+            let src = pos.ins().heap_addr(addr_ty, src_heap, src, len);
+            let dst = pos.ins().heap_addr(addr_ty, dst_heap, dst, len);
+            let flags = ir::MemFlags::new();
+            let accesses = Self::bulk_memory_accesses(len);
+            // Load everything before storing anything, as the ranges may
+            // overlap.
+            let values = accesses
+                .iter()
+                .map(|&(offset, ty)| pos.ins().load(ty, flags, src, offset))
+                .collect::<Vec<_>>();
+            for (&(offset, _), value) in accesses.iter().zip(values) {
+                pos.ins().store(flags, value, dst, offset);
+            }
+            return Ok(());
+        }
+
         let (func_sig, src_index, func_idx) = self.get_memory_copy_func(pos.func, src_index);
 
         let src_index_arg = pos.ins().iconst(I32, src_index as i64);
@@ -1320,11 +1613,29 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         &mut self,
         mut pos: FuncCursor,
         memory_index: MemoryIndex,
-        _heap: ir::Heap,
+        heap: ir::Heap,
         dst: ir::Value,
         val: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        if let Some(len) = self.inline_bulk_memory_len(pos.func, len) {
+            let dst = pos.ins().heap_addr(self.pointer_type(), heap, dst, len);
+            let flags = ir::MemFlags::new();
+            // Repeat the byte in every byte of an `i64`.
+            let byte = pos.ins().band_imm(val, 0xff);
+            let byte = pos.ins().uextend(I64, byte);
+            let splat = pos.ins().imul_imm(byte, 0x0101_0101_0101_0101);
+            for (offset, ty) in Self::bulk_memory_accesses(len) {
+                let value = if ty == I64 {
+                    splat
+                } else {
+                    pos.ins().ireduce(ty, splat)
+                };
+                pos.ins().store(flags, value, dst, offset);
+            }
+            return Ok(());
+        }
+
         let (func_sig, memory_index, func_idx) = self.get_memory_fill_func(pos.func, memory_index);
 
         let memory_index_arg = pos.ins().iconst(I32, memory_index as i64);
@@ -1464,6 +1775,118 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(())
     }
 
+    fn exceptions(&self) -> bool {
+        self.exceptions
+    }
+
+    fn translate_throw(
+        &mut self,
+        mut pos: FuncCursor,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<()> {
+        self.tag_value_types(tag_index)?;
+        let (func_sig, func_idx) = self.get_throw_func(pos.func);
+
+        let values = self.exception_values_addr(&mut pos, args.len());
+        for (i, arg) in args.iter().enumerate() {
+            pos.ins()
+                .store(ir::MemFlags::trusted(), *arg, values, 16 * i as i32);
+        }
+        let tag_index_arg = pos.ins().iconst(I32, tag_index.as_u32() as i64);
+
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        pos.ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, tag_index_arg, values]);
+
+        Ok(())
+    }
+
+    fn translate_rethrow(&mut self, mut pos: FuncCursor, slot: ir::Value) -> WasmResult<()> {
+        let (func_sig, func_idx) = self.get_rethrow_func(pos.func);
+        let (_vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        pos.ins().call_indirect(func_sig, func_addr, &[slot]);
+
+        Ok(())
+    }
+
+    fn translate_exception_pending(&mut self, mut pos: FuncCursor) -> WasmResult<ir::Value> {
+        let (func_sig, func_idx) = self.get_exception_pending_func(pos.func);
+        let (_vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        let call_inst = pos.ins().call_indirect(func_sig, func_addr, &[]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn translate_exception_matches(
+        &mut self,
+        mut pos: FuncCursor,
+        tag_index: TagIndex,
+    ) -> WasmResult<ir::Value> {
+        let (func_sig, func_idx) = self.get_exception_matches_func(pos.func);
+
+        let tag_index_arg = pos.ins().iconst(I32, tag_index.as_u32() as i64);
+
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        let call_inst = pos
+            .ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, tag_index_arg]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn translate_exception_catch(
+        &mut self,
+        mut pos: FuncCursor,
+        slot: ir::Value,
+        tag_index: Option<TagIndex>,
+    ) -> WasmResult<Vec<ir::Value>> {
+        let types = match tag_index {
+            Some(tag_index) => self.tag_value_types(tag_index)?,
+            None => vec![],
+        };
+        let (func_sig, func_idx) = self.get_exception_catch_func(pos.func);
+
+        let values = self.exception_values_addr(&mut pos, types.len());
+
+        let (_vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        pos.ins()
+            .call_indirect(func_sig, func_addr, &[slot, values]);
+
+        Ok(types
+            .into_iter()
+            .enumerate()
+            .map(|(i, ty)| {
+                pos.ins()
+                    .load(ty, ir::MemFlags::trusted(), values, 16 * i as i32)
+            })
+            .collect())
+    }
+
+    fn translate_exception_slots(&mut self, mut pos: FuncCursor) -> WasmResult<ir::Value> {
+        let (func_sig, func_idx) = self.get_exception_slots_func(pos.func);
+        let (_vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        let call_inst = pos.ins().call_indirect(func_sig, func_addr, &[]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn translate_exception_release(
+        &mut self,
+        mut pos: FuncCursor,
+        slots: ir::Value,
+    ) -> WasmResult<()> {
+        let (func_sig, func_idx) = self.get_exception_release_func(pos.func);
+        let (_vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        pos.ins().call_indirect(func_sig, func_addr, &[slots]);
+
+        Ok(())
+    }
+
     fn translate_atomic_wait(
         &mut self,
         _pos: FuncCursor,
@@ -1525,6 +1948,11 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         self.module.signatures.get(sig_index)
     }
 
+    fn get_tag_sig(&self, tag_index: TagIndex) -> Option<&FunctionType> {
+        let sig_idx = self.module.tags.get(tag_index)?;
+        Some(&self.module.signatures[*sig_idx])
+    }
+
     fn translate_drop_locals(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
         // TODO: this allocation can be removed without too much effort but it will require
         //       maneuvering around the borrow checker
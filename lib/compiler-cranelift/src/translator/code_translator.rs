@@ -95,8 +95,8 @@ use std::vec::Vec;
 use wasmer_compiler::wasmparser::{MemoryImmediate, Operator, Type as WPType};
 use wasmer_compiler::{from_binaryreadererror_wasmerror, wasm_unsupported, ModuleTranslationState};
 use wasmer_types::{
-    FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, Type as WasmerType,
-    WasmResult,
+    FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, TagIndex,
+    Type as WasmerType, WasmResult,
 };
 
 // Clippy warns about "align: _" but its important to document that the align field is ignored
@@ -413,7 +413,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 _ => unreachable!(),
             }
         }
-        Operator::End => {
+        Operator::End | Operator::Delegate { .. } => {
             let frame = state.control_stack.pop().unwrap();
             let next_block = frame.following_code();
 
@@ -429,6 +429,8 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 // below.
             }
 
+            translate_try_end(op, &frame, builder, state);
+
             builder.switch_to_block(next_block);
             builder.seal_block(next_block);
 
@@ -583,28 +585,68 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 });
                 bitcast_arguments(return_args, &return_types, builder);
                 match environ.return_mode() {
-                    ReturnMode::NormalReturns => builder.ins().return_(return_args),
-                    ReturnMode::FallthroughReturn => canonicalise_then_jump(
-                        builder,
-                        br_destination,
-                        (&*return_args, &*return_args_metadata),
-                    ),
+                    ReturnMode::NormalReturns => {
+                        let return_inst = builder.ins().return_(return_args);
+                        state.return_insts.push(return_inst);
+                    }
+                    ReturnMode::FallthroughReturn => {
+                        canonicalise_then_jump(
+                            builder,
+                            br_destination,
+                            (&*return_args, &*return_args_metadata),
+                        );
+                    }
                 };
             }
             state.popn(return_count);
             state.reachable = false;
         }
-        /********************************** Exception handing **********************************/
-        Operator::Try { .. }
-        | Operator::Catch { .. }
-        | Operator::Throw { .. }
-        | Operator::Rethrow { .. }
-        | Operator::Delegate { .. }
-        | Operator::CatchAll => {
-            return Err(wasm_unsupported!(
-                "proposed exception handling operator {:?}",
-                op
-            ));
+        /********************************** Exception handing **********************************
+         *  An exception is thrown by recording it in the VM, then jumping to its handler: the
+         *  landing block of the innermost `try` whose body is being translated, or a block
+         *  returning from the function. Every call checks whether its callee threw an exception
+         *  the same way.
+         *
+         *  The `catch` clauses of a `try` are a chain of tests of the tag of the exception,
+         *  starting from its landing block, and the `end` or `delegate` of the `try` jumps from
+         *  the end of the chain to the handler of the enclosing frames. The caught exceptions
+         *  are kept by the VM in slots, one per level of nested `catch` clauses, following the
+         *  slots of the calling functions, for `rethrow`.
+         ***********************************************************************************/
+        Operator::Try { ty } => {
+            let (params, results) = module_translation_state.blocktype_params_results(*ty)?;
+            let next = block_with_params(builder, results, environ)?;
+            state.push_try(next, params.len(), results.len());
+        }
+        Operator::Catch { index } => {
+            translate_catch(Some(TagIndex::from_u32(*index)), builder, state, environ)?;
+        }
+        Operator::CatchAll => translate_catch(None, builder, state, environ)?,
+        Operator::Throw { index } => {
+            let tag_index = TagIndex::from_u32(*index);
+            let num_args = environ.get_tag_sig(tag_index).unwrap().params().len();
+            let (args, _args_metadata) = state.peekn(num_args);
+            environ.translate_throw(builder.cursor(), tag_index, args)?;
+            state.popn(num_args);
+            let handler = state.exception_handler(builder, state.control_stack.len());
+            builder.ins().jump(handler, &[]);
+            state.reachable = false;
+        }
+        Operator::Rethrow { relative_depth } => {
+            let i = state.control_stack.len() - 1 - (*relative_depth as usize);
+            let catch_slot = match state.control_stack[i] {
+                ControlStackFrame::Try {
+                    catch_slot: Some(catch_slot),
+                    ..
+                } => catch_slot,
+                _ => unreachable!(),
+            };
+            let base = state.get_exception_base(builder.func, environ)?;
+            let slot = builder.ins().iadd_imm(base, i64::from(catch_slot));
+            environ.translate_rethrow(builder.cursor(), slot)?;
+            let handler = state.exception_handler(builder, state.control_stack.len());
+            builder.ins().jump(handler, &[]);
+            state.reachable = false;
         }
         /************************************ Calls ****************************************
          * The call instructions pop off their arguments from the stack and append their
@@ -645,6 +687,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             }
             state.popn(num_args);
             state.pushn(inst_results, &results_metadata);
+            translate_exception_check(builder, state, environ)?;
         }
         Operator::CallIndirect { index, table_index } => {
             // `index` is the index of the function's signature and `table_index` is the index of
@@ -690,6 +733,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             }
             state.popn(num_args);
             state.pushn(inst_results, &results_metadata);
+            translate_exception_check(builder, state, environ)?;
         }
         /******************************* Memory management ***********************************
          * Memory management is handled by environment. It is usually translated into calls to
@@ -2129,6 +2173,13 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
         Operator::Loop { ty: _ } | Operator::Block { ty: _ } => {
             state.push_block(ir::Block::reserved_value(), 0, 0);
         }
+        Operator::Try { ty: _ } => {
+            state.push_try(ir::Block::reserved_value(), 0, 0);
+        }
+        Operator::Catch { index } => {
+            translate_catch(Some(TagIndex::from_u32(index)), builder, state, environ)?;
+        }
+        Operator::CatchAll => translate_catch(None, builder, state, environ)?,
         Operator::Else => {
             let i = state.control_stack.len() - 1;
             match state.control_stack[i] {
@@ -2177,10 +2228,13 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
                 _ => unreachable!(),
             }
         }
-        Operator::End => {
+        Operator::End | Operator::Delegate { .. } => {
+            let frame = state.control_stack.pop().unwrap();
+
+            // The exceptions thrown in a `try` still unwind to their handler.
+            translate_try_end(op, &frame, builder, state);
+
             let stack = &mut state.stack;
-            let control_stack = &mut state.control_stack;
-            let frame = control_stack.pop().unwrap();
 
             // Pop unused parameters from stack.
             frame.truncate_value_stack_to_original_size(stack);
@@ -2232,6 +2286,121 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
     Ok(())
 }
 
+/// Translates a `catch` clause catching the exceptions with the tag `tag_index`, or a
+/// `catch_all` one, of the innermost `try`.
+fn translate_catch<FE: FuncEnvironment + ?Sized>(
+    tag_index: Option<TagIndex>,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let i = state.control_stack.len() - 1;
+    if state.reachable {
+        // The body or the previous clause falls through to the code after the `try`.
+        let frame = &mut state.control_stack[i];
+        frame.set_branched_to_exit();
+        let return_count = frame.num_return_values();
+        let destination = frame.following_code();
+        canonicalise_then_jump(builder, destination, state.peekn(return_count));
+    }
+    state.control_stack[i].truncate_value_stack_to_original_size(&mut state.stack);
+
+    let slot = state.catch_slot(i);
+    let landing = match state.control_stack[i] {
+        ControlStackFrame::Try {
+            ref mut landing,
+            ref mut catch_slot,
+            ..
+        } => {
+            *catch_slot = Some(slot);
+            landing.take()
+        }
+        _ => unreachable!(),
+    };
+
+    // If nothing may throw in the body, nothing is caught either.
+    let landing = match landing {
+        Some(landing) => landing,
+        None => {
+            state.reachable = false;
+            return Ok(());
+        }
+    };
+    builder.switch_to_block(landing);
+    builder.seal_block(landing);
+
+    // The exceptions with other tags go on to the next clause.
+    if let Some(tag_index) = tag_index {
+        let matches = environ.translate_exception_matches(builder.cursor(), tag_index)?;
+        let catch_block = builder.create_block();
+        let next = builder.create_block();
+        builder.ins().brnz(matches, catch_block, &[]);
+        builder.ins().jump(next, &[]);
+        if let ControlStackFrame::Try {
+            ref mut landing, ..
+        } = state.control_stack[i]
+        {
+            *landing = Some(next);
+        }
+        builder.seal_block(catch_block);
+        builder.switch_to_block(catch_block);
+    }
+
+    let base = state.get_exception_base(builder.func, environ)?;
+    let slot = builder.ins().iadd_imm(base, i64::from(slot));
+    let values = environ.translate_exception_catch(builder.cursor(), slot, tag_index)?;
+    state.pushn(&values, &vec![ValueExtraInfo::default(); values.len()]);
+    state.reachable = true;
+    Ok(())
+}
+
+/// Translates the end of the landing block of a `try` frame ended by `op`: the exceptions its
+/// `catch` clauses don't catch unwind to the handler of the enclosing frames, or of the frame
+/// the `delegate` targets.
+fn translate_try_end(
+    op: &Operator,
+    frame: &ControlStackFrame,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+) {
+    if let ControlStackFrame::Try {
+        landing: Some(landing),
+        ..
+    } = *frame
+    {
+        let limit = match *op {
+            Operator::Delegate { relative_depth } => {
+                state.control_stack.len() - relative_depth as usize
+            }
+            _ => state.control_stack.len(),
+        };
+        builder.switch_to_block(landing);
+        builder.seal_block(landing);
+        let handler = state.exception_handler(builder, limit);
+        builder.ins().jump(handler, &[]);
+    }
+}
+
+/// Checks whether the callee of the call just translated threw an exception, and if it did,
+/// jumps to its handler.
+fn translate_exception_check<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    if !environ.exceptions() {
+        return Ok(());
+    }
+    let pending = environ.translate_exception_pending(builder.cursor())?;
+    let handler = state.exception_handler(builder, state.control_stack.len());
+    let next = builder.create_block();
+    builder.ins().brnz(pending, handler, &[]);
+    builder.ins().jump(next, &[]);
+    builder.seal_block(next);
+    builder.switch_to_block(next);
+    Ok(())
+}
+
 /// Get the address+offset to use for a heap access.
 fn get_heap_addr(
     heap: ir::Heap,
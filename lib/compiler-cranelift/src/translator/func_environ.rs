@@ -15,7 +15,7 @@ use cranelift_frontend::FunctionBuilder;
 use wasmer_compiler::wasmparser::{Operator, Type};
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, SignatureIndex,
-    TableIndex, Type as WasmerType, WasmResult,
+    TableIndex, TagIndex, Type as WasmerType, WasmResult,
 };
 
 /// The value of a WebAssembly global variable.
@@ -454,6 +454,62 @@ pub trait FuncEnvironment: TargetEnvironment {
         count: ir::Value,
     ) -> WasmResult<ir::Value>;
 
+    /// Returns whether the exception handling proposal is enabled, in which
+    /// case every call checks whether its callee threw an exception.
+    fn exceptions(&self) -> bool;
+
+    /// Translate a `throw` WebAssembly instruction, throwing an exception
+    /// with the tag `tag_index` and the values `args`.
+    ///
+    /// The exception is then unwound to its handler by the translated code.
+    fn translate_throw(
+        &mut self,
+        pos: FuncCursor,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<()>;
+
+    /// Translate a `rethrow` WebAssembly instruction, throwing the
+    /// exception caught in `slot` again.
+    fn translate_rethrow(&mut self, pos: FuncCursor, slot: ir::Value) -> WasmResult<()>;
+
+    /// Check whether an exception is being thrown, after a call.
+    ///
+    /// Returns an i32, which is non-zero if an exception is being thrown.
+    fn translate_exception_pending(&mut self, pos: FuncCursor) -> WasmResult<ir::Value>;
+
+    /// Check whether the exception being thrown has the tag `tag_index`,
+    /// for a `catch` WebAssembly instruction.
+    ///
+    /// Returns an i32, which is non-zero if the tag matches.
+    fn translate_exception_matches(
+        &mut self,
+        pos: FuncCursor,
+        tag_index: TagIndex,
+    ) -> WasmResult<ir::Value>;
+
+    /// Translate a `catch` WebAssembly instruction with the tag
+    /// `tag_index`, or a `catch_all` one without a tag, catching the
+    /// exception being thrown in `slot`.
+    ///
+    /// Returns the values of the exception.
+    fn translate_exception_catch(
+        &mut self,
+        pos: FuncCursor,
+        slot: ir::Value,
+        tag_index: Option<TagIndex>,
+    ) -> WasmResult<Vec<ir::Value>>;
+
+    /// Get the number of slots of the caught exceptions, which the slots
+    /// of the exceptions caught by the function follow.
+    ///
+    /// Returns an i32.
+    fn translate_exception_slots(&mut self, pos: FuncCursor) -> WasmResult<ir::Value>;
+
+    /// Release the exceptions caught by the function, from `slots` on,
+    /// when it returns.
+    fn translate_exception_release(&mut self, pos: FuncCursor, slots: ir::Value) -> WasmResult<()>;
+
     /// Emit code at the beginning of every wasm loop.
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
@@ -506,6 +562,10 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Get the type of a function with the given signature index.
     fn get_function_sig(&self, sig_index: SignatureIndex) -> Option<&FunctionType>;
 
+    /// Get the type of the tag at the given index, as a function type
+    /// whose parameters are the values of its exceptions.
+    fn get_tag_sig(&self, tag_index: TagIndex) -> Option<&FunctionType>;
+
     /// Drops all locals that need to be dropped. Useful for returning from functions.
     fn translate_drop_locals(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()>;
 }
@@ -11,7 +11,9 @@
 
 use super::func_environ::{FuncEnvironment, GlobalVariable};
 use crate::{HashMap, Occupied, Vacant};
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
 use cranelift_codegen::ir::{self, Block, Inst, Value};
+use cranelift_frontend::FunctionBuilder;
 use std::vec::Vec;
 use wasmer_types::{
     FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, WasmResult,
@@ -44,8 +46,8 @@ pub enum ElseData {
     },
 }
 
-/// A control stack frame can be an `if`, a `block` or a `loop`, each one having the following
-/// fields:
+/// A control stack frame can be an `if`, a `block`, a `loop` or a `try`, each one having the
+/// following fields:
 ///
 /// - `destination`: reference to the `Block` that will hold the code after the control block;
 /// - `num_return_values`: number of values returned by the control block;
@@ -53,7 +55,10 @@ pub enum ElseData {
 ///
 /// Moreover, the `if` frame has the `branch_inst` field that points to the `brz` instruction
 /// separating the `true` and `false` branch. The `loop` frame has a `header` field that references
-/// the `Block` that contains the beginning of the body of the loop.
+/// the `Block` that contains the beginning of the body of the loop. The `try` frame has a
+/// `landing` field that references the `Block` the exceptions thrown in its body unwind to, and
+/// then the `Block` testing the next `catch` clause, and a `catch_slot` field that is set when
+/// translating its `catch` clauses.
 #[derive(Debug)]
 pub enum ControlStackFrame {
     If {
@@ -90,6 +95,21 @@ pub enum ControlStackFrame {
         num_return_values: usize,
         original_stack_size: usize,
     },
+    Try {
+        destination: Block,
+        /// The block the exceptions not caught yet unwind to, created when
+        /// something may throw.
+        landing: Option<Block>,
+        /// The slot, relative to the first slot of the function, of the
+        /// exception caught by the `catch` clauses.
+        ///
+        /// This is `None` while translating the body of the `try`.
+        catch_slot: Option<u32>,
+        num_param_values: usize,
+        num_return_values: usize,
+        original_stack_size: usize,
+        exit_is_branched_to: bool,
+    },
 }
 
 /// Helper methods for the control stack objects.
@@ -104,6 +124,9 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_return_values, ..
+            }
+            | Self::Try {
+                num_return_values, ..
             } => num_return_values,
         }
     }
@@ -117,6 +140,9 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_param_values, ..
+            }
+            | Self::Try {
+                num_param_values, ..
             } => num_param_values,
         }
     }
@@ -124,12 +150,15 @@ impl ControlStackFrame {
         match *self {
             Self::If { destination, .. }
             | Self::Block { destination, .. }
-            | Self::Loop { destination, .. } => destination,
+            | Self::Loop { destination, .. }
+            | Self::Try { destination, .. } => destination,
         }
     }
     pub fn br_destination(&self) -> Block {
         match *self {
-            Self::If { destination, .. } | Self::Block { destination, .. } => destination,
+            Self::If { destination, .. }
+            | Self::Block { destination, .. }
+            | Self::Try { destination, .. } => destination,
             Self::Loop { header, .. } => header,
         }
     }
@@ -148,12 +177,16 @@ impl ControlStackFrame {
             | Self::Loop {
                 original_stack_size,
                 ..
+            }
+            | Self::Try {
+                original_stack_size,
+                ..
             } => original_stack_size,
         }
     }
     pub fn is_loop(&self) -> bool {
         match *self {
-            Self::If { .. } | Self::Block { .. } => false,
+            Self::If { .. } | Self::Block { .. } | Self::Try { .. } => false,
             Self::Loop { .. } => true,
         }
     }
@@ -167,6 +200,10 @@ impl ControlStackFrame {
             | Self::Block {
                 exit_is_branched_to,
                 ..
+            }
+            | Self::Try {
+                exit_is_branched_to,
+                ..
             } => exit_is_branched_to,
             Self::Loop { .. } => false,
         }
@@ -181,6 +218,10 @@ impl ControlStackFrame {
             | Self::Block {
                 ref mut exit_is_branched_to,
                 ..
+            }
+            | Self::Try {
+                ref mut exit_is_branched_to,
+                ..
             } => *exit_is_branched_to = true,
             Self::Loop { .. } => {}
         }
@@ -254,6 +295,18 @@ pub struct FuncTranslationState {
     // `FuncEnvironment::make_direct_func()`.
     // Stores both the function reference and the number of WebAssembly arguments
     functions: HashMap<FunctionIndex, (ir::FuncRef, usize)>,
+
+    /// The first slot of the exceptions caught by the function, computed in
+    /// the entry block by the first `catch` or `rethrow`.
+    pub(crate) exception_base: Option<Value>,
+
+    /// The block returning from the function when an exception unwinds
+    /// out of it, created when something may throw outside of a `try`.
+    pub(crate) propagate_block: Option<Block>,
+
+    /// The return instructions of the function, before which the caught
+    /// exceptions are released.
+    pub(crate) return_insts: Vec<Inst>,
 }
 
 // Public methods that are exposed to non-`cranelift_wasm` API consumers.
@@ -280,6 +333,9 @@ impl FuncTranslationState {
             tables: HashMap::new(),
             signatures: HashMap::new(),
             functions: HashMap::new(),
+            exception_base: None,
+            propagate_block: None,
+            return_insts: Vec::new(),
         }
     }
 
@@ -292,6 +348,9 @@ impl FuncTranslationState {
         self.tables.clear();
         self.signatures.clear();
         self.functions.clear();
+        self.exception_base = None;
+        self.propagate_block = None;
+        self.return_insts.clear();
     }
 
     /// Initialize the state for compiling a function with the given signature.
@@ -492,6 +551,91 @@ impl FuncTranslationState {
             blocktype,
         });
     }
+
+    /// Push a try on the control stack.
+    pub(crate) fn push_try(
+        &mut self,
+        following_code: Block,
+        num_param_types: usize,
+        num_result_types: usize,
+    ) {
+        debug_assert!(num_param_types <= self.stack.len());
+        self.control_stack.push(ControlStackFrame::Try {
+            destination: following_code,
+            landing: None,
+            catch_slot: None,
+            original_stack_size: self.stack.len() - num_param_types,
+            num_param_values: num_param_types,
+            num_return_values: num_result_types,
+            exit_is_branched_to: false,
+        });
+    }
+}
+
+/// Methods for handling exceptions.
+impl FuncTranslationState {
+    /// Get the `Block` an exception thrown with the control frames
+    /// `control_stack[..limit]` unwinds to: the landing block of the
+    /// innermost `try` whose body is being translated, or the block
+    /// returning from the function.
+    ///
+    /// Create the block if necessary.
+    pub(crate) fn exception_handler(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        limit: usize,
+    ) -> Block {
+        for frame in self.control_stack[..limit].iter_mut().rev() {
+            if let ControlStackFrame::Try {
+                landing,
+                catch_slot: None,
+                ..
+            } = frame
+            {
+                return *landing.get_or_insert_with(|| builder.create_block());
+            }
+        }
+        *self
+            .propagate_block
+            .get_or_insert_with(|| builder.create_block())
+    }
+
+    /// Get the slot, relative to the first slot of the function, of the
+    /// exception caught by the `try` frame `control_stack[index]`: its
+    /// number of enclosing `try` frames whose `catch` clauses are being
+    /// translated.
+    pub(crate) fn catch_slot(&self, index: usize) -> u32 {
+        self.control_stack[..index]
+            .iter()
+            .filter(|frame| {
+                matches!(
+                    frame,
+                    ControlStackFrame::Try {
+                        catch_slot: Some(_),
+                        ..
+                    }
+                )
+            })
+            .count() as u32
+    }
+
+    /// Get the first slot of the exceptions caught by the function.
+    ///
+    /// Compute it at the beginning of the function if necessary.
+    pub(crate) fn get_exception_base<FE: FuncEnvironment + ?Sized>(
+        &mut self,
+        func: &mut ir::Function,
+        environ: &mut FE,
+    ) -> WasmResult<Value> {
+        if let Some(base) = self.exception_base {
+            return Ok(base);
+        }
+        let entry_block = func.layout.entry_block().unwrap();
+        let pos = FuncCursor::new(func).at_first_insertion_point(entry_block);
+        let base = environ.translate_exception_slots(pos)?;
+        self.exception_base = Some(base);
+        Ok(base)
+    }
 }
 
 /// Methods for handling entity references.
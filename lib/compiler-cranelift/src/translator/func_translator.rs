@@ -11,6 +11,7 @@ use super::code_translator::{bitcast_arguments, translate_operator, wasm_param_t
 use super::func_environ::{FuncEnvironment, ReturnMode};
 use super::func_state::FuncTranslationState;
 use super::translation_utils::get_vmctx_value_label;
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
 use cranelift_codegen::entity::EntityRef;
 use cranelift_codegen::ir::{self, Block, InstBuilder, ValueLabel};
 use cranelift_codegen::timing;
@@ -271,9 +272,12 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
                         environ.is_wasm_return(&builder.func.signature, i)
                     });
                     bitcast_arguments(&mut state.stack, &return_types, builder);
-                    builder.ins().return_(&state.stack)
+                    let return_inst = builder.ins().return_(&state.stack);
+                    state.return_insts.push(return_inst);
+                }
+                ReturnMode::FallthroughReturn => {
+                    builder.ins().fallthrough_return(&state.stack);
                 }
-                ReturnMode::FallthroughReturn => builder.ins().fallthrough_return(&state.stack),
             };
         }
     }
@@ -283,11 +287,55 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
     state.stack.clear();
     //state.metadata_stack.clear();
 
+    // The exceptions unwinding out of the function return zero values, and
+    // are then unwound by the caller.
+    if let Some(propagate_block) = state.propagate_block {
+        builder.switch_to_block(propagate_block);
+        builder.seal_block(propagate_block);
+        environ.translate_drop_locals(builder)?;
+        let return_values = builder
+            .func
+            .signature
+            .returns
+            .iter()
+            .map(|param| param.value_type)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|ty| zero_value(builder, ty))
+            .collect::<Vec<_>>();
+        let return_inst = builder.ins().return_(&return_values);
+        state.return_insts.push(return_inst);
+    }
+
+    // The exceptions caught by the function are released when it returns.
+    if let Some(base) = state.exception_base {
+        for return_inst in state.return_insts.drain(..) {
+            let pos = FuncCursor::new(builder.func).at_inst(return_inst);
+            environ.translate_exception_release(pos, base)?;
+        }
+    }
+
     debug_assert!(reader.eof());
 
     Ok(())
 }
 
+/// Create a zero value of type `ty`.
+fn zero_value(builder: &mut FunctionBuilder, ty: ir::Type) -> ir::Value {
+    if ty.is_ref() {
+        builder.ins().null(ty)
+    } else if ty.is_vector() {
+        let constant_handle = builder.func.dfg.constants.insert([0; 16].to_vec().into());
+        builder.ins().vconst(ty, constant_handle)
+    } else if ty == ir::types::F32 {
+        builder.ins().f32const(ir::immediates::Ieee32::with_bits(0))
+    } else if ty == ir::types::F64 {
+        builder.ins().f64const(ir::immediates::Ieee64::with_bits(0))
+    } else {
+        builder.ins().iconst(ty, 0)
+    }
+}
+
 /// Get the current source location from a reader.
 fn cur_srcloc(reader: &dyn FunctionBinaryReader) -> ir::SourceLoc {
     // We record source locations as byte code offsets relative to the beginning of the file.
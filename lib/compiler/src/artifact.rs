@@ -8,7 +8,7 @@ use std::path::Path;
 use std::sync::Arc;
 use std::{fs, mem};
 use wasmer_types::entity::PrimaryMap;
-use wasmer_types::{DeserializeError, SerializeError};
+use wasmer_types::{ArtifactVersion, DeserializeError, SerializeError};
 use wasmer_types::{
     MemoryIndex, MemoryStyle, ModuleInfo, OwnedDataInitializer, TableIndex, TableStyle,
 };
@@ -97,8 +97,8 @@ impl dyn ArtifactCreate + 'static {
     }
 }
 
-/// Metadata header which holds an ABI version and the length of the remaining
-/// metadata.
+/// Metadata header which holds the format version and the length of the
+/// remaining metadata.
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct MetadataHeader {
@@ -108,9 +108,18 @@ pub struct MetadataHeader {
 }
 
 impl MetadataHeader {
-    /// Current ABI version. Increment this any time breaking changes are made
-    /// to the format of the serialized data.
-    const CURRENT_VERSION: u32 = 1;
+    /// Current format version. Bump the major version any time breaking
+    /// changes are made to the format of the serialized data, and the minor
+    /// version for changes older readers can ignore.
+    pub const CURRENT_VERSION: ArtifactVersion = ArtifactVersion::new(5, 0, 0);
+
+    /// Compatibility table of the format versions this Wasmer can read.
+    ///
+    /// Each entry is the newest version of a format family; artifacts of the
+    /// same major version and an older minor version are read as well.
+    /// When the major version is bumped, the previous entry should be kept
+    /// here as long as the deserializer still handles its layout.
+    pub const COMPATIBLE_VERSIONS: &'static [ArtifactVersion] = &[Self::CURRENT_VERSION];
 
     /// Magic number to identify wasmer metadata.
     const MAGIC: [u8; 8] = *b"WASMER\0\0";
@@ -125,7 +134,7 @@ impl MetadataHeader {
     pub fn new(len: usize) -> Self {
         Self {
             magic: Self::MAGIC,
-            version: Self::CURRENT_VERSION,
+            version: Self::CURRENT_VERSION.to_u32(),
             len: len.try_into().expect("metadata exceeds maximum length"),
         }
     }
@@ -135,8 +144,27 @@ impl MetadataHeader {
         unsafe { mem::transmute(self) }
     }
 
+    /// Returns whether artifacts of the given format version can be read.
+    pub fn is_compatible(version: ArtifactVersion) -> bool {
+        Self::COMPATIBLE_VERSIONS
+            .iter()
+            .any(|supported| version.is_readable_by(supported))
+    }
+
     /// Parses the header and returns the length of the metadata following it.
     pub fn parse(bytes: &[u8]) -> Result<usize, DeserializeError> {
+        let header = Self::parse_header(bytes)?;
+        let found = ArtifactVersion::from_u32(header.version);
+        if !Self::is_compatible(found) {
+            return Err(DeserializeError::IncompatibleVersion {
+                found,
+                supported: Self::CURRENT_VERSION,
+            });
+        }
+        Ok(header.len as usize)
+    }
+
+    fn parse_header(bytes: &[u8]) -> Result<Self, DeserializeError> {
         if bytes.as_ptr() as usize % 16 != 0 {
             return Err(DeserializeError::CorruptedBinary(
                 "misaligned metadata".to_string(),
@@ -155,12 +183,6 @@ impl MetadataHeader {
                 "The provided bytes were not serialized by Wasmer".to_string(),
             ));
         }
-        if header.version != Self::CURRENT_VERSION {
-            return Err(DeserializeError::Incompatible(
-                "The provided bytes were serialized by an incompatible version of Wasmer"
-                    .to_string(),
-            ));
-        }
-        Ok(header.len as usize)
+        Ok(header)
     }
 }
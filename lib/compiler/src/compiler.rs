@@ -55,6 +55,18 @@ pub trait CompilerConfig {
         // in case they create an IR that they can verify.
     }
 
+    /// Emit the `memory.copy` and `memory.fill` operations of a constant
+    /// length of at most `threshold` bytes inline, rather than calling
+    /// into the runtime, which uses the host's vectorized `memmove` and
+    /// `memset`.
+    ///
+    /// Calling into the runtime only pays off for longer operations, and
+    /// the best threshold depends on the host.
+    fn bulk_memory_inline_threshold(&mut self, _threshold: u32) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they can emit bulk memory operations inline.
+    }
+
     /// Gets the custom compiler config
     fn compiler(self: Box<Self>) -> Box<dyn Compiler>;
 
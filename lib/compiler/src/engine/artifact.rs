@@ -104,6 +104,15 @@ pub trait Artifact: Send + Sync + Upcastable + ArtifactCreate {
             .create_globals(&module)
             .map_err(InstantiationError::Link)?
             .into_boxed_slice();
+        // The functions the tables and globals hold are kept as long as
+        // they are.
+        let func_data_registry = self.func_data_registry();
+        for table in finished_tables.values() {
+            func_data_registry.add_table_root(table);
+        }
+        for global in finished_globals.values() {
+            func_data_registry.add_global_root(global);
+        }
 
         self.register_frame_info();
 
@@ -1,7 +1,9 @@
 //! The WebAssembly possible errors
 use crate::engine::trap::RuntimeError;
 use thiserror::Error;
-pub use wasmer_types::{DeserializeError, ImportError, SerializeError};
+pub use wasmer_types::{
+    CompileError, DeserializeError, ImportError, ImportsReport, SerializeError,
+};
 
 /// The WebAssembly.LinkError object indicates an error during
 /// module instantiation (besides traps from the start function).
@@ -16,6 +18,10 @@ pub enum LinkError {
     #[error("Error while importing {0:?}.{1:?}: {2}")]
     Import(String, String, ImportError),
 
+    /// Several imports are missing or have incompatible types.
+    #[error("{0}")]
+    Imports(ImportsReport),
+
     /// A trap ocurred during linking.
     #[error("RuntimeError occurred during linking: {0}")]
     Trap(#[source] RuntimeError),
@@ -46,4 +52,9 @@ pub enum InstantiationError {
     /// A runtime error occured while invoking the start function
     #[error(transparent)]
     Start(RuntimeError),
+
+    /// The module couldn't be compiled, when compiled lazily at its first
+    /// instantiation.
+    #[error(transparent)]
+    Compile(CompileError),
 }
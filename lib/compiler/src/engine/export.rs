@@ -1,5 +1,7 @@
 use std::sync::Arc;
-use wasmer_vm::{ImportInitializerFuncPtr, VMExtern, VMFunction, VMGlobal, VMMemory, VMTable};
+use wasmer_vm::{
+    ImportInitializerFuncPtr, VMExtern, VMFunction, VMGlobal, VMMemory, VMTable, VMTag,
+};
 
 /// The value of an export passed from one instance to another.
 #[derive(Debug, Clone)]
@@ -15,6 +17,9 @@ pub enum Export {
 
     /// A global export value.
     Global(VMGlobal),
+
+    /// A tag export value.
+    Tag(VMTag),
 }
 
 impl From<Export> for VMExtern {
@@ -24,6 +29,7 @@ impl From<Export> for VMExtern {
             Export::Memory(vm_memory) => Self::Memory(vm_memory),
             Export::Table(vm_table) => Self::Table(vm_table),
             Export::Global(vm_global) => Self::Global(vm_global),
+            Export::Tag(vm_tag) => Self::Tag(vm_tag),
         }
     }
 }
@@ -38,6 +44,7 @@ impl From<VMExtern> for Export {
             VMExtern::Memory(vm_memory) => Self::Memory(vm_memory),
             VMExtern::Table(vm_table) => Self::Table(vm_table),
             VMExtern::Global(vm_global) => Self::Global(vm_global),
+            VMExtern::Tag(vm_tag) => Self::Tag(vm_tag),
         }
     }
 }
@@ -164,3 +171,9 @@ impl From<VMGlobal> for Export {
         Self::Global(global)
     }
 }
+
+impl From<VMTag> for Export {
+    fn from(tag: VMTag) -> Self {
+        Self::Tag(tag)
+    }
+}
@@ -8,7 +8,10 @@ use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
 use wasmer_types::{CompileError, DeserializeError, FunctionType};
-use wasmer_vm::{VMCallerCheckedAnyfunc, VMFuncRef, VMSharedSignatureIndex};
+use wasmer_vm::{
+    FuncDataCollection, FuncDataOwner, Global, Table, VMCallerCheckedAnyfunc, VMFuncRef,
+    VMSharedSignatureIndex,
+};
 
 /// A unimplemented Wasmer `Engine`.
 ///
@@ -23,8 +26,22 @@ pub trait Engine {
     /// Register a signature
     fn register_signature(&self, func_type: &FunctionType) -> VMSharedSignatureIndex;
 
-    /// Register a function's data.
-    fn register_function_metadata(&self, func_data: VMCallerCheckedAnyfunc) -> VMFuncRef;
+    /// Register a function's data, owned by `owner`.
+    fn register_function_metadata(
+        &self,
+        func_data: VMCallerCheckedAnyfunc,
+        owner: FuncDataOwner,
+    ) -> VMFuncRef;
+
+    /// Keeps the data of the functions held by `table` while it's alive.
+    fn register_table_root(&self, table: &Arc<dyn Table>);
+
+    /// Keeps the data of the function held by `global` while it's alive.
+    fn register_global_root(&self, global: &Arc<Global>);
+
+    /// Frees the data of the functions gone, see
+    /// [`FuncDataRegistry::collect`](wasmer_vm::FuncDataRegistry::collect).
+    fn collect_function_metadata(&self) -> FuncDataCollection;
 
     /// Lookup a signature
     fn lookup_signature(&self, sig: VMSharedSignatureIndex) -> Option<FunctionType>;
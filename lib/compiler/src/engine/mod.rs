@@ -7,8 +7,6 @@ mod inner;
 mod resolver;
 mod trap;
 mod tunables;
-
-#[cfg(feature = "translator")]
 mod universal;
 
 pub use self::artifact::Artifact;
@@ -18,6 +16,4 @@ pub use self::inner::{Engine, EngineId};
 pub use self::resolver::resolve_imports;
 pub use self::trap::*;
 pub use self::tunables::Tunables;
-
-#[cfg(feature = "translator")]
 pub use self::universal::*;
@@ -0,0 +1,100 @@
+//! Source-level symbolication of the Wasm frames, from the DWARF debug
+//! info found in the custom sections of a module (e.g. compiled with
+//! `-g` by clang, or in debug mode by rustc).
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use wasmer_types::ModuleInfo;
+
+type Reader = gimli::EndianArcSlice<gimli::LittleEndian>;
+
+/// The DWARF debug info of a module.
+pub(crate) struct DebugSymbols {
+    /// The addresses of the debug info are offsets in the code section.
+    code_section_offset: usize,
+    /// The context parses the debug info lazily, hence the lock.
+    context: Mutex<addr2line::Context<Reader>>,
+}
+
+impl DebugSymbols {
+    /// Loads the debug info of `module`, returning `None` if it has none
+    /// or if it can't be parsed.
+    pub(crate) fn new(module: &ModuleInfo) -> Option<Self> {
+        module.custom_sections(".debug_info").next()?;
+
+        let empty: Arc<[u8]> = Arc::new([]);
+        let dwarf = gimli::Dwarf::load(|id| -> Result<Reader, gimli::Error> {
+            let data = module
+                .custom_sections(id.name())
+                .next()
+                .unwrap_or_else(|| empty.clone());
+            Ok(Reader::new(data, gimli::LittleEndian))
+        })
+        .ok()?;
+        let context = addr2line::Context::from_dwarf(dwarf).ok()?;
+
+        Some(Self {
+            code_section_offset: module.code_section_offset,
+            context: Mutex::new(context),
+        })
+    }
+
+    /// Finds the source location of the instruction at `module_offset`.
+    pub(crate) fn lookup(&self, module_offset: usize) -> Option<SourceLocation> {
+        let address = module_offset.checked_sub(self.code_section_offset)? as u64;
+        let context = self.context.lock().ok()?;
+        let location = context.find_location(address).ok()??;
+
+        Some(SourceLocation {
+            file: location.file?.to_string(),
+            line: location.line,
+            column: location.column,
+        })
+    }
+}
+
+impl fmt::Debug for DebugSymbols {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebugSymbols")
+            .field("code_section_offset", &self.code_section_offset)
+            .finish()
+    }
+}
+
+/// A location in the source code a Wasm module was compiled from, as
+/// described by its DWARF debug info.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    file: String,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+impl SourceLocation {
+    /// Returns the path of the source file.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Returns the line in the source file, starting from 1, if known.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Returns the column in the line, starting from 1, if known.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use backtrace::Backtrace;
 use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
+use wasmer_types::RuntimeErrorKind;
 use wasmer_vm::{raise_user_trap, Trap, TrapCode};
 
 /// A struct representing an aborted instruction execution, with a message
@@ -19,6 +20,8 @@ enum RuntimeErrorSource {
     OutOfMemory,
     User(Box<dyn Error + Send + Sync>),
     Trap(TrapCode),
+    /// An uncaught `Exception`.
+    Exception(Box<dyn Error + Send + Sync>),
 }
 
 impl fmt::Display for RuntimeErrorSource {
@@ -28,6 +31,7 @@ impl fmt::Display for RuntimeErrorSource {
             Self::User(s) => write!(f, "{}", s),
             Self::OutOfMemory => write!(f, "Wasmer VM out of memory"),
             Self::Trap(s) => write!(f, "{}", s.message()),
+            Self::Exception(s) => write!(f, "{}", s),
         }
     }
 }
@@ -103,6 +107,13 @@ impl RuntimeError {
                 trap_code,
                 backtrace,
             } => Self::new_with_trace(&info, None, RuntimeErrorSource::Trap(trap_code), backtrace),
+            // An exception thrown by the Wasm code and not caught
+            Trap::Exception(exception) => Self::new_with_trace(
+                &info,
+                None,
+                RuntimeErrorSource::Exception(Box::new(exception)),
+                Backtrace::new_unresolved(),
+            ),
         }
     }
 
@@ -185,12 +196,18 @@ impl RuntimeError {
     }
 
     /// Attempts to downcast the `RuntimeError` to a concrete type.
+    ///
+    /// An uncaught exception downcasts to a `wasmer_vm::Exception`.
     pub fn downcast<T: Error + 'static>(self) -> Result<T, Self> {
         match Arc::try_unwrap(self.inner) {
-            // We only try to downcast user errors
+            // We only try to downcast user errors and exceptions
             Ok(RuntimeErrorInner {
                 source: RuntimeErrorSource::User(err),
                 ..
+            })
+            | Ok(RuntimeErrorInner {
+                source: RuntimeErrorSource::Exception(err),
+                ..
             }) if err.is::<T>() => Ok(*err.downcast::<T>().unwrap()),
             Ok(inner) => Err(Self {
                 inner: Arc::new(inner),
@@ -199,6 +216,29 @@ impl RuntimeError {
         }
     }
 
+    /// Attempts to downcast a reference to the `RuntimeError` to a
+    /// concrete type.
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        match &self.inner.source {
+            // We only try to downcast user errors and exceptions
+            RuntimeErrorSource::User(err) | RuntimeErrorSource::Exception(err) => {
+                err.downcast_ref::<T>()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns what the error comes from.
+    pub fn kind(&self) -> RuntimeErrorKind {
+        match &self.inner.source {
+            RuntimeErrorSource::Generic(_) => RuntimeErrorKind::Generic,
+            RuntimeErrorSource::OutOfMemory => RuntimeErrorKind::OutOfMemory,
+            RuntimeErrorSource::User(_) => RuntimeErrorKind::User,
+            RuntimeErrorSource::Trap(trap_code) => RuntimeErrorKind::Trap(*trap_code),
+            RuntimeErrorSource::Exception(_) => RuntimeErrorKind::Exception,
+        }
+    }
+
     /// Returns trap code, if it's a Trap
     pub fn to_trap(self) -> Option<TrapCode> {
         if let RuntimeErrorSource::Trap(trap_code) = self.inner.source {
@@ -211,7 +251,7 @@ impl RuntimeError {
     /// Returns true if the `RuntimeError` is the same as T
     pub fn is<T: Error + 'static>(&self) -> bool {
         match &self.inner.source {
-            RuntimeErrorSource::User(err) => err.is::<T>(),
+            RuntimeErrorSource::User(err) | RuntimeErrorSource::Exception(err) => err.is::<T>(),
             _ => false,
         }
     }
@@ -253,6 +293,10 @@ impl fmt::Display for RuntimeError {
                 func_index,
                 frame.module_offset()
             )?;
+            #[cfg(feature = "debug-symbols")]
+            if let Some(location) = frame.source_location() {
+                write!(f, " at {}", location)?;
+            }
         }
         Ok(())
     }
@@ -261,7 +305,7 @@ impl fmt::Display for RuntimeError {
 impl std::error::Error for RuntimeError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match &self.inner.source {
-            RuntimeErrorSource::User(err) => Some(&**err),
+            RuntimeErrorSource::User(err) | RuntimeErrorSource::Exception(err) => Some(&**err),
             RuntimeErrorSource::Trap(err) => Some(err),
             _ => None,
         }
@@ -11,6 +11,8 @@
 //! let module: ModuleInfo = ...;
 //! FRAME_INFO.register(module, compiled_functions);
 //! ```
+#[cfg(feature = "debug-symbols")]
+use super::debug_symbols::{DebugSymbols, SourceLocation};
 use std::cmp;
 use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
@@ -56,6 +58,8 @@ struct ModuleInfoFrameInfo {
     functions: BTreeMap<usize, FunctionInfo>,
     module: Arc<ModuleInfo>,
     frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    #[cfg(feature = "debug-symbols")]
+    debug_symbols: Option<DebugSymbols>,
 }
 
 impl ModuleInfoFrameInfo {
@@ -135,6 +139,36 @@ impl GlobalFrameInfo {
             function_name: module.module.function_names.get(&func_index).cloned(),
             instr,
             func_start: instr_map.start_srcloc,
+            #[cfg(feature = "debug-symbols")]
+            source_location: module
+                .debug_symbols
+                .as_ref()
+                .and_then(|symbols| symbols.lookup(instr.bits() as usize)),
+        })
+    }
+
+    /// Fetches the compiled function containing `pc`.
+    ///
+    /// Returns `None` if `pc` isn't in the code of a registered module.
+    pub fn lookup_symbol(&self, pc: usize) -> Option<JitSymbol> {
+        let module = self.module_info(pc)?;
+        // The functions are keyed by the address right after their end.
+        let (end, func) = module.functions.range(pc + 1..).next()?;
+        if pc < func.start {
+            return None;
+        }
+
+        let func_index = module.module.func_index(func.local_index);
+        let name = match module.module.function_names.get(&func_index) {
+            Some(name) => format!("{:#}", rustc_demangle::demangle(name)),
+            None => format!("wasm-function[{}]", func_index.index()),
+        };
+        Some(JitSymbol {
+            name,
+            module_name: module.module.name(),
+            func_index: func_index.index() as u32,
+            address: func.start,
+            size: end - func.start,
         })
     }
 
@@ -215,6 +249,8 @@ pub fn register(
     if functions.is_empty() {
         return None;
     }
+    #[cfg(feature = "debug-symbols")]
+    let debug_symbols = DebugSymbols::new(&module);
 
     let mut info = FRAME_INFO.write().unwrap();
     // First up assert that our chunk of jit functions doesn't collide with
@@ -234,12 +270,62 @@ pub fn register(
             functions,
             module,
             frame_infos,
+            #[cfg(feature = "debug-symbols")]
+            debug_symbols,
         },
     );
     assert!(prev.is_none());
     Some(GlobalFrameInfoRegistration { key: max })
 }
 
+/// Resolves `address` to the compiled WebAssembly function containing
+/// it, e.g. to name the samples of a profiler.
+///
+/// Only the functions of the modules which are instantiated are known.
+/// Returns `None` if `address` isn't in the code of one of them.
+pub fn lookup_jit_symbol(address: usize) -> Option<JitSymbol> {
+    FRAME_INFO.read().unwrap().lookup_symbol(address)
+}
+
+/// A compiled WebAssembly function, as found by [`lookup_jit_symbol`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JitSymbol {
+    name: String,
+    module_name: String,
+    func_index: u32,
+    address: usize,
+    size: usize,
+}
+
+impl JitSymbol {
+    /// Returns the demangled name of the function, from the `name`
+    /// section of the module, or `wasm-function[<index>]`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the module of the function.
+    pub fn module_name(&self) -> &str {
+        &self.module_name
+    }
+
+    /// Returns the index of the function in the function index space of
+    /// its module.
+    pub fn func_index(&self) -> u32 {
+        self.func_index
+    }
+
+    /// Returns the address of the code of the function.
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    /// Returns the size of the code of the function, in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
 /// Description of a frame in a backtrace for a [`RuntimeError::trace`](crate::RuntimeError::trace).
 ///
 /// Whenever a WebAssembly trap occurs an instance of [`RuntimeError`]
@@ -255,6 +341,8 @@ pub struct FrameInfo {
     function_name: Option<String>,
     func_start: SourceLoc,
     instr: SourceLoc,
+    #[cfg(feature = "debug-symbols")]
+    source_location: Option<SourceLocation>,
 }
 
 impl FrameInfo {
@@ -315,4 +403,11 @@ impl FrameInfo {
     pub fn func_offset(&self) -> usize {
         (self.instr.bits() - self.func_start.bits()) as usize
     }
+
+    /// Returns the location in the source code of this frame's program
+    /// counter, if the module has DWARF debug info covering it.
+    #[cfg(feature = "debug-symbols")]
+    pub fn source_location(&self) -> Option<&SourceLocation> {
+        self.source_location.as_ref()
+    }
 }
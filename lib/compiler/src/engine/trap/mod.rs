@@ -1,7 +1,11 @@
+#[cfg(feature = "debug-symbols")]
+mod debug_symbols;
 mod error;
 mod frame_info;
+#[cfg(feature = "debug-symbols")]
+pub use debug_symbols::SourceLocation;
 pub use error::RuntimeError;
 pub use frame_info::{
-    register as register_frame_info, FrameInfo, FunctionExtent, GlobalFrameInfoRegistration,
-    FRAME_INFO,
+    lookup_jit_symbol, register as register_frame_info, FrameInfo, FunctionExtent,
+    GlobalFrameInfoRegistration, JitSymbol, FRAME_INFO,
 };
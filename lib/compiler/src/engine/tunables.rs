@@ -13,6 +13,31 @@ use wasmer_vm::{VMMemoryDefinition, VMTableDefinition};
 
 /// An engine delegates the creation of memories, tables, and globals
 /// to a foreign implementor of this trait.
+///
+/// Implementing it lets an embedder allocate the memories and tables of
+/// the instances itself, e.g. from huge pages, NUMA-local memory, a
+/// memfd or a preallocated arena, while wrapping `wasmer::BaseTunables`
+/// keeps the default behavior for everything else. A store creates its
+/// memories, tables and globals with the tunables given to
+/// `wasmer::Store::new_with_tunables` or `wasmer::Store::set_tunables`.
+///
+/// The compiled code relies on the [`MemoryStyle`] of a memory, which
+/// its implementation must honor:
+///
+/// - a [`MemoryStyle::Static`] memory never moves, and reserves `bound`
+///   followed by `offset_guard_size` bytes of guard region, where any
+///   access must fault;
+/// - a [`MemoryStyle::Dynamic`] memory may move when it grows, and is
+///   followed by `offset_guard_size` bytes of guard region. A memory
+///   without a guard region must have an `offset_guard_size` of 0, so
+///   the compiled code checks the bounds of every access.
+///
+/// The compiled code reads the base and the length of a memory from its
+/// [`VMMemoryDefinition`], which the memory must keep up to date when it
+/// grows. A memory owned by the VM must use the definition at
+/// `vm_definition_location`.
+///
+/// See the `tunables-limit-memory` and `tunables-memory-arena` examples.
 pub trait Tunables {
     /// Construct a `MemoryStyle` for the provided `MemoryType`
     fn memory_style(&self, memory: &MemoryType) -> MemoryStyle;
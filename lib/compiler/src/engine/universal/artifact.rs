@@ -44,9 +44,9 @@ impl UniversalArtifact {
         tunables: &dyn Tunables,
     ) -> Result<Self, CompileError> {
         let environ = ModuleEnvironment::new();
-        let mut inner_engine = engine.inner_mut();
         let translation = environ.translate(data).map_err(CompileError::Wasm)?;
         let module = translation.module;
+        engine.check_deterministic(&module)?;
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
             .memories
             .values()
@@ -58,6 +58,19 @@ impl UniversalArtifact {
             .map(|table_type| tunables.table_style(table_type))
             .collect();
 
+        Self::compile(engine, data, memory_styles, table_styles)
+    }
+
+    /// Compile a data buffer into a `UniversalArtifactBuild`, with the
+    /// given memory and table styles.
+    #[cfg(feature = "universal_engine")]
+    pub(crate) fn compile(
+        engine: &UniversalEngine,
+        data: &[u8],
+        memory_styles: PrimaryMap<MemoryIndex, MemoryStyle>,
+        table_styles: PrimaryMap<TableIndex, TableStyle>,
+    ) -> Result<Self, CompileError> {
+        let mut inner_engine = engine.inner_mut();
         let artifact = UniversalArtifactBuild::new(
             inner_engine.builder_mut(),
             data,
@@ -96,6 +109,11 @@ impl UniversalArtifact {
         let metadata_slice: &[u8] = &bytes[MetadataHeader::LEN..][..metadata_len];
         let serializable = SerializableModule::deserialize(metadata_slice)?;
         let artifact = UniversalArtifactBuild::from_serializable(serializable);
+        if engine.is_deterministic() && !artifact.is_deterministic() {
+            return Err(DeserializeError::Incompatible(
+                "The module was not compiled to run deterministically".to_string(),
+            ));
+        }
         let mut inner_engine = engine.inner_mut();
         Self::from_parts(&mut inner_engine, artifact).map_err(DeserializeError::Compiler)
     }
@@ -157,6 +175,8 @@ impl UniversalArtifact {
 
         engine_inner.publish_eh_frame(eh_frame)?;
 
+        engine_inner.register_jit_debug(artifact.module_ref(), &finished_functions);
+
         let finished_function_lengths = finished_functions
             .values()
             .map(|extent| extent.length)
@@ -1,16 +1,26 @@
 use super::UniversalEngine;
-use crate::{CompilerConfig, Features, Target};
+#[cfg(feature = "translator")]
+use crate::CompilerConfig;
+use crate::{Features, Target};
 
 /// The Universal builder
 pub struct Universal {
+    #[cfg(feature = "translator")]
     #[allow(dead_code)]
     compiler_config: Option<Box<dyn CompilerConfig>>,
     target: Option<Target>,
     features: Option<Features>,
+    lazy: bool,
+    deterministic: bool,
+    bulk_memory_inline_threshold: Option<u32>,
+    #[cfg(feature = "jit-debug")]
+    jit_debug: bool,
+    perf_map: bool,
 }
 
 impl Universal {
     /// Create a new Universal
+    #[cfg(feature = "translator")]
     pub fn new<T>(compiler_config: T) -> Self
     where
         T: Into<Box<dyn CompilerConfig>>,
@@ -19,15 +29,28 @@ impl Universal {
             compiler_config: Some(compiler_config.into()),
             target: None,
             features: None,
+            lazy: false,
+            deterministic: false,
+            bulk_memory_inline_threshold: None,
+            #[cfg(feature = "jit-debug")]
+            jit_debug: false,
+            perf_map: false,
         }
     }
 
     /// Create a new headless Universal
     pub fn headless() -> Self {
         Self {
+            #[cfg(feature = "translator")]
             compiler_config: None,
             target: None,
             features: None,
+            lazy: false,
+            deterministic: false,
+            bulk_memory_inline_threshold: None,
+            #[cfg(feature = "jit-debug")]
+            jit_debug: false,
+            perf_map: false,
         }
     }
 
@@ -43,18 +66,99 @@ impl Universal {
         self
     }
 
+    /// Compile the modules when they are first instantiated, rather than
+    /// when they are created.
+    ///
+    /// Creating a module then only validates and translates it, which is
+    /// much faster for large modules, and the compilation cost moves to
+    /// its first instantiation (or serialization). A module failing to
+    /// compile then fails to instantiate with
+    /// `InstantiationError::Compile`.
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// Run the modules deterministically: the same calls with the same
+    /// arguments then have the same results on every host.
+    ///
+    /// The compiler canonicalizes the NaNs, the threads proposal is
+    /// disabled, and the modules importing the clocks or the random
+    /// numbers of WASI, or any function of WASIX, fail to compile. The
+    /// deserialized modules must have been compiled deterministically
+    /// too, which headless engines also check.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Emit the `memory.copy` and `memory.fill` operations of a constant
+    /// length of at most `threshold` bytes inline, when the compiler
+    /// supports it.
+    ///
+    /// The other operations call into the runtime, which uses the
+    /// host's vectorized `memmove` and `memset`: that call only pays off
+    /// for long enough operations. See the `memory_bulk` benchmark to
+    /// pick a threshold for a host.
+    pub fn bulk_memory_inline_threshold(mut self, threshold: u32) -> Self {
+        self.bulk_memory_inline_threshold = Some(threshold);
+        self
+    }
+
+    /// Register the compiled functions with the native debuggers and
+    /// profilers, so they show their names in backtraces and profiles.
+    ///
+    /// The functions are registered with the GDB JIT interface, so gdb
+    /// and lldb can break on them. On Linux, they are also written to a
+    /// `jit-<pid>.dump` file in `$JITDUMPDIR`, or in the current
+    /// directory, for `perf record -k mono` and `perf inject --jit`.
+    #[cfg(feature = "jit-debug")]
+    pub fn jit_debug(mut self, enable: bool) -> Self {
+        self.jit_debug = enable;
+        self
+    }
+
+    /// Write the compiled functions to the perf map of the process,
+    /// `/tmp/perf-<pid>.map`, so `perf` names them in its profiles, and
+    /// so in the flamegraphs made from them. This is only supported on
+    /// Linux.
+    ///
+    /// Unlike the jitdump files of [`Universal::jit_debug`], the map
+    /// only has the names of the functions, but `perf report` reads it
+    /// directly. The entries are never removed from the map, even when
+    /// the code is freed.
+    pub fn perf_map(mut self, enable: bool) -> Self {
+        self.perf_map = enable;
+        self
+    }
+
     /// Build the `UniversalEngine` for this configuration
     #[cfg(feature = "universal_engine")]
     pub fn engine(self) -> UniversalEngine {
         let target = self.target.unwrap_or_default();
-        if let Some(compiler_config) = self.compiler_config {
-            let features = self
+        if let Some(mut compiler_config) = self.compiler_config {
+            if let Some(threshold) = self.bulk_memory_inline_threshold {
+                compiler_config.bulk_memory_inline_threshold(threshold);
+            }
+            let mut features = self
                 .features
                 .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+            if self.deterministic {
+                compiler_config.canonicalize_nans(true);
+                features.threads(false);
+            }
             let compiler = compiler_config.compiler();
-            UniversalEngine::new(compiler, target, features)
+            let mut engine = UniversalEngine::new(compiler, target, features);
+            engine.set_lazy(self.lazy);
+            engine.set_deterministic(self.deterministic);
+            #[cfg(feature = "jit-debug")]
+            engine.set_jit_debug(self.jit_debug);
+            engine.set_perf_map(self.perf_map);
+            engine
         } else {
-            UniversalEngine::headless()
+            let mut engine = UniversalEngine::headless();
+            engine.set_deterministic(self.deterministic);
+            engine
         }
     }
 
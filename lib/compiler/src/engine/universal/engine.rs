@@ -1,11 +1,12 @@
 //! Universal compilation.
 
-#[cfg(feature = "universal_engine")]
-use crate::Compiler;
+use super::jit_debug::{self, JitDebugOptions, JitDebugRegistration};
 use crate::Target;
 use crate::UniversalEngineBuilder;
 use crate::{Artifact, Engine, EngineId, FunctionExtent, Tunables};
 use crate::{CodeMemory, UniversalArtifact};
+#[cfg(feature = "universal_engine")]
+use crate::{Compiler, LazyArtifact};
 use std::sync::{Arc, Mutex};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::FunctionBody;
@@ -15,8 +16,9 @@ use wasmer_types::{
 };
 use wasmer_types::{CustomSection, CustomSectionProtection, SectionIndex};
 use wasmer_vm::{
-    FuncDataRegistry, FunctionBodyPtr, SectionBodyPtr, SignatureRegistry, VMCallerCheckedAnyfunc,
-    VMFuncRef, VMFunctionBody, VMSharedSignatureIndex, VMTrampoline,
+    FuncDataCollection, FuncDataOwner, FuncDataRegistry, FunctionBodyPtr, Global, SectionBodyPtr,
+    SignatureRegistry, Table, VMCallerCheckedAnyfunc, VMFuncRef, VMFunctionBody,
+    VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// A WebAssembly `Universal` Engine.
@@ -26,6 +28,8 @@ pub struct UniversalEngine {
     /// The target for the compiler
     target: Arc<Target>,
     engine_id: EngineId,
+    lazy: bool,
+    deterministic: bool,
 }
 
 impl UniversalEngine {
@@ -35,12 +39,16 @@ impl UniversalEngine {
         Self {
             inner: Arc::new(Mutex::new(UniversalEngineInner {
                 builder: UniversalEngineBuilder::new(Some(compiler), features),
+                jit_debug: JitDebugOptions::default(),
+                jit_debug_registrations: vec![],
                 code_memory: vec![],
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
+            lazy: false,
+            deterministic: false,
         }
     }
 
@@ -60,14 +68,81 @@ impl UniversalEngine {
     pub fn headless() -> Self {
         Self {
             inner: Arc::new(Mutex::new(UniversalEngineInner {
-                builder: UniversalEngineBuilder::new(None, Features::default()),
+                builder: UniversalEngineBuilder::headless(Features::default()),
+                jit_debug: JitDebugOptions::default(),
+                jit_debug_registrations: vec![],
                 code_memory: vec![],
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
             })),
             target: Arc::new(Target::default()),
             engine_id: EngineId::default(),
+            lazy: false,
+            deterministic: false,
+        }
+    }
+
+    /// Whether the modules are compiled when first instantiated, rather
+    /// than when created.
+    ///
+    /// See [`Universal::lazy`](crate::Universal::lazy).
+    pub fn is_lazy(&self) -> bool {
+        self.lazy
+    }
+
+    #[cfg(feature = "universal_engine")]
+    pub(crate) fn set_lazy(&mut self, lazy: bool) {
+        self.lazy = lazy;
+    }
+
+    /// Whether the modules run deterministically.
+    ///
+    /// See [`Universal::deterministic`](crate::Universal::deterministic).
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    #[cfg(feature = "universal_engine")]
+    pub(crate) fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+        self.inner_mut().builder.set_deterministic(deterministic);
+    }
+
+    /// Checks that `module` doesn't import non-deterministic functions,
+    /// if the modules must run deterministically.
+    #[cfg(feature = "universal_engine")]
+    pub(crate) fn check_deterministic(&self, module: &ModuleInfo) -> Result<(), CompileError> {
+        if !self.deterministic {
+            return Ok(());
         }
+        for (namespace, name, _) in module.imports.keys() {
+            let non_deterministic = match namespace.as_str() {
+                "wasi_unstable" | "wasi_snapshot_preview1" => matches!(
+                    name.as_str(),
+                    "clock_res_get" | "clock_time_get" | "poll_oneoff" | "random_get"
+                ),
+                // WASIX adds threads and networking.
+                "wasix_32v1" | "wasix_64v1" => true,
+                _ => false,
+            };
+            if non_deterministic {
+                return Err(CompileError::Validate(format!(
+                    "the import {}.{} is not deterministic",
+                    namespace, name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "jit-debug")]
+    pub(crate) fn set_jit_debug(&mut self, enable: bool) {
+        self.inner_mut().jit_debug.debuggers = enable;
+    }
+
+    #[cfg(feature = "universal_engine")]
+    pub(crate) fn set_perf_map(&mut self, enable: bool) {
+        self.inner_mut().jit_debug.perf_map = enable;
     }
 
     pub(crate) fn inner(&self) -> std::sync::MutexGuard<'_, UniversalEngineInner> {
@@ -91,9 +166,28 @@ impl Engine for UniversalEngine {
         compiler.signatures().register(func_type)
     }
 
-    fn register_function_metadata(&self, func_data: VMCallerCheckedAnyfunc) -> VMFuncRef {
+    fn register_function_metadata(
+        &self,
+        func_data: VMCallerCheckedAnyfunc,
+        owner: FuncDataOwner,
+    ) -> VMFuncRef {
+        let compiler = self.inner();
+        compiler.func_data().register(func_data, owner)
+    }
+
+    fn register_table_root(&self, table: &Arc<dyn Table>) {
+        let compiler = self.inner();
+        compiler.func_data().add_table_root(table)
+    }
+
+    fn register_global_root(&self, global: &Arc<Global>) {
+        let compiler = self.inner();
+        compiler.func_data().add_global_root(global)
+    }
+
+    fn collect_function_metadata(&self) -> FuncDataCollection {
         let compiler = self.inner();
-        compiler.func_data().register(func_data)
+        compiler.func_data().collect()
     }
 
     /// Lookup a signature
@@ -114,6 +208,9 @@ impl Engine for UniversalEngine {
         binary: &[u8],
         tunables: &dyn Tunables,
     ) -> Result<Arc<dyn Artifact>, CompileError> {
+        if self.lazy {
+            return Ok(Arc::new(LazyArtifact::new(self, binary, tunables)?));
+        }
         Ok(Arc::new(UniversalArtifact::new(self, binary, tunables)?))
     }
 
@@ -148,6 +245,12 @@ impl Engine for UniversalEngine {
 pub struct UniversalEngineInner {
     /// The builder (include compiler and cpu features)
     builder: UniversalEngineBuilder,
+    /// Where the compiled code is registered for the debuggers and the
+    /// profilers.
+    jit_debug: JitDebugOptions,
+    /// The registrations of the compiled code. They are dropped before
+    /// the code they describe.
+    jit_debug_registrations: Vec<JitDebugRegistration>,
     /// The code memory is responsible of publishing the compiled
     /// functions to memory.
     code_memory: Vec<CodeMemory>,
@@ -292,6 +395,18 @@ impl UniversalEngineInner {
         Ok(())
     }
 
+    /// Register the compiled functions with the debuggers and the
+    /// profilers, if enabled.
+    pub(crate) fn register_jit_debug(
+        &mut self,
+        module: &ModuleInfo,
+        functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    ) {
+        if let Some(registration) = jit_debug::register(self.jit_debug, module, functions) {
+            self.jit_debug_registrations.push(registration);
+        }
+    }
+
     /// Shared signature registry.
     pub fn signatures(&self) -> &SignatureRegistry {
         &self.signatures
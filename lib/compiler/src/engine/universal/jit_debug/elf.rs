@@ -0,0 +1,243 @@
+//! A minimal ELF object describing the compiled functions, for the GDB
+//! JIT interface.
+//!
+//! The object has a `.text` section without contents, at the address of
+//! the code, and a symbol for each function. As in the objects loaded
+//! by a JIT linker, the sections are at their load address and the
+//! values of the symbols are relative to their section.
+
+use super::JitFunction;
+
+const EHDR_SIZE: usize = 64;
+const SHDR_SIZE: usize = 64;
+const SYM_SIZE: usize = 24;
+
+const ET_REL: u16 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const STB_GLOBAL: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+/// The section indices.
+const TEXT: u16 = 1;
+const STRTAB: u32 = 3;
+const SHSTRTAB: u16 = 4;
+
+/// The ELF machine of the host, if supported.
+pub(super) fn machine() -> Option<u16> {
+    if cfg!(target_arch = "x86_64") {
+        Some(62)
+    } else if cfg!(target_arch = "aarch64") {
+        Some(183)
+    } else if cfg!(target_arch = "riscv64") {
+        Some(243)
+    } else {
+        None
+    }
+}
+
+/// Writes the ELF object describing `functions`, if the host is a
+/// supported 64-bit little-endian architecture.
+pub(super) fn write(functions: &[JitFunction]) -> Option<Vec<u8>> {
+    if !cfg!(all(target_pointer_width = "64", target_endian = "little")) || functions.is_empty() {
+        return None;
+    }
+    let machine = machine()?;
+
+    let text_start = functions.iter().map(|f| f.address).min()?;
+    let text_end = functions.iter().map(|f| f.address + f.len).max()?;
+
+    let shstrtab = b"\0.text\0.symtab\0.strtab\0.shstrtab\0";
+    let section_names = [0, 1, 7, 15, 23];
+
+    let mut strtab = vec![0];
+    let mut symtab = vec![0; SYM_SIZE];
+    for function in functions {
+        let name = strtab.len() as u32;
+        strtab.extend_from_slice(function.name.as_bytes());
+        strtab.push(0);
+
+        symtab.extend_from_slice(&name.to_le_bytes());
+        symtab.push(STB_GLOBAL << 4 | STT_FUNC);
+        symtab.push(0);
+        symtab.extend_from_slice(&TEXT.to_le_bytes());
+        symtab.extend_from_slice(&((function.address - text_start) as u64).to_le_bytes());
+        symtab.extend_from_slice(&(function.len as u64).to_le_bytes());
+    }
+
+    let mut elf = vec![0; EHDR_SIZE];
+    let shstrtab_offset = append(&mut elf, shstrtab);
+    let strtab_offset = append(&mut elf, &strtab);
+    let symtab_offset = append(&mut elf, &symtab);
+    let shdrs_offset = append(&mut elf, &[]);
+
+    // The ELF header.
+    elf[..16].copy_from_slice(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0");
+    let mut header = Vec::with_capacity(EHDR_SIZE - 16);
+    header.extend_from_slice(&ET_REL.to_le_bytes());
+    header.extend_from_slice(&machine.to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&0u64.to_le_bytes()); // e_entry
+    header.extend_from_slice(&0u64.to_le_bytes()); // e_phoff
+    header.extend_from_slice(&(shdrs_offset as u64).to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+    header.extend_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes()); // e_phentsize
+    header.extend_from_slice(&0u16.to_le_bytes()); // e_phnum
+    header.extend_from_slice(&(SHDR_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&5u16.to_le_bytes()); // e_shnum
+    header.extend_from_slice(&SHSTRTAB.to_le_bytes());
+    elf[16..EHDR_SIZE].copy_from_slice(&header);
+
+    // The section headers.
+    elf.resize(elf.len() + SHDR_SIZE, 0);
+    section_header(
+        &mut elf,
+        section_names[1],
+        SHT_NOBITS,
+        SHF_ALLOC | SHF_EXECINSTR,
+        text_start,
+        0,
+        text_end - text_start,
+        (0, 0),
+        16,
+        0,
+    );
+    section_header(
+        &mut elf,
+        section_names[2],
+        SHT_SYMTAB,
+        0,
+        0,
+        symtab_offset,
+        symtab.len(),
+        // The symbols after the first one are all global.
+        (STRTAB, 1),
+        8,
+        SYM_SIZE,
+    );
+    section_header(
+        &mut elf,
+        section_names[3],
+        SHT_STRTAB,
+        0,
+        0,
+        strtab_offset,
+        strtab.len(),
+        (0, 0),
+        1,
+        0,
+    );
+    section_header(
+        &mut elf,
+        section_names[4],
+        SHT_STRTAB,
+        0,
+        0,
+        shstrtab_offset,
+        shstrtab.len(),
+        (0, 0),
+        1,
+        0,
+    );
+
+    Some(elf)
+}
+
+/// Appends `data` to `elf` at an 8 bytes aligned offset, and returns it.
+fn append(elf: &mut Vec<u8>, data: &[u8]) -> usize {
+    elf.resize((elf.len() + 7) & !7, 0);
+    let offset = elf.len();
+    elf.extend_from_slice(data);
+    offset
+}
+
+#[allow(clippy::too_many_arguments)]
+fn section_header(
+    elf: &mut Vec<u8>,
+    name: u32,
+    ty: u32,
+    flags: u64,
+    address: usize,
+    offset: usize,
+    size: usize,
+    (link, info): (u32, u32),
+    align: u64,
+    entry_size: usize,
+) {
+    elf.extend_from_slice(&name.to_le_bytes());
+    elf.extend_from_slice(&ty.to_le_bytes());
+    elf.extend_from_slice(&flags.to_le_bytes());
+    elf.extend_from_slice(&(address as u64).to_le_bytes());
+    elf.extend_from_slice(&(offset as u64).to_le_bytes());
+    elf.extend_from_slice(&(size as u64).to_le_bytes());
+    elf.extend_from_slice(&link.to_le_bytes());
+    elf.extend_from_slice(&info.to_le_bytes());
+    elf.extend_from_slice(&align.to_le_bytes());
+    elf.extend_from_slice(&(entry_size as u64).to_le_bytes());
+}
+
+#[cfg(all(
+    test,
+    target_pointer_width = "64",
+    target_endian = "little",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn u16_at(elf: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(elf[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u64_at(elf: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(elf[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn describes_the_functions() {
+        let functions = [
+            JitFunction {
+                name: "first".to_string(),
+                address: 0x1000,
+                len: 0x20,
+            },
+            JitFunction {
+                name: "second".to_string(),
+                address: 0x1040,
+                len: 0x10,
+            },
+        ];
+        let elf = write(&functions).unwrap();
+
+        assert_eq!(&elf[..4], b"\x7fELF");
+        assert_eq!(u16_at(&elf, 18), machine().unwrap());
+        let shdrs = u64_at(&elf, 40) as usize;
+        assert_eq!(u16_at(&elf, 60), 5);
+
+        // `.text` spans the functions.
+        let text = shdrs + SHDR_SIZE;
+        assert_eq!(u64_at(&elf, text + 16), 0x1000);
+        assert_eq!(u64_at(&elf, text + 32), 0x50);
+
+        // The symbols are relative to `.text`.
+        let symtab = shdrs + 2 * SHDR_SIZE;
+        let (symbols, symbols_size) = (
+            u64_at(&elf, symtab + 24) as usize,
+            u64_at(&elf, symtab + 32) as usize,
+        );
+        let strtab = u64_at(&elf, shdrs + 3 * SHDR_SIZE + 24) as usize;
+        assert_eq!(symbols_size, 3 * SYM_SIZE);
+        let second = symbols + 2 * SYM_SIZE;
+        let name =
+            strtab + u32::from_le_bytes(elf[second..second + 4].try_into().unwrap()) as usize;
+        assert_eq!(&elf[name..name + 7], b"second\0");
+        assert_eq!(u16_at(&elf, second + 6), TEXT);
+        assert_eq!(u64_at(&elf, second + 8), 0x40);
+        assert_eq!(u64_at(&elf, second + 16), 0x10);
+    }
+}
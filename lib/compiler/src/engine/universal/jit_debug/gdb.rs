@@ -0,0 +1,153 @@
+//! The GDB JIT interface: the debugger puts a breakpoint in
+//! `__jit_debug_register_code`, and reads the objects linked from
+//! `__jit_debug_descriptor` whenever it's called.
+//!
+//! See <https://sourceware.org/gdb/current/onlinedocs/gdb/JIT-Interface.html>.
+
+use std::ptr;
+use std::sync::Mutex;
+
+#[repr(C)]
+struct JitCodeEntry {
+    next_entry: *mut JitCodeEntry,
+    prev_entry: *mut JitCodeEntry,
+    symfile_addr: *const u8,
+    symfile_size: u64,
+}
+
+#[repr(C)]
+struct JitDescriptor {
+    version: u32,
+    action_flag: u32,
+    relevant_entry: *mut JitCodeEntry,
+    first_entry: *mut JitCodeEntry,
+}
+
+const JIT_NOACTION: u32 = 0;
+const JIT_REGISTER_FN: u32 = 1;
+const JIT_UNREGISTER_FN: u32 = 2;
+
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+static mut __jit_debug_descriptor: JitDescriptor = JitDescriptor {
+    version: 1,
+    action_flag: JIT_NOACTION,
+    relevant_entry: ptr::null_mut(),
+    first_entry: ptr::null_mut(),
+};
+
+#[no_mangle]
+#[inline(never)]
+extern "C" fn __jit_debug_register_code() {
+    // The debugger breaks here: keep the function from being optimized
+    // away.
+    unsafe { ptr::read_volatile(&JIT_NOACTION) };
+}
+
+lazy_static::lazy_static! {
+    /// Serializes the updates of `__jit_debug_descriptor`.
+    static ref GDB_REGISTRATION: Mutex<()> = Mutex::new(());
+}
+
+/// An object registered with the GDB JIT interface, unregistered when
+/// dropped.
+pub(super) struct GdbJitImageRegistration {
+    entry: *mut JitCodeEntry,
+    _image: Box<[u8]>,
+}
+
+// The entry is only accessed while holding `GDB_REGISTRATION`.
+unsafe impl Send for GdbJitImageRegistration {}
+unsafe impl Sync for GdbJitImageRegistration {}
+
+impl GdbJitImageRegistration {
+    /// Registers the object `image`.
+    pub(super) fn register(image: Vec<u8>) -> Self {
+        let image = image.into_boxed_slice();
+        let entry = Box::into_raw(Box::new(JitCodeEntry {
+            next_entry: ptr::null_mut(),
+            prev_entry: ptr::null_mut(),
+            symfile_addr: image.as_ptr(),
+            symfile_size: image.len() as u64,
+        }));
+
+        let _guard = GDB_REGISTRATION.lock().unwrap();
+        unsafe {
+            let first = __jit_debug_descriptor.first_entry;
+            (*entry).next_entry = first;
+            if !first.is_null() {
+                (*first).prev_entry = entry;
+            }
+            __jit_debug_descriptor.first_entry = entry;
+            __jit_debug_descriptor.relevant_entry = entry;
+            __jit_debug_descriptor.action_flag = JIT_REGISTER_FN;
+            __jit_debug_register_code();
+            __jit_debug_descriptor.action_flag = JIT_NOACTION;
+            __jit_debug_descriptor.relevant_entry = ptr::null_mut();
+        }
+
+        Self {
+            entry,
+            _image: image,
+        }
+    }
+}
+
+impl Drop for GdbJitImageRegistration {
+    fn drop(&mut self) {
+        let _guard = GDB_REGISTRATION.lock().unwrap();
+        unsafe {
+            let entry = self.entry;
+            let (prev, next) = ((*entry).prev_entry, (*entry).next_entry);
+            if prev.is_null() {
+                __jit_debug_descriptor.first_entry = next;
+            } else {
+                (*prev).next_entry = next;
+            }
+            if !next.is_null() {
+                (*next).prev_entry = prev;
+            }
+            __jit_debug_descriptor.relevant_entry = entry;
+            __jit_debug_descriptor.action_flag = JIT_UNREGISTER_FN;
+            __jit_debug_register_code();
+            __jit_debug_descriptor.action_flag = JIT_NOACTION;
+            __jit_debug_descriptor.relevant_entry = ptr::null_mut();
+            drop(Box::from_raw(entry));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns whether an object is registered at `image`.
+    fn is_registered(image: *const u8) -> bool {
+        let _guard = GDB_REGISTRATION.lock().unwrap();
+        let mut entry = unsafe { __jit_debug_descriptor.first_entry };
+        while !entry.is_null() {
+            unsafe {
+                if (*entry).symfile_addr == image {
+                    return true;
+                }
+                entry = (*entry).next_entry;
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn registrations_are_linked_until_dropped() {
+        let first = GdbJitImageRegistration::register(vec![1]);
+        let second = GdbJitImageRegistration::register(vec![2]);
+        let (first_image, second_image) = (first._image.as_ptr(), second._image.as_ptr());
+        assert!(is_registered(first_image));
+        assert!(is_registered(second_image));
+
+        drop(first);
+        assert!(!is_registered(first_image));
+        assert!(is_registered(second_image));
+        drop(second);
+        assert!(!is_registered(second_image));
+    }
+}
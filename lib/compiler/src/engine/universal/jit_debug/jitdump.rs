@@ -0,0 +1,127 @@
+//! The jitdump files of `perf`: `perf record -k mono` notices the
+//! mapping of `jit-<pid>.dump`, and `perf inject --jit` reads the code
+//! of the functions from it.
+//!
+//! The file is created in `$JITDUMPDIR`, or in the current directory.
+//! See <https://github.com/torvalds/linux/blob/master/tools/perf/Documentation/jitdump-specification.txt>.
+
+use super::{elf, JitFunction};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const JITDUMP_MAGIC: u32 = 0x4A69_5444;
+const JITDUMP_VERSION: u32 = 1;
+const JIT_CODE_LOAD: u32 = 0;
+
+struct JitDump {
+    file: File,
+    code_index: u64,
+}
+
+lazy_static::lazy_static! {
+    /// The jitdump file of the process, if it could be created.
+    static ref JITDUMP: Mutex<Option<JitDump>> = Mutex::new(JitDump::create().ok());
+}
+
+/// The timestamps of the records, from the clock `perf record -k mono`
+/// uses.
+fn timestamp() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+impl JitDump {
+    fn create() -> std::io::Result<Self> {
+        let directory = std::env::var_os("JITDUMPDIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("."));
+        // The file is mapped below, so it must be readable.
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(directory.join(format!("jit-{}.dump", std::process::id())))?;
+
+        let mut header = Vec::with_capacity(40);
+        header.extend_from_slice(&JITDUMP_MAGIC.to_le_bytes());
+        header.extend_from_slice(&JITDUMP_VERSION.to_le_bytes());
+        header.extend_from_slice(&40u32.to_le_bytes());
+        header.extend_from_slice(&(elf::machine().unwrap_or(0) as u32).to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&std::process::id().to_le_bytes());
+        header.extend_from_slice(&timestamp().to_le_bytes());
+        header.extend_from_slice(&0u64.to_le_bytes());
+        file.write_all(&header)?;
+
+        // perf only looks for the executable mappings of the file. It
+        // stays mapped until the process exits.
+        let ret = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                region::page::size(),
+                libc::PROT_READ | libc::PROT_EXEC,
+                libc::MAP_PRIVATE,
+                std::os::unix::io::AsRawFd::as_raw_fd(&file),
+                0,
+            )
+        };
+        if ret == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            file,
+            code_index: 0,
+        })
+    }
+
+    fn write_code_load(&mut self, function: &JitFunction) -> std::io::Result<()> {
+        let pid = std::process::id();
+        let tid = unsafe { libc::syscall(libc::SYS_gettid) } as u32;
+        let code =
+            unsafe { std::slice::from_raw_parts(function.address as *const u8, function.len) };
+
+        let mut record = Vec::with_capacity(64 + function.name.len() + 1 + code.len());
+        record.extend_from_slice(&JIT_CODE_LOAD.to_le_bytes());
+        record.extend_from_slice(&0u32.to_le_bytes()); // The size, set below.
+        record.extend_from_slice(&timestamp().to_le_bytes());
+        record.extend_from_slice(&pid.to_le_bytes());
+        record.extend_from_slice(&tid.to_le_bytes());
+        record.extend_from_slice(&(function.address as u64).to_le_bytes());
+        record.extend_from_slice(&(function.address as u64).to_le_bytes());
+        record.extend_from_slice(&(function.len as u64).to_le_bytes());
+        record.extend_from_slice(&self.code_index.to_le_bytes());
+        record.extend_from_slice(function.name.as_bytes());
+        record.push(0);
+        record.extend_from_slice(code);
+        let size = record.len() as u32;
+        record[4..8].copy_from_slice(&size.to_le_bytes());
+
+        self.file.write_all(&record)?;
+        self.code_index += 1;
+        Ok(())
+    }
+}
+
+/// Writes `functions` to the jitdump file of the process, if any.
+pub(super) fn write(functions: &[JitFunction]) {
+    let mut jitdump = JITDUMP.lock().unwrap();
+    if let Some(dump) = jitdump.as_mut() {
+        if functions
+            .iter()
+            .try_for_each(|function| dump.write_code_load(function))
+            .is_err()
+        {
+            // Stop at the first error rather than writing a truncated
+            // record after it.
+            *jitdump = None;
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Registration of the compiled functions with the native debuggers
+//! and profilers, so they can name the functions of the modules.
+//!
+//! The functions are registered with the GDB JIT interface, which gdb
+//! and lldb read, and, on Linux, written to a jitdump file for `perf`,
+//! with the `jit-debug` feature. They can also be written to a perf map,
+//! which `perf` reads without further setup.
+
+#[cfg(feature = "jit-debug")]
+mod elf;
+#[cfg(feature = "jit-debug")]
+mod gdb;
+#[cfg(all(feature = "jit-debug", target_os = "linux"))]
+mod jitdump;
+mod perf_map;
+
+use crate::FunctionExtent;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+
+/// A compiled function, as seen by the debuggers and profilers.
+struct JitFunction {
+    name: String,
+    address: usize,
+    len: usize,
+}
+
+/// Where the compiled functions are registered.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct JitDebugOptions {
+    /// With the GDB JIT interface and in a jitdump file.
+    #[cfg(feature = "jit-debug")]
+    pub(crate) debuggers: bool,
+    /// In a perf map.
+    pub(crate) perf_map: bool,
+}
+
+impl JitDebugOptions {
+    fn is_enabled(&self) -> bool {
+        #[cfg(feature = "jit-debug")]
+        if self.debuggers {
+            return true;
+        }
+        self.perf_map
+    }
+}
+
+/// An RAII structure used to unregister the functions of a module from
+/// the debuggers when their code is freed.
+pub struct JitDebugRegistration {
+    #[cfg(feature = "jit-debug")]
+    _gdb: Option<gdb::GdbJitImageRegistration>,
+}
+
+/// Registers the compiled `functions` of `module` as `options` say.
+pub(crate) fn register(
+    options: JitDebugOptions,
+    module: &ModuleInfo,
+    functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+) -> Option<JitDebugRegistration> {
+    if !options.is_enabled() {
+        return None;
+    }
+
+    let functions = functions
+        .iter()
+        .map(|(local_index, extent)| {
+            let index = module.func_index(local_index);
+            JitFunction {
+                name: module
+                    .function_names
+                    .get(&index)
+                    .cloned()
+                    .unwrap_or_else(|| format!("wasm-function[{}]", index.as_u32())),
+                address: *extent.ptr as usize,
+                len: extent.length,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if options.perf_map {
+        perf_map::write(&functions);
+    }
+
+    Some(JitDebugRegistration {
+        #[cfg(feature = "jit-debug")]
+        _gdb: if options.debuggers {
+            register_with_debuggers(&functions)
+        } else {
+            None
+        },
+    })
+}
+
+#[cfg(feature = "jit-debug")]
+fn register_with_debuggers(functions: &[JitFunction]) -> Option<gdb::GdbJitImageRegistration> {
+    #[cfg(target_os = "linux")]
+    jitdump::write(functions);
+
+    elf::write(functions).map(gdb::GdbJitImageRegistration::register)
+}
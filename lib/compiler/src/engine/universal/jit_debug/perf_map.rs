@@ -0,0 +1,48 @@
+//! The perf map of the process, `/tmp/perf-<pid>.map`, which `perf`
+//! reads to name the code it found no other symbols for. Each line is
+//! the address and the size of a function, in hexadecimal, and its name.
+
+use super::JitFunction;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    /// The perf map of the process, if it could be created.
+    static ref PERF_MAP: Mutex<Option<File>> = Mutex::new(File::create(path()).ok());
+}
+
+fn path() -> PathBuf {
+    // `perf` always looks in `/tmp`, whatever `$TMPDIR` is.
+    let directory = if cfg!(unix) {
+        PathBuf::from("/tmp")
+    } else {
+        std::env::temp_dir()
+    };
+    directory.join(format!("perf-{}.map", std::process::id()))
+}
+
+/// Appends `functions` to the perf map of the process, if any.
+pub(super) fn write(functions: &[JitFunction]) {
+    // Write all the lines at once, so `perf` doesn't see a partial line
+    // if it reads the map meanwhile.
+    let lines = functions
+        .iter()
+        .map(|function| {
+            format!(
+                "{:x} {:x} {:#}\n",
+                function.address,
+                function.len,
+                rustc_demangle::demangle(&function.name)
+            )
+        })
+        .collect::<String>();
+
+    let mut perf_map = PERF_MAP.lock().unwrap();
+    if let Some(file) = perf_map.as_mut() {
+        if file.write_all(lines.as_bytes()).is_err() {
+            *perf_map = None;
+        }
+    }
+}
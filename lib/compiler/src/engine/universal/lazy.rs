@@ -0,0 +1,185 @@
+//! Define `LazyArtifact`, a `UniversalArtifact` compiled when first
+//! instantiated.
+
+use super::engine::UniversalEngine;
+use crate::{Artifact, ArtifactCreate, Engine, InstantiationError, UniversalArtifact};
+use crate::{CpuFeature, Features, ModuleEnvironment, ModuleMiddlewareChain, Tunables};
+use enumset::EnumSet;
+use once_cell::sync::OnceCell;
+use std::sync::Arc;
+use wasmer_types::entity::{BoxedSlice, PrimaryMap};
+use wasmer_types::{
+    CompileError, FunctionIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo, OwnedDataInitializer,
+    SerializeError, SignatureIndex, TableIndex,
+};
+use wasmer_vm::{
+    FuncDataRegistry, FunctionBodyPtr, MemoryStyle, TableStyle, VMSharedSignatureIndex,
+    VMTrampoline,
+};
+
+/// A wasm module translated but not compiled yet.
+///
+/// The module is compiled the first time it is instantiated or
+/// serialized. Until then, only its validation and translation have
+/// been paid for.
+pub struct LazyArtifact {
+    engine: UniversalEngine,
+    binary: Box<[u8]>,
+    module: Arc<ModuleInfo>,
+    features: Features,
+    cpu_features: EnumSet<CpuFeature>,
+    memory_styles: PrimaryMap<MemoryIndex, MemoryStyle>,
+    table_styles: PrimaryMap<TableIndex, TableStyle>,
+    data_initializers: Box<[OwnedDataInitializer]>,
+    compiled: OnceCell<UniversalArtifact>,
+}
+
+impl LazyArtifact {
+    /// Translate a data buffer into a `LazyArtifact`, to be compiled when
+    /// first instantiated.
+    pub fn new(
+        engine: &UniversalEngine,
+        data: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<Self, CompileError> {
+        let environ = ModuleEnvironment::new();
+        let translation = environ.translate(data).map_err(CompileError::Wasm)?;
+
+        // The middlewares are applied the same way the compilation
+        // applies them, so that the module doesn't change once compiled.
+        let mut module = translation.module;
+        engine.check_deterministic(&module)?;
+        let features = {
+            let inner_engine = engine.inner();
+            let middlewares = inner_engine.compiler()?.get_middlewares();
+            middlewares.apply_on_module_info(&mut module);
+            inner_engine.features().clone()
+        };
+
+        let memory_styles = module
+            .memories
+            .values()
+            .map(|memory_type| tunables.memory_style(memory_type))
+            .collect();
+        let table_styles = module
+            .tables
+            .values()
+            .map(|table_type| tunables.table_style(table_type))
+            .collect();
+        let data_initializers = translation
+            .data_initializers
+            .iter()
+            .map(OwnedDataInitializer::new)
+            .collect();
+
+        Ok(Self {
+            engine: engine.clone(),
+            binary: data.into(),
+            module: Arc::new(module),
+            features,
+            cpu_features: *engine.target().cpu_features(),
+            memory_styles,
+            table_styles,
+            data_initializers,
+            compiled: OnceCell::new(),
+        })
+    }
+
+    /// Whether the module has been compiled already.
+    pub fn is_compiled(&self) -> bool {
+        self.compiled.get().is_some()
+    }
+
+    /// Compiles the module, if it isn't compiled yet.
+    pub fn compiled(&self) -> Result<&UniversalArtifact, CompileError> {
+        self.compiled.get_or_try_init(|| {
+            let mut artifact = UniversalArtifact::compile(
+                &self.engine,
+                &self.binary,
+                self.memory_styles.clone(),
+                self.table_styles.clone(),
+            )?;
+            if let Some(module) = artifact.module_mut() {
+                module.name = self.module.name.clone();
+            }
+
+            Ok(artifact)
+        })
+    }
+
+    fn finished(&self) -> &UniversalArtifact {
+        self.compiled()
+            .expect("the module is compiled before it is instantiated")
+    }
+}
+
+impl ArtifactCreate for LazyArtifact {
+    fn module(&self) -> Arc<ModuleInfo> {
+        self.module.clone()
+    }
+
+    fn module_ref(&self) -> &ModuleInfo {
+        &self.module
+    }
+
+    fn module_mut(&mut self) -> Option<&mut ModuleInfo> {
+        Arc::get_mut(&mut self.module)
+    }
+
+    fn features(&self) -> &Features {
+        &self.features
+    }
+
+    fn cpu_features(&self) -> EnumSet<CpuFeature> {
+        self.cpu_features
+    }
+
+    fn data_initializers(&self) -> &[OwnedDataInitializer] {
+        &self.data_initializers
+    }
+
+    fn memory_styles(&self) -> &PrimaryMap<MemoryIndex, MemoryStyle> {
+        &self.memory_styles
+    }
+
+    fn table_styles(&self) -> &PrimaryMap<TableIndex, TableStyle> {
+        &self.table_styles
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        self.compiled()
+            .map_err(|error| SerializeError::Generic(error.to_string()))?
+            .serialize()
+    }
+}
+
+impl Artifact for LazyArtifact {
+    fn preinstantiate(&self) -> Result<(), InstantiationError> {
+        self.compiled().map_err(InstantiationError::Compile)?;
+        Ok(())
+    }
+
+    fn register_frame_info(&self) {
+        self.finished().register_frame_info()
+    }
+
+    fn finished_functions(&self) -> &BoxedSlice<LocalFunctionIndex, FunctionBodyPtr> {
+        self.finished().finished_functions()
+    }
+
+    fn finished_function_call_trampolines(&self) -> &BoxedSlice<SignatureIndex, VMTrampoline> {
+        self.finished().finished_function_call_trampolines()
+    }
+
+    fn finished_dynamic_function_trampolines(&self) -> &BoxedSlice<FunctionIndex, FunctionBodyPtr> {
+        self.finished().finished_dynamic_function_trampolines()
+    }
+
+    fn signatures(&self) -> &BoxedSlice<SignatureIndex, VMSharedSignatureIndex> {
+        self.finished().signatures()
+    }
+
+    fn func_data_registry(&self) -> &FuncDataRegistry {
+        self.finished().func_data_registry()
+    }
+}
@@ -8,6 +8,9 @@ mod artifact;
 mod builder;
 mod code_memory;
 mod engine;
+mod jit_debug;
+#[cfg(feature = "universal_engine")]
+mod lazy;
 mod link;
 mod unwind;
 
@@ -15,4 +18,6 @@ pub use self::artifact::UniversalArtifact;
 pub use self::builder::Universal;
 pub use self::code_memory::CodeMemory;
 pub use self::engine::UniversalEngine;
+#[cfg(feature = "universal_engine")]
+pub use self::lazy::LazyArtifact;
 pub use self::link::link_module;
@@ -59,10 +59,8 @@ pub use crate::artifact::*;
 #[cfg(not(target_arch = "wasm32"))]
 pub use crate::engine::*;
 
-#[cfg(feature = "translator")]
 mod universal_artifact;
 
-#[cfg(feature = "translator")]
 pub use self::universal_artifact::*;
 
 #[cfg(feature = "translator")]
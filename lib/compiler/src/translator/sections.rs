@@ -22,7 +22,7 @@ use wasmer_types::entity::packed_option::ReservedValue;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{
     DataIndex, ElemIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalInit, GlobalType,
-    MemoryIndex, MemoryType, Pages, SignatureIndex, TableIndex, TableType, Type, V128,
+    MemoryIndex, MemoryType, Pages, SignatureIndex, TableIndex, TableType, TagIndex, Type, V128,
 };
 use wasmer_types::{WasmError, WasmResult};
 use wasmparser::{
@@ -30,7 +30,8 @@ use wasmparser::{
     ElementSectionReader, Export, ExportSectionReader, ExternalKind, FuncType as WPFunctionType,
     FunctionSectionReader, GlobalSectionReader, GlobalType as WPGlobalType, ImportSectionEntryType,
     ImportSectionReader, MemorySectionReader, MemoryType as WPMemoryType, NameSectionReader,
-    Naming, NamingReader, Operator, TableSectionReader, TypeDef, TypeSectionReader,
+    Naming, NamingReader, Operator, TableSectionReader, TagSectionReader, TypeDef,
+    TypeSectionReader,
 };
 
 /// Helper function translating wasmparser types to Wasm Type.
@@ -109,8 +110,12 @@ pub fn parse_import_section<'data>(
             ImportSectionEntryType::Module(_) | ImportSectionEntryType::Instance(_) => {
                 unimplemented!("module linking not implemented yet")
             }
-            ImportSectionEntryType::Tag(_) => {
-                unimplemented!("exception handling not implemented yet")
+            ImportSectionEntryType::Tag(tag) => {
+                environ.declare_tag_import(
+                    SignatureIndex::from_u32(tag.type_index),
+                    module_name,
+                    field_name.unwrap_or_default(),
+                )?;
             }
             ImportSectionEntryType::Memory(WPMemoryType {
                 shared,
@@ -275,6 +280,21 @@ pub fn parse_global_section(
     Ok(())
 }
 
+/// Parses the Tag section of the wasm module.
+pub fn parse_tag_section(
+    tags: TagSectionReader,
+    environ: &mut ModuleEnvironment,
+) -> WasmResult<()> {
+    environ.reserve_tags(tags.get_count())?;
+
+    for entry in tags {
+        let tag = entry.map_err(from_binaryreadererror_wasmerror)?;
+        environ.declare_tag(SignatureIndex::from_u32(tag.type_index))?;
+    }
+
+    Ok(())
+}
+
 /// Parses the Export section of the wasm module.
 pub fn parse_export_section<'data>(
     exports: ExportSectionReader<'data>,
@@ -307,9 +327,7 @@ pub fn parse_export_section<'data>(
             ExternalKind::Type | ExternalKind::Module | ExternalKind::Instance => {
                 unimplemented!("module linking not implemented yet")
             }
-            ExternalKind::Tag => {
-                unimplemented!("exception handling not implemented yet")
-            }
+            ExternalKind::Tag => environ.declare_tag_export(TagIndex::new(index), field)?,
         }
     }
 
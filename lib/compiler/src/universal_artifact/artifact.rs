@@ -114,6 +114,7 @@ impl UniversalArtifactBuild {
             compile_info,
             data_initializers,
             cpu_features: target.cpu_features().as_u64(),
+            deterministic: inner_engine.is_deterministic(),
         };
         Ok(Self { serializable })
     }
@@ -131,6 +132,12 @@ impl UniversalArtifactBuild {
         Self { serializable }
     }
 
+    /// Whether the module was compiled to run deterministically, see
+    /// [`Universal::deterministic`](crate::Universal::deterministic).
+    pub fn is_deterministic(&self) -> bool {
+        self.serializable.deterministic
+    }
+
     /// Get the default extension when serializing this artifact
     pub fn get_default_extension(_triple: &Triple) -> &'static str {
         // `.wasmu` is the default extension for all the triples. It
@@ -1,23 +1,44 @@
 //! Universal compilation.
 
+#[cfg(feature = "translator")]
 use crate::Compiler;
 use wasmer_types::{CompileError, Features};
 
 /// The Builder contents of `UniversalEngine`
 pub struct UniversalEngineBuilder {
     /// The compiler
+    #[cfg(feature = "translator")]
     compiler: Option<Box<dyn Compiler>>,
     /// The features to compile the Wasm module with
     features: Features,
+    /// Whether the Wasm modules are compiled to run deterministically
+    deterministic: bool,
 }
 
 impl UniversalEngineBuilder {
     /// Create a new builder with pre-made components
+    #[cfg(feature = "translator")]
     pub fn new(compiler: Option<Box<dyn Compiler>>, features: Features) -> Self {
-        Self { compiler, features }
+        Self {
+            compiler,
+            features,
+            deterministic: false,
+        }
+    }
+
+    /// Create a new builder without a compiler, which can't compile
+    /// or validate modules
+    pub fn headless(features: Features) -> Self {
+        Self {
+            #[cfg(feature = "translator")]
+            compiler: None,
+            features,
+            deterministic: false,
+        }
     }
 
     /// Gets the compiler associated to this engine.
+    #[cfg(feature = "translator")]
     pub fn compiler(&self) -> Result<&dyn Compiler, CompileError> {
         if self.compiler.is_none() {
             return Err(CompileError::Codegen(
@@ -28,12 +49,33 @@ impl UniversalEngineBuilder {
     }
 
     /// Validate the module
+    #[cfg(feature = "translator")]
     pub fn validate(&self, data: &[u8]) -> Result<(), CompileError> {
         self.compiler()?.validate_module(self.features(), data)
     }
 
+    /// Validate the module
+    #[cfg(not(feature = "translator"))]
+    pub fn validate(&self, _data: &[u8]) -> Result<(), CompileError> {
+        Err(CompileError::Validate(
+            "The UniversalEngine is operating in headless mode, so it can not validate Modules."
+                .to_string(),
+        ))
+    }
+
     /// The Wasm features
     pub fn features(&self) -> &Features {
         &self.features
     }
+
+    /// Whether the Wasm modules are compiled to run deterministically
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Sets whether the Wasm modules are compiled to run
+    /// deterministically, which the compiler must be configured for
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
 }
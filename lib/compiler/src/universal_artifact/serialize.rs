@@ -39,6 +39,8 @@ pub struct SerializableModule {
     pub data_initializers: Box<[OwnedDataInitializer]>,
     /// CPU Feature flags for this compilation
     pub cpu_features: u64,
+    /// Whether the module was compiled to run deterministically
+    pub deterministic: bool,
 }
 
 fn to_serialize_error(err: impl std::error::Error) -> SerializeError {
@@ -0,0 +1,261 @@
+use crate::wit::{self, Interface, Record, Type};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use std::fs;
+use std::path::Path;
+use syn::{Ident, LitStr};
+
+/// Expands `host_bindings!(path)`.
+pub fn expand(path: &LitStr) -> syn::Result<TokenStream> {
+    let error = |message: String| syn::Error::new(path.span(), message);
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| error("`CARGO_MANIFEST_DIR` isn't set".to_string()))?;
+    let file = Path::new(&manifest_dir).join(path.value());
+    let source = fs::read_to_string(&file)
+        .map_err(|e| error(format!("failed to read `{}`: {}", file.display(), e)))?;
+    let interface = wit::parse(&source)
+        .map_err(|e| error(format!("invalid WIT file `{}`: {}", file.display(), e)))?;
+    let name = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| error(format!("invalid WIT file name `{}`", file.display())))?;
+    let file = file.to_string_lossy().into_owned();
+
+    let module = snake_case(name);
+    let records = interface.records.iter().map(record);
+    let host_trait = host_trait(name, &interface);
+    let add_to_imports = add_to_imports(name, &interface);
+
+    Ok(quote! {
+        pub mod #module {
+            // Rebuilds the bindings when the WIT file changes.
+            const _: &str = include_str!(#file);
+
+            #(#records)*
+            #host_trait
+            #add_to_imports
+        }
+    })
+}
+
+fn record(record: &Record) -> TokenStream {
+    let name = camel_case(&record.name);
+    let fields = record
+        .fields
+        .iter()
+        .map(|(field, _)| snake_case(field))
+        .collect::<Vec<_>>();
+    let wit_fields = record.fields.iter().map(|(field, _)| field);
+    let wit_fields_2 = wit_fields.clone();
+    let wit_fields_3 = wit_fields.clone();
+    let types = record
+        .fields
+        .iter()
+        .map(|(_, ty)| rust_type(ty))
+        .collect::<Vec<_>>();
+
+    quote! {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct #name {
+            #(pub #fields: #types,)*
+        }
+
+        impl ::wasmer_component::ComponentType for #name {
+            fn ty() -> ::wasmer_component::InterfaceType {
+                ::wasmer_component::InterfaceType::Record(vec![
+                    #((
+                        #wit_fields.to_string(),
+                        <#types as ::wasmer_component::ComponentType>::ty(),
+                    ),)*
+                ])
+            }
+
+            fn into_value(self) -> ::wasmer_component::InterfaceValue {
+                ::wasmer_component::InterfaceValue::Record(vec![
+                    #((
+                        #wit_fields_2.to_string(),
+                        ::wasmer_component::ComponentType::into_value(self.#fields),
+                    ),)*
+                ])
+            }
+
+            fn from_value(
+                value: ::wasmer_component::InterfaceValue,
+            ) -> Result<Self, ::wasmer_component::ComponentError> {
+                let names: &[&str] = &[#(#wit_fields_3),*];
+                match value {
+                    ::wasmer_component::InterfaceValue::Record(fields)
+                        if fields.iter().map(|(name, _)| name.as_str()).eq(names.iter().copied()) =>
+                    {
+                        #[allow(unused_mut, unused_variables)]
+                        let mut values = fields.into_iter().map(|(_, value)| value);
+                        Ok(Self {
+                            #(#fields: ::wasmer_component::ComponentType::from_value(
+                                values.next().unwrap(),
+                            )?,)*
+                        })
+                    }
+                    value => Err(::wasmer_component::ComponentError::TypeMismatch {
+                        expected: <Self as ::wasmer_component::ComponentType>::ty(),
+                        value,
+                    }),
+                }
+            }
+        }
+    }
+}
+
+fn host_trait(name: &str, interface: &Interface) -> TokenStream {
+    let name = camel_case(name);
+    let functions = interface.functions.iter().map(|function| {
+        let name = snake_case(&function.name);
+        let params = function.params.iter().map(|(param, ty)| {
+            let param = snake_case(param);
+            let ty = rust_type(ty);
+            quote!(#param: #ty)
+        });
+        let result = function.result.as_ref().map(|ty| {
+            let ty = rust_type(ty);
+            quote!(-> #ty)
+        });
+
+        quote! {
+            fn #name(&self, #(#params),*) #result;
+        }
+    });
+
+    quote! {
+        /// The host functions of the interface.
+        pub trait #name: Send + Sync + 'static {
+            #(#functions)*
+        }
+    }
+}
+
+fn add_to_imports(name: &str, interface: &Interface) -> TokenStream {
+    let host_trait = camel_case(name);
+    let functions = interface.functions.iter().map(|function| {
+        let wit_name = &function.name;
+        let method = snake_case(&function.name);
+        let param_types = function
+            .params
+            .iter()
+            .map(|(_, ty)| rust_type(ty))
+            .collect::<Vec<_>>();
+        let args = (0..function.params.len())
+            .map(|index| format_ident!("arg{}", index))
+            .collect::<Vec<_>>();
+        let result_type = function.result.as_ref().map(rust_type);
+        let result_type_iter = result_type.iter();
+        let results = match &result_type {
+            Some(_) => quote!(vec![::wasmer_component::ComponentType::into_value(result)]),
+            None => quote!({
+                let () = result;
+                vec![]
+            }),
+        };
+
+        quote! {
+            {
+                let host = host.clone();
+                imports.define(
+                    #name,
+                    #wit_name,
+                    ::wasmer_component::FuncType::new::<
+                        Vec<::wasmer_component::InterfaceType>,
+                        Vec<::wasmer_component::InterfaceType>,
+                    >(
+                        vec![#(<#param_types as ::wasmer_component::ComponentType>::ty()),*],
+                        vec![#(<#result_type_iter as ::wasmer_component::ComponentType>::ty()),*],
+                    ),
+                    move |args| {
+                        #[allow(unused_mut, unused_variables)]
+                        let mut args = args.iter().cloned();
+                        #(
+                            let #args = <#param_types as ::wasmer_component::ComponentType>::from_value(
+                                args.next().unwrap(),
+                            )?;
+                        )*
+                        let result = host.#method(#(#args),*);
+
+                        Ok(#results)
+                    },
+                );
+            }
+        }
+    });
+
+    quote! {
+        /// Defines the functions of `host` in `imports`.
+        pub fn add_to_imports<H: #host_trait>(
+            imports: &mut ::wasmer_component::ComponentImports,
+            host: H,
+        ) {
+            #[allow(unused_variables)]
+            let host = ::std::sync::Arc::new(host);
+            #(#functions)*
+        }
+    }
+}
+
+fn rust_type(ty: &Type) -> TokenStream {
+    match ty {
+        Type::Scalar("bool") => quote!(bool),
+        Type::Scalar("s8") => quote!(i8),
+        Type::Scalar("u8") => quote!(u8),
+        Type::Scalar("s16") => quote!(i16),
+        Type::Scalar("u16") => quote!(u16),
+        Type::Scalar("s32") => quote!(i32),
+        Type::Scalar("u32") => quote!(u32),
+        Type::Scalar("s64") => quote!(i64),
+        Type::Scalar("u64") => quote!(u64),
+        Type::Scalar("float32") => quote!(f32),
+        Type::Scalar("float64") => quote!(f64),
+        Type::Scalar("char") => quote!(char),
+        Type::Scalar("string") => quote!(String),
+        Type::Scalar(scalar) => unreachable!("unknown scalar type `{}`", scalar),
+        Type::List(element) => {
+            let element = rust_type(element);
+            quote!(Vec<#element>)
+        }
+        Type::Record(name) => {
+            let name = camel_case(name);
+            quote!(#name)
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "try",
+    "type", "unsafe", "use", "where", "while", "yield",
+];
+
+/// Converts a kebab-case WIT name to a snake_case Rust name, appending
+/// `_` to keywords.
+fn snake_case(name: &str) -> Ident {
+    let name = name.replace('-', "_");
+    if KEYWORDS.contains(&name.as_str()) {
+        format_ident!("{}_", name)
+    } else {
+        Ident::new(&name, Span::call_site())
+    }
+}
+
+/// Converts a kebab-case WIT name to a CamelCase Rust name.
+fn camel_case(name: &str) -> Ident {
+    let name = name
+        .split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect::<String>();
+
+    Ident::new(&name, Span::call_site())
+}
@@ -0,0 +1,25 @@
+//! Code generation for `wasmer-component`.
+//!
+//! Use the macros through the `wasmer-component` crate, which re-exports
+//! them.
+
+extern crate proc_macro;
+
+use syn::{parse_macro_input, LitStr};
+
+mod host;
+mod wit;
+
+/// Generates the glue to expose host functions described by a WIT file
+/// to components.
+///
+/// See `wasmer_component::host_bindings` for the documentation.
+#[proc_macro]
+pub fn host_bindings(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+
+    match host::expand(&path) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
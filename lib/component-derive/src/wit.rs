@@ -0,0 +1,384 @@
+//! A parser for the subset of the WIT format `host_bindings!` supports.
+//!
+//! ```wit
+//! // Comments start with `//`.
+//! record point {
+//!     x: s32,
+//!     y: s32,
+//! }
+//!
+//! log: func(message: string)
+//! distance: func(from: point, to: point) -> float64
+//! ```
+//!
+//! The types are the scalar types, `string`, `list<T>`, and the
+//! records defined in the file.
+
+use std::fmt;
+
+/// A type of a WIT file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    /// A scalar type or `string`, e.g. `u32`.
+    Scalar(&'static str),
+    /// `list<T>`.
+    List(Box<Type>),
+    /// A record defined in the file.
+    Record(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub fields: Vec<(String, Type)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<(String, Type)>,
+    pub result: Option<Type>,
+}
+
+/// The contents of a WIT file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Interface {
+    pub records: Vec<Record>,
+    pub functions: Vec<Function>,
+}
+
+/// An error in a WIT file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    /// The line of the error, if it is about a specific line.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+const SCALARS: &[&str] = &[
+    "bool", "s8", "u8", "s16", "u16", "s32", "u32", "s64", "u64", "float32", "float64", "char",
+    "string",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Punct(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "`{}`", ident),
+            Token::Punct(punct) => write!(f, "`{}`", punct),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let mut tokens = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split("//").next().unwrap_or_default();
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            let token = match c {
+                c if c.is_whitespace() => continue,
+                '{' => Token::Punct("{"),
+                '}' => Token::Punct("}"),
+                '(' => Token::Punct("("),
+                ')' => Token::Punct(")"),
+                '<' => Token::Punct("<"),
+                '>' => Token::Punct(">"),
+                ':' => Token::Punct(":"),
+                ',' => Token::Punct(","),
+                '-' if matches!(chars.peek(), Some((_, '>'))) => {
+                    chars.next();
+                    Token::Punct("->")
+                }
+                c if c.is_ascii_alphabetic() => {
+                    let mut end = start + c.len_utf8();
+                    while let Some(&(index, c)) = chars.peek() {
+                        if !(c.is_ascii_alphanumeric() || c == '-') {
+                            break;
+                        }
+                        end = index + c.len_utf8();
+                        chars.next();
+                    }
+                    Token::Ident(line[start..end].to_string())
+                }
+                c => {
+                    return Err(Error {
+                        line: Some(line_number),
+                        message: format!("unexpected character `{}`", c),
+                    })
+                }
+            };
+            tokens.push((token, line_number));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn error<T>(&self, message: String) -> Result<T, Error> {
+        let line = self
+            .tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map(|(_, line)| *line);
+
+        Err(Error { line, message })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token, Error> {
+        match self.tokens.get(self.position) {
+            Some((token, _)) => {
+                self.position += 1;
+                Ok(token.clone())
+            }
+            None => self.error("unexpected end of file".to_string()),
+        }
+    }
+
+    fn eat(&mut self, punct: &'static str) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<(), Error> {
+        match self.next()? {
+            Token::Punct(found) if found == punct => Ok(()),
+            found => {
+                self.position -= 1;
+                self.error(format!("expected `{}`, found {}", punct, found))
+            }
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, Error> {
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            found => {
+                self.position -= 1;
+                self.error(format!("expected an identifier, found {}", found))
+            }
+        }
+    }
+
+    fn ty(&mut self) -> Result<Type, Error> {
+        let name = self.ident()?;
+        if name == "list" {
+            self.expect("<")?;
+            let element = self.ty()?;
+            self.expect(">")?;
+            return Ok(Type::List(Box::new(element)));
+        }
+
+        Ok(match SCALARS.iter().find(|scalar| **scalar == name) {
+            Some(scalar) => Type::Scalar(scalar),
+            None => Type::Record(name),
+        })
+    }
+
+    /// Parses `name: type` items separated by commas, up to `close`.
+    fn named_types(&mut self, close: &'static str) -> Result<Vec<(String, Type)>, Error> {
+        let mut named_types = Vec::new();
+        while !self.eat(close) {
+            let name = self.ident()?;
+            self.expect(":")?;
+            named_types.push((name, self.ty()?));
+            if !self.eat(",") {
+                self.expect(close)?;
+                break;
+            }
+        }
+
+        Ok(named_types)
+    }
+}
+
+/// Parses the contents of a WIT file.
+pub fn parse(source: &str) -> Result<Interface, Error> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+    };
+    let mut interface = Interface::default();
+
+    while parser.peek().is_some() {
+        let name = parser.ident()?;
+        if name == "record" {
+            let name = parser.ident()?;
+            parser.expect("{")?;
+            let fields = parser.named_types("}")?;
+            interface.records.push(Record { name, fields });
+        } else {
+            parser.expect(":")?;
+            match parser.next()? {
+                Token::Ident(keyword) if keyword == "func" => {}
+                found => {
+                    parser.position -= 1;
+                    return parser.error(format!("expected `func`, found {}", found));
+                }
+            }
+            parser.expect("(")?;
+            let params = parser.named_types(")")?;
+            let result = if parser.eat("->") {
+                Some(parser.ty()?)
+            } else {
+                None
+            };
+            interface.functions.push(Function {
+                name,
+                params,
+                result,
+            });
+        }
+    }
+
+    check(&interface)?;
+
+    Ok(interface)
+}
+
+/// Checks that the records used are defined, and that names are unique.
+fn check(interface: &Interface) -> Result<(), Error> {
+    fn check_type(interface: &Interface, ty: &Type) -> Result<(), Error> {
+        match ty {
+            Type::Scalar(_) => Ok(()),
+            Type::List(element) => check_type(interface, element),
+            Type::Record(name) if interface.records.iter().any(|record| record.name == *name) => {
+                Ok(())
+            }
+            Type::Record(name) => Err(Error {
+                line: None,
+                message: format!("unknown type `{}`", name),
+            }),
+        }
+    }
+
+    let mut names = Vec::new();
+    let records = interface.records.iter().map(|record| &record.name);
+    let functions = interface.functions.iter().map(|function| &function.name);
+    for name in records.chain(functions) {
+        if names.contains(&name) {
+            return Err(Error {
+                line: None,
+                message: format!("`{}` is defined twice", name),
+            });
+        }
+        names.push(name);
+    }
+
+    for record in &interface.records {
+        for (_, ty) in &record.fields {
+            check_type(interface, ty)?;
+        }
+    }
+    for function in &interface.functions {
+        for (_, ty) in &function.params {
+            check_type(interface, ty)?;
+        }
+        if let Some(ty) = &function.result {
+            check_type(interface, ty)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_interface() {
+        let interface = parse(
+            "
+            // Geometry.
+            record point { x: s32, y: s32 }
+
+            log: func(message: string)
+            centroid: func(points: list<point>,) -> point
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            interface.records,
+            vec![Record {
+                name: "point".to_string(),
+                fields: vec![
+                    ("x".to_string(), Type::Scalar("s32")),
+                    ("y".to_string(), Type::Scalar("s32")),
+                ],
+            }]
+        );
+        assert_eq!(
+            interface.functions,
+            vec![
+                Function {
+                    name: "log".to_string(),
+                    params: vec![("message".to_string(), Type::Scalar("string"))],
+                    result: None,
+                },
+                Function {
+                    name: "centroid".to_string(),
+                    params: vec![(
+                        "points".to_string(),
+                        Type::List(Box::new(Type::Record("point".to_string())))
+                    )],
+                    result: Some(Type::Record("point".to_string())),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse("log: func(message: string\n").unwrap_err(),
+            Error {
+                line: Some(1),
+                message: "unexpected end of file".to_string()
+            }
+        );
+        assert_eq!(
+            parse("\nlog: fn()").unwrap_err(),
+            Error {
+                line: Some(2),
+                message: "expected `func`, found `fn`".to_string()
+            }
+        );
+        assert_eq!(
+            parse("log: func(message: text)").unwrap_err().message,
+            "unknown type `text`"
+        );
+    }
+}
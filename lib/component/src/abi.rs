@@ -0,0 +1,662 @@
+//! The [canonical ABI], which lowers interface values to core values and
+//! linear memory, and lifts them back.
+//!
+//! Strings are always encoded in UTF-8.
+//!
+//! [canonical ABI]: https://github.com/WebAssembly/component-model/blob/main/design/mvp/CanonicalABI.md
+
+use crate::{ComponentError, FuncType, InterfaceType, InterfaceValue};
+use std::convert::TryInto;
+use wasmer::{Function, Memory, Type, Value};
+
+/// Functions with more flat parameters than this receive them in memory.
+pub(crate) const MAX_FLAT_PARAMS: usize = 16;
+
+/// Functions with more flat results than this return them in memory.
+pub(crate) const MAX_FLAT_RESULTS: usize = 1;
+
+fn align_to(offset: u32, alignment: u32) -> u32 {
+    (offset + alignment - 1) / alignment * alignment
+}
+
+impl InterfaceType {
+    /// The alignment of the type in memory.
+    pub(crate) fn alignment(&self) -> u32 {
+        match self {
+            Self::Bool | Self::S8 | Self::U8 => 1,
+            Self::S16 | Self::U16 => 2,
+            Self::S32 | Self::U32 | Self::Float32 | Self::Char => 4,
+            Self::S64 | Self::U64 | Self::Float64 => 8,
+            Self::String | Self::List(_) => 4,
+            Self::Record(fields) => tuple_alignment(fields.iter().map(|(_, ty)| ty)),
+        }
+    }
+
+    /// The size of the type in memory.
+    pub(crate) fn size(&self) -> u32 {
+        match self {
+            Self::Bool | Self::S8 | Self::U8 => 1,
+            Self::S16 | Self::U16 => 2,
+            Self::S32 | Self::U32 | Self::Float32 | Self::Char => 4,
+            Self::S64 | Self::U64 | Self::Float64 => 8,
+            Self::String | Self::List(_) => 8,
+            Self::Record(fields) => tuple_size(fields.iter().map(|(_, ty)| ty)),
+        }
+    }
+
+    /// Appends the core types the type is flattened to.
+    fn flatten(&self, flat: &mut Vec<Type>) {
+        match self {
+            Self::Bool
+            | Self::S8
+            | Self::U8
+            | Self::S16
+            | Self::U16
+            | Self::S32
+            | Self::U32
+            | Self::Char => flat.push(Type::I32),
+            Self::S64 | Self::U64 => flat.push(Type::I64),
+            Self::Float32 => flat.push(Type::F32),
+            Self::Float64 => flat.push(Type::F64),
+            Self::String | Self::List(_) => flat.extend([Type::I32, Type::I32].iter()),
+            Self::Record(fields) => {
+                for (_, ty) in fields {
+                    ty.flatten(flat);
+                }
+            }
+        }
+    }
+}
+
+fn tuple_alignment<'a>(types: impl IntoIterator<Item = &'a InterfaceType>) -> u32 {
+    types
+        .into_iter()
+        .map(InterfaceType::alignment)
+        .max()
+        .unwrap_or(1)
+}
+
+fn tuple_size<'a>(types: impl IntoIterator<Item = &'a InterfaceType> + Clone) -> u32 {
+    let alignment = tuple_alignment(types.clone());
+    let end = types
+        .into_iter()
+        .fold(0, |offset, ty| align_to(offset, ty.alignment()) + ty.size());
+
+    align_to(end, alignment)
+}
+
+/// The offsets of the fields of a tuple, or of a record.
+fn field_offsets<'a>(types: impl IntoIterator<Item = &'a InterfaceType>) -> Vec<u32> {
+    let mut offset = 0;
+    types
+        .into_iter()
+        .map(|ty| {
+            let field_offset = align_to(offset, ty.alignment());
+            offset = field_offset + ty.size();
+            field_offset
+        })
+        .collect()
+}
+
+/// The core types `types` are flattened to.
+fn flatten_types(types: &[InterfaceType]) -> Vec<Type> {
+    let mut flat = Vec::new();
+    for ty in types {
+        ty.flatten(&mut flat);
+    }
+
+    flat
+}
+
+impl FuncType {
+    /// Whether the parameters are passed in memory rather than as core
+    /// values.
+    pub(crate) fn params_in_memory(&self) -> bool {
+        flatten_types(self.params()).len() > MAX_FLAT_PARAMS
+    }
+
+    /// Whether the results are returned in memory rather than as core
+    /// values.
+    pub(crate) fn results_in_memory(&self) -> bool {
+        flatten_types(self.results()).len() > MAX_FLAT_RESULTS
+    }
+
+    /// The core type of an imported function, i.e. of a host function
+    /// lowered into the component.
+    ///
+    /// Results returned in memory are written at a pointer given as
+    /// the last parameter.
+    pub(crate) fn lowered_core_type(&self) -> wasmer::FunctionType {
+        let mut params = if self.params_in_memory() {
+            vec![Type::I32]
+        } else {
+            flatten_types(self.params())
+        };
+        let results = if self.results_in_memory() {
+            params.push(Type::I32);
+            vec![]
+        } else {
+            flatten_types(self.results())
+        };
+
+        wasmer::FunctionType::new(params, results)
+    }
+}
+
+/// Gives access to the memory of a component, and to its allocator.
+///
+/// The functions passing only scalars don't need a memory.
+pub(crate) struct Context<'a> {
+    pub(crate) memory: Option<&'a Memory>,
+    pub(crate) realloc: Option<&'a Function>,
+}
+
+impl Context<'_> {
+    fn memory(&self) -> Result<&Memory, ComponentError> {
+        self.memory.ok_or(ComponentError::MissingMemory)
+    }
+
+    /// Allocates `size` bytes with `cabi_realloc`.
+    pub(crate) fn realloc(&self, alignment: u32, size: u32) -> Result<u32, ComponentError> {
+        let realloc = self.realloc.ok_or(ComponentError::MissingRealloc)?;
+        let results = realloc.call(&[
+            Value::I32(0),
+            Value::I32(0),
+            Value::I32(alignment as i32),
+            Value::I32(size as i32),
+        ])?;
+        let ptr = match &*results {
+            [Value::I32(ptr)] => *ptr as u32,
+            _ => return Err(ComponentError::CoreTypeMismatch),
+        };
+        self.check_range(ptr, alignment, size as u64)?;
+
+        Ok(ptr)
+    }
+
+    /// Checks that `len` bytes at `ptr` are in the bounds of the memory,
+    /// and that `ptr` is aligned.
+    fn check_range(&self, ptr: u32, alignment: u32, len: u64) -> Result<(), ComponentError> {
+        if ptr % alignment != 0 || ptr as u64 + len > self.memory()?.data_size() {
+            return Err(ComponentError::InvalidPointer(ptr));
+        }
+
+        Ok(())
+    }
+
+    fn read<const N: usize>(&self, ptr: u32) -> Result<[u8; N], ComponentError> {
+        let mut bytes = [0; N];
+        self.memory()?.read(ptr as u64, &mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    fn write(&self, ptr: u32, bytes: &[u8]) -> Result<(), ComponentError> {
+        self.memory()?.write(ptr as u64, bytes)?;
+
+        Ok(())
+    }
+
+    /// Loads a value of type `ty` from memory.
+    fn load(&self, ty: &InterfaceType, ptr: u32) -> Result<InterfaceValue, ComponentError> {
+        Ok(match ty {
+            InterfaceType::Bool => InterfaceValue::Bool(self.read::<1>(ptr)?[0] != 0),
+            InterfaceType::S8 => InterfaceValue::S8(i8::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::U8 => InterfaceValue::U8(u8::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::S16 => InterfaceValue::S16(i16::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::U16 => InterfaceValue::U16(u16::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::S32 => InterfaceValue::S32(i32::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::U32 => InterfaceValue::U32(u32::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::S64 => InterfaceValue::S64(i64::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::U64 => InterfaceValue::U64(u64::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::Float32 => InterfaceValue::Float32(f32::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::Float64 => InterfaceValue::Float64(f64::from_le_bytes(self.read(ptr)?)),
+            InterfaceType::Char => lift_char(u32::from_le_bytes(self.read(ptr)?))?,
+            InterfaceType::String => {
+                let (data, len) = self.load_pair(ptr)?;
+                self.lift_string(data, len)?
+            }
+            InterfaceType::List(element) => {
+                let (data, len) = self.load_pair(ptr)?;
+                self.lift_list(element, data, len)?
+            }
+            InterfaceType::Record(fields) => {
+                let types = fields.iter().map(|(_, ty)| ty);
+                let values = self.load_tuple_at(types, ptr)?;
+                InterfaceValue::Record(
+                    fields
+                        .iter()
+                        .map(|(name, _)| name.clone())
+                        .zip(values)
+                        .collect(),
+                )
+            }
+        })
+    }
+
+    /// Stores `value`, of type `ty`, into memory.
+    fn store(
+        &self,
+        value: &InterfaceValue,
+        ty: &InterfaceType,
+        ptr: u32,
+    ) -> Result<(), ComponentError> {
+        match (value, ty) {
+            (InterfaceValue::Bool(value), InterfaceType::Bool) => self.write(ptr, &[*value as u8]),
+            (InterfaceValue::S8(value), InterfaceType::S8) => self.write(ptr, &value.to_le_bytes()),
+            (InterfaceValue::U8(value), InterfaceType::U8) => self.write(ptr, &value.to_le_bytes()),
+            (InterfaceValue::S16(value), InterfaceType::S16) => {
+                self.write(ptr, &value.to_le_bytes())
+            }
+            (InterfaceValue::U16(value), InterfaceType::U16) => {
+                self.write(ptr, &value.to_le_bytes())
+            }
+            (InterfaceValue::S32(value), InterfaceType::S32) => {
+                self.write(ptr, &value.to_le_bytes())
+            }
+            (InterfaceValue::U32(value), InterfaceType::U32) => {
+                self.write(ptr, &value.to_le_bytes())
+            }
+            (InterfaceValue::S64(value), InterfaceType::S64) => {
+                self.write(ptr, &value.to_le_bytes())
+            }
+            (InterfaceValue::U64(value), InterfaceType::U64) => {
+                self.write(ptr, &value.to_le_bytes())
+            }
+            (InterfaceValue::Float32(value), InterfaceType::Float32) => {
+                self.write(ptr, &value.to_le_bytes())
+            }
+            (InterfaceValue::Float64(value), InterfaceType::Float64) => {
+                self.write(ptr, &value.to_le_bytes())
+            }
+            (InterfaceValue::Char(value), InterfaceType::Char) => {
+                self.write(ptr, &(*value as u32).to_le_bytes())
+            }
+            (InterfaceValue::String(value), InterfaceType::String) => {
+                let (data, len) = self.lower_string(value)?;
+                self.store_pair(ptr, data, len)
+            }
+            (InterfaceValue::List(values), InterfaceType::List(element)) => {
+                let (data, len) = self.lower_list(values, element)?;
+                self.store_pair(ptr, data, len)
+            }
+            (InterfaceValue::Record(values), InterfaceType::Record(fields)) => {
+                check_record(values, fields, value, ty)?;
+                let offsets = field_offsets(fields.iter().map(|(_, ty)| ty));
+                for (((_, value), (_, ty)), offset) in values.iter().zip(fields).zip(offsets) {
+                    self.store(value, ty, ptr + offset)?;
+                }
+
+                Ok(())
+            }
+            _ => Err(type_mismatch(value, ty)),
+        }
+    }
+
+    fn load_pair(&self, ptr: u32) -> Result<(u32, u32), ComponentError> {
+        let first = u32::from_le_bytes(self.read(ptr)?);
+        let second = u32::from_le_bytes(self.read(ptr + 4)?);
+
+        Ok((first, second))
+    }
+
+    fn store_pair(&self, ptr: u32, first: u32, second: u32) -> Result<(), ComponentError> {
+        let mut bytes = [0; 8];
+        bytes[..4].copy_from_slice(&first.to_le_bytes());
+        bytes[4..].copy_from_slice(&second.to_le_bytes());
+
+        self.write(ptr, &bytes)
+    }
+
+    fn lift_string(&self, data: u32, len: u32) -> Result<InterfaceValue, ComponentError> {
+        self.check_range(data, 1, len as u64)?;
+        let mut bytes = vec![0; len as usize];
+        self.memory()?.read(data as u64, &mut bytes)?;
+
+        String::from_utf8(bytes)
+            .map(InterfaceValue::String)
+            .map_err(|_| ComponentError::InvalidUtf8)
+    }
+
+    fn lower_string(&self, value: &str) -> Result<(u32, u32), ComponentError> {
+        let len = value
+            .len()
+            .try_into()
+            .map_err(|_| ComponentError::Overflow)?;
+        let data = self.realloc(1, len)?;
+        self.write(data, value.as_bytes())?;
+
+        Ok((data, len))
+    }
+
+    fn lift_list(
+        &self,
+        element: &InterfaceType,
+        data: u32,
+        len: u32,
+    ) -> Result<InterfaceValue, ComponentError> {
+        let size = element.size();
+        self.check_range(data, element.alignment(), len as u64 * size as u64)?;
+
+        (0..len)
+            .map(|index| self.load(element, data + index * size))
+            .collect::<Result<_, _>>()
+            .map(InterfaceValue::List)
+    }
+
+    fn lower_list(
+        &self,
+        values: &[InterfaceValue],
+        element: &InterfaceType,
+    ) -> Result<(u32, u32), ComponentError> {
+        let size = element.size();
+        let len: u32 = values
+            .len()
+            .try_into()
+            .map_err(|_| ComponentError::Overflow)?;
+        let byte_len = len.checked_mul(size).ok_or(ComponentError::Overflow)?;
+        let data = self.realloc(element.alignment(), byte_len)?;
+        for (index, value) in (0..).zip(values) {
+            self.store(value, element, data + index * size)?;
+        }
+
+        Ok((data, len))
+    }
+
+    fn load_tuple_at<'a>(
+        &self,
+        types: impl IntoIterator<Item = &'a InterfaceType> + Clone,
+        ptr: u32,
+    ) -> Result<Vec<InterfaceValue>, ComponentError> {
+        let offsets = field_offsets(types.clone());
+        types
+            .into_iter()
+            .zip(offsets)
+            .map(|(ty, offset)| self.load(ty, ptr + offset))
+            .collect()
+    }
+
+    /// Loads values of `types`, laid out like the fields of a record,
+    /// from memory.
+    pub(crate) fn load_tuple(
+        &self,
+        types: &[InterfaceType],
+        ptr: u32,
+    ) -> Result<Vec<InterfaceValue>, ComponentError> {
+        self.check_range(ptr, tuple_alignment(types), tuple_size(types) as u64)?;
+
+        self.load_tuple_at(types, ptr)
+    }
+
+    /// Stores `values` of `types`, laid out like the fields of a record,
+    /// into memory.
+    pub(crate) fn store_tuple(
+        &self,
+        values: &[InterfaceValue],
+        types: &[InterfaceType],
+        ptr: u32,
+    ) -> Result<(), ComponentError> {
+        check_count(values, types)?;
+        self.check_range(ptr, tuple_alignment(types), tuple_size(types) as u64)?;
+        for ((value, ty), offset) in values.iter().zip(types).zip(field_offsets(types)) {
+            self.store(value, ty, ptr + offset)?;
+        }
+
+        Ok(())
+    }
+
+    /// Allocates memory with `cabi_realloc` and stores `values` of
+    /// `types` into it, laid out like the fields of a record.
+    pub(crate) fn lower_tuple(
+        &self,
+        values: &[InterfaceValue],
+        types: &[InterfaceType],
+    ) -> Result<u32, ComponentError> {
+        check_count(values, types)?;
+        let ptr = self.realloc(tuple_alignment(types), tuple_size(types))?;
+        self.store_tuple(values, types, ptr)?;
+
+        Ok(ptr)
+    }
+
+    /// Lifts values of `types` from the core values `flat`.
+    pub(crate) fn lift_flat_values(
+        &self,
+        types: &[InterfaceType],
+        flat: &[Value],
+    ) -> Result<Vec<InterfaceValue>, ComponentError> {
+        let mut flat = flat.iter();
+        let values = types
+            .iter()
+            .map(|ty| self.lift_flat(ty, &mut flat))
+            .collect::<Result<_, _>>()?;
+        if flat.next().is_some() {
+            return Err(ComponentError::CoreTypeMismatch);
+        }
+
+        Ok(values)
+    }
+
+    /// Lowers `values` of `types` to core values.
+    pub(crate) fn lower_flat_values(
+        &self,
+        values: &[InterfaceValue],
+        types: &[InterfaceType],
+    ) -> Result<Vec<Value>, ComponentError> {
+        check_count(values, types)?;
+        let mut flat = Vec::new();
+        for (value, ty) in values.iter().zip(types) {
+            self.lower_flat(value, ty, &mut flat)?;
+        }
+
+        Ok(flat)
+    }
+
+    fn lift_flat(
+        &self,
+        ty: &InterfaceType,
+        flat: &mut std::slice::Iter<'_, Value>,
+    ) -> Result<InterfaceValue, ComponentError> {
+        Ok(match ty {
+            InterfaceType::Bool => InterfaceValue::Bool(next_i32(flat)? != 0),
+            InterfaceType::S8 => InterfaceValue::S8(next_i32(flat)? as i8),
+            InterfaceType::U8 => InterfaceValue::U8(next_i32(flat)? as u8),
+            InterfaceType::S16 => InterfaceValue::S16(next_i32(flat)? as i16),
+            InterfaceType::U16 => InterfaceValue::U16(next_i32(flat)? as u16),
+            InterfaceType::S32 => InterfaceValue::S32(next_i32(flat)?),
+            InterfaceType::U32 => InterfaceValue::U32(next_i32(flat)? as u32),
+            InterfaceType::S64 => InterfaceValue::S64(next_i64(flat)?),
+            InterfaceType::U64 => InterfaceValue::U64(next_i64(flat)? as u64),
+            InterfaceType::Float32 => match flat.next() {
+                Some(Value::F32(value)) => InterfaceValue::Float32(*value),
+                _ => return Err(ComponentError::CoreTypeMismatch),
+            },
+            InterfaceType::Float64 => match flat.next() {
+                Some(Value::F64(value)) => InterfaceValue::Float64(*value),
+                _ => return Err(ComponentError::CoreTypeMismatch),
+            },
+            InterfaceType::Char => lift_char(next_i32(flat)? as u32)?,
+            InterfaceType::String => {
+                let data = next_i32(flat)? as u32;
+                let len = next_i32(flat)? as u32;
+                self.lift_string(data, len)?
+            }
+            InterfaceType::List(element) => {
+                let data = next_i32(flat)? as u32;
+                let len = next_i32(flat)? as u32;
+                self.lift_list(element, data, len)?
+            }
+            InterfaceType::Record(fields) => InterfaceValue::Record(
+                fields
+                    .iter()
+                    .map(|(name, ty)| Ok((name.clone(), self.lift_flat(ty, flat)?)))
+                    .collect::<Result<_, ComponentError>>()?,
+            ),
+        })
+    }
+
+    fn lower_flat(
+        &self,
+        value: &InterfaceValue,
+        ty: &InterfaceType,
+        flat: &mut Vec<Value>,
+    ) -> Result<(), ComponentError> {
+        match (value, ty) {
+            (InterfaceValue::Bool(value), InterfaceType::Bool) => {
+                flat.push(Value::I32(*value as i32))
+            }
+            (InterfaceValue::S8(value), InterfaceType::S8) => flat.push(Value::I32(*value as i32)),
+            (InterfaceValue::U8(value), InterfaceType::U8) => flat.push(Value::I32(*value as i32)),
+            (InterfaceValue::S16(value), InterfaceType::S16) => {
+                flat.push(Value::I32(*value as i32))
+            }
+            (InterfaceValue::U16(value), InterfaceType::U16) => {
+                flat.push(Value::I32(*value as i32))
+            }
+            (InterfaceValue::S32(value), InterfaceType::S32) => flat.push(Value::I32(*value)),
+            (InterfaceValue::U32(value), InterfaceType::U32) => {
+                flat.push(Value::I32(*value as i32))
+            }
+            (InterfaceValue::S64(value), InterfaceType::S64) => flat.push(Value::I64(*value)),
+            (InterfaceValue::U64(value), InterfaceType::U64) => {
+                flat.push(Value::I64(*value as i64))
+            }
+            (InterfaceValue::Float32(value), InterfaceType::Float32) => {
+                flat.push(Value::F32(*value))
+            }
+            (InterfaceValue::Float64(value), InterfaceType::Float64) => {
+                flat.push(Value::F64(*value))
+            }
+            (InterfaceValue::Char(value), InterfaceType::Char) => {
+                flat.push(Value::I32(*value as u32 as i32))
+            }
+            (InterfaceValue::String(value), InterfaceType::String) => {
+                let (data, len) = self.lower_string(value)?;
+                flat.extend(vec![Value::I32(data as i32), Value::I32(len as i32)]);
+            }
+            (InterfaceValue::List(values), InterfaceType::List(element)) => {
+                let (data, len) = self.lower_list(values, element)?;
+                flat.extend(vec![Value::I32(data as i32), Value::I32(len as i32)]);
+            }
+            (InterfaceValue::Record(values), InterfaceType::Record(fields)) => {
+                check_record(values, fields, value, ty)?;
+                for ((_, value), (_, ty)) in values.iter().zip(fields) {
+                    self.lower_flat(value, ty, flat)?;
+                }
+            }
+            _ => return Err(type_mismatch(value, ty)),
+        }
+
+        Ok(())
+    }
+}
+
+fn next_i32(flat: &mut std::slice::Iter<'_, Value>) -> Result<i32, ComponentError> {
+    match flat.next() {
+        Some(Value::I32(value)) => Ok(*value),
+        _ => Err(ComponentError::CoreTypeMismatch),
+    }
+}
+
+fn next_i64(flat: &mut std::slice::Iter<'_, Value>) -> Result<i64, ComponentError> {
+    match flat.next() {
+        Some(Value::I64(value)) => Ok(*value),
+        _ => Err(ComponentError::CoreTypeMismatch),
+    }
+}
+
+fn lift_char(value: u32) -> Result<InterfaceValue, ComponentError> {
+    std::char::from_u32(value)
+        .map(InterfaceValue::Char)
+        .ok_or(ComponentError::InvalidChar(value))
+}
+
+fn check_count(values: &[InterfaceValue], types: &[InterfaceType]) -> Result<(), ComponentError> {
+    if values.len() != types.len() {
+        return Err(ComponentError::ValueCount {
+            expected: types.len(),
+            actual: values.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks that the fields of a record value are the fields of its type.
+fn check_record(
+    values: &[(String, InterfaceValue)],
+    fields: &[(String, InterfaceType)],
+    value: &InterfaceValue,
+    ty: &InterfaceType,
+) -> Result<(), ComponentError> {
+    if values.len() != fields.len()
+        || values
+            .iter()
+            .zip(fields)
+            .any(|((value_name, _), (field_name, _))| value_name != field_name)
+    {
+        return Err(type_mismatch(value, ty));
+    }
+
+    Ok(())
+}
+
+fn type_mismatch(value: &InterfaceValue, ty: &InterfaceType) -> ComponentError {
+    ComponentError::TypeMismatch {
+        expected: ty.clone(),
+        value: value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn layout() {
+        let record = InterfaceType::Record(vec![
+            ("flag".to_string(), InterfaceType::Bool),
+            ("count".to_string(), InterfaceType::U64),
+            ("name".to_string(), InterfaceType::String),
+            ("tag".to_string(), InterfaceType::U16),
+        ]);
+
+        assert_eq!(record.alignment(), 8);
+        assert_eq!(record.size(), 32);
+        assert_eq!(
+            field_offsets(
+                [
+                    InterfaceType::Bool,
+                    InterfaceType::U64,
+                    InterfaceType::String,
+                    InterfaceType::U16
+                ]
+                .iter()
+            ),
+            vec![0, 8, 16, 24]
+        );
+        assert_eq!(
+            flatten_types(&[record, InterfaceType::List(Box::new(InterfaceType::Char))]),
+            vec![
+                Type::I32,
+                Type::I64,
+                Type::I32,
+                Type::I32,
+                Type::I32,
+                Type::I32,
+                Type::I32
+            ]
+        );
+    }
+
+    #[test]
+    fn spilled_params() {
+        let ty = FuncType::new(vec![InterfaceType::String; 9], vec![InterfaceType::String]);
+        assert!(ty.params_in_memory());
+        assert!(ty.results_in_memory());
+        assert_eq!(
+            ty.lowered_core_type(),
+            wasmer::FunctionType::new(vec![Type::I32, Type::I32], vec![])
+        );
+    }
+}
@@ -0,0 +1,889 @@
+//! A parser of [component binaries].
+//!
+//! Only what's needed to run simple components is supported: core
+//! modules and their instances, imported host functions and instances
+//! of host functions, functions lifted out of core instances and
+//! lowered into them, and the value types of [`InterfaceType`]. The
+//! other constructs, e.g. nested components, resources or variants,
+//! are reported as [`ComponentError::Unsupported`].
+//!
+//! The definitions are returned in their order in the binary, which is
+//! the order they are instantiated in, with their types resolved.
+//!
+//! [component binaries]: https://github.com/WebAssembly/component-model/blob/main/design/mvp/Binary.md
+
+use crate::{ComponentError, FuncType, InterfaceType};
+use std::fmt;
+
+/// The version and the layer of the component binaries, after the magic.
+const COMPONENT_HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00];
+
+/// The namespace of the functions imported by the component itself,
+/// rather than by one of the instances it imports.
+pub(crate) const ROOT_NAMESPACE: &str = "$root";
+
+/// A sort of the core index spaces an alias or an export can refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CoreSort {
+    Func,
+    Table,
+    Memory,
+    Global,
+}
+
+/// The options of a lifted or lowered function, as indices of core
+/// items.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CanonOptions {
+    pub(crate) memory: Option<u32>,
+    pub(crate) realloc: Option<u32>,
+    pub(crate) post_return: Option<u32>,
+}
+
+/// A definition of a component which creates something at
+/// instantiation.
+#[derive(Debug, Clone)]
+pub(crate) enum Definition {
+    /// Instantiates a core module, with the exports of core instances
+    /// as its imports, by module name.
+    CoreInstantiate {
+        module: u32,
+        args: Vec<(String, u32)>,
+    },
+    /// Bundles core items into a core instance.
+    CoreInlineInstance(Vec<(String, CoreSort, u32)>),
+    /// Aliases an export of a core instance.
+    CoreAlias {
+        instance: u32,
+        name: String,
+        sort: CoreSort,
+    },
+    /// Imports a host function of the root namespace.
+    ImportFunc { name: String, ty: FuncType },
+    /// Imports an instance of host functions.
+    ImportInstance { name: String },
+    /// Aliases a function of an imported instance.
+    AliasFunc {
+        instance: u32,
+        name: String,
+        ty: FuncType,
+    },
+    /// Lifts a core function into a component function.
+    Lift {
+        core_func: u32,
+        options: CanonOptions,
+        ty: FuncType,
+    },
+    /// Lowers a component function into a core function.
+    Lower {
+        func: u32,
+        options: CanonOptions,
+        ty: FuncType,
+    },
+    /// Exports a component function.
+    ExportFunc { name: String, func: u32 },
+}
+
+/// A type of the type index space.
+#[derive(Debug, Clone)]
+enum TypeDef {
+    Value(InterfaceType),
+    Func(FuncType),
+    Instance(Vec<(String, InstanceExport)>),
+}
+
+/// What an instance type exports.
+#[derive(Debug, Clone)]
+enum InstanceExport {
+    Func(FuncType),
+    Type(TypeDef),
+}
+
+/// What an import or an export is declared as.
+enum ExternDesc {
+    Func(u32),
+    /// A type equal to the type at the index.
+    TypeEq(u32),
+    Instance(u32),
+}
+
+/// A component binary, parsed.
+#[derive(Debug, Default)]
+pub(crate) struct ParsedComponent<'data> {
+    /// The binaries of the core modules, in the order of their index.
+    pub(crate) modules: Vec<&'data [u8]>,
+    pub(crate) definitions: Vec<Definition>,
+    /// The host functions imported, by namespace and name.
+    pub(crate) imports: Vec<(String, String, FuncType)>,
+    pub(crate) exports: Vec<(String, FuncType)>,
+}
+
+/// Parses the component binary `bytes`.
+pub(crate) fn parse(bytes: &[u8]) -> Result<ParsedComponent<'_>, ComponentError> {
+    let mut reader = Reader::new(bytes, 0);
+    match reader.bytes(COMPONENT_HEADER.len()) {
+        Ok(header) if header == COMPONENT_HEADER => {}
+        Ok(header) if header[..4] == COMPONENT_HEADER[..4] && header[6..] == [0, 0] => {
+            return Err(reader.error_at(4, "this is a core module, see `ComponentInstance::new`"))
+        }
+        _ => return Err(reader.error_at(0, "not a component binary")),
+    }
+
+    let mut parser = Parser::default();
+    while !reader.eof() {
+        let id = reader.byte()?;
+        let size = reader.u32()? as usize;
+        let offset = reader.offset();
+        let mut section = Reader::new(reader.bytes(size)?, offset);
+        parser.section(id, &mut section)?;
+        if !section.eof() {
+            return Err(section.error("unexpected data at the end of the section"));
+        }
+    }
+
+    Ok(parser.component)
+}
+
+/// The index spaces of the component being parsed which are resolved
+/// while parsing.
+#[derive(Default)]
+struct Parser<'data> {
+    component: ParsedComponent<'data>,
+    types: Vec<TypeDef>,
+    funcs: Vec<FuncType>,
+    /// The exports of the imported instances.
+    instances: Vec<Vec<(String, InstanceExport)>>,
+    /// The lengths of the core index spaces, to check the indices the
+    /// definitions refer to.
+    core_instances: u32,
+    core_funcs: u32,
+    core_tables: u32,
+    core_memories: u32,
+    core_globals: u32,
+}
+
+impl<'data> Parser<'data> {
+    fn section(&mut self, id: u8, reader: &mut Reader<'data>) -> Result<(), ComponentError> {
+        match id {
+            // Custom sections, e.g. the names, are ignored.
+            0 => {
+                reader.bytes(reader.remaining())?;
+            }
+            1 => {
+                let module = reader.bytes(reader.remaining())?;
+                self.component.modules.push(module);
+            }
+            2 => {
+                for _ in 0..reader.u32()? {
+                    self.core_instance(reader)?;
+                }
+            }
+            3 => return Err(unsupported("core type definitions")),
+            4 => return Err(unsupported("nested components")),
+            5 => return Err(unsupported("component instances")),
+            6 => {
+                for _ in 0..reader.u32()? {
+                    self.alias(reader)?;
+                }
+            }
+            7 => {
+                for _ in 0..reader.u32()? {
+                    let ty = deftype(reader, &self.types, &[])?;
+                    self.types.push(ty);
+                }
+            }
+            8 => {
+                for _ in 0..reader.u32()? {
+                    self.canon(reader)?;
+                }
+            }
+            9 => return Err(unsupported("start functions")),
+            10 => {
+                for _ in 0..reader.u32()? {
+                    self.import(reader)?;
+                }
+            }
+            11 => {
+                for _ in 0..reader.u32()? {
+                    self.export(reader)?;
+                }
+            }
+            12 => return Err(unsupported("values")),
+            _ => return Err(reader.error_at(0, format!("unknown section {}", id))),
+        }
+
+        Ok(())
+    }
+
+    fn core_instance(&mut self, reader: &mut Reader) -> Result<(), ComponentError> {
+        let definition = match reader.byte()? {
+            0x00 => {
+                let module = reader.u32()?;
+                check_index(
+                    reader,
+                    module,
+                    self.component.modules.len() as u32,
+                    "module",
+                )?;
+                let core_instances = self.core_instances;
+                let args = reader.vec(|reader| {
+                    let name = reader.string()?;
+                    reader.expect(0x12, "instantiation argument")?;
+                    let instance = reader.u32()?;
+                    check_index(reader, instance, core_instances, "core instance")?;
+                    Ok((name, instance))
+                })?;
+                Definition::CoreInstantiate { module, args }
+            }
+            0x01 => Definition::CoreInlineInstance(reader.vec(|reader| {
+                let name = reader.string()?;
+                let sort = core_sort(reader)?;
+                let index = reader.u32()?;
+                self.check_core_index(reader, sort, index)?;
+                Ok((name, sort, index))
+            })?),
+            byte => return Err(reader.invalid_byte(byte, "core instance")),
+        };
+        self.core_instances += 1;
+        self.component.definitions.push(definition);
+
+        Ok(())
+    }
+
+    fn alias(&mut self, reader: &mut Reader) -> Result<(), ComponentError> {
+        let sort = reader.byte()?;
+        if sort == 0x00 {
+            let sort = core_sort(reader)?;
+            return match reader.byte()? {
+                0x01 => {
+                    let instance = reader.u32()?;
+                    check_index(reader, instance, self.core_instances, "core instance")?;
+                    let name = reader.string()?;
+                    *self.core_len(sort) += 1;
+                    self.component.definitions.push(Definition::CoreAlias {
+                        instance,
+                        name,
+                        sort,
+                    });
+                    Ok(())
+                }
+                0x02 => Err(unsupported("outer aliases")),
+                byte => Err(reader.invalid_byte(byte, "core alias")),
+            };
+        }
+
+        match reader.byte()? {
+            0x00 => {}
+            0x01 => return Err(reader.error("core export alias of a component sort")),
+            0x02 => return Err(unsupported("outer aliases")),
+            byte => return Err(reader.invalid_byte(byte, "alias")),
+        }
+        let instance = reader.u32()?;
+        let name = reader.string()?;
+        let export = self
+            .instances
+            .get(instance as usize)
+            .ok_or_else(|| reader.error(format!("unknown instance {}", instance)))?
+            .iter()
+            .find(|(export_name, _)| *export_name == name)
+            .map(|(_, export)| export.clone())
+            .ok_or_else(|| {
+                reader.error(format!("instance {} has no export `{}`", instance, name))
+            })?;
+        match (sort, export) {
+            (0x01, InstanceExport::Func(ty)) => {
+                self.funcs.push(ty.clone());
+                self.component
+                    .definitions
+                    .push(Definition::AliasFunc { instance, name, ty });
+            }
+            (0x03, InstanceExport::Type(ty)) => self.types.push(ty),
+            (0x01, _) | (0x03, _) => {
+                return Err(reader.error(format!("export `{}` has another sort", name)))
+            }
+            (sort, _) => return Err(unsupported_sort(reader, sort)),
+        }
+
+        Ok(())
+    }
+
+    fn canon(&mut self, reader: &mut Reader) -> Result<(), ComponentError> {
+        let definition = match reader.byte()? {
+            0x00 => {
+                reader.expect(0x00, "canonical lift")?;
+                let core_func = reader.u32()?;
+                self.check_core_index(reader, CoreSort::Func, core_func)?;
+                let options = self.canon_options(reader)?;
+                let ty = reader.u32()?;
+                let ty = match get(reader, &self.types, ty, "type")? {
+                    TypeDef::Func(ty) => ty,
+                    _ => return Err(reader.error("lifting to a type which isn't a function")),
+                };
+                self.funcs.push(ty.clone());
+                Definition::Lift {
+                    core_func,
+                    options,
+                    ty,
+                }
+            }
+            0x01 => {
+                reader.expect(0x00, "canonical lower")?;
+                let func = reader.u32()?;
+                let ty = get(reader, &self.funcs, func, "function")?;
+                let options = self.canon_options(reader)?;
+                self.core_funcs += 1;
+                Definition::Lower { func, options, ty }
+            }
+            _ => return Err(unsupported("resources and async built-ins")),
+        };
+        self.component.definitions.push(definition);
+
+        Ok(())
+    }
+
+    fn canon_options(&self, reader: &mut Reader) -> Result<CanonOptions, ComponentError> {
+        let mut options = CanonOptions::default();
+        for _ in 0..reader.u32()? {
+            match reader.byte()? {
+                0x00 => {}
+                0x01 | 0x02 => return Err(unsupported("UTF-16 strings")),
+                0x03 => {
+                    let memory = reader.u32()?;
+                    self.check_core_index(reader, CoreSort::Memory, memory)?;
+                    options.memory = Some(memory);
+                }
+                option @ 0x04 | option @ 0x05 => {
+                    let func = reader.u32()?;
+                    self.check_core_index(reader, CoreSort::Func, func)?;
+                    if option == 0x04 {
+                        options.realloc = Some(func);
+                    } else {
+                        options.post_return = Some(func);
+                    }
+                }
+                0x06 | 0x07 => return Err(unsupported("async functions")),
+                byte => return Err(reader.invalid_byte(byte, "canonical option")),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn import(&mut self, reader: &mut Reader) -> Result<(), ComponentError> {
+        let name = extern_name(reader)?;
+        match extern_desc(reader)? {
+            ExternDesc::Func(ty) => {
+                let ty = match get(reader, &self.types, ty, "type")? {
+                    TypeDef::Func(ty) => ty,
+                    _ => return Err(reader.error("importing a function of a non-function type")),
+                };
+                self.funcs.push(ty.clone());
+                self.component
+                    .imports
+                    .push((ROOT_NAMESPACE.to_string(), name.clone(), ty.clone()));
+                self.component
+                    .definitions
+                    .push(Definition::ImportFunc { name, ty });
+            }
+            ExternDesc::Instance(ty) => {
+                let exports = match get(reader, &self.types, ty, "type")? {
+                    TypeDef::Instance(exports) => exports,
+                    _ => return Err(reader.error("importing an instance of a non-instance type")),
+                };
+                for (export_name, export) in &exports {
+                    if let InstanceExport::Func(ty) = export {
+                        self.component.imports.push((
+                            name.clone(),
+                            export_name.clone(),
+                            ty.clone(),
+                        ));
+                    }
+                }
+                self.instances.push(exports);
+                self.component
+                    .definitions
+                    .push(Definition::ImportInstance { name });
+            }
+            ExternDesc::TypeEq(ty) => {
+                let ty = get(reader, &self.types, ty, "type")?;
+                self.types.push(ty);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn export(&mut self, reader: &mut Reader) -> Result<(), ComponentError> {
+        let name = extern_name(reader)?;
+        let sort = reader.byte()?;
+        let index = reader.u32()?;
+        // The type ascribed to the export, if any, is the one of the
+        // item.
+        match reader.byte()? {
+            0x00 => {}
+            0x01 => {
+                extern_desc(reader)?;
+            }
+            byte => return Err(reader.invalid_byte(byte, "export type")),
+        }
+
+        match sort {
+            0x01 => {
+                let ty = get(reader, &self.funcs, index, "function")?;
+                self.funcs.push(ty.clone());
+                self.component.exports.push((name.clone(), ty));
+                self.component
+                    .definitions
+                    .push(Definition::ExportFunc { name, func: index });
+            }
+            0x03 => {
+                let ty = get(reader, &self.types, index, "type")?;
+                self.types.push(ty);
+            }
+            sort => return Err(unsupported_sort(reader, sort)),
+        }
+
+        Ok(())
+    }
+
+    fn core_len(&mut self, sort: CoreSort) -> &mut u32 {
+        match sort {
+            CoreSort::Func => &mut self.core_funcs,
+            CoreSort::Table => &mut self.core_tables,
+            CoreSort::Memory => &mut self.core_memories,
+            CoreSort::Global => &mut self.core_globals,
+        }
+    }
+
+    fn check_core_index(
+        &self,
+        reader: &Reader,
+        sort: CoreSort,
+        index: u32,
+    ) -> Result<(), ComponentError> {
+        let (len, what) = match sort {
+            CoreSort::Func => (self.core_funcs, "core function"),
+            CoreSort::Table => (self.core_tables, "core table"),
+            CoreSort::Memory => (self.core_memories, "core memory"),
+            CoreSort::Global => (self.core_globals, "core global"),
+        };
+        check_index(reader, index, len, what)
+    }
+}
+
+/// Parses a type definition, in the type index space `types`, nested
+/// in a type whose enclosing type index space is `outer`.
+fn deftype(
+    reader: &mut Reader,
+    types: &[TypeDef],
+    outer: &[TypeDef],
+) -> Result<TypeDef, ComponentError> {
+    match reader.peek()? {
+        0x40 => {
+            reader.byte()?;
+            let params = reader.vec(|reader| {
+                reader.string()?;
+                valtype(reader, types)
+            })?;
+            let results = match reader.byte()? {
+                0x00 => vec![valtype(reader, types)?],
+                0x01 => reader.vec(|reader| {
+                    reader.string()?;
+                    valtype(reader, types)
+                })?,
+                byte => return Err(reader.invalid_byte(byte, "result list")),
+            };
+            Ok(TypeDef::Func(FuncType::new(params, results)))
+        }
+        0x41 => Err(unsupported("component types")),
+        0x42 => {
+            reader.byte()?;
+            instance_type(reader, types)
+        }
+        0x3e | 0x3f => Err(unsupported("resources")),
+        0x43 => Err(unsupported("async functions")),
+        _ if !outer.is_empty() => Err(reader.error("nested instance types")),
+        _ => Ok(TypeDef::Value(defvaltype(reader, types)?)),
+    }
+}
+
+/// Parses the declarations of an instance type, nested in the type
+/// index space `outer`.
+fn instance_type(reader: &mut Reader, outer: &[TypeDef]) -> Result<TypeDef, ComponentError> {
+    let mut types = Vec::new();
+    let mut exports = Vec::new();
+    for _ in 0..reader.u32()? {
+        match reader.byte()? {
+            0x00 => return Err(unsupported("core types")),
+            0x01 => {
+                let ty = match reader.peek()? {
+                    0x40 => deftype(reader, &types, outer)?,
+                    0x41 | 0x42 => return Err(unsupported("nested instance types")),
+                    _ => deftype(reader, &types, &[])?,
+                };
+                types.push(ty);
+            }
+            0x02 => {
+                // Only the outer aliases of types can appear in types.
+                reader.expect(0x03, "type alias")?;
+                reader.expect(0x02, "outer alias")?;
+                if reader.u32()? != 1 {
+                    return Err(unsupported("outer aliases beyond the enclosing component"));
+                }
+                let ty = reader.u32()?;
+                let ty = get(reader, outer, ty, "type")?;
+                types.push(ty);
+            }
+            0x04 => {
+                let name = extern_name(reader)?;
+                let export = match extern_desc(reader)? {
+                    ExternDesc::Func(ty) => match get(reader, &types, ty, "type")? {
+                        TypeDef::Func(ty) => InstanceExport::Func(ty),
+                        _ => {
+                            return Err(reader.error("exporting a function of a non-function type"))
+                        }
+                    },
+                    ExternDesc::TypeEq(ty) => {
+                        let ty = get(reader, &types, ty, "type")?;
+                        types.push(ty.clone());
+                        InstanceExport::Type(ty)
+                    }
+                    ExternDesc::Instance(_) => return Err(unsupported("nested instances")),
+                };
+                exports.push((name, export));
+            }
+            byte => return Err(reader.invalid_byte(byte, "instance type declaration")),
+        }
+    }
+
+    Ok(TypeDef::Instance(exports))
+}
+
+/// Parses a defined value type.
+fn defvaltype(reader: &mut Reader, types: &[TypeDef]) -> Result<InterfaceType, ComponentError> {
+    if let Some(ty) = primvaltype(reader.peek()?) {
+        reader.byte()?;
+        return Ok(ty);
+    }
+
+    match reader.byte()? {
+        0x72 => Ok(InterfaceType::Record(reader.vec(|reader| {
+            let name = reader.string()?;
+            Ok((name, valtype(reader, types)?))
+        })?)),
+        0x70 => Ok(InterfaceType::List(Box::new(valtype(reader, types)?))),
+        0x71 => Err(unsupported("variants")),
+        0x6f => Err(unsupported("tuples")),
+        0x6e => Err(unsupported("flags")),
+        0x6d => Err(unsupported("enums")),
+        0x6b => Err(unsupported("options")),
+        0x6a => Err(unsupported("results")),
+        0x69 | 0x68 => Err(unsupported("resources")),
+        0x64..=0x67 => Err(unsupported("async types")),
+        byte => Err(reader.invalid_byte(byte, "value type")),
+    }
+}
+
+/// Parses a value type: a primitive type, or the index of a defined
+/// value type.
+fn valtype(reader: &mut Reader, types: &[TypeDef]) -> Result<InterfaceType, ComponentError> {
+    if let Some(ty) = primvaltype(reader.peek()?) {
+        reader.byte()?;
+        return Ok(ty);
+    }
+
+    let index = reader.s33()?;
+    if index < 0 {
+        return Err(reader.error(format!("unknown primitive type {}", index)));
+    }
+    match get(reader, types, index as u32, "type")? {
+        TypeDef::Value(ty) => Ok(ty),
+        _ => Err(reader.error(format!("type {} isn't a value type", index))),
+    }
+}
+
+fn primvaltype(byte: u8) -> Option<InterfaceType> {
+    Some(match byte {
+        0x7f => InterfaceType::Bool,
+        0x7e => InterfaceType::S8,
+        0x7d => InterfaceType::U8,
+        0x7c => InterfaceType::S16,
+        0x7b => InterfaceType::U16,
+        0x7a => InterfaceType::S32,
+        0x79 => InterfaceType::U32,
+        0x78 => InterfaceType::S64,
+        0x77 => InterfaceType::U64,
+        0x76 => InterfaceType::Float32,
+        0x75 => InterfaceType::Float64,
+        0x74 => InterfaceType::Char,
+        0x73 => InterfaceType::String,
+        _ => return None,
+    })
+}
+
+fn core_sort(reader: &mut Reader) -> Result<CoreSort, ComponentError> {
+    match reader.byte()? {
+        0x00 => Ok(CoreSort::Func),
+        0x01 => Ok(CoreSort::Table),
+        0x02 => Ok(CoreSort::Memory),
+        0x03 => Ok(CoreSort::Global),
+        0x10 | 0x11 | 0x12 => Err(unsupported("core types, modules or instances as items")),
+        byte => Err(reader.invalid_byte(byte, "core sort")),
+    }
+}
+
+/// The error of a sort of item, other than a function or a type, which
+/// isn't supported.
+fn unsupported_sort(reader: &Reader, sort: u8) -> ComponentError {
+    match sort {
+        0x00 => unsupported("core items as component items"),
+        0x02 => unsupported("values"),
+        0x04 => unsupported("nested components"),
+        0x05 => unsupported("instance exports"),
+        byte => reader.error(format!("invalid sort {:#04x}", byte)),
+    }
+}
+
+/// Parses the name of an import or an export.
+fn extern_name(reader: &mut Reader) -> Result<String, ComponentError> {
+    match reader.byte()? {
+        0x00 => reader.string(),
+        // The name has a version suffix.
+        0x01 => {
+            let name = reader.string()?;
+            reader.string()?;
+            Ok(name)
+        }
+        byte => Err(reader.invalid_byte(byte, "name")),
+    }
+}
+
+fn extern_desc(reader: &mut Reader) -> Result<ExternDesc, ComponentError> {
+    match reader.byte()? {
+        0x00 => Err(unsupported("core modules as imports or exports")),
+        0x01 => Ok(ExternDesc::Func(reader.u32()?)),
+        0x02 => Err(unsupported("values")),
+        0x03 => match reader.byte()? {
+            0x00 => Ok(ExternDesc::TypeEq(reader.u32()?)),
+            0x01 => Err(unsupported("resources")),
+            byte => Err(reader.invalid_byte(byte, "type bound")),
+        },
+        0x04 => Err(unsupported("nested components")),
+        0x05 => Ok(ExternDesc::Instance(reader.u32()?)),
+        byte => Err(reader.invalid_byte(byte, "extern description")),
+    }
+}
+
+/// Returns the item at `index` of the index space `items` of `what`.
+fn get<T: Clone>(
+    reader: &Reader,
+    items: &[T],
+    index: u32,
+    what: &str,
+) -> Result<T, ComponentError> {
+    items
+        .get(index as usize)
+        .cloned()
+        .ok_or_else(|| reader.error(format!("unknown {} {}", what, index)))
+}
+
+fn check_index(reader: &Reader, index: u32, len: u32, what: &str) -> Result<(), ComponentError> {
+    if index < len {
+        Ok(())
+    } else {
+        Err(reader.error(format!("unknown {} {}", what, index)))
+    }
+}
+
+fn unsupported(feature: &str) -> ComponentError {
+    ComponentError::Unsupported(feature.to_string())
+}
+
+/// Reads the bytes of a section, knowing their offset in the binary.
+struct Reader<'data> {
+    bytes: &'data [u8],
+    position: usize,
+    /// The offset of `bytes` in the binary.
+    base: usize,
+}
+
+impl<'data> Reader<'data> {
+    fn new(bytes: &'data [u8], base: usize) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            base,
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.base + self.position
+    }
+
+    fn eof(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn error(&self, message: impl fmt::Display) -> ComponentError {
+        ComponentError::InvalidComponent {
+            offset: self.offset(),
+            message: message.to_string(),
+        }
+    }
+
+    fn error_at(&self, offset: usize, message: impl fmt::Display) -> ComponentError {
+        ComponentError::InvalidComponent {
+            offset: self.base + offset,
+            message: message.to_string(),
+        }
+    }
+
+    fn invalid_byte(&self, byte: u8, what: &str) -> ComponentError {
+        self.error_at(
+            self.position - 1,
+            format!("invalid byte {:#04x} for {}", byte, what),
+        )
+    }
+
+    fn peek(&self) -> Result<u8, ComponentError> {
+        self.bytes
+            .get(self.position)
+            .copied()
+            .ok_or_else(|| self.error("unexpected end"))
+    }
+
+    fn byte(&mut self) -> Result<u8, ComponentError> {
+        let byte = self.peek()?;
+        self.position += 1;
+
+        Ok(byte)
+    }
+
+    fn expect(&mut self, expected: u8, what: &str) -> Result<(), ComponentError> {
+        match self.byte()? {
+            byte if byte == expected => Ok(()),
+            byte => Err(self.invalid_byte(byte, what)),
+        }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'data [u8], ComponentError> {
+        if len > self.remaining() {
+            return Err(self.error("unexpected end"));
+        }
+        let bytes = &self.bytes[self.position..self.position + len];
+        self.position += len;
+
+        Ok(bytes)
+    }
+
+    /// Reads an unsigned LEB128 integer.
+    fn u32(&mut self) -> Result<u32, ComponentError> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            if shift == 28 && byte >> 4 != 0 {
+                return Err(self.error("integer too large"));
+            }
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        unreachable!()
+    }
+
+    /// Reads a signed LEB128 integer of 33 bits.
+    fn s33(&mut self) -> Result<i64, ComponentError> {
+        let mut value = 0i64;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as i64) << shift;
+            if byte & 0x80 == 0 {
+                // Sign-extends the value from its last bit read.
+                let bits = shift + 7;
+                return Ok(if bits < 64 {
+                    (value << (64 - bits)) >> (64 - bits)
+                } else {
+                    value
+                });
+            }
+            if shift == 28 {
+                return Err(self.error("integer too large"));
+            }
+        }
+
+        unreachable!()
+    }
+
+    fn string(&mut self) -> Result<String, ComponentError> {
+        let len = self.u32()? as usize;
+        let offset = self.position;
+        std::str::from_utf8(self.bytes(len)?)
+            .map(str::to_string)
+            .map_err(|_| self.error_at(offset, "invalid UTF-8 name"))
+    }
+
+    /// Reads a vector of items read by `item`.
+    fn vec<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ComponentError>,
+    ) -> Result<Vec<T>, ComponentError> {
+        let len = self.u32()? as usize;
+        // Every item takes at least a byte.
+        let mut items = Vec::with_capacity(len.min(self.remaining()));
+        for _ in 0..len {
+            items.push(item(self)?);
+        }
+
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn leb128() {
+        let mut reader = Reader::new(&[0xe5, 0x8e, 0x26, 0x7f, 0xc0, 0x00], 0);
+        assert_eq!(reader.u32().unwrap(), 624485);
+        assert_eq!(reader.s33().unwrap(), -1);
+        assert_eq!(reader.s33().unwrap(), 64);
+        assert!(reader.eof());
+
+        let mut reader = Reader::new(&[0xff, 0xff, 0xff, 0xff, 0x1f], 0);
+        assert!(matches!(
+            reader.u32(),
+            Err(ComponentError::InvalidComponent { offset: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn headers() {
+        assert!(matches!(
+            parse(b"\0asm\x01\0\0\0"),
+            Err(ComponentError::InvalidComponent { offset: 4, .. })
+        ));
+        assert!(matches!(
+            parse(b"\0wat"),
+            Err(ComponentError::InvalidComponent { offset: 0, .. })
+        ));
+        assert!(parse(&COMPONENT_HEADER).unwrap().definitions.is_empty());
+    }
+
+    #[test]
+    fn unsupported_types() {
+        // A type section defining an option.
+        let mut bytes = COMPONENT_HEADER.to_vec();
+        bytes.extend(&[0x07, 0x03, 0x01, 0x6b, 0x79]);
+        assert!(matches!(
+            parse(&bytes),
+            Err(ComponentError::Unsupported(feature)) if feature == "options"
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::abi::Context;
+use crate::binary::{self, CanonOptions, CoreSort, Definition};
+use crate::imports::{self, HostFunc};
+use crate::instance::call_lifted;
+use crate::{ComponentError, ComponentImports, FuncType, InterfaceValue};
+use wasmer::{
+    ExportError, Exports, Extern, Function, Global, Imports, Instance, Memory, Module, Store, Table,
+};
+
+/// A compiled component binary.
+///
+/// The component imports host functions, defined in
+/// [`ComponentImports`], and exports component functions, with their
+/// types.
+#[derive(Debug, Clone)]
+pub struct Component {
+    store: Store,
+    modules: Vec<Module>,
+    definitions: Vec<Definition>,
+    imports: Vec<(String, String, FuncType)>,
+    exports: Vec<(String, FuncType)>,
+}
+
+impl Component {
+    /// Parses the component binary `bytes` and compiles its core
+    /// modules.
+    ///
+    /// The component can only use the parts of the component model
+    /// expressible with [`InterfaceType`](crate::InterfaceType)s and
+    /// host functions: others, e.g. nested components or resources,
+    /// make it fail with [`ComponentError::Unsupported`].
+    pub fn new(store: &Store, bytes: impl AsRef<[u8]>) -> Result<Self, ComponentError> {
+        let component = binary::parse(bytes.as_ref())?;
+        let modules = component
+            .modules
+            .iter()
+            .map(|module| Module::from_binary(store, module))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            store: store.clone(),
+            modules,
+            definitions: component.definitions,
+            imports: component.imports,
+            exports: component.exports,
+        })
+    }
+
+    /// The host functions the component imports, by namespace and
+    /// name, with their types.
+    pub fn imports(&self) -> impl Iterator<Item = (&str, &str, &FuncType)> {
+        self.imports
+            .iter()
+            .map(|(namespace, name, ty)| (namespace.as_str(), name.as_str(), ty))
+    }
+
+    /// The functions the component exports, by name, with their types.
+    pub fn exports(&self) -> impl Iterator<Item = (&str, &FuncType)> {
+        self.exports.iter().map(|(name, ty)| (name.as_str(), ty))
+    }
+
+    /// Instantiates the component with the host functions of `imports`.
+    ///
+    /// Every function the component imports must be defined, with its
+    /// type.
+    pub fn instantiate(
+        &self,
+        imports: &ComponentImports,
+    ) -> Result<ComponentExports, ComponentError> {
+        for (namespace, name, ty) in &self.imports {
+            imports.get(namespace, name, ty)?;
+        }
+
+        let mut instantiation = Instantiation::default();
+        for definition in &self.definitions {
+            instantiation.define(self, imports, definition)?;
+        }
+
+        Ok(ComponentExports {
+            funcs: instantiation.exports,
+        })
+    }
+}
+
+/// The index spaces of a component being instantiated.
+#[derive(Default)]
+struct Instantiation {
+    core_instances: Vec<Exports>,
+    core_funcs: Vec<Function>,
+    core_tables: Vec<Table>,
+    core_memories: Vec<Memory>,
+    core_globals: Vec<Global>,
+    funcs: Vec<ComponentFunc>,
+    /// The names of the imported instances.
+    instances: Vec<String>,
+    exports: Vec<(String, ComponentFunc)>,
+}
+
+impl Instantiation {
+    fn define(
+        &mut self,
+        component: &Component,
+        imports: &ComponentImports,
+        definition: &Definition,
+    ) -> Result<(), ComponentError> {
+        match definition {
+            Definition::CoreInstantiate { module, args } => {
+                let mut core_imports = Imports::new();
+                for (namespace, instance) in args {
+                    for (name, export) in self.core_instances[*instance as usize].iter() {
+                        core_imports.define(namespace, name, export.clone());
+                    }
+                }
+                let instance = Instance::new(&component.modules[*module as usize], &core_imports)?;
+                self.core_instances.push(instance.exports.clone());
+            }
+            Definition::CoreInlineInstance(items) => {
+                let mut exports = Exports::new();
+                for (name, sort, index) in items {
+                    let index = *index as usize;
+                    let item: Extern = match sort {
+                        CoreSort::Func => self.core_funcs[index].clone().into(),
+                        CoreSort::Table => self.core_tables[index].clone().into(),
+                        CoreSort::Memory => self.core_memories[index].clone().into(),
+                        CoreSort::Global => self.core_globals[index].clone().into(),
+                    };
+                    exports.insert(name.clone(), item);
+                }
+                self.core_instances.push(exports);
+            }
+            Definition::CoreAlias {
+                instance,
+                name,
+                sort,
+            } => {
+                let export = self.core_instances[*instance as usize]
+                    .get_extern(name)
+                    .ok_or_else(|| ExportError::Missing(name.clone()))?;
+                match (sort, export) {
+                    (CoreSort::Func, Extern::Function(func)) => self.core_funcs.push(func.clone()),
+                    (CoreSort::Table, Extern::Table(table)) => self.core_tables.push(table.clone()),
+                    (CoreSort::Memory, Extern::Memory(memory)) => {
+                        self.core_memories.push(memory.clone())
+                    }
+                    (CoreSort::Global, Extern::Global(global)) => {
+                        self.core_globals.push(global.clone())
+                    }
+                    _ => return Err(ExportError::IncompatibleType.into()),
+                }
+            }
+            Definition::ImportFunc { name, ty } => {
+                let func = imports.get(binary::ROOT_NAMESPACE, name, ty)?;
+                self.funcs.push(ComponentFunc::host(ty, func));
+            }
+            Definition::ImportInstance { name } => self.instances.push(name.clone()),
+            Definition::AliasFunc { instance, name, ty } => {
+                let func = imports.get(&self.instances[*instance as usize], name, ty)?;
+                self.funcs.push(ComponentFunc::host(ty, func));
+            }
+            Definition::Lift {
+                core_func,
+                options,
+                ty,
+            } => {
+                let (memory, realloc) = self.options(options);
+                self.funcs.push(ComponentFunc {
+                    ty: ty.clone(),
+                    inner: FuncInner::Lifted {
+                        function: self.core_funcs[*core_func as usize].clone(),
+                        memory,
+                        realloc,
+                        post_return: options
+                            .post_return
+                            .map(|func| self.core_funcs[func as usize].clone()),
+                    },
+                });
+            }
+            Definition::Lower { func, options, ty } => {
+                let func = match &self.funcs[*func as usize].inner {
+                    FuncInner::Host(func) => func.clone(),
+                    FuncInner::Lifted { .. } => {
+                        return Err(ComponentError::Unsupported(
+                            "lowering functions of the component itself".to_string(),
+                        ))
+                    }
+                };
+                let (memory, realloc) = self.options(options);
+                let function = imports::lower(&component.store, ty, func, memory, realloc);
+                self.core_funcs.push(function);
+            }
+            Definition::ExportFunc { name, func } => {
+                let func = self.funcs[*func as usize].clone();
+                self.funcs.push(func.clone());
+                self.exports.push((name.clone(), func));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The memory and the allocator given by canonical options.
+    fn options(&self, options: &CanonOptions) -> (Option<Memory>, Option<Function>) {
+        (
+            options
+                .memory
+                .map(|memory| self.core_memories[memory as usize].clone()),
+            options
+                .realloc
+                .map(|func| self.core_funcs[func as usize].clone()),
+        )
+    }
+}
+
+/// The functions exported by an instance of a [`Component`].
+#[derive(Debug, Clone)]
+pub struct ComponentExports {
+    funcs: Vec<(String, ComponentFunc)>,
+}
+
+impl ComponentExports {
+    /// Returns the exported function `name`.
+    pub fn get_func(&self, name: &str) -> Result<&ComponentFunc, ExportError> {
+        self.funcs
+            .iter()
+            .find(|(export_name, _)| export_name == name)
+            .map(|(_, func)| func)
+            .ok_or_else(|| ExportError::Missing(name.to_string()))
+    }
+
+    /// Iterates over the exported functions, by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ComponentFunc)> {
+        self.funcs.iter().map(|(name, func)| (name.as_str(), func))
+    }
+}
+
+/// A function of a component instance.
+#[derive(Debug, Clone)]
+pub struct ComponentFunc {
+    ty: FuncType,
+    inner: FuncInner,
+}
+
+#[derive(Debug, Clone)]
+enum FuncInner {
+    /// A core function, lifted with its canonical options.
+    Lifted {
+        function: Function,
+        memory: Option<Memory>,
+        realloc: Option<Function>,
+        post_return: Option<Function>,
+    },
+    /// A host function the component imports.
+    Host(HostFunc),
+}
+
+impl ComponentFunc {
+    fn host(ty: &FuncType, func: &HostFunc) -> Self {
+        Self {
+            ty: ty.clone(),
+            inner: FuncInner::Host(func.clone()),
+        }
+    }
+
+    /// The type of the function.
+    pub fn ty(&self) -> &FuncType {
+        &self.ty
+    }
+
+    /// Calls the function with `args`.
+    ///
+    /// The arguments are lowered into the core instance and the results
+    /// lifted out of it following the canonical ABI, with the memory
+    /// and the allocator given by the canonical options of the
+    /// function.
+    pub fn call(&self, args: &[InterfaceValue]) -> Result<Vec<InterfaceValue>, ComponentError> {
+        match &self.inner {
+            FuncInner::Lifted {
+                function,
+                memory,
+                realloc,
+                post_return,
+            } => {
+                let context = Context {
+                    memory: memory.as_ref(),
+                    realloc: realloc.as_ref(),
+                };
+                call_lifted(&context, function, &self.ty, post_return.as_ref(), args)
+            }
+            FuncInner::Host(func) => Ok(func.call(args)?),
+        }
+    }
+}
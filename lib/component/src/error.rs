@@ -0,0 +1,80 @@
+use crate::{InterfaceType, InterfaceValue};
+use thiserror::Error;
+use wasmer::{CompileError, ExportError, InstantiationError, MemoryAccessError, RuntimeError};
+
+/// An error while instantiating a component or calling one of its
+/// functions.
+#[derive(Debug, Error)]
+pub enum ComponentError {
+    /// The component binary is malformed.
+    #[error("invalid component at offset {offset:#x}: {message}")]
+    InvalidComponent { offset: usize, message: String },
+    /// The component uses a feature of the component model which isn't
+    /// supported yet.
+    #[error("unsupported component feature: {0}")]
+    Unsupported(String),
+    /// A core module of the component couldn't be compiled.
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+    /// A host function the component imports isn't defined.
+    #[error("missing import `{namespace}`.`{name}`")]
+    MissingImport { namespace: String, name: String },
+    /// A host function the component imports is defined with another
+    /// type.
+    #[error("import `{namespace}`.`{name}` has another type")]
+    ImportTypeMismatch { namespace: String, name: String },
+    /// The core module couldn't be instantiated.
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+    /// An export the component needs is missing, or has the wrong kind.
+    #[error(transparent)]
+    Export(#[from] ExportError),
+    /// A function trapped, or a host function failed.
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+    /// A value was read or written out of the bounds of the memory.
+    #[error(transparent)]
+    MemoryAccess(#[from] MemoryAccessError),
+    /// A function received a wrong number of values.
+    #[error("expected {expected} values, got {actual}")]
+    ValueCount { expected: usize, actual: usize },
+    /// A value doesn't have the type the interface expects.
+    #[error("expected a value of type {expected:?}, got {value:?}")]
+    TypeMismatch {
+        expected: InterfaceType,
+        value: InterfaceValue,
+    },
+    /// A core function doesn't have the signature the canonical ABI
+    /// expects.
+    #[error("core values don't match the canonical ABI signature")]
+    CoreTypeMismatch,
+    /// A string in memory isn't valid UTF-8.
+    #[error("invalid UTF-8 string")]
+    InvalidUtf8,
+    /// A char in memory isn't a Unicode scalar value.
+    #[error("invalid char: {0:#x}")]
+    InvalidChar(u32),
+    /// A pointer is misaligned, or points out of the bounds of the
+    /// memory.
+    #[error("invalid pointer: {0:#x}")]
+    InvalidPointer(u32),
+    /// The component doesn't export its `memory`.
+    #[error("the component doesn't export a memory")]
+    MissingMemory,
+    /// The component doesn't export `cabi_realloc`, needed to pass it
+    /// strings and lists.
+    #[error("the component doesn't export `cabi_realloc`")]
+    MissingRealloc,
+    /// A value doesn't fit in the 32-bit address space of the component.
+    #[error("value too large for the component memory")]
+    Overflow,
+}
+
+impl From<ComponentError> for RuntimeError {
+    fn from(error: ComponentError) -> Self {
+        match error {
+            ComponentError::Runtime(error) => error,
+            error => RuntimeError::new(error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::abi::Context;
+use crate::{ComponentError, FuncType, InterfaceValue};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use wasmer::{Function, Imports, LazyInit, Memory, RuntimeError, Store, Value, WasmerEnv};
+
+/// The host functions a component imports.
+///
+/// The host functions receive and return interface values: their
+/// arguments are lifted out of the component, and their results
+/// lowered into it, following the canonical ABI.
+#[derive(Debug, Clone)]
+pub struct ComponentImports {
+    store: Store,
+    imports: Imports,
+    funcs: HashMap<(String, String), (FuncType, HostFunc)>,
+}
+
+/// A host function, lowered into a core function once the memory and
+/// the allocator it uses are known.
+#[derive(Clone)]
+pub(crate) struct HostFunc(
+    Arc<dyn Fn(&[InterfaceValue]) -> Result<Vec<InterfaceValue>, RuntimeError> + Send + Sync>,
+);
+
+impl HostFunc {
+    pub(crate) fn call(
+        &self,
+        args: &[InterfaceValue],
+    ) -> Result<Vec<InterfaceValue>, RuntimeError> {
+        (self.0)(args)
+    }
+}
+
+impl fmt::Debug for HostFunc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HostFunc").finish()
+    }
+}
+
+#[derive(Clone, WasmerEnv)]
+struct HostEnv {
+    #[wasmer(export)]
+    memory: LazyInit<Memory>,
+    #[wasmer(export(optional = true, name = "cabi_realloc"))]
+    realloc: LazyInit<Function>,
+}
+
+/// The environment of a host function lowered into a component binary,
+/// whose canonical options give the memory and the allocator.
+#[derive(Clone)]
+struct LoweredEnv {
+    memory: Option<Memory>,
+    realloc: Option<Function>,
+}
+
+impl WasmerEnv for LoweredEnv {}
+
+impl ComponentImports {
+    /// Creates an empty set of imports.
+    pub fn new(store: &Store) -> Self {
+        Self {
+            store: store.clone(),
+            imports: Imports::new(),
+            funcs: HashMap::new(),
+        }
+    }
+
+    /// Defines the host function `namespace`.`name`, of type `ty`.
+    ///
+    /// Results which don't fit in a single core value are written at a
+    /// pointer passed by the component as the last argument.
+    ///
+    /// A [`Component`](crate::Component) imports the function `name`
+    /// of its imported instance `namespace`, or the function `name` of
+    /// the `$root` namespace if it imports the function itself.
+    pub fn define<F>(&mut self, namespace: &str, name: &str, ty: FuncType, func: F) -> &mut Self
+    where
+        F: Fn(&[InterfaceValue]) -> Result<Vec<InterfaceValue>, RuntimeError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let func = HostFunc(Arc::new(func));
+        let env = HostEnv {
+            memory: LazyInit::new(),
+            realloc: LazyInit::new(),
+        };
+        let function = Function::new_with_env(&self.store, ty.lowered_core_type(), env, {
+            let ty = ty.clone();
+            let func = func.clone();
+            move |env: &HostEnv, args: &[Value]| {
+                let context = Context {
+                    memory: Some(env.memory.get_ref().ok_or(ComponentError::MissingMemory)?),
+                    realloc: env.realloc.get_ref(),
+                };
+                call_host(&context, &ty, &func, args).map_err(RuntimeError::from)
+            }
+        });
+        self.imports.define(namespace, name, function);
+        self.funcs
+            .insert((namespace.to_string(), name.to_string()), (ty, func));
+
+        self
+    }
+
+    /// The imports to instantiate the component with.
+    pub fn imports(&self) -> &Imports {
+        &self.imports
+    }
+
+    /// Returns the imports to instantiate the component with.
+    pub fn into_imports(self) -> Imports {
+        self.imports
+    }
+
+    /// Returns the host function `namespace`.`name`, checking it has the
+    /// type `ty`.
+    pub(crate) fn get(
+        &self,
+        namespace: &str,
+        name: &str,
+        ty: &FuncType,
+    ) -> Result<&HostFunc, ComponentError> {
+        match self.funcs.get(&(namespace.to_string(), name.to_string())) {
+            Some((defined_ty, func)) if defined_ty == ty => Ok(func),
+            Some(_) => Err(ComponentError::ImportTypeMismatch {
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+            }),
+            None => Err(ComponentError::MissingImport {
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+            }),
+        }
+    }
+}
+
+/// Lowers the host function `func`, of type `ty`, into a core function
+/// using `memory` and `realloc`.
+pub(crate) fn lower(
+    store: &Store,
+    ty: &FuncType,
+    func: HostFunc,
+    memory: Option<Memory>,
+    realloc: Option<Function>,
+) -> Function {
+    let env = LoweredEnv { memory, realloc };
+    let ty = ty.clone();
+    Function::new_with_env(
+        store,
+        ty.lowered_core_type(),
+        env,
+        move |env: &LoweredEnv, args: &[Value]| {
+            let context = Context {
+                memory: env.memory.as_ref(),
+                realloc: env.realloc.as_ref(),
+            };
+            call_host(&context, &ty, &func, args).map_err(RuntimeError::from)
+        },
+    )
+}
+
+fn call_host(
+    context: &Context,
+    ty: &FuncType,
+    func: &HostFunc,
+    args: &[Value],
+) -> Result<Vec<Value>, ComponentError> {
+    let (args, retptr) = match args {
+        [args @ .., Value::I32(retptr)] if ty.results_in_memory() => (args, Some(*retptr as u32)),
+        _ if ty.results_in_memory() => return Err(ComponentError::CoreTypeMismatch),
+        _ => (args, None),
+    };
+    let values = if ty.params_in_memory() {
+        match args {
+            [Value::I32(ptr)] => context.load_tuple(ty.params(), *ptr as u32)?,
+            _ => return Err(ComponentError::CoreTypeMismatch),
+        }
+    } else {
+        context.lift_flat_values(ty.params(), args)?
+    };
+
+    let results = func.call(&values)?;
+
+    match retptr {
+        Some(retptr) => {
+            context.store_tuple(&results, ty.results(), retptr)?;
+            Ok(vec![])
+        }
+        None => context.lower_flat_values(&results, ty.results()),
+    }
+}
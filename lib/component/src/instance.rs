@@ -0,0 +1,98 @@
+use crate::abi::Context;
+use crate::{ComponentError, FuncType, InterfaceValue};
+use wasmer::{Function, Imports, Instance, Memory, Module, Value};
+
+/// An instance of a component, from its core module.
+///
+/// The types of the functions are given by the embedder; a component
+/// binary, which gives them, is instantiated with
+/// [`Component`](crate::Component) instead.
+///
+/// The core module must export its `memory` and, to receive strings
+/// and lists, a `cabi_realloc` function with the signature
+/// `(old_ptr: i32, old_size: i32, align: i32, new_size: i32) -> i32`.
+#[derive(Debug, Clone)]
+pub struct ComponentInstance {
+    instance: Instance,
+    memory: Memory,
+    realloc: Option<Function>,
+}
+
+impl ComponentInstance {
+    /// Instantiates the core module of a component with `imports`,
+    /// e.g. the imports of [`ComponentImports`](crate::ComponentImports).
+    pub fn new(module: &Module, imports: &Imports) -> Result<Self, ComponentError> {
+        let instance = Instance::new(module, imports)?;
+        let memory = instance.exports.get_memory("memory")?.clone();
+        let realloc = instance.exports.get_function("cabi_realloc").ok().cloned();
+
+        Ok(Self {
+            instance,
+            memory,
+            realloc,
+        })
+    }
+
+    /// The core instance.
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
+
+    /// Calls the exported function `name`, of type `ty`, with `args`.
+    ///
+    /// The arguments are lowered into the instance and the results
+    /// lifted out of it following the canonical ABI. If the instance
+    /// exports a `cabi_post_<name>` function, it is called once the
+    /// results are lifted, to let the instance free them.
+    pub fn call(
+        &self,
+        name: &str,
+        ty: &FuncType,
+        args: &[InterfaceValue],
+    ) -> Result<Vec<InterfaceValue>, ComponentError> {
+        let function = self.instance.exports.get_function(name)?;
+        let context = Context {
+            memory: Some(&self.memory),
+            realloc: self.realloc.as_ref(),
+        };
+        let post_return = self
+            .instance
+            .exports
+            .get_function(&format!("cabi_post_{}", name))
+            .ok();
+
+        call_lifted(&context, function, ty, post_return, args)
+    }
+}
+
+/// Calls the core function `function` lifted with the type `ty`, then
+/// `post_return` with its core results.
+pub(crate) fn call_lifted(
+    context: &Context,
+    function: &Function,
+    ty: &FuncType,
+    post_return: Option<&Function>,
+    args: &[InterfaceValue],
+) -> Result<Vec<InterfaceValue>, ComponentError> {
+    let core_args = if ty.params_in_memory() {
+        let ptr = context.lower_tuple(args, ty.params())?;
+        vec![Value::I32(ptr as i32)]
+    } else {
+        context.lower_flat_values(args, ty.params())?
+    };
+    let core_results = function.call(&core_args)?;
+    let results = if ty.results_in_memory() {
+        match &*core_results {
+            [Value::I32(ptr)] => context.load_tuple(ty.results(), *ptr as u32)?,
+            _ => return Err(ComponentError::CoreTypeMismatch),
+        }
+    } else {
+        context.lift_flat_values(ty.results(), &core_results)?
+    };
+
+    if let Some(post_return) = post_return {
+        post_return.call(&core_results)?;
+    }
+
+    Ok(results)
+}
@@ -0,0 +1,123 @@
+//! Support for the [component model] in Wasmer.
+//!
+//! The functions of a component take and return [`InterfaceValue`]s,
+//! such as strings, lists and records, rather than core numbers.
+//! [`ComponentInstance::call`] lowers the arguments into the linear
+//! memory of the core instance and lifts the results back out,
+//! following the [canonical ABI]; [`ComponentImports`] does the same,
+//! the other way around, for the host functions a component imports.
+//!
+//! ```rust,ignore
+//! use wasmer::{Module, Store};
+//! use wasmer_component::{
+//!     ComponentImports, ComponentInstance, FuncType, InterfaceType, InterfaceValue,
+//! };
+//!
+//! let store = Store::default();
+//! let module = Module::new(&store, wasm_bytes)?;
+//!
+//! let mut imports = ComponentImports::new(&store);
+//! imports.define(
+//!     "host",
+//!     "log",
+//!     FuncType::new([InterfaceType::String], []),
+//!     |args| {
+//!         println!("{:?}", args[0]);
+//!         Ok(vec![])
+//!     },
+//! );
+//!
+//! let instance = ComponentInstance::new(&module, imports.imports())?;
+//! let greeting = instance.call(
+//!     "greet",
+//!     &FuncType::new([InterfaceType::String], [InterfaceType::String]),
+//!     &[InterfaceValue::String("Ferris".to_string())],
+//! )?;
+//! ```
+//!
+//! A [`Component`] is loaded from a component binary, which gives the
+//! types of the functions it imports and exports:
+//!
+//! ```rust,ignore
+//! use wasmer::Store;
+//! use wasmer_component::{Component, ComponentImports, InterfaceValue};
+//!
+//! let store = Store::default();
+//! let component = Component::new(&store, component_bytes)?;
+//!
+//! let mut imports = ComponentImports::new(&store);
+//! // The host functions the component imports, e.g. from a `host`
+//! // instance, are defined in `imports` as above.
+//!
+//! let exports = component.instantiate(&imports)?;
+//! let greeting = exports
+//!     .get_func("greet")?
+//!     .call(&[InterfaceValue::String("Ferris".to_string())])?;
+//! ```
+//!
+//! Only the parts of the component model expressible with
+//! [`InterfaceType`]s are supported: nested components, resources,
+//! variants, options and results, among others, are not.
+//!
+//! [component model]: https://github.com/WebAssembly/component-model
+//! [canonical ABI]: https://github.com/WebAssembly/component-model/blob/main/design/mvp/CanonicalABI.md
+
+mod abi;
+mod binary;
+mod component;
+mod error;
+mod imports;
+mod instance;
+mod types;
+
+pub use crate::component::{Component, ComponentExports, ComponentFunc};
+pub use crate::error::ComponentError;
+pub use crate::imports::ComponentImports;
+pub use crate::instance::ComponentInstance;
+pub use crate::types::{ComponentType, FuncType, InterfaceType, InterfaceValue};
+
+/// Generates typed glue for the host functions described by a WIT file.
+///
+/// The path of the WIT file is relative to the root of the crate. The
+/// file declares records and functions:
+///
+/// ```wit
+/// record point { x: s32, y: s32 }
+///
+/// log: func(message: string)
+/// centroid: func(points: list<point>) -> point
+/// ```
+///
+/// For a file named `geometry.wit`, `host_bindings!("geometry.wit")`
+/// generates a `geometry` module containing:
+///
+/// - a Rust struct for every record, e.g. `Point`, implementing
+///   [`ComponentType`];
+/// - a `Geometry` trait with a method for every function, e.g.
+///   `fn centroid(&self, points: Vec<Point>) -> Point`;
+/// - an `add_to_imports` function, defining the methods of a
+///   `Geometry` implementation in [`ComponentImports`], in the
+///   `geometry` namespace.
+///
+/// ```rust,ignore
+/// wasmer_component::host_bindings!("geometry.wit");
+///
+/// struct Host;
+///
+/// impl geometry::Geometry for Host {
+///     fn log(&self, message: String) {
+///         println!("{}", message);
+///     }
+///
+///     fn centroid(&self, points: Vec<geometry::Point>) -> geometry::Point {
+///         // ...
+///     }
+/// }
+///
+/// let mut imports = ComponentImports::new(&store);
+/// geometry::add_to_imports(&mut imports, Host);
+/// ```
+///
+/// The types supported are the scalar types, `string`, `list<T>`, and
+/// the records defined in the file.
+pub use wasmer_component_derive::host_bindings;
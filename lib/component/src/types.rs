@@ -0,0 +1,154 @@
+//! The types and values of component interfaces.
+
+use crate::ComponentError;
+
+/// The type of a value crossing a component interface.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InterfaceType {
+    Bool,
+    S8,
+    U8,
+    S16,
+    U16,
+    S32,
+    U32,
+    S64,
+    U64,
+    Float32,
+    Float64,
+    Char,
+    String,
+    /// A list of values of the given type.
+    List(Box<InterfaceType>),
+    /// A record, i.e. a sequence of named fields.
+    Record(Vec<(String, InterfaceType)>),
+}
+
+/// A value crossing a component interface.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterfaceValue {
+    Bool(bool),
+    S8(i8),
+    U8(u8),
+    S16(i16),
+    U16(u16),
+    S32(i32),
+    U32(u32),
+    S64(i64),
+    U64(u64),
+    Float32(f32),
+    Float64(f64),
+    Char(char),
+    String(String),
+    List(Vec<InterfaceValue>),
+    Record(Vec<(String, InterfaceValue)>),
+}
+
+/// The type of a function of a component interface.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FuncType {
+    params: Vec<InterfaceType>,
+    results: Vec<InterfaceType>,
+}
+
+impl FuncType {
+    /// Creates a function type.
+    pub fn new<Params, Results>(params: Params, results: Results) -> Self
+    where
+        Params: Into<Vec<InterfaceType>>,
+        Results: Into<Vec<InterfaceType>>,
+    {
+        Self {
+            params: params.into(),
+            results: results.into(),
+        }
+    }
+
+    /// The types of the parameters.
+    pub fn params(&self) -> &[InterfaceType] {
+        &self.params
+    }
+
+    /// The types of the results.
+    pub fn results(&self) -> &[InterfaceType] {
+        &self.results
+    }
+}
+
+/// A Rust type which can cross a component interface, as an
+/// [`InterfaceValue`] of type [`Self::ty`].
+///
+/// It is implemented for the Rust equivalents of the interface types,
+/// and for the records generated by [`host_bindings!`](crate::host_bindings).
+pub trait ComponentType: Sized {
+    /// The interface type of the values.
+    fn ty() -> InterfaceType;
+
+    /// Converts the Rust value to an interface value.
+    fn into_value(self) -> InterfaceValue;
+
+    /// Converts an interface value to the Rust value.
+    fn from_value(value: InterfaceValue) -> Result<Self, ComponentError>;
+}
+
+macro_rules! impl_component_type {
+    ($($rust_type:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl ComponentType for $rust_type {
+                fn ty() -> InterfaceType {
+                    InterfaceType::$variant
+                }
+
+                fn into_value(self) -> InterfaceValue {
+                    InterfaceValue::$variant(self)
+                }
+
+                fn from_value(value: InterfaceValue) -> Result<Self, ComponentError> {
+                    match value {
+                        InterfaceValue::$variant(value) => Ok(value),
+                        value => Err(ComponentError::TypeMismatch {
+                            expected: Self::ty(),
+                            value,
+                        }),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_component_type! {
+    bool => Bool,
+    i8 => S8,
+    u8 => U8,
+    i16 => S16,
+    u16 => U16,
+    i32 => S32,
+    u32 => U32,
+    i64 => S64,
+    u64 => U64,
+    f32 => Float32,
+    f64 => Float64,
+    char => Char,
+    String => String,
+}
+
+impl<T: ComponentType> ComponentType for Vec<T> {
+    fn ty() -> InterfaceType {
+        InterfaceType::List(Box::new(T::ty()))
+    }
+
+    fn into_value(self) -> InterfaceValue {
+        InterfaceValue::List(self.into_iter().map(T::into_value).collect())
+    }
+
+    fn from_value(value: InterfaceValue) -> Result<Self, ComponentError> {
+        match value {
+            InterfaceValue::List(values) => values.into_iter().map(T::from_value).collect(),
+            value => Err(ComponentError::TypeMismatch {
+                expected: Self::ty(),
+                value,
+            }),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+;; The component of `sys_binary.rs`. `points.wasm` is generated with
+;; `wasm-tools parse points.wat -o points.wasm`.
+(component
+  (import "host" (instance $host
+    (export "shout" (func (param "message" string) (result string)))))
+
+  ;; The memory and the allocator shared by the core instances.
+  (core module $libc
+    (memory (export "memory") 1)
+    (global $heap (mut i32) (i32.const 1024))
+
+    (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32)
+      (local $ptr i32)
+      (local.set $ptr
+        (i32.and
+          (i32.add (global.get $heap) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $heap (i32.add (local.get $ptr) (local.get 3)))
+      (local.get $ptr)))
+  (core instance $libc (instantiate $libc))
+
+  (alias export $host "shout" (func $shout))
+  (core func $shout
+    (canon lower (func $shout)
+      (memory $libc "memory") (realloc (func $libc "cabi_realloc"))))
+
+  (core module $main
+    (import "libc" "memory" (memory 1))
+    (import "host" "shout" (func $shout (param i32 i32 i32)))
+
+    (func (export "add") (param i32 i32) (result i32)
+      (i32.add (local.get 0) (local.get 1)))
+
+    (func (export "relay") (param i32 i32) (result i32)
+      (call $shout (local.get 0) (local.get 1) (i32.const 32))
+      (i32.const 32))
+
+    (func (export "sum") (param $ptr i32) (param $len i32) (result i32)
+      (local $x i32)
+      (local $y i32)
+      (block $done
+        (loop $next
+          (br_if $done (i32.eqz (local.get $len)))
+          (local.set $x (i32.add (local.get $x) (i32.load (local.get $ptr))))
+          (local.set $y (i32.add (local.get $y) (i32.load offset=4 (local.get $ptr))))
+          (local.set $ptr (i32.add (local.get $ptr) (i32.const 8)))
+          (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+          (br $next)))
+      (i32.store (i32.const 16) (local.get $x))
+      (i32.store (i32.const 20) (local.get $y))
+      (i32.const 16)))
+  (core instance $main
+    (instantiate $main
+      (with "libc" (instance $libc))
+      (with "host" (instance (export "shout" (func $shout))))))
+
+  (type $point' (record (field "x" s32) (field "y" s32)))
+  (export $point "point" (type $point'))
+
+  (func (export "add") (param "a" s32) (param "b" s32) (result s32)
+    (canon lift (core func $main "add")))
+  (func (export "relay") (param "message" string) (result string)
+    (canon lift (core func $main "relay")
+      (memory $libc "memory") (realloc (func $libc "cabi_realloc"))))
+  (func (export "sum") (param "points" (list $point)) (result $point)
+    (canon lift (core func $main "sum")
+      (memory $libc "memory") (realloc (func $libc "cabi_realloc")))))
@@ -0,0 +1,156 @@
+#[cfg(feature = "sys")]
+mod sys {
+    use anyhow::Result;
+    use wasmer::Store;
+    use wasmer_component::{
+        Component, ComponentError, ComponentExports, ComponentImports, FuncType, InterfaceType,
+        InterfaceValue,
+    };
+
+    /// The binary of `points.wat`.
+    const POINTS: &[u8] = include_bytes!("points.wasm");
+
+    fn string(value: &str) -> InterfaceValue {
+        InterfaceValue::String(value.to_string())
+    }
+
+    fn point(x: i32, y: i32) -> InterfaceValue {
+        InterfaceValue::Record(vec![
+            ("x".to_string(), InterfaceValue::S32(x)),
+            ("y".to_string(), InterfaceValue::S32(y)),
+        ])
+    }
+
+    fn point_type() -> InterfaceType {
+        InterfaceType::Record(vec![
+            ("x".to_string(), InterfaceType::S32),
+            ("y".to_string(), InterfaceType::S32),
+        ])
+    }
+
+    fn shout_type() -> FuncType {
+        FuncType::new([InterfaceType::String], [InterfaceType::String])
+    }
+
+    fn instantiate() -> Result<ComponentExports> {
+        let store = Store::default();
+        let component = Component::new(&store, POINTS)?;
+
+        let mut imports = ComponentImports::new(&store);
+        imports.define("host", "shout", shout_type(), |args| match args {
+            [InterfaceValue::String(value)] => Ok(vec![string(&value.to_uppercase())]),
+            _ => unreachable!(),
+        });
+
+        Ok(component.instantiate(&imports)?)
+    }
+
+    #[test]
+    fn component_types() -> Result<()> {
+        let store = Store::default();
+        let component = Component::new(&store, POINTS)?;
+
+        assert_eq!(
+            component.imports().collect::<Vec<_>>(),
+            vec![("host", "shout", &shout_type())]
+        );
+        assert_eq!(
+            component.exports().collect::<Vec<_>>(),
+            vec![
+                (
+                    "add",
+                    &FuncType::new(
+                        [InterfaceType::S32, InterfaceType::S32],
+                        [InterfaceType::S32]
+                    )
+                ),
+                ("relay", &shout_type()),
+                (
+                    "sum",
+                    &FuncType::new(
+                        [InterfaceType::List(Box::new(point_type()))],
+                        [point_type()]
+                    )
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_exports() -> Result<()> {
+        let exports = instantiate()?;
+
+        assert_eq!(
+            exports
+                .get_func("add")?
+                .call(&[InterfaceValue::S32(1), InterfaceValue::S32(-3)])?,
+            vec![InterfaceValue::S32(-2)]
+        );
+        assert_eq!(
+            exports.get_func("sum")?.call(&[InterfaceValue::List(vec![
+                point(1, 2),
+                point(3, 4),
+                point(-5, 6)
+            ])])?,
+            vec![point(-1, 12)]
+        );
+        assert!(exports.get_func("sub").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_imports() -> Result<()> {
+        let exports = instantiate()?;
+
+        assert_eq!(
+            exports.get_func("relay")?.call(&[string("hello")])?,
+            vec![string("HELLO")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_imports() -> Result<()> {
+        let store = Store::default();
+        let component = Component::new(&store, POINTS)?;
+
+        let imports = ComponentImports::new(&store);
+        assert!(matches!(
+            component.instantiate(&imports),
+            Err(ComponentError::MissingImport { namespace, name })
+                if namespace == "host" && name == "shout"
+        ));
+
+        let mut imports = ComponentImports::new(&store);
+        imports.define(
+            "host",
+            "shout",
+            FuncType::new([InterfaceType::String], []),
+            |_| Ok(vec![]),
+        );
+        assert!(matches!(
+            component.instantiate(&imports),
+            Err(ComponentError::ImportTypeMismatch { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_binaries() {
+        let store = Store::default();
+
+        assert!(matches!(
+            Component::new(&store, &POINTS[..POINTS.len() - 1]),
+            Err(ComponentError::InvalidComponent { .. })
+        ));
+        assert!(matches!(
+            Component::new(&store, b"\0asm\x01\0\0\0"),
+            Err(ComponentError::InvalidComponent { offset: 4, .. })
+        ));
+    }
+}
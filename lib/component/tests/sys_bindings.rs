@@ -0,0 +1,62 @@
+#[cfg(feature = "sys")]
+mod sys {
+    use anyhow::Result;
+    use wasmer::{Module, Store};
+    use wasmer_component::{
+        ComponentImports, ComponentInstance, ComponentType, FuncType, InterfaceType,
+    };
+
+    wasmer_component::host_bindings!("tests/greeter.wit");
+
+    struct Host;
+
+    impl greeter::Greeter for Host {
+        fn greet(&self, person: greeter::Person) -> String {
+            format!("Hello, {} ({})!", person.name, person.age)
+        }
+    }
+
+    #[test]
+    fn host_bindings() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+            (module
+              (import "greeter" "greet" (func $greet (param i32 i32 i32 i32)))
+              (memory (export "memory") 1)
+              (global $heap (mut i32) (i32.const 1024))
+
+              (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr
+                  (i32.and
+                    (i32.add (global.get $heap) (i32.sub (local.get 2) (i32.const 1)))
+                    (i32.sub (i32.const 0) (local.get 2))))
+                (global.set $heap (i32.add (local.get $ptr) (local.get 3)))
+                (local.get $ptr))
+
+              (func (export "run") (param i32 i32 i32) (result i32)
+                (call $greet (local.get 0) (local.get 1) (local.get 2) (i32.const 32))
+                (i32.const 32)))
+            "#,
+        )?;
+
+        let mut imports = ComponentImports::new(&store);
+        greeter::add_to_imports(&mut imports, Host);
+        let instance = ComponentInstance::new(&module, imports.imports())?;
+
+        let person = greeter::Person {
+            name: "Ferris".to_string(),
+            age: 7,
+        };
+        let run = FuncType::new([greeter::Person::ty()], [InterfaceType::String]);
+        let results = instance.call("run", &run, &[person.into_value()])?;
+        assert_eq!(
+            String::from_value(results[0].clone())?,
+            "Hello, Ferris (7)!"
+        );
+
+        Ok(())
+    }
+}
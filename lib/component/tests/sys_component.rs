@@ -0,0 +1,145 @@
+#[cfg(feature = "sys")]
+mod sys {
+    use anyhow::Result;
+    use wasmer::{Module, Store};
+    use wasmer_component::{
+        ComponentError, ComponentImports, ComponentInstance, FuncType, InterfaceType,
+        InterfaceValue,
+    };
+
+    const WAT: &str = r#"
+    (module
+      (import "host" "shout" (func $shout (param i32 i32 i32)))
+      (memory (export "memory") 1)
+      (global $heap (mut i32) (i32.const 1024))
+
+      (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32)
+        (local $ptr i32)
+        (local.set $ptr
+          (i32.and
+            (i32.add (global.get $heap) (i32.sub (local.get 2) (i32.const 1)))
+            (i32.sub (i32.const 0) (local.get 2))))
+        (global.set $heap (i32.add (local.get $ptr) (local.get 3)))
+        (local.get $ptr))
+
+      (func (export "add") (param i32 i32) (result i32)
+        (i32.add (local.get 0) (local.get 1)))
+
+      (func (export "len") (param i32 i32) (result i32)
+        (local.get 1))
+
+      (func (export "echo") (param i32 i32) (result i32)
+        (i32.store (i32.const 16) (local.get 0))
+        (i32.store (i32.const 20) (local.get 1))
+        (i32.const 16))
+
+      (func (export "relay") (param i32 i32) (result i32)
+        (call $shout (local.get 0) (local.get 1) (i32.const 32))
+        (i32.const 32))
+
+      (func (export "dangling") (result i32)
+        (i32.store (i32.const 16) (i32.const 0x20000))
+        (i32.store (i32.const 20) (i32.const 4))
+        (i32.const 16)))
+    "#;
+
+    fn string(value: &str) -> InterfaceValue {
+        InterfaceValue::String(value.to_string())
+    }
+
+    fn instantiate() -> Result<ComponentInstance> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+
+        let mut imports = ComponentImports::new(&store);
+        imports.define(
+            "host",
+            "shout",
+            FuncType::new([InterfaceType::String], [InterfaceType::String]),
+            |args| match args {
+                [InterfaceValue::String(value)] => Ok(vec![string(&value.to_uppercase())]),
+                _ => unreachable!(),
+            },
+        );
+
+        Ok(ComponentInstance::new(&module, imports.imports())?)
+    }
+
+    #[test]
+    fn call_exports() -> Result<()> {
+        let instance = instantiate()?;
+
+        let add = FuncType::new(
+            [InterfaceType::U32, InterfaceType::U32],
+            [InterfaceType::U32],
+        );
+        assert_eq!(
+            instance.call(
+                "add",
+                &add,
+                &[InterfaceValue::U32(1), InterfaceValue::U32(u32::MAX)]
+            )?,
+            vec![InterfaceValue::U32(0)]
+        );
+
+        let len = FuncType::new([InterfaceType::String], [InterfaceType::U32]);
+        assert_eq!(
+            instance.call("len", &len, &[string("héllo")])?,
+            vec![InterfaceValue::U32(6)]
+        );
+
+        let echo = FuncType::new([InterfaceType::String], [InterfaceType::String]);
+        assert_eq!(
+            instance.call("echo", &echo, &[string("hello")])?,
+            vec![string("hello")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_imports() -> Result<()> {
+        let instance = instantiate()?;
+
+        let relay = FuncType::new([InterfaceType::String], [InterfaceType::String]);
+        assert_eq!(
+            instance.call("relay", &relay, &[string("hello")])?,
+            vec![string("HELLO")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_values() -> Result<()> {
+        let instance = instantiate()?;
+
+        let add = FuncType::new(
+            [InterfaceType::U32, InterfaceType::U32],
+            [InterfaceType::U32],
+        );
+        assert!(matches!(
+            instance.call("add", &add, &[InterfaceValue::U32(1)]),
+            Err(ComponentError::ValueCount {
+                expected: 2,
+                actual: 1
+            })
+        ));
+        assert!(matches!(
+            instance.call(
+                "add",
+                &add,
+                &[InterfaceValue::U32(1), InterfaceValue::S64(2)]
+            ),
+            Err(ComponentError::TypeMismatch { .. })
+        ));
+
+        let dangling = FuncType::new([], [InterfaceType::String]);
+        assert!(matches!(
+            instance.call("dangling", &dangling, &[]),
+            Err(ComponentError::InvalidPointer(0x20000))
+        ));
+
+        Ok(())
+    }
+}
@@ -1,11 +1,11 @@
 use proc_macro2::TokenStream;
 use proc_macro_error::abort;
 use quote::quote;
-use syn::{Data, DeriveInput, Fields, Member, Meta, MetaList, NestedMeta};
+use syn::{Data, DataEnum, DeriveInput, Fields, Member, Meta, MetaList, NestedMeta};
 
-/// We can only validate types that have a well defined layout.
-fn check_repr(input: &DeriveInput) {
-    let reprs = input
+/// The paths in the `#[repr(...)]` attributes of the type.
+fn reprs(input: &DeriveInput) -> impl Iterator<Item = syn::Path> + '_ {
+    input
         .attrs
         .iter()
         .filter_map(|attr| {
@@ -16,15 +16,20 @@ fn check_repr(input: &DeriveInput) {
             }
             None
         })
-        .flatten();
+        .flatten()
+        .filter_map(|meta| match meta {
+            NestedMeta::Meta(Meta::Path(path)) => Some(path),
+            _ => None,
+        })
+}
 
+/// We can only validate types that have a well defined layout.
+fn check_repr(input: &DeriveInput) {
     // We require either repr(C) or repr(transparent) to ensure fields are in
     // source code order.
-    for meta in reprs {
-        if let NestedMeta::Meta(Meta::Path(path)) = meta {
-            if path.is_ident("C") || path.is_ident("transparent") {
-                return;
-            }
+    for path in reprs(input) {
+        if path.is_ident("C") || path.is_ident("transparent") {
+            return;
         }
     }
 
@@ -88,18 +93,68 @@ fn zero_padding(fields: &Fields) -> TokenStream {
     out
 }
 
-pub fn impl_value_type(input: &DeriveInput) -> TokenStream {
-    check_repr(input);
+/// Fieldless enums are only valid for all bit patterns when they have a
+/// variant for each value of their integer representation: any value may be
+/// read from the memory of the guest.
+fn check_enum(input: &DeriveInput, data: &DataEnum) {
+    let bits = reprs(input)
+        .find_map(|path| {
+            let bits = match path.get_ident()?.to_string().as_str() {
+                "u8" | "i8" => 8,
+                "u16" | "i16" => 16,
+                "u32" | "i32" => 32,
+                _ => return None,
+            };
+            Some(bits)
+        })
+        .unwrap_or_else(|| {
+            abort!(
+                input,
+                "ValueType can only be derived for enums with a #[repr(u8)], #[repr(u16)] or #[repr(u32)]"
+            )
+        });
 
+    if let Some(variant) = data
+        .variants
+        .iter()
+        .find(|variant| !matches!(variant.fields, Fields::Unit))
+    {
+        abort!(
+            variant,
+            "ValueType can only be derived for enums without fields"
+        )
+    }
+
+    // The compiler rejects the discriminants which are repeated or don't
+    // fit the representation, so the variants cover all the values iff
+    // there are as many of them.
+    let values = 1u64 << bits;
+    if data.variants.len() as u64 != values {
+        abort!(
+            input,
+            "ValueType can only be derived for enums with a variant for each of the {} values of their representation, since any of them may be read from the memory of the guest, found {} variants; use a newtype of the integer otherwise",
+            values,
+            data.variants.len()
+        )
+    }
+}
+
+pub fn impl_value_type(input: &DeriveInput) -> TokenStream {
     let struct_name = &input.ident;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
-    let fields = match &input.data {
-        Data::Struct(ds) => &ds.fields,
-        _ => abort!(input, "ValueType can only be derived for structs"),
+    let zero_padding = match &input.data {
+        Data::Struct(ds) => {
+            check_repr(input);
+            zero_padding(&ds.fields)
+        }
+        // The integer representation has no padding.
+        Data::Enum(de) => {
+            check_enum(input, de);
+            TokenStream::new()
+        }
+        _ => abort!(input, "ValueType can only be derived for structs and enums"),
     };
 
-    let zero_padding = zero_padding(fields);
-
     quote! {
         unsafe impl #impl_generics ::wasmer::ValueType for #struct_name #ty_generics #where_clause {
             #[inline]
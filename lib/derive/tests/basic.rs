@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use wasmer::{Function, Global, LazyInit, Memory, Table, TypedFunction, WasmerEnv};
+use wasmer::{Function, Global, LazyInit, Memory, Table, TypedFunction, ValueType, WasmerEnv};
 
 #[derive(WasmerEnv, Clone)]
 struct MyEnv {
@@ -117,3 +117,36 @@ struct StructWithAliases {
 fn test_derive_with_aliases() {
     assert!(impls_wasmer_env::<StructWithAliases>());
 }
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(C)]
+struct Padded {
+    tag: u8,
+    value: u32,
+}
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(C)]
+struct WithArrays {
+    items: [Padded; 2],
+    bytes: [u8; 3],
+}
+
+#[test]
+fn test_derive_value_type_with_arrays() {
+    use std::mem::{size_of, MaybeUninit};
+
+    let value = WithArrays {
+        items: [Padded { tag: 1, value: 2 }; 2],
+        bytes: [3; 3],
+    };
+    let mut bytes = [MaybeUninit::new(0xffu8); size_of::<WithArrays>()];
+    value.zero_padding_bytes(&mut bytes);
+
+    let bytes = bytes.map(|byte| unsafe { byte.assume_init() });
+    // The padding after the tag of each item, and after the bytes.
+    assert_eq!(&bytes[1..4], &[0, 0, 0]);
+    assert_eq!(&bytes[9..12], &[0, 0, 0]);
+    assert_eq!(&bytes[16..19], &[0xff, 0xff, 0xff]);
+    assert_eq!(bytes[19], 0);
+}
@@ -0,0 +1,17 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(u8)]
+enum NotAllValues { //~ ValueType can only be derived for enums with a variant for each of the 256 values of their representation
+    A = 0,
+    B = 1,
+}
+
+#[derive(Copy, Clone, ValueType)]
+enum NoRepr { //~ ValueType can only be derived for enums with a #[repr(u8)], #[repr(u16)] or #[repr(u32)]
+    A,
+}
+
+fn main() {}
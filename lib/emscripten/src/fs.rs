@@ -0,0 +1,462 @@
+//! The virtual file system of emscripten programs.
+//!
+//! By default, the file syscalls of emscripten programs use the file
+//! system of the host directly. When a [`wasmer_vfs::FileSystem`] is
+//! set with [`EmEnv::set_file_system`], they go through it instead, as
+//! the ones of WASI programs do, and the files it opens get their own
+//! descriptors. The standard streams, pipes and sockets stay on the
+//! host.
+//!
+//! [`EmEnv::set_file_system`]: crate::EmEnv::set_file_system
+
+use crate::utils::copy_metadata_into_wasm;
+use crate::EmEnv;
+use libc::{EBADF, EEXIST, EFAULT, EINVAL, EIO, EISDIR, ENOENT, ENOTDIR, ENOTEMPTY, EPERM};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use wasmer_vfs::{FileSystem, FsError, Metadata, VirtualFile};
+
+/// The first descriptor of the virtual files, above the ones the host
+/// gives to the streams and sockets.
+const VIRTUAL_FD_BASE: i32 = 1 << 24;
+
+// The flags of `open`, as defined by emscripten.
+const O_ACCMODE: i32 = 0o3;
+const O_WRONLY: i32 = 0o1;
+const O_RDWR: i32 = 0o2;
+const O_CREAT: i32 = 0o100;
+const O_EXCL: i32 = 0o200;
+const O_TRUNC: i32 = 0o1000;
+const O_APPEND: i32 = 0o2000;
+
+// The file types of `getdents`.
+const DT_UNKNOWN: u8 = 0;
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+const DT_LNK: u8 = 10;
+
+/// A file system, and the files the program opened in it.
+#[derive(Debug)]
+pub(crate) struct EmFs {
+    fs: Box<dyn FileSystem>,
+    fds: Mutex<Fds>,
+}
+
+#[derive(Debug, Default)]
+struct Fds {
+    next: i32,
+    open: HashMap<i32, EmFile>,
+}
+
+#[derive(Debug)]
+enum EmFile {
+    File(Box<dyn VirtualFile + Send + Sync>),
+    /// A directory, and how many of its entries have been read.
+    Dir(PathBuf, usize),
+}
+
+/// The result of a syscall: its value, or the error number it
+/// returns negated.
+type SyscallResult<T> = Result<T, i32>;
+
+fn errno(error: FsError) -> i32 {
+    match error {
+        FsError::EntityNotFound => ENOENT,
+        FsError::AlreadyExists => EEXIST,
+        FsError::BaseNotDirectory => ENOTDIR,
+        FsError::NotAFile => EISDIR,
+        FsError::InvalidFd => EBADF,
+        FsError::PermissionDenied => EPERM,
+        FsError::DirectoryNotEmpty => ENOTEMPTY,
+        FsError::InvalidInput => EINVAL,
+        _ => EIO,
+    }
+}
+
+/// The error number of a failed lookup of a path, which is missing
+/// when some file systems, e.g. the memory one, report it isn't a file.
+fn lookup_errno(error: FsError) -> i32 {
+    match error {
+        FsError::NotAFile => ENOENT,
+        error => errno(error),
+    }
+}
+
+fn io_errno(error: std::io::Error) -> i32 {
+    errno(error.into())
+}
+
+impl EmFs {
+    pub(crate) fn new(fs: Box<dyn FileSystem>) -> Self {
+        Self {
+            fs,
+            fds: Mutex::new(Fds {
+                next: VIRTUAL_FD_BASE,
+                open: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Whether `fd` is a descriptor of this file system, rather than of
+    /// the host.
+    pub(crate) fn owns(fd: i32) -> bool {
+        fd >= VIRTUAL_FD_BASE
+    }
+
+    pub(crate) fn open(&self, path: &Path, flags: i32) -> SyscallResult<i32> {
+        let file = match self.fs.metadata(path) {
+            Ok(metadata) if metadata.is_dir() => {
+                if flags & O_ACCMODE != 0 {
+                    return Err(EISDIR);
+                }
+                EmFile::Dir(path.to_owned(), 0)
+            }
+            // Some file systems, e.g. the memory one, don't report
+            // opening a missing file as such.
+            Err(error) if flags & O_CREAT == 0 => return Err(lookup_errno(error)),
+            _ => {
+                let access = flags & O_ACCMODE;
+                let file = self
+                    .fs
+                    .new_open_options()
+                    .read(access != O_WRONLY)
+                    .write(access == O_WRONLY || access == O_RDWR)
+                    .append(flags & O_APPEND != 0)
+                    .truncate(flags & O_TRUNC != 0)
+                    .create(flags & O_CREAT != 0)
+                    .create_new(flags & O_CREAT != 0 && flags & O_EXCL != 0)
+                    .open(path)
+                    .map_err(errno)?;
+                EmFile::File(file)
+            }
+        };
+
+        let mut fds = self.fds.lock().unwrap();
+        let fd = fds.next;
+        fds.next += 1;
+        fds.open.insert(fd, file);
+        Ok(fd)
+    }
+
+    pub(crate) fn close(&self, fd: i32) -> SyscallResult<()> {
+        match self.fds.lock().unwrap().open.remove(&fd) {
+            Some(_) => Ok(()),
+            None => Err(EBADF),
+        }
+    }
+
+    fn with_file<T>(
+        &self,
+        fd: i32,
+        f: impl FnOnce(&mut Box<dyn VirtualFile + Send + Sync>) -> SyscallResult<T>,
+    ) -> SyscallResult<T> {
+        match self.fds.lock().unwrap().open.get_mut(&fd) {
+            Some(EmFile::File(file)) => f(file),
+            Some(EmFile::Dir(..)) => Err(EISDIR),
+            None => Err(EBADF),
+        }
+    }
+
+    pub(crate) fn read(&self, fd: i32, buf: &mut [u8]) -> SyscallResult<usize> {
+        self.with_file(fd, |file| file.read(buf).map_err(io_errno))
+    }
+
+    pub(crate) fn write(&self, fd: i32, buf: &[u8]) -> SyscallResult<usize> {
+        self.with_file(fd, |file| file.write(buf).map_err(io_errno))
+    }
+
+    pub(crate) fn pread(&self, fd: i32, buf: &mut [u8], offset: u64) -> SyscallResult<usize> {
+        self.with_file(fd, |file| {
+            let position = file.stream_position().map_err(io_errno)?;
+            file.seek(SeekFrom::Start(offset)).map_err(io_errno)?;
+            let read = file.read(buf).map_err(io_errno);
+            file.seek(SeekFrom::Start(position)).map_err(io_errno)?;
+            read
+        })
+    }
+
+    pub(crate) fn pwrite(&self, fd: i32, buf: &[u8], offset: u64) -> SyscallResult<usize> {
+        self.with_file(fd, |file| {
+            let position = file.stream_position().map_err(io_errno)?;
+            file.seek(SeekFrom::Start(offset)).map_err(io_errno)?;
+            let written = file.write(buf).map_err(io_errno);
+            file.seek(SeekFrom::Start(position)).map_err(io_errno)?;
+            written
+        })
+    }
+
+    pub(crate) fn seek(&self, fd: i32, offset: i64, whence: i32) -> SyscallResult<u64> {
+        let position = match whence {
+            0 if offset >= 0 => SeekFrom::Start(offset as u64),
+            1 => SeekFrom::Current(offset),
+            2 => SeekFrom::End(offset),
+            _ => return Err(EINVAL),
+        };
+        self.with_file(fd, |file| file.seek(position).map_err(io_errno))
+    }
+
+    pub(crate) fn truncate(&self, fd: i32, length: u64) -> SyscallResult<()> {
+        self.with_file(fd, |file| file.set_len(length).map_err(errno))
+    }
+
+    pub(crate) fn metadata(&self, path: &Path) -> SyscallResult<Metadata> {
+        self.fs.metadata(path).map_err(lookup_errno)
+    }
+
+    pub(crate) fn symlink_metadata(&self, path: &Path) -> SyscallResult<Metadata> {
+        self.fs.symlink_metadata(path).map_err(lookup_errno)
+    }
+
+    pub(crate) fn fd_metadata(&self, fd: i32) -> SyscallResult<Metadata> {
+        let path = match self.fds.lock().unwrap().open.get(&fd) {
+            Some(EmFile::File(file)) => {
+                return Ok(Metadata {
+                    ft: wasmer_vfs::FileType {
+                        file: true,
+                        ..Default::default()
+                    },
+                    accessed: file.last_accessed(),
+                    created: file.created_time(),
+                    modified: file.last_modified(),
+                    len: file.size(),
+                })
+            }
+            Some(EmFile::Dir(path, _)) => path.clone(),
+            None => return Err(EBADF),
+        };
+        self.metadata(&path)
+    }
+
+    pub(crate) fn create_dir(&self, path: &Path) -> SyscallResult<()> {
+        self.fs.create_dir(path).map_err(errno)
+    }
+
+    pub(crate) fn remove_dir(&self, path: &Path) -> SyscallResult<()> {
+        self.fs.remove_dir(path).map_err(errno)
+    }
+
+    pub(crate) fn remove_file(&self, path: &Path) -> SyscallResult<()> {
+        // Some file systems, e.g. the memory one, don't tell a missing
+        // file from a directory when removing it.
+        if self.symlink_metadata(path)?.is_dir() {
+            return Err(EISDIR);
+        }
+        self.fs.remove_file(path).map_err(errno)
+    }
+
+    pub(crate) fn rename(&self, from: &Path, to: &Path) -> SyscallResult<()> {
+        self.fs.rename(from, to).map_err(errno)
+    }
+
+    /// Reads the next entries of the directory `fd`, as many as `f`
+    /// accepts: it's given the name and type of each entry, and returns
+    /// whether it was written.
+    pub(crate) fn read_dir(
+        &self,
+        fd: i32,
+        mut f: impl FnMut(&str, u8) -> bool,
+    ) -> SyscallResult<()> {
+        let mut fds = self.fds.lock().unwrap();
+        let (path, position) = match fds.open.get_mut(&fd) {
+            Some(EmFile::Dir(path, position)) => (path.clone(), position),
+            Some(EmFile::File(_)) => return Err(ENOTDIR),
+            None => return Err(EBADF),
+        };
+
+        let entries = self.fs.read_dir(&path).map_err(errno)?;
+        for entry in entries.skip(*position) {
+            let entry = entry.map_err(errno)?;
+            let kind = match entry.file_type() {
+                Ok(ft) if ft.is_dir() => DT_DIR,
+                Ok(ft) if ft.is_file() => DT_REG,
+                Ok(ft) if ft.is_symlink() => DT_LNK,
+                _ => DT_UNKNOWN,
+            };
+            if !f(&entry.file_name().to_string_lossy(), kind) {
+                break;
+            }
+            *position += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the path at `path`, in the host memory, as an absolute path of
+/// the virtual file system.
+pub(crate) fn virtual_path(path: *const c_char) -> SyscallResult<PathBuf> {
+    let path = unsafe { CStr::from_ptr(path) }
+        .to_str()
+        .map_err(|_| EINVAL)?;
+    Ok(Path::new("/").join(path))
+}
+
+/// Converts the result of a syscall to the value it returns.
+pub(crate) fn syscall_ret(result: SyscallResult<i32>) -> i32 {
+    result.unwrap_or_else(|errno| -errno)
+}
+
+// The syscalls on the virtual files, reading and writing the memory of
+// the program.
+
+pub(crate) fn read(ctx: &EmEnv, fs: &EmFs, fd: i32, buf: u32, count: u32) -> i32 {
+    let mut buffer = vec![0; count as usize];
+    syscall_ret(fs.read(fd, &mut buffer).and_then(|read| {
+        ctx.memory(0)
+            .write(buf as u64, &buffer[..read])
+            .map_err(|_| EFAULT)?;
+        Ok(read as i32)
+    }))
+}
+
+pub(crate) fn write(ctx: &EmEnv, fs: &EmFs, fd: i32, buf: u32, count: u32) -> i32 {
+    let mut buffer = vec![0; count as usize];
+    syscall_ret(
+        ctx.memory(0)
+            .read(buf as u64, &mut buffer)
+            .map_err(|_| EFAULT)
+            .and_then(|()| fs.write(fd, &buffer))
+            .map(|written| written as i32),
+    )
+}
+
+pub(crate) fn pread(ctx: &EmEnv, fs: &EmFs, fd: i32, buf: u32, count: u32, offset: i64) -> i32 {
+    if offset < 0 {
+        return -EINVAL;
+    }
+    let mut buffer = vec![0; count as usize];
+    syscall_ret(fs.pread(fd, &mut buffer, offset as u64).and_then(|read| {
+        ctx.memory(0)
+            .write(buf as u64, &buffer[..read])
+            .map_err(|_| EFAULT)?;
+        Ok(read as i32)
+    }))
+}
+
+pub(crate) fn pwrite(ctx: &EmEnv, fs: &EmFs, fd: i32, buf: u32, count: u32, offset: i64) -> i32 {
+    if offset < 0 {
+        return -EINVAL;
+    }
+    let mut buffer = vec![0; count as usize];
+    syscall_ret(
+        ctx.memory(0)
+            .read(buf as u64, &mut buffer)
+            .map_err(|_| EFAULT)
+            .and_then(|()| fs.pwrite(fd, &buffer, offset as u64))
+            .map(|written| written as i32),
+    )
+}
+
+/// Reads the `(base, length)` pairs of an array of `iovec`s.
+fn iovecs(ctx: &EmEnv, iov: u32, iovcnt: i32) -> SyscallResult<Vec<(u32, u32)>> {
+    let mut buffer = vec![0; iovcnt.max(0) as usize * 8];
+    ctx.memory(0)
+        .read(iov as u64, &mut buffer)
+        .map_err(|_| EFAULT)?;
+    Ok(buffer
+        .chunks_exact(8)
+        .map(|iovec| {
+            (
+                u32::from_le_bytes([iovec[0], iovec[1], iovec[2], iovec[3]]),
+                u32::from_le_bytes([iovec[4], iovec[5], iovec[6], iovec[7]]),
+            )
+        })
+        .collect())
+}
+
+pub(crate) fn readv(ctx: &EmEnv, fs: &EmFs, fd: i32, iov: u32, iovcnt: i32) -> i32 {
+    let iovecs = match iovecs(ctx, iov, iovcnt) {
+        Ok(iovecs) => iovecs,
+        Err(errno) => return -errno,
+    };
+    let mut total = 0;
+    for (base, len) in iovecs {
+        let read = read(ctx, fs, fd, base, len);
+        if read < 0 {
+            return read;
+        }
+        total += read;
+        if (read as u32) < len {
+            break;
+        }
+    }
+    total
+}
+
+pub(crate) fn writev(ctx: &EmEnv, fs: &EmFs, fd: i32, iov: u32, iovcnt: i32) -> i32 {
+    let iovecs = match iovecs(ctx, iov, iovcnt) {
+        Ok(iovecs) => iovecs,
+        Err(errno) => return -errno,
+    };
+    let mut total = 0;
+    for (base, len) in iovecs {
+        let written = write(ctx, fs, fd, base, len);
+        if written < 0 {
+            return written;
+        }
+        total += written;
+    }
+    total
+}
+
+pub(crate) fn stat(ctx: &EmEnv, fs: &EmFs, path: *const c_char, buf: u32, follow: bool) -> i32 {
+    let metadata = virtual_path(path).and_then(|path| {
+        if follow {
+            fs.metadata(&path)
+        } else {
+            fs.symlink_metadata(&path)
+        }
+    });
+    match metadata {
+        Ok(metadata) => {
+            unsafe { copy_metadata_into_wasm(ctx, buf, &metadata) };
+            0
+        }
+        Err(errno) => -errno,
+    }
+}
+
+pub(crate) fn fstat(ctx: &EmEnv, fs: &EmFs, fd: i32, buf: u32) -> i32 {
+    match fs.fd_metadata(fd) {
+        Ok(metadata) => {
+            unsafe { copy_metadata_into_wasm(ctx, buf, &metadata) };
+            0
+        }
+        Err(errno) => -errno,
+    }
+}
+
+/// Writes the next entries of the directory `fd` at `dirp`, in the
+/// format of the host implementation of `getdents`.
+pub(crate) fn getdents(ctx: &EmEnv, fs: &EmFs, fd: i32, dirp: u32, count: u32) -> i32 {
+    const RECORD_SIZE: u32 = 256 + 12;
+    let memory = ctx.memory(0);
+    let mut position = 0;
+    let mut fault = false;
+    let result = fs.read_dir(fd, |name, kind| {
+        if position + RECORD_SIZE > count {
+            return false;
+        }
+        let mut record = [0u8; RECORD_SIZE as usize];
+        record[4..8].copy_from_slice(&position.to_le_bytes());
+        record[8..10].copy_from_slice(&(RECORD_SIZE as u16).to_le_bytes());
+        record[10] = kind;
+        let name = &name.as_bytes()[..name.len().min(255)];
+        record[11..11 + name.len()].copy_from_slice(name);
+        if memory.write((dirp + position) as u64, &record).is_err() {
+            fault = true;
+            return false;
+        }
+        position += RECORD_SIZE;
+        true
+    });
+
+    match result {
+        Ok(()) if fault => -EFAULT,
+        Ok(()) => position as i32,
+        Err(errno) => -errno,
+    }
+}
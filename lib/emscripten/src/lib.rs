@@ -46,6 +46,7 @@ mod errno;
 mod exception;
 mod exec;
 mod exit;
+mod fs;
 mod inet;
 mod io;
 mod jmp;
@@ -76,12 +77,17 @@ pub use self::utils::{
 pub struct EmEnv {
     memory: Arc<RwLock<Option<Memory>>>,
     data: Arc<Mutex<EmscriptenData>>,
+    threads: Option<Arc<pthread::EmThreads>>,
+    thread_id: i32,
+    linker: Arc<Mutex<linking::Linker>>,
+    fs: Option<Arc<fs::EmFs>>,
 }
 
 impl WasmerEnv for EmEnv {
     fn init_with_instance(&mut self, instance: &Instance) -> Result<(), wasmer::HostEnvInitError> {
         let mut ed = self.data.lock().unwrap();
         ed.init_with_instance(instance)?;
+        self.linker.lock().unwrap().init_with_instance(instance);
         Ok(())
     }
 }
@@ -91,14 +97,66 @@ impl EmEnv {
         Self {
             memory: Arc::new(RwLock::new(None)),
             data: Arc::new(Mutex::new(EmscriptenData::new(data.clone(), mapped_dirs))),
+            threads: None,
+            thread_id: 0,
+            linker: Arc::new(Mutex::new(Default::default())),
+            fs: None,
         }
     }
 
+    /// Enables the threads of the program, which are otherwise never
+    /// created by `pthread_create`.
+    ///
+    /// Each thread runs in a new instance of `module`, sharing the
+    /// memory of `globals`, which must be shared, and calls its start
+    /// routine through the `dynCall_ii` export of the module. This must
+    /// be called before the imports are generated by
+    /// [`generate_emscripten_env`].
+    pub fn enable_threads(
+        &mut self,
+        store: &Store,
+        module: &Module,
+        globals: &EmscriptenGlobals,
+    ) -> Result<(), String> {
+        if !globals.memory.ty().shared {
+            return Err("the threads require a shared memory".to_string());
+        }
+
+        let mapped_dirs = self.data.lock().unwrap().mapped_dirs.clone();
+        self.threads = Some(Arc::new(pthread::EmThreads::new(
+            store,
+            module,
+            globals,
+            mapped_dirs,
+        )));
+        Ok(())
+    }
+
     pub fn set_memory(&mut self, memory: Memory) {
         let mut w = self.memory.write().unwrap();
         *w = Some(memory);
     }
 
+    /// Routes the file syscalls of the program through `fs`, instead of
+    /// the file system of the host.
+    ///
+    /// This must be called before the imports are generated by
+    /// [`generate_emscripten_env`].
+    pub fn set_file_system(&mut self, fs: Box<dyn wasmer_vfs::FileSystem>) {
+        self.fs = Some(Arc::new(fs::EmFs::new(fs)));
+    }
+
+    /// The file system of the program, if it's virtual.
+    pub(crate) fn fs(&self) -> Option<&fs::EmFs> {
+        self.fs.as_deref()
+    }
+
+    /// Sets the table holding the function pointers of the program, in
+    /// which `dlopen` places the functions of the side modules.
+    pub fn set_table(&mut self, table: Table) {
+        self.linker.lock().unwrap().set_table(table);
+    }
+
     /// Get a reference to the memory
     pub fn memory(&self, _mem_idx: u32) -> Memory {
         (&*self.memory.read().unwrap()).as_ref().cloned().unwrap()
@@ -371,6 +429,7 @@ pub fn run_emscripten_instance(
     entrypoint: Option<String>,
 ) -> Result<(), RuntimeError> {
     env.set_memory(globals.memory.clone());
+    env.set_table(globals.table.clone());
     set_up_emscripten(instance)?;
 
     // println!("running emscripten instance");
@@ -440,6 +499,7 @@ pub struct EmscriptenGlobalsData {
     use_old_abort_on_cannot_grow_memory: bool,
 }
 
+#[derive(Clone)]
 pub struct EmscriptenGlobals {
     // The emscripten data
     pub data: EmscriptenGlobalsData,
@@ -908,6 +968,7 @@ pub fn generate_emscripten_env(
         "_pthread_attr_setstacksize" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_attr_setstacksize),
         "_pthread_cleanup_pop" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_cleanup_pop),
         "_pthread_cleanup_push" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_cleanup_push),
+        "_pthread_cond_broadcast" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_cond_broadcast),
         "_pthread_cond_destroy" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_cond_destroy),
         "_pthread_cond_init" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_cond_init),
         "_pthread_cond_signal" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_cond_signal),
@@ -927,6 +988,9 @@ pub fn generate_emscripten_env(
         "_pthread_key_create" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_key_create),
         "_pthread_mutex_destroy" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_mutex_destroy),
         "_pthread_mutex_init" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_mutex_init),
+        "_pthread_mutex_lock" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_mutex_lock),
+        "_pthread_mutex_trylock" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_mutex_trylock),
+        "_pthread_mutex_unlock" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_mutex_unlock),
         "_pthread_mutexattr_destroy" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_mutexattr_destroy),
         "_pthread_mutexattr_init" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_mutexattr_init),
         "_pthread_mutexattr_settype" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_mutexattr_settype),
@@ -939,6 +1003,8 @@ pub fn generate_emscripten_env(
         "_pthread_setcancelstate" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_setcancelstate),
         "_pthread_setspecific" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_setspecific),
         "_pthread_sigmask" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_sigmask),
+        "_emscripten_futex_wait" => Function::new_native_with_env(store, env.clone(), crate::pthread::_emscripten_futex_wait),
+        "_emscripten_futex_wake" => Function::new_native_with_env(store, env.clone(), crate::pthread::_emscripten_futex_wake),
         "___gxx_personality_v0" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::___gxx_personality_v0),
         "_gai_strerror" => Function::new_native_with_env(store, env.clone(), crate::env::_gai_strerror),
         "_getdtablesize" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::_getdtablesize),
@@ -1,27 +1,440 @@
+//! The dynamic linking of emscripten side modules, with `dlopen`.
+//!
+//! A side module (built with `-s SIDE_MODULE=1`) describes the memory
+//! and the table it needs in its `dylink` section. When it's loaded,
+//! they are allocated in the memory and the table of the main module,
+//! and its imports are resolved against the exports of the main module
+//! and of the side modules loaded before it: the functions of `env`,
+//! and the addresses of the data (`GOT.mem`) and of the functions
+//! (`GOT.func`) it uses.
+
+use crate::env::{call_malloc, call_memalign, call_memset, get_emscripten_data};
+use crate::utils::{copy_cstr_into_wasm, get_cstr_path};
 use crate::EmEnv;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::path::PathBuf;
+use wasmer::{Exports, Extern, Global, Imports, Instance, Module, Table, Val, WasmPtr};
+
+/// The handle of the main module, returned by `dlopen(NULL, ...)`.
+const MAIN_HANDLE: i32 = 1;
+
+/// The dynamic linking state of an emscripten program.
+#[derive(Default)]
+pub(crate) struct Linker {
+    /// The exports of the main module.
+    exports: Option<Exports>,
+    /// The table holding the function pointers.
+    table: Option<Table>,
+    libraries: HashMap<i32, Library>,
+    next_handle: i32,
+    /// The table slots of the functions whose address was taken, by
+    /// handle and name.
+    function_slots: HashMap<(i32, String), u32>,
+    /// The error of the last failed call, returned by `dlerror`.
+    error: Option<String>,
+    /// The last message returned by `dlerror`, freed by the next call.
+    error_message: u32,
+}
+
+/// A loaded side module.
+struct Library {
+    path: PathBuf,
+    instance: Instance,
+    memory_base: u32,
+    /// How many times it has been opened and not closed.
+    references: u32,
+}
+
+/// The memory and table requirements of a side module.
+#[derive(Debug, Default)]
+struct DylinkInfo {
+    memory_size: u32,
+    memory_align: u32,
+    table_size: u32,
+    table_align: u32,
+}
+
+impl Linker {
+    /// Sets up the linker for the main `instance`.
+    pub(crate) fn init_with_instance(&mut self, instance: &Instance) {
+        if self.exports.is_some() {
+            return;
+        }
+        if self.table.is_none() {
+            self.table = instance
+                .exports
+                .iter()
+                .tables()
+                .next()
+                .map(|(_, t)| t.clone());
+        }
+        self.exports = Some(instance.exports.clone());
+    }
+
+    pub(crate) fn set_table(&mut self, table: Table) {
+        self.table = Some(table);
+    }
+
+    fn table(&self) -> Result<&Table, String> {
+        self.table
+            .as_ref()
+            .ok_or_else(|| "the program has no table".to_string())
+    }
+
+    /// Finds the export `name`, or `_name`, of a library, and the base
+    /// of its data.
+    fn lookup(&self, handle: i32, name: &str) -> Option<(Extern, u32)> {
+        let (exports, memory_base) = if handle == MAIN_HANDLE {
+            (self.exports.as_ref()?, 0)
+        } else {
+            let library = self.libraries.get(&handle)?;
+            (&library.instance.exports, library.memory_base)
+        };
+
+        exports
+            .get_extern(name)
+            .or_else(|| exports.get_extern(&format!("_{}", name)))
+            .map(|export| (export.clone(), memory_base))
+    }
+
+    /// Finds the symbol `name` in the main module, then in the libraries
+    /// in the order they were loaded.
+    fn resolve(&self, name: &str) -> Option<(i32, Extern, u32)> {
+        let mut handles = self.libraries.keys().copied().collect::<Vec<_>>();
+        handles.sort_unstable();
+
+        std::iter::once(MAIN_HANDLE)
+            .chain(handles)
+            .find_map(|handle| {
+                self.lookup(handle, name)
+                    .map(|(export, memory_base)| (handle, export, memory_base))
+            })
+    }
+
+    /// The address of a symbol: the slot of a function in the table, or
+    /// the location of data in the memory.
+    fn address(
+        &mut self,
+        handle: i32,
+        name: &str,
+        export: Extern,
+        memory_base: u32,
+    ) -> Result<u32, String> {
+        match export {
+            Extern::Function(function) => {
+                if let Some(slot) = self.function_slots.get(&(handle, name.to_string())) {
+                    return Ok(*slot);
+                }
+                let slot = self
+                    .table()?
+                    .grow(1, Val::FuncRef(Some(function)))
+                    .map_err(|e| e.to_string())?;
+                self.function_slots.insert((handle, name.to_string()), slot);
+                Ok(slot)
+            }
+            Extern::Global(global) => match global.get() {
+                Val::I32(offset) => Ok(memory_base.wrapping_add(offset as u32)),
+                _ => Err(format!("the symbol `{}` isn't an address", name)),
+            },
+            _ => Err(format!("the symbol `{}` isn't a function or data", name)),
+        }
+    }
+
+    /// Loads the side module at `path`, returning its handle and the
+    /// instance to initialize.
+    fn load(&mut self, ctx: &EmEnv, path: PathBuf) -> Result<(i32, Option<Instance>), String> {
+        if let Some((handle, library)) = self
+            .libraries
+            .iter_mut()
+            .find(|(_, library)| library.path == path)
+        {
+            library.references += 1;
+            return Ok((*handle, None));
+        }
+
+        let table = self.table()?.clone();
+        let store = table.store().clone();
+        let bytes = std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let module = Module::new(&store, bytes).map_err(|e| e.to_string())?;
+        let dylink = DylinkInfo::parse(&module)
+            .ok_or_else(|| format!("{} isn't a side module", path.display()))?;
+
+        // Allocate the memory and the table of the module.
+        let memory_base = if dylink.memory_size > 0 {
+            let align = 1u32.checked_shl(dylink.memory_align).unwrap_or(0).max(16);
+            let memory_base = if get_emscripten_data(ctx).memalign_ref().is_some() {
+                call_memalign(ctx, align, dylink.memory_size)
+            } else {
+                call_malloc(ctx, dylink.memory_size + align)
+            };
+            if memory_base == 0 {
+                return Err("out of memory".to_string());
+            }
+            let memory_base = (memory_base + align - 1) & !(align - 1);
+            call_memset(ctx, memory_base, 0, dylink.memory_size);
+            memory_base
+        } else {
+            0
+        };
+        let table_align = 1u32.checked_shl(dylink.table_align).unwrap_or(1).max(1);
+        let padding = (table_align - table.size() % table_align) % table_align;
+        let table_base = table
+            .grow(padding + dylink.table_size, Val::FuncRef(None))
+            .map_err(|e| e.to_string())?
+            + padding;
+
+        // Resolve the imports. The GOT entries of the symbols defined
+        // by the module itself are filled once it's instantiated.
+        let mut imports = Imports::new();
+        let mut unresolved = Vec::new();
+        for import in module.imports() {
+            let (namespace, name) = (import.module(), import.name());
+            let export: Extern = match (namespace, name) {
+                ("env", "memory") => ctx.memory(0).into(),
+                ("env", "table") | ("env", "__indirect_function_table") => table.clone().into(),
+                ("env", "__memory_base") | ("env", "memoryBase") | ("env", "gb") => {
+                    Global::new(&store, Val::I32(memory_base as i32)).into()
+                }
+                ("env", "__table_base") | ("env", "tableBase") | ("env", "fb") => {
+                    Global::new(&store, Val::I32(table_base as i32)).into()
+                }
+                ("GOT.mem", _) | ("GOT.func", _) => {
+                    let address = match self.resolve(name) {
+                        Some((handle, export, memory_base)) => {
+                            self.address(handle, name, export, memory_base)?
+                        }
+                        None => 0,
+                    };
+                    let global = Global::new_mut(&store, Val::I32(address as i32));
+                    unresolved.push((name.to_string(), global.clone()));
+                    global.into()
+                }
+                ("env", _) => match self.resolve(name) {
+                    Some((_, export, _)) => export,
+                    None => return Err(format!("undefined symbol `{}`", name)),
+                },
+                _ => return Err(format!("unknown import `{}.{}`", namespace, name)),
+            };
+            imports.define(namespace, name, export);
+        }
+
+        let instance = Instance::new(&module, &imports).map_err(|e| e.to_string())?;
+        let handle = self.next_handle.max(MAIN_HANDLE + 1);
+        self.next_handle = handle + 1;
+        self.libraries.insert(
+            handle,
+            Library {
+                path,
+                instance: instance.clone(),
+                memory_base,
+                references: 1,
+            },
+        );
 
-// TODO: Need to implement.
+        for (name, global) in unresolved {
+            if global.get() != Val::I32(0) {
+                continue;
+            }
+            if let Some((export, memory_base)) = self.lookup(handle, &name) {
+                let address = self.address(handle, &name, export, memory_base)?;
+                global
+                    .set(Val::I32(address as i32))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        Ok((handle, Some(instance)))
+    }
+
+    fn fail(&mut self, error: String) {
+        debug!("emscripten::dl error: {}", error);
+        self.error = Some(error);
+    }
+}
+
+impl DylinkInfo {
+    /// Reads the `dylink` section of a side module, in its legacy or in
+    /// its `dylink.0` format.
+    fn parse(module: &Module) -> Option<Self> {
+        if let Some(section) = module.custom_sections("dylink.0").next() {
+            let mut bytes = &section[..];
+            while !bytes.is_empty() {
+                let kind = read_u8(&mut bytes)?;
+                let size = read_leb128(&mut bytes)? as usize;
+                if size > bytes.len() {
+                    return None;
+                }
+                let (mut payload, rest) = bytes.split_at(size);
+                // `WASM_DYLINK_MEM_INFO`.
+                if kind == 1 {
+                    return Self::read(&mut payload);
+                }
+                bytes = rest;
+            }
+            return Some(Self::default());
+        }
+
+        let section = module.custom_sections("dylink").next()?;
+        Self::read(&mut &section[..])
+    }
+
+    fn read(bytes: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            memory_size: read_leb128(bytes)?,
+            memory_align: read_leb128(bytes)?,
+            table_size: read_leb128(bytes)?,
+            table_align: read_leb128(bytes)?,
+        })
+    }
+}
+
+fn read_u8(bytes: &mut &[u8]) -> Option<u8> {
+    let (byte, rest) = bytes.split_first()?;
+    *bytes = rest;
+    Some(*byte)
+}
+
+fn read_leb128(bytes: &mut &[u8]) -> Option<u32> {
+    let mut value = 0u32;
+    let mut shift = 0;
+    loop {
+        let byte = read_u8(bytes)?;
+        if shift >= 32 {
+            return None;
+        }
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+/// The functions run when a side module is loaded, which apply its
+/// relocations and construct its globals.
+const INIT_FUNCTIONS: &[&str] = &[
+    "__wasm_apply_data_relocs",
+    "__wasm_apply_relocs",
+    "__wasm_call_ctors",
+    "__post_instantiate",
+];
 
 /// emscripten: dlopen(filename: *const c_char, flag: c_int) -> *mut c_void
-pub fn _dlopen(_ctx: &EmEnv, _filename: u32, _flag: u32) -> i32 {
-    debug!("emscripten::_dlopen");
-    -1
+pub fn _dlopen(ctx: &EmEnv, filename: u32, _flag: u32) -> i32 {
+    debug!("emscripten::_dlopen({})", filename);
+    if filename == 0 {
+        return MAIN_HANDLE;
+    }
+
+    let path = match WasmPtr::<u8>::new(filename).read_utf8_string_with_nul(&ctx.memory(0)) {
+        Ok(path) => path,
+        Err(e) => {
+            ctx.linker.lock().unwrap().fail(e.to_string());
+            return 0;
+        }
+    };
+    let path = CString::new(path.clone())
+        .ok()
+        .and_then(|cpath| get_cstr_path(ctx, cpath.as_ptr() as *const _))
+        .and_then(|cpath| cpath.into_string().ok())
+        .unwrap_or(path);
+
+    let loaded = ctx.linker.lock().unwrap().load(ctx, PathBuf::from(path));
+    let (handle, instance) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            ctx.linker.lock().unwrap().fail(e);
+            return 0;
+        }
+    };
+
+    // The module is initialized without the linker locked, as it can
+    // load other modules.
+    if let Some(instance) = instance {
+        for name in INIT_FUNCTIONS {
+            if let Ok(init) = instance.exports.get_function(name) {
+                if let Err(e) = init.call(&[]) {
+                    ctx.linker.lock().unwrap().fail(e.to_string());
+                    return 0;
+                }
+            }
+        }
+    }
+
+    handle
 }
 
 /// emscripten: dlclose(handle: *mut c_void) -> c_int
-pub fn _dlclose(_ctx: &EmEnv, _filename: u32) -> i32 {
-    debug!("emscripten::_dlclose");
-    -1
+pub fn _dlclose(ctx: &EmEnv, handle: i32) -> i32 {
+    debug!("emscripten::_dlclose({})", handle);
+    if handle == MAIN_HANDLE {
+        return 0;
+    }
+
+    // The memory and the table slots of the module stay allocated, as
+    // the program may still reference them.
+    let mut linker = ctx.linker.lock().unwrap();
+    match linker.libraries.get_mut(&handle) {
+        Some(library) => {
+            library.references -= 1;
+            if library.references == 0 {
+                linker.libraries.remove(&handle);
+            }
+            0
+        }
+        None => {
+            linker.fail(format!("invalid handle {}", handle));
+            -1
+        }
+    }
 }
 
 /// emscripten: dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void
-pub fn _dlsym(_ctx: &EmEnv, _filepath: u32, _symbol: u32) -> i32 {
-    debug!("emscripten::_dlsym");
-    -1
+pub fn _dlsym(ctx: &EmEnv, handle: i32, symbol: u32) -> i32 {
+    debug!("emscripten::_dlsym({}, {})", handle, symbol);
+    let symbol = match WasmPtr::<u8>::new(symbol).read_utf8_string_with_nul(&ctx.memory(0)) {
+        Ok(symbol) => symbol,
+        Err(e) => {
+            ctx.linker.lock().unwrap().fail(e.to_string());
+            return 0;
+        }
+    };
+
+    let mut linker = ctx.linker.lock().unwrap();
+    let address = match linker.lookup(handle, &symbol) {
+        Some((export, memory_base)) => linker.address(handle, &symbol, export, memory_base),
+        None => Err(format!("undefined symbol `{}`", symbol)),
+    };
+    match address {
+        Ok(address) => address as i32,
+        Err(e) => {
+            linker.fail(e);
+            0
+        }
+    }
 }
 
 /// emscripten: dlerror() -> *mut c_char
-pub fn _dlerror(_ctx: &EmEnv) -> i32 {
+pub fn _dlerror(ctx: &EmEnv) -> i32 {
     debug!("emscripten::_dlerror");
-    -1
+    let (error, previous) = {
+        let mut linker = ctx.linker.lock().unwrap();
+        let previous = std::mem::replace(&mut linker.error_message, 0);
+        (linker.error.take(), previous)
+    };
+
+    if previous != 0 {
+        if let Some(free) = get_emscripten_data(ctx).free_ref() {
+            let _ = free.call(previous);
+        }
+    }
+    let error = match error.and_then(|error| CString::new(error).ok()) {
+        Some(error) => error,
+        None => return 0,
+    };
+
+    let message = unsafe { copy_cstr_into_wasm(ctx, error.as_ptr()) };
+    ctx.linker.lock().unwrap().error_message = message;
+    message as i32
 }
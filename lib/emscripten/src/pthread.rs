@@ -1,4 +1,276 @@
-use crate::EmEnv;
+//! The pthreads of emscripten programs.
+//!
+//! The threads are opt-in, with [`EmEnv::enable_threads`], as they
+//! require the memory of the program to be shared. Each thread then
+//! runs on a host thread, in its own instance of the module, with its
+//! own table and stack, as a Web Worker would. The mutexes and
+//! condition variables are host ones, keyed by their address in the
+//! memory.
+
+use crate::env::{call_malloc, get_emscripten_data};
+use crate::fs::EmFs;
+use crate::{generate_emscripten_env, EmEnv, EmscriptenGlobals};
+use libc::{EAGAIN, EBUSY, EINVAL, EPERM, ESRCH, ETIMEDOUT};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use wasmer::{Instance, Module, RuntimeError, Store, Table, Val, WaitResult, WasmPtr};
+
+/// The size of the stacks of the threads, the default of emscripten.
+const PTHREAD_STACK_SIZE: u32 = 2 * 1024 * 1024;
+
+/// The threads of an emscripten program, shared by all of them.
+pub(crate) struct EmThreads {
+    store: Store,
+    module: Module,
+    globals: EmscriptenGlobals,
+    mapped_dirs: HashMap<String, PathBuf>,
+    next_id: AtomicI32,
+    handles: Mutex<HashMap<i32, JoinHandle<i32>>>,
+    mutexes: Mutex<HashMap<u32, Arc<HostMutex>>>,
+    conds: Mutex<HashMap<u32, Arc<HostCond>>>,
+}
+
+impl EmThreads {
+    pub(crate) fn new(
+        store: &Store,
+        module: &Module,
+        globals: &EmscriptenGlobals,
+        mapped_dirs: HashMap<String, PathBuf>,
+    ) -> Self {
+        Self {
+            store: store.clone(),
+            module: module.clone(),
+            globals: globals.clone(),
+            mapped_dirs,
+            // The main thread is the thread 0.
+            next_id: AtomicI32::new(1),
+            handles: Mutex::new(HashMap::new()),
+            mutexes: Mutex::new(HashMap::new()),
+            conds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn mutex(&self, address: u32) -> Arc<HostMutex> {
+        self.mutexes
+            .lock()
+            .unwrap()
+            .entry(address)
+            .or_default()
+            .clone()
+    }
+
+    fn cond(&self, address: u32) -> Arc<HostCond> {
+        self.conds
+            .lock()
+            .unwrap()
+            .entry(address)
+            .or_default()
+            .clone()
+    }
+
+    /// Runs the thread `id`, returning the value of its start routine, or
+    /// the one it gave to `pthread_exit`.
+    fn run(self: Arc<Self>, parent: ThreadParent, start_routine: u32, arg: i32) -> i32 {
+        let id = parent.id;
+        match self.start(parent, start_routine, arg) {
+            Ok(value) => value,
+            Err(error) => match error.downcast::<PthreadExit>() {
+                Ok(PthreadExit(value)) => value,
+                Err(error) => {
+                    error!("emscripten::pthread {} failed: {}", id, error);
+                    0
+                }
+            },
+        }
+    }
+
+    fn start(
+        self: &Arc<Self>,
+        parent: ThreadParent,
+        start_routine: u32,
+        arg: i32,
+    ) -> Result<i32, RuntimeError> {
+        let ThreadParent { id, stack, fs } = parent;
+        let mut globals = self.globals.clone();
+        globals.table = Table::new(&self.store, *globals.table.ty(), Val::FuncRef(None))?;
+        globals.data.stacktop = stack;
+        globals.data.stack_max = stack + PTHREAD_STACK_SIZE;
+
+        let mut env = EmEnv::new(&globals.data, self.mapped_dirs.clone());
+        env.threads = Some(self.clone());
+        env.thread_id = id;
+        env.fs = fs;
+        env.set_memory(globals.memory.clone());
+        let import_object = generate_emscripten_env(&self.store, &mut globals, &env);
+        let _instance = Instance::new(&self.module, &import_object)
+            .map_err(|e| RuntimeError::new(e.to_string()))?;
+
+        // The routine is called through the table of the instance, like
+        // the `invoke_*` functions do.
+        let dyn_call = get_emscripten_data(&env)
+            .dyn_call_ii_ref()
+            .cloned()
+            .ok_or_else(|| {
+                RuntimeError::new("the module doesn't export `dynCall_ii` to start threads")
+            })?;
+        let result = dyn_call.call(start_routine as i32, arg);
+
+        if let Some(free) = get_emscripten_data(&env).free_ref() {
+            free.call(stack)?;
+        }
+
+        result
+    }
+}
+
+/// What a thread gets from the thread creating it.
+struct ThreadParent {
+    id: i32,
+    stack: u32,
+    fs: Option<Arc<EmFs>>,
+}
+
+/// The error raised by `pthread_exit` to unwind the thread.
+#[derive(Debug)]
+struct PthreadExit(i32);
+
+impl fmt::Display for PthreadExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pthread_exit({})", self.0)
+    }
+}
+
+impl Error for PthreadExit {}
+
+/// A recursive mutex, which can be locked and unlocked in different
+/// calls.
+#[derive(Default)]
+struct HostMutex {
+    /// The thread holding the mutex, and how many times.
+    owner: Mutex<Option<(i32, u32)>>,
+    unlocked: Condvar,
+}
+
+impl HostMutex {
+    fn lock(&self, thread: i32) {
+        self.acquire(thread, 1);
+    }
+
+    fn try_lock(&self, thread: i32) -> i32 {
+        let mut owner = self.owner.lock().unwrap();
+        match &mut *owner {
+            None => *owner = Some((thread, 1)),
+            Some((holder, count)) if *holder == thread => *count += 1,
+            Some(_) => return EBUSY,
+        }
+        0
+    }
+
+    fn unlock(&self, thread: i32) -> i32 {
+        let mut owner = self.owner.lock().unwrap();
+        match &mut *owner {
+            Some((holder, count)) if *holder == thread => {
+                *count -= 1;
+                if *count == 0 {
+                    *owner = None;
+                    self.unlocked.notify_one();
+                }
+                0
+            }
+            _ => EPERM,
+        }
+    }
+
+    /// Locks the mutex `count` times.
+    fn acquire(&self, thread: i32, count: u32) {
+        let mut owner = self.owner.lock().unwrap();
+        loop {
+            match &mut *owner {
+                None => {
+                    *owner = Some((thread, count));
+                    return;
+                }
+                Some((holder, held)) if *holder == thread => {
+                    *held += count;
+                    return;
+                }
+                Some(_) => owner = self.unlocked.wait(owner).unwrap(),
+            }
+        }
+    }
+
+    /// Unlocks the mutex entirely, returning how many times it was
+    /// locked, if `thread` held it.
+    fn release(&self, thread: i32) -> Option<u32> {
+        let mut owner = self.owner.lock().unwrap();
+        match *owner {
+            Some((holder, count)) if holder == thread => {
+                *owner = None;
+                self.unlocked.notify_one();
+                Some(count)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A condition variable, whose waiters wait for the next signal.
+#[derive(Default)]
+struct HostCond {
+    /// How many times the condition has been signaled.
+    signals: Mutex<u64>,
+    signaled: Condvar,
+}
+
+impl HostCond {
+    fn wait(&self, mutex: &HostMutex, thread: i32, deadline: Option<SystemTime>) -> i32 {
+        // The signals are locked before the mutex is released, so that
+        // a signal can't be missed.
+        let mut signals = self.signals.lock().unwrap();
+        let seen = *signals;
+        let count = match mutex.release(thread) {
+            Some(count) => count,
+            None => return EPERM,
+        };
+
+        let mut timed_out = false;
+        while *signals == seen {
+            signals = match deadline {
+                Some(deadline) => match deadline.duration_since(SystemTime::now()) {
+                    Ok(timeout) => self.signaled.wait_timeout(signals, timeout).unwrap().0,
+                    Err(_) => {
+                        timed_out = true;
+                        break;
+                    }
+                },
+                None => self.signaled.wait(signals).unwrap(),
+            };
+        }
+        drop(signals);
+
+        mutex.acquire(thread, count);
+        if timed_out {
+            ETIMEDOUT
+        } else {
+            0
+        }
+    }
+
+    fn signal(&self, all: bool) {
+        *self.signals.lock().unwrap() += 1;
+        if all {
+            self.signaled.notify_all();
+        } else {
+            self.signaled.notify_one();
+        }
+    }
+}
 
 pub fn _pthread_attr_destroy(_ctx: &EmEnv, _a: i32) -> i32 {
     trace!("emscripten::_pthread_attr_destroy");
@@ -36,8 +308,11 @@ pub fn _pthread_cleanup_push(_ctx: &EmEnv, _a: i32, _b: i32) {
     trace!("emscripten::_pthread_cleanup_push");
 }
 
-pub fn _pthread_cond_destroy(_ctx: &EmEnv, _a: i32) -> i32 {
-    trace!("emscripten::_pthread_cond_destroy");
+pub fn _pthread_cond_destroy(ctx: &EmEnv, cond: u32) -> i32 {
+    trace!("emscripten::_pthread_cond_destroy({})", cond);
+    if let Some(threads) = &ctx.threads {
+        threads.conds.lock().unwrap().remove(&cond);
+    }
     0
 }
 
@@ -46,19 +321,59 @@ pub fn _pthread_cond_init(_ctx: &EmEnv, _a: i32, _b: i32) -> i32 {
     0
 }
 
-pub fn _pthread_cond_signal(_ctx: &EmEnv, _a: i32) -> i32 {
-    trace!("emscripten::_pthread_cond_signal");
+pub fn _pthread_cond_signal(ctx: &EmEnv, cond: u32) -> i32 {
+    trace!("emscripten::_pthread_cond_signal({})", cond);
+    if let Some(threads) = &ctx.threads {
+        threads.cond(cond).signal(false);
+    }
     0
 }
 
-pub fn _pthread_cond_timedwait(_ctx: &EmEnv, _a: i32, _b: i32, _c: i32) -> i32 {
-    trace!("emscripten::_pthread_cond_timedwait");
+pub fn _pthread_cond_broadcast(ctx: &EmEnv, cond: u32) -> i32 {
+    trace!("emscripten::_pthread_cond_broadcast({})", cond);
+    if let Some(threads) = &ctx.threads {
+        threads.cond(cond).signal(true);
+    }
     0
 }
 
-pub fn _pthread_cond_wait(_ctx: &EmEnv, _a: i32, _b: i32) -> i32 {
-    trace!("emscripten::_pthread_cond_wait");
-    0
+pub fn _pthread_cond_timedwait(ctx: &EmEnv, cond: u32, mutex: u32, abstime: u32) -> i32 {
+    trace!(
+        "emscripten::_pthread_cond_timedwait({}, {}, {})",
+        cond,
+        mutex,
+        abstime
+    );
+    let threads = match &ctx.threads {
+        Some(threads) => threads,
+        None => return 0,
+    };
+
+    // `abstime` is a `struct timespec` of the realtime clock.
+    let memory = ctx.memory(0);
+    let seconds = WasmPtr::<i32>::new(abstime).deref(&memory).read();
+    let nanoseconds = WasmPtr::<i32>::new(abstime + 4).deref(&memory).read();
+    let (seconds, nanoseconds) = match (seconds, nanoseconds) {
+        (Ok(seconds), Ok(nanoseconds)) if (0..1_000_000_000).contains(&nanoseconds) => {
+            (seconds.max(0) as u64, nanoseconds as u32)
+        }
+        _ => return EINVAL,
+    };
+    let deadline = UNIX_EPOCH + Duration::new(seconds, nanoseconds);
+
+    threads
+        .cond(cond)
+        .wait(&threads.mutex(mutex), ctx.thread_id, Some(deadline))
+}
+
+pub fn _pthread_cond_wait(ctx: &EmEnv, cond: u32, mutex: u32) -> i32 {
+    trace!("emscripten::_pthread_cond_wait({}, {})", cond, mutex);
+    match &ctx.threads {
+        Some(threads) => threads
+            .cond(cond)
+            .wait(&threads.mutex(mutex), ctx.thread_id, None),
+        None => 0,
+    }
 }
 
 pub fn _pthread_condattr_destroy(_ctx: &EmEnv, _a: i32) -> i32 {
@@ -76,24 +391,74 @@ pub fn _pthread_condattr_setclock(_ctx: &EmEnv, _a: i32, _b: i32) -> i32 {
     0
 }
 
-pub fn _pthread_create(_ctx: &EmEnv, _a: i32, _b: i32, _c: i32, _d: i32) -> i32 {
-    trace!("emscripten::_pthread_create");
-    // 11 seems to mean "no"
-    11
-}
-
-pub fn _pthread_detach(_ctx: &EmEnv, _a: i32) -> i32 {
-    trace!("emscripten::_pthread_detach");
-    0
-}
-
-pub fn _pthread_equal(_ctx: &EmEnv, _a: i32, _b: i32) -> i32 {
-    trace!("emscripten::_pthread_equal");
-    0
-}
-
-pub fn _pthread_exit(_ctx: &EmEnv, _a: i32) {
-    trace!("emscripten::_pthread_exit");
+pub fn _pthread_create(ctx: &EmEnv, thread: u32, _attr: u32, start_routine: u32, arg: i32) -> i32 {
+    trace!(
+        "emscripten::_pthread_create({}, {}, {})",
+        thread,
+        start_routine,
+        arg
+    );
+    let threads = match &ctx.threads {
+        Some(threads) => threads.clone(),
+        None => return EAGAIN,
+    };
+
+    let id = threads.next_id.fetch_add(1, Ordering::SeqCst);
+    if WasmPtr::<i32>::new(thread)
+        .deref(&ctx.memory(0))
+        .write(id)
+        .is_err()
+    {
+        return EINVAL;
+    }
+    let stack = call_malloc(ctx, PTHREAD_STACK_SIZE);
+    if stack == 0 {
+        return EAGAIN;
+    }
+
+    let handle = {
+        let threads = threads.clone();
+        let parent = ThreadParent {
+            id,
+            stack,
+            fs: ctx.fs.clone(),
+        };
+        thread::Builder::new()
+            .name(format!("pthread-{}", id))
+            .spawn(move || threads.run(parent, start_routine, arg))
+    };
+    match handle {
+        Ok(handle) => {
+            threads.handles.lock().unwrap().insert(id, handle);
+            0
+        }
+        Err(_) => EAGAIN,
+    }
+}
+
+pub fn _pthread_detach(ctx: &EmEnv, thread: i32) -> i32 {
+    trace!("emscripten::_pthread_detach({})", thread);
+    if let Some(threads) = &ctx.threads {
+        if threads.handles.lock().unwrap().remove(&thread).is_none() {
+            return ESRCH;
+        }
+    }
+    0
+}
+
+pub fn _pthread_equal(_ctx: &EmEnv, a: i32, b: i32) -> i32 {
+    trace!("emscripten::_pthread_equal({}, {})", a, b);
+    (a == b) as i32
+}
+
+pub fn _pthread_exit(ctx: &EmEnv, value: i32) -> Result<(), RuntimeError> {
+    trace!("emscripten::_pthread_exit({})", value);
+    // The thread unwinds up to its start routine, the main thread
+    // keeps running.
+    if ctx.threads.is_some() && ctx.thread_id != 0 {
+        return Err(RuntimeError::user(Box::new(PthreadExit(value))));
+    }
+    Ok(())
 }
 
 pub fn _pthread_getattr_np(_ctx: &EmEnv, _thread: i32, _attr: i32) -> i32 {
@@ -106,14 +471,32 @@ pub fn _pthread_getspecific(_ctx: &EmEnv, _a: i32) -> i32 {
     0
 }
 
-pub fn _pthread_join(_ctx: &EmEnv, _a: i32, _b: i32) -> i32 {
-    trace!("emscripten::_pthread_join");
+pub fn _pthread_join(ctx: &EmEnv, thread: i32, retval: u32) -> i32 {
+    trace!("emscripten::_pthread_join({}, {})", thread, retval);
+    let handle = match &ctx.threads {
+        Some(threads) => threads.handles.lock().unwrap().remove(&thread),
+        None => None,
+    };
+    let handle = match handle {
+        Some(handle) => handle,
+        None => return ESRCH,
+    };
+
+    let value = handle.join().unwrap_or(0);
+    if retval != 0
+        && WasmPtr::<i32>::new(retval)
+            .deref(&ctx.memory(0))
+            .write(value)
+            .is_err()
+    {
+        return EINVAL;
+    }
     0
 }
 
-pub fn _pthread_self(_ctx: &EmEnv) -> i32 {
+pub fn _pthread_self(ctx: &EmEnv) -> i32 {
     trace!("emscripten::_pthread_self");
-    0
+    ctx.thread_id
 }
 
 pub fn _pthread_key_create(_ctx: &EmEnv, _a: i32, _b: i32) -> i32 {
@@ -121,8 +504,11 @@ pub fn _pthread_key_create(_ctx: &EmEnv, _a: i32, _b: i32) -> i32 {
     0
 }
 
-pub fn _pthread_mutex_destroy(_ctx: &EmEnv, _a: i32) -> i32 {
-    trace!("emscripten::_pthread_mutex_destroy");
+pub fn _pthread_mutex_destroy(ctx: &EmEnv, mutex: u32) -> i32 {
+    trace!("emscripten::_pthread_mutex_destroy({})", mutex);
+    if let Some(threads) = &ctx.threads {
+        threads.mutexes.lock().unwrap().remove(&mutex);
+    }
     0
 }
 
@@ -131,6 +517,30 @@ pub fn _pthread_mutex_init(_ctx: &EmEnv, _a: i32, _b: i32) -> i32 {
     0
 }
 
+pub fn _pthread_mutex_lock(ctx: &EmEnv, mutex: u32) -> i32 {
+    trace!("emscripten::_pthread_mutex_lock({})", mutex);
+    if let Some(threads) = &ctx.threads {
+        threads.mutex(mutex).lock(ctx.thread_id);
+    }
+    0
+}
+
+pub fn _pthread_mutex_trylock(ctx: &EmEnv, mutex: u32) -> i32 {
+    trace!("emscripten::_pthread_mutex_trylock({})", mutex);
+    match &ctx.threads {
+        Some(threads) => threads.mutex(mutex).try_lock(ctx.thread_id),
+        None => 0,
+    }
+}
+
+pub fn _pthread_mutex_unlock(ctx: &EmEnv, mutex: u32) -> i32 {
+    trace!("emscripten::_pthread_mutex_unlock({})", mutex);
+    match &ctx.threads {
+        Some(threads) => threads.mutex(mutex).unlock(ctx.thread_id),
+        None => 0,
+    }
+}
+
 pub fn _pthread_mutexattr_destroy(_ctx: &EmEnv, _a: i32) -> i32 {
     trace!("emscripten::_pthread_mutexattr_destroy");
     0
@@ -190,3 +600,137 @@ pub fn _pthread_sigmask(_ctx: &EmEnv, _a: i32, _b: i32, _c: i32) -> i32 {
     trace!("emscripten::_pthread_sigmask");
     0
 }
+
+pub fn _emscripten_futex_wait(ctx: &EmEnv, address: u32, value: u32, max_wait_ms: f64) -> i32 {
+    trace!(
+        "emscripten::_emscripten_futex_wait({}, {}, {})",
+        address,
+        value,
+        max_wait_ms
+    );
+    let timeout = if max_wait_ms.is_finite() {
+        Some(Duration::from_secs_f64(max_wait_ms.max(0.0) / 1000.0))
+    } else {
+        None
+    };
+    match ctx.memory(0).wait32(address as u64, value, timeout) {
+        Ok(WaitResult::Ok) => 0,
+        Ok(WaitResult::NotEqual) => -EAGAIN,
+        Ok(WaitResult::TimedOut) => -ETIMEDOUT,
+        Err(_) => -EINVAL,
+    }
+}
+
+pub fn _emscripten_futex_wake(ctx: &EmEnv, address: u32, count: i32) -> i32 {
+    trace!("emscripten::_emscripten_futex_wake({}, {})", address, count);
+    let count = if count < 0 { u32::MAX } else { count as u32 };
+    match ctx.memory(0).notify(address as u64, count) {
+        Ok(woken) => woken.min(i32::MAX as u32) as i32,
+        Err(_) => -EINVAL,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HostCond, HostMutex};
+    use libc::{EBUSY, EPERM, ETIMEDOUT};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn mutex_is_recursive() {
+        let mutex = HostMutex::default();
+        mutex.lock(1);
+        mutex.lock(1);
+        assert_eq!(mutex.try_lock(1), 0);
+        assert_eq!(mutex.try_lock(2), EBUSY);
+
+        assert_eq!(mutex.unlock(1), 0);
+        assert_eq!(mutex.unlock(1), 0);
+        assert_eq!(mutex.try_lock(2), EBUSY);
+        assert_eq!(mutex.unlock(1), 0);
+        assert_eq!(mutex.try_lock(2), 0);
+        assert_eq!(mutex.unlock(2), 0);
+    }
+
+    #[test]
+    fn mutex_is_only_unlocked_by_its_owner() {
+        let mutex = HostMutex::default();
+        assert_eq!(mutex.unlock(1), EPERM);
+
+        mutex.lock(1);
+        assert_eq!(mutex.unlock(2), EPERM);
+        assert_eq!(mutex.unlock(1), 0);
+        assert_eq!(mutex.unlock(1), EPERM);
+    }
+
+    #[test]
+    fn mutex_lock_waits_for_unlock() {
+        let mutex = Arc::new(HostMutex::default());
+        mutex.lock(1);
+
+        let locked = Arc::new(AtomicBool::new(false));
+        let locker = {
+            let (mutex, locked) = (mutex.clone(), locked.clone());
+            thread::spawn(move || {
+                mutex.lock(2);
+                locked.store(true, Ordering::SeqCst);
+                mutex.unlock(2)
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!locked.load(Ordering::SeqCst));
+        assert_eq!(mutex.unlock(1), 0);
+        assert_eq!(locker.join().unwrap(), 0);
+        assert!(locked.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn cond_wait_times_out() {
+        let mutex = HostMutex::default();
+        let cond = HostCond::default();
+        mutex.lock(1);
+        mutex.lock(1);
+
+        let deadline = SystemTime::now() + Duration::from_millis(20);
+        assert_eq!(cond.wait(&mutex, 1, Some(deadline)), ETIMEDOUT);
+        assert!(SystemTime::now() >= deadline);
+        // The mutex is held again, as many times as before.
+        assert_eq!(mutex.try_lock(2), EBUSY);
+        assert_eq!(mutex.unlock(1), 0);
+        assert_eq!(mutex.unlock(1), 0);
+        assert_eq!(mutex.unlock(1), EPERM);
+    }
+
+    #[test]
+    fn cond_wait_requires_the_mutex() {
+        let mutex = HostMutex::default();
+        let cond = HostCond::default();
+        assert_eq!(cond.wait(&mutex, 1, None), EPERM);
+
+        mutex.lock(2);
+        assert_eq!(cond.wait(&mutex, 1, None), EPERM);
+    }
+
+    #[test]
+    fn cond_signal_wakes_a_waiter() {
+        let mutex = Arc::new(HostMutex::default());
+        let cond = Arc::new(HostCond::default());
+        mutex.lock(1);
+
+        let signaler = {
+            let (mutex, cond) = (mutex.clone(), cond.clone());
+            thread::spawn(move || {
+                // The waiter released the mutex while waiting.
+                mutex.lock(2);
+                cond.signal(false);
+                mutex.unlock(2)
+            })
+        };
+        assert_eq!(cond.wait(&mutex, 1, None), 0);
+        assert_eq!(mutex.unlock(1), 0);
+        assert_eq!(signaler.join().unwrap(), 0);
+    }
+}
@@ -11,6 +11,7 @@ pub use self::unix::*;
 pub use self::windows::*;
 
 use crate::{
+    fs::{self, syscall_ret, virtual_path, EmFs},
     utils::{copy_stat_into_wasm, get_cstr_path, get_current_directory},
     EmEnv,
 };
@@ -67,6 +68,9 @@ pub fn ___syscall3(ctx: &EmEnv, _which: i32, mut varargs: VarArgs) -> i32 {
     let buf: u32 = varargs.get(ctx);
     let count: i32 = varargs.get(ctx);
     debug!("=> fd: {}, buf_offset: {}, count: {}", fd, buf, count);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return fs::read(ctx, vfs, fd, buf, count as u32);
+    }
     let buf_addr = emscripten_memory_pointer!(ctx.memory(0), buf) as *mut c_void;
     let ret = unsafe { read(fd, buf_addr, count as _) };
     debug!("=> ret: {}", ret);
@@ -80,6 +84,9 @@ pub fn ___syscall4(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     let buf: i32 = varargs.get(ctx);
     let count: i32 = varargs.get(ctx);
     debug!("=> fd: {}, buf: {}, count: {}", fd, buf, count);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return fs::write(ctx, vfs, fd, buf as u32, count as u32);
+    }
     let buf_addr = emscripten_memory_pointer!(ctx.memory(0), buf) as *const c_void;
     unsafe { write(fd, buf_addr, count as _) as i32 }
 }
@@ -89,6 +96,9 @@ pub fn ___syscall6(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall6 (close) {}", _which);
     let fd: i32 = varargs.get(ctx);
     debug!("fd: {}", fd);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return syscall_ret(vfs.close(fd).map(|()| 0));
+    }
     unsafe { close(fd) }
 }
 
@@ -111,8 +121,16 @@ pub fn ___syscall12(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     ret
 }
 
-pub fn ___syscall10(_ctx: &EmEnv, _one: i32, _two: i32) -> i32 {
-    debug!("emscripten::___syscall10");
+// unlink
+pub fn ___syscall10(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
+    debug!("emscripten::___syscall10 (unlink)");
+    if let Some(vfs) = ctx.fs() {
+        let pathname_addr = varargs.get_str(ctx);
+        return syscall_ret(virtual_path(pathname_addr).and_then(|path| {
+            vfs.remove_file(&path)?;
+            Ok(0)
+        }));
+    }
     -1
 }
 
@@ -167,6 +185,12 @@ pub fn ___syscall38(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> i32 {
     debug!("emscripten::___syscall38 (rename)");
     let old_path = varargs.get_str(ctx);
     let new_path = varargs.get_str(ctx);
+    if let Some(vfs) = ctx.fs() {
+        return syscall_ret(virtual_path(old_path).and_then(|old_path| {
+            vfs.rename(&old_path, &virtual_path(new_path)?)?;
+            Ok(0)
+        }));
+    }
     let real_old_path_owned = get_cstr_path(ctx, old_path as *const _);
     let real_old_path = if let Some(ref rp) = real_old_path_owned {
         rp.as_c_str().as_ptr()
@@ -193,6 +217,12 @@ pub fn ___syscall38(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> i32 {
 pub fn ___syscall40(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall40 (rmdir)");
     let pathname_addr = varargs.get_str(ctx);
+    if let Some(vfs) = ctx.fs() {
+        return syscall_ret(virtual_path(pathname_addr).and_then(|path| {
+            vfs.remove_dir(&path)?;
+            Ok(0)
+        }));
+    }
     let real_path_owned = get_cstr_path(ctx, pathname_addr as *const _);
     let real_path = if let Some(ref rp) = real_path_owned {
         rp.as_c_str().as_ptr()
@@ -409,8 +439,17 @@ pub fn ___syscall140(ctx: &EmEnv, _which: i32, mut varargs: VarArgs) -> i32 {
     let result_ptr_value: WasmPtr<i64> = varargs.get(ctx);
     let whence: i32 = varargs.get(ctx);
     let offset = offset_low;
-    let ret = unsafe { lseek(fd, offset as _, whence) as i64 };
     let memory = ctx.memory(0);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return match vfs.seek(fd, offset as i64, whence) {
+            Ok(position) => match result_ptr_value.deref(&memory).write(position as i64) {
+                Ok(()) => 0,
+                Err(_) => -libc::EFAULT,
+            },
+            Err(errno) => -errno,
+        };
+    }
+    let ret = unsafe { lseek(fd, offset as _, whence) as i64 };
 
     let result_ptr = result_ptr_value.deref(&memory);
     result_ptr.write(ret).unwrap();
@@ -444,6 +483,9 @@ pub fn ___syscall145(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> i32 {
     }
 
     debug!("=> fd: {}, iov: {}, iovcnt = {}", fd, iov, iovcnt);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return fs::readv(ctx, vfs, fd, iov as u32, iovcnt);
+    }
     let mut ret = 0;
     unsafe {
         for i in 0..iovcnt {
@@ -480,6 +522,9 @@ pub fn ___syscall146(ctx: &EmEnv, _which: i32, mut varargs: VarArgs) -> i32 {
     }
 
     debug!("=> fd: {}, iov: {}, iovcnt = {}", fd, iov, iovcnt);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return fs::writev(ctx, vfs, fd, iov as u32, iovcnt);
+    }
     let mut ret = 0;
     for i in 0..iovcnt {
         unsafe {
@@ -534,6 +579,9 @@ pub fn ___syscall195(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int
     debug!("emscripten::___syscall195 (stat64) {}", _which);
     let pathname_addr = varargs.get_str(ctx);
     let buf: u32 = varargs.get(ctx);
+    if let Some(vfs) = ctx.fs() {
+        return fs::stat(ctx, vfs, pathname_addr, buf, true);
+    }
 
     let real_path_owned = get_cstr_path(ctx, pathname_addr as *const _);
     let real_path = if let Some(ref rp) = real_path_owned {
@@ -566,6 +614,9 @@ pub fn ___syscall197(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int
 
     let fd: c_int = varargs.get(ctx);
     let buf: u32 = varargs.get(ctx);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return fs::fstat(ctx, vfs, fd, buf);
+    }
 
     unsafe {
         let mut stat = std::mem::zeroed();
@@ -115,6 +115,7 @@ fn translate_ioctl(wasm_ioctl: u32) -> c_ulong {
 use std::ffi::CStr;
 
 use crate::env::EmSockAddr;
+use crate::fs::{self, syscall_ret, virtual_path, EmFs};
 use crate::utils::{self, get_cstr_path};
 use crate::EmEnv;
 #[allow(unused_imports)]
@@ -162,6 +163,9 @@ pub fn ___syscall5(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     let pathname_addr = varargs.get_str(ctx);
     let flags: i32 = varargs.get(ctx);
     let mode: u32 = varargs.get(ctx);
+    if let Some(vfs) = ctx.fs() {
+        return syscall_ret(virtual_path(pathname_addr).and_then(|path| vfs.open(&path, flags)));
+    }
     let real_path_owned = utils::get_cstr_path(ctx, pathname_addr as *const _);
     let real_path = if let Some(ref rp) = real_path_owned {
         rp.as_c_str().as_ptr()
@@ -270,6 +274,9 @@ pub fn ___syscall194(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int
     debug!("emscripten::___syscall194 (ftruncate64) {}", _which);
     let _fd: c_int = varargs.get(ctx);
     let _length: i64 = varargs.get(ctx);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(_fd)) {
+        return syscall_ret(vfs.truncate(_fd, _length as u64).map(|()| 0));
+    }
     #[cfg(not(any(target_os = "freebsd", target_vendor = "apple")))]
     unsafe {
         ftruncate64(_fd, _length)
@@ -356,6 +363,12 @@ pub fn ___syscall219(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int
 pub fn ___syscall33(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall33 (access) {}", _which);
     let path = varargs.get_str(ctx);
+    if let Some(vfs) = ctx.fs() {
+        return syscall_ret(virtual_path(path).and_then(|path| {
+            vfs.metadata(&path)?;
+            Ok(0)
+        }));
+    }
     let real_path_owned = utils::get_cstr_path(ctx, path as *const _);
     let real_path = if let Some(ref rp) = real_path_owned {
         rp.as_c_str().as_ptr()
@@ -384,6 +397,12 @@ pub fn ___syscall34(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
 pub fn ___syscall39(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall39 (mkdir) {}", _which);
     let pathname_addr = varargs.get_str(ctx);
+    if let Some(vfs) = ctx.fs() {
+        return syscall_ret(virtual_path(pathname_addr).and_then(|path| {
+            vfs.create_dir(&path)?;
+            Ok(0)
+        }));
+    }
     let real_path_owned = utils::get_cstr_path(ctx, pathname_addr as *const _);
     let real_path = if let Some(ref rp) = real_path_owned {
         rp.as_c_str().as_ptr()
@@ -882,6 +901,9 @@ pub fn ___syscall180(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int
         assert_eq!(zero, 0);
     }
     let offset: i64 = varargs.get(ctx);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return fs::pread(ctx, vfs, fd, buf, count, offset);
+    }
 
     let buf_ptr = emscripten_memory_pointer!(ctx.memory(0), buf) as _;
 
@@ -899,6 +921,9 @@ pub fn ___syscall181(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int
         assert_eq!(zero, 0);
     }
     let offset: i64 = varargs.get(ctx);
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return fs::pwrite(ctx, vfs, fd, buf, count, offset);
+    }
 
     let buf_ptr = emscripten_memory_pointer!(ctx.memory(0), buf) as _;
     let status = unsafe { pwrite(fd, buf_ptr, count as _, offset) as _ };
@@ -1004,6 +1029,10 @@ pub fn ___syscall122(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int
 pub fn ___syscall196(ctx: &EmEnv, _which: i32, mut varargs: VarArgs) -> i32 {
     debug!("emscripten::___syscall196 (lstat64) {}", _which);
     let path = varargs.get_str(ctx);
+    if let Some(vfs) = ctx.fs() {
+        let buf_ptr: u32 = varargs.get(ctx);
+        return fs::stat(ctx, vfs, path, buf_ptr, false);
+    }
     let real_path_owned = utils::get_cstr_path(ctx, path as *const _);
     let real_path = if let Some(ref rp) = real_path_owned {
         rp.as_c_str().as_ptr()
@@ -1054,6 +1083,9 @@ pub fn ___syscall220(ctx: &EmEnv, _which: i32, mut varargs: VarArgs) -> i32 {
         "emscripten::___syscall220 (getdents) {} {} {}",
         fd, dirp_addr, count
     );
+    if let Some(vfs) = ctx.fs().filter(|_| EmFs::owns(fd)) {
+        return fs::getdents(ctx, vfs, fd, dirp_addr as u32, count);
+    }
 
     let dirp = emscripten_memory_pointer!(ctx.memory(0), dirp_addr) as *mut u8;
 
@@ -1,3 +1,4 @@
+use crate::fs::{syscall_ret, virtual_path};
 use crate::utils::{copy_cstr_into_wasm, get_cstr_path};
 use crate::varargs::VarArgs;
 use crate::EmEnv;
@@ -26,6 +27,9 @@ pub fn ___syscall5(ctx: &EmEnv, which: c_int, mut varargs: VarArgs) -> c_int {
     };
     let flags: i32 = varargs.get(ctx);
     let mode: u32 = varargs.get(ctx);
+    if let Some(vfs) = ctx.fs() {
+        return syscall_ret(virtual_path(pathname_addr).and_then(|path| vfs.open(&path, flags)));
+    }
     let path_str = unsafe { std::ffi::CStr::from_ptr(real_path).to_str().unwrap() };
     let memory = ctx.memory(0);
 
@@ -103,6 +107,12 @@ pub fn ___syscall39(ctx: &EmEnv, which: c_int, mut varargs: VarArgs) -> c_int {
     #[cfg(not(feature = "debug"))]
     let _ = which;
     let pathname_addr = varargs.get_str(ctx);
+    if let Some(vfs) = ctx.fs() {
+        return syscall_ret(virtual_path(pathname_addr).and_then(|path| {
+            vfs.create_dir(&path)?;
+            Ok(0)
+        }));
+    }
     let real_path_owned = get_cstr_path(ctx, pathname_addr);
     let real_path = if let Some(ref rp) = real_path_owned {
         rp.as_c_str().as_ptr()
@@ -9,6 +9,7 @@ use std::os::raw::c_char;
 use std::path::PathBuf;
 use std::slice;
 use wasmer::{GlobalInit, Memory, Module, Pages, WasmPtr};
+use wasmer_vfs::Metadata;
 
 /// We check if a provided module is an Emscripten generated one
 pub fn is_emscripten_module(module: &Module) -> bool {
@@ -188,6 +189,49 @@ pub struct GuestStat {
     st_ino: u32,
 }
 
+/// Copies the metadata of a file of the virtual file system into a
+/// `struct stat` of the guest.
+#[allow(clippy::cast_ptr_alignment)]
+pub(crate) unsafe fn copy_metadata_into_wasm(ctx: &EmEnv, buf: u32, metadata: &Metadata) {
+    // The file types and permissions, as defined by emscripten.
+    let mode = if metadata.ft.dir {
+        0o040000 | 0o755
+    } else if metadata.ft.symlink {
+        0o120000 | 0o777
+    } else if metadata.ft.char_device {
+        0o020000 | 0o666
+    } else if metadata.ft.block_device {
+        0o060000 | 0o666
+    } else if metadata.ft.socket {
+        0o140000 | 0o666
+    } else if metadata.ft.fifo {
+        0o010000 | 0o666
+    } else {
+        0o100000 | 0o644
+    };
+
+    let stat_ptr = emscripten_memory_pointer!(ctx.memory(0), buf) as *mut GuestStat;
+    stat_ptr.write(GuestStat {
+        st_dev: 0,
+        __st_dev_padding: 0,
+        __st_ino_truncated: 0,
+        st_mode: mode,
+        st_nlink: 1,
+        st_uid: 0,
+        st_gid: 0,
+        st_rdev: 0,
+        __st_rdev_padding: 0,
+        st_size: metadata.len as _,
+        st_blksize: 4096,
+        st_blocks: ((metadata.len + 511) / 512) as _,
+        // The times of the virtual file systems are in nanoseconds.
+        st_atime: metadata.accessed / 1_000_000_000,
+        st_mtime: metadata.modified / 1_000_000_000,
+        st_ctime: metadata.created / 1_000_000_000,
+        st_ino: 0,
+    });
+}
+
 #[allow(clippy::cast_ptr_alignment)]
 pub unsafe fn copy_stat_into_wasm(ctx: &EmEnv, buf: u32, stat: &stat) {
     let stat_ptr = emscripten_memory_pointer!(ctx.memory(0), buf) as *mut GuestStat;
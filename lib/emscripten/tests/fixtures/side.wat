@@ -0,0 +1,33 @@
+;; A side module, as built with `-s SIDE_MODULE=1`, needing 16 bytes
+;; aligned on 16 bytes and 2 table slots.
+(module
+    (@custom "dylink" "\10\04\02\00")
+    (import "env" "memory" (memory 1))
+    (import "env" "table" (table 0 funcref))
+    (import "env" "__memory_base" (global $memory_base i32))
+    (import "env" "__table_base" (global $table_base i32))
+    ;; Defined by the main module, as `_host_value`.
+    (import "env" "host_value" (func $host_value (result i32)))
+    ;; Defined by the main module.
+    (import "GOT.mem" "main_data" (global $main_data (mut i32)))
+    ;; Defined by the side module itself.
+    (import "GOT.mem" "counter" (global $counter_address (mut i32)))
+    (import "GOT.func" "double" (global $double_address (mut i32)))
+
+    (global (export "answer") i32 (i32.const 0))
+    (global (export "counter") i32 (i32.const 8))
+    (data (global.get $memory_base) "\03\00\00\00")
+    (elem (global.get $table_base) $double)
+
+    (func $double (export "double") (param i32) (result i32)
+        (i32.mul (local.get 0) (i32.const 2)))
+    (func (export "__wasm_call_ctors")
+        (i32.store (global.get $counter_address) (call $host_value)))
+    (func (export "get_counter") (result i32)
+        (i32.load (global.get $counter_address)))
+    (func (export "get_main_data") (result i32)
+        (i32.load (global.get $main_data)))
+    (func (export "call_double") (param i32) (result i32)
+        (call_indirect (param i32) (result i32)
+            (local.get 0)
+            (global.get $double_address))))
@@ -0,0 +1,196 @@
+#![cfg(unix)]
+
+use libc::{EBADF, ENOENT};
+use std::io::{Read, Write};
+use std::path::Path;
+use wasmer::{Instance, Memory, Module, Store, Value};
+use wasmer_emscripten::{generate_emscripten_env, EmEnv, EmscriptenGlobals};
+use wasmer_vfs::{mem_fs, FileSystem};
+
+/// A program exposing the file syscalls, which take the address of
+/// their arguments.
+const PROGRAM: &str = r#"
+(module
+    (import "env" "memory" (memory 256))
+    (import "env" "table" (table 1 funcref))
+    (import "env" "___syscall3" (func $read (param i32 i32) (result i32)))
+    (import "env" "___syscall4" (func $write (param i32 i32) (result i32)))
+    (import "env" "___syscall5" (func $open (param i32 i32) (result i32)))
+    (import "env" "___syscall6" (func $close (param i32 i32) (result i32)))
+    (import "env" "___syscall10" (func $unlink (param i32 i32) (result i32)))
+    (import "env" "___syscall140" (func $lseek (param i32 i32) (result i32)))
+    (import "env" "___syscall195" (func $stat (param i32 i32) (result i32)))
+    (import "env" "___syscall197" (func $fstat (param i32 i32) (result i32)))
+    (func (export "read") (param i32) (result i32)
+        (call $read (i32.const 3) (local.get 0)))
+    (func (export "write") (param i32) (result i32)
+        (call $write (i32.const 4) (local.get 0)))
+    (func (export "open") (param i32) (result i32)
+        (call $open (i32.const 5) (local.get 0)))
+    (func (export "close") (param i32) (result i32)
+        (call $close (i32.const 6) (local.get 0)))
+    (func (export "unlink") (param i32) (result i32)
+        (call $unlink (i32.const 10) (local.get 0)))
+    (func (export "lseek") (param i32) (result i32)
+        (call $lseek (i32.const 140) (local.get 0)))
+    (func (export "stat") (param i32) (result i32)
+        (call $stat (i32.const 195) (local.get 0)))
+    (func (export "fstat") (param i32) (result i32)
+        (call $fstat (i32.const 197) (local.get 0))))
+"#;
+
+// Where the arguments of the syscalls are written.
+const VARARGS: u32 = 0x10000;
+const PATH: u32 = 0x11000;
+const BUFFER: u32 = 0x12000;
+const STAT: u32 = 0x13000;
+const RESULT: u32 = 0x14000;
+
+// The flags of `open`, as defined by emscripten.
+const O_WRONLY: u32 = 0o1;
+const O_CREAT: u32 = 0o100;
+
+struct Program {
+    instance: Instance,
+    memory: Memory,
+}
+
+impl Program {
+    fn new(fs: mem_fs::FileSystem) -> Self {
+        let store = Store::default();
+        let module = Module::new(&store, PROGRAM).unwrap();
+        let mut globals = EmscriptenGlobals::new(&store, &module).unwrap();
+        let mut env = EmEnv::new(&globals.data, Default::default());
+        env.set_file_system(Box::new(fs));
+        let import_object = generate_emscripten_env(&store, &mut globals, &env);
+        let instance = Instance::new(&module, &import_object).unwrap();
+        env.set_memory(globals.memory.clone());
+
+        Self {
+            instance,
+            memory: globals.memory,
+        }
+    }
+
+    /// Calls the syscall `name` with `args`.
+    fn syscall(&self, name: &str, args: &[u32]) -> i32 {
+        let varargs = args
+            .iter()
+            .flat_map(|arg| arg.to_le_bytes())
+            .collect::<Vec<_>>();
+        self.memory.write(VARARGS as u64, &varargs).unwrap();
+        let function = self.instance.exports.get_function(name).unwrap();
+        match &*function.call(&[Value::I32(VARARGS as i32)]).unwrap() {
+            [Value::I32(result)] => *result,
+            results => panic!("unexpected results {:?}", results),
+        }
+    }
+
+    fn open(&self, path: &str, flags: u32) -> i32 {
+        self.path(path);
+        self.syscall("open", &[PATH, flags, 0o644])
+    }
+
+    fn stat(&self, path: &str) -> i32 {
+        self.path(path);
+        self.syscall("stat", &[PATH, STAT])
+    }
+
+    fn path(&self, path: &str) {
+        let mut bytes = path.as_bytes().to_vec();
+        bytes.push(0);
+        self.memory.write(PATH as u64, &bytes).unwrap();
+    }
+
+    fn read_u32(&self, address: u32) -> u32 {
+        let mut bytes = [0; 4];
+        self.memory.read(address as u64, &mut bytes).unwrap();
+        u32::from_le_bytes(bytes)
+    }
+
+    fn read_bytes(&self, address: u32, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; len];
+        self.memory.read(address as u64, &mut bytes).unwrap();
+        bytes
+    }
+}
+
+/// The `st_mode` and `st_size` of the last `stat`.
+fn stat_mode_and_size(program: &Program) -> (u32, u32) {
+    (program.read_u32(STAT + 12), program.read_u32(STAT + 36))
+}
+
+#[test]
+fn write_creates_files_in_the_file_system() {
+    let fs = mem_fs::FileSystem::default();
+    let program = Program::new(fs.clone());
+
+    let fd = program.open("hello.txt", O_CREAT | O_WRONLY);
+    assert!(fd > 2, "open failed with {}", fd);
+    program.memory.write(BUFFER as u64, b"hello").unwrap();
+    assert_eq!(program.syscall("write", &[fd as u32, BUFFER, 5]), 5);
+    assert_eq!(program.syscall("close", &[fd as u32]), 0);
+    assert_eq!(program.syscall("close", &[fd as u32]), -EBADF);
+
+    let mut file = fs
+        .new_open_options()
+        .read(true)
+        .open(Path::new("/hello.txt"))
+        .unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "hello");
+}
+
+#[test]
+fn read_and_stat_files_of_the_file_system() {
+    let fs = mem_fs::FileSystem::default();
+    fs.create_dir(Path::new("/data")).unwrap();
+    let mut file = fs
+        .new_open_options()
+        .write(true)
+        .create(true)
+        .open(Path::new("/data/hello.txt"))
+        .unwrap();
+    file.write_all(b"hello").unwrap();
+    drop(file);
+    let program = Program::new(fs);
+
+    assert_eq!(program.stat("/data/hello.txt"), 0);
+    assert_eq!(stat_mode_and_size(&program), (0o100644, 5));
+    assert_eq!(program.stat("/data"), 0);
+    assert_eq!(stat_mode_and_size(&program).0, 0o040755);
+
+    let fd = program.open("/data/hello.txt", 0);
+    assert!(fd > 2, "open failed with {}", fd);
+    assert_eq!(program.syscall("read", &[fd as u32, BUFFER, 16]), 5);
+    assert_eq!(program.read_bytes(BUFFER, 5), b"hello");
+    assert_eq!(program.syscall("read", &[fd as u32, BUFFER, 16]), 0);
+
+    assert_eq!(program.syscall("lseek", &[fd as u32, 0, 1, RESULT, 0]), 0);
+    assert_eq!(program.read_u32(RESULT), 1);
+    assert_eq!(program.syscall("read", &[fd as u32, BUFFER, 16]), 4);
+    assert_eq!(program.read_bytes(BUFFER, 4), b"ello");
+
+    assert_eq!(program.syscall("fstat", &[fd as u32, STAT]), 0);
+    assert_eq!(stat_mode_and_size(&program), (0o100644, 5));
+    assert_eq!(program.syscall("close", &[fd as u32]), 0);
+}
+
+#[test]
+fn unlink_removes_files_of_the_file_system() {
+    let fs = mem_fs::FileSystem::default();
+    fs.new_open_options()
+        .write(true)
+        .create(true)
+        .open(Path::new("/hello.txt"))
+        .unwrap();
+    let program = Program::new(fs.clone());
+
+    program.path("/hello.txt");
+    assert_eq!(program.syscall("unlink", &[PATH]), 0);
+    assert_eq!(program.syscall("unlink", &[PATH]), -ENOENT);
+    assert_eq!(program.stat("/hello.txt"), -ENOENT);
+    assert_eq!(program.open("/hello.txt", 0), -ENOENT);
+    assert!(fs.metadata(Path::new("/hello.txt")).is_err());
+}
@@ -0,0 +1,169 @@
+use wasmer::{Instance, Memory, Module, Store, Value};
+use wasmer_emscripten::{generate_emscripten_env, EmEnv, EmscriptenGlobals};
+
+const SIDE_MODULE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/side.wat");
+
+/// A main module exposing the `dl*` functions, and calling or reading
+/// what they return.
+const PROGRAM: &str = r#"
+(module
+    (import "env" "memory" (memory 256))
+    (import "env" "table" (table 1 funcref))
+    (import "env" "_dlopen" (func $dlopen (param i32 i32) (result i32)))
+    (import "env" "_dlsym" (func $dlsym (param i32 i32) (result i32)))
+    (import "env" "_dlclose" (func $dlclose (param i32) (result i32)))
+    (import "env" "_dlerror" (func $dlerror (result i32)))
+    (global (export "main_data") i32 (i32.const 64))
+    (data (i32.const 64) "\07\00\00\00")
+    (func (export "_host_value") (result i32)
+        (i32.const 42))
+    (func (export "_malloc") (param $size i32) (result i32)
+        (local $pointer i32)
+        (local.set $pointer (i32.load (i32.const 16)))
+        (if (i32.eqz (local.get $pointer))
+            (then (local.set $pointer (i32.const 0x100000))))
+        (i32.store (i32.const 16) (i32.add (local.get $pointer) (local.get $size)))
+        (local.get $pointer))
+    (func (export "_memset") (param i32 i32 i32) (result i32)
+        (memory.fill (local.get 0) (local.get 1) (local.get 2))
+        (local.get 0))
+    (func (export "dlopen") (param $filename i32) (result i32)
+        (call $dlopen (local.get $filename) (i32.const 0)))
+    (func (export "dlsym") (param $handle i32) (param $symbol i32) (result i32)
+        (call $dlsym (local.get $handle) (local.get $symbol)))
+    (func (export "dlclose") (param $handle i32) (result i32)
+        (call $dlclose (local.get $handle)))
+    (func (export "dlerror") (result i32)
+        (call $dlerror))
+    (func (export "call") (param $function i32) (result i32)
+        (call_indirect (result i32) (local.get $function)))
+    (func (export "call_with") (param $function i32) (param $arg i32) (result i32)
+        (call_indirect (param i32) (result i32) (local.get $arg) (local.get $function)))
+    (func (export "load") (param $address i32) (result i32)
+        (i32.load (local.get $address))))
+"#;
+
+/// Where the strings given to the `dl*` functions are written.
+const STRINGS: u32 = 0x10000;
+
+struct Program {
+    instance: Instance,
+    memory: Memory,
+}
+
+impl Program {
+    fn new() -> Self {
+        let store = Store::default();
+        let module = Module::new(&store, PROGRAM).unwrap();
+        let mut globals = EmscriptenGlobals::new(&store, &module).unwrap();
+        let mut env = EmEnv::new(&globals.data, Default::default());
+        env.set_table(globals.table.clone());
+        let import_object = generate_emscripten_env(&store, &mut globals, &env);
+        let instance = Instance::new(&module, &import_object).unwrap();
+        env.set_memory(globals.memory.clone());
+
+        Self {
+            instance,
+            memory: globals.memory,
+        }
+    }
+
+    fn call(&self, name: &str, args: &[i32]) -> i32 {
+        let args = args.iter().copied().map(Value::I32).collect::<Vec<_>>();
+        let function = self.instance.exports.get_function(name).unwrap();
+        match &*function.call(&args).unwrap() {
+            [Value::I32(result)] => *result,
+            results => panic!("unexpected results {:?}", results),
+        }
+    }
+
+    /// Writes `string` in the memory, returning its address.
+    fn string(&self, string: &str) -> i32 {
+        let mut bytes = string.as_bytes().to_vec();
+        bytes.push(0);
+        self.memory.write(STRINGS as u64, &bytes).unwrap();
+        STRINGS as i32
+    }
+
+    fn dlopen(&self, path: &str) -> i32 {
+        self.call("dlopen", &[self.string(path)])
+    }
+
+    fn dlsym(&self, handle: i32, symbol: &str) -> i32 {
+        self.call("dlsym", &[handle, self.string(symbol)])
+    }
+}
+
+#[test]
+fn dlopen_resolves_the_symbols() {
+    let program = Program::new();
+    let handle = program.dlopen(SIDE_MODULE);
+    assert_ne!(handle, 0);
+
+    // The functions are given table slots.
+    let get_main_data = program.dlsym(handle, "get_main_data");
+    assert_ne!(get_main_data, 0);
+    assert_eq!(program.call("call", &[get_main_data]), 7);
+    let double = program.dlsym(handle, "double");
+    assert_eq!(program.call("call_with", &[double, 21]), 42);
+    assert_eq!(program.dlsym(handle, "double"), double);
+
+    // The data is relative to the memory of the module.
+    let answer = program.dlsym(handle, "answer");
+    assert_eq!(program.call("load", &[answer]), 3);
+    assert_eq!(program.dlsym(handle, "counter"), answer + 8);
+
+    // The symbols of the main module are found with its handle.
+    let main = program.call("dlopen", &[0]);
+    assert_eq!(program.dlsym(main, "main_data"), 64);
+
+    assert_eq!(program.dlsym(handle, "undefined"), 0);
+    assert_ne!(program.call("dlerror", &[]), 0);
+    assert_eq!(program.call("dlerror", &[]), 0);
+}
+
+#[test]
+fn dlopen_relocates_the_got() {
+    let program = Program::new();
+    let handle = program.dlopen(SIDE_MODULE);
+    assert_ne!(handle, 0);
+
+    // The `GOT.mem` entry of `counter`, defined by the side module, was
+    // filled before its constructors ran, with the value of
+    // `_host_value` of the main module.
+    let get_counter = program.dlsym(handle, "get_counter");
+    assert_eq!(program.call("call", &[get_counter]), 42);
+    let counter = program.dlsym(handle, "counter");
+    assert_eq!(program.call("load", &[counter]), 42);
+
+    // The `GOT.func` entry of `double` points to its table slot.
+    let call_double = program.dlsym(handle, "call_double");
+    assert_eq!(program.call("call_with", &[call_double, 4]), 8);
+}
+
+#[test]
+fn dlclose_releases_the_module() {
+    let program = Program::new();
+    let handle = program.dlopen(SIDE_MODULE);
+    assert_ne!(handle, 0);
+    assert_eq!(program.dlopen(SIDE_MODULE), handle);
+
+    assert_eq!(program.call("dlclose", &[handle]), 0);
+    assert_ne!(program.dlsym(handle, "double"), 0);
+    assert_eq!(program.call("dlclose", &[handle]), 0);
+    assert_eq!(program.dlsym(handle, "double"), 0);
+    assert_eq!(program.call("dlclose", &[handle]), -1);
+    assert_ne!(program.call("dlerror", &[]), 0);
+
+    // The module is loaded again.
+    let reopened = program.dlopen(SIDE_MODULE);
+    assert_ne!(reopened, 0);
+    assert_ne!(reopened, handle);
+}
+
+#[test]
+fn dlopen_reports_missing_modules() {
+    let program = Program::new();
+    assert_eq!(program.dlopen("/missing/side.wasm"), 0);
+    assert_ne!(program.call("dlerror", &[]), 0);
+}
@@ -0,0 +1,96 @@
+use libc::{EAGAIN, ESRCH};
+use wasmer::{Cranelift, Features, Instance, Module, Store, Universal, Value};
+use wasmer_emscripten::{generate_emscripten_env, EmEnv, EmscriptenGlobals};
+
+/// A program running its `run` function in a thread, with the routines
+/// of the table.
+const PROGRAM: &str = r#"
+(module
+    (import "env" "memory" (memory 256 256 shared))
+    (import "env" "table" (table 2 2 funcref))
+    (import "env" "_pthread_create" (func $create (param i32 i32 i32 i32) (result i32)))
+    (import "env" "_pthread_join" (func $join (param i32 i32) (result i32)))
+    (import "env" "_pthread_exit" (func $exit (param i32)))
+    (elem (i32.const 0) $increment $exit_early)
+    (func $increment (param i32) (result i32)
+        (i32.add (local.get 0) (i32.const 1)))
+    (func $exit_early (param i32) (result i32)
+        (call $exit (local.get 0))
+        (unreachable))
+    (func (export "dynCall_ii") (param $routine i32) (param $arg i32) (result i32)
+        (call_indirect (param i32) (result i32) (local.get $arg) (local.get $routine)))
+    ;; Allocates the stacks of the threads after the one of the main
+    ;; thread, and never frees them.
+    (func (export "_malloc") (param $size i32) (result i32)
+        (local $pointer i32)
+        (local.set $pointer (i32.load (i32.const 16)))
+        (if (i32.eqz (local.get $pointer))
+            (then (local.set $pointer (i32.const 0x800000))))
+        (i32.store (i32.const 16) (i32.add (local.get $pointer) (local.get $size)))
+        (local.get $pointer))
+    ;; Runs the routine in a thread and returns what it returned, or the
+    ;; negated error of `pthread_create` or `pthread_join`.
+    (func (export "run") (param $routine i32) (param $arg i32) (result i32)
+        (local $errno i32)
+        (local.set $errno
+            (call $create (i32.const 32) (i32.const 0) (local.get $routine) (local.get $arg)))
+        (if (local.get $errno)
+            (then (return (i32.sub (i32.const 0) (local.get $errno)))))
+        (local.set $errno (call $join (i32.load (i32.const 32)) (i32.const 36)))
+        (if (local.get $errno)
+            (then (return (i32.sub (i32.const 0) (local.get $errno)))))
+        (i32.load (i32.const 36)))
+    (func (export "join") (param $thread i32) (result i32)
+        (call $join (local.get $thread) (i32.const 0))))
+"#;
+
+#[test]
+fn pthread_create_join_exit() {
+    let mut features = Features::default();
+    features.threads(true);
+    let engine = Universal::new(Cranelift::default())
+        .features(features)
+        .engine();
+    let store = Store::new_with_engine(&engine);
+    let module = Module::new(&store, PROGRAM).unwrap();
+
+    let mut globals = EmscriptenGlobals::new(&store, &module).unwrap();
+    let mut env = EmEnv::new(&globals.data, Default::default());
+    env.enable_threads(&store, &module, &globals).unwrap();
+    let import_object = generate_emscripten_env(&store, &mut globals, &env);
+    let instance = Instance::new(&module, &import_object).unwrap();
+    env.set_memory(globals.memory.clone());
+
+    let run = instance.exports.get_function("run").unwrap();
+    let join = instance.exports.get_function("join").unwrap();
+    let call = |routine: i32, arg: i32| run.call(&[Value::I32(routine), Value::I32(arg)]).unwrap();
+
+    // The thread 1 returns from its routine.
+    assert_eq!(&*call(0, 41), &[Value::I32(42)]);
+    // The thread 2 exits in the middle of its routine.
+    assert_eq!(&*call(1, 7), &[Value::I32(7)]);
+    // A thread can only be joined once.
+    assert_eq!(&*join.call(&[Value::I32(1)]).unwrap(), &[Value::I32(ESRCH)]);
+}
+
+#[test]
+fn pthread_create_requires_enabled_threads() {
+    let mut features = Features::default();
+    features.threads(true);
+    let engine = Universal::new(Cranelift::default())
+        .features(features)
+        .engine();
+    let store = Store::new_with_engine(&engine);
+    let module = Module::new(&store, PROGRAM).unwrap();
+
+    let mut globals = EmscriptenGlobals::new(&store, &module).unwrap();
+    let env = EmEnv::new(&globals.data, Default::default());
+    let import_object = generate_emscripten_env(&store, &mut globals, &env);
+    let instance = Instance::new(&module, &import_object).unwrap();
+
+    let run = instance.exports.get_function("run").unwrap();
+    assert_eq!(
+        &*run.call(&[Value::I32(0), Value::I32(41)]).unwrap(),
+        &[Value::I32(-EAGAIN)]
+    );
+}
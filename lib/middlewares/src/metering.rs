@@ -8,9 +8,12 @@
 //! [See the `metering` detailed and complete
 //! example](https://github.com/wasmerio/wasmer/blob/master/examples/metering.rs).
 
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
 use wasmer::{
     ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance, LocalFunctionIndex,
@@ -133,6 +136,17 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> Metering<F> {
     }
 }
 
+impl Metering<Box<dyn Fn(&Operator) -> u64 + Send + Sync>> {
+    /// Creates a `Metering` middleware pricing the operators with
+    /// `costs`.
+    pub fn with_cost_table(initial_limit: u64, costs: Arc<CostTable>) -> Self {
+        Self::new(
+            initial_limit,
+            Box::new(move |operator| costs.cost(operator)),
+        )
+    }
+}
+
 impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Metering")
@@ -258,6 +272,117 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
     }
 }
 
+/// The costs of the operators, in "points", e.g. loaded from the
+/// configuration of the host.
+///
+/// The operators are named after their [`Operator`] variant, e.g.
+/// `I32Add`, `LocalGet` or `Call`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::metering::{CostTable, Metering};
+///
+/// fn create_metering_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     let mut costs = CostTable::default();
+///     costs.default_cost = 1;
+///     costs.costs.insert("Call".to_string(), 10);
+///
+///     let metering = Arc::new(Metering::with_cost_table(10_000, Arc::new(costs)));
+///     compiler_config.push_middleware(metering);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "enable-serde", serde(default))]
+pub struct CostTable {
+    /// The cost of the operators missing from `costs`.
+    pub default_cost: u64,
+
+    /// The costs of the operators, by name.
+    pub costs: HashMap<String, u64>,
+}
+
+impl CostTable {
+    /// Returns the cost of `operator`.
+    pub fn cost(&self, operator: &Operator) -> u64 {
+        // The name of the variant is what its `Debug` implementation
+        // writes first.
+        struct VariantName(String);
+
+        impl fmt::Write for VariantName {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                match s.find(|c: char| !c.is_ascii_alphanumeric()) {
+                    Some(end) => {
+                        self.0.push_str(&s[..end]);
+                        // Stop the formatting at the end of the name.
+                        Err(fmt::Error)
+                    }
+                    None => {
+                        self.0.push_str(s);
+                        Ok(())
+                    }
+                }
+            }
+        }
+
+        let mut name = VariantName(String::new());
+        let _ = fmt::write(&mut name, format_args!("{:?}", operator));
+        self.costs
+            .get(&name.0)
+            .copied()
+            .unwrap_or(self.default_cost)
+    }
+}
+
+/// A [`CostTable`] which can be replaced while the host runs, e.g.
+/// when its configuration is reloaded.
+///
+/// The costs are compiled in the modules: the modules compiled with a
+/// [`Metering`] middleware created after a replacement use the new
+/// costs, and the others keep the previous ones.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::{CompilerConfig, Cranelift, Module, Store, Universal};
+/// use wasmer_middlewares::metering::{CostTable, Metering, SharedCostTable};
+///
+/// fn compile(costs: &SharedCostTable, wasm: &[u8]) -> Module {
+///     // A `Metering` middleware can only be used by one module.
+///     let mut compiler_config = Cranelift::default();
+///     compiler_config.push_middleware(Arc::new(Metering::with_cost_table(10_000, costs.get())));
+///     let store = Store::new_with_engine(&Universal::new(compiler_config).engine());
+///     Module::new(&store, wasm).unwrap()
+/// }
+///
+/// fn reload(costs: &SharedCostTable, table: CostTable) {
+///     costs.replace(table);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SharedCostTable(Arc<RwLock<Arc<CostTable>>>);
+
+impl SharedCostTable {
+    /// Creates a `SharedCostTable` with `costs`.
+    pub fn new(costs: CostTable) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(costs))))
+    }
+
+    /// Returns the current costs.
+    pub fn get(&self) -> Arc<CostTable> {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Replaces the costs, for the modules compiled afterwards.
+    pub fn replace(&self, costs: CostTable) {
+        *self.0.write().unwrap() = Arc::new(costs);
+    }
+}
+
 /// Get the remaining points in an [`Instance`][wasmer::Instance].
 ///
 /// Note: This can be used in a headless engine after an ahead-of-time
@@ -476,4 +601,70 @@ mod tests {
             MeteringPoints::Remaining(4)
         );
     }
+
+    fn cost_table() -> CostTable {
+        CostTable {
+            default_cost: 0,
+            costs: vec![
+                ("LocalGet".to_string(), 1),
+                ("I32Const".to_string(), 1),
+                ("I32Add".to_string(), 2),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    /// Instantiates the module compiled with `costs`, and calls
+    /// `add_one` once.
+    fn remaining_points_after_a_call(costs: &SharedCostTable) -> MeteringPoints {
+        let metering = Arc::new(Metering::with_cost_table(10, costs.get()));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let store = Store::new_with_engine(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let add_one = instance
+            .exports
+            .get_function("add_one")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+        add_one.call(1).unwrap();
+        get_remaining_points(&instance)
+    }
+
+    #[test]
+    fn cost_tables_price_the_operators_by_name() {
+        let costs = cost_table();
+        assert_eq!(costs.cost(&Operator::I32Add), 2);
+        assert_eq!(costs.cost(&Operator::LocalGet { local_index: 3 }), 1);
+        assert_eq!(costs.cost(&Operator::I32Sub), 0);
+    }
+
+    #[test]
+    fn cost_tables_can_be_replaced() {
+        let costs = SharedCostTable::new(cost_table());
+        assert_eq!(
+            remaining_points_after_a_call(&costs),
+            MeteringPoints::Remaining(6)
+        );
+
+        let mut table = cost_table();
+        table.costs.insert("I32Add".to_string(), 5);
+        costs.replace(table);
+        assert_eq!(
+            remaining_points_after_a_call(&costs),
+            MeteringPoints::Remaining(3)
+        );
+    }
+
+    #[cfg(feature = "enable-serde")]
+    #[test]
+    fn cost_tables_are_deserializable() {
+        let costs: CostTable =
+            serde_json::from_str(r#"{"costs": {"LocalGet": 1, "I32Const": 1, "I32Add": 2}}"#)
+                .unwrap();
+        assert_eq!(costs, cost_table());
+    }
 }
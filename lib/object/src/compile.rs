@@ -0,0 +1,84 @@
+use crate::error::ObjectError;
+use crate::module::{emit_header, emit_serialized, get_object_for_target};
+use wasmer_compiler::{
+    ArtifactCreate, CompilerConfig, ModuleEnvironment, Target, Tunables, UniversalArtifactBuild,
+    UniversalEngineBuilder,
+};
+use wasmer_types::CompileError;
+
+/// A module compiled to a native object file, with the C header to use
+/// it.
+#[derive(Debug, Clone)]
+pub struct ObjectFile {
+    /// The contents of the object file.
+    pub object: Vec<u8>,
+    /// The C header declaring the symbols of the object.
+    pub header: String,
+}
+
+/// Compile the Wasm module `wasm` for `target`, which can differ from
+/// the host, and write it in a native object file.
+///
+/// The object holds the module serialized for the Universal engine, as
+/// written by [`emit_serialized`]: a program linking it deserializes it
+/// with the function of the header, rather than compiling the module.
+/// The memories and the tables are compiled with the styles `tunables`
+/// choose for `target`. A static library is made from the object with
+/// `ar`.
+///
+/// # Usage
+///
+/// ```rust
+/// # use wasmer_compiler::{CompilerConfig, Target, Tunables};
+/// # use wasmer_object::ObjectError;
+/// use wasmer_object::compile_object;
+///
+/// # fn compile(
+/// #     compiler_config: Box<dyn CompilerConfig>,
+/// #     target: &Target,
+/// #     tunables: &dyn Tunables,
+/// #     wasm: &[u8],
+/// # ) -> Result<(), ObjectError> {
+/// let object_file = compile_object(compiler_config, target, tunables, wasm, "module")?;
+/// std::fs::write("module.o", &object_file.object).unwrap();
+/// std::fs::write("module.h", &object_file.header).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+pub fn compile_object(
+    compiler_config: Box<dyn CompilerConfig>,
+    target: &Target,
+    tunables: &dyn Tunables,
+    wasm: &[u8],
+    object_name: &str,
+) -> Result<ObjectFile, ObjectError> {
+    let header = emit_header(object_name)?;
+
+    let translation = ModuleEnvironment::new()
+        .translate(wasm)
+        .map_err(CompileError::Wasm)?;
+    let memory_styles = translation
+        .module
+        .memories
+        .values()
+        .map(|memory_type| tunables.memory_style(memory_type))
+        .collect();
+    let table_styles = translation
+        .module
+        .tables
+        .values()
+        .map(|table_type| tunables.table_style(table_type))
+        .collect();
+
+    let features = compiler_config.default_features_for_target(target);
+    let mut builder = UniversalEngineBuilder::new(Some(compiler_config.compiler()), features);
+    let artifact =
+        UniversalArtifactBuild::new(&mut builder, wasm, target, memory_styles, table_styles)?;
+
+    let triple = target.triple();
+    let mut object = get_object_for_target(triple)?;
+    emit_serialized(&mut object, &artifact.serialize()?, triple, object_name)?;
+    let object = object.write()?;
+
+    Ok(ObjectFile { object, header })
+}
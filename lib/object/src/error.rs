@@ -1,5 +1,6 @@
 use object::write::Error as ObjectWriteError;
 use thiserror::Error;
+use wasmer_types::{CompileError, SerializeError};
 
 /// The Object error can occur when creating an object file
 /// from a `Compilation`.
@@ -17,4 +18,13 @@ pub enum ObjectError {
     /// The object was provided a not-supported architecture
     #[error("Error when writing the object: {0}")]
     Write(#[from] ObjectWriteError),
+    /// The object was given a name that can't be part of a C identifier
+    #[error("Invalid object name {0:?}: only ASCII letters, digits and underscores are allowed")]
+    InvalidObjectName(String),
+    /// The module couldn't be compiled
+    #[error("Error when compiling the module: {0}")]
+    Compile(#[from] CompileError),
+    /// The compiled module couldn't be serialized
+    #[error("Error when serializing the module: {0}")]
+    Serialize(#[from] SerializeError),
 }
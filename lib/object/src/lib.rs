@@ -2,6 +2,9 @@
 //!
 //! Given a compilation result (this is, the result when calling `Compiler::compile_module`)
 //! this exposes functions to create an Object file for a given target.
+//!
+//! [`compile_object`] compiles a module to an object file for a given
+//! target, with the C header to link it in a program.
 
 #![deny(missing_docs, trivial_numeric_casts, unused_extern_crates)]
 #![warn(unused_import_braces)]
@@ -19,8 +22,12 @@
     )
 )]
 
+mod compile;
 mod error;
 mod module;
 
+pub use crate::compile::{compile_object, ObjectFile};
 pub use crate::error::ObjectError;
-pub use crate::module::{emit_compilation, emit_data, get_object_for_target};
+pub use crate::module::{
+    emit_compilation, emit_data, emit_header, emit_serialized, get_object_for_target,
+};
@@ -6,7 +6,9 @@ use object::{
     elf, macho, RelocationEncoding, RelocationKind, SectionKind, SymbolFlags, SymbolKind,
     SymbolScope,
 };
-use wasmer_compiler::{Architecture, BinaryFormat, Endianness, Symbol, SymbolRegistry, Triple};
+use wasmer_compiler::{
+    Architecture, BinaryFormat, Endianness, MetadataHeader, Symbol, SymbolRegistry, Triple,
+};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::LocalFunctionIndex;
 use wasmer_types::{
@@ -105,6 +107,120 @@ pub fn emit_data(
     Ok(())
 }
 
+/// Write a serialized module into an existing object, as the
+/// `WASMER_MODULE_<object_name>` symbol, with its length as the
+/// `WASMER_MODULE_<object_name>_LENGTH` symbol.
+///
+/// The module can then be deserialized from the program the object is
+/// linked in, with the functions of the header made by [`emit_header`].
+///
+/// # Usage
+///
+/// ```rust
+/// # use wasmer_compiler::Triple;
+/// # use wasmer_object::ObjectError;
+/// use wasmer_object::{get_object_for_target, emit_serialized};
+///
+/// # fn emit_serialized_into_object(triple: &Triple, serialized: &[u8]) -> Result<(), ObjectError> {
+/// let mut object = get_object_for_target(&triple)?;
+/// emit_serialized(&mut object, serialized, &triple, "module")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn emit_serialized(
+    obj: &mut Object,
+    serialized: &[u8],
+    triple: &Triple,
+    object_name: &str,
+) -> Result<(), ObjectError> {
+    check_object_name(object_name)?;
+    let length = serialized.len() as u64;
+    let length = match triple
+        .endianness()
+        .map_err(|_| ObjectError::UnknownEndianness)?
+    {
+        Endianness::Little => length.to_le_bytes(),
+        Endianness::Big => length.to_be_bytes(),
+    };
+
+    let name = format!("WASMER_MODULE_{}", object_name);
+    // The deserialization copies the misaligned modules.
+    emit_data(
+        obj,
+        name.as_bytes(),
+        serialized,
+        MetadataHeader::ALIGN as u64,
+    )?;
+    emit_data(obj, format!("{}_LENGTH", name).as_bytes(), &length, 8)?;
+
+    Ok(())
+}
+
+/// Returns the C header declaring the symbols written by
+/// [`emit_serialized`], and a function deserializing the module with the
+/// Wasmer C API.
+///
+/// # Usage
+///
+/// ```rust
+/// # use wasmer_object::ObjectError;
+/// use wasmer_object::emit_header;
+///
+/// # fn write_header() -> Result<(), ObjectError> {
+/// let header = emit_header("module")?;
+/// assert!(header.contains("wasm_module_t* wasmer_module_module_new(wasm_store_t* store)"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn emit_header(object_name: &str) -> Result<String, ObjectError> {
+    check_object_name(object_name)?;
+    Ok(format!(
+        r#"#ifndef WASMER_MODULE_{name}_H
+#define WASMER_MODULE_{name}_H
+
+#include <stddef.h>
+#include <stdint.h>
+#include "wasmer.h"
+
+#ifdef __cplusplus
+extern "C" {{
+#endif
+
+/* The serialized module. */
+extern const unsigned char WASMER_MODULE_{name}[];
+extern const uint64_t WASMER_MODULE_{name}_LENGTH;
+
+/* Deserializes the module in `store`, or returns NULL. */
+static inline wasm_module_t* wasmer_module_{name}_new(wasm_store_t* store) {{
+    wasm_byte_vec_t bytes = {{
+        (size_t) WASMER_MODULE_{name}_LENGTH,
+        (wasm_byte_t*) WASMER_MODULE_{name},
+    }};
+    return wasm_module_deserialize(store, &bytes);
+}}
+
+#ifdef __cplusplus
+}}
+#endif
+
+#endif /* WASMER_MODULE_{name}_H */
+"#,
+        name = object_name
+    ))
+}
+
+/// Checks that `object_name` can be part of a C identifier.
+fn check_object_name(object_name: &str) -> Result<(), ObjectError> {
+    if object_name.is_empty()
+        || !object_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(ObjectError::InvalidObjectName(object_name.to_string()));
+    }
+    Ok(())
+}
+
 /// Emit the compilation result into an existing object.
 ///
 /// # Usage
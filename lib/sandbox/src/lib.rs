@@ -0,0 +1,345 @@
+//! Sandbox profiles, bundling the configuration of a WASI program, the
+//! limit of its memories, the metering of its instructions and the
+//! network policy of its requests, so that the operators can define
+//! presets declaratively and apply them with a single call.
+//!
+//! A [`SandboxProfile`] is serializable, e.g. to keep the presets in
+//! configuration files:
+//!
+//! ```
+//! # use wasmer_sandbox::SandboxProfile;
+//! let profile: SandboxProfile = serde_json::from_str(
+//!     r#"{
+//!         "args": ["--verbose"],
+//!         "envs": { "LOG": "debug" },
+//!         "max_memory_pages": 256,
+//!         "metering": { "limit": 1000000, "costs": { "default_cost": 1 } },
+//!         "net": { "allowed_hosts": ["api.example.com"], "allowed_schemes": ["https"] }
+//!     }"#,
+//! )
+//! .unwrap();
+//! ```
+//!
+//! or is built with its setters, and then instantiates the programs
+//! with [`SandboxProfile::instantiate`].
+
+#![deny(missing_docs, unused_extern_crates)]
+#![warn(unused_import_braces)]
+
+mod tunables;
+
+use crate::tunables::MemoryLimitTunables;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use thiserror::Error;
+use wasmer::{
+    BaseTunables, CompileError, CompilerConfig, Engine, Instance, InstantiationError, Module,
+    Pages, RuntimeError, Store, Universal,
+};
+use wasmer_middlewares::metering::{get_remaining_points, CostTable, MeteringPoints};
+use wasmer_middlewares::Metering;
+use wasmer_wasi::{
+    NetPolicy, WasiEnv, WasiError, WasiExitStatus, WasiState, WasiStateCreationError,
+};
+
+/// An error while applying a [`SandboxProfile`].
+#[derive(Error, Debug)]
+pub enum SandboxError {
+    /// The module couldn't be compiled.
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+    /// The WASI environment couldn't be created, e.g. because a mapped
+    /// directory doesn't exist.
+    #[error(transparent)]
+    Wasi(#[from] WasiStateCreationError),
+    /// The imports of the WASI environment couldn't be generated.
+    #[error(transparent)]
+    Imports(#[from] WasiError),
+    /// The module couldn't be instantiated, e.g. because its memory
+    /// exceeds the limit.
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+}
+
+/// The metering of the instructions of a program, see
+/// [`wasmer_middlewares::metering`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeteringProfile {
+    /// The points the program may spend.
+    pub limit: u64,
+    /// The points each operator costs.
+    pub costs: CostTable,
+}
+
+/// The remote resources a program may reach, see [`NetPolicy`].
+///
+/// Each restriction is opt-in: the default profile allows everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetProfile {
+    /// The hosts the program may request, if restricted.
+    pub allowed_hosts: Option<Vec<String>>,
+    /// The URL schemes the program may use, if restricted.
+    pub allowed_schemes: Option<Vec<String>>,
+    /// The maximum size of the bodies of the requests.
+    pub max_request_body_size: Option<usize>,
+    /// The maximum size of the bodies of the responses.
+    pub max_response_body_size: Option<usize>,
+}
+
+impl NetProfile {
+    fn policy(&self) -> NetPolicy {
+        let mut policy = NetPolicy::new();
+        for host in self.allowed_hosts.iter().flatten() {
+            policy.allow_host(host);
+        }
+        for scheme in self.allowed_schemes.iter().flatten() {
+            policy.allow_scheme(scheme);
+        }
+        if let Some(size) = self.max_request_body_size {
+            policy.max_request_body_size(size);
+        }
+        if let Some(size) = self.max_response_body_size {
+            policy.max_response_body_size(size);
+        }
+
+        policy
+    }
+}
+
+/// A preset of the environment of a WASI program.
+///
+/// The default profile runs the program without arguments, environment
+/// variables or directories, without limiting its memories or metering
+/// its instructions, and without restricting its network requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxProfile {
+    args: Vec<String>,
+    envs: BTreeMap<String, String>,
+    /// The host directories, by their path for the program.
+    mapped_dirs: BTreeMap<String, PathBuf>,
+    max_memory_pages: Option<u32>,
+    metering: Option<MeteringProfile>,
+    net: NetProfile,
+}
+
+impl SandboxProfile {
+    /// Creates the default profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an argument to the program.
+    pub fn arg(&mut self, arg: &str) -> &mut Self {
+        self.args.push(arg.to_string());
+
+        self
+    }
+
+    /// Sets an environment variable of the program.
+    pub fn env(&mut self, key: &str, value: &str) -> &mut Self {
+        self.envs.insert(key.to_string(), value.to_string());
+
+        self
+    }
+
+    /// Maps the host directory `host_path` to `alias` for the program.
+    pub fn map_dir(&mut self, alias: &str, host_path: impl Into<PathBuf>) -> &mut Self {
+        self.mapped_dirs.insert(alias.to_string(), host_path.into());
+
+        self
+    }
+
+    /// Limits each memory of the program to `pages`.
+    ///
+    /// A module declaring a larger minimum fails to instantiate, and the
+    /// growths beyond the limit fail, as if the memory declared it as
+    /// its maximum.
+    pub fn max_memory(&mut self, pages: Pages) -> &mut Self {
+        self.max_memory_pages = Some(pages.0);
+
+        self
+    }
+
+    /// Meters the instructions of the program, which traps once it
+    /// spent `limit` points.
+    pub fn metering(&mut self, limit: u64, costs: CostTable) -> &mut Self {
+        self.metering = Some(MeteringProfile { limit, costs });
+
+        self
+    }
+
+    /// Restricts the network requests of the program.
+    pub fn net(&mut self, net: NetProfile) -> &mut Self {
+        self.net = net;
+
+        self
+    }
+
+    /// Compiles `wasm` with `compiler_config`, and instantiates it in a
+    /// WASI environment for `program_name`, with all the settings of
+    /// the profile applied.
+    ///
+    /// The store, and its engine, are created for the program: the
+    /// metering middleware can only be used by one module.
+    pub fn instantiate(
+        &self,
+        mut compiler_config: impl CompilerConfig + 'static,
+        program_name: &str,
+        wasm: &[u8],
+    ) -> Result<Sandbox, SandboxError> {
+        if let Some(metering) = &self.metering {
+            compiler_config.push_middleware(Arc::new(Metering::with_cost_table(
+                metering.limit,
+                Arc::new(metering.costs.clone()),
+            )));
+        }
+        let engine = Universal::new(compiler_config).engine();
+        let store = match self.max_memory_pages {
+            Some(pages) => Store::new_with_tunables(
+                &engine,
+                MemoryLimitTunables::new(BaseTunables::for_target(engine.target()), Pages(pages)),
+            ),
+            None => Store::new_with_engine(&engine),
+        };
+
+        let mut builder = WasiState::new(program_name);
+        builder
+            .args(&self.args)
+            .envs(&self.envs)
+            .net_policy(self.net.policy());
+        for (alias, host_path) in &self.mapped_dirs {
+            builder.map_dir(alias, host_path)?;
+        }
+        let mut wasi_env = builder.finalize()?;
+
+        let module = Module::new(&store, wasm)?;
+        let imports = wasi_env.import_object(&module)?;
+        let instance = Instance::new(&module, &imports)?;
+
+        Ok(Sandbox {
+            instance,
+            wasi_env,
+            metered: self.metering.is_some(),
+        })
+    }
+}
+
+/// A program instantiated with a [`SandboxProfile`].
+pub struct Sandbox {
+    /// The instance of the program.
+    pub instance: Instance,
+    /// The WASI environment of the program.
+    pub wasi_env: WasiEnv,
+    metered: bool,
+}
+
+impl Sandbox {
+    /// Runs the `_start` function of the program, and returns how it
+    /// terminated, see [`WasiEnv::wait`].
+    pub fn run(&self) -> Result<WasiExitStatus, RuntimeError> {
+        let start = self
+            .instance
+            .exports
+            .get_function("_start")
+            .map_err(|error| RuntimeError::new(error.to_string()))?;
+        self.wasi_env.wait(start.call(&[]))
+    }
+
+    /// The points the program has left, if the profile meters it.
+    pub fn remaining_points(&self) -> Option<MeteringPoints> {
+        if self.metered {
+            Some(get_remaining_points(&self.instance))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasmer::{wat2wasm, Cranelift};
+
+    fn program(memory: &str) -> Vec<u8> {
+        wat2wasm(
+            format!(
+                r#"
+                (module
+                  (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+                  (memory (export "memory") {})
+                  (func (export "grow") (param i32) (result i32)
+                    local.get 0
+                    memory.grow)
+                  (func (export "_start")
+                    i32.const 3
+                    call $proc_exit))
+                "#,
+                memory
+            )
+            .as_bytes(),
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn profile_serialization() {
+        let mut profile = SandboxProfile::new();
+        profile
+            .arg("--verbose")
+            .env("LOG", "debug")
+            .max_memory(Pages(2))
+            .metering(100, CostTable::default())
+            .net(NetProfile {
+                allowed_hosts: Some(vec!["api.example.com".to_string()]),
+                ..NetProfile::default()
+            });
+
+        let json = serde_json::to_string(&profile).unwrap();
+        let deserialized: SandboxProfile = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, profile);
+
+        let empty: SandboxProfile = serde_json::from_str("{}").unwrap();
+        assert_eq!(empty, SandboxProfile::default());
+    }
+
+    #[test]
+    fn run_in_sandbox() {
+        let mut profile = SandboxProfile::new();
+        profile.metering(1000, CostTable::default());
+        let sandbox = profile
+            .instantiate(Cranelift::default(), "program", &program("1"))
+            .unwrap();
+
+        assert_eq!(sandbox.run().unwrap(), WasiExitStatus::Exited(3));
+        assert_eq!(
+            sandbox.remaining_points(),
+            Some(MeteringPoints::Remaining(1000))
+        );
+    }
+
+    #[test]
+    fn memory_limit() {
+        let mut profile = SandboxProfile::new();
+        profile.max_memory(Pages(2));
+
+        let sandbox = profile
+            .instantiate(Cranelift::default(), "program", &program("1"))
+            .unwrap();
+        let grow = sandbox.instance.exports.get_function("grow").unwrap();
+        assert_eq!(grow.call(&[1.into()]).unwrap()[0].unwrap_i32(), 1);
+        assert_eq!(grow.call(&[1.into()]).unwrap()[0].unwrap_i32(), -1);
+        assert_eq!(sandbox.remaining_points(), None);
+
+        assert!(matches!(
+            profile.instantiate(Cranelift::default(), "program", &program("3")),
+            Err(SandboxError::Instantiation(_))
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+use std::ptr::NonNull;
+use std::sync::Arc;
+use wasmer::vm::{
+    self, MemoryError, MemoryStyle, TableStyle, VMMemoryDefinition, VMTableDefinition,
+};
+use wasmer::{MemoryType, Pages, TableType, Tunables};
+
+/// Tunables limiting the size of each memory to `limit` pages.
+///
+/// The maximum of the memories is lowered to the limit, so that they
+/// refuse to grow beyond it, and the memories whose minimum exceeds the
+/// limit aren't created.
+pub(crate) struct MemoryLimitTunables<T: Tunables> {
+    base: T,
+    limit: Pages,
+}
+
+impl<T: Tunables> MemoryLimitTunables<T> {
+    pub(crate) fn new(base: T, limit: Pages) -> Self {
+        Self { base, limit }
+    }
+
+    fn adjust_memory(&self, requested: &MemoryType) -> MemoryType {
+        let mut adjusted = *requested;
+        adjusted.maximum = Some(match requested.maximum {
+            Some(maximum) => maximum.min(self.limit),
+            None => self.limit,
+        });
+        adjusted
+    }
+
+    fn validate_memory(&self, ty: &MemoryType) -> Result<(), MemoryError> {
+        if ty.minimum > self.limit {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: ty.minimum,
+                max_allowed: self.limit,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Tunables> Tunables for MemoryLimitTunables<T> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(&self.adjust_memory(memory))
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        self.validate_memory(ty)?;
+        self.base.create_host_memory(&self.adjust_memory(ty), style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        self.validate_memory(ty)?;
+        self.base
+            .create_vm_memory(&self.adjust_memory(ty), style, vm_definition_location)
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base.create_vm_table(ty, style, vm_definition_location)
+    }
+}
@@ -0,0 +1,84 @@
+//! Versioning of the serialized artifact format.
+use crate::lib::std::fmt;
+
+/// The version of the format used to serialize a compiled module.
+///
+/// The version follows semantic versioning: artifacts can be read by any
+/// Wasmer supporting the same `major` version and an equal or greater `minor`
+/// version. The `patch` version is only informative.
+///
+/// The version is stored in the artifact header packed in a `u32`, with the
+/// `major` version in the upper 16 bits, followed by the `minor` and `patch`
+/// versions in 8 bits each. Artifacts serialized before the format was
+/// versioned store a bare ABI number in that field, which decodes as
+/// `0.0.<abi>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ArtifactVersion {
+    /// Incremented for changes breaking the reading of older artifacts.
+    pub major: u16,
+    /// Incremented for changes older readers can safely ignore.
+    pub minor: u8,
+    /// Incremented for changes not affecting the format itself.
+    pub patch: u8,
+}
+
+impl ArtifactVersion {
+    /// Creates a new artifact version.
+    pub const fn new(major: u16, minor: u8, patch: u8) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Decodes a version from its packed representation in the artifact header.
+    pub const fn from_u32(packed: u32) -> Self {
+        Self {
+            major: (packed >> 16) as u16,
+            minor: (packed >> 8) as u8,
+            patch: packed as u8,
+        }
+    }
+
+    /// Encodes the version to its packed representation in the artifact header.
+    pub const fn to_u32(self) -> u32 {
+        (self.major as u32) << 16 | (self.minor as u32) << 8 | self.patch as u32
+    }
+
+    /// Returns whether an artifact of this version can be read by a reader
+    /// supporting the `reader` version.
+    pub fn is_readable_by(&self, reader: &Self) -> bool {
+        self.major == reader.major && self.minor <= reader.minor
+    }
+}
+
+impl fmt::Display for ArtifactVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_roundtrip() {
+        let version = ArtifactVersion::new(3, 1, 7);
+        assert_eq!(version.to_u32(), 0x0003_0107);
+        assert_eq!(ArtifactVersion::from_u32(version.to_u32()), version);
+        // Unversioned artifacts only stored the ABI number.
+        assert_eq!(ArtifactVersion::from_u32(2), ArtifactVersion::new(0, 0, 2));
+    }
+
+    #[test]
+    fn readable_by_same_major_and_newer_minor() {
+        let reader = ArtifactVersion::new(1, 2, 0);
+        assert!(ArtifactVersion::new(1, 0, 5).is_readable_by(&reader));
+        assert!(ArtifactVersion::new(1, 2, 9).is_readable_by(&reader));
+        assert!(!ArtifactVersion::new(1, 3, 0).is_readable_by(&reader));
+        assert!(!ArtifactVersion::new(0, 0, 2).is_readable_by(&reader));
+        assert!(!ArtifactVersion::new(2, 0, 0).is_readable_by(&reader));
+    }
+}
@@ -1,5 +1,6 @@
 //! The WebAssembly possible errors
-use crate::ExternType;
+use crate::{ArtifactVersion, ExternType, TrapCode};
+use std::fmt;
 use std::io;
 use thiserror::Error;
 
@@ -28,6 +29,18 @@ pub enum DeserializeError {
     /// Incompatible serialized binary
     #[error("incompatible binary: {0}")]
     Incompatible(String),
+    /// The binary was serialized with a format version this Wasmer cannot
+    /// read. The module should be recompiled from its Wasm source.
+    #[error("incompatible artifact format version {found} (supported: {supported})")]
+    IncompatibleVersion {
+        /// The format version found in the binary.
+        found: ArtifactVersion,
+        /// The format version written by this Wasmer.
+        supported: ArtifactVersion,
+    },
+    /// The signature of a signed binary is missing, untrusted or invalid
+    #[error("invalid signature: {0}")]
+    InvalidSignature(String),
     /// The provided binary is corrupted
     #[error("corrupted binary: {0}")]
     CorruptedBinary(String),
@@ -54,6 +67,106 @@ pub enum ImportError {
     UnknownImport(ExternType),
 }
 
+/// All the imports of a module which are missing or whose types are
+/// incompatible, in the order the module declares them, so that they
+/// can be fixed at once rather than one instantiation at a time.
+#[derive(Debug, Default)]
+pub struct ImportsReport {
+    /// The module and the name of each import, with its error.
+    pub errors: Vec<(String, String, ImportError)>,
+}
+
+impl ImportsReport {
+    /// Whether all the imports are satisfied.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for ImportsReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} import(s) missing or with incompatible types:",
+            self.errors.len()
+        )?;
+        for (module, name, error) in &self.errors {
+            write!(f, "\n  - ")?;
+            fmt_import_error(f, module, name, error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ImportsReport> for Vec<LinkIssue> {
+    fn from(report: ImportsReport) -> Self {
+        report
+            .errors
+            .into_iter()
+            .map(|(module, name, error)| LinkIssue {
+                module,
+                name,
+                error,
+            })
+            .collect()
+    }
+}
+
+/// An import of a module which is missing or whose type is
+/// incompatible, found without instantiating the module.
+#[derive(Debug)]
+pub struct LinkIssue {
+    /// The module of the import.
+    pub module: String,
+    /// The name of the import.
+    pub name: String,
+    /// Whether the import is missing or has an incompatible type.
+    pub error: ImportError,
+}
+
+impl fmt::Display for LinkIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_import_error(f, &self.module, &self.name, &self.error)
+    }
+}
+
+impl std::error::Error for LinkIssue {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+fn fmt_import_error(
+    f: &mut fmt::Formatter,
+    module: &str,
+    name: &str,
+    error: &ImportError,
+) -> fmt::Result {
+    write!(f, "{:?}.{:?}: ", module, name)?;
+    match error {
+        ImportError::UnknownImport(expected) => {
+            write!(f, "missing, expected {}", describe(expected))
+        }
+        ImportError::IncompatibleType(expected, provided) => write!(
+            f,
+            "expected {}, but {} was provided",
+            describe(expected),
+            describe(provided)
+        ),
+    }
+}
+
+fn describe(ty: &ExternType) -> String {
+    match ty {
+        ExternType::Function(ty) => format!("a function of type `{}`", ty),
+        ExternType::Global(ty) => format!("a global of type `{}`", ty),
+        ExternType::Table(ty) => format!("a table of type `{}`", ty),
+        ExternType::Memory(ty) => format!("a memory of type `{}`", ty),
+        ExternType::Tag(ty) => format!("a tag of type `{}`", ty),
+    }
+}
+
 /// An error while preinstantiating a module.
 ///
 #[derive(Error, Debug)]
@@ -193,6 +306,30 @@ pub enum ParseCpuFeatureError {
 /// A convenient alias for a `Result` that uses `WasmError` as the error type.
 pub type WasmResult<T> = Result<T, WasmError>;
 
+/// What a `RuntimeError` comes from, reported the same way by every
+/// backend, so that the errors can be handled without any
+/// backend-specific code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    /// A generic error, with a message.
+    Generic,
+    /// The Wasm code trapped.
+    ///
+    /// The `js` backend maps the exceptions of the JS engine to the trap
+    /// codes, which are guessed from their messages.
+    Trap(TrapCode),
+    /// The host ran out of memory.
+    OutOfMemory,
+    /// An error raised by the host, which can be downcast to its type.
+    User,
+    /// An exception thrown by the Wasm code and not caught, which can be
+    /// downcast to an `Exception` to inspect its tag and values.
+    Exception,
+    /// An exception of the JS engine which isn't a trap, only reported
+    /// by the `js` backend.
+    Js,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::fmt;
 use std::ptr;
 use std::sync::atomic;
 
@@ -47,6 +48,22 @@ impl VMExternRef {
         Self(Box::into_raw(Box::new(VMExternRefInner::new::<T>(value))))
     }
 
+    /// Make a new extern reference, calling `on_drop` with the value when
+    /// the last reference to it is dropped.
+    pub fn new_with_drop_callback<T, F>(value: T, on_drop: F) -> Self
+    where
+        T: Any + Send + Sync + 'static + Sized,
+        F: FnOnce(T) + Send + 'static,
+    {
+        let mut inner = VMExternRefInner::new::<T>(value);
+        inner.on_drop = Some(Box::new(move |data: Box<dyn Any + Send + Sync>| {
+            if let Ok(value) = data.downcast::<T>() {
+                on_drop(*value)
+            }
+        }));
+        Self(Box::into_raw(Box::new(inner)))
+    }
+
     /// Try to downcast to the given value
     pub fn downcast<T>(&self) -> Option<&T>
     where
@@ -146,13 +163,35 @@ impl VMExternRef {
     }
 }
 
-#[derive(Debug)]
+/// A callback called with the data of a reference when it is dropped.
+type DropCallback = Box<dyn FnOnce(Box<dyn Any + Send + Sync + 'static>) + Send>;
+
 #[repr(C)]
 pub(crate) struct VMExternRefInner {
     strong: atomic::AtomicUsize,
     /// Do something obviously correct to get started. This can "easily" be improved
     /// to be an inline allocation later as the logic is fully encapsulated.
     data: Box<dyn Any + Send + Sync + 'static>,
+    on_drop: Option<DropCallback>,
+}
+
+impl fmt::Debug for VMExternRefInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMExternRefInner")
+            .field("strong", &self.strong)
+            .field("data", &self.data)
+            .field("on_drop", &self.on_drop.is_some())
+            .finish()
+    }
+}
+
+impl Drop for VMExternRefInner {
+    fn drop(&mut self) {
+        if let Some(on_drop) = self.on_drop.take() {
+            let data = std::mem::replace(&mut self.data, Box::new(()));
+            on_drop(data);
+        }
+    }
 }
 
 impl VMExternRefInner {
@@ -163,6 +202,7 @@ impl VMExternRefInner {
         Self {
             strong: atomic::AtomicUsize::new(1),
             data: Box::new(value),
+            on_drop: None,
         }
     }
 
@@ -262,6 +302,20 @@ impl ExternRef {
         }
     }
 
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    /// Make a new extern reference, calling `on_drop` with the value when
+    /// the last reference to it is dropped, e.g. to release what the value
+    /// stands for in the host's own heap.
+    pub fn new_with_drop_callback<T, F>(value: T, on_drop: F) -> Self
+    where
+        T: Any + Send + Sync + 'static + Sized,
+        F: FnOnce(T) + Send + 'static,
+    {
+        Self {
+            inner: VMExternRef::new_with_drop_callback(value, on_drop),
+        }
+    }
+
     #[cfg(feature = "experimental-reference-types-extern-ref")]
     /// Try to downcast to the given value
     pub fn downcast<T>(&self) -> Option<&T>
@@ -29,6 +29,9 @@ pub struct Features {
     /// 64-bit Memory proposal should be enabled
     pub memory64: bool,
     /// Wasm exceptions proposal should be enabled
+    ///
+    /// Only the Cranelift compiler supports it: the others fail to
+    /// compile the exception handling operators with a codegen error.
     pub exceptions: bool,
     /// Relaxed SIMD proposal should be enabled
     pub relaxed_simd: bool,
@@ -117,6 +117,26 @@ pub struct GlobalIndex(u32);
 entity_impl!(GlobalIndex);
 entity_impl!(ArchivedGlobalIndex);
 
+/// Index type of an exception tag (imported or local) inside the WebAssembly module.
+#[derive(
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Debug,
+    RkyvSerialize,
+    RkyvDeserialize,
+    Archive,
+)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[archive_attr(derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug))]
+pub struct TagIndex(u32);
+entity_impl!(TagIndex);
+entity_impl!(ArchivedTagIndex);
+
 /// Index type of a linear memory (imported or local) inside the WebAssembly module.
 #[derive(
     Copy,
@@ -232,6 +252,8 @@ pub enum ExportIndex {
     Memory(MemoryIndex),
     /// Global export.
     Global(GlobalIndex),
+    /// Tag export.
+    Tag(TagIndex),
 }
 
 /// An entity to import.
@@ -249,4 +271,6 @@ pub enum ImportIndex {
     Memory(MemoryIndex),
     /// Global import.
     Global(GlobalIndex),
+    /// Tag import.
+    Tag(TagIndex),
 }
@@ -53,6 +53,7 @@ pub mod lib {
     }
 }
 
+mod artifact_version;
 pub mod compilation;
 pub mod error;
 mod extern_ref;
@@ -72,10 +73,13 @@ mod values;
 mod vmoffsets;
 
 pub use error::{
-    CompileError, DeserializeError, ImportError, MiddlewareError, ParseCpuFeatureError,
-    PreInstantiationError, SerializeError, WasmError, WasmResult,
+    CompileError, DeserializeError, ImportError, ImportsReport, LinkIssue, MiddlewareError,
+    ParseCpuFeatureError, PreInstantiationError, RuntimeErrorKind, SerializeError, WasmError,
+    WasmResult,
 };
 
+pub use crate::artifact_version::ArtifactVersion;
+
 /// The entity module, with common helpers for Rust structures
 pub mod entity;
 pub use crate::extern_ref::{ExternRef, VMExternRef};
@@ -83,12 +87,12 @@ pub use crate::features::Features;
 pub use crate::indexes::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, ImportIndex,
     LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    SignatureIndex, TableIndex,
+    SignatureIndex, TableIndex, TagIndex,
 };
 pub use crate::initializers::{
     DataInitializer, DataInitializerLocation, OwnedDataInitializer, TableInitializer,
 };
-pub use crate::memory::{Memory32, Memory64, MemorySize};
+pub use crate::memory::{AtomicRmwOp, Memory32, Memory64, MemorySize, WaitResult};
 pub use crate::module::{ExportsIterator, ImportsIterator, ModuleInfo};
 pub use crate::native::{NativeWasmType, ValueType};
 pub use crate::units::{
@@ -97,7 +101,7 @@ pub use crate::units::{
 pub use crate::values::{Value, WasmValueType};
 pub use types::{
     ExportType, ExternType, FunctionType, GlobalInit, GlobalType, ImportType, MemoryType,
-    Mutability, TableType, Type, V128,
+    Mutability, TableType, TagType, Type, V128,
 };
 
 pub use crate::libcalls::LibCall;
@@ -42,6 +42,38 @@ impl MemoryStyle {
     }
 }
 
+/// The read-modify-write operations of the atomic instructions, like
+/// `i32.atomic.rmw.add`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AtomicRmwOp {
+    /// Wrapping addition.
+    Add,
+    /// Wrapping subtraction.
+    Sub,
+    /// Bitwise and.
+    And,
+    /// Bitwise or.
+    Or,
+    /// Bitwise exclusive or.
+    Xor,
+    /// Replacement of the value.
+    Xchg,
+}
+
+/// The outcome of waiting on a location of a shared memory, as returned
+/// by `memory.atomic.wait32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum WaitResult {
+    /// The waiter was woken up by a notification.
+    Ok = 0,
+    /// The location didn't hold the expected value, so there was no
+    /// wait.
+    NotEqual = 1,
+    /// The timeout elapsed before a notification.
+    TimedOut = 2,
+}
+
 /// Trait for the `Memory32` and `Memory64` marker types.
 ///
 /// This allows code to be generic over 32-bit and 64-bit memories.
@@ -252,7 +252,9 @@ mod test_native_type {
 ///
 /// To maintain safety, types which implement this trait must be valid for all
 /// bit patterns. This means that it cannot contain enums, `bool`, references,
-/// etc.
+/// etc. The only enums which are valid for all bit patterns are the fieldless
+/// enums with a variant for each value of their integer representation, which
+/// `#[derive(ValueType)]` accepts.
 ///
 /// Concretely a `u32` is a Value type because every combination of 32 bits is
 /// a valid `u32`. However a `bool` is _not_ a Value type because any bit patterns
@@ -269,17 +271,13 @@ pub unsafe trait ValueType: Copy {
     fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]);
 }
 
-// Trivial implementations for primitive types and arrays of them.
+// Trivial implementations for primitive types.
 macro_rules! primitives {
     ($($t:ident)*) => ($(
         unsafe impl ValueType for $t {
             #[inline]
             fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
         }
-        unsafe impl<const N: usize> ValueType for [$t; N] {
-            #[inline]
-            fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
-        }
     )*)
 }
 primitives! {
@@ -292,6 +290,17 @@ primitives! {
     f32 f64
 }
 
+// Arrays have no padding between their elements, only within them.
+unsafe impl<T: ValueType, const N: usize> ValueType for [T; N] {
+    #[inline]
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+        let size = std::mem::size_of::<T>();
+        for (i, item) in self.iter().enumerate() {
+            item.zero_padding_bytes(&mut bytes[i * size..(i + 1) * size]);
+        }
+    }
+}
+
 // This impl for PhantomData allows #[derive(ValueType)] to work with types
 // that contain a PhantomData.
 unsafe impl<T: ?Sized> ValueType for PhantomData<T> {
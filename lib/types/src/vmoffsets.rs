@@ -123,9 +123,41 @@ impl VMBuiltinFunctionIndex {
     pub const fn get_externref_dec_index() -> Self {
         Self(25)
     }
+    /// Returns an index for wasm's `throw` instruction.
+    pub const fn get_throw_index() -> Self {
+        Self(26)
+    }
+    /// Returns an index for wasm's `rethrow` instruction.
+    pub const fn get_rethrow_index() -> Self {
+        Self(27)
+    }
+    /// Returns an index for a function to check whether an exception is
+    /// being thrown, after a call.
+    pub const fn get_exception_pending_index() -> Self {
+        Self(28)
+    }
+    /// Returns an index for a function to check the tag of the exception
+    /// being thrown, for wasm's `catch` instruction.
+    pub const fn get_exception_matches_index() -> Self {
+        Self(29)
+    }
+    /// Returns an index for wasm's `catch` and `catch_all` instructions.
+    pub const fn get_exception_catch_index() -> Self {
+        Self(30)
+    }
+    /// Returns an index for a function to get the number of caught
+    /// exceptions, which the caught exceptions of a function follow.
+    pub const fn get_exception_slots_index() -> Self {
+        Self(31)
+    }
+    /// Returns an index for a function to release the exceptions caught
+    /// by a function, when it returns.
+    pub const fn get_exception_release_index() -> Self {
+        Self(32)
+    }
     /// Returns the total number of builtin functions.
     pub const fn builtin_functions_total_number() -> u32 {
-        26
+        33
     }
 
     /// Return the index as an u32 number.
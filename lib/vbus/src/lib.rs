@@ -46,7 +46,11 @@ pub struct SpawnOptionsConfig {
     stdin_mode: StdioMode,
     stdout_mode: StdioMode,
     stderr_mode: StdioMode,
+    stdin_file: Option<String>,
+    stdout_file: Option<String>,
+    stderr_file: Option<String>,
     working_dir: String,
+    envs: Vec<(String, String)>,
     remote_instance: Option<String>,
     access_token: Option<String>,
 }
@@ -80,10 +84,34 @@ impl SpawnOptionsConfig {
         self.stderr_mode
     }
 
+    /// The path of the file stdin is read from, in the file system of
+    /// the spawning process, when its mode is [`StdioMode::File`].
+    pub fn stdin_file(&self) -> Option<&str> {
+        self.stdin_file.as_deref()
+    }
+
+    /// The path of the file stdout is written to, in the file system
+    /// of the spawning process, when its mode is [`StdioMode::File`].
+    pub fn stdout_file(&self) -> Option<&str> {
+        self.stdout_file.as_deref()
+    }
+
+    /// The path of the file stderr is written to, in the file system
+    /// of the spawning process, when its mode is [`StdioMode::File`].
+    pub fn stderr_file(&self) -> Option<&str> {
+        self.stderr_file.as_deref()
+    }
+
     pub fn working_dir(&self) -> &str {
         self.working_dir.as_str()
     }
 
+    /// The environment variables set for the process, in addition to
+    /// the ones of the package, which they override.
+    pub const fn envs(&self) -> &Vec<(String, String)> {
+        &self.envs
+    }
+
     pub fn remote_instance(&self) -> Option<&str> {
         self.remote_instance.as_deref()
     }
@@ -110,7 +138,11 @@ impl SpawnOptions {
                 stdin_mode: StdioMode::Null,
                 stdout_mode: StdioMode::Null,
                 stderr_mode: StdioMode::Null,
+                stdin_file: None,
+                stdout_file: None,
+                stderr_file: None,
                 working_dir: "/".to_string(),
+                envs: Vec::new(),
                 remote_instance: None,
                 access_token: None,
             },
@@ -156,11 +188,46 @@ impl SpawnOptions {
         self
     }
 
+    /// Reads stdin from the file at `path`, in the file system of the
+    /// spawning process.
+    pub fn stdin_file(&mut self, path: String) -> &mut Self {
+        self.conf.stdin_mode = StdioMode::File;
+        self.conf.stdin_file = Some(path);
+        self
+    }
+
+    /// Writes stdout to the file at `path`, in the file system of the
+    /// spawning process.
+    pub fn stdout_file(&mut self, path: String) -> &mut Self {
+        self.conf.stdout_mode = StdioMode::File;
+        self.conf.stdout_file = Some(path);
+        self
+    }
+
+    /// Writes stderr to the file at `path`, in the file system of the
+    /// spawning process.
+    pub fn stderr_file(&mut self, path: String) -> &mut Self {
+        self.conf.stderr_mode = StdioMode::File;
+        self.conf.stderr_file = Some(path);
+        self
+    }
+
     pub fn working_dir(&mut self, working_dir: String) -> &mut Self {
         self.conf.working_dir = working_dir;
         self
     }
 
+    /// Sets the environment variable `key` of the process.
+    pub fn env(&mut self, key: String, value: String) -> &mut Self {
+        self.conf.envs.push((key, value));
+        self
+    }
+
+    pub fn envs(&mut self, envs: Vec<(String, String)>) -> &mut Self {
+        self.conf.envs = envs;
+        self
+    }
+
     pub fn remote_instance(&mut self, remote_instance: String) -> &mut Self {
         self.conf.remote_instance = Some(remote_instance);
         self
@@ -212,6 +279,13 @@ pub trait VirtualBusProcess:
 
     /// Returns a file descriptor used to write to STDERR
     fn stderr_fd(&self) -> Option<FileDescriptor>;
+
+    /// Terminates the instance, whose exit code is then set
+    ///
+    /// By default, the instance can't be terminated
+    fn kill(&self) -> Result<()> {
+        Err(BusError::Unsupported)
+    }
 }
 
 pub trait VirtualBusInvocation:
@@ -0,0 +1,431 @@
+//! The files of a [`FileSystem`](crate::FileSystem).
+
+use crate::store::{CompletedPart, ObjectMeta, ObjectStore};
+use std::io::{self, Read, Seek, Write};
+use std::sync::{Arc, Mutex};
+use wasmer_vfs::{FsError, Result, VirtualFile};
+
+/// A file opened for reading only, whose contents are streamed from
+/// the store with ranged reads of `read_ahead` bytes.
+#[derive(Debug)]
+pub(crate) struct ObjectReader {
+    store: Arc<dyn ObjectStore>,
+    object: ObjectMeta,
+    read_ahead: usize,
+    position: u64,
+    /// The bytes of the object from `buffer_start`, fetched by the
+    /// last ranged read.
+    buffer: Vec<u8>,
+    buffer_start: u64,
+}
+
+impl ObjectReader {
+    pub(crate) fn new(store: Arc<dyn ObjectStore>, object: ObjectMeta, read_ahead: usize) -> Self {
+        Self {
+            store,
+            object,
+            read_ahead,
+            position: 0,
+            buffer: Vec::new(),
+            buffer_start: 0,
+        }
+    }
+}
+
+impl VirtualFile for ObjectReader {
+    fn last_accessed(&self) -> u64 {
+        self.object.modified
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.object.modified
+    }
+
+    fn created_time(&self) -> u64 {
+        self.object.modified
+    }
+
+    fn size(&self) -> u64 {
+        self.object.size
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.store.delete(&self.object.key)
+    }
+
+    fn bytes_available_read(&self) -> Result<Option<usize>> {
+        Ok(Some(self.object.size.saturating_sub(self.position) as usize))
+    }
+}
+
+impl Read for ObjectReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.object.size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let buffer_end = self.buffer_start + self.buffer.len() as u64;
+        if self.position < self.buffer_start || self.position >= buffer_end {
+            let end = self.object.size.min(self.position + self.read_ahead as u64);
+            self.buffer = self
+                .store
+                .get_range(&self.object.key, self.position..end)
+                .map_err(into_io_error)?;
+            self.buffer_start = self.position;
+        }
+
+        let start = (self.position - self.buffer_start) as usize;
+        let read = (&self.buffer[start.min(self.buffer.len())..]).read(buf)?;
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Seek for ObjectReader {
+    fn seek(&mut self, position: io::SeekFrom) -> io::Result<u64> {
+        self.position = seek(position, self.position, self.object.size)?;
+
+        Ok(self.position)
+    }
+}
+
+impl Write for ObjectReader {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the file is opened for reading only",
+        ))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A file opened for writing, whose contents are held in memory and
+/// written whole to the store when the file is synced or closed.
+///
+/// The contents are uploaded in parts of `part_size` bytes as soon as
+/// they are written past, so that writing a large file sequentially
+/// doesn't hold it in memory. Those bytes can't be read, written or
+/// truncated anymore.
+#[derive(Debug)]
+pub(crate) struct ObjectWriter {
+    store: Arc<dyn ObjectStore>,
+    key: String,
+    part_size: usize,
+    append: bool,
+    modified: u64,
+    position: u64,
+    upload: Mutex<Upload>,
+}
+
+#[derive(Debug, Default)]
+struct Upload {
+    multipart: Option<Multipart>,
+    /// The number of bytes uploaded as parts of `multipart`.
+    uploaded: u64,
+    /// The bytes following the uploaded ones.
+    pending: Vec<u8>,
+    /// Whether there are writes not sent to the store yet.
+    dirty: bool,
+    /// Whether a multipart upload has been completed, after which the
+    /// object can't be modified anymore.
+    completed: bool,
+}
+
+#[derive(Debug)]
+struct Multipart {
+    upload_id: String,
+    parts: Vec<CompletedPart>,
+}
+
+impl ObjectWriter {
+    /// Creates a file of the object `key` holding `contents`, which
+    /// are written when the file is synced or closed.
+    pub(crate) fn new(
+        store: Arc<dyn ObjectStore>,
+        key: String,
+        contents: Vec<u8>,
+        modified: u64,
+        append: bool,
+        part_size: usize,
+    ) -> Self {
+        Self {
+            store,
+            key,
+            part_size,
+            append,
+            modified,
+            position: 0,
+            upload: Mutex::new(Upload {
+                pending: contents,
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn upload(&mut self) -> &mut Upload {
+        // The upload is left consistent by a panicking writer.
+        match self.upload.get_mut() {
+            Ok(upload) => upload,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl Upload {
+    fn len(&self) -> u64 {
+        self.uploaded + self.pending.len() as u64
+    }
+
+    /// The position in `pending` of `position` in the file.
+    fn offset(&self, position: u64) -> io::Result<usize> {
+        if self.completed || position < self.uploaded {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the bytes have already been uploaded",
+            ));
+        }
+
+        Ok((position - self.uploaded) as usize)
+    }
+
+    fn read_at(&self, position: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if position >= self.len() {
+            return Ok(0);
+        }
+
+        let offset = self.offset(position)?;
+        (&self.pending[offset..]).read(buf)
+    }
+
+    fn write_at(&mut self, position: u64, buf: &[u8]) -> io::Result<()> {
+        let offset = self.offset(position)?;
+        let end = offset + buf.len();
+        if self.pending.len() < end {
+            self.pending.resize(end, 0);
+        }
+        self.pending[offset..end].copy_from_slice(buf);
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    fn set_len(&mut self, new_size: u64) -> Result<()> {
+        let offset = self.offset(new_size).map_err(|_| FsError::InvalidInput)?;
+        self.pending.resize(offset, 0);
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// Uploads the parts of `pending` before `position`.
+    fn upload_parts(
+        &mut self,
+        store: &dyn ObjectStore,
+        key: &str,
+        position: u64,
+        part_size: usize,
+    ) -> Result<()> {
+        while position.saturating_sub(self.uploaded) >= part_size as u64
+            && self.pending.len() >= part_size
+        {
+            if self.multipart.is_none() {
+                self.multipart = Some(Multipart {
+                    upload_id: store.create_multipart_upload(key)?,
+                    parts: Vec::new(),
+                });
+            }
+            let multipart = self.multipart.as_mut().unwrap();
+
+            let part = self.pending[..part_size].to_vec();
+            let number = multipart.parts.len() as u32 + 1;
+            multipart
+                .parts
+                .push(store.upload_part(key, &multipart.upload_id, number, part)?);
+            self.pending.drain(..part_size);
+            self.uploaded += part_size as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the object to the store.
+    fn commit(&mut self, store: &dyn ObjectStore, key: &str) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        match self.multipart.as_mut() {
+            Some(multipart) => {
+                if !self.pending.is_empty() {
+                    let number = multipart.parts.len() as u32 + 1;
+                    let part = store.upload_part(
+                        key,
+                        &multipart.upload_id,
+                        number,
+                        self.pending.clone(),
+                    )?;
+                    multipart.parts.push(part);
+                    self.uploaded += self.pending.len() as u64;
+                    self.pending.clear();
+                }
+                store.complete_multipart_upload(key, &multipart.upload_id, &multipart.parts)?;
+
+                self.multipart = None;
+                self.completed = true;
+            }
+            None => store.put(key, self.pending.clone())?,
+        }
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+impl Drop for ObjectWriter {
+    fn drop(&mut self) {
+        let (store, key) = (self.store.clone(), self.key.clone());
+        let upload = self.upload();
+
+        if let Err(error) = upload.commit(&*store, &key) {
+            tracing::warn!("failed to write the object `{}`: {}", key, error);
+
+            if let Some(multipart) = upload.multipart.take() {
+                let _ = store.abort_multipart_upload(&key, &multipart.upload_id);
+            }
+        }
+    }
+}
+
+impl VirtualFile for ObjectWriter {
+    fn last_accessed(&self) -> u64 {
+        self.modified
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.modified
+    }
+
+    fn created_time(&self) -> u64 {
+        self.modified
+    }
+
+    fn size(&self) -> u64 {
+        match self.upload.lock() {
+            Ok(upload) => upload.len(),
+            Err(poisoned) => poisoned.into_inner().len(),
+        }
+    }
+
+    fn set_len(&mut self, new_size: u64) -> Result<()> {
+        self.upload().set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        let upload = self.upload();
+        upload.dirty = false;
+        let multipart = upload.multipart.take();
+
+        if let Some(multipart) = multipart {
+            self.store
+                .abort_multipart_upload(&self.key, &multipart.upload_id)?;
+        }
+        self.store.delete(&self.key)
+    }
+
+    fn sync_to_disk(&self) -> Result<()> {
+        self.upload
+            .lock()
+            .map_err(|_| FsError::Lock)?
+            .commit(&*self.store, &self.key)
+    }
+
+    fn bytes_available_read(&self) -> Result<Option<usize>> {
+        Ok(Some(self.size().saturating_sub(self.position) as usize))
+    }
+}
+
+impl Read for ObjectWriter {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let position = self.position;
+        let read = self.upload().read_at(position, buf)?;
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Seek for ObjectWriter {
+    fn seek(&mut self, position: io::SeekFrom) -> io::Result<u64> {
+        let len = self.upload().len();
+        self.position = seek(position, self.position, len)?;
+
+        Ok(self.position)
+    }
+}
+
+impl Write for ObjectWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (store, key, part_size) = (self.store.clone(), self.key.clone(), self.part_size);
+        let append = self.append;
+        let mut position = self.position;
+
+        let upload = self.upload();
+        if append {
+            position = upload.len();
+        }
+        upload.write_at(position, buf)?;
+        position += buf.len() as u64;
+        upload
+            .upload_parts(&*store, &key, position, part_size)
+            .map_err(into_io_error)?;
+
+        self.position = position;
+
+        Ok(buf.len())
+    }
+
+    /// The writes are sent to the store by
+    /// [`sync_to_disk`](VirtualFile::sync_to_disk) or when the file is
+    /// closed, since an object can only be written whole.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn seek(position: io::SeekFrom, current: u64, len: u64) -> io::Result<u64> {
+    let position = match position {
+        io::SeekFrom::Start(offset) => offset as i64,
+        io::SeekFrom::End(offset) => len as i64 + offset,
+        io::SeekFrom::Current(offset) => current as i64 + offset,
+    };
+
+    if position < 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "seeking before the beginning of the file",
+        ));
+    }
+
+    Ok(position as u64)
+}
+
+fn into_io_error(error: FsError) -> io::Error {
+    let kind = match error {
+        FsError::EntityNotFound => io::ErrorKind::NotFound,
+        FsError::PermissionDenied => io::ErrorKind::PermissionDenied,
+        FsError::TimedOut => io::ErrorKind::TimedOut,
+        FsError::InvalidInput => io::ErrorKind::InvalidInput,
+        FsError::InvalidData => io::ErrorKind::InvalidData,
+        _ => io::ErrorKind::Other,
+    };
+
+    io::Error::new(kind, error)
+}
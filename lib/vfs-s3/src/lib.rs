@@ -0,0 +1,550 @@
+//! A file system exposing a prefix of an object storage, like an S3
+//! bucket, as a directory, e.g. for a program processing the data
+//! held by the storage:
+//!
+//! ```rust,ignore
+//! use wasmer_vfs_s3::{FileSystem, S3Store};
+//! use wasmer_wasi::WasiState;
+//!
+//! let fs = FileSystem::new(S3Store::new(bucket), "datasets/2022");
+//! let wasi_env = WasiState::new("program")
+//!     .set_fs(Box::new(fs))
+//!     .preopen_dir("/")?
+//!     .finalize()?;
+//! ```
+//!
+//! The key of the object of a file is the prefix followed by the path
+//! of the file, `/` being the separator of the directories. The
+//! directories are the prefixes of the keys, plus the empty ones
+//! created with `create_dir`, which are kept as empty objects whose
+//! key ends with a `/`, like the consoles of the storages do.
+//!
+//! The objects are only ever read and written whole:
+//!
+//! * a file opened for reading only is streamed with ranged reads,
+//! * a file opened for writing holds its contents in memory, the
+//!   current contents being downloaded first unless the file is
+//!   truncated, and is written to the storage when it's synced or
+//!   closed. The contents written sequentially are uploaded in parts
+//!   as they are written, with a multipart upload.
+//!
+//! The storages have no renaming, so renaming copies the objects then
+//! removes them, which isn't atomic.
+
+use std::path::{Component, Path};
+use std::sync::Arc;
+use wasmer_vfs::{
+    DirEntry, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, Result,
+    VirtualFile,
+};
+
+mod file;
+#[cfg(feature = "s3")]
+mod s3_store;
+mod store;
+
+use file::{ObjectReader, ObjectWriter};
+#[cfg(feature = "s3")]
+pub use s3;
+#[cfg(feature = "s3")]
+pub use s3_store::S3Store;
+pub use store::{CompletedPart, Listing, MemoryStore, ObjectMeta, ObjectStore};
+
+/// The smallest size of the parts of a multipart upload, but the last
+/// one, accepted by S3.
+pub const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// The default size of the parts of the multipart uploads.
+pub const DEFAULT_PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// The default number of bytes fetched by a ranged read.
+pub const DEFAULT_READ_AHEAD: usize = 1024 * 1024;
+
+/// A file system exposing a prefix of an object store, see the crate
+/// documentation to learn more.
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    store: Arc<dyn ObjectStore>,
+    prefix: String,
+    part_size: usize,
+    read_ahead: usize,
+}
+
+impl FileSystem {
+    /// Creates a file system exposing the objects of `store` whose key
+    /// starts with `prefix`, which is the root directory. An empty
+    /// prefix exposes the whole store.
+    pub fn new<S: ObjectStore>(store: S, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+
+        Self {
+            store: Arc::new(store),
+            prefix: if prefix.is_empty() {
+                String::new()
+            } else {
+                format!("{}/", prefix)
+            },
+            part_size: DEFAULT_PART_SIZE,
+            read_ahead: DEFAULT_READ_AHEAD,
+        }
+    }
+
+    /// Sets the size of the parts of the multipart uploads, which is
+    /// at least [`MIN_PART_SIZE`].
+    pub fn with_part_size(mut self, part_size: usize) -> Self {
+        self.part_size = part_size.max(MIN_PART_SIZE);
+        self
+    }
+
+    /// Sets the number of bytes fetched by a ranged read, at least one.
+    pub fn with_read_ahead(mut self, read_ahead: usize) -> Self {
+        self.read_ahead = read_ahead.max(1);
+        self
+    }
+
+    /// The key of the object of the file `path`, which is the prefix
+    /// itself for the root directory.
+    fn key(&self, path: &Path) -> Result<String> {
+        let mut key = self.prefix.clone();
+
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => {
+                    if !key.is_empty() && !key.ends_with('/') {
+                        key.push('/');
+                    }
+                    key.push_str(name.to_str().ok_or(FsError::InvalidInput)?);
+                }
+                Component::RootDir | Component::CurDir => {}
+                Component::ParentDir | Component::Prefix(_) => return Err(FsError::InvalidInput),
+            }
+        }
+
+        Ok(key)
+    }
+
+    /// The prefix of the keys of the entries of the directory `key`.
+    fn directory_prefix(&self, key: &str) -> String {
+        if key.len() == self.prefix.len() {
+            self.prefix.clone()
+        } else {
+            format!("{}/", key)
+        }
+    }
+
+    fn is_root(&self, key: &str) -> bool {
+        key.len() == self.prefix.len()
+    }
+
+    /// Returns the metadata of the directory `key`, or `None` if there
+    /// is no such directory.
+    fn directory_metadata(&self, key: &str) -> Result<Option<Metadata>> {
+        if self.is_root(key) {
+            return Ok(Some(directory_metadata(0)));
+        }
+
+        let prefix = self.directory_prefix(key);
+        let listing = self.store.list(&prefix)?;
+        if listing.objects.is_empty() && listing.prefixes.is_empty() {
+            return Ok(None);
+        }
+
+        let modified = listing
+            .objects
+            .iter()
+            .find(|object| object.key == prefix)
+            .map_or(0, |marker| marker.modified);
+
+        Ok(Some(directory_metadata(modified)))
+    }
+
+    /// Checks that the parent of the file `key` is a directory.
+    fn check_parent(&self, key: &str) -> Result<()> {
+        let parent = match key.rfind('/') {
+            Some(position) if position + 1 > self.prefix.len() => &key[..position],
+            _ => return Ok(()),
+        };
+
+        match self.directory_metadata(parent)? {
+            Some(_) => Ok(()),
+            None if self.store.head(parent)?.is_some() => Err(FsError::BaseNotDirectory),
+            None => Err(FsError::EntityNotFound),
+        }
+    }
+
+    /// Returns all the objects under `prefix`, at any depth.
+    fn walk(&self, prefix: &str) -> Result<Vec<ObjectMeta>> {
+        let listing = self.store.list(prefix)?;
+        let mut objects = listing.objects;
+
+        for prefix in listing.prefixes {
+            objects.extend(self.walk(&prefix)?);
+        }
+
+        Ok(objects)
+    }
+}
+
+fn directory_metadata(modified: u64) -> Metadata {
+    Metadata {
+        ft: FileType {
+            dir: true,
+            ..Default::default()
+        },
+        accessed: modified,
+        created: modified,
+        modified,
+        len: 0,
+    }
+}
+
+fn file_metadata(object: &ObjectMeta) -> Metadata {
+    Metadata {
+        ft: FileType {
+            file: true,
+            ..Default::default()
+        },
+        accessed: object.modified,
+        created: object.modified,
+        modified: object.modified,
+        len: object.size,
+    }
+}
+
+impl wasmer_vfs::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        let key = self.key(path)?;
+        let prefix = self.directory_prefix(&key);
+        let listing = self.store.list(&prefix)?;
+
+        if !self.is_root(&key) && listing.objects.is_empty() && listing.prefixes.is_empty() {
+            return match self.store.head(&key)? {
+                Some(_) => Err(FsError::BaseNotDirectory),
+                None => Err(FsError::EntityNotFound),
+            };
+        }
+
+        let files = listing
+            .objects
+            .iter()
+            .filter(|object| object.key != prefix)
+            .map(|object| DirEntry {
+                path: path.join(&object.key[prefix.len()..]),
+                metadata: Ok(file_metadata(object)),
+            });
+        let directories = listing.prefixes.iter().map(|directory| DirEntry {
+            path: path.join(directory[prefix.len()..].trim_end_matches('/')),
+            metadata: Ok(directory_metadata(0)),
+        });
+
+        Ok(ReadDir::new(directories.chain(files).collect()))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        let key = self.key(path)?;
+        if self.directory_metadata(&key)?.is_some() || self.store.head(&key)?.is_some() {
+            return Err(FsError::AlreadyExists);
+        }
+        self.check_parent(&key)?;
+
+        self.store.put(&self.directory_prefix(&key), Vec::new())
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        let key = self.key(path)?;
+        if self.is_root(&key) {
+            return Err(FsError::PermissionDenied);
+        }
+
+        let prefix = self.directory_prefix(&key);
+        let listing = self.store.list(&prefix)?;
+        let has_marker = listing.objects.iter().any(|object| object.key == prefix);
+
+        if !listing.prefixes.is_empty() || listing.objects.len() > has_marker as usize {
+            return Err(FsError::DirectoryNotEmpty);
+        }
+        if !has_marker {
+            return match self.store.head(&key)? {
+                Some(_) => Err(FsError::BaseNotDirectory),
+                None => Err(FsError::EntityNotFound),
+            };
+        }
+
+        self.store.delete(&prefix)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let (from, to) = (self.key(from)?, self.key(to)?);
+        if self.is_root(&from) || self.is_root(&to) {
+            return Err(FsError::PermissionDenied);
+        }
+        self.check_parent(&to)?;
+
+        if self.store.head(&from)?.is_some() {
+            self.store.copy(&from, &to)?;
+            return self.store.delete(&from);
+        }
+
+        let (from_prefix, to_prefix) = (self.directory_prefix(&from), self.directory_prefix(&to));
+        if to_prefix.starts_with(&from_prefix) {
+            return Err(FsError::InvalidInput);
+        }
+        let objects = self.walk(&from_prefix)?;
+        if objects.is_empty() {
+            return Err(FsError::EntityNotFound);
+        }
+
+        for object in &objects {
+            let destination = format!("{}{}", to_prefix, &object.key[from_prefix.len()..]);
+            self.store.copy(&object.key, &destination)?;
+        }
+        for object in &objects {
+            self.store.delete(&object.key)?;
+        }
+
+        Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        let key = self.key(path)?;
+        if !self.is_root(&key) {
+            if let Some(object) = self.store.head(&key)? {
+                return Ok(file_metadata(&object));
+            }
+        }
+
+        self.directory_metadata(&key)?
+            .ok_or(FsError::EntityNotFound)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        let key = self.key(path)?;
+        if self.is_root(&key) {
+            return Err(FsError::NotAFile);
+        }
+
+        match self.store.head(&key)? {
+            Some(_) => self.store.delete(&key),
+            None if self.directory_metadata(&key)?.is_some() => Err(FsError::NotAFile),
+            None => Err(FsError::EntityNotFound),
+        }
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(FileOpener {
+            filesystem: self.clone(),
+        }))
+    }
+}
+
+/// The opener of files in an object store [`FileSystem`].
+#[derive(Debug, Clone)]
+pub struct FileOpener {
+    filesystem: FileSystem,
+}
+
+impl wasmer_vfs::FileOpener for FileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let fs = &self.filesystem;
+        let key = fs.key(path)?;
+        if fs.is_root(&key) {
+            return Err(FsError::NotAFile);
+        }
+
+        let object = fs.store.head(&key)?;
+        if object.is_none() && fs.directory_metadata(&key)?.is_some() {
+            return Err(FsError::NotAFile);
+        }
+
+        let writes = conf.write() || conf.append() || conf.truncate();
+        if !writes && !conf.create() && !conf.create_new() {
+            let object = object.ok_or(FsError::EntityNotFound)?;
+
+            return Ok(Box::new(ObjectReader::new(
+                fs.store.clone(),
+                object,
+                fs.read_ahead,
+            )));
+        }
+
+        let (contents, modified) = match object {
+            Some(_) if conf.create_new() => return Err(FsError::AlreadyExists),
+            Some(object) if conf.truncate() => {
+                fs.store.put(&key, Vec::new())?;
+                (Vec::new(), object.modified)
+            }
+            Some(object) => (fs.store.get_range(&key, 0..object.size)?, object.modified),
+            None if conf.create() || conf.create_new() => {
+                // The file exists as soon as it's created.
+                fs.check_parent(&key)?;
+                fs.store.put(&key, Vec::new())?;
+                (Vec::new(), 0)
+            }
+            None => return Err(FsError::EntityNotFound),
+        };
+
+        Ok(Box::new(ObjectWriter::new(
+            fs.store.clone(),
+            key,
+            contents,
+            modified,
+            conf.append(),
+            fs.part_size,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test_filesystem {
+    use super::{FileSystem, MemoryStore, ObjectStore, MIN_PART_SIZE};
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::path::Path;
+    use std::sync::Arc;
+    use wasmer_vfs::{FileSystem as FS, FsError};
+
+    fn filesystem() -> (FileSystem, Arc<MemoryStore>) {
+        let store = Arc::new(MemoryStore::new());
+        store.put("data/in/a.csv", b"1,2,3".to_vec()).unwrap();
+        store.put("data/in/nested/b.csv", b"4,5".to_vec()).unwrap();
+        store.put("other/c.csv", b"6".to_vec()).unwrap();
+
+        (FileSystem::new(store.clone(), "/data/"), store)
+    }
+
+    #[test]
+    fn test_directories() {
+        let (fs, store) = filesystem();
+
+        assert!(fs.metadata(Path::new("/")).unwrap().is_dir());
+        assert!(fs.metadata(Path::new("/in/nested")).unwrap().is_dir());
+        assert_eq!(fs.metadata(Path::new("/in/a.csv")).unwrap().len(), 5);
+        assert_eq!(
+            fs.metadata(Path::new("/c.csv")).map(|_| ()),
+            Err(FsError::EntityNotFound)
+        );
+
+        let mut names = fs
+            .read_dir(Path::new("/in"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["a.csv", "nested"]);
+
+        fs.create_dir(Path::new("/out")).unwrap();
+        assert!(store.head("data/out/").unwrap().is_some());
+        assert_eq!(
+            fs.create_dir(Path::new("/out")),
+            Err(FsError::AlreadyExists)
+        );
+        assert_eq!(
+            fs.create_dir(Path::new("/missing/out")),
+            Err(FsError::EntityNotFound)
+        );
+        assert_eq!(
+            fs.remove_dir(Path::new("/in")),
+            Err(FsError::DirectoryNotEmpty)
+        );
+        fs.remove_dir(Path::new("/out")).unwrap();
+        assert_eq!(
+            fs.metadata(Path::new("/out")).map(|_| ()),
+            Err(FsError::EntityNotFound)
+        );
+
+        fs.rename(Path::new("/in"), Path::new("/moved")).unwrap();
+        assert!(store.head("data/moved/nested/b.csv").unwrap().is_some());
+        assert!(store.head("data/in/a.csv").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_write() {
+        let (fs, store) = filesystem();
+
+        let mut file = fs.new_open_options().read(true).open("/in/a.csv").unwrap();
+        file.seek(SeekFrom::Start(2)).unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "2,3");
+        assert!(file.write(b"4").is_err());
+
+        {
+            let mut file = fs
+                .new_open_options()
+                .append(true)
+                .open("/in/a.csv")
+                .unwrap();
+            file.write_all(b",4").unwrap();
+
+            // The object is written whole when the file is closed.
+            assert_eq!(store.head("data/in/a.csv").unwrap().unwrap().size, 5);
+        }
+        assert_eq!(store.get_range("data/in/a.csv", 0..10).unwrap(), b"1,2,3,4");
+
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create_new(true)
+            .open("/in/new.csv")
+            .unwrap();
+        assert!(store.head("data/in/new.csv").unwrap().is_some());
+        file.write_all(b"7,8").unwrap();
+        file.sync_to_disk().unwrap();
+        assert_eq!(store.get_range("data/in/new.csv", 0..10).unwrap(), b"7,8");
+
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .create_new(true)
+                .open("/in/new.csv")
+                .map(|_| ()),
+            Err(FsError::AlreadyExists)
+        );
+        assert_eq!(
+            fs.new_open_options().read(true).open("/in").map(|_| ()),
+            Err(FsError::NotAFile)
+        );
+    }
+
+    #[test]
+    fn test_multipart_upload() {
+        let (fs, store) = filesystem();
+        let fs = fs.with_part_size(MIN_PART_SIZE);
+
+        {
+            let mut file = fs
+                .new_open_options()
+                .write(true)
+                .create(true)
+                .open("/large.bin")
+                .unwrap();
+            for byte in 0..3u8 {
+                file.write_all(&vec![byte; MIN_PART_SIZE]).unwrap();
+            }
+            file.write_all(b"end").unwrap();
+
+            // The parts already uploaded can't be rewritten.
+            file.seek(SeekFrom::Start(0)).unwrap();
+            assert!(file.write(b"start").is_err());
+        }
+
+        let size = 3 * MIN_PART_SIZE as u64 + 3;
+        assert_eq!(store.head("data/large.bin").unwrap().unwrap().size, size);
+        assert_eq!(
+            store
+                .get_range(
+                    "data/large.bin",
+                    MIN_PART_SIZE as u64 - 1..MIN_PART_SIZE as u64 + 1
+                )
+                .unwrap(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            store.get_range("data/large.bin", size - 4..size).unwrap(),
+            b"\x02end"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! An [`ObjectStore`] backed by an S3 bucket.
+
+use crate::store::{CompletedPart, Listing, ObjectMeta, ObjectStore};
+use s3::bucket::Bucket;
+use s3::error::S3Error;
+use s3::serde_types::Part;
+use std::fmt;
+use std::ops::Range;
+use wasmer_vfs::{FsError, Result};
+
+/// The content type of the objects written by the file system, which
+/// knows nothing about their contents.
+const CONTENT_TYPE: &str = "application/octet-stream";
+
+/// An object store backed by an S3 bucket, or by the bucket of any
+/// storage compatible with S3 (MinIO, Ceph, R2, etc).
+///
+/// ```rust,ignore
+/// use wasmer_vfs_s3::s3::{bucket::Bucket, creds::Credentials, region::Region};
+/// use wasmer_vfs_s3::S3Store;
+///
+/// let bucket = Bucket::new("datasets", Region::EuWest1, Credentials::default()?)?;
+/// let store = S3Store::new(bucket);
+/// ```
+pub struct S3Store {
+    bucket: Bucket,
+}
+
+impl S3Store {
+    /// Creates a store holding its objects in `bucket`.
+    pub fn new(bucket: Bucket) -> Self {
+        Self { bucket }
+    }
+}
+
+impl fmt::Debug for S3Store {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("S3Store")
+            .field("bucket", &self.bucket.name())
+            .finish()
+    }
+}
+
+fn from_s3_error(error: S3Error) -> FsError {
+    tracing::debug!("s3 request failed: {}", error);
+
+    FsError::IOError
+}
+
+fn check_status(status: u16) -> Result<()> {
+    match status {
+        200..=299 => Ok(()),
+        403 => Err(FsError::PermissionDenied),
+        404 => Err(FsError::EntityNotFound),
+        416 => Err(FsError::InvalidInput),
+        _ => Err(FsError::IOError),
+    }
+}
+
+impl ObjectStore for S3Store {
+    fn head(&self, key: &str) -> Result<Option<ObjectMeta>> {
+        let (head, status) = self.bucket.head_object(key).map_err(from_s3_error)?;
+        if status == 404 {
+            return Ok(None);
+        }
+        check_status(status)?;
+
+        Ok(Some(ObjectMeta {
+            key: key.to_string(),
+            size: head.content_length.unwrap_or(0).max(0) as u64,
+            modified: head
+                .last_modified
+                .as_deref()
+                .and_then(parse_http_date)
+                .unwrap_or(0),
+        }))
+    }
+
+    fn get_range(&self, key: &str, range: Range<u64>) -> Result<Vec<u8>> {
+        if range.start >= range.end {
+            return Ok(Vec::new());
+        }
+
+        // The end of a ranged read is inclusive.
+        let response = self
+            .bucket
+            .get_object_range(key, range.start, Some(range.end - 1))
+            .map_err(from_s3_error)?;
+        match response.status_code() {
+            // The range starts past the end of the object.
+            416 => Ok(Vec::new()),
+            status => {
+                check_status(status)?;
+                Ok(response.bytes().to_vec())
+            }
+        }
+    }
+
+    fn list(&self, prefix: &str) -> Result<Listing> {
+        let pages = self
+            .bucket
+            .list(prefix.to_string(), Some("/".to_string()))
+            .map_err(from_s3_error)?;
+        let mut listing = Listing::default();
+
+        for page in pages {
+            listing
+                .objects
+                .extend(page.contents.into_iter().map(|object| ObjectMeta {
+                    modified: parse_iso8601(&object.last_modified).unwrap_or(0),
+                    key: object.key,
+                    size: object.size,
+                }));
+            listing.prefixes.extend(
+                page.common_prefixes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|common_prefix| common_prefix.prefix),
+            );
+        }
+
+        Ok(listing)
+    }
+
+    fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let response = self.bucket.put_object(key, &data).map_err(from_s3_error)?;
+
+        check_status(response.status_code())
+    }
+
+    fn copy(&self, from: &str, to: &str) -> Result<()> {
+        let status = self
+            .bucket
+            .copy_object_internal(from, to)
+            .map_err(from_s3_error)?;
+
+        check_status(status)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let response = self.bucket.delete_object(key).map_err(from_s3_error)?;
+
+        match response.status_code() {
+            404 => Ok(()),
+            status => check_status(status),
+        }
+    }
+
+    fn create_multipart_upload(&self, key: &str) -> Result<String> {
+        let response = self
+            .bucket
+            .initiate_multipart_upload(key, CONTENT_TYPE)
+            .map_err(from_s3_error)?;
+
+        Ok(response.upload_id)
+    }
+
+    fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        number: u32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart> {
+        let part = self
+            .bucket
+            .put_multipart_chunk(data, key, number, upload_id, CONTENT_TYPE)
+            .map_err(from_s3_error)?;
+
+        Ok(CompletedPart {
+            number: part.part_number,
+            etag: part.etag,
+        })
+    }
+
+    fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+    ) -> Result<()> {
+        let parts = parts
+            .iter()
+            .map(|part| Part {
+                part_number: part.number,
+                etag: part.etag.clone(),
+            })
+            .collect();
+        let response = self
+            .bucket
+            .complete_multipart_upload(key, upload_id, parts)
+            .map_err(from_s3_error)?;
+
+        check_status(response.status_code())
+    }
+
+    fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        self.bucket
+            .abort_upload(key, upload_id)
+            .map_err(from_s3_error)
+    }
+}
+
+/// Parses the dates of the listings, e.g. `2009-10-12T17:50:30.000Z`,
+/// into a UNIX timestamp in nanoseconds.
+fn parse_iso8601(date: &str) -> Option<u64> {
+    let (date, time) = date.trim_end_matches('Z').split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let nanos = format!("{:0<9}", fraction).get(..9)?.parse::<u64>().ok()?;
+
+    Some(timestamp(year, month, day, time)? + nanos)
+}
+
+/// Parses the dates of the HTTP headers, e.g.
+/// `Mon, 12 Oct 2009 17:50:30 GMT`, into a UNIX timestamp in
+/// nanoseconds.
+fn parse_http_date(date: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let mut parts = date.split_whitespace().skip(1);
+    let day = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+    let year = parts.next()?.parse().ok()?;
+
+    timestamp(year, month, day, parts.next()?)
+}
+
+/// Converts a date and a `hh:mm:ss` time in UTC to a UNIX timestamp in
+/// nanoseconds.
+fn timestamp(year: i64, month: i64, day: i64, time: &str) -> Option<u64> {
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    // Days since the UNIX epoch of a date in the proleptic Gregorian
+    // calendar, see http://howardhinnant.github.io/date_algorithms.html.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second;
+
+    Some((seconds.max(0) as u64).saturating_mul(1_000_000_000))
+}
+
+#[cfg(test)]
+mod test_dates {
+    use super::{parse_http_date, parse_iso8601};
+
+    #[test]
+    fn test_parse_dates() {
+        assert_eq!(
+            parse_iso8601("2009-10-12T17:50:30.000Z"),
+            Some(1_255_369_830_000_000_000)
+        );
+        assert_eq!(
+            parse_iso8601("2009-10-12T17:50:30.5Z"),
+            Some(1_255_369_830_500_000_000)
+        );
+        assert_eq!(
+            parse_http_date("Mon, 12 Oct 2009 17:50:30 GMT"),
+            Some(1_255_369_830_000_000_000)
+        );
+        assert_eq!(parse_iso8601("yesterday"), None);
+        assert_eq!(parse_http_date("Mon, 12 Foo 2009 17:50:30 GMT"), None);
+    }
+}
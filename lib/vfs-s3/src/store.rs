@@ -0,0 +1,333 @@
+//! The object storages a [`FileSystem`](crate::FileSystem) is backed
+//! by, see [`ObjectStore`].
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use wasmer_vfs::{FsError, Result};
+
+/// An object of a store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectMeta {
+    /// The full key of the object.
+    pub key: String,
+    /// The size of the object in bytes.
+    pub size: u64,
+    /// The time of the last modification of the object in nanoseconds
+    /// as a UNIX timestamp, or 0 if it's unknown.
+    pub modified: u64,
+}
+
+/// The entries found under a prefix, see [`ObjectStore::list`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Listing {
+    /// The objects whose key has no `/` after the prefix, including
+    /// the object whose key is the prefix itself, if any.
+    pub objects: Vec<ObjectMeta>,
+    /// The distinct prefixes of the other keys up to their first `/`
+    /// after the prefix, that `/` included.
+    pub prefixes: Vec<String>,
+}
+
+/// A part of a multipart upload, see [`ObjectStore::upload_part`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedPart {
+    /// The number of the part, starting at 1.
+    pub number: u32,
+    /// The entity tag returned by the store when the part was uploaded.
+    pub etag: String,
+}
+
+/// A flat key-value object storage, like an S3 bucket.
+///
+/// The objects are only ever written whole: either at once with
+/// [`put`](ObjectStore::put), or in parts with a multipart upload
+/// which makes the object visible when it's completed.
+pub trait ObjectStore: fmt::Debug + Send + Sync + 'static {
+    /// Returns the object `key`, or `None` if it doesn't exist.
+    fn head(&self, key: &str) -> Result<Option<ObjectMeta>>;
+
+    /// Returns the bytes of the object `key` in `range`, which is
+    /// clamped to the size of the object.
+    fn get_range(&self, key: &str, range: Range<u64>) -> Result<Vec<u8>>;
+
+    /// Lists the entries under `prefix`, using `/` as the delimiter.
+    fn list(&self, prefix: &str) -> Result<Listing>;
+
+    /// Creates or replaces the object `key`.
+    fn put(&self, key: &str, data: Vec<u8>) -> Result<()>;
+
+    /// Copies the object `from` to `to`, replacing `to`.
+    fn copy(&self, from: &str, to: &str) -> Result<()>;
+
+    /// Removes the object `key`. Removing a missing object succeeds.
+    fn delete(&self, key: &str) -> Result<()>;
+
+    /// Starts a multipart upload of the object `key`, returning the
+    /// identifier of the upload.
+    fn create_multipart_upload(&self, key: &str) -> Result<String>;
+
+    /// Uploads the part `number` of the upload `upload_id`. Every part
+    /// but the last one must be at least
+    /// [`MIN_PART_SIZE`](crate::MIN_PART_SIZE) long on S3.
+    fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        number: u32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart>;
+
+    /// Completes the upload `upload_id`, creating or replacing the
+    /// object `key` with the concatenation of `parts`.
+    fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+    ) -> Result<()>;
+
+    /// Abandons the upload `upload_id`, releasing its parts.
+    fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()>;
+}
+
+impl<S: ObjectStore + ?Sized> ObjectStore for Arc<S> {
+    fn head(&self, key: &str) -> Result<Option<ObjectMeta>> {
+        (**self).head(key)
+    }
+
+    fn get_range(&self, key: &str, range: Range<u64>) -> Result<Vec<u8>> {
+        (**self).get_range(key, range)
+    }
+
+    fn list(&self, prefix: &str) -> Result<Listing> {
+        (**self).list(prefix)
+    }
+
+    fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        (**self).put(key, data)
+    }
+
+    fn copy(&self, from: &str, to: &str) -> Result<()> {
+        (**self).copy(from, to)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        (**self).delete(key)
+    }
+
+    fn create_multipart_upload(&self, key: &str) -> Result<String> {
+        (**self).create_multipart_upload(key)
+    }
+
+    fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        number: u32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart> {
+        (**self).upload_part(key, upload_id, number, data)
+    }
+
+    fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+    ) -> Result<()> {
+        (**self).complete_multipart_upload(key, upload_id, parts)
+    }
+
+    fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        (**self).abort_multipart_upload(key, upload_id)
+    }
+}
+
+/// An object store held in memory, e.g. for testing the programs
+/// meant to run against a real object storage.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    objects: Mutex<BTreeMap<String, MemoryObject>>,
+    uploads: Mutex<HashMap<String, MemoryUpload>>,
+    next_upload_id: AtomicU64,
+}
+
+#[derive(Debug)]
+struct MemoryObject {
+    data: Vec<u8>,
+    modified: u64,
+}
+
+#[derive(Debug)]
+struct MemoryUpload {
+    key: String,
+    parts: BTreeMap<u32, Vec<u8>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let modified = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+
+        self.objects
+            .lock()
+            .map_err(|_| FsError::Lock)?
+            .insert(key.to_string(), MemoryObject { data, modified });
+
+        Ok(())
+    }
+}
+
+impl ObjectStore for MemoryStore {
+    fn head(&self, key: &str) -> Result<Option<ObjectMeta>> {
+        let objects = self.objects.lock().map_err(|_| FsError::Lock)?;
+
+        Ok(objects.get(key).map(|object| ObjectMeta {
+            key: key.to_string(),
+            size: object.data.len() as u64,
+            modified: object.modified,
+        }))
+    }
+
+    fn get_range(&self, key: &str, range: Range<u64>) -> Result<Vec<u8>> {
+        let objects = self.objects.lock().map_err(|_| FsError::Lock)?;
+        let data = &objects.get(key).ok_or(FsError::EntityNotFound)?.data;
+
+        let end = (range.end as usize).min(data.len());
+        let start = (range.start as usize).min(end);
+
+        Ok(data[start..end].to_vec())
+    }
+
+    fn list(&self, prefix: &str) -> Result<Listing> {
+        let objects = self.objects.lock().map_err(|_| FsError::Lock)?;
+        let mut listing = Listing::default();
+        let mut prefixes = BTreeSet::new();
+
+        for (key, object) in objects
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+        {
+            match key[prefix.len()..].find('/') {
+                Some(position) => {
+                    prefixes.insert(key[..prefix.len() + position + 1].to_string());
+                }
+                None => listing.objects.push(ObjectMeta {
+                    key: key.clone(),
+                    size: object.data.len() as u64,
+                    modified: object.modified,
+                }),
+            }
+        }
+        listing.prefixes = prefixes.into_iter().collect();
+
+        Ok(listing)
+    }
+
+    fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        self.insert(key, data)
+    }
+
+    fn copy(&self, from: &str, to: &str) -> Result<()> {
+        let data = {
+            let objects = self.objects.lock().map_err(|_| FsError::Lock)?;
+            objects
+                .get(from)
+                .ok_or(FsError::EntityNotFound)?
+                .data
+                .clone()
+        };
+
+        self.insert(to, data)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.objects.lock().map_err(|_| FsError::Lock)?.remove(key);
+
+        Ok(())
+    }
+
+    fn create_multipart_upload(&self, key: &str) -> Result<String> {
+        let upload_id = self
+            .next_upload_id
+            .fetch_add(1, Ordering::Relaxed)
+            .to_string();
+
+        self.uploads.lock().map_err(|_| FsError::Lock)?.insert(
+            upload_id.clone(),
+            MemoryUpload {
+                key: key.to_string(),
+                parts: BTreeMap::new(),
+            },
+        );
+
+        Ok(upload_id)
+    }
+
+    fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        number: u32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart> {
+        let mut uploads = self.uploads.lock().map_err(|_| FsError::Lock)?;
+        let upload = uploads
+            .get_mut(upload_id)
+            .filter(|upload| upload.key == key)
+            .ok_or(FsError::EntityNotFound)?;
+        upload.parts.insert(number, data);
+
+        Ok(CompletedPart {
+            number,
+            etag: format!("{}-{}", upload_id, number),
+        })
+    }
+
+    fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+    ) -> Result<()> {
+        let mut upload = {
+            let mut uploads = self.uploads.lock().map_err(|_| FsError::Lock)?;
+            match uploads.get(upload_id) {
+                Some(upload) if upload.key == key => uploads.remove(upload_id).unwrap(),
+                _ => return Err(FsError::EntityNotFound),
+            }
+        };
+
+        let mut data = Vec::new();
+        for part in parts {
+            data.extend(
+                upload
+                    .parts
+                    .remove(&part.number)
+                    .ok_or(FsError::InvalidInput)?,
+            );
+        }
+
+        self.insert(key, data)
+    }
+
+    fn abort_multipart_upload(&self, _key: &str, upload_id: &str) -> Result<()> {
+        self.uploads
+            .lock()
+            .map_err(|_| FsError::Lock)?
+            .remove(upload_id);
+
+        Ok(())
+    }
+}
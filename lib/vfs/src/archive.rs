@@ -0,0 +1,304 @@
+//! Building blocks shared by the read-only archive file systems,
+//! i.e. [`tar_fs`](crate::tar_fs) and [`zip_fs`](crate::zip_fs).
+//!
+//! An archive is indexed once, when it is mounted: the index maps
+//! every path to its metadata and to the location of its contents
+//! in the archive, so that lookups and directory listings never have
+//! to walk the archive again.
+
+use crate::overlay_fs::normalize;
+use crate::{
+    DirEntry, FileType, FsError, Metadata, OpenOptionsConfig, ReadDir, Result, VirtualFile,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsString;
+use std::io::{self, Read, Seek, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug)]
+enum NodeKind<L> {
+    Directory { children: BTreeSet<OsString> },
+    File { location: L },
+}
+
+#[derive(Debug)]
+struct Node<L> {
+    kind: NodeKind<L>,
+    metadata: Metadata,
+}
+
+/// The index of an archive, where `L` is the location of the
+/// contents of a file in the archive.
+#[derive(Debug)]
+pub(crate) struct Index<L> {
+    nodes: HashMap<PathBuf, Node<L>>,
+}
+
+impl<L> Index<L> {
+    pub(crate) fn new() -> Self {
+        let mut nodes = HashMap::new();
+        nodes.insert(PathBuf::from("/"), Self::directory(0));
+
+        Self { nodes }
+    }
+
+    fn directory(modified: u64) -> Node<L> {
+        Node {
+            kind: NodeKind::Directory {
+                children: BTreeSet::new(),
+            },
+            metadata: Metadata {
+                ft: FileType {
+                    dir: true,
+                    ..Default::default()
+                },
+                accessed: modified,
+                created: modified,
+                modified,
+                len: 0,
+            },
+        }
+    }
+
+    /// Adds `path` to the children of its parent, creating all the
+    /// missing ancestors on the way.
+    fn link(&mut self, path: &Path) -> Result<()> {
+        let (parent, name) = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => (parent, name),
+            // The root directory has no parent.
+            _ => return Ok(()),
+        };
+
+        if !self.nodes.contains_key(parent) {
+            self.nodes.insert(parent.to_path_buf(), Self::directory(0));
+            self.link(parent)?;
+        }
+
+        match self.nodes.get_mut(parent) {
+            Some(Node {
+                kind: NodeKind::Directory { children },
+                ..
+            }) => {
+                children.insert(name.to_os_string());
+
+                Ok(())
+            }
+            _ => Err(FsError::InvalidData),
+        }
+    }
+
+    /// Registers a directory. Directories are also implicitly created
+    /// for the ancestors of every registered path.
+    pub(crate) fn insert_directory(&mut self, path: &Path, modified: u64) -> Result<()> {
+        let path = normalize(path);
+
+        match self.nodes.get_mut(&path) {
+            Some(Node {
+                kind: NodeKind::Directory { .. },
+                metadata,
+            }) => {
+                metadata.accessed = modified;
+                metadata.created = modified;
+                metadata.modified = modified;
+
+                Ok(())
+            }
+            Some(_) => Err(FsError::InvalidData),
+            None => {
+                self.link(&path)?;
+                self.nodes.insert(path, Self::directory(modified));
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Registers a file of `len` bytes, whose contents are found at
+    /// `location`. A later entry for the same path replaces the
+    /// previous one, as it does when extracting the archive.
+    pub(crate) fn insert_file(
+        &mut self,
+        path: &Path,
+        len: u64,
+        modified: u64,
+        location: L,
+    ) -> Result<()> {
+        let path = normalize(path);
+
+        if let Some(Node {
+            kind: NodeKind::Directory { .. },
+            ..
+        }) = self.nodes.get(&path)
+        {
+            return Err(FsError::InvalidData);
+        }
+
+        self.link(&path)?;
+        self.nodes.insert(
+            path,
+            Node {
+                kind: NodeKind::File { location },
+                metadata: Metadata {
+                    ft: FileType {
+                        file: true,
+                        ..Default::default()
+                    },
+                    accessed: modified,
+                    created: modified,
+                    modified,
+                    len,
+                },
+            },
+        );
+
+        Ok(())
+    }
+
+    pub(crate) fn metadata(&self, path: &Path) -> Result<Metadata> {
+        self.nodes
+            .get(&normalize(path))
+            .map(|node| node.metadata.clone())
+            .ok_or(FsError::EntityNotFound)
+    }
+
+    pub(crate) fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        let normalized = normalize(path);
+
+        match self.nodes.get(&normalized) {
+            Some(Node {
+                kind: NodeKind::Directory { children },
+                ..
+            }) => Ok(ReadDir::new(
+                children
+                    .iter()
+                    .map(|name| DirEntry {
+                        path: path.join(name),
+                        metadata: self.metadata(&normalized.join(name)),
+                    })
+                    .collect(),
+            )),
+            Some(_) => Err(FsError::BaseNotDirectory),
+            None => Err(FsError::EntityNotFound),
+        }
+    }
+
+    /// Checks that `conf` doesn't require to modify the archive, and
+    /// returns the location and the metadata of the file at `path`.
+    pub(crate) fn open(&self, path: &Path, conf: &OpenOptionsConfig) -> Result<(&L, &Metadata)> {
+        if conf.write() || conf.append() || conf.truncate() {
+            return Err(FsError::PermissionDenied);
+        }
+
+        match self.nodes.get(&normalize(path)) {
+            Some(_) if conf.create_new() => Err(FsError::AlreadyExists),
+            Some(Node {
+                kind: NodeKind::File { location },
+                metadata,
+            }) => Ok((location, metadata)),
+            Some(_) => Err(FsError::NotAFile),
+            None if conf.create() || conf.create_new() => Err(FsError::PermissionDenied),
+            None => Err(FsError::EntityNotFound),
+        }
+    }
+}
+
+/// A read-only file of an archive file system.
+#[derive(Debug)]
+pub struct ArchiveFile {
+    data: Arc<[u8]>,
+    range: Range<usize>,
+    position: usize,
+    metadata: Metadata,
+}
+
+impl ArchiveFile {
+    /// Creates a file whose contents are `data[range]`.
+    pub(crate) fn new(data: Arc<[u8]>, range: Range<usize>, metadata: Metadata) -> Self {
+        Self {
+            data,
+            range,
+            position: 0,
+            metadata,
+        }
+    }
+
+    fn contents(&self) -> &[u8] {
+        &self.data[self.range.clone()]
+    }
+}
+
+impl VirtualFile for ArchiveFile {
+    fn last_accessed(&self) -> u64 {
+        self.metadata.accessed
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.metadata.modified
+    }
+
+    fn created_time(&self) -> u64 {
+        self.metadata.created
+    }
+
+    fn size(&self) -> u64 {
+        self.range.len() as u64
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn bytes_available(&self) -> Result<usize> {
+        Ok(self.range.len().saturating_sub(self.position))
+    }
+}
+
+impl Read for ArchiveFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let position = self.position;
+        let read = (&self.contents()[position.min(self.range.len())..]).read(buf)?;
+        self.position += read;
+
+        Ok(read)
+    }
+}
+
+impl Seek for ArchiveFile {
+    fn seek(&mut self, position: io::SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            io::SeekFrom::Start(offset) => offset as i64,
+            io::SeekFrom::End(offset) => self.range.len() as i64 + offset,
+            io::SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seeking before the beginning of the file",
+            ));
+        }
+
+        self.position = position as usize;
+
+        Ok(self.position as u64)
+    }
+}
+
+impl Write for ArchiveFile {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "archive files are read-only",
+        ))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
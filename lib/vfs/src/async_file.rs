@@ -0,0 +1,286 @@
+//! Asynchronous files, and adapters between them and [`VirtualFile`]s.
+//!
+//! [`SyncFile`] turns an [`AsyncVirtualFile`] into a [`VirtualFile`],
+//! so that it can be used wherever files are expected: the WASI
+//! syscalls drive its I/O with the executor of their runtime, instead
+//! of blocking the thread. [`AsyncFile`] goes the other way.
+
+use crate::{Result, VirtualFile};
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use tokio::io::{
+    AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt, ReadBuf,
+};
+
+/// A file whose reads, writes and seeks are asynchronous, e.g. a
+/// wrapper around a `tokio::fs::File`.
+///
+/// The metadata of the file is the same as the one of a [`VirtualFile`].
+pub trait AsyncVirtualFile:
+    fmt::Debug + AsyncRead + AsyncWrite + AsyncSeek + Send + Sync + Unpin + 'static
+{
+    /// the last time the file was accessed in nanoseconds as a UNIX timestamp
+    fn last_accessed(&self) -> u64;
+
+    /// the last time the file was modified in nanoseconds as a UNIX timestamp
+    fn last_modified(&self) -> u64;
+
+    /// the time at which the file was created in nanoseconds as a UNIX timestamp
+    fn created_time(&self) -> u64;
+
+    /// the size of the file in bytes
+    fn size(&self) -> u64;
+
+    /// Change the size of the file, if the `new_size` is greater than the current size
+    /// the extra bytes will be allocated and zeroed
+    fn set_len(&mut self, new_size: u64) -> Result<()>;
+
+    /// Request deletion of the file
+    fn unlink(&mut self) -> Result<()>;
+
+    /// Store file contents and metadata to disk
+    /// Default implementation returns `Ok(())`
+    fn sync_to_disk(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `future` to completion on the current thread, parking the thread
+/// while the future is pending.
+///
+/// Futures needing the context of a specific executor, like the ones of
+/// `tokio::fs`, must be run by that executor instead.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unparker(Thread);
+
+    impl Wake for Unparker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(Unparker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// An [`AsyncVirtualFile`] used as a [`VirtualFile`].
+///
+/// Used through [`Read`], [`Write`] and [`Seek`], the file blocks the
+/// current thread with [`block_on`]. Callers with their own executor get
+/// the asynchronous file back with [`VirtualFile::as_async_mut`].
+#[derive(Debug)]
+pub struct SyncFile<F: AsyncVirtualFile> {
+    inner: F,
+}
+
+impl<F: AsyncVirtualFile> SyncFile<F> {
+    /// Wraps `inner`.
+    pub fn new(inner: F) -> Self {
+        Self { inner }
+    }
+
+    /// Unwraps the asynchronous file.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: AsyncVirtualFile> Read for SyncFile<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        block_on(self.inner.read(buf))
+    }
+}
+
+impl<F: AsyncVirtualFile> Write for SyncFile<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        block_on(self.inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        block_on(self.inner.flush())
+    }
+}
+
+impl<F: AsyncVirtualFile> Seek for SyncFile<F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        block_on(self.inner.seek(pos))
+    }
+}
+
+impl<F: AsyncVirtualFile> VirtualFile for SyncFile<F> {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.inner.unlink()
+    }
+
+    fn sync_to_disk(&self) -> Result<()> {
+        self.inner.sync_to_disk()
+    }
+
+    fn as_async_mut(&mut self) -> Option<&mut dyn AsyncVirtualFile> {
+        Some(&mut self.inner)
+    }
+}
+
+/// A [`VirtualFile`] used as an [`AsyncVirtualFile`].
+///
+/// The I/O of the file is done synchronously when polled, which suits
+/// files that don't block, like the ones of the memory file system.
+#[derive(Debug)]
+pub struct AsyncFile {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+    seek: Option<io::Result<u64>>,
+}
+
+impl AsyncFile {
+    /// Wraps `inner`.
+    pub fn new(inner: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
+        Self { inner, seek: None }
+    }
+
+    /// Unwraps the file.
+    pub fn into_inner(self) -> Box<dyn VirtualFile + Send + Sync + 'static> {
+        self.inner
+    }
+}
+
+impl AsyncRead for AsyncFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let read = self.get_mut().inner.read(buf.initialize_unfilled())?;
+        buf.advance(read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for AsyncFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.get_mut().inner.write(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.get_mut().inner.flush())
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl AsyncSeek for AsyncFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        this.seek = Some(this.inner.seek(position));
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        Poll::Ready(match this.seek.take() {
+            Some(result) => result,
+            None => this.inner.stream_position(),
+        })
+    }
+}
+
+impl AsyncVirtualFile for AsyncFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.inner.unlink()
+    }
+
+    fn sync_to_disk(&self) -> Result<()> {
+        self.inner.sync_to_disk()
+    }
+}
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod test_adapters {
+    use super::*;
+    use crate::mem_fs;
+    use crate::FileSystem;
+    use std::path::Path;
+
+    #[test]
+    fn round_trip() {
+        let fs = mem_fs::FileSystem::default();
+        let file = fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(Path::new("/foo.txt"))
+            .unwrap();
+
+        let mut file = SyncFile::new(AsyncFile::new(file));
+        file.write_all(b"hello").unwrap();
+        assert_eq!(file.seek(SeekFrom::Start(1)).unwrap(), 1);
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "ello");
+        assert_eq!(file.size(), 5);
+
+        let inner = file.as_async_mut().unwrap();
+        assert_eq!(block_on(inner.seek(SeekFrom::Start(0))).unwrap(), 0);
+        let mut contents = Vec::new();
+        block_on(inner.read_to_end(&mut contents)).unwrap();
+        assert_eq!(contents, b"hello");
+    }
+}
@@ -0,0 +1,612 @@
+//! A read-only, content-addressed file system.
+//!
+//! The contents of the files are _blobs_ kept in a [`BlobStore`] under
+//! their BLAKE3 hash, their [`Digest`], so that identical files are
+//! stored once. The tree of the file system is described by a
+//! [`Manifest`], mapping every path to a directory or to the digest of
+//! a blob:
+//!
+//! ```text
+//! dir /etc
+//! file /etc/hosts 12 a3f5…
+//! ```
+//!
+//! Blobs are checked against their digest when they are read, so a
+//! corrupted or tampered store is detected, and the same manifest
+//! always gives the same tree.
+//!
+//! Manifests can be stacked as layers, e.g. a base image and the
+//! layers of the programs installed on top of it. An upper layer
+//! overrides the entries of the lower ones, or removes them with a
+//! `whiteout` entry. Layering only merges the manifests, the blobs
+//! being shared:
+//!
+//! ```rust,ignore
+//! use wasmer_vfs::cas_fs::{FileSystem, Manifest, MemoryBlobStore};
+//!
+//! let store = MemoryBlobStore::default();
+//! let mut base = Manifest::new();
+//! base.insert_file("/etc/hosts", store.insert(b"127.0.0.1 localhost"), 19);
+//!
+//! let fs = FileSystem::new(store, &base)?.layer(&"whiteout /etc/hosts".parse()?)?;
+//! ```
+
+use crate::archive::{ArchiveFile, Index};
+use crate::overlay_fs::normalize;
+use crate::{FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, Result, VirtualFile};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+/// The BLAKE3 hash of a blob.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Digest([u8; 32]);
+
+impl Digest {
+    /// Hashes `data`.
+    pub fn of(data: &[u8]) -> Self {
+        Self(blake3::hash(data).into())
+    }
+
+    /// The bytes of the hash.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "Digest({})", self)
+    }
+}
+
+impl FromStr for Digest {
+    type Err = FsError;
+
+    /// Parses the hexadecimal representation of a hash.
+    fn from_str(digest: &str) -> Result<Self> {
+        let bytes = hex::decode(digest).map_err(|_| FsError::InvalidInput)?;
+
+        Ok(Self(bytes.try_into().map_err(|_| FsError::InvalidInput)?))
+    }
+}
+
+/// The storage of the blobs of a [`FileSystem`].
+pub trait BlobStore: fmt::Debug + Send + Sync + 'static {
+    /// Returns the blob `digest`. The blob doesn't have to be checked
+    /// against its digest, the file system does it.
+    fn read(&self, digest: &Digest) -> Result<Vec<u8>>;
+}
+
+/// A store keeping the blobs in memory.
+#[derive(Debug, Default)]
+pub struct MemoryBlobStore {
+    blobs: RwLock<HashMap<Digest, Arc<[u8]>>>,
+}
+
+impl MemoryBlobStore {
+    /// Adds the blob `data`, returning its digest.
+    pub fn insert(&self, data: &[u8]) -> Digest {
+        let digest = Digest::of(data);
+        self.blobs
+            .write()
+            .unwrap()
+            .entry(digest)
+            .or_insert_with(|| data.into());
+
+        digest
+    }
+}
+
+impl BlobStore for MemoryBlobStore {
+    fn read(&self, digest: &Digest) -> Result<Vec<u8>> {
+        self.blobs
+            .read()
+            .map_err(|_| FsError::Lock)?
+            .get(digest)
+            .map(|blob| blob.to_vec())
+            .ok_or(FsError::EntityNotFound)
+    }
+}
+
+/// A store keeping every blob in a file of a host directory, named
+/// after the hexadecimal representation of its digest.
+#[derive(Debug, Clone)]
+pub struct DirectoryBlobStore {
+    root: PathBuf,
+}
+
+impl DirectoryBlobStore {
+    /// Creates a store keeping its blobs in `root`, which must exist.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Adds the blob `data`, returning its digest.
+    pub fn insert(&self, data: &[u8]) -> Result<Digest> {
+        let digest = Digest::of(data);
+        let path = self.root.join(digest.to_string());
+
+        if !path.exists() {
+            // Written aside then renamed, so that a blob is never seen
+            // partially written.
+            let partial = self.root.join(format!("{}.partial", digest));
+            fs::write(&partial, data)?;
+            fs::rename(&partial, &path)?;
+        }
+
+        Ok(digest)
+    }
+}
+
+impl BlobStore for DirectoryBlobStore {
+    fn read(&self, digest: &Digest) -> Result<Vec<u8>> {
+        Ok(fs::read(self.root.join(digest.to_string()))?)
+    }
+}
+
+/// An entry of a [`Manifest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestEntry {
+    /// A directory, possibly empty.
+    Directory,
+    File {
+        /// The digest of the contents of the file.
+        digest: Digest,
+        /// The size of the file in bytes.
+        size: u64,
+    },
+    /// Removes the entry, and its children, from the lower layers.
+    Whiteout,
+}
+
+/// The tree of a [`FileSystem`], or of one of its layers.
+///
+/// A manifest is written as text, with one entry per line:
+///
+/// * `dir <path>` for a directory,
+/// * `file <path> <size> <digest>` for a file,
+/// * `whiteout <path>` for an entry removed from the lower layers.
+///
+/// The paths are absolute and can't contain line breaks; the other
+/// spaces are kept. Empty lines and lines starting with `#` are
+/// ignored. The ancestors of the entries are implicitly directories.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    entries: BTreeMap<PathBuf, ManifestEntry>,
+}
+
+impl Manifest {
+    /// Creates an empty manifest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a directory.
+    pub fn insert_directory<P: AsRef<Path>>(&mut self, path: P) {
+        self.insert(path.as_ref(), ManifestEntry::Directory);
+    }
+
+    /// Adds a file, whose contents are the blob `digest` of `size`
+    /// bytes.
+    pub fn insert_file<P: AsRef<Path>>(&mut self, path: P, digest: Digest, size: u64) {
+        self.insert(path.as_ref(), ManifestEntry::File { digest, size });
+    }
+
+    /// Removes `path` from the lower layers.
+    pub fn insert_whiteout<P: AsRef<Path>>(&mut self, path: P) {
+        self.insert(path.as_ref(), ManifestEntry::Whiteout);
+    }
+
+    fn insert(&mut self, path: &Path, entry: ManifestEntry) {
+        self.entries.insert(normalize(path), entry);
+    }
+
+    /// The entries, sorted by path.
+    pub fn entries(&self) -> impl Iterator<Item = (&Path, &ManifestEntry)> {
+        self.entries
+            .iter()
+            .map(|(path, entry)| (path.as_path(), entry))
+    }
+
+    /// Stacks `upper` on top of `self`.
+    fn merge(&mut self, upper: &Manifest) {
+        for (path, entry) in &upper.entries {
+            // An entry replaces the files it's nested in, and the
+            // children of the directory it replaces.
+            for ancestor in path.ancestors().skip(1) {
+                if let Some(ManifestEntry::File { .. }) = self.entries.get(ancestor) {
+                    self.entries.remove(ancestor);
+                }
+            }
+            if !matches!(entry, ManifestEntry::Directory) {
+                // The lower layer may not list `path` itself, which is
+                // then only implied by its children.
+                let children = self
+                    .entries
+                    .range::<PathBuf, _>(path.clone()..)
+                    .take_while(|(child, _)| child.starts_with(path))
+                    .filter(|(child, _)| *child != path)
+                    .map(|(child, _)| child.clone())
+                    .collect::<Vec<_>>();
+                let existed = !children.is_empty() || self.entries.contains_key(path);
+                for child in children {
+                    self.entries.remove(&child);
+                }
+
+                // The parent of a removed entry may have been implied by
+                // it only, and stays.
+                if let (ManifestEntry::Whiteout, true, Some(parent)) =
+                    (entry, existed, path.parent())
+                {
+                    if parent.parent().is_some() && !self.entries.contains_key(parent) {
+                        self.entries
+                            .insert(parent.to_path_buf(), ManifestEntry::Directory);
+                    }
+                }
+            }
+
+            match entry {
+                ManifestEntry::Whiteout => {
+                    self.entries.remove(path);
+                }
+                entry => {
+                    self.entries.insert(path.clone(), *entry);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, entry) in &self.entries {
+            let path = path.display();
+
+            match entry {
+                ManifestEntry::Directory => writeln!(formatter, "dir {}", path)?,
+                ManifestEntry::File { digest, size } => {
+                    writeln!(formatter, "file {} {} {}", path, size, digest)?
+                }
+                ManifestEntry::Whiteout => writeln!(formatter, "whiteout {}", path)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = FsError;
+
+    fn from_str(manifest: &str) -> Result<Self> {
+        let mut parsed = Self::new();
+
+        for line in manifest.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (kind, rest) = line.split_once(' ').ok_or(FsError::InvalidData)?;
+            match kind {
+                "dir" => parsed.insert_directory(rest),
+                "whiteout" => parsed.insert_whiteout(rest),
+                "file" => {
+                    // The path comes first, but may contain spaces.
+                    let (rest, digest) = rest.rsplit_once(' ').ok_or(FsError::InvalidData)?;
+                    let (path, size) = rest.rsplit_once(' ').ok_or(FsError::InvalidData)?;
+                    let digest = digest.parse().map_err(|_| FsError::InvalidData)?;
+                    let size = size.parse().map_err(|_| FsError::InvalidData)?;
+
+                    parsed.insert_file(path, digest, size);
+                }
+                _ => return Err(FsError::InvalidData),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// A read-only file system whose tree is given by one or more
+/// [`Manifest`]s, see the module documentation to learn more.
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    inner: Arc<FileSystemInner>,
+}
+
+#[derive(Debug)]
+struct FileSystemInner {
+    blobs: Arc<Blobs>,
+    /// The layers merged together.
+    manifest: Manifest,
+    index: Index<Digest>,
+}
+
+/// The blobs of the file systems sharing a store.
+#[derive(Debug)]
+struct Blobs {
+    store: Box<dyn BlobStore>,
+    /// The blobs read and checked, kept while a file holds them, so
+    /// that the files with the same contents share them.
+    verified: Mutex<HashMap<Digest, Weak<[u8]>>>,
+}
+
+impl Blobs {
+    fn get(&self, digest: &Digest, size: u64) -> Result<Arc<[u8]>> {
+        if let Some(blob) = self
+            .verified
+            .lock()
+            .map_err(|_| FsError::Lock)?
+            .get(digest)
+            .and_then(Weak::upgrade)
+        {
+            return Ok(blob);
+        }
+
+        let blob = self.store.read(digest)?;
+        if blob.len() as u64 != size || Digest::of(&blob) != *digest {
+            tracing::warn!("the blob {} is corrupted", digest);
+
+            return Err(FsError::InvalidData);
+        }
+
+        let blob: Arc<[u8]> = blob.into();
+        let mut verified = self.verified.lock().map_err(|_| FsError::Lock)?;
+        verified.retain(|_, blob| blob.strong_count() > 0);
+        verified.insert(*digest, Arc::downgrade(&blob));
+
+        Ok(blob)
+    }
+}
+
+impl FileSystem {
+    /// Creates a file system whose tree is `manifest`, the contents of
+    /// its files being read from `store`.
+    pub fn new<S: BlobStore>(store: S, manifest: &Manifest) -> Result<Self> {
+        let blobs = Arc::new(Blobs {
+            store: Box::new(store),
+            verified: Mutex::new(HashMap::new()),
+        });
+
+        Self::with_manifest(blobs, manifest.clone())
+    }
+
+    /// Creates a file system stacking the layer `upper` on top of this
+    /// one, whose blobs must be in the same store. This file system is
+    /// left untouched.
+    pub fn layer(&self, upper: &Manifest) -> Result<Self> {
+        let mut manifest = self.inner.manifest.clone();
+        manifest.merge(upper);
+
+        Self::with_manifest(self.inner.blobs.clone(), manifest)
+    }
+
+    /// The manifest of the file system, all its layers merged.
+    pub fn manifest(&self) -> &Manifest {
+        &self.inner.manifest
+    }
+
+    fn with_manifest(blobs: Arc<Blobs>, mut manifest: Manifest) -> Result<Self> {
+        // A lone layer may hold whiteouts, meant for layers stacked
+        // later below it, which remove nothing.
+        manifest
+            .entries
+            .retain(|_, entry| !matches!(entry, ManifestEntry::Whiteout));
+
+        let mut index = Index::new();
+        for (path, entry) in manifest.entries() {
+            match entry {
+                ManifestEntry::Directory => index.insert_directory(path, 0)?,
+                ManifestEntry::File { digest, size } => {
+                    index.insert_file(path, *size, 0, *digest)?
+                }
+                ManifestEntry::Whiteout => {}
+            }
+        }
+
+        Ok(Self {
+            inner: Arc::new(FileSystemInner {
+                blobs,
+                manifest,
+                index,
+            }),
+        })
+    }
+}
+
+impl crate::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        self.inner.index.read_dir(path)
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        self.inner.index.metadata(path)
+    }
+
+    fn remove_file(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(FileOpener {
+            filesystem: self.clone(),
+        }))
+    }
+}
+
+/// The opener of files in a content-addressed [`FileSystem`].
+#[derive(Debug, Clone)]
+pub struct FileOpener {
+    filesystem: FileSystem,
+}
+
+impl crate::FileOpener for FileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let inner = &self.filesystem.inner;
+        let (digest, metadata) = inner.index.open(path, conf)?;
+
+        let blob = inner.blobs.get(digest, metadata.len)?;
+        let len = blob.len();
+
+        Ok(Box::new(ArchiveFile::new(blob, 0..len, metadata.clone())))
+    }
+}
+
+#[cfg(test)]
+mod test_filesystem {
+    use super::{Digest, FileSystem, Manifest, ManifestEntry, MemoryBlobStore};
+    use crate::{FileSystem as FS, FsError};
+    use std::io::Read;
+    use std::path::Path;
+
+    fn base() -> (MemoryBlobStore, Manifest) {
+        let store = MemoryBlobStore::default();
+        let mut manifest = Manifest::new();
+
+        let hello = store.insert(b"hello");
+        manifest.insert_directory("/tmp");
+        manifest.insert_file("/etc/motd", hello, 5);
+        manifest.insert_file("/usr/share/doc/readme", store.insert(b"doc"), 3);
+        // Deduplicated with `/etc/motd`.
+        manifest.insert_file("/etc/greeting", hello, 5);
+
+        (store, manifest)
+    }
+
+    fn read(fs: &FileSystem, path: &str) -> Result<String, FsError> {
+        let mut file = fs.new_open_options().read(true).open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        Ok(contents)
+    }
+
+    #[test]
+    fn test_manifest() {
+        let (_, manifest) = base();
+        let text = manifest.to_string();
+        assert!(text.contains(&format!("file /etc/motd 5 {}\n", Digest::of(b"hello"))));
+        assert_eq!(text.parse::<Manifest>().unwrap(), manifest);
+
+        let parsed = "# comment\n\ndir /a b\nwhiteout /c\n"
+            .parse::<Manifest>()
+            .unwrap();
+        assert_eq!(
+            parsed.entries().collect::<Vec<_>>(),
+            vec![
+                (Path::new("/a b"), &ManifestEntry::Directory),
+                (Path::new("/c"), &ManifestEntry::Whiteout)
+            ]
+        );
+        assert_eq!(
+            "file /a 3 not-a-digest".parse::<Manifest>(),
+            Err(FsError::InvalidData)
+        );
+    }
+
+    #[test]
+    fn test_read_only() {
+        let (store, manifest) = base();
+        let fs = FileSystem::new(store, &manifest).unwrap();
+
+        assert!(fs.metadata(Path::new("/usr/share")).unwrap().is_dir());
+        assert_eq!(fs.metadata(Path::new("/etc/motd")).unwrap().len(), 5);
+        assert_eq!(read(&fs, "/etc/greeting").unwrap(), "hello");
+
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open("/etc/motd")
+                .map(|_| ()),
+            Err(FsError::PermissionDenied)
+        );
+        assert_eq!(
+            fs.create_dir(Path::new("/var")),
+            Err(FsError::PermissionDenied)
+        );
+    }
+
+    #[test]
+    fn test_integrity() {
+        let (_, manifest) = base();
+        let store = MemoryBlobStore::default();
+
+        // The blob stored under the digest of `hello` isn't `hello`.
+        store
+            .blobs
+            .write()
+            .unwrap()
+            .insert(Digest::of(b"hello"), b"hellO".to_vec().into());
+
+        let fs = FileSystem::new(store, &manifest).unwrap();
+        assert_eq!(read(&fs, "/etc/motd"), Err(FsError::InvalidData));
+        assert_eq!(
+            read(&fs, "/usr/share/doc/readme"),
+            Err(FsError::EntityNotFound)
+        );
+    }
+
+    #[test]
+    fn test_layers() {
+        let (store, base) = base();
+        let mut upper = Manifest::new();
+        upper.insert_file("/etc/motd", store.insert(b"welcome"), 7);
+        upper.insert_whiteout("/usr/share");
+        upper.insert_file("/tmp/file", store.insert(b"new"), 3);
+
+        let lower = FileSystem::new(store, &base).unwrap();
+        let fs = lower.layer(&upper).unwrap();
+
+        assert_eq!(read(&fs, "/etc/motd").unwrap(), "welcome");
+        assert_eq!(read(&fs, "/etc/greeting").unwrap(), "hello");
+        assert_eq!(read(&fs, "/tmp/file").unwrap(), "new");
+        assert_eq!(
+            fs.metadata(Path::new("/usr/share/doc")).map(|_| ()),
+            Err(FsError::EntityNotFound)
+        );
+        assert!(fs.metadata(Path::new("/usr")).unwrap().is_dir());
+
+        // Removing the only descendants of a directory leaves it empty.
+        let mut upper = Manifest::new();
+        upper.insert_whiteout("/usr/share/doc/readme");
+        upper.insert_whiteout("/var/missing");
+        let fs = lower.layer(&upper).unwrap();
+        assert!(fs.metadata(Path::new("/usr/share/doc")).unwrap().is_dir());
+        assert_eq!(fs.read_dir(Path::new("/usr/share/doc")).unwrap().count(), 0);
+        assert_eq!(
+            fs.metadata(Path::new("/var")).map(|_| ()),
+            Err(FsError::EntityNotFound)
+        );
+
+        // The lower layer is left untouched.
+        assert_eq!(read(&lower, "/etc/motd").unwrap(), "hello");
+        assert!(lower.metadata(Path::new("/usr/share/doc")).is_ok());
+    }
+}
@@ -2,6 +2,10 @@ use crate::{
     DirEntry, FileDescriptor, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir,
     Result, VirtualFile,
 };
+#[cfg(feature = "host-fs-notify")]
+use crate::{FsEvent, FsEventKind, FsEventSink, FsWatch};
+#[cfg(feature = "host-fs-notify")]
+use notify::Watcher;
 #[cfg(feature = "enable-serde")]
 use serde::{de, Deserialize, Serialize};
 use std::convert::TryInto;
@@ -113,8 +117,58 @@ impl crate::FileSystem for FileSystem {
             .and_then(TryInto::try_into)
             .map_err(Into::into)
     }
+
+    #[cfg(feature = "host-fs-notify")]
+    fn watch(&self, path: &Path, sink: FsEventSink) -> Result<Box<dyn FsWatch>> {
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(error) => {
+                        debug!("failed to watch a path: {}", error);
+                        return;
+                    }
+                };
+
+                let kind = match event.kind {
+                    notify::EventKind::Create(_) => FsEventKind::Create,
+                    notify::EventKind::Modify(notify::event::ModifyKind::Name(
+                        notify::event::RenameMode::From,
+                    ))
+                    | notify::EventKind::Remove(_) => FsEventKind::Remove,
+                    notify::EventKind::Modify(notify::event::ModifyKind::Name(
+                        notify::event::RenameMode::To,
+                    )) => FsEventKind::Create,
+                    notify::EventKind::Modify(_) => FsEventKind::Modify,
+                    _ => return,
+                };
+
+                for path in event.paths {
+                    sink(FsEvent { kind, path });
+                }
+            })
+            .map_err(|_| FsError::UnknownError)?;
+
+        watcher
+            .watch(path, notify::RecursiveMode::NonRecursive)
+            .map_err(|error| match error.kind {
+                notify::ErrorKind::PathNotFound => FsError::EntityNotFound,
+                notify::ErrorKind::Io(error) => error.into(),
+                _ => FsError::UnknownError,
+            })?;
+
+        Ok(Box::new(HostWatch(watcher)))
+    }
 }
 
+/// Keeps a path of the host watched with the `notify` crate.
+#[cfg(feature = "host-fs-notify")]
+#[derive(Debug)]
+struct HostWatch(notify::RecommendedWatcher);
+
+#[cfg(feature = "host-fs-notify")]
+impl FsWatch for HostWatch {}
+
 impl TryInto<Metadata> for fs::Metadata {
     type Error = io::Error;
 
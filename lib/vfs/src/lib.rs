@@ -11,10 +11,47 @@ compile_error!("At least the `host-fs` or the `mem-fs` feature must be enabled.
 #[cfg(all(feature = "mem-fs", feature = "enable-serde"))]
 compile_error!("`mem-fs` does not support `enable-serde` for the moment.");
 
+#[cfg(all(
+    any(feature = "tar-fs", feature = "zip-fs", feature = "cas-fs"),
+    feature = "enable-serde"
+))]
+compile_error!("`tar-fs`, `zip-fs` and `cas-fs` do not support `enable-serde` for the moment.");
+
+#[cfg(all(feature = "async", feature = "enable-serde"))]
+compile_error!("`async` does not support `enable-serde` for the moment.");
+
+#[cfg(feature = "cas-fs")]
+pub mod cas_fs;
 #[cfg(feature = "host-fs")]
 pub mod host_fs;
 #[cfg(feature = "mem-fs")]
 pub mod mem_fs;
+pub mod overlay_fs;
+#[cfg(feature = "tar-fs")]
+pub mod tar_fs;
+#[cfg(all(feature = "host-fs-uring", target_os = "linux"))]
+pub mod uring_fs;
+mod watch;
+#[cfg(feature = "zip-fs")]
+pub mod zip_fs;
+
+#[cfg(any(feature = "tar-fs", feature = "zip-fs", feature = "cas-fs"))]
+mod archive;
+#[cfg(feature = "async")]
+mod async_file;
+#[cfg(any(feature = "tar-fs", feature = "zip-fs", feature = "cas-fs"))]
+pub use archive::ArchiveFile;
+#[cfg(feature = "async")]
+pub use async_file::{block_on, AsyncFile, AsyncVirtualFile, SyncFile};
+#[cfg(feature = "cas-fs")]
+pub use cas_fs::FileSystem as CasFs;
+#[cfg(feature = "tar-fs")]
+pub use tar_fs::FileSystem as TarFs;
+#[cfg(all(feature = "host-fs-uring", target_os = "linux"))]
+pub use uring_fs::FileSystem as UringFs;
+pub use watch::{FsEvent, FsEventKind, FsEventSink, FsWatch};
+#[cfg(feature = "zip-fs")]
+pub use zip_fs::FileSystem as ZipFs;
 
 pub type Result<T> = std::result::Result<T, FsError>;
 
@@ -49,6 +86,16 @@ pub trait FileSystem: fmt::Debug + Send + Sync + 'static + Upcastable {
     fn remove_file(&self, path: &Path) -> Result<()>;
 
     fn new_open_options(&self) -> OpenOptions;
+
+    /// Reports the changes of `path`, and of its entries if it's a
+    /// directory, to `sink` until the returned [`FsWatch`] is dropped.
+    ///
+    /// The file systems which can't be watched return
+    /// `FsError::UnknownError`, as the host one does without the
+    /// `host-fs-notify` feature.
+    fn watch(&self, _path: &Path, _sink: FsEventSink) -> Result<Box<dyn FsWatch>> {
+        Err(FsError::UnknownError)
+    }
 }
 
 impl dyn FileSystem + 'static {
@@ -228,6 +275,14 @@ pub trait VirtualFile: fmt::Debug + Write + Read + Seek + Upcastable {
     fn get_fd(&self) -> Option<FileDescriptor> {
         None
     }
+
+    /// Returns the file as an asynchronous file, for the callers able to
+    /// drive its I/O with their own executor rather than blocking on it.
+    /// Defaults to `None`, the file being synchronous
+    #[cfg(feature = "async")]
+    fn as_async_mut(&mut self) -> Option<&mut dyn AsyncVirtualFile> {
+        None
+    }
 }
 
 // Implementation of `Upcastable` taken from https://users.rust-lang.org/t/why-does-downcasting-not-work-for-subtraits/33286/7 .
@@ -264,6 +319,9 @@ pub enum StdioMode {
     Null,
     /// Stdio will be sent to the log handler
     Log,
+    /// Stdio will be redirected to a file, whose path is configured
+    /// alongside the mode
+    File,
 }
 
 /// Error type for external users
@@ -3,7 +3,7 @@
 //! `FileHandle` can be used through the `VirtualFile` trait object.
 
 use super::*;
-use crate::{FileDescriptor, FsError, Result, VirtualFile};
+use crate::{FileDescriptor, FsError, FsEventKind, Result, VirtualFile};
 use std::cmp;
 use std::convert::TryInto;
 use std::fmt;
@@ -114,6 +114,10 @@ impl VirtualFile for FileHandle {
             }
             _ => return Err(FsError::NotAFile),
         }
+        drop(fs);
+
+        self.filesystem
+            .notify_inode(FsEventKind::Modify, self.inode);
 
         Ok(())
     }
@@ -152,6 +156,7 @@ impl VirtualFile for FileHandle {
 
             (inode_of_parent, position, inode_of_file)
         };
+        let path_of_file = self.filesystem.path_of_watched_inode(inode_of_file);
 
         {
             // Write lock.
@@ -168,6 +173,12 @@ impl VirtualFile for FileHandle {
             fs.remove_child_from_node(inode_of_parent, position)?;
         }
 
+        if let Some(path_of_file) = path_of_file {
+            self.filesystem
+                .watches
+                .notify(FsEventKind::Remove, &path_of_file);
+        }
+
         Ok(())
     }
 
@@ -576,6 +587,10 @@ impl Write for FileHandle {
         let bytes_written = file.write(buf)?;
 
         metadata.len = file.len().try_into().unwrap();
+        drop(fs);
+
+        self.filesystem
+            .notify_inode(FsEventKind::Modify, self.inode);
 
         Ok(bytes_written)
     }
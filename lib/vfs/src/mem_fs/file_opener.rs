@@ -1,5 +1,5 @@
 use super::*;
-use crate::{FileType, FsError, Metadata, OpenOptionsConfig, Result, VirtualFile};
+use crate::{FileType, FsError, FsEventKind, Metadata, OpenOptionsConfig, Result, VirtualFile};
 use std::io::{self, Seek};
 use std::path::Path;
 
@@ -67,7 +67,7 @@ impl crate::FileOpener for FileOpener {
             (inode_of_parent, maybe_inode_of_file, name_of_file)
         };
 
-        let inode_of_file = match maybe_inode_of_file {
+        let (inode_of_file, event) = match maybe_inode_of_file {
             // The file already exists, and a _new_ one _must_ be
             // created; it's not OK.
             Some(_inode_of_file) if create_new => return Err(FsError::AlreadyExists),
@@ -105,7 +105,7 @@ impl crate::FileOpener for FileOpener {
                     _ => return Err(FsError::NotAFile),
                 }
 
-                inode_of_file
+                (inode_of_file, truncate.then(|| FsEventKind::Modify))
             }
 
             // The file doesn't already exist; it's OK to create it if:
@@ -151,12 +151,16 @@ impl crate::FileOpener for FileOpener {
                 // Adding the new directory to its parent.
                 fs.add_child_to_node(inode_of_parent, inode_of_file)?;
 
-                inode_of_file
+                (inode_of_file, Some(FsEventKind::Create))
             }
 
             None => return Err(FsError::PermissionDenied),
         };
 
+        if let Some(kind) = event {
+            self.filesystem.notify_inode(kind, inode_of_file);
+        }
+
         Ok(Box::new(FileHandle::new(
             inode_of_file,
             self.filesystem.clone(),
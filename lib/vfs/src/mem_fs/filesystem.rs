@@ -1,7 +1,11 @@
 //! This module contains the [`FileSystem`] type itself.
 
 use super::*;
-use crate::{DirEntry, FileType, FsError, Metadata, OpenOptions, ReadDir, Result};
+use crate::watch::Watches;
+use crate::{
+    DirEntry, FileType, FsError, FsEventKind, FsEventSink, FsWatch, Metadata, OpenOptions, ReadDir,
+    Result,
+};
 use slab::Slab;
 use std::convert::identity;
 use std::ffi::OsString;
@@ -17,6 +21,25 @@ use std::sync::{Arc, RwLock};
 #[derive(Clone, Default)]
 pub struct FileSystem {
     pub(super) inner: Arc<RwLock<FileSystemInner>>,
+    pub(super) watches: Watches,
+}
+
+impl FileSystem {
+    /// Get the path of an inode, if the file system is watched.
+    pub(super) fn path_of_watched_inode(&self, inode: Inode) -> Option<PathBuf> {
+        if self.watches.is_empty() {
+            return None;
+        }
+
+        self.inner.try_read().ok()?.path_of(inode)
+    }
+
+    /// Report a change of an inode to the watches.
+    pub(super) fn notify_inode(&self, kind: FsEventKind, inode: Inode) {
+        if let Some(path) = self.path_of_watched_inode(inode) {
+            self.watches.notify(kind, &path);
+        }
+    }
 }
 
 impl crate::FileSystem for FileSystem {
@@ -50,7 +73,7 @@ impl crate::FileSystem for FileSystem {
     }
 
     fn create_dir(&self, path: &Path) -> Result<()> {
-        let (inode_of_parent, name_of_directory) = {
+        let (inode_of_parent, name_of_directory, path) = {
             // Read lock.
             let fs = self.inner.try_read().map_err(|_| FsError::Lock)?;
 
@@ -70,7 +93,7 @@ impl crate::FileSystem for FileSystem {
             // Find the parent inode.
             let inode_of_parent = fs.inode_of_parent(parent_of_path)?;
 
-            (inode_of_parent, name_of_directory)
+            (inode_of_parent, name_of_directory, path)
         };
 
         {
@@ -108,11 +131,13 @@ impl crate::FileSystem for FileSystem {
             fs.add_child_to_node(inode_of_parent, inode_of_directory)?;
         }
 
+        self.watches.notify(FsEventKind::Create, &path);
+
         Ok(())
     }
 
     fn remove_dir(&self, path: &Path) -> Result<()> {
-        let (inode_of_parent, position, inode_of_directory) = {
+        let (inode_of_parent, position, inode_of_directory, path) = {
             // Read lock.
             let fs = self.inner.try_read().map_err(|_| FsError::Lock)?;
 
@@ -140,7 +165,7 @@ impl crate::FileSystem for FileSystem {
                     DirectoryMustBeEmpty::Yes,
                 )?;
 
-            (inode_of_parent, position, inode_of_directory)
+            (inode_of_parent, position, inode_of_directory, path)
         };
 
         {
@@ -154,11 +179,17 @@ impl crate::FileSystem for FileSystem {
             fs.remove_child_from_node(inode_of_parent, position)?;
         }
 
+        self.watches.notify(FsEventKind::Remove, &path);
+
         Ok(())
     }
 
     fn rename(&self, from: &Path, to: &Path) -> Result<()> {
-        let ((position_of_from, inode, inode_of_from_parent), (inode_of_to_parent, name_of_to)) = {
+        let (
+            (position_of_from, inode, inode_of_from_parent),
+            (inode_of_to_parent, name_of_to),
+            (from, to),
+        ) = {
             // Read lock.
             let fs = self.inner.try_read().map_err(|_| FsError::Lock)?;
 
@@ -189,6 +220,7 @@ impl crate::FileSystem for FileSystem {
             (
                 (position_of_from, inode, inode_of_from_parent),
                 (inode_of_to_parent, name_of_to),
+                (from, to),
             )
         };
 
@@ -221,6 +253,9 @@ impl crate::FileSystem for FileSystem {
             }
         }
 
+        self.watches.notify(FsEventKind::Remove, &from);
+        self.watches.notify(FsEventKind::Create, &to);
+
         Ok(())
     }
 
@@ -237,7 +272,7 @@ impl crate::FileSystem for FileSystem {
     }
 
     fn remove_file(&self, path: &Path) -> Result<()> {
-        let (inode_of_parent, position, inode_of_file) = {
+        let (inode_of_parent, position, inode_of_file, path) = {
             // Read lock.
             let fs = self.inner.try_read().map_err(|_| FsError::Lock)?;
 
@@ -261,7 +296,7 @@ impl crate::FileSystem for FileSystem {
                 fs.from_parent_get_position_and_inode_of_file(inode_of_parent, &name_of_file)?;
 
             match maybe_position_and_inode_of_file {
-                Some((position, inode_of_file)) => (inode_of_parent, position, inode_of_file),
+                Some((position, inode_of_file)) => (inode_of_parent, position, inode_of_file, path),
                 None => return Err(FsError::NotAFile),
             }
         };
@@ -277,6 +312,8 @@ impl crate::FileSystem for FileSystem {
             fs.remove_child_from_node(inode_of_parent, position)?;
         }
 
+        self.watches.notify(FsEventKind::Remove, &path);
+
         Ok(())
     }
 
@@ -285,6 +322,16 @@ impl crate::FileSystem for FileSystem {
             filesystem: self.clone(),
         }))
     }
+
+    fn watch(&self, path: &Path, sink: FsEventSink) -> Result<Box<dyn FsWatch>> {
+        // Read lock.
+        let fs = self.inner.try_read().map_err(|_| FsError::Lock)?;
+
+        // Canonicalize the path, which must exist.
+        let (path, _) = fs.canonicalize(path)?;
+
+        Ok(self.watches.add(path, sink))
+    }
 }
 
 impl fmt::Debug for FileSystem {
@@ -302,6 +349,40 @@ pub(super) struct FileSystemInner {
 }
 
 impl FileSystemInner {
+    /// Get the canonical path of an inode, by looking for it from the
+    /// root.
+    pub(super) fn path_of(&self, inode: Inode) -> Option<PathBuf> {
+        fn find(fs: &FileSystemInner, current: Inode, inode: Inode, path: &mut PathBuf) -> bool {
+            if current == inode {
+                return true;
+            }
+
+            if let Some(Node::Directory { children, .. }) = fs.storage.get(current) {
+                for child in children {
+                    if let Some(node) = fs.storage.get(*child) {
+                        path.push(node.name());
+
+                        if find(fs, *child, inode, path) {
+                            return true;
+                        }
+
+                        path.pop();
+                    }
+                }
+            }
+
+            false
+        }
+
+        let mut path = PathBuf::from("/");
+
+        if find(self, ROOT_INODE, inode, &mut path) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
     /// Get the inode associated to a path if it exists.
     pub(super) fn inode_of(&self, path: &Path) -> Result<Inode> {
         // SAFETY: The root node always exists, so it's safe to unwrap here.
@@ -1284,6 +1365,80 @@ mod test_filesystem {
         assert!(matches!(readdir.next(), None), "no more entries");
     }
 
+    #[test]
+    fn test_watch() {
+        use crate::{FsEvent, FsEventKind};
+        use std::io::Write;
+        use std::sync::{Arc, Mutex};
+
+        let fs = FileSystem::default();
+
+        assert_eq!(fs.create_dir(path!("/foo")), Ok(()));
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let watch = {
+            let events = events.clone();
+
+            fs.watch(
+                path!("/foo"),
+                Arc::new(move |event| events.lock().unwrap().push(event)),
+            )
+        };
+        assert!(watch.is_ok(), "watching `/foo`");
+
+        assert!(
+            matches!(
+                fs.watch(path!("/bar"), Arc::new(|_| {})),
+                Err(FsError::NotAFile)
+            ),
+            "watching a path which doesn't exist",
+        );
+
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/foo/hello.txt"))
+            .unwrap();
+        assert!(file.write_all(b"hello").is_ok(), "writing to a file");
+        assert_eq!(
+            fs.rename(path!("/foo/hello.txt"), path!("/foo/world.txt")),
+            Ok(())
+        );
+        assert_eq!(fs.create_dir(path!("/foo/bar")), Ok(()));
+        assert_eq!(fs.remove_dir(path!("/foo/bar")), Ok(()));
+        assert_eq!(fs.remove_file(path!("/foo/world.txt")), Ok(()));
+
+        // Not in `/foo`.
+        assert_eq!(fs.create_dir(path!("/baz")), Ok(()));
+
+        let event = |kind, path| FsEvent {
+            kind,
+            path: path!(buf path),
+        };
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                event(FsEventKind::Create, "/foo/hello.txt"),
+                event(FsEventKind::Modify, "/foo/hello.txt"),
+                event(FsEventKind::Remove, "/foo/hello.txt"),
+                event(FsEventKind::Create, "/foo/world.txt"),
+                event(FsEventKind::Create, "/foo/bar"),
+                event(FsEventKind::Remove, "/foo/bar"),
+                event(FsEventKind::Remove, "/foo/world.txt"),
+            ],
+            "the changes of `/foo` are reported",
+        );
+
+        drop(watch);
+        assert_eq!(fs.create_dir(path!("/foo/qux")), Ok(()));
+        assert_eq!(
+            events.lock().unwrap().len(),
+            7,
+            "the changes aren't reported once the watch is dropped",
+        );
+    }
+
     #[test]
     fn test_canonicalize() {
         let fs = FileSystem::default();
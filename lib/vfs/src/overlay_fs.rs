@@ -0,0 +1,585 @@
+//! An overlay (or union) file system.
+//!
+//! An overlay file system stacks a writable _upper_ file system on top
+//! of a _lower_ file system that is never modified. Reads fall through
+//! to the lower layer unless the upper layer contains the same path,
+//! while every modification happens in the upper layer. Files are
+//! copied up from the lower layer the first time they are opened for
+//! writing, and removed entries are hidden behind _whiteouts_.
+//!
+//! A typical setup is an in-memory file system layered over the host
+//! file system, so that a program sees the host files but cannot
+//! modify them:
+//!
+//! ```rust,ignore
+//! use wasmer_vfs::{host_fs, mem_fs, overlay_fs};
+//!
+//! let fs = overlay_fs::FileSystem::new(
+//!     Box::new(mem_fs::FileSystem::default()),
+//!     Box::new(host_fs::FileSystem::default()),
+//! );
+//! ```
+
+use crate::{
+    DirEntry, FsError, FsEventSink, FsWatch, Metadata, OpenOptions, OpenOptionsConfig, ReadDir,
+    Result, VirtualFile,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// An overlay file system, see the module documentation to learn more.
+#[derive(Clone)]
+pub struct FileSystem {
+    inner: Arc<FileSystemInner>,
+}
+
+struct FileSystemInner {
+    upper: Box<dyn crate::FileSystem>,
+    lower: Box<dyn crate::FileSystem>,
+    whiteouts: RwLock<HashSet<PathBuf>>,
+}
+
+impl FileSystem {
+    /// Creates a new overlay file system where `upper` receives all
+    /// the modifications and `lower` is only ever read.
+    ///
+    /// Paths are forwarded as is to the lower layer, and are made
+    /// absolute before being forwarded to the upper layer.
+    pub fn new(upper: Box<dyn crate::FileSystem>, lower: Box<dyn crate::FileSystem>) -> Self {
+        Self {
+            inner: Arc::new(FileSystemInner {
+                upper,
+                lower,
+                whiteouts: RwLock::new(HashSet::new()),
+            }),
+        }
+    }
+
+    /// The writable upper layer.
+    pub fn upper(&self) -> &dyn crate::FileSystem {
+        self.inner.upper.as_ref()
+    }
+
+    /// The read-only lower layer.
+    pub fn lower(&self) -> &dyn crate::FileSystem {
+        self.inner.lower.as_ref()
+    }
+}
+
+impl FileSystemInner {
+    /// Whether `path` in the lower layer is hidden neither by itself
+    /// nor by one of its ancestors.
+    fn is_lower_visible(&self, path: &Path) -> Result<bool> {
+        let whiteouts = self.whiteouts.read().map_err(|_| FsError::Lock)?;
+        let path = normalize(path);
+
+        Ok(!path
+            .ancestors()
+            .any(|ancestor| whiteouts.contains(ancestor)))
+    }
+
+    fn whiteout(&self, path: &Path) -> Result<()> {
+        self.whiteouts
+            .write()
+            .map_err(|_| FsError::Lock)?
+            .insert(normalize(path));
+
+        Ok(())
+    }
+
+    fn upper_metadata(&self, path: &Path) -> Option<Metadata> {
+        self.upper.metadata(&normalize(path)).ok()
+    }
+
+    fn lower_metadata(&self, path: &Path) -> Result<Option<Metadata>> {
+        if !self.is_lower_visible(path)? {
+            return Ok(None);
+        }
+
+        Ok(self.lower.metadata(path).ok())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        if let Some(metadata) = self.upper_metadata(path) {
+            return Ok(metadata);
+        }
+
+        self.lower_metadata(path)?.ok_or(FsError::EntityNotFound)
+    }
+
+    /// Makes sure the directory `path`, and all its ancestors, exist
+    /// in the upper layer.
+    fn copy_up_dirs(&self, path: &Path) -> Result<()> {
+        let path = normalize(path);
+        let mut current = PathBuf::from("/");
+
+        for component in path.components().skip(1) {
+            current.push(component);
+
+            match self.upper.metadata(&current) {
+                Ok(metadata) if metadata.is_dir() => continue,
+                Ok(_) => return Err(FsError::BaseNotDirectory),
+                Err(_) => self.upper.create_dir(&current)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies the file or directory `path` from the lower layer into
+    /// the upper layer, unless it is already there.
+    fn copy_up(&self, path: &Path) -> Result<()> {
+        if self.upper_metadata(path).is_some() {
+            return Ok(());
+        }
+
+        let metadata = self.lower_metadata(path)?.ok_or(FsError::EntityNotFound)?;
+
+        if let Some(parent) = path.parent() {
+            self.copy_up_dirs(parent)?;
+        }
+
+        if metadata.is_dir() {
+            self.upper.create_dir(&normalize(path))?;
+
+            for entry in self.lower.read_dir(path)? {
+                let entry = entry?;
+                let child = path.join(entry.file_name());
+
+                if self.is_lower_visible(&child)? {
+                    self.copy_up(&child)?;
+                }
+            }
+        } else {
+            let mut source = self.lower.new_open_options().read(true).open(path)?;
+            let mut destination = self
+                .upper
+                .new_open_options()
+                .write(true)
+                .create_new(true)
+                .open(normalize(path))?;
+
+            io::copy(&mut source, &mut destination)?;
+        }
+
+        Ok(())
+    }
+
+    fn ensure_parent_exists(&self, path: &Path) -> Result<()> {
+        match path.parent() {
+            Some(parent) if parent != Path::new("") => {
+                if self.metadata(parent)?.is_dir() {
+                    Ok(())
+                } else {
+                    Err(FsError::BaseNotDirectory)
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for FileSystem {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("FileSystem")
+            .field("upper", &self.inner.upper)
+            .field("lower", &self.inner.lower)
+            .field("whiteouts", &self.inner.whiteouts)
+            .finish()
+    }
+}
+
+impl crate::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        let inner = &self.inner;
+        let mut entries = BTreeMap::new();
+        let mut found = false;
+
+        if matches!(inner.lower_metadata(path)?, Some(metadata) if metadata.is_dir()) {
+            found = true;
+
+            for entry in inner.lower.read_dir(path)? {
+                let entry = entry?;
+                let name = entry.file_name();
+
+                if inner.is_lower_visible(&path.join(&name))? {
+                    entries.insert(name, entry);
+                }
+            }
+        }
+
+        if matches!(inner.upper_metadata(path), Some(metadata) if metadata.is_dir()) {
+            found = true;
+
+            for entry in inner.upper.read_dir(&normalize(path))? {
+                let entry = entry?;
+                entries.insert(entry.file_name(), entry);
+            }
+        }
+
+        if !found {
+            return Err(FsError::EntityNotFound);
+        }
+
+        Ok(ReadDir::new(
+            entries
+                .into_iter()
+                .map(|(name, entry)| DirEntry {
+                    path: path.join(name),
+                    metadata: entry.metadata,
+                })
+                .collect(),
+        ))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        let inner = &self.inner;
+
+        if inner.metadata(path).is_ok() {
+            return Err(FsError::AlreadyExists);
+        }
+
+        inner.ensure_parent_exists(path)?;
+
+        if let Some(parent) = path.parent() {
+            inner.copy_up_dirs(parent)?;
+        }
+
+        inner.upper.create_dir(&normalize(path))?;
+
+        // A directory with the same name may have been removed from
+        // the lower layer: it must not shine through the new one.
+        if inner.lower.metadata(path).is_ok() {
+            inner.whiteout(path)?;
+        }
+
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        let inner = &self.inner;
+
+        if !inner.metadata(path)?.is_dir() {
+            return Err(FsError::BaseNotDirectory);
+        }
+
+        if self.read_dir(path)?.next().is_some() {
+            return Err(FsError::DirectoryNotEmpty);
+        }
+
+        if inner.upper_metadata(path).is_some() {
+            inner.upper.remove_dir(&normalize(path))?;
+        }
+
+        if inner.lower_metadata(path)?.is_some() {
+            inner.whiteout(path)?;
+        }
+
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let inner = &self.inner;
+
+        inner.metadata(from)?;
+        inner.ensure_parent_exists(to)?;
+        inner.copy_up(from)?;
+
+        if let Some(parent) = to.parent() {
+            inner.copy_up_dirs(parent)?;
+        }
+
+        inner.upper.rename(&normalize(from), &normalize(to))?;
+
+        if inner.lower.metadata(to).is_ok() {
+            inner.whiteout(to)?;
+        }
+
+        if inner.lower.metadata(from).is_ok() {
+            inner.whiteout(from)?;
+        }
+
+        Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        self.inner.metadata(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        let inner = &self.inner;
+
+        if !inner.metadata(path)?.is_file() {
+            return Err(FsError::NotAFile);
+        }
+
+        if inner.upper_metadata(path).is_some() {
+            inner.upper.remove_file(&normalize(path))?;
+        }
+
+        if inner.lower_metadata(path)?.is_some() {
+            inner.whiteout(path)?;
+        }
+
+        Ok(())
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(FileOpener {
+            filesystem: self.clone(),
+        }))
+    }
+
+    fn watch(&self, path: &Path, sink: FsEventSink) -> Result<Box<dyn FsWatch>> {
+        let inner = &self.inner;
+
+        // Every change is made in the upper layer, so it's the only
+        // one to watch, once the path exists there.
+        if inner.metadata(path)?.is_dir() {
+            inner.copy_up_dirs(path)?;
+        } else {
+            inner.copy_up(path)?;
+        }
+
+        inner.upper.watch(&normalize(path), sink)
+    }
+}
+
+/// The opener of files in an overlay [`FileSystem`].
+#[derive(Clone)]
+pub struct FileOpener {
+    filesystem: FileSystem,
+}
+
+impl crate::FileOpener for FileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let inner = &self.filesystem.inner;
+        let modifies = conf.write() || conf.append() || conf.truncate();
+
+        if !modifies && !conf.create() && !conf.create_new() {
+            if inner.upper_metadata(path).is_some() {
+                return inner
+                    .upper
+                    .new_open_options()
+                    .options(conf.clone())
+                    .open(normalize(path));
+            }
+
+            if inner.lower_metadata(path)?.is_some() {
+                return inner
+                    .lower
+                    .new_open_options()
+                    .options(conf.clone())
+                    .open(path);
+            }
+
+            return Err(FsError::EntityNotFound);
+        }
+
+        match inner.metadata(path) {
+            Ok(_) if conf.create_new() => return Err(FsError::AlreadyExists),
+            Ok(metadata) if metadata.is_dir() => return Err(FsError::NotAFile),
+            Ok(_) => {
+                if let Some(parent) = path.parent() {
+                    inner.copy_up_dirs(parent)?;
+                }
+
+                // There is no point copying data that is about to be
+                // truncated.
+                if !conf.truncate() {
+                    inner.copy_up(path)?;
+                }
+            }
+            Err(_) if conf.create() || conf.create_new() => {
+                inner.ensure_parent_exists(path)?;
+
+                if let Some(parent) = path.parent() {
+                    inner.copy_up_dirs(parent)?;
+                }
+            }
+            Err(error) => return Err(error),
+        }
+
+        let mut options = conf.clone();
+        options.create = true;
+        options.create_new = false;
+
+        let file = inner
+            .upper
+            .new_open_options()
+            .options(options)
+            .open(normalize(path))?;
+
+        if inner.lower.metadata(path).is_ok() {
+            inner.whiteout(path)?;
+        }
+
+        Ok(file)
+    }
+}
+
+/// Makes `path` absolute and lexically removes `.` and `..`
+/// components, which is how paths are handed to the upper layer and
+/// keyed in the whiteouts set.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+
+    normalized
+}
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod test_filesystem {
+    use super::FileSystem;
+    use crate::{mem_fs, FileSystem as FS, FsError};
+    use std::io::{Read, Write};
+    use std::path::Path;
+
+    fn lower() -> mem_fs::FileSystem {
+        let fs = mem_fs::FileSystem::default();
+
+        fs.create_dir(Path::new("/foo")).unwrap();
+        fs.create_dir(Path::new("/foo/bar")).unwrap();
+
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create_new(true)
+            .open("/foo/hello.txt")
+            .unwrap();
+        file.write_all(b"hello").unwrap();
+
+        fs
+    }
+
+    fn read_to_string(fs: &dyn FS, path: &str) -> String {
+        let mut file = fs.new_open_options().read(true).open(path).unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+
+        contents
+    }
+
+    #[test]
+    fn test_read_falls_through() {
+        let fs = FileSystem::new(Box::new(mem_fs::FileSystem::default()), Box::new(lower()));
+
+        assert!(fs.metadata(Path::new("/foo")).unwrap().is_dir());
+        assert!(fs.metadata(Path::new("/foo/hello.txt")).unwrap().is_file());
+        assert_eq!(read_to_string(&fs, "/foo/hello.txt"), "hello");
+        assert!(fs.upper().metadata(Path::new("/foo")).is_err());
+    }
+
+    #[test]
+    fn test_write_is_copied_up() {
+        let lower = lower();
+        let fs = FileSystem::new(
+            Box::new(mem_fs::FileSystem::default()),
+            Box::new(lower.clone()),
+        );
+
+        {
+            let mut file = fs
+                .new_open_options()
+                .write(true)
+                .append(true)
+                .open("/foo/hello.txt")
+                .unwrap();
+            file.write_all(b", world").unwrap();
+        }
+
+        assert_eq!(read_to_string(&fs, "/foo/hello.txt"), "hello, world");
+        assert_eq!(read_to_string(&lower, "/foo/hello.txt"), "hello");
+
+        {
+            let mut file = fs
+                .new_open_options()
+                .write(true)
+                .create_new(true)
+                .open("/foo/bar/new.txt")
+                .unwrap();
+            file.write_all(b"new").unwrap();
+        }
+
+        assert_eq!(read_to_string(&fs, "/foo/bar/new.txt"), "new");
+        assert!(lower.metadata(Path::new("/foo/bar/new.txt")).is_err());
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .create_new(true)
+                .open("/foo/hello.txt")
+                .map(|_| ()),
+            Err(FsError::AlreadyExists),
+        );
+    }
+
+    #[test]
+    fn test_remove_and_rename() {
+        let lower = lower();
+        let fs = FileSystem::new(
+            Box::new(mem_fs::FileSystem::default()),
+            Box::new(lower.clone()),
+        );
+
+        assert_eq!(
+            fs.remove_dir(Path::new("/foo")),
+            Err(FsError::DirectoryNotEmpty)
+        );
+
+        fs.remove_file(Path::new("/foo/hello.txt")).unwrap();
+        assert_eq!(
+            fs.metadata(Path::new("/foo/hello.txt")).map(|_| ()),
+            Err(FsError::EntityNotFound)
+        );
+        assert!(lower.metadata(Path::new("/foo/hello.txt")).is_ok());
+
+        fs.rename(Path::new("/foo/bar"), Path::new("/baz")).unwrap();
+        assert!(fs.metadata(Path::new("/foo/bar")).is_err());
+        assert!(fs.metadata(Path::new("/baz")).unwrap().is_dir());
+        assert!(lower.metadata(Path::new("/foo/bar")).is_ok());
+
+        assert_eq!(fs.read_dir(Path::new("/foo")).unwrap().count(), 0);
+        fs.remove_dir(Path::new("/foo")).unwrap();
+        assert!(fs.metadata(Path::new("/foo")).is_err());
+
+        fs.create_dir(Path::new("/foo")).unwrap();
+        assert_eq!(fs.read_dir(Path::new("/foo")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_read_dir_merges_layers() {
+        let fs = FileSystem::new(Box::new(mem_fs::FileSystem::default()), Box::new(lower()));
+
+        fs.new_open_options()
+            .write(true)
+            .create_new(true)
+            .open("/foo/upper.txt")
+            .unwrap();
+
+        let mut names = fs
+            .read_dir(Path::new("/foo"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+
+        assert_eq!(names, vec!["bar", "hello.txt", "upper.txt"]);
+    }
+}
@@ -0,0 +1,220 @@
+//! A read-only file system backed by a tar archive.
+//!
+//! The archive is kept in memory as is, and indexed when it is
+//! mounted. Since tar archives aren't compressed, opening a file
+//! doesn't copy anything: the file reads straight from the archive
+//! buffer.
+//!
+//! ```rust,ignore
+//! use wasmer_vfs::tar_fs;
+//!
+//! let fs = tar_fs::FileSystem::new(std::fs::read("assets.tar")?)?;
+//! ```
+
+use crate::archive::{ArchiveFile, Index};
+use crate::{FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, Result, VirtualFile};
+use std::convert::TryInto;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use tar::{Archive, EntryType};
+
+/// A read-only file system exposing the contents of a tar archive.
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    inner: Arc<FileSystemInner>,
+}
+
+#[derive(Debug)]
+struct FileSystemInner {
+    buffer: Arc<[u8]>,
+    index: Index<Range<usize>>,
+}
+
+impl FileSystem {
+    /// Mounts the tar archive held by `buffer`.
+    ///
+    /// Only regular files and directories are exposed, other kinds
+    /// of entries (links, devices…) are ignored.
+    pub fn new<B: Into<Arc<[u8]>>>(buffer: B) -> Result<Self> {
+        let buffer = buffer.into();
+        let mut index = Index::new();
+
+        for entry in Archive::new(&buffer[..]).entries()? {
+            let entry = entry?;
+            let header = entry.header();
+            let modified = header.mtime().unwrap_or(0).saturating_mul(1_000_000_000);
+            let path = entry.path()?;
+
+            match header.entry_type() {
+                EntryType::Directory => index.insert_directory(&path, modified)?,
+                EntryType::Regular | EntryType::Continuous => {
+                    let start: usize = entry
+                        .raw_file_position()
+                        .try_into()
+                        .map_err(|_| FsError::InvalidData)?;
+                    let len: usize = entry.size().try_into().map_err(|_| FsError::InvalidData)?;
+                    let end = start.checked_add(len).ok_or(FsError::InvalidData)?;
+
+                    if end > buffer.len() {
+                        return Err(FsError::UnexpectedEof);
+                    }
+
+                    index.insert_file(&path, len as u64, modified, start..end)?;
+                }
+                _ => continue,
+            }
+        }
+
+        Ok(Self {
+            inner: Arc::new(FileSystemInner { buffer, index }),
+        })
+    }
+}
+
+impl crate::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        self.inner.index.read_dir(path)
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        self.inner.index.metadata(path)
+    }
+
+    fn remove_file(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(FileOpener {
+            filesystem: self.clone(),
+        }))
+    }
+}
+
+/// The opener of files in a tar [`FileSystem`].
+#[derive(Debug, Clone)]
+pub struct FileOpener {
+    filesystem: FileSystem,
+}
+
+impl crate::FileOpener for FileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let inner = &self.filesystem.inner;
+        let (range, metadata) = inner.index.open(path, conf)?;
+
+        Ok(Box::new(ArchiveFile::new(
+            inner.buffer.clone(),
+            range.clone(),
+            metadata.clone(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test_filesystem {
+    use super::FileSystem;
+    use crate::{FileSystem as FS, FsError};
+    use std::io::Read;
+    use std::path::Path;
+
+    fn archive() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_mtime(42);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "assets/hello.txt", &b"hello"[..])
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "assets/empty/", &b""[..])
+            .unwrap();
+
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_index() {
+        let fs = FileSystem::new(archive()).unwrap();
+
+        assert!(fs.metadata(Path::new("/")).unwrap().is_dir());
+        assert!(fs.metadata(Path::new("/assets")).unwrap().is_dir());
+        assert!(fs.metadata(Path::new("assets/empty")).unwrap().is_dir());
+
+        let metadata = fs.metadata(Path::new("/assets/hello.txt")).unwrap();
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), 5);
+        assert_eq!(metadata.modified(), 42_000_000_000);
+
+        let mut names = fs
+            .read_dir(Path::new("/assets"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["empty", "hello.txt"]);
+    }
+
+    #[test]
+    fn test_read_only() {
+        let fs = FileSystem::new(archive()).unwrap();
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .open("/assets/hello.txt")
+            .unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open("/assets/hello.txt")
+                .map(|_| ()),
+            Err(FsError::PermissionDenied)
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .create(true)
+                .open("/assets/new.txt")
+                .map(|_| ()),
+            Err(FsError::PermissionDenied)
+        );
+        assert_eq!(
+            fs.remove_file(Path::new("/assets/hello.txt")),
+            Err(FsError::PermissionDenied)
+        );
+        assert_eq!(
+            fs.create_dir(Path::new("/other")),
+            Err(FsError::PermissionDenied)
+        );
+    }
+}
@@ -0,0 +1,636 @@
+//! The host file system, with the file I/O done through io_uring on
+//! Linux.
+//!
+//! [`FileSystem`] is the file system of [`crate::host_fs`], except that
+//! the reads, writes and syncs of its files are submitted to an
+//! io_uring shared by the process, whose completions are dispatched by a
+//! thread of their own. The files are [`AsyncVirtualFile`]s: used as
+//! [`VirtualFile`]s they block the calling thread until their I/O is
+//! complete, and an executor can drive them instead, through
+//! [`VirtualFile::as_async_mut`].
+//!
+//! Where io_uring isn't available, e.g. on kernels older than 5.6 or
+//! when seccomp forbids it, the files are the ones of the host file
+//! system.
+
+use crate::host_fs;
+use crate::{
+    block_on, AsyncVirtualFile, FileOpener, FsError, FsEventSink, FsWatch, Metadata, OpenOptions,
+    OpenOptionsConfig, ReadDir, Result, SyncFile, Upcastable, VirtualFile,
+};
+use io_uring::{opcode, squeue, types, IoUring};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use tracing::debug;
+
+/// The number of entries of the submission queue of the ring.
+const RING_ENTRIES: u32 = 256;
+
+/// The most bytes read or written by one operation.
+const MAX_IO_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    ring: Option<Arc<Ring>>,
+}
+
+impl FileSystem {
+    /// Creates the file system, whose files use io_uring if it's
+    /// available.
+    pub fn new() -> Self {
+        Self {
+            ring: Ring::shared(),
+        }
+    }
+
+    /// Whether the files use io_uring, rather than being the files of
+    /// the host file system.
+    pub fn uses_io_uring(&self) -> bool {
+        self.ring.is_some()
+    }
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl crate::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        host_fs::FileSystem.read_dir(path)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        host_fs::FileSystem.create_dir(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        host_fs::FileSystem.remove_dir(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        host_fs::FileSystem.rename(from, to)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        host_fs::FileSystem.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        host_fs::FileSystem.symlink_metadata(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        host_fs::FileSystem.remove_file(path)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(UringFileOpener {
+            ring: self.ring.clone(),
+        }))
+    }
+
+    fn watch(&self, path: &Path, sink: FsEventSink) -> Result<Box<dyn FsWatch>> {
+        host_fs::FileSystem.watch(path, sink)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct UringFileOpener {
+    ring: Option<Arc<Ring>>,
+}
+
+impl FileOpener for UringFileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let file = host_fs::FileOpener.open(path, conf)?;
+        let ring = match &self.ring {
+            Some(ring) => ring.clone(),
+            None => return Ok(file),
+        };
+
+        let inner = file
+            .upcast_any_box()
+            .downcast::<host_fs::File>()
+            .map_err(|_| FsError::UnknownError)?;
+
+        Ok(Box::new(SyncFile::new(File {
+            inner: *inner,
+            ring,
+            append: conf.append(),
+            position: 0,
+            in_flight: None,
+            seek: None,
+        })))
+    }
+}
+
+/// A host file whose I/O goes through io_uring.
+#[derive(Debug)]
+pub struct File {
+    inner: host_fs::File,
+    ring: Arc<Ring>,
+    append: bool,
+    position: u64,
+    /// The operation submitted for the last read or write, until it's
+    /// complete.
+    in_flight: Option<InFlight>,
+    seek: Option<io::Result<u64>>,
+}
+
+#[derive(Debug)]
+struct InFlight {
+    kind: OperationKind,
+    operation: Submitted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperationKind {
+    Read,
+    Write,
+}
+
+impl File {
+    /// Waits for the operation of `kind` in flight, submitting it with
+    /// `submit` first if there is none, and returns its buffer and
+    /// result. A write in flight is the one of the caller if it writes
+    /// the same `data`.
+    fn poll_operation(
+        &mut self,
+        cx: &mut Context<'_>,
+        kind: OperationKind,
+        data: Option<&[u8]>,
+        submit: impl FnOnce(&mut Self) -> io::Result<Submitted>,
+    ) -> Poll<io::Result<(Vec<u8>, usize)>> {
+        let mut submit = Some(submit);
+
+        loop {
+            let in_flight = match self.in_flight.as_mut() {
+                Some(in_flight) => in_flight,
+                None => {
+                    let submit = submit.take().expect("submitted twice");
+                    let operation = submit(self)?;
+                    self.in_flight = Some(InFlight { kind, operation });
+                    continue;
+                }
+            };
+            let ours = in_flight.kind == kind
+                && data.map_or(true, |data| in_flight.operation.has_buffer(data));
+            let (buffer, result) = match Pin::new(&mut in_flight.operation).poll(cx) {
+                Poll::Ready(completion) => completion,
+                // If it's another caller's operation, ours is submitted
+                // once the task is woken by its completion.
+                Poll::Pending => return Poll::Pending,
+            };
+            let in_flight_kind = in_flight.kind;
+            self.in_flight = None;
+
+            if ours {
+                return Poll::Ready(result.map(|size| (buffer, size)));
+            }
+
+            // The operation of a caller which gave up, e.g. a read whose
+            // future was dropped. What it has written is written.
+            if let (OperationKind::Write, Ok(written)) = (in_flight_kind, result) {
+                self.wrote(written);
+            }
+        }
+    }
+
+    fn wrote(&mut self, written: usize) {
+        self.position = if self.append {
+            self.inner.size()
+        } else {
+            self.position + written as u64
+        };
+    }
+}
+
+impl AsyncRead for File {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let len = buf.remaining().min(MAX_IO_SIZE);
+        let fd = types::Fd(this.inner.inner.as_raw_fd());
+
+        let (buffer, read) = match this.poll_operation(cx, OperationKind::Read, None, |file| {
+            let position = file.position;
+            file.ring.submit(vec![0; len], |data| {
+                opcode::Read::new(fd, data.as_mut_ptr(), data.len() as u32)
+                    .offset(position as _)
+                    .build()
+            })
+        }) {
+            Poll::Ready(completion) => completion?,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        // The read may have been submitted by a caller with a larger
+        // buffer, which gave up.
+        let read = read.min(buf.remaining());
+        buf.put_slice(&buffer[..read]);
+        this.position += read as u64;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for File {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let data = &buf[..buf.len().min(MAX_IO_SIZE)];
+        let fd = types::Fd(this.inner.inner.as_raw_fd());
+
+        let written = match this.poll_operation(cx, OperationKind::Write, Some(data), |file| {
+            // The files opened to append are written at their end, which
+            // the offset `-1` stands for.
+            let offset = if file.append {
+                -1
+            } else {
+                file.position as i64
+            };
+            file.ring.submit(data.to_vec(), |data| {
+                opcode::Write::new(fd, data.as_ptr(), data.len() as u32)
+                    .offset(offset as _)
+                    .build()
+            })
+        }) {
+            Poll::Ready(completion) => completion?.1,
+            Poll::Pending => return Poll::Pending,
+        };
+        this.wrote(written);
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // The writes are complete once `poll_write` returns.
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl AsyncSeek for File {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => offset_by(this.inner.size(), offset),
+            SeekFrom::Current(offset) => offset_by(this.position, offset),
+        };
+
+        this.seek = Some(position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        }));
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        if let Some(Ok(position)) = this.seek {
+            this.position = position;
+        }
+
+        Poll::Ready(this.seek.take().unwrap_or(Ok(this.position)))
+    }
+}
+
+fn offset_by(position: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        position.checked_add(offset as u64)
+    } else {
+        position.checked_sub(offset.unsigned_abs())
+    }
+}
+
+impl AsyncVirtualFile for File {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.inner.unlink()
+    }
+
+    fn sync_to_disk(&self) -> Result<()> {
+        let fd = types::Fd(self.inner.inner.as_raw_fd());
+        let operation = self
+            .ring
+            .submit(Vec::new(), |_| opcode::Fsync::new(fd).build())?;
+
+        block_on(operation).1.map(|_| ()).map_err(Into::into)
+    }
+}
+
+/// The io_uring of the process, and the operations submitted to it.
+struct Ring {
+    uring: IoUring,
+    /// Serializes the pushes to the submission queue.
+    submission: Mutex<()>,
+    operations: Mutex<HashMap<u64, Operation>>,
+    next_id: AtomicU64,
+}
+
+enum Operation {
+    /// Submitted, with the buffer the kernel reads or writes, and the
+    /// task waiting for the completion.
+    Pending {
+        buffer: Vec<u8>,
+        waker: Option<Waker>,
+    },
+    Completed {
+        buffer: Vec<u8>,
+        result: i32,
+    },
+    /// Submitted by a caller which gave up: its buffer is kept until
+    /// the kernel is done with it.
+    Abandoned {
+        buffer: Vec<u8>,
+    },
+}
+
+impl fmt::Debug for Ring {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Ring")
+            .field("operations", &self.operations.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Ring {
+    /// Returns the ring of the process, created with the thread
+    /// completing its operations on the first call, or `None` if
+    /// io_uring isn't available.
+    fn shared() -> Option<Arc<Ring>> {
+        static INIT: Once = Once::new();
+        static mut RING: Option<Arc<Ring>> = None;
+
+        INIT.call_once(|| {
+            let ring = match IoUring::new(RING_ENTRIES) {
+                Ok(uring) => Arc::new(Ring {
+                    uring,
+                    submission: Mutex::new(()),
+                    operations: Mutex::new(HashMap::new()),
+                    next_id: AtomicU64::new(0),
+                }),
+                Err(error) => {
+                    debug!("io_uring is unavailable, using the host files: {}", error);
+                    return;
+                }
+            };
+
+            let completer = ring.clone();
+            let spawned = thread::Builder::new()
+                .name("wasmer-vfs-io-uring".to_string())
+                .spawn(move || completer.complete());
+
+            match spawned {
+                // Safe because `RING` is only written here, once.
+                Ok(_) => unsafe { RING = Some(ring) },
+                Err(error) => debug!("failed to spawn the io_uring thread: {}", error),
+            }
+        });
+
+        // Safe because `RING` isn't written anymore.
+        unsafe { RING.clone() }
+    }
+
+    /// Submits the operation built by `build` with `buffer`, which is
+    /// owned by the ring until the operation is complete.
+    fn submit(
+        self: &Arc<Self>,
+        buffer: Vec<u8>,
+        build: impl FnOnce(&mut Vec<u8>) -> squeue::Entry,
+    ) -> io::Result<Submitted> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut operations = self.operations.lock().unwrap();
+        operations.insert(
+            id,
+            Operation::Pending {
+                buffer,
+                waker: None,
+            },
+        );
+        // The data of the buffer doesn't move with the operation, so
+        // the pointers given to the kernel stay valid.
+        let entry = match operations.get_mut(&id) {
+            Some(Operation::Pending { buffer, .. }) => build(buffer).user_data(id),
+            _ => unreachable!(),
+        };
+        drop(operations);
+
+        let pushed = {
+            let _submission = self.submission.lock().unwrap();
+            // Safe because the pushes are serialized, and the buffer
+            // lives until the operation is complete.
+            unsafe {
+                let mut queue = self.uring.submission_shared();
+                if queue.is_full() {
+                    drop(queue);
+                    self.uring.submitter().submit()?;
+                    queue = self.uring.submission_shared();
+                }
+                queue.push(&entry).is_ok()
+            }
+        };
+        if !pushed {
+            self.operations.lock().unwrap().remove(&id);
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "the io_uring submission queue is full",
+            ));
+        }
+        self.uring.submitter().submit()?;
+
+        Ok(Submitted {
+            ring: self.clone(),
+            id,
+        })
+    }
+
+    /// Waits for the completions, and wakes the tasks waiting for
+    /// them. Runs on the thread of the ring.
+    fn complete(&self) {
+        loop {
+            match self.uring.submitter().submit_and_wait(1) {
+                Ok(_) => {}
+                Err(error) if error.raw_os_error() == Some(libc::EINTR) => continue,
+                Err(error) => {
+                    debug!("the io_uring thread stops: {}", error);
+                    return;
+                }
+            }
+
+            // Safe because this thread is the only one reading the
+            // completion queue.
+            let completions = unsafe { self.uring.completion_shared() }
+                .map(|entry| (entry.user_data(), entry.result()))
+                .collect::<Vec<_>>();
+
+            let mut wakers = Vec::new();
+            {
+                let mut operations = self.operations.lock().unwrap();
+                for (id, result) in completions {
+                    match operations.remove(&id) {
+                        Some(Operation::Pending { buffer, waker }) => {
+                            operations.insert(id, Operation::Completed { buffer, result });
+                            wakers.extend(waker);
+                        }
+                        Some(Operation::Abandoned { .. }) | None => {}
+                        Some(completed) => {
+                            operations.insert(id, completed);
+                        }
+                    }
+                }
+            }
+
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// An operation submitted to the ring, which resolves to its buffer
+/// and result once it's complete.
+#[derive(Debug)]
+struct Submitted {
+    ring: Arc<Ring>,
+    id: u64,
+}
+
+impl Submitted {
+    /// Whether the buffer of the operation, while it's pending, holds
+    /// `data`.
+    fn has_buffer(&self, data: &[u8]) -> bool {
+        match self.ring.operations.lock().unwrap().get(&self.id) {
+            Some(Operation::Pending { buffer, .. }) | Some(Operation::Completed { buffer, .. }) => {
+                buffer.as_slice() == data
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Future for Submitted {
+    type Output = (Vec<u8>, io::Result<usize>);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut operations = self.ring.operations.lock().unwrap();
+
+        match operations.get_mut(&self.id) {
+            Some(Operation::Pending { waker, .. }) => {
+                *waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            Some(Operation::Completed { .. }) => match operations.remove(&self.id) {
+                Some(Operation::Completed { buffer, result }) => {
+                    let result = if result < 0 {
+                        Err(io::Error::from_raw_os_error(-result))
+                    } else {
+                        Ok(result as usize)
+                    };
+                    Poll::Ready((buffer, result))
+                }
+                _ => unreachable!(),
+            },
+            _ => Poll::Ready((
+                Vec::new(),
+                Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "the io_uring operation is gone",
+                )),
+            )),
+        }
+    }
+}
+
+impl Drop for Submitted {
+    fn drop(&mut self) {
+        let mut operations = self.ring.operations.lock().unwrap();
+
+        if let Some(Operation::Pending { .. }) = operations.get(&self.id) {
+            if let Some(Operation::Pending { buffer, .. }) = operations.remove(&self.id) {
+                operations.insert(self.id, Operation::Abandoned { buffer });
+            }
+        } else {
+            operations.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_uring_fs {
+    use super::*;
+    use crate::FileSystem as _;
+    use std::io::{Read, Seek, Write};
+
+    #[test]
+    fn test_read_write() {
+        let fs = FileSystem::new();
+        let path = std::env::temp_dir().join(format!("wasmer-vfs-uring-{}", std::process::id()));
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"hello, world").unwrap();
+        file.sync_to_disk().unwrap();
+        assert_eq!(file.size(), 12);
+
+        assert_eq!(file.seek(SeekFrom::Start(7)).unwrap(), 7);
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "world");
+
+        fs.remove_file(&path).unwrap();
+    }
+}
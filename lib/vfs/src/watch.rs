@@ -0,0 +1,157 @@
+//! Watching the changes of a file system, see [`FileSystem::watch`].
+//!
+//! [`FileSystem::watch`]: crate::FileSystem::watch
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The kind of a change reported to an [`FsEventSink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsEventKind {
+    /// A file or a directory has been created, or renamed to this path.
+    Create,
+    /// A file has been written to, or its size has changed.
+    Modify,
+    /// A file or a directory has been removed, or renamed from this path.
+    Remove,
+}
+
+/// A change of a watched path, or of an entry of a watched directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsEvent {
+    /// The kind of the change.
+    pub kind: FsEventKind,
+    /// The path which changed.
+    pub path: PathBuf,
+}
+
+/// Receives the changes of a watched path. It's called from the
+/// thread which made the change, or from a thread of the backend.
+pub type FsEventSink = Arc<dyn Fn(FsEvent) + Send + Sync>;
+
+/// Keeps a path watched until it's dropped.
+pub trait FsWatch: fmt::Debug + Send + Sync {}
+
+#[cfg(feature = "mem-fs")]
+pub(crate) use self::registry::Watches;
+
+#[cfg(feature = "mem-fs")]
+mod registry {
+    use super::{FsEvent, FsEventKind, FsEventSink, FsWatch};
+    use std::fmt;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex, Weak};
+
+    /// The watches of a backend which reports its own changes, like the
+    /// in-memory file system.
+    #[derive(Default)]
+    pub(crate) struct Watches {
+        inner: Arc<Mutex<WatchesInner>>,
+    }
+
+    #[derive(Default)]
+    struct WatchesInner {
+        next_id: u64,
+        watches: Vec<(u64, PathBuf, FsEventSink)>,
+    }
+
+    impl Watches {
+        /// Watches `path`, which must be normalized like the paths given to
+        /// [`Watches::notify`].
+        pub(crate) fn add(&self, path: PathBuf, sink: FsEventSink) -> Box<dyn FsWatch> {
+            let mut inner = self.inner.lock().unwrap();
+            let id = inner.next_id;
+            inner.next_id += 1;
+            inner.watches.push((id, path, sink));
+
+            Box::new(Watch {
+                id,
+                watches: Arc::downgrade(&self.inner),
+            })
+        }
+
+        /// Whether no path is watched, so changes don't have to be
+        /// reported.
+        pub(crate) fn is_empty(&self) -> bool {
+            self.inner.lock().unwrap().watches.is_empty()
+        }
+
+        /// Reports a change of `path` to the watches of `path` and of its
+        /// parent directory.
+        pub(crate) fn notify(&self, kind: FsEventKind, path: &Path) {
+            let sinks = {
+                let inner = self.inner.lock().unwrap();
+                if inner.watches.is_empty() {
+                    return;
+                }
+
+                inner
+                    .watches
+                    .iter()
+                    .filter(|(_, watched, _)| {
+                        watched == path || Some(watched.as_path()) == path.parent()
+                    })
+                    .map(|(_, _, sink)| sink.clone())
+                    .collect::<Vec<_>>()
+            };
+
+            // The sinks are called without the lock held, so that they can
+            // watch or unwatch paths.
+            for sink in sinks {
+                sink(FsEvent {
+                    kind,
+                    path: path.to_path_buf(),
+                });
+            }
+        }
+    }
+
+    impl Clone for Watches {
+        fn clone(&self) -> Self {
+            Self {
+                inner: self.inner.clone(),
+            }
+        }
+    }
+
+    impl fmt::Debug for Watches {
+        fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let inner = self.inner.lock().unwrap();
+
+            formatter
+                .debug_list()
+                .entries(inner.watches.iter().map(|(_, path, _)| path))
+                .finish()
+        }
+    }
+
+    /// A watch registered in [`Watches`], removed when it's dropped.
+    struct Watch {
+        id: u64,
+        watches: Weak<Mutex<WatchesInner>>,
+    }
+
+    impl FsWatch for Watch {}
+
+    impl fmt::Debug for Watch {
+        fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter
+                .debug_struct("Watch")
+                .field("id", &self.id)
+                .finish()
+        }
+    }
+
+    impl Drop for Watch {
+        fn drop(&mut self) {
+            if let Some(watches) = self.watches.upgrade() {
+                watches
+                    .lock()
+                    .unwrap()
+                    .watches
+                    .retain(|(id, _, _)| *id != self.id);
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! A read-only file system backed by a zip archive.
+//!
+//! The archive is kept in memory and indexed when it is mounted,
+//! without decompressing anything. A file is only decompressed when
+//! it is opened.
+//!
+//! ```rust,ignore
+//! use wasmer_vfs::zip_fs;
+//!
+//! let fs = zip_fs::FileSystem::new(std::fs::read("assets.zip")?)?;
+//! ```
+
+use crate::archive::{ArchiveFile, Index};
+use crate::{FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, Result, VirtualFile};
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use zip::result::ZipError;
+use zip::{DateTime, ZipArchive};
+
+/// The most bytes allocated upfront when a file is opened.
+const MAX_PREALLOCATION: u64 = 1024 * 1024;
+
+/// A read-only file system exposing the contents of a zip archive.
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    inner: Arc<FileSystemInner>,
+}
+
+struct FileSystemInner {
+    archive: Mutex<ZipArchive<Cursor<Arc<[u8]>>>>,
+    index: Index<usize>,
+}
+
+impl fmt::Debug for FileSystemInner {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("FileSystemInner")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl From<ZipError> for FsError {
+    fn from(error: ZipError) -> Self {
+        match error {
+            ZipError::Io(error) => error.into(),
+            ZipError::FileNotFound => FsError::EntityNotFound,
+            ZipError::InvalidArchive(_) | ZipError::UnsupportedArchive(_) => FsError::InvalidData,
+        }
+    }
+}
+
+impl FileSystem {
+    /// Mounts the zip archive held by `buffer`.
+    ///
+    /// Entries whose name would escape the root of the archive are
+    /// ignored.
+    pub fn new<B: Into<Arc<[u8]>>>(buffer: B) -> Result<Self> {
+        let mut archive = ZipArchive::new(Cursor::new(buffer.into()))?;
+        let mut index = Index::new();
+
+        for position in 0..archive.len() {
+            let file = archive.by_index_raw(position)?;
+            let path = match file.enclosed_name() {
+                Some(path) => path.to_path_buf(),
+                None => continue,
+            };
+            let modified = to_timestamp(file.last_modified());
+
+            if file.is_dir() {
+                index.insert_directory(&path, modified)?;
+            } else {
+                index.insert_file(&path, file.size(), modified, position)?;
+            }
+        }
+
+        Ok(Self {
+            inner: Arc::new(FileSystemInner {
+                archive: Mutex::new(archive),
+                index,
+            }),
+        })
+    }
+}
+
+/// Converts a zip (MS-DOS) date time, which has no time zone, to a
+/// UNIX timestamp in nanoseconds, assuming UTC.
+fn to_timestamp(date_time: DateTime) -> u64 {
+    // Days since the UNIX epoch of a date in the proleptic Gregorian
+    // calendar, see http://howardhinnant.github.io/date_algorithms.html.
+    let (year, month, day) = (
+        i64::from(date_time.year()),
+        i64::from(date_time.month()),
+        i64::from(date_time.day()),
+    );
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400
+        + i64::from(date_time.hour()) * 3_600
+        + i64::from(date_time.minute()) * 60
+        + i64::from(date_time.second());
+
+    (seconds.max(0) as u64).saturating_mul(1_000_000_000)
+}
+
+impl crate::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        self.inner.index.read_dir(path)
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        self.inner.index.metadata(path)
+    }
+
+    fn remove_file(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(FileOpener {
+            filesystem: self.clone(),
+        }))
+    }
+}
+
+/// The opener of files in a zip [`FileSystem`].
+#[derive(Debug, Clone)]
+pub struct FileOpener {
+    filesystem: FileSystem,
+}
+
+impl crate::FileOpener for FileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let inner = &self.filesystem.inner;
+        let (position, metadata) = inner.index.open(path, conf)?;
+
+        // The size is the one declared by the archive, which can't be
+        // trusted: the buffer only grows as the data is decompressed.
+        let mut contents = Vec::with_capacity(metadata.len.min(MAX_PREALLOCATION) as usize);
+        {
+            let mut archive = inner.archive.lock().map_err(|_| FsError::Lock)?;
+            archive
+                .by_index(*position)?
+                .take(metadata.len)
+                .read_to_end(&mut contents)?;
+        }
+        let len = contents.len();
+
+        Ok(Box::new(ArchiveFile::new(
+            contents.into(),
+            0..len,
+            metadata.clone(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test_filesystem {
+    use super::FileSystem;
+    use crate::{FileSystem as FS, FsError};
+    use std::io::{Cursor, Read, Write};
+    use std::path::Path;
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, DateTime, ZipWriter};
+
+    fn archive() -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(DateTime::from_date_and_time(2000, 1, 1, 0, 0, 0).unwrap());
+
+        writer.add_directory("assets/empty/", options).unwrap();
+        writer.start_file("assets/hello.txt", options).unwrap();
+        writer.write_all(b"hello").unwrap();
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_index() {
+        let fs = FileSystem::new(archive()).unwrap();
+
+        assert!(fs.metadata(Path::new("/")).unwrap().is_dir());
+        assert!(fs.metadata(Path::new("/assets")).unwrap().is_dir());
+        assert!(fs.metadata(Path::new("assets/empty")).unwrap().is_dir());
+
+        let metadata = fs.metadata(Path::new("/assets/hello.txt")).unwrap();
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), 5);
+        assert_eq!(metadata.modified(), 946_684_800_000_000_000);
+
+        let mut names = fs
+            .read_dir(Path::new("/assets"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["empty", "hello.txt"]);
+    }
+
+    #[test]
+    fn test_read_only() {
+        let fs = FileSystem::new(archive()).unwrap();
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .open("/assets/hello.txt")
+            .unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+
+        assert_eq!(
+            fs.new_open_options()
+                .append(true)
+                .open("/assets/hello.txt")
+                .map(|_| ()),
+            Err(FsError::PermissionDenied)
+        );
+        assert_eq!(
+            fs.rename(Path::new("/assets"), Path::new("/other")),
+            Err(FsError::PermissionDenied)
+        );
+    }
+
+    #[test]
+    fn test_invalid_archive() {
+        assert_eq!(
+            FileSystem::new(&b"not a zip archive"[..]).map(|_| ()),
+            Err(FsError::InvalidData)
+        );
+    }
+}
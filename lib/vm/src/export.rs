@@ -5,10 +5,11 @@ use crate::global::Global;
 use crate::instance::WeakOrStrongInstanceRef;
 use crate::memory::Memory;
 use crate::table::Table;
+use crate::tag::Tag;
 use crate::vmcontext::{VMFunctionEnvironment, VMFunctionKind, VMTrampoline};
 use crate::VMFunctionBody;
 use std::sync::Arc;
-use wasmer_types::{FunctionType, MemoryStyle, MemoryType, TableStyle, TableType};
+use wasmer_types::{FunctionType, MemoryStyle, MemoryType, TableStyle, TableType, TagType};
 
 /// The value of an export passed from one instance to another.
 #[derive(Debug)]
@@ -24,6 +25,9 @@ pub enum VMExtern {
 
     /// A global export value.
     Global(VMGlobal),
+
+    /// A tag export value.
+    Tag(VMTag),
 }
 
 /// A function export value.
@@ -233,3 +237,49 @@ impl From<VMGlobal> for VMExtern {
         Self::Global(global)
     }
 }
+
+/// A tag export value.
+#[derive(Debug, Clone)]
+pub struct VMTag {
+    /// Pointer to the containing `Tag`.
+    pub from: Arc<Tag>,
+
+    /// A “reference” to the instance through the
+    /// `InstanceRef`. `None` if it is a host tag.
+    pub instance_ref: Option<WeakOrStrongInstanceRef>,
+}
+
+/// # Safety
+/// This is correct because a `Tag` is immutable.
+unsafe impl Send for VMTag {}
+
+/// # Safety
+/// This is correct because a `Tag` is immutable.
+unsafe impl Sync for VMTag {}
+
+impl VMTag {
+    /// Get the type for this exported tag
+    pub fn ty(&self) -> &TagType {
+        self.from.ty()
+    }
+
+    /// Returns whether or not the two `VMTag`s refer to the same Tag.
+    pub fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.from, &other.from)
+    }
+
+    /// Converts the stored instance ref into a strong `InstanceRef` if it is weak.
+    /// Returns None if it cannot be upgraded.
+    pub fn upgrade_instance_ref(&mut self) -> Option<()> {
+        if let Some(ref mut ir) = self.instance_ref {
+            *ir = ir.upgrade()?;
+        }
+        Some(())
+    }
+}
+
+impl From<VMTag> for VMExtern {
+    fn from(tag: VMTag) -> Self {
+        Self::Tag(tag)
+    }
+}
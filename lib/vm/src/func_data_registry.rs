@@ -2,11 +2,17 @@
 //! identical `VMCallerCheckedAnyfunc`s will give us identical funcrefs.
 //!
 //! This registry also helps ensure that the `VMFuncRef`s can stay valid for as
-//! long as we need them to.
+//! long as we need them to, and frees them once their functions are gone and
+//! no table or global holds them anymore, see [`FuncDataRegistry::collect`].
 
+use crate::global::Global;
+use crate::instance::WeakInstanceRef;
+use crate::table::{Table, TableElement};
 use crate::vmcontext::VMCallerCheckedAnyfunc;
-use std::collections::HashMap;
-use std::sync::Mutex;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, Weak};
+use wasmer_types::Type;
 
 /// The registry that holds the values that `VMFuncRef`s point to.
 #[derive(Debug, Default)]
@@ -86,10 +92,106 @@ impl std::ops::DerefMut for VMFuncRef {
 unsafe impl Send for VMFuncRef {}
 unsafe impl Sync for VMFuncRef {}
 
+/// What keeps the function of a `VMFuncRef` alive, with which the
+/// registry tells whether the function is gone.
+#[derive(Debug, Clone)]
+pub enum FuncDataOwner {
+    /// The function has no environment, it's never gone.
+    Static,
+    /// The function is defined or imported by an instance.
+    Instance(WeakInstanceRef),
+    /// The function is a host function, whose environment is owned by
+    /// this object.
+    Host(Weak<dyn Any + Send + Sync>),
+}
+
+impl FuncDataOwner {
+    fn is_alive(&self) -> bool {
+        match self {
+            Self::Static => true,
+            Self::Instance(instance) => instance.is_alive(),
+            Self::Host(host) => host.strong_count() > 0,
+        }
+    }
+}
+
+/// A table or a global which may hold `VMFuncRef`s, whose functions are
+/// kept by [`FuncDataRegistry::collect`] while it's alive.
+#[derive(Debug, Clone)]
+enum Root {
+    Table(Weak<dyn Table>),
+    Global(Weak<Global>),
+}
+
+impl Root {
+    /// Calls `visit` with the `VMFuncRef`s held by the root, and returns
+    /// whether it's still alive.
+    fn visit(&self, visit: &mut impl FnMut(VMFuncRef)) -> bool {
+        match self {
+            Self::Table(table) => match table.upgrade() {
+                Some(table) => {
+                    for index in 0..table.size() {
+                        if let Some(TableElement::FuncRef(func_ref)) = table.get(index) {
+                            visit(func_ref);
+                        }
+                    }
+                    true
+                }
+                None => false,
+            },
+            Self::Global(global) => match global.upgrade() {
+                Some(global) => {
+                    if let Some(func_ref) = global.funcref() {
+                        visit(func_ref);
+                    }
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        match self {
+            Self::Table(table) => table.strong_count() > 0,
+            Self::Global(global) => global.strong_count() > 0,
+        }
+    }
+}
+
+/// The outcome of [`FuncDataRegistry::collect`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FuncDataCollection {
+    /// The number of function data freed.
+    pub reclaimed: usize,
+    /// The number of function data still registered.
+    pub retained: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    // Boxed, so that the `VMFuncRef`s don't move with the map.
+    data: Box<VMCallerCheckedAnyfunc>,
+    owner: FuncDataOwner,
+}
+
 #[derive(Debug, Default)]
 struct Inner {
-    func_data: Vec<VMCallerCheckedAnyfunc>,
-    anyfunc_to_index: HashMap<VMCallerCheckedAnyfunc, usize>,
+    func_data: HashMap<VMCallerCheckedAnyfunc, Entry>,
+    roots: Vec<Root>,
+    /// The number of roots above which the dropped ones are pruned when
+    /// adding a new one.
+    roots_limit: usize,
+}
+
+impl Inner {
+    fn add_root(&mut self, root: Root) {
+        if self.roots.len() >= self.roots_limit {
+            self.roots.retain(Root::is_alive);
+            self.roots_limit = (self.roots.len() * 2).max(64);
+        }
+        self.roots.push(root);
+    }
 }
 
 impl FuncDataRegistry {
@@ -98,21 +200,60 @@ impl FuncDataRegistry {
         Default::default()
     }
 
-    /// Register a signature and return its unique index.
-    pub fn register(&self, anyfunc: VMCallerCheckedAnyfunc) -> VMFuncRef {
+    /// Register a function's data, owned by `owner`, and return its unique
+    /// `VMFuncRef`.
+    pub fn register(&self, anyfunc: VMCallerCheckedAnyfunc, owner: FuncDataOwner) -> VMFuncRef {
+        let mut inner = self.inner.lock().unwrap();
+        let entry = inner.func_data.entry(anyfunc).or_insert_with(|| Entry {
+            data: Box::new(anyfunc),
+            owner: owner.clone(),
+        });
+        // The environment of a function gone may have been reused by a new
+        // one.
+        if !entry.owner.is_alive() {
+            entry.owner = owner;
+        }
+        VMFuncRef(&*entry.data)
+    }
+
+    /// Keeps the data of the functions held by `table` while it's alive,
+    /// if it's a `funcref` table.
+    pub fn add_table_root(&self, table: &Arc<dyn Table>) {
+        if table.ty().ty == Type::FuncRef {
+            let mut inner = self.inner.lock().unwrap();
+            inner.add_root(Root::Table(Arc::downgrade(table)));
+        }
+    }
+
+    /// Keeps the data of the function held by `global` while it's alive,
+    /// if it's a `funcref` global.
+    pub fn add_global_root(&self, global: &Arc<Global>) {
+        if global.ty().ty == Type::FuncRef {
+            let mut inner = self.inner.lock().unwrap();
+            inner.add_root(Root::Global(Arc::downgrade(global)));
+        }
+    }
+
+    /// Frees the data of the functions gone, whose instance or host
+    /// environment has been dropped, and which no table or global added
+    /// with [`FuncDataRegistry::add_table_root`] or
+    /// [`FuncDataRegistry::add_global_root`] holds anymore.
+    pub fn collect(&self) -> FuncDataCollection {
         let mut inner = self.inner.lock().unwrap();
-        if let Some(&idx) = inner.anyfunc_to_index.get(&anyfunc) {
-            let data: &_ = &inner.func_data[idx];
-            let inner_ptr: &VMCallerCheckedAnyfunc = &*data;
-            VMFuncRef(inner_ptr)
-        } else {
-            let idx = inner.func_data.len();
-            inner.func_data.push(anyfunc);
-            inner.anyfunc_to_index.insert(anyfunc, idx);
-
-            let data: &_ = &inner.func_data[idx];
-            let inner_ptr: &VMCallerCheckedAnyfunc = &*data;
-            VMFuncRef(inner_ptr)
+        let mut reachable = HashSet::new();
+        inner.roots.retain(|root| {
+            root.visit(&mut |func_ref| {
+                reachable.insert(func_ref);
+            })
+        });
+        let before = inner.func_data.len();
+        inner.func_data.retain(|_, entry| {
+            entry.owner.is_alive() || reachable.contains(&VMFuncRef(&*entry.data))
+        });
+        let retained = inner.func_data.len();
+        FuncDataCollection {
+            reclaimed: before - retained,
+            retained,
         }
     }
 }
@@ -1,7 +1,10 @@
+use crate::func_data_registry::VMFuncRef;
 use crate::vmcontext::VMGlobalDefinition;
 use std::cell::UnsafeCell;
+use std::fmt;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
 use thiserror::Error;
 use wasmer_types::{GlobalType, Mutability, Type, Value, WasmValueType};
 
@@ -13,6 +16,69 @@ pub struct Global {
     vm_global_definition: Box<UnsafeCell<VMGlobalDefinition>>,
     // used to synchronize gets/sets
     lock: Mutex<()>,
+    /// The observer of the writes of the Wasm code, if any, see
+    /// [`Global::observe`].
+    observer: Mutex<Option<GlobalObserver>>,
+}
+
+/// The callback of a [`GlobalObserver`].
+pub type GlobalObserverCallback = Arc<dyn Fn(&Global) + Send + Sync>;
+
+/// Observes the changes of a global made by the Wasm code.
+struct GlobalObserver {
+    /// The bits of the value the observer has last seen.
+    value: u128,
+    callback: GlobalObserverCallback,
+}
+
+impl fmt::Debug for GlobalObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GlobalObserver")
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+lazy_static::lazy_static! {
+    /// The globals with an observer, checked by
+    /// [`notify_global_observers`].
+    static ref OBSERVED_GLOBALS: Mutex<Vec<Weak<Global>>> = Mutex::new(Vec::new());
+}
+
+/// The number of globals in `OBSERVED_GLOBALS`, so that checking them
+/// costs a load when there is none.
+static OBSERVED_GLOBALS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Invokes the observers of the globals whose value has been changed by
+/// the Wasm code since they were last checked, see [`Global::observe`].
+///
+/// This is called whenever the Wasm code calls a host function or
+/// returns to the host.
+pub fn notify_global_observers() {
+    if OBSERVED_GLOBALS_COUNT.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+
+    let mut changed = Vec::new();
+    {
+        let mut observed = OBSERVED_GLOBALS.lock().unwrap();
+        observed.retain(|global| match global.upgrade() {
+            Some(global) => {
+                if let Some(callback) = global.observed_change() {
+                    changed.push((global, callback));
+                }
+                true
+            }
+            None => false,
+        });
+        OBSERVED_GLOBALS_COUNT.store(observed.len(), Ordering::Relaxed);
+    }
+
+    // Called without any lock held, so that the observers can access
+    // the globals.
+    for (global, callback) in changed {
+        callback(&global);
+    }
 }
 
 /// # Safety
@@ -48,9 +114,51 @@ impl Global {
             ty: global_type,
             vm_global_definition: Box::new(UnsafeCell::new(VMGlobalDefinition::new())),
             lock: Mutex::new(()),
+            observer: Mutex::new(None),
         }
     }
 
+    /// Invokes `callback` whenever the Wasm code changes the value of
+    /// the global, replacing the previous observer, if any.
+    ///
+    /// The Wasm code writes the globals directly, so the changes are
+    /// detected when it calls a host function or returns to the host,
+    /// and the observer is invoked once for several writes in between.
+    /// The writes of the host aren't observed.
+    pub fn observe(self: &Arc<Self>, callback: GlobalObserverCallback) {
+        let value = {
+            let _global_guard = self.lock.lock().unwrap();
+            unsafe { (*self.vm_global_definition.get()).to_u128() }
+        };
+        let previous = self
+            .observer
+            .lock()
+            .unwrap()
+            .replace(GlobalObserver { value, callback });
+
+        if previous.is_none() {
+            let mut observed = OBSERVED_GLOBALS.lock().unwrap();
+            observed.push(Arc::downgrade(self));
+            OBSERVED_GLOBALS_COUNT.store(observed.len(), Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the callback of the observer, if the value has changed
+    /// since it has last seen it.
+    fn observed_change(&self) -> Option<GlobalObserverCallback> {
+        let _global_guard = self.lock.lock().unwrap();
+        let value = unsafe { (*self.vm_global_definition.get()).to_u128() };
+
+        let mut observer = self.observer.lock().unwrap();
+        let observer = observer.as_mut()?;
+        if observer.value == value {
+            return None;
+        }
+        observer.value = value;
+
+        Some(observer.callback.clone())
+    }
+
     /// Get the type of the global.
     pub fn ty(&self) -> &GlobalType {
         &self.ty
@@ -63,6 +171,17 @@ impl Global {
         unsafe { NonNull::new_unchecked(ptr) }
     }
 
+    /// Get the function reference held by the global, if it's a
+    /// `funcref` global.
+    pub(crate) fn funcref(&self) -> Option<VMFuncRef> {
+        if self.ty.ty != Type::FuncRef {
+            return None;
+        }
+        let _global_guard = self.lock.lock().unwrap();
+        let definition = unsafe { &*self.vm_global_definition.get() };
+        Some(definition.to_funcref())
+    }
+
     /// Get a value from the global.
     // TODO(reftypes): the `&dyn Any` here for `Store` is a work-around for the fact
     // that `Store` is defined in `API` when we need it earlier. Ideally this should
@@ -134,6 +253,10 @@ impl Global {
                 r.write_value_to(definition.as_u128_mut() as *mut u128 as *mut i128)
             }
         }
+        // The observer is only notified of the writes of the Wasm code.
+        if let Some(observer) = self.observer.lock().unwrap().as_mut() {
+            observer.value = definition.to_u128();
+        }
         Ok(())
     }
 }
@@ -9,16 +9,19 @@
 
 mod allocator;
 mod r#ref;
+mod snapshot;
 
 pub use allocator::InstanceAllocator;
 pub use r#ref::{InstanceRef, WeakInstanceRef, WeakOrStrongInstanceRef};
+pub use snapshot::InstanceSnapshot;
 
 use crate::export::VMExtern;
 use crate::func_data_registry::VMFuncRef;
 use crate::global::Global;
 use crate::imports::Imports;
-use crate::memory::{Memory, MemoryError};
+use crate::memory::{Memory, MemoryError, MemoryStats};
 use crate::table::{Table, TableElement};
+use crate::tag::Tag;
 use crate::trap::{catch_traps, Trap, TrapCode, TrapHandler};
 use crate::vmcontext::{
     VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionEnvironment,
@@ -26,7 +29,7 @@ use crate::vmcontext::{
     VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition, VMTableImport, VMTrampoline,
 };
 use crate::{FunctionBodyPtr, VMFunctionBody, VMOffsets};
-use crate::{VMFunction, VMGlobal, VMMemory, VMTable};
+use crate::{VMFunction, VMGlobal, VMMemory, VMTable, VMTag};
 use memoffset::offset_of;
 use more_asserts::assert_lt;
 use std::any::Any;
@@ -38,12 +41,12 @@ use std::fmt;
 use std::mem;
 use std::ptr::{self, NonNull};
 use std::slice;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
-    DataIndex, DataInitializer, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, GlobalInit,
-    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    ModuleInfo, Pages, SignatureIndex, TableIndex, TableInitializer,
+    DataIndex, DataInitializer, ElemIndex, ExportIndex, ExternRef, FunctionIndex, GlobalIndex,
+    GlobalInit, LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex,
+    MemoryIndex, ModuleInfo, Pages, SignatureIndex, TableIndex, TableInitializer, TagIndex, Type,
 };
 
 /// The function pointer to call with data and an [`Instance`] pointer to
@@ -51,6 +54,9 @@ use wasmer_types::{
 pub type ImportInitializerFuncPtr<ResultErr = *mut ffi::c_void> =
     fn(*mut ffi::c_void, *const ffi::c_void) -> Result<(), ResultErr>;
 
+/// A host callback run when an instance is deallocated.
+pub type TeardownCallback = Box<dyn FnOnce() + Send>;
+
 /// A WebAssembly instance.
 ///
 /// The type is dynamically-sized. Indeed, the `vmctx` field can
@@ -74,6 +80,9 @@ pub(crate) struct Instance {
     /// WebAssembly global data.
     globals: BoxedSlice<LocalGlobalIndex, Arc<Global>>,
 
+    /// WebAssembly exception tags, imported and local.
+    tags: BoxedSlice<TagIndex, Arc<Tag>>,
+
     /// Pointers to functions in executable memory.
     functions: BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
 
@@ -103,6 +112,10 @@ pub(crate) struct Instance {
     /// functions from other Wasm modules.
     imported_function_envs: BoxedSlice<FunctionIndex, ImportFunctionEnv>,
 
+    /// Host callbacks run when the instance is deallocated, see
+    /// [`InstanceHandle::on_teardown`].
+    teardown_callbacks: Mutex<Vec<TeardownCallback>>,
+
     /// Additional context used by compiled WebAssembly code. This
     /// field is last, and represents a dynamically-sized array that
     /// extends beyond the nominal end of the struct (similar to a
@@ -212,6 +225,14 @@ impl Instance {
         &self.module
     }
 
+    /// Runs the teardown callbacks, in the order they were registered.
+    fn run_teardown_callbacks(&self) {
+        let callbacks = mem::take(&mut *self.teardown_callbacks.lock().unwrap());
+        for callback in callbacks {
+            callback();
+        }
+    }
+
     pub(crate) fn module_ref(&self) -> &ModuleInfo {
         &*self.module
     }
@@ -825,6 +846,11 @@ impl Instance {
         Ok(())
     }
 
+    /// Get the tag with the given index.
+    pub(crate) fn get_tag(&self, tag_index: TagIndex) -> &Arc<Tag> {
+        &self.tags[tag_index]
+    }
+
     /// Drop the given data segment, truncating its length to zero.
     pub(crate) fn data_drop(&self, data_index: DataIndex) {
         let mut passive_data = self.passive_data.borrow_mut();
@@ -906,6 +932,19 @@ impl InstanceHandle {
             .collect::<PrimaryMap<LocalGlobalIndex, _>>()
             .into_boxed_slice();
         let passive_data = RefCell::new(module.passive_data.clone());
+        let tags = imports
+            .tags
+            .values()
+            .cloned()
+            .chain(
+                module
+                    .tags
+                    .keys()
+                    .skip(module.num_imported_tags)
+                    .map(|index| Arc::new(Tag::new(module.tag_type(index)))),
+            )
+            .collect::<PrimaryMap<TagIndex, _>>()
+            .into_boxed_slice();
 
         let handle = {
             let offsets = allocator.offsets().clone();
@@ -918,6 +957,7 @@ impl InstanceHandle {
                 memories: finished_memories,
                 tables: finished_tables,
                 globals: finished_globals,
+                tags,
                 functions: finished_functions,
                 function_call_trampolines: finished_function_call_trampolines,
                 passive_elements: Default::default(),
@@ -925,6 +965,7 @@ impl InstanceHandle {
                 host_state,
                 funcrefs,
                 imported_function_envs,
+                teardown_callbacks: Mutex::new(Vec::new()),
                 vmctx: VMContext {},
             };
 
@@ -1137,6 +1178,11 @@ impl InstanceHandle {
                 }
                 .into()
             }
+            ExportIndex::Tag(index) => VMTag {
+                from: instance_ref.tags[*index].clone(),
+                instance_ref: Some(WeakOrStrongInstanceRef::Strong(instance)),
+            }
+            .into(),
         }
     }
 
@@ -1154,6 +1200,23 @@ impl InstanceHandle {
         self.instance().as_ref().host_state()
     }
 
+    /// Registers `callback`, run when the instance is deallocated, once
+    /// the handles and the exports referencing it are all dropped.
+    pub fn on_teardown(&self, callback: TeardownCallback) {
+        self.instance()
+            .as_ref()
+            .teardown_callbacks
+            .lock()
+            .unwrap()
+            .push(callback);
+    }
+
+    /// Whether this handle is the only reference keeping the instance
+    /// alive, i.e. whether dropping it deallocates the instance.
+    pub fn is_unique(&self) -> bool {
+        self.instance().is_unique()
+    }
+
     /// Return the memory index for the given `VMMemoryDefinition` in this instance.
     pub fn memory_index(&self, memory: &VMMemoryDefinition) -> LocalMemoryIndex {
         self.instance().as_ref().memory_index(memory)
@@ -1174,6 +1237,21 @@ impl InstanceHandle {
         self.instance().as_ref().memory_grow(memory_index, delta)
     }
 
+    /// Return the usage statistics of the memories of this instance,
+    /// imported ones included, by memory index.
+    pub fn memory_stats(&self) -> Vec<MemoryStats> {
+        let instance = self.instance().as_ref();
+        instance
+            .module
+            .memories
+            .keys()
+            .map(|index| match instance.module.local_memory_index(index) {
+                Some(local_index) => instance.memories[local_index].stats(),
+                None => instance.imported_memory(index).from.stats(),
+            })
+            .collect()
+    }
+
     /// Return the table index for the given `VMTableDefinition` in this instance.
     pub fn table_index(&self, table: &VMTableDefinition) -> LocalTableIndex {
         self.instance().as_ref().table_index(table)
@@ -1218,6 +1296,42 @@ impl InstanceHandle {
         self.instance().as_ref().get_local_table(index)
     }
 
+    /// The non-null `externref`s held by the tables and the globals
+    /// defined by this instance.
+    ///
+    /// The `externref`s of imported tables and globals are held by the
+    /// instance or the host defining them, and aren't returned.
+    pub fn externref_roots(&self) -> Vec<ExternRef> {
+        let instance = self.instance().as_ref();
+        let mut roots = Vec::new();
+
+        for table in instance.tables.values() {
+            if table.ty().ty != Type::ExternRef {
+                continue;
+            }
+            for index in 0..table.size() {
+                if let Some(TableElement::ExternRef(extern_ref)) = table.get(index) {
+                    if !extern_ref.is_null() {
+                        roots.push(extern_ref);
+                    }
+                }
+            }
+        }
+
+        for index in instance.globals.keys() {
+            let global_index = instance.module.global_index(index);
+            if instance.module.globals[global_index].ty != Type::ExternRef {
+                continue;
+            }
+            let extern_ref = instance.global(index).to_externref();
+            if !extern_ref.is_null() {
+                roots.push(extern_ref.ref_clone().into());
+            }
+        }
+
+        roots
+    }
+
     /// Initializes the host environments.
     ///
     /// # Safety
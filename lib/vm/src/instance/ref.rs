@@ -3,6 +3,7 @@ use std::alloc::Layout;
 use std::convert::TryFrom;
 use std::ptr::{self, NonNull};
 use std::sync::{Arc, Weak};
+use wasmer_types::ModuleInfo;
 
 /// Dynamic instance allocation.
 ///
@@ -70,8 +71,10 @@ impl PartialEq for InstanceInner {
 }
 
 impl Drop for InstanceInner {
-    /// Drop the `InstanceInner`.
+    /// Drop the `InstanceInner`, after running the teardown callbacks
+    /// of the instance, which is still valid.
     fn drop(&mut self) {
+        self.as_ref().run_teardown_callbacks();
         unsafe { Self::deallocate_instance(self) };
     }
 }
@@ -126,6 +129,17 @@ impl InstanceRef {
         (&*self.0).as_ref()
     }
 
+    /// The module the `Instance` is an instance of.
+    pub fn module(&self) -> &Arc<ModuleInfo> {
+        &self.as_ref().module
+    }
+
+    /// Whether this is the only reference to the `Instance`.
+    #[inline]
+    pub(super) fn is_unique(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
+
     /// Only succeeds if ref count is 1.
     #[inline]
     pub(super) fn as_mut(&mut self) -> Option<&mut Instance> {
@@ -164,6 +178,11 @@ impl WeakInstanceRef {
         let inner = self.0.upgrade()?;
         Some(InstanceRef(inner))
     }
+
+    /// Whether the `Instance` is still alive, without keeping it alive.
+    pub fn is_alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
 }
 
 /// An `InstanceRef` that may or may not be keeping the `Instance` alive.
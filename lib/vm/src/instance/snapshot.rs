@@ -0,0 +1,216 @@
+//! Snapshots of the state of instances, to start new instances from.
+
+use super::{initialize_tables, Instance, InstanceHandle, InstanceRef};
+use crate::memory::Memory;
+use crate::trap::Trap;
+use std::fmt;
+use std::sync::Arc;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{Bytes, LocalGlobalIndex, Mutability, Pages, Type};
+
+/// The state of an instance: the contents of its local memories, and the
+/// values of its mutable numeric globals.
+///
+/// Tables, imported memories and imported globals aren't part of the
+/// snapshot.
+#[derive(Debug)]
+pub struct InstanceSnapshot {
+    memories: Vec<MemoryImage>,
+    globals: Vec<(LocalGlobalIndex, [u8; 16])>,
+}
+
+/// The contents of a linear memory.
+struct MemoryImage {
+    pages: Pages,
+    data: ImageData,
+}
+
+enum ImageData {
+    /// The contents are in an in-memory file, mapped copy-on-write into
+    /// the memories restored from the image.
+    #[cfg(target_os = "linux")]
+    File(std::fs::File),
+    /// The contents are copied into the memories restored from the image.
+    Bytes(Box<[u8]>),
+}
+
+impl fmt::Debug for MemoryImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryImage")
+            .field("pages", &self.pages)
+            .finish()
+    }
+}
+
+impl MemoryImage {
+    fn new(pages: Pages, bytes: &[u8]) -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(file) = memfd(bytes) {
+                return Self {
+                    pages,
+                    data: ImageData::File(file),
+                };
+            }
+        }
+
+        Self {
+            pages,
+            data: ImageData::Bytes(bytes.into()),
+        }
+    }
+
+    /// Grows `memory` to the size of the image, and fills it with the
+    /// image.
+    unsafe fn restore(&self, memory: &Arc<dyn Memory>) -> Result<(), Trap> {
+        let current = memory.size();
+        if current < self.pages {
+            memory
+                .grow(Pages(self.pages.0 - current.0))
+                .map_err(|error| Trap::User(Box::new(error)))?;
+        }
+
+        let definition = memory.vmmemory().as_ref();
+        let len = Bytes::from(self.pages).0;
+        if len == 0 {
+            return Ok(());
+        }
+
+        match &self.data {
+            #[cfg(target_os = "linux")]
+            ImageData::File(file) if memory.is_mmap_backed() => {
+                use std::os::unix::io::AsRawFd;
+
+                let ptr = libc::mmap(
+                    definition.base as *mut libc::c_void,
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_FIXED,
+                    file.as_raw_fd(),
+                    0,
+                );
+                if ptr as isize == -1_isize {
+                    return Err(Trap::User(Box::new(std::io::Error::last_os_error())));
+                }
+            }
+            #[cfg(target_os = "linux")]
+            ImageData::File(file) => {
+                use std::os::unix::fs::FileExt;
+
+                let bytes = std::slice::from_raw_parts_mut(definition.base, len);
+                file.read_exact_at(bytes, 0)
+                    .map_err(|error| Trap::User(Box::new(error)))?;
+            }
+            ImageData::Bytes(bytes) => {
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), definition.base, len);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes `bytes` to an anonymous in-memory file.
+#[cfg(target_os = "linux")]
+fn memfd(bytes: &[u8]) -> Option<std::fs::File> {
+    use std::io::Write;
+    use std::os::unix::io::FromRawFd;
+
+    let fd = unsafe { libc::memfd_create(b"wasmer-snapshot\0".as_ptr() as _, libc::MFD_CLOEXEC) };
+    if fd == -1 {
+        return None;
+    }
+
+    let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+    file.write_all(bytes).ok()?;
+
+    Some(file)
+}
+
+impl InstanceRef {
+    /// Captures the state of the instance.
+    ///
+    /// The contents of the memories are copied into the snapshot.
+    pub fn snapshot(&self) -> InstanceSnapshot {
+        let instance = self.as_ref();
+
+        let memories = instance
+            .memories
+            .values()
+            .map(|memory| {
+                let pages = memory.size();
+                let bytes = unsafe {
+                    let definition = memory.vmmemory().as_ref();
+                    std::slice::from_raw_parts(definition.base, definition.current_length)
+                };
+                MemoryImage::new(pages, bytes)
+            })
+            .collect();
+
+        let globals = (0..instance.globals.len())
+            .map(LocalGlobalIndex::new)
+            .filter(|index| is_snapshotted(instance, *index))
+            .map(|index| (index, instance.global(index).to_bytes()))
+            .collect();
+
+        InstanceSnapshot { memories, globals }
+    }
+}
+
+impl InstanceHandle {
+    /// Captures the state of the instance.
+    pub fn snapshot(&self) -> InstanceSnapshot {
+        self.instance().snapshot()
+    }
+
+    /// Finishes the instantiation process started by `Instance::new` from
+    /// a snapshot of another instance of the same module: the tables are
+    /// initialized, and the memories and globals restored from the
+    /// snapshot. The data initializers aren't applied, and the start
+    /// function isn't called, as their effects are in the snapshot.
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after instantiation, with a snapshot
+    /// of an instance of the same module.
+    pub unsafe fn finish_instantiation_from_snapshot(
+        &self,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<(), Trap> {
+        let instance = self.instance().as_ref();
+        assert_eq!(
+            instance.memories.len(),
+            snapshot.memories.len(),
+            "the snapshot was taken from an instance of another module"
+        );
+
+        initialize_tables(instance)?;
+
+        for (memory, image) in instance.memories.values().zip(&snapshot.memories) {
+            image.restore(memory)?;
+        }
+
+        for (index, bytes) in &snapshot.globals {
+            *instance.global_ptr(*index).as_mut().as_bytes_mut() = *bytes;
+        }
+
+        Ok(())
+    }
+}
+
+/// Only the mutable numeric globals are snapshotted: the other globals
+/// keep the value of their initializer, which is the same in every
+/// instance or refers to the instance itself.
+fn is_snapshotted(instance: &Instance, index: LocalGlobalIndex) -> bool {
+    let global = &instance.module.globals[instance.module.global_index(index)];
+    global.mutability == Mutability::Var
+        && matches!(
+            global.ty,
+            Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::V128
+        )
+}
+
+fn _assert() {
+    fn _assert_send_sync<T: Send + Sync>() {}
+    _assert_send_sync::<InstanceSnapshot>();
+}
@@ -30,30 +30,36 @@ mod mmap;
 mod probestack;
 mod sig_registry;
 mod table;
+mod tag;
 mod trap;
 mod vmcontext;
+mod waiters;
 
 pub mod libcalls;
 
 pub use crate::export::*;
-pub use crate::func_data_registry::{FuncDataRegistry, VMFuncRef};
+pub use crate::func_data_registry::{
+    FuncDataCollection, FuncDataOwner, FuncDataRegistry, VMFuncRef,
+};
 pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{
-    ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle,
-    WeakOrStrongInstanceRef,
+    ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle, InstanceRef,
+    InstanceSnapshot, TeardownCallback, WeakInstanceRef, WeakOrStrongInstanceRef,
 };
-pub use crate::memory::{LinearMemory, Memory, MemoryError};
+pub use crate::memory::{FileMapping, LinearMemory, Memory, MemoryError, MemoryPool, MemoryStats};
 pub use crate::mmap::Mmap;
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
 pub use crate::table::{LinearTable, Table, TableElement};
+pub use crate::tag::{exception_pending, Exception, Tag};
 pub use crate::trap::*;
 pub use crate::vmcontext::{
     VMCallerCheckedAnyfunc, VMContext, VMDynamicFunctionContext, VMFunctionEnvironment,
     VMFunctionImport, VMFunctionKind, VMGlobalDefinition, VMGlobalImport, VMMemoryDefinition,
     VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition, VMTableImport, VMTrampoline,
 };
+pub use crate::waiters::{memory_notify, memory_wait32};
 pub use wasmer_types::LibCall;
 pub use wasmer_types::MemoryStyle;
 pub use wasmer_types::TableStyle;
@@ -38,15 +38,17 @@
 #![allow(missing_docs)] // For some reason lint fails saying that `LibCall` is not documented, when it actually is
 
 use crate::func_data_registry::VMFuncRef;
+use crate::memory::MemoryError;
 use crate::probestack::PROBESTACK;
 use crate::table::{RawTableElement, TableElement};
-use crate::trap::{raise_lib_trap, Trap, TrapCode};
+use crate::tag::{self, Exception};
+use crate::trap::{raise_lib_trap, raise_user_trap, Trap, TrapCode};
 use crate::vmcontext::VMContext;
 use crate::{on_host_stack, VMExternRef};
 pub use wasmer_types::LibCall;
 use wasmer_types::{
-    DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    TableIndex, Type,
+    DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, Pages,
+    TableIndex, TagIndex, Type,
 };
 
 /// Implementation of f32.ceil
@@ -139,6 +141,17 @@ pub extern "C" fn wasmer_vm_f64_nearest(x: f64) -> f64 {
     }
 }
 
+/// The result of `memory.grow`: the previous size, or -1 if the memory
+/// couldn't grow. Traps if the host is out of memory, see
+/// [`MemoryError::OutOfMemory`].
+unsafe fn memory_grow_result(result: Result<Pages, MemoryError>) -> u32 {
+    match result {
+        Ok(pages) => pages.0,
+        Err(error @ MemoryError::OutOfMemory { .. }) => raise_user_trap(Box::new(error)),
+        Err(_) => u32::max_value(),
+    }
+}
+
 /// Implementation of memory.grow for locally-defined 32-bit memories.
 ///
 /// # Safety
@@ -150,15 +163,13 @@ pub unsafe extern "C" fn wasmer_vm_memory32_grow(
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (&*vmctx).instance();
         let memory_index = LocalMemoryIndex::from_u32(memory_index);
 
-        instance
-            .memory_grow(memory_index, delta)
-            .map(|pages| pages.0)
-            .unwrap_or(u32::max_value())
-    })
+        instance.memory_grow(memory_index, delta)
+    });
+    memory_grow_result(result)
 }
 
 /// Implementation of memory.grow for imported 32-bit memories.
@@ -172,15 +183,13 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_grow(
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (&*vmctx).instance();
         let memory_index = MemoryIndex::from_u32(memory_index);
 
-        instance
-            .imported_memory_grow(memory_index, delta)
-            .map(|pages| pages.0)
-            .unwrap_or(u32::max_value())
-    })
+        instance.imported_memory_grow(memory_index, delta)
+    });
+    memory_grow_result(result)
 }
 
 /// Implementation of memory.size for locally-defined 32-bit memories.
@@ -680,6 +689,94 @@ pub unsafe extern "C" fn wasmer_vm_raise_trap(trap_code: TrapCode) -> ! {
     raise_lib_trap(trap)
 }
 
+/// Implementation of `throw`, throwing an exception with the tag
+/// `tag_index` and the values at `values`.
+///
+/// The Wasm code then unwinds to the handler of the exception.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable, and `values` must point to as many
+/// 16-byte values, which need not be aligned, as the tag has parameters.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_throw(vmctx: *mut VMContext, tag_index: u32, values: *const u8) {
+    on_host_stack(|| {
+        let tag_index = TagIndex::from_u32(tag_index);
+        let instance = (&*vmctx).instance();
+        let tag = instance.get_tag(tag_index).clone();
+        let values = (0..tag.ty().params().len())
+            .map(|i| std::ptr::read_unaligned((values as *const u128).add(i)))
+            .collect();
+        tag::throw(Exception::new(tag, values));
+    })
+}
+
+/// Implementation of `rethrow`, throwing the exception caught in `slot`
+/// again.
+///
+/// # Safety
+///
+/// An exception must have been caught in `slot`.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_rethrow(slot: u32) {
+    on_host_stack(|| tag::rethrow(slot as usize))
+}
+
+/// Returns 1 if an exception is being thrown, after a call, and 0
+/// otherwise.
+#[no_mangle]
+pub extern "C" fn wasmer_vm_exception_pending() -> u32 {
+    tag::exception_pending() as u32
+}
+
+/// Returns 1 if the exception being thrown has the tag `tag_index`, for
+/// `catch`, and 0 otherwise.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_matches(vmctx: *mut VMContext, tag_index: u32) -> u32 {
+    let tag_index = TagIndex::from_u32(tag_index);
+    let instance = (&*vmctx).instance();
+    tag::exception_matches(instance.get_tag(tag_index)) as u32
+}
+
+/// Implementation of `catch` and `catch_all`, catching the exception
+/// being thrown in `slot` and writing its values to `values`, if it
+/// isn't null.
+///
+/// # Safety
+///
+/// `values` must be null or point to as many 16-byte values, which need
+/// not be aligned, as the tag of the exception has parameters.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_catch(slot: u32, values: *mut u8) {
+    on_host_stack(|| {
+        if let Some(exception) = tag::catch_exception(slot as usize) {
+            if !values.is_null() {
+                for (i, value) in exception.raw_values().iter().enumerate() {
+                    std::ptr::write_unaligned((values as *mut u128).add(i), *value);
+                }
+            }
+        }
+    })
+}
+
+/// Returns the number of slots of the caught exceptions, which the
+/// slots of the exceptions caught by a function follow.
+#[no_mangle]
+pub extern "C" fn wasmer_vm_exception_slots() -> u32 {
+    tag::exception_slots() as u32
+}
+
+/// Releases the exceptions caught from `slots` on, when a function
+/// which caught exceptions returns.
+#[no_mangle]
+pub extern "C" fn wasmer_vm_exception_release(slots: u32) {
+    on_host_stack(|| tag::release_exceptions(slots as usize))
+}
+
 /// Probestack check
 ///
 /// # Safety
@@ -10,10 +10,11 @@ use crate::vmcontext::VMMemoryDefinition;
 use more_asserts::assert_ge;
 use std::borrow::BorrowMut;
 use std::cell::UnsafeCell;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use wasmer_types::{Bytes, MemoryStyle, MemoryType, Pages};
 
@@ -32,6 +33,16 @@ pub enum MemoryError {
         /// The attempted amount to grow by in pages.
         attempted_delta: Pages,
     },
+    /// The host denied the growth because it's out of memory, which
+    /// traps the Wasm code growing the memory, rather than failing its
+    /// `memory.grow`.
+    #[error("The host is out of memory: current size {} pages, requested increase: {} pages", current.0, attempted_delta.0)]
+    OutOfMemory {
+        /// The current size in pages.
+        current: Pages,
+        /// The attempted amount to grow by in pages.
+        attempted_delta: Pages,
+    },
     /// The operation would cause the size of the memory size exceed the maximum.
     #[error("The memory is invalid because {}", reason)]
     InvalidMemory {
@@ -77,6 +88,77 @@ pub trait Memory: fmt::Debug + Send + Sync {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Whether the accessible bytes of the memory are pages of an anonymous
+    /// mapping owned by the memory, which may be replaced by other mappings,
+    /// e.g. copy-on-write mappings of a snapshot.
+    fn is_mmap_backed(&self) -> bool {
+        false
+    }
+
+    /// Maps the first `len` bytes of `file` over the start of the
+    /// memory, which must be at least as large, e.g. for
+    /// `Memory::new_from_file` in the `wasmer` crate.
+    ///
+    /// The memories which can't map files, which is the case of the
+    /// memories not implementing it, return an error.
+    #[cfg(unix)]
+    fn map_file(
+        &self,
+        _file: &std::fs::File,
+        _len: usize,
+        _mapping: FileMapping,
+    ) -> Result<(), MemoryError> {
+        Err(MemoryError::Generic(
+            "the memory can't map files".to_string(),
+        ))
+    }
+
+    /// Writes the changes to the file this memory maps, if any, back to
+    /// the file.
+    fn flush(&self) -> Result<(), MemoryError> {
+        Ok(())
+    }
+
+    /// Prevents the memory from moving when it grows, until [`Memory::unpin`]
+    /// is called, so the host can keep references into it. The grows which
+    /// would move the memory fail in the meantime. Pins are counted, and
+    /// the memory can move again once every pin is released.
+    ///
+    /// Returns `false` if the memory can't be pinned, which is the case of
+    /// the memories not implementing it.
+    fn pin(&self) -> bool {
+        false
+    }
+
+    /// Releases a pin taken with [`Memory::pin`].
+    fn unpin(&self) {}
+
+    /// Returns the usage statistics of this memory.
+    ///
+    /// Memories which don't track their growth report their current size
+    /// as their peak, and no grow events.
+    fn stats(&self) -> MemoryStats {
+        let size = self.size();
+        MemoryStats {
+            current: size,
+            peak: size,
+            grows: 0,
+        }
+    }
+}
+
+/// Usage statistics of a memory, e.g. to bill or monitor the memory of
+/// the guests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The current size of the memory.
+    pub current: Pages,
+    /// The largest size the memory has had.
+    pub peak: Pages,
+    /// The number of times the memory successfully grew, not counting
+    /// grows by 0 pages.
+    pub grows: u64,
 }
 
 /// A linear memory instance.
@@ -100,6 +182,9 @@ pub struct LinearMemory {
 
     /// The owned memory definition used by the generated code
     vm_memory_definition: VMMemoryDefinitionOwnership,
+
+    /// The pool the allocation goes back to when the memory is dropped.
+    pool: Option<Arc<MemoryPool>>,
 }
 
 /// A type to help manage who is responsible for the backing memory of them
@@ -133,6 +218,27 @@ struct WasmMmap {
     alloc: Mmap,
     // The current logical size in wasm pages of this linear memory.
     size: Pages,
+    // The largest logical size in wasm pages this linear memory has had.
+    peak: Pages,
+    // The number of times this linear memory grew.
+    grows: u64,
+    // How a file is mapped at the start of the allocation, and the
+    // length of the mapping.
+    file: Option<(FileMapping, usize)>,
+    // The number of pins preventing the allocation from moving.
+    pins: usize,
+}
+
+/// How [`LinearMemory::map_file`] maps a file into a memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileMapping {
+    /// The writes to the memory stay private to it, and the pages are
+    /// only copied when first written to.
+    CopyOnWrite,
+    /// The writes to the memory go to the file, and are visible to the
+    /// other mappings of the file. [`Memory::flush`] waits for them to
+    /// reach the file.
+    Shared,
 }
 
 impl LinearMemory {
@@ -141,7 +247,17 @@ impl LinearMemory {
     /// This creates a `LinearMemory` with owned metadata: this can be used to create a memory
     /// that will be imported into Wasm modules.
     pub fn new(memory: &MemoryType, style: &MemoryStyle) -> Result<Self, MemoryError> {
-        unsafe { Self::new_internal(memory, style, None) }
+        unsafe { Self::new_internal(memory, style, None, None) }
+    }
+
+    /// Like [`LinearMemory::new`], but reusing an allocation of `pool` if
+    /// possible, and giving the allocation back to `pool` when dropped.
+    pub fn new_pooled(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        pool: &Arc<MemoryPool>,
+    ) -> Result<Self, MemoryError> {
+        unsafe { Self::new_internal(memory, style, None, Some(pool)) }
     }
 
     /// Create a new linear memory instance with specified minimum and maximum number of wasm pages.
@@ -156,7 +272,22 @@ impl LinearMemory {
         style: &MemoryStyle,
         vm_memory_location: NonNull<VMMemoryDefinition>,
     ) -> Result<Self, MemoryError> {
-        Self::new_internal(memory, style, Some(vm_memory_location))
+        Self::new_internal(memory, style, Some(vm_memory_location), None)
+    }
+
+    /// Like [`LinearMemory::from_definition`], but reusing an allocation of
+    /// `pool` if possible, and giving the allocation back to `pool` when
+    /// dropped.
+    ///
+    /// # Safety
+    /// - `vm_memory_location` must point to a valid location in VM memory.
+    pub unsafe fn from_definition_pooled(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        vm_memory_location: NonNull<VMMemoryDefinition>,
+        pool: &Arc<MemoryPool>,
+    ) -> Result<Self, MemoryError> {
+        Self::new_internal(memory, style, Some(vm_memory_location), Some(pool))
     }
 
     /// Build a `LinearMemory` with either self-owned or VM owned metadata.
@@ -164,6 +295,7 @@ impl LinearMemory {
         memory: &MemoryType,
         style: &MemoryStyle,
         vm_memory_location: Option<NonNull<VMMemoryDefinition>>,
+        pool: Option<&Arc<MemoryPool>>,
     ) -> Result<Self, MemoryError> {
         if memory.minimum > Pages::max_value() {
             return Err(MemoryError::MinimumMemoryTooLarge {
@@ -203,10 +335,18 @@ impl LinearMemory {
         let mapped_pages = memory.minimum;
         let mapped_bytes = mapped_pages.bytes();
 
+        let alloc = match pool {
+            Some(pool) => pool.take(mapped_bytes.0, request_bytes),
+            None => Mmap::accessible_reserved(mapped_bytes.0, request_bytes),
+        }
+        .map_err(MemoryError::Region)?;
         let mut mmap = WasmMmap {
-            alloc: Mmap::accessible_reserved(mapped_bytes.0, request_bytes)
-                .map_err(MemoryError::Region)?,
+            alloc,
             size: memory.minimum,
+            peak: memory.minimum,
+            grows: 0,
+            file: None,
+            pins: 0,
         };
 
         let base_ptr = mmap.alloc.as_mut_ptr();
@@ -233,6 +373,7 @@ impl LinearMemory {
             },
             memory: *memory,
             style: style.clone(),
+            pool: pool.cloned(),
         })
     }
 
@@ -324,6 +465,14 @@ impl Memory for LinearMemory {
         if new_bytes > mmap.alloc.len() - self.offset_guard_size {
             // If the new size is within the declared maximum, but needs more memory than we
             // have on hand, it's a dynamic heap and it can move.
+            if mmap.pins > 0 || matches!(mmap.file, Some((FileMapping::Shared, _))) {
+                // Moving would invalidate the pinned references, or stop
+                // writing to the file.
+                return Err(MemoryError::CouldNotGrow {
+                    current: mmap.size,
+                    attempted_delta: delta,
+                });
+            }
             let guard_bytes = self.offset_guard_size;
             let request_bytes =
                 new_bytes
@@ -340,6 +489,7 @@ impl Memory for LinearMemory {
             new_mmap.as_mut_slice()[..copy_len].copy_from_slice(&mmap.alloc.as_slice()[..copy_len]);
 
             mmap.alloc = new_mmap;
+            mmap.file = None;
         } else if delta_bytes > 0 {
             // Make the newly allocated pages accessible.
             mmap.alloc
@@ -348,6 +498,8 @@ impl Memory for LinearMemory {
         }
 
         mmap.size = new_pages;
+        mmap.peak = mmap.peak.max(new_pages);
+        mmap.grows += 1;
 
         // update memory definition
         unsafe {
@@ -365,4 +517,282 @@ impl Memory for LinearMemory {
         let _mmap_guard = self.mmap.lock().unwrap();
         unsafe { self.get_vm_memory_definition() }
     }
+
+    fn is_mmap_backed(&self) -> bool {
+        true
+    }
+
+    fn pin(&self) -> bool {
+        self.mmap.lock().unwrap().pins += 1;
+        true
+    }
+
+    fn unpin(&self) {
+        let mut mmap = self.mmap.lock().unwrap();
+        debug_assert!(mmap.pins > 0);
+        mmap.pins -= 1;
+    }
+
+    /// The memory keeps the mapping until it has to move to grow, which
+    /// copies the contents of a [`FileMapping::CopyOnWrite`] mapping, and
+    /// fails for a [`FileMapping::Shared`] one. The file must not shrink
+    /// under `len` bytes while it is mapped: accessing the pages past its
+    /// end would crash the process.
+    #[cfg(unix)]
+    fn map_file(
+        &self,
+        file: &std::fs::File,
+        len: usize,
+        mapping: FileMapping,
+    ) -> Result<(), MemoryError> {
+        use std::os::unix::io::AsRawFd;
+
+        let mut mmap = self.mmap.lock().unwrap();
+        if mmap.file.is_some() {
+            return Err(MemoryError::Generic(
+                "a file is already mapped in the memory".to_string(),
+            ));
+        }
+        if len > mmap.size.bytes().0 {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "the file ({} bytes) is larger than the memory ({} pages)",
+                    len, mmap.size.0
+                ),
+            });
+        }
+        if len == 0 {
+            return Ok(());
+        }
+
+        // The end of the last page past the end of the file reads as
+        // zeros, and the pages after it stay anonymous.
+        let page_size = region::page::size();
+        let map_len = (len + page_size - 1) & !(page_size - 1);
+        let flags = match mapping {
+            FileMapping::CopyOnWrite => libc::MAP_PRIVATE,
+            FileMapping::Shared => libc::MAP_SHARED,
+        };
+        let ptr = unsafe {
+            libc::mmap(
+                mmap.alloc.as_mut_ptr() as *mut libc::c_void,
+                map_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags | libc::MAP_FIXED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(MemoryError::Region(
+                std::io::Error::last_os_error().to_string(),
+            ));
+        }
+        mmap.file = Some((mapping, map_len));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn flush(&self) -> Result<(), MemoryError> {
+        let mmap = self.mmap.lock().unwrap();
+        if let Some((FileMapping::Shared, len)) = mmap.file {
+            let ret = unsafe {
+                libc::msync(mmap.alloc.as_ptr() as *mut libc::c_void, len, libc::MS_SYNC)
+            };
+            if ret != 0 {
+                return Err(MemoryError::Region(
+                    std::io::Error::last_os_error().to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the usage statistics of this memory.
+    fn stats(&self) -> MemoryStats {
+        let mmap = self.mmap.lock().unwrap();
+        MemoryStats {
+            current: mmap.size,
+            peak: mmap.peak,
+            grows: mmap.grows,
+        }
+    }
+}
+
+impl Drop for LinearMemory {
+    fn drop(&mut self) {
+        if let Some(pool) = &self.pool {
+            let mmap = match self.mmap.get_mut() {
+                Ok(mmap) => mmap,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            pool.give_back(std::mem::replace(&mut mmap.alloc, Mmap::new()));
+        }
+    }
+}
+
+/// A pool of allocations reused across linear memories.
+///
+/// Reserving the address space of a linear memory, several GiB for a
+/// static memory, is a large part of the cost of an instantiation. A
+/// memory created with a pool gives its allocation back to the pool when
+/// dropped, after zeroing it, and the next memory of the same reserved
+/// size reuses the allocation instead of mapping a new one.
+#[derive(Debug)]
+pub struct MemoryPool {
+    /// The idle allocations, by reserved size.
+    idle: Mutex<HashMap<usize, Vec<Mmap>>>,
+    /// The maximum number of idle allocations kept for every reserved size.
+    max_idle: usize,
+}
+
+impl MemoryPool {
+    /// Create an empty pool, keeping at most `max_idle` idle allocations of
+    /// every reserved size. Allocations given back to a full pool are unmapped.
+    pub fn new(max_idle: usize) -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+            max_idle,
+        }
+    }
+
+    /// The number of idle allocations in the pool.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    /// Take an allocation of `mapping_size` bytes from the pool, with its first
+    /// `accessible_size` bytes accessible, or map a new one if there is none.
+    fn take(&self, accessible_size: usize, mapping_size: usize) -> Result<Mmap, String> {
+        let idle = self
+            .idle
+            .lock()
+            .unwrap()
+            .get_mut(&mapping_size)
+            .and_then(Vec::pop);
+
+        match idle {
+            Some(mut mmap) => {
+                if accessible_size != 0 {
+                    mmap.reset(accessible_size)?;
+                }
+                Ok(mmap)
+            }
+            None => Mmap::accessible_reserved(accessible_size, mapping_size),
+        }
+    }
+
+    /// Zero `mmap` and keep it for a later memory.
+    fn give_back(&self, mut mmap: Mmap) {
+        if mmap.is_empty() || mmap.reset(0).is_err() {
+            return;
+        }
+
+        let mut idle = self.idle.lock().unwrap();
+        let allocations = idle.entry(mmap.len()).or_insert_with(Vec::new);
+        if allocations.len() < self.max_idle {
+            allocations.push(mmap);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pooled_memories_are_reused_and_zeroed() {
+        let pool = Arc::new(MemoryPool::new(1));
+        let ty = MemoryType::new(1, Some(2), false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0x1_0000,
+        };
+
+        let memory = LinearMemory::new_pooled(&ty, &style, &pool).unwrap();
+        let base = unsafe {
+            let definition = memory.vmmemory().as_ref();
+            *definition.base = 42;
+            definition.base
+        };
+        drop(memory);
+        assert_eq!(pool.idle(), 1);
+
+        let memory = LinearMemory::new_pooled(&ty, &style, &pool).unwrap();
+        assert_eq!(pool.idle(), 0);
+        unsafe {
+            let definition = memory.vmmemory().as_ref();
+            assert_eq!(definition.base, base);
+            assert_eq!(*definition.base, 0);
+        }
+        assert_eq!(memory.size(), Pages(1));
+    }
+
+    #[test]
+    fn stats_track_grows() {
+        let ty = MemoryType::new(1, Some(4), false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0x1_0000,
+        };
+
+        let memory = LinearMemory::new(&ty, &style).unwrap();
+        memory.grow(Pages(0)).unwrap();
+        memory.grow(Pages(2)).unwrap();
+        memory.grow(Pages(2)).unwrap_err();
+        assert_eq!(
+            memory.stats(),
+            MemoryStats {
+                current: Pages(3),
+                peak: Pages(3),
+                grows: 1,
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_mappings() {
+        use std::io::{Read, Seek, SeekFrom, Write};
+
+        let path =
+            std::env::temp_dir().join(format!("wasmer-vm-file-mappings-{}", std::process::id()));
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        file.write_all(b"abcd").unwrap();
+
+        let ty = MemoryType::new(1, Some(2), false);
+        let style = MemoryStyle::Static {
+            bound: Pages(2),
+            offset_guard_size: 0x1_0000,
+        };
+        let read_back = |file: &mut std::fs::File| {
+            let mut contents = Vec::new();
+            file.seek(SeekFrom::Start(0)).unwrap();
+            file.read_to_end(&mut contents).unwrap();
+            contents
+        };
+
+        for &(mapping, expected) in &[
+            (FileMapping::CopyOnWrite, b"abcd"),
+            (FileMapping::Shared, b"xbcd"),
+        ] {
+            let memory = LinearMemory::new(&ty, &style).unwrap();
+            memory.map_file(&file, 4, mapping).unwrap();
+            memory.map_file(&file, 4, mapping).unwrap_err();
+            unsafe {
+                let base = memory.vmmemory().as_ref().base;
+                assert_eq!(std::slice::from_raw_parts(base, 5), b"abcd\0");
+                *base = b'x';
+            }
+            memory.flush().unwrap();
+            memory.grow(Pages(1)).unwrap();
+            assert_eq!(read_back(&mut file), expected);
+        }
+    }
 }
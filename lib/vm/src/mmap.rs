@@ -227,6 +227,87 @@ impl Mmap {
         Ok(())
     }
 
+    /// Discard the contents of the mapping, leaving `accessible_size` bytes of zeroed,
+    /// accessible memory at its start and the rest reserved but inaccessible, as
+    /// `accessible_reserved(accessible_size, self.len())` would. `accessible_size` must be a
+    /// native page-size multiple.
+    #[cfg(not(target_os = "windows"))]
+    pub fn reset(&mut self, accessible_size: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_le!(accessible_size, self.len);
+        assert_eq!(accessible_size & (page_size - 1), 0);
+
+        if self.len == 0 {
+            return Ok(());
+        }
+
+        // Mapping fresh anonymous pages over the old ones zeroes them, and
+        // gives their physical memory back to the system.
+        let protection = if accessible_size == self.len {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_NONE
+        };
+        let ptr = unsafe {
+            libc::mmap(
+                self.ptr as *mut libc::c_void,
+                self.len,
+                protection,
+                libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+                -1,
+                0,
+            )
+        };
+        if ptr as isize == -1_isize {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        if accessible_size != 0 && accessible_size != self.len {
+            self.make_accessible(0, accessible_size)?;
+        }
+
+        Ok(())
+    }
+
+    /// Discard the contents of the mapping, leaving `accessible_size` bytes of zeroed,
+    /// accessible memory at its start and the rest reserved but inaccessible, as
+    /// `accessible_reserved(accessible_size, self.len())` would. `accessible_size` must be a
+    /// native page-size multiple.
+    #[cfg(target_os = "windows")]
+    pub fn reset(&mut self, accessible_size: usize) -> Result<(), String> {
+        use winapi::ctypes::c_void;
+        use winapi::um::memoryapi::{VirtualAlloc, VirtualFree};
+        use winapi::um::winnt::{MEM_COMMIT, MEM_DECOMMIT, PAGE_READWRITE};
+        let page_size = region::page::size();
+        assert_le!(accessible_size, self.len);
+        assert_eq!(accessible_size & (page_size - 1), 0);
+
+        if self.len == 0 {
+            return Ok(());
+        }
+
+        // Decommitted pages are zeroed when they are committed again.
+        if unsafe { VirtualFree(self.ptr as *mut c_void, self.len, MEM_DECOMMIT) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        if accessible_size != 0
+            && unsafe {
+                VirtualAlloc(
+                    self.ptr as *mut c_void,
+                    accessible_size,
+                    MEM_COMMIT,
+                    PAGE_READWRITE,
+                )
+            }
+            .is_null()
+        {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(())
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
@@ -295,4 +376,17 @@ mod tests {
         assert_eq!(round_up_to_page_size(4096, 4096), 4096);
         assert_eq!(round_up_to_page_size(4097, 4096), 8192);
     }
+
+    #[test]
+    fn test_reset() {
+        let page_size = region::page::size();
+        let mut mmap = Mmap::accessible_reserved(page_size, 4 * page_size).unwrap();
+        mmap.as_mut_slice()[0] = 42;
+
+        mmap.reset(2 * page_size).unwrap();
+        assert!(mmap.as_slice()[..2 * page_size]
+            .iter()
+            .all(|byte| *byte == 0));
+        mmap.as_mut_slice()[2 * page_size - 1] = 42;
+    }
 }
@@ -0,0 +1,155 @@
+//! Exception tags, and the exceptions of the exception handling
+//! proposal thrown by the Wasm code.
+//!
+//! An exception is thrown by recording it as pending, then unwinding
+//! the Wasm frames with their regular returns: every call made by the
+//! Wasm code checks for a pending exception, and jumps to the handler
+//! of its `try` block, or returns to its own caller. The exceptions
+//! caught by the `catch` clauses are kept for `rethrow`, on a stack
+//! shared by the Wasm frames of a thread.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use wasmer_types::{TagType, Value, WasmValueType};
+
+/// A WebAssembly exception tag.
+///
+/// A tag is only equal to itself: two tags with the same type are
+/// distinct, and so are the exceptions they are thrown with.
+#[derive(Debug)]
+pub struct Tag {
+    ty: TagType,
+}
+
+impl Tag {
+    /// Create a new tag with the given type.
+    pub fn new(ty: TagType) -> Self {
+        Self { ty }
+    }
+
+    /// Get the type of the tag.
+    pub fn ty(&self) -> &TagType {
+        &self.ty
+    }
+}
+
+/// An exception thrown by the Wasm code with a tag and the values of
+/// the parameters of its type.
+#[derive(Debug, Clone)]
+pub struct Exception {
+    tag: Arc<Tag>,
+    values: Box<[u128]>,
+}
+
+impl Exception {
+    /// Create a new exception with the given tag, and the raw bits of
+    /// its values.
+    pub fn new(tag: Arc<Tag>, values: Box<[u128]>) -> Self {
+        debug_assert_eq!(values.len(), tag.ty().params().len());
+        Self { tag, values }
+    }
+
+    /// Get the tag the exception was thrown with.
+    pub fn tag(&self) -> &Arc<Tag> {
+        &self.tag
+    }
+
+    /// Get the type of the tag the exception was thrown with.
+    pub fn ty(&self) -> &TagType {
+        self.tag.ty()
+    }
+
+    /// Get the values the exception was thrown with.
+    ///
+    /// The tags with reference types are rejected at compile time, so
+    /// the values are always numbers.
+    pub fn values<T: WasmValueType>(&self) -> Vec<Value<T>> {
+        self.ty()
+            .params()
+            .iter()
+            .zip(self.values.iter())
+            .map(|(ty, value)| unsafe {
+                Value::read_value_from(&(), value as *const u128 as *const i128, *ty)
+            })
+            .collect()
+    }
+
+    /// Get the raw bits of the values the exception was thrown with.
+    pub(crate) fn raw_values(&self) -> &[u128] {
+        &self.values
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "uncaught exception with tag of type {}", self.ty())
+    }
+}
+
+impl Error for Exception {}
+
+thread_local! {
+    /// The exception being thrown, until it is caught.
+    static PENDING: RefCell<Option<Exception>> = RefCell::new(None);
+    /// The exceptions caught by the `catch` clauses being executed, by
+    /// slot.
+    static CAUGHT: RefCell<Vec<Exception>> = RefCell::new(Vec::new());
+}
+
+/// Throw `exception`, which the Wasm code will then unwind to its
+/// handler.
+pub(crate) fn throw(exception: Exception) {
+    PENDING.with(|pending| *pending.borrow_mut() = Some(exception));
+}
+
+/// Returns whether an exception is being thrown.
+pub fn exception_pending() -> bool {
+    PENDING.with(|pending| pending.borrow().is_some())
+}
+
+/// Returns whether the exception being thrown has the given tag.
+pub(crate) fn exception_matches(tag: &Arc<Tag>) -> bool {
+    PENDING.with(|pending| {
+        pending
+            .borrow()
+            .as_ref()
+            .map_or(false, |exception| Arc::ptr_eq(&exception.tag, tag))
+    })
+}
+
+/// Catch the exception being thrown, in `slot`.
+pub(crate) fn catch_exception(slot: usize) -> Option<Exception> {
+    let exception = PENDING.with(|pending| pending.borrow_mut().take())?;
+    CAUGHT.with(|caught| {
+        let mut caught = caught.borrow_mut();
+        debug_assert!(caught.len() >= slot);
+        caught.truncate(slot);
+        caught.push(exception.clone());
+    });
+    Some(exception)
+}
+
+/// Throw the exception caught in `slot` again.
+pub(crate) fn rethrow(slot: usize) {
+    let exception = CAUGHT.with(|caught| caught.borrow()[slot].clone());
+    throw(exception);
+}
+
+/// Returns the number of slots of the caught exceptions.
+pub(crate) fn exception_slots() -> usize {
+    CAUGHT.with(|caught| caught.borrow().len())
+}
+
+/// Release the caught exceptions from `slots` on.
+pub(crate) fn release_exceptions(slots: usize) {
+    CAUGHT.with(|caught| caught.borrow_mut().truncate(slots));
+}
+
+/// Takes the exception still being thrown when the Wasm code returns to
+/// the host, releasing the exceptions caught since `slots`.
+pub(crate) fn take_uncaught_exception(slots: usize) -> Option<Exception> {
+    release_exceptions(slots);
+    PENDING.with(|pending| pending.borrow_mut().take())
+}
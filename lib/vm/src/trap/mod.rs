@@ -5,9 +5,11 @@
 //! in Wasmer Runtime
 
 #[allow(clippy::module_inception)]
+mod sampler;
 mod trap;
 mod traphandlers;
 
+pub use sampler::{Samples, SamplesSlot, SamplingTimer};
 pub use trap::Trap;
 pub use traphandlers::{
     catch_traps, on_host_stack, raise_lib_trap, raise_user_trap, wasmer_call_trampoline,
@@ -0,0 +1,410 @@
+//! Sampling of the Wasm stacks, for profilers.
+//!
+//! While a [`SamplingTimer`] runs, the process receives a `SIGPROF` at a
+//! fixed frequency of its CPU time. If the thread receiving it runs Wasm
+//! code of a trap handler (a store) with [`Samples`] attached to its
+//! [`SamplesSlot`], the program counters of the frames on its Wasm stack
+//! are recorded there. The frames are found by following the frame
+//! pointers, which the compilers keep.
+
+use std::io;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+/// The maximum number of frames recorded per sample.
+const MAX_FRAMES: usize = 128;
+
+/// The number of signal handlers currently recording a sample.
+static RECORDING: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether a [`SamplingTimer`] runs.
+static SAMPLING: AtomicBool = AtomicBool::new(false);
+
+/// The stacks sampled while profiling.
+///
+/// The samples are stored in a buffer allocated upfront, since they are
+/// recorded from a signal handler. The samples that don't fit anymore are
+/// only counted.
+pub struct Samples {
+    /// Each sample is its number of frames followed by their program
+    /// counters, from the innermost frame.
+    words: Box<[AtomicUsize]>,
+    used: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+impl Samples {
+    /// Creates a buffer for samples of `capacity` frames in total.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            words: (0..capacity).map(|_| AtomicUsize::new(0)).collect(),
+            used: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records a sample of the program counters `pcs`, from the innermost
+    /// frame. No frames means the thread ran host code.
+    fn record(&self, pcs: &[usize]) {
+        let len = pcs.len() + 1;
+        let mut start = self.used.load(Ordering::Relaxed);
+        loop {
+            if start + len > self.words.len() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            match self.used.compare_exchange_weak(
+                start,
+                start + len,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(used) => start = used,
+            }
+        }
+        self.words[start].store(pcs.len(), Ordering::Relaxed);
+        for (word, &pc) in self.words[start + 1..start + len].iter().zip(pcs) {
+            word.store(pc, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of samples that didn't fit in the buffer.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Takes the recorded samples, each with the program counters of its
+    /// frames from the innermost one. The program counters of the outer
+    /// frames are return addresses.
+    pub fn take(&mut self) -> Vec<Vec<usize>> {
+        let used = *self.used.get_mut();
+        let mut samples = vec![];
+        let mut start = 0;
+        while start < used {
+            let len = *self.words[start].get_mut();
+            samples.push(
+                self.words[start + 1..start + 1 + len]
+                    .iter_mut()
+                    .map(|word| *word.get_mut())
+                    .collect(),
+            );
+            start += 1 + len;
+        }
+        *self.used.get_mut() = 0;
+        *self.dropped.get_mut() = 0;
+        samples
+    }
+}
+
+/// Where a trap handler points to the [`Samples`] of the code it runs,
+/// while it's being profiled.
+#[derive(Default)]
+pub struct SamplesSlot {
+    samples: AtomicPtr<Samples>,
+}
+
+impl SamplesSlot {
+    /// Records the samples of the code run with the trap handler in
+    /// `samples`, until [`detach`](Self::detach) is called.
+    ///
+    /// Returns `false` if other samples are attached already.
+    ///
+    /// # Safety
+    ///
+    /// `samples` must not be moved or dropped before `detach` is called.
+    pub unsafe fn attach(&self, samples: &Samples) -> bool {
+        self.samples
+            .compare_exchange(
+                ptr::null_mut(),
+                samples as *const Samples as *mut Samples,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+    }
+
+    /// Stops recording the samples, and waits until the samples being
+    /// recorded are complete.
+    pub fn detach(&self) {
+        self.samples.store(ptr::null_mut(), Ordering::SeqCst);
+        while RECORDING.load(Ordering::SeqCst) != 0 {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Samples the Wasm stacks of the process until dropped. Only one timer
+/// can run at a time.
+pub struct SamplingTimer {
+    _private: (),
+}
+
+impl SamplingTimer {
+    /// Starts sampling the Wasm stacks `frequency` times per second of
+    /// CPU time of the process.
+    pub fn start(frequency: u32) -> io::Result<Self> {
+        if !imp::SUPPORTED {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "sampling is not supported on this platform",
+            ));
+        }
+        if frequency == 0 || frequency > 1_000_000 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the sampling frequency must be between 1 and 1000000 Hz",
+            ));
+        }
+        if SAMPLING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the process is being sampled already",
+            ));
+        }
+
+        unsafe {
+            imp::init();
+            if let Err(error) = imp::set_timer(1_000_000 / u64::from(frequency)) {
+                SAMPLING.store(false, Ordering::SeqCst);
+                return Err(error);
+            }
+        }
+        Ok(Self { _private: () })
+    }
+}
+
+impl Drop for SamplingTimer {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = imp::set_timer(0);
+        }
+        // A signal sent meanwhile is ignored.
+        SAMPLING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Records a sample of the Wasm stack of the current thread, whose
+/// registers are `pc`, `sp` and `fp`.
+///
+/// This is called from the signal handler.
+#[cfg_attr(not(unix), allow(dead_code))]
+unsafe fn sample(pc: usize, sp: usize, fp: usize) {
+    RECORDING.fetch_add(1, Ordering::SeqCst);
+    #[cfg(unix)]
+    super::traphandlers::with_running_wasm(|trap_handler, on_wasm_stack| {
+        let samples = match trap_handler.samples_slot() {
+            Some(slot) => slot.samples.load(Ordering::SeqCst),
+            None => return,
+        };
+        if samples.is_null() {
+            return;
+        }
+
+        let mut pcs = [0; MAX_FRAMES];
+        let mut len = 0;
+        // Outside of the Wasm stack, the thread runs host code.
+        if on_wasm_stack(sp) {
+            pcs[0] = pc;
+            len = 1;
+            // Each frame starts with the frame pointer of its caller,
+            // followed by the return address.
+            let word = std::mem::size_of::<usize>();
+            let mut fp = fp;
+            while len < MAX_FRAMES
+                && fp % word == 0
+                && on_wasm_stack(fp)
+                && on_wasm_stack(fp + 2 * word - 1)
+            {
+                let (caller_fp, return_address) =
+                    (*(fp as *const usize), *((fp + word) as *const usize));
+                if return_address == 0 {
+                    break;
+                }
+                pcs[len] = return_address;
+                len += 1;
+                // The stack grows down.
+                if caller_fp <= fp {
+                    break;
+                }
+                fp = caller_fp;
+            }
+        }
+        (*samples).record(&pcs[..len]);
+    });
+    RECORDING.fetch_sub(1, Ordering::SeqCst);
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::SAMPLING;
+    use std::io;
+    use std::mem::{self, MaybeUninit};
+    use std::ptr;
+    use std::sync::atomic::Ordering;
+    use std::sync::Once;
+
+    static mut PREV_SIGPROF: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(
+            all(
+                any(target_os = "linux", target_os = "android", target_os = "freebsd"),
+                any(target_arch = "x86_64", target_arch = "aarch64"),
+            ),
+            all(target_vendor = "apple", any(target_arch = "x86_64", target_arch = "aarch64")),
+        ))] {
+            pub(super) const SUPPORTED: bool = true;
+        } else {
+            pub(super) const SUPPORTED: bool = false;
+        }
+    }
+
+    /// Installs the `SIGPROF` handler. It stays installed, and forwards
+    /// the signals to the previous handler while not sampling.
+    pub(super) unsafe fn init() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            let mut handler: libc::sigaction = mem::zeroed();
+            // SA_RESTART keeps the system calls interrupted by the samples
+            // from failing.
+            handler.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK | libc::SA_RESTART;
+            handler.sa_sigaction = sigprof_handler as usize;
+            libc::sigemptyset(&mut handler.sa_mask);
+            if libc::sigaction(libc::SIGPROF, &handler, PREV_SIGPROF.as_mut_ptr()) != 0 {
+                panic!(
+                    "unable to install signal handler: {}",
+                    io::Error::last_os_error(),
+                );
+            }
+        });
+    }
+
+    /// Sends a `SIGPROF` every `interval` microseconds of CPU time, or
+    /// never if `interval` is 0.
+    pub(super) unsafe fn set_timer(interval: u64) -> io::Result<()> {
+        let interval = libc::timeval {
+            tv_sec: (interval / 1_000_000) as libc::time_t,
+            tv_usec: (interval % 1_000_000) as libc::suseconds_t,
+        };
+        let timer = libc::itimerval {
+            it_interval: interval,
+            it_value: interval,
+        };
+        if libc::setitimer(libc::ITIMER_PROF, &timer, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    unsafe extern "C" fn sigprof_handler(
+        signum: libc::c_int,
+        siginfo: *mut libc::siginfo_t,
+        context: *mut libc::c_void,
+    ) {
+        if SAMPLING.load(Ordering::SeqCst) {
+            let (pc, sp, fp) = get_pc_sp_fp(&*(context as *const libc::ucontext_t));
+            super::sample(pc, sp, fp);
+            return;
+        }
+
+        let previous = &*PREV_SIGPROF.as_ptr();
+        if previous.sa_flags & libc::SA_SIGINFO != 0 {
+            mem::transmute::<
+                usize,
+                extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void),
+            >(previous.sa_sigaction)(signum, siginfo, context)
+        } else if previous.sa_sigaction != libc::SIG_DFL && previous.sa_sigaction != libc::SIG_IGN {
+            mem::transmute::<usize, extern "C" fn(libc::c_int)>(previous.sa_sigaction)(signum)
+        }
+    }
+
+    #[allow(unused_variables)]
+    unsafe fn get_pc_sp_fp(context: &libc::ucontext_t) -> (usize, usize, usize) {
+        cfg_if::cfg_if! {
+            if #[cfg(all(
+                any(target_os = "linux", target_os = "android"),
+                target_arch = "x86_64",
+            ))] {
+                (
+                    context.uc_mcontext.gregs[libc::REG_RIP as usize] as usize,
+                    context.uc_mcontext.gregs[libc::REG_RSP as usize] as usize,
+                    context.uc_mcontext.gregs[libc::REG_RBP as usize] as usize,
+                )
+            } else if #[cfg(all(
+                any(target_os = "linux", target_os = "android"),
+                target_arch = "aarch64",
+            ))] {
+                (
+                    context.uc_mcontext.pc as usize,
+                    context.uc_mcontext.sp as usize,
+                    context.uc_mcontext.regs[29] as usize,
+                )
+            } else if #[cfg(all(target_os = "freebsd", target_arch = "x86_64"))] {
+                (
+                    context.uc_mcontext.mc_rip as usize,
+                    context.uc_mcontext.mc_rsp as usize,
+                    context.uc_mcontext.mc_rbp as usize,
+                )
+            } else if #[cfg(all(target_os = "freebsd", target_arch = "aarch64"))] {
+                (
+                    context.uc_mcontext.mc_gpregs.gp_elr as usize,
+                    context.uc_mcontext.mc_gpregs.gp_sp as usize,
+                    context.uc_mcontext.mc_gpregs.gp_x[29] as usize,
+                )
+            } else if #[cfg(all(target_vendor = "apple", target_arch = "x86_64"))] {
+                (
+                    (*context.uc_mcontext).__ss.__rip as usize,
+                    (*context.uc_mcontext).__ss.__rsp as usize,
+                    (*context.uc_mcontext).__ss.__rbp as usize,
+                )
+            } else if #[cfg(all(target_vendor = "apple", target_arch = "aarch64"))] {
+                (
+                    (*context.uc_mcontext).__ss.__pc as usize,
+                    (*context.uc_mcontext).__ss.__sp as usize,
+                    (*context.uc_mcontext).__ss.__fp as usize,
+                )
+            } else {
+                // Never called: `SUPPORTED` is false.
+                (0, 0, 0)
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::io;
+
+    pub(super) const SUPPORTED: bool = false;
+
+    pub(super) unsafe fn init() {}
+
+    pub(super) unsafe fn set_timer(_interval: u64) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_taken_until_the_buffer_is_full() {
+        let mut samples = Samples::with_capacity(6);
+        samples.record(&[1, 2]);
+        samples.record(&[]);
+        samples.record(&[3, 4]);
+        samples.record(&[5]);
+        assert_eq!(samples.dropped(), 1);
+        assert_eq!(samples.take(), vec![vec![1, 2], vec![], vec![5]]);
+
+        // Taking the samples empties the buffer.
+        samples.record(&[6, 7, 8, 9, 10]);
+        assert_eq!(samples.dropped(), 0);
+        assert_eq!(samples.take(), vec![vec![6, 7, 8, 9, 10]]);
+    }
+}
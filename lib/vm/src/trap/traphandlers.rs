@@ -4,6 +4,9 @@
 //! WebAssembly trap handling, which is built on top of the lower-level
 //! signalhandling mechanisms.
 
+use super::sampler::SamplesSlot;
+use crate::global::notify_global_observers;
+use crate::tag;
 use crate::vmcontext::{VMFunctionEnvironment, VMTrampoline};
 use crate::{Trap, VMFunctionBody};
 use backtrace::Backtrace;
@@ -100,6 +103,12 @@ pub unsafe trait TrapHandler {
     ///
     /// Returns `true` if `call` returns true, otherwise returns `false`.
     fn custom_trap_handler(&self, call: &dyn Fn(&TrapHandlerFn) -> bool) -> bool;
+
+    /// Returns where the samples of the code run with this trap handler
+    /// are recorded while it's being profiled, if anywhere.
+    fn samples_slot(&self) -> Option<&SamplesSlot> {
+        None
+    }
 }
 
 cfg_if::cfg_if! {
@@ -640,7 +649,15 @@ where
     // Ensure that per-thread initialization is done.
     lazy_per_thread_init()?;
 
-    on_wasm_stack(trap_handler, closure).map_err(UnwindReason::into_trap)
+    let exception_slots = tag::exception_slots();
+    let result = on_wasm_stack(trap_handler, closure);
+    let exception = tag::take_uncaught_exception(exception_slots);
+    notify_global_observers();
+
+    match (result, exception) {
+        (Ok(_), Some(exception)) => Err(Trap::Exception(exception)),
+        (result, _) => result.map_err(UnwindReason::into_trap),
+    }
 }
 
 // We need two separate thread-local variables here:
@@ -669,6 +686,7 @@ struct TrapHandlerContext {
         Option<TrapCode>,
         &mut dyn FnMut(TrapHandlerRegs),
     ) -> bool,
+    stack_ptr_in_bounds: fn(*const u8, usize) -> bool,
     custom_trap: *const dyn TrapHandler,
 }
 struct TrapHandlerContextInner<T> {
@@ -704,10 +722,18 @@ impl TrapHandlerContext {
                 )
             }
         }
+        fn stack_ptr_in_bounds<T>(ptr: *const u8, sp: usize) -> bool {
+            unsafe {
+                (*(ptr as *const TrapHandlerContextInner<T>))
+                    .coro_trap_handler
+                    .stack_ptr_in_bounds(sp)
+            }
+        }
         let inner = TrapHandlerContextInner { coro_trap_handler };
         let ctx = Self {
             inner: &inner as *const _ as *const u8,
             handle_trap: func::<T>,
+            stack_ptr_in_bounds: stack_ptr_in_bounds::<T>,
             custom_trap,
         };
 
@@ -758,6 +784,26 @@ impl TrapHandlerContext {
     }
 }
 
+/// Calls `f` with the trap handler of the Wasm code running on this
+/// thread, and a function telling whether an address is on its stack, if
+/// this thread is running Wasm code.
+///
+/// This is called from signal handlers, like the trap handler.
+#[cfg(unix)]
+pub(super) unsafe fn with_running_wasm<R>(
+    f: impl FnOnce(&dyn TrapHandler, &dyn Fn(usize) -> bool) -> R,
+) -> Option<R> {
+    let ptr = TRAP_HANDLER.with(|ptr| ptr.load(Ordering::Relaxed));
+    if ptr.is_null() {
+        return None;
+    }
+
+    let ctx = &*ptr;
+    Some(f(&*ctx.custom_trap, &|sp| {
+        (ctx.stack_ptr_in_bounds)(ctx.inner, sp)
+    }))
+}
+
 impl<T> TrapHandlerContextInner<T> {
     unsafe fn handle_trap(
         &self,
@@ -907,6 +953,11 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
 /// stack overflow in the middle of a sensitive host operations (e.g. growing
 /// a memory) which would be hard to recover from.
 pub fn on_host_stack<F: FnOnce() -> T, T>(f: F) -> T {
+    let f = move || {
+        notify_global_observers();
+        f()
+    };
+
     // Reset YIEDER to None for the duration of this call to indicate that we
     // are no longer on the Wasm stack.
     let yielder_ptr = YIELDER.with(|cell| cell.replace(None));
@@ -914,6 +914,19 @@ impl VMBuiltinFunctionsArray {
             wasmer_vm_externref_inc as usize;
         ptrs[VMBuiltinFunctionIndex::get_externref_dec_index().index() as usize] =
             wasmer_vm_externref_dec as usize;
+        ptrs[VMBuiltinFunctionIndex::get_throw_index().index() as usize] = wasmer_vm_throw as usize;
+        ptrs[VMBuiltinFunctionIndex::get_rethrow_index().index() as usize] =
+            wasmer_vm_rethrow as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_pending_index().index() as usize] =
+            wasmer_vm_exception_pending as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_matches_index().index() as usize] =
+            wasmer_vm_exception_matches as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_catch_index().index() as usize] =
+            wasmer_vm_exception_catch as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_slots_index().index() as usize] =
+            wasmer_vm_exception_slots as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_release_index().index() as usize] =
+            wasmer_vm_exception_release as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
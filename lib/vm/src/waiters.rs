@@ -0,0 +1,144 @@
+//! Waiting on, and notifying, the locations of shared memories, as done
+//! by `memory.atomic.wait32` and `memory.atomic.notify`.
+//!
+//! The waiters are queued per address, in a table shared by all the
+//! memories of the process.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use wasmer_types::WaitResult;
+
+struct Waiter {
+    notified: Mutex<bool>,
+    condvar: Condvar,
+}
+
+lazy_static::lazy_static! {
+    static ref WAITERS: Mutex<HashMap<usize, VecDeque<Arc<Waiter>>>> = Mutex::new(HashMap::new());
+}
+
+/// Blocks the current thread until `location` is notified with
+/// [`memory_notify`], or `timeout` elapses, if `location` holds
+/// `expected`.
+pub fn memory_wait32(location: &AtomicU32, expected: u32, timeout: Option<Duration>) -> WaitResult {
+    let address = location as *const AtomicU32 as usize;
+    let waiter = {
+        // The value is loaded with the table locked, so that a
+        // notification can't slip in before the waiter is queued.
+        let mut waiters = WAITERS.lock().unwrap();
+        if location.load(Ordering::SeqCst) != expected {
+            return WaitResult::NotEqual;
+        }
+
+        let waiter = Arc::new(Waiter {
+            notified: Mutex::new(false),
+            condvar: Condvar::new(),
+        });
+        waiters
+            .entry(address)
+            .or_default()
+            .push_back(waiter.clone());
+        waiter
+    };
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut notified = waiter.notified.lock().unwrap();
+    while !*notified {
+        notified = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                waiter
+                    .condvar
+                    .wait_timeout(notified, deadline - now)
+                    .unwrap()
+                    .0
+            }
+            None => waiter.condvar.wait(notified).unwrap(),
+        };
+    }
+    if *notified {
+        return WaitResult::Ok;
+    }
+    drop(notified);
+
+    // Timed out: the waiter leaves the queue, unless a notification
+    // dequeued it in the meantime.
+    let mut waiters = WAITERS.lock().unwrap();
+    if *waiter.notified.lock().unwrap() {
+        return WaitResult::Ok;
+    }
+    if let Some(queue) = waiters.get_mut(&address) {
+        queue.retain(|queued| !Arc::ptr_eq(queued, &waiter));
+        if queue.is_empty() {
+            waiters.remove(&address);
+        }
+    }
+
+    WaitResult::TimedOut
+}
+
+/// Wakes up at most `count` of the threads waiting on `location`, in the
+/// order they started waiting, and returns how many were woken up.
+pub fn memory_notify(location: &AtomicU32, count: u32) -> u32 {
+    let address = location as *const AtomicU32 as usize;
+    let mut waiters = WAITERS.lock().unwrap();
+    let queue = match waiters.get_mut(&address) {
+        Some(queue) => queue,
+        None => return 0,
+    };
+
+    let mut woken = 0;
+    while woken < count {
+        let waiter = match queue.pop_front() {
+            Some(waiter) => waiter,
+            None => break,
+        };
+        *waiter.notified.lock().unwrap() = true;
+        waiter.condvar.notify_one();
+        woken += 1;
+    }
+    if queue.is_empty() {
+        waiters.remove(&address);
+    }
+
+    woken
+}
+
+#[cfg(test)]
+mod test_waiters {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn not_equal() {
+        let location = AtomicU32::new(1);
+        assert_eq!(memory_wait32(&location, 0, None), WaitResult::NotEqual);
+    }
+
+    #[test]
+    fn timed_out() {
+        let location = AtomicU32::new(0);
+        let timeout = Some(Duration::from_millis(10));
+        assert_eq!(memory_wait32(&location, 0, timeout), WaitResult::TimedOut);
+        assert_eq!(memory_notify(&location, 1), 0);
+    }
+
+    #[test]
+    fn notified() {
+        let location = Arc::new(AtomicU32::new(0));
+        let waiter = {
+            let location = location.clone();
+            thread::spawn(move || memory_wait32(&location, 0, None))
+        };
+
+        while memory_notify(&location, 1) == 0 {
+            thread::yield_now();
+        }
+        assert_eq!(waiter.join().unwrap(), WaitResult::Ok);
+    }
+}
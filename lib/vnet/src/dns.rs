@@ -0,0 +1,279 @@
+//! Pluggable DNS resolution, so the embedders can control how the
+//! guests resolve host names.
+
+use crate::{NetworkError, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+#[cfg(feature = "dns-over-https")]
+use crate::VirtualNetworking;
+
+/// Resolves host names into IP addresses.
+pub trait DnsResolver: fmt::Debug + Send + Sync + 'static {
+    /// Performs DNS resolution for a specific hostname
+    fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>>;
+}
+
+/// Resolves host names with the resolver of the operating system.
+///
+/// The DNS server hint is ignored: the system configuration is used.
+#[derive(Debug, Default)]
+pub struct SystemDnsResolver {}
+
+impl DnsResolver for SystemDnsResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        _dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        use std::net::ToSocketAddrs;
+        (host, port.unwrap_or(0))
+            .to_socket_addrs()
+            .map(|addrs| addrs.map(|addr| addr.ip()).collect())
+            .map_err(crate::io_err_into_net_error)
+    }
+}
+
+/// Resolves host names from a static map, like a hosts file.
+///
+/// The host names absent from the map are resolved by the fallback
+/// resolver if there's one, or fail with
+/// [`NetworkError::AddressNotAvailable`] otherwise, so that the guest
+/// can only reach the hosts of the map.
+#[derive(Debug, Default)]
+pub struct HostsDnsResolver {
+    hosts: HashMap<String, Vec<IpAddr>>,
+    fallback: Option<Arc<dyn DnsResolver>>,
+}
+
+impl HostsDnsResolver {
+    /// Creates a resolver without any host.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a resolver from the contents of a hosts file, where each
+    /// line holds an IP address followed by the host names it's the
+    /// address of. Comments start with `#`.
+    pub fn from_hosts_file(contents: &str) -> Result<Self> {
+        let mut resolver = Self::new();
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let ip = match fields.next() {
+                Some(ip) => ip.parse().map_err(|_| NetworkError::InvalidInput)?,
+                None => continue,
+            };
+            for host in fields {
+                resolver.insert(host, ip);
+            }
+        }
+        Ok(resolver)
+    }
+
+    /// Adds `ip` to the addresses `host` resolves to. Host names are
+    /// compared case-insensitively.
+    pub fn insert(&mut self, host: &str, ip: IpAddr) -> &mut Self {
+        self.hosts
+            .entry(host.to_ascii_lowercase())
+            .or_default()
+            .push(ip);
+
+        self
+    }
+
+    /// Resolves the host names absent from the map with `fallback`.
+    pub fn with_fallback(mut self, fallback: Arc<dyn DnsResolver>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+}
+
+impl DnsResolver for HostsDnsResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        if let Some(ips) = self.hosts.get(&host.to_ascii_lowercase()) {
+            return Ok(ips.clone());
+        }
+        match &self.fallback {
+            Some(fallback) => fallback.resolve(host, port, dns_server),
+            None => Err(NetworkError::AddressNotAvailable),
+        }
+    }
+}
+
+/// Resolves host names with DNS-over-HTTPS, using the JSON API of
+/// servers like `https://cloudflare-dns.com/dns-query`.
+///
+/// The HTTP requests are made through a [`VirtualNetworking`], so the
+/// resolution goes through the same network as the guest. The DNS
+/// server hint is ignored.
+#[cfg(feature = "dns-over-https")]
+#[derive(Debug)]
+pub struct DohDnsResolver {
+    networking: Arc<dyn VirtualNetworking>,
+    url: String,
+}
+
+#[cfg(feature = "dns-over-https")]
+#[derive(serde::Deserialize)]
+struct DohReply {
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[cfg(feature = "dns-over-https")]
+#[derive(serde::Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+#[cfg(feature = "dns-over-https")]
+impl DohDnsResolver {
+    /// DNS record type of the IPv4 addresses.
+    const A: u16 = 1;
+    /// DNS record type of the IPv6 addresses.
+    const AAAA: u16 = 28;
+
+    /// Creates a resolver querying the DNS-over-HTTPS server at `url`.
+    pub fn new(networking: Arc<dyn VirtualNetworking>, url: &str) -> Self {
+        Self {
+            networking,
+            url: url.to_string(),
+        }
+    }
+
+    fn query(&self, host: &str, record_type: u16) -> Result<Vec<IpAddr>> {
+        let url = format!("{}?name={}&type={}", self.url, host, record_type);
+        let request =
+            self.networking
+                .http_request(&url, "GET", "accept: application/dns-json", false)?;
+        // There's no request body.
+        drop(request.request);
+
+        let mut body = Vec::new();
+        if let Some(response) = request.response {
+            for chunk in response.iter() {
+                body.extend(chunk);
+            }
+        }
+        let status = request
+            .status
+            .lock()
+            .unwrap()
+            .recv()
+            .map_err(|_| NetworkError::BrokenPipe)??;
+        if status.status != 200 {
+            return Err(NetworkError::AddressNotAvailable);
+        }
+
+        let reply: DohReply =
+            serde_json::from_slice(&body).map_err(|_| NetworkError::InvalidData)?;
+        Ok(reply
+            .answer
+            .iter()
+            .filter(|answer| answer.record_type == record_type)
+            .filter_map(|answer| answer.data.parse().ok())
+            .collect())
+    }
+}
+
+#[cfg(feature = "dns-over-https")]
+impl DnsResolver for DohDnsResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        _port: Option<u16>,
+        _dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        // The host name is put as is in the query string.
+        if host.is_empty()
+            || !host
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-')
+        {
+            return Err(NetworkError::InvalidInput);
+        }
+
+        let mut ips = self.query(host, Self::A)?;
+        ips.extend(self.query(host, Self::AAAA)?);
+        if ips.is_empty() {
+            return Err(NetworkError::AddressNotAvailable);
+        }
+        Ok(ips)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn hosts_file() {
+        let resolver = HostsDnsResolver::from_hosts_file(
+            "# local names\n\
+             127.0.0.1 localhost Local.Test\n\
+             \n\
+             ::1 localhost # IPv6\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            resolver.resolve("localhost", None, None),
+            Ok(vec![
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST)
+            ])
+        );
+        assert_eq!(
+            resolver.resolve("local.test", Some(80), None),
+            Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)])
+        );
+        assert_eq!(
+            resolver.resolve("example.com", None, None),
+            Err(NetworkError::AddressNotAvailable)
+        );
+        assert_eq!(
+            HostsDnsResolver::from_hosts_file("localhost 127.0.0.1").unwrap_err(),
+            NetworkError::InvalidInput
+        );
+    }
+
+    #[test]
+    fn hosts_fallback() {
+        let mut fallback = HostsDnsResolver::new();
+        fallback
+            .insert("example.com", IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)))
+            .insert("example.net", IpAddr::V4(Ipv4Addr::new(192, 0, 2, 3)));
+        let mut resolver = HostsDnsResolver::new().with_fallback(Arc::new(fallback));
+        resolver.insert("example.com", IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)));
+
+        assert_eq!(
+            resolver.resolve("example.com", None, None),
+            Ok(vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))])
+        );
+        assert_eq!(
+            resolver.resolve("example.net", None, None),
+            Ok(vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 3))])
+        );
+        assert_eq!(
+            resolver.resolve("example.org", None, None),
+            Err(NetworkError::AddressNotAvailable)
+        );
+    }
+}
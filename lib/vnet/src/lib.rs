@@ -4,15 +4,31 @@ use std::net::Ipv4Addr;
 use std::net::Ipv6Addr;
 use std::net::Shutdown;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
 use thiserror::Error;
 
+mod dns;
+mod switch;
+mod tap;
+
 pub use bytes::Bytes;
 pub use bytes::BytesMut;
 
+#[cfg(feature = "dns-over-https")]
+pub use dns::DohDnsResolver;
+pub use dns::{DnsResolver, HostsDnsResolver, SystemDnsResolver};
+pub use switch::{
+    SwitchNetworking, SwitchTcpListener, SwitchTcpStream, SwitchUdpSocket, VirtualSwitch,
+};
+pub use tap::{
+    CapturedPacket, Direction, PacketTap, PcapWriter, Protocol, TapNetworking, TapTcpListener,
+    TapTcpSocket, TapUdpSocket,
+};
+
 pub type Result<T> = std::result::Result<T, NetworkError>;
 
 /// Socket descriptors are also file descriptors and so
@@ -144,6 +160,35 @@ pub trait VirtualNetworking: fmt::Debug + Send + Sync + 'static {
         port: Option<u16>,
         dns_server: Option<IpAddr>,
     ) -> Result<Vec<IpAddr>>;
+
+    /// Listens for connections on a Unix domain socket bound to `path`
+    ///
+    /// Unix domain sockets are stream sockets without IP addresses: the
+    /// sockets they accept report unspecified local and peer addresses.
+    fn listen_unix(&self, _path: &Path) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        Err(NetworkError::Unsupported)
+    }
+
+    /// Opens a connection to the Unix domain socket bound to `path`
+    fn connect_unix(
+        &self,
+        _path: &Path,
+        _timeout: Option<Duration>,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        Err(NetworkError::Unsupported)
+    }
+
+    /// Upgrades a connected TCP socket to TLS, performing the client
+    /// handshake with `server_name` as the name the certificate of the
+    /// peer is checked against. The returned socket sends and receives
+    /// the plaintext.
+    fn upgrade_tls(
+        &self,
+        _socket: Box<dyn VirtualTcpSocket + Sync>,
+        _server_name: &str,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        Err(NetworkError::Unsupported)
+    }
 }
 
 /// Holds the interface used to work with a pending HTTP request
@@ -231,6 +276,15 @@ pub trait VirtualSocket: fmt::Debug + Send + Sync + 'static {
 
     /// Returns the status/state of the socket
     fn status(&self) -> Result<SocketStatus>;
+
+    /// Returns the descriptor of the host socket this socket sends its
+    /// data to unchanged, for the callers able to use it directly, e.g.
+    /// with `sendfile(2)`. Defaults to `None`, which means the data
+    /// must go through `send`
+    #[cfg(unix)]
+    fn as_raw_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        None
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
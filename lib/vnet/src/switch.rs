@@ -0,0 +1,965 @@
+//! An in-memory network shared by a group of instances, so they can talk
+//! to each other over sockets without touching the network of the host.
+//!
+//! ```rust,ignore
+//! use wasmer_vnet::{IpCidr, VirtualSwitch};
+//!
+//! let switch = VirtualSwitch::new(IpCidr {
+//!     ip: "10.0.0.0".parse()?,
+//!     prefix: 24,
+//! });
+//! // Each instance joins the switch with its own IP address.
+//! let server = switch.join()?; // 10.0.0.1
+//! let client = switch.join()?; // 10.0.0.2
+//!
+//! // With WASI, the networking is part of the runtime of the instance.
+//! let mut runtime = PluggableRuntimeImplementation::default();
+//! runtime.set_networking_implementation(server);
+//! let env = WasiState::new("server").runtime(runtime).finalize()?;
+//! ```
+//!
+//! The TCP and UDP sockets are supported; the loopback addresses are
+//! routed to the instance itself.
+
+use crate::{
+    IpCidr, IpRoute, NetworkError, Result, SocketHttpRequest, SocketReceive, SocketReceiveFrom,
+    SocketStatus, StreamSecurity, TimeType, VirtualConnectedSocket, VirtualConnectionlessSocket,
+    VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket, VirtualSocket, VirtualTcpListener,
+    VirtualTcpSocket, VirtualUdpSocket, VirtualWebSocket,
+};
+use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+/// First port handed out to the sockets bound to port 0.
+const EPHEMERAL_PORTS_START: u16 = 49152;
+
+/// A software switch connecting the [`SwitchNetworking`]s that joined it.
+#[derive(Debug)]
+pub struct VirtualSwitch {
+    subnet: IpCidr,
+    state: Mutex<SwitchState>,
+}
+
+#[derive(Debug, Default)]
+struct SwitchState {
+    hosts: HashSet<IpAddr>,
+    tcp_listeners: HashMap<SocketAddr, mpsc::Sender<SwitchTcpStream>>,
+    udp_sockets: HashMap<SocketAddr, mpsc::Sender<(Bytes, SocketAddr)>>,
+    next_port: u16,
+}
+
+impl SwitchState {
+    fn is_bound(&self, addr: &SocketAddr) -> bool {
+        self.tcp_listeners.contains_key(addr) || self.udp_sockets.contains_key(addr)
+    }
+
+    /// Picks the port of a socket bound to `addr`, allocating an
+    /// ephemeral port if its port is 0.
+    fn bind_addr(&mut self, addr: SocketAddr) -> Result<SocketAddr> {
+        if addr.port() != 0 {
+            return match self.is_bound(&addr) {
+                true => Err(NetworkError::AddressInUse),
+                false => Ok(addr),
+            };
+        }
+        for _ in EPHEMERAL_PORTS_START..=u16::MAX {
+            let port = self.next_ephemeral_port();
+            let addr = SocketAddr::new(addr.ip(), port);
+            if !self.is_bound(&addr) {
+                return Ok(addr);
+            }
+        }
+        Err(NetworkError::AddressInUse)
+    }
+
+    fn next_ephemeral_port(&mut self) -> u16 {
+        if self.next_port < EPHEMERAL_PORTS_START {
+            self.next_port = EPHEMERAL_PORTS_START;
+        }
+        let port = self.next_port;
+        self.next_port = self.next_port.checked_add(1).unwrap_or(0);
+        port
+    }
+}
+
+impl VirtualSwitch {
+    /// Creates a switch handing out the addresses of `subnet`.
+    pub fn new(subnet: IpCidr) -> Arc<Self> {
+        Arc::new(Self {
+            subnet,
+            state: Mutex::new(SwitchState::default()),
+        })
+    }
+
+    /// Returns the subnet of the switch.
+    pub fn subnet(&self) -> IpCidr {
+        self.subnet
+    }
+
+    /// Joins the switch with the first free address of the subnet.
+    pub fn join(self: &Arc<Self>) -> Result<SwitchNetworking> {
+        let mut state = self.state.lock().unwrap();
+        let ip = (1..)
+            .map(|index| host_addr(&self.subnet, index))
+            .take_while(Option::is_some)
+            .flatten()
+            .find(|ip| !state.hosts.contains(ip))
+            .ok_or(NetworkError::AddressNotAvailable)?;
+        state.hosts.insert(ip);
+        Ok(SwitchNetworking {
+            switch: self.clone(),
+            ip,
+        })
+    }
+
+    /// Joins the switch with a specific address of the subnet.
+    pub fn join_with_ip(self: &Arc<Self>, ip: IpAddr) -> Result<SwitchNetworking> {
+        if !cidr_contains(&self.subnet, ip) {
+            return Err(NetworkError::AddressNotAvailable);
+        }
+        let mut state = self.state.lock().unwrap();
+        if !state.hosts.insert(ip) {
+            return Err(NetworkError::AddressInUse);
+        }
+        Ok(SwitchNetworking {
+            switch: self.clone(),
+            ip,
+        })
+    }
+}
+
+fn cidr_contains(cidr: &IpCidr, ip: IpAddr) -> bool {
+    match (cidr.ip, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => {
+            let mask = u32::MAX
+                .checked_shl(32u32.saturating_sub(cidr.prefix as u32))
+                .unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(ip)) => {
+            let mask = u128::MAX
+                .checked_shl(128u32.saturating_sub(cidr.prefix as u32))
+                .unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Returns the address of the `index`th host of `subnet`, skipping the
+/// broadcast address of the IPv4 subnets.
+fn host_addr(subnet: &IpCidr, index: u128) -> Option<IpAddr> {
+    match subnet.ip {
+        IpAddr::V4(network) => {
+            let host_bits = 32u32.saturating_sub(subnet.prefix as u32);
+            let hosts = (1u64 << host_bits).saturating_sub(1) as u128;
+            if index >= hosts {
+                return None;
+            }
+            let mask = u32::MAX.checked_shl(host_bits).unwrap_or(0);
+            Some(IpAddr::V4(Ipv4Addr::from(
+                (u32::from(network) & mask) | index as u32,
+            )))
+        }
+        IpAddr::V6(network) => {
+            let host_bits = 128u32.saturating_sub(subnet.prefix as u32);
+            if host_bits < 128 && index >= 1u128 << host_bits {
+                return None;
+            }
+            let mask = u128::MAX.checked_shl(host_bits).unwrap_or(0);
+            Some(IpAddr::V6(Ipv6Addr::from(
+                (u128::from(network) & mask) | index,
+            )))
+        }
+    }
+}
+
+/// The networking of an instance connected to a [`VirtualSwitch`].
+///
+/// The address of the instance is released when it's dropped.
+#[derive(Debug)]
+pub struct SwitchNetworking {
+    switch: Arc<VirtualSwitch>,
+    ip: IpAddr,
+}
+
+impl SwitchNetworking {
+    /// Returns the address of the instance on the switch.
+    pub fn ip(&self) -> IpAddr {
+        self.ip
+    }
+
+    /// Maps the unspecified and loopback addresses to the address of
+    /// the instance.
+    fn route(&self, ip: IpAddr) -> IpAddr {
+        if ip.is_unspecified() || ip.is_loopback() {
+            self.ip
+        } else {
+            ip
+        }
+    }
+
+    fn local_addr(&self, addr: SocketAddr) -> Result<SocketAddr> {
+        let ip = self.route(addr.ip());
+        if ip != self.ip {
+            return Err(NetworkError::AddressNotAvailable);
+        }
+        Ok(SocketAddr::new(ip, addr.port()))
+    }
+}
+
+impl Drop for SwitchNetworking {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.switch.state.lock() {
+            state.hosts.remove(&self.ip);
+        }
+    }
+}
+
+impl VirtualNetworking for SwitchNetworking {
+    fn ws_connect(&self, _url: &str) -> Result<Box<dyn VirtualWebSocket + Sync>> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn http_request(
+        &self,
+        _url: &str,
+        _method: &str,
+        _headers: &str,
+        _gzip: bool,
+    ) -> Result<SocketHttpRequest> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn bridge(&self, _network: &str, _access_token: &str, _security: StreamSecurity) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn unbridge(&self) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        Ok(vec![self.ip])
+    }
+
+    fn ip_add(&self, _ip: IpAddr, _prefix: u8) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn ip_remove(&self, _ip: IpAddr) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn ip_clear(&self) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        Ok(vec![IpCidr {
+            ip: self.ip,
+            prefix: self.switch.subnet.prefix,
+        }])
+    }
+
+    fn mac(&self) -> Result<[u8; 6]> {
+        // A locally administered address derived from the IP address.
+        let octets = match self.ip {
+            IpAddr::V4(ip) => ip.octets(),
+            IpAddr::V6(ip) => {
+                let octets = ip.octets();
+                [octets[12], octets[13], octets[14], octets[15]]
+            }
+        };
+        Ok([0x02, 0x00, octets[0], octets[1], octets[2], octets[3]])
+    }
+
+    fn gateway_set(&self, _ip: IpAddr) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn route_add(
+        &self,
+        _cidr: IpCidr,
+        _via_router: IpAddr,
+        _preferred_until: Option<Duration>,
+        _expires_at: Option<Duration>,
+    ) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn route_remove(&self, _cidr: IpAddr) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn route_clear(&self) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn route_list(&self) -> Result<Vec<IpRoute>> {
+        Ok(Vec::new())
+    }
+
+    fn bind_raw(&self) -> Result<Box<dyn VirtualRawSocket + Sync>> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        _only_v6: bool,
+        _reuse_port: bool,
+        _reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let addr = self.local_addr(addr)?;
+        let mut state = self.switch.state.lock().unwrap();
+        let addr = state.bind_addr(addr)?;
+        let (tx, rx) = mpsc::channel();
+        state.tcp_listeners.insert(addr, tx);
+        Ok(Box::new(SwitchTcpListener {
+            switch: self.switch.clone(),
+            addr,
+            rx: Mutex::new(rx),
+            timeout: None,
+            ttl: 64,
+        }))
+    }
+
+    fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        _reuse_port: bool,
+        _reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        let addr = self.local_addr(addr)?;
+        let mut state = self.switch.state.lock().unwrap();
+        let addr = state.bind_addr(addr)?;
+        let (tx, rx) = mpsc::channel();
+        state.udp_sockets.insert(addr, tx);
+        Ok(Box::new(SwitchUdpSocket {
+            switch: self.switch.clone(),
+            ip: self.ip,
+            addr,
+            rx: Mutex::new(rx),
+            peer: None,
+            peeked: None,
+            read_timeout: None,
+            ttl: 64,
+        }))
+    }
+
+    fn bind_icmp(&self, _addr: IpAddr) -> Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let addr = self.local_addr(addr)?;
+        let peer = SocketAddr::new(self.route(peer.ip()), peer.port());
+        let mut state = self.switch.state.lock().unwrap();
+        let addr = match addr.port() {
+            0 => SocketAddr::new(addr.ip(), state.next_ephemeral_port()),
+            _ => addr,
+        };
+        let listener = state
+            .tcp_listeners
+            .get(&peer)
+            .ok_or(NetworkError::ConnectionRefused)?;
+
+        let (client_tx, server_rx) = mpsc::channel();
+        let (server_tx, client_rx) = mpsc::channel();
+        listener
+            .send(SwitchTcpStream::new(peer, addr, server_tx, server_rx))
+            .map_err(|_| NetworkError::ConnectionRefused)?;
+        let mut stream = SwitchTcpStream::new(addr, peer, client_tx, client_rx);
+        stream.connect_timeout = timeout;
+        Ok(Box::new(stream))
+    }
+
+    fn resolve(
+        &self,
+        host: &str,
+        _port: Option<u16>,
+        _dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        if host.eq_ignore_ascii_case("localhost") {
+            return Ok(vec![self.ip]);
+        }
+        host.parse()
+            .map(|ip| vec![ip])
+            .map_err(|_| NetworkError::AddressNotAvailable)
+    }
+}
+
+/// A TCP listener of a [`SwitchNetworking`].
+#[derive(Debug)]
+pub struct SwitchTcpListener {
+    switch: Arc<VirtualSwitch>,
+    addr: SocketAddr,
+    rx: Mutex<mpsc::Receiver<SwitchTcpStream>>,
+    timeout: Option<Duration>,
+    ttl: u8,
+}
+
+impl Drop for SwitchTcpListener {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.switch.state.lock() {
+            state.tcp_listeners.remove(&self.addr);
+        }
+    }
+}
+
+impl VirtualTcpListener for SwitchTcpListener {
+    fn accept(&self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        if let Some(timeout) = self.timeout {
+            return self.accept_timeout(timeout);
+        }
+        let stream = self
+            .rx
+            .lock()
+            .unwrap()
+            .recv()
+            .map_err(|_| NetworkError::ConnectionAborted)?;
+        let peer = stream.peer;
+        Ok((Box::new(stream), peer))
+    }
+
+    fn accept_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        let stream = self
+            .rx
+            .lock()
+            .unwrap()
+            .recv_timeout(timeout)
+            .map_err(|err| match err {
+                mpsc::RecvTimeoutError::Timeout => NetworkError::TimedOut,
+                mpsc::RecvTimeoutError::Disconnected => NetworkError::ConnectionAborted,
+            })?;
+        let peer = stream.peer;
+        Ok((Box::new(stream), peer))
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn timeout(&self) -> Result<Option<Duration>> {
+        Ok(self.timeout)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn set_ttl(&mut self, ttl: u8) -> Result<()> {
+        self.ttl = ttl;
+        Ok(())
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        Ok(self.ttl)
+    }
+}
+
+/// A TCP connection between two instances of a [`VirtualSwitch`].
+#[derive(Debug)]
+pub struct SwitchTcpStream {
+    addr: SocketAddr,
+    peer: SocketAddr,
+    /// Closed once the writing half is shut down.
+    tx: Option<Mutex<mpsc::Sender<Bytes>>>,
+    rx: Mutex<mpsc::Receiver<Bytes>>,
+    /// Data peeked but not received yet.
+    peeked: Option<Bytes>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    linger: Option<Duration>,
+    nodelay: bool,
+    ttl: u32,
+}
+
+impl SwitchTcpStream {
+    fn new(
+        addr: SocketAddr,
+        peer: SocketAddr,
+        tx: mpsc::Sender<Bytes>,
+        rx: mpsc::Receiver<Bytes>,
+    ) -> Self {
+        Self {
+            addr,
+            peer,
+            tx: Some(Mutex::new(tx)),
+            rx: Mutex::new(rx),
+            peeked: None,
+            read_timeout: None,
+            write_timeout: None,
+            connect_timeout: None,
+            linger: None,
+            nodelay: false,
+            ttl: 64,
+        }
+    }
+
+    /// Returns the next chunk of data, which is empty once the peer
+    /// closed the connection.
+    fn read(&mut self) -> Result<Bytes> {
+        if let Some(peeked) = self.peeked.take() {
+            return Ok(peeked);
+        }
+        let rx = self.rx.get_mut().unwrap();
+        let received = match self.read_timeout {
+            Some(timeout) => rx.recv_timeout(timeout).map_err(|err| match err {
+                mpsc::RecvTimeoutError::Timeout => NetworkError::WouldBlock,
+                mpsc::RecvTimeoutError::Disconnected => NetworkError::UnexpectedEof,
+            }),
+            None => rx.recv().map_err(|_| NetworkError::UnexpectedEof),
+        };
+        match received {
+            Ok(data) => Ok(data),
+            Err(NetworkError::UnexpectedEof) => Ok(Bytes::new()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl VirtualTcpSocket for SwitchTcpStream {
+    fn set_opt_time(&mut self, ty: TimeType, timeout: Option<Duration>) -> Result<()> {
+        match ty {
+            TimeType::ReadTimeout => self.read_timeout = timeout,
+            TimeType::WriteTimeout => self.write_timeout = timeout,
+            TimeType::ConnectTimeout => self.connect_timeout = timeout,
+            TimeType::Linger => self.linger = timeout,
+            _ => return Err(NetworkError::InvalidInput),
+        }
+        Ok(())
+    }
+
+    fn opt_time(&self, ty: TimeType) -> Result<Option<Duration>> {
+        match ty {
+            TimeType::ReadTimeout => Ok(self.read_timeout),
+            TimeType::WriteTimeout => Ok(self.write_timeout),
+            TimeType::ConnectTimeout => Ok(self.connect_timeout),
+            TimeType::Linger => Ok(self.linger),
+            _ => Err(NetworkError::InvalidInput),
+        }
+    }
+
+    fn set_recv_buf_size(&mut self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn set_send_buf_size(&mut self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn set_nodelay(&mut self, nodelay: bool) -> Result<()> {
+        self.nodelay = nodelay;
+        Ok(())
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        Ok(self.nodelay)
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        Ok(self.peer)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        if how != Shutdown::Read {
+            self.tx = None;
+        }
+        Ok(())
+    }
+}
+
+impl VirtualConnectedSocket for SwitchTcpStream {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.linger = linger;
+        Ok(())
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        Ok(self.linger)
+    }
+
+    fn send(&mut self, data: Bytes) -> Result<usize> {
+        let tx = self.tx.as_mut().ok_or(NetworkError::BrokenPipe)?;
+        let len = data.len();
+        tx.get_mut()
+            .unwrap()
+            .send(data)
+            .map_err(|_| NetworkError::BrokenPipe)?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<SocketReceive> {
+        let data = self.read()?;
+        Ok(SocketReceive {
+            data,
+            truncated: false,
+        })
+    }
+
+    fn peek(&mut self) -> Result<SocketReceive> {
+        let data = self.read()?;
+        self.peeked = Some(data.clone());
+        Ok(SocketReceive {
+            data,
+            truncated: false,
+        })
+    }
+}
+
+impl VirtualSocket for SwitchTcpStream {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.ttl = ttl;
+        Ok(())
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        Ok(self.ttl)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        match self.tx {
+            Some(_) => Ok(SocketStatus::Opened),
+            None => Ok(SocketStatus::Closed),
+        }
+    }
+}
+
+/// A UDP socket of a [`SwitchNetworking`].
+///
+/// Like on a real network, the datagrams sent to an address no socket is
+/// bound to are dropped.
+#[derive(Debug)]
+pub struct SwitchUdpSocket {
+    switch: Arc<VirtualSwitch>,
+    ip: IpAddr,
+    addr: SocketAddr,
+    rx: Mutex<mpsc::Receiver<(Bytes, SocketAddr)>>,
+    peer: Option<SocketAddr>,
+    /// Datagram peeked but not received yet.
+    peeked: Option<(Bytes, SocketAddr)>,
+    read_timeout: Option<Duration>,
+    ttl: u32,
+}
+
+impl SwitchUdpSocket {
+    /// Receives the next datagram, from the connected peer if any.
+    fn read(&mut self) -> Result<(Bytes, SocketAddr)> {
+        loop {
+            let (data, from) = match self.peeked.take() {
+                Some(peeked) => peeked,
+                None => {
+                    let rx = self.rx.get_mut().unwrap();
+                    match self.read_timeout {
+                        Some(timeout) => rx.recv_timeout(timeout).map_err(|err| match err {
+                            mpsc::RecvTimeoutError::Timeout => NetworkError::WouldBlock,
+                            mpsc::RecvTimeoutError::Disconnected => NetworkError::BrokenPipe,
+                        })?,
+                        None => rx.recv().map_err(|_| NetworkError::BrokenPipe)?,
+                    }
+                }
+            };
+            match self.peer {
+                Some(peer) if peer != from => continue,
+                _ => return Ok((data, from)),
+            }
+        }
+    }
+}
+
+impl Drop for SwitchUdpSocket {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.switch.state.lock() {
+            state.udp_sockets.remove(&self.addr);
+        }
+    }
+}
+
+impl VirtualUdpSocket for SwitchUdpSocket {
+    fn connect(&mut self, addr: SocketAddr) -> Result<()> {
+        self.peer = Some(addr);
+        Ok(())
+    }
+
+    fn set_broadcast(&mut self, _broadcast: bool) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn broadcast(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn set_multicast_loop_v4(&mut self, _val: bool) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn multicast_loop_v4(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn set_multicast_loop_v6(&mut self, _val: bool) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn multicast_loop_v6(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn set_multicast_ttl_v4(&mut self, _ttl: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn multicast_ttl_v4(&self) -> Result<u32> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn join_multicast_v4(&mut self, _multiaddr: Ipv4Addr, _iface: Ipv4Addr) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn leave_multicast_v4(&mut self, _multiaddr: Ipv4Addr, _iface: Ipv4Addr) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn join_multicast_v6(&mut self, _multiaddr: Ipv6Addr, _iface: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn leave_multicast_v6(&mut self, _multiaddr: Ipv6Addr, _iface: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn addr_peer(&self) -> Result<Option<SocketAddr>> {
+        Ok(self.peer)
+    }
+}
+
+impl VirtualConnectedSocket for SwitchUdpSocket {
+    fn set_linger(&mut self, _linger: Option<Duration>) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn send(&mut self, data: Bytes) -> Result<usize> {
+        let peer = self.peer.ok_or(NetworkError::NotConnected)?;
+        self.send_to(data, peer)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<SocketReceive> {
+        let (data, _) = self.read()?;
+        Ok(SocketReceive {
+            data,
+            truncated: false,
+        })
+    }
+
+    fn peek(&mut self) -> Result<SocketReceive> {
+        let (data, from) = self.read()?;
+        self.peeked = Some((data.clone(), from));
+        Ok(SocketReceive {
+            data,
+            truncated: false,
+        })
+    }
+}
+
+impl VirtualConnectionlessSocket for SwitchUdpSocket {
+    fn send_to(&mut self, data: Bytes, addr: SocketAddr) -> Result<usize> {
+        let ip = if addr.ip().is_unspecified() || addr.ip().is_loopback() {
+            self.ip
+        } else {
+            addr.ip()
+        };
+        let len = data.len();
+        let state = self.switch.state.lock().unwrap();
+        if let Some(socket) = state.udp_sockets.get(&SocketAddr::new(ip, addr.port())) {
+            // The datagram is lost if the socket is being closed.
+            let _ = socket.send((data, self.addr));
+        }
+        Ok(len)
+    }
+
+    fn recv_from(&mut self) -> Result<SocketReceiveFrom> {
+        let (data, addr) = self.read()?;
+        Ok(SocketReceiveFrom {
+            data,
+            truncated: false,
+            addr,
+        })
+    }
+
+    fn peek_from(&mut self) -> Result<SocketReceiveFrom> {
+        let (data, addr) = self.read()?;
+        self.peeked = Some((data.clone(), addr));
+        Ok(SocketReceiveFrom {
+            data,
+            truncated: false,
+            addr,
+        })
+    }
+}
+
+impl VirtualSocket for SwitchUdpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.ttl = ttl;
+        Ok(())
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        Ok(self.ttl)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        Ok(SocketStatus::Opened)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn subnet() -> IpCidr {
+        IpCidr {
+            ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)),
+            prefix: 30,
+        }
+    }
+
+    #[test]
+    fn allocate_addresses() {
+        let switch = VirtualSwitch::new(subnet());
+        let first = switch.join().unwrap();
+        let second = switch.join().unwrap();
+        assert_eq!(first.ip(), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(second.ip(), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+        // The last address of the subnet is the broadcast address.
+        assert_eq!(
+            switch.join().unwrap_err(),
+            NetworkError::AddressNotAvailable
+        );
+
+        drop(first);
+        assert_eq!(
+            switch.join().unwrap().ip(),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))
+        );
+        assert_eq!(
+            switch.join_with_ip(second.ip()).unwrap_err(),
+            NetworkError::AddressInUse
+        );
+        assert_eq!(
+            switch
+                .join_with_ip(IpAddr::V4(Ipv4Addr::new(10, 0, 1, 1)))
+                .unwrap_err(),
+            NetworkError::AddressNotAvailable
+        );
+    }
+
+    #[test]
+    fn tcp_between_instances() {
+        let switch = VirtualSwitch::new(subnet());
+        let server = switch.join().unwrap();
+        let client = switch.join().unwrap();
+
+        let listener = server
+            .listen_tcp(([0, 0, 0, 0], 8080).into(), false, false, false)
+            .unwrap();
+        let server_addr = SocketAddr::new(server.ip(), 8080);
+        let handle = thread::spawn(move || {
+            let (mut stream, peer) = listener.accept().unwrap();
+            let data = stream.recv().unwrap().data;
+            stream.send(data).unwrap();
+            peer
+        });
+
+        let mut stream = client
+            .connect_tcp(([0, 0, 0, 0], 0).into(), server_addr, None)
+            .unwrap();
+        stream.send(Bytes::from_static(b"ping")).unwrap();
+        assert_eq!(&stream.recv().unwrap().data[..], b"ping");
+        assert_eq!(handle.join().unwrap(), stream.addr_local().unwrap());
+        // The server dropped its end of the connection.
+        assert!(stream.recv().unwrap().data.is_empty());
+
+        assert_eq!(
+            client
+                .connect_tcp(([0, 0, 0, 0], 0).into(), server_addr, None)
+                .unwrap_err(),
+            NetworkError::ConnectionRefused
+        );
+    }
+
+    #[test]
+    fn udp_between_instances() {
+        let switch = VirtualSwitch::new(subnet());
+        let first = switch.join().unwrap();
+        let second = switch.join().unwrap();
+
+        let mut a = first
+            .bind_udp(([0, 0, 0, 0], 5000).into(), false, false)
+            .unwrap();
+        let mut b = second
+            .bind_udp(([0, 0, 0, 0], 0).into(), false, false)
+            .unwrap();
+        let b_addr = b.addr_local().unwrap();
+        assert_eq!(b_addr.ip(), second.ip());
+
+        a.send_to(Bytes::from_static(b"hello"), b_addr).unwrap();
+        let received = b.recv_from().unwrap();
+        assert_eq!(&received.data[..], b"hello");
+        assert_eq!(received.addr, SocketAddr::new(first.ip(), 5000));
+        assert_eq!(
+            first
+                .bind_udp(([10, 0, 0, 1], 5000).into(), false, false)
+                .unwrap_err(),
+            NetworkError::AddressInUse
+        );
+    }
+}
@@ -0,0 +1,865 @@
+//! Capture of the traffic of the guests, to debug their connections.
+//!
+//! A [`TapNetworking`] wraps another [`VirtualNetworking`] and mirrors
+//! the TCP segments and UDP datagrams going through its sockets to a
+//! [`PacketTap`], e.g. a [`PcapWriter`] producing a file Wireshark can
+//! open:
+//!
+//! ```rust,ignore
+//! use std::fs::File;
+//! use std::sync::Arc;
+//! use wasmer_vnet::{PcapWriter, TapNetworking};
+//!
+//! let pcap = PcapWriter::new(File::create("guest.pcap")?)?;
+//! let networking = TapNetworking::new(LocalNetworking::default(), Arc::new(pcap));
+//! ```
+//!
+//! Only the payloads are captured: the IP, TCP and UDP headers of the
+//! pcap files are synthesized, without the handshakes of the TCP
+//! connections nor the checksums of the TCP and UDP headers.
+
+use crate::{
+    IpCidr, IpRoute, Result, SocketHttpRequest, SocketReceive, SocketReceiveFrom, SocketStatus,
+    StreamSecurity, TimeType, VirtualConnectedSocket, VirtualConnectionlessSocket,
+    VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket, VirtualSocket, VirtualTcpListener,
+    VirtualTcpSocket, VirtualUdpSocket, VirtualWebSocket,
+};
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The direction of a captured packet, from the point of view of the
+/// guest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received by the guest.
+    Inbound,
+    /// Sent by the guest.
+    Outbound,
+}
+
+/// The transport protocol of a captured packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// A TCP segment or a UDP datagram sent or received by the guest.
+#[derive(Debug)]
+pub struct CapturedPacket<'a> {
+    pub timestamp: SystemTime,
+    pub direction: Direction,
+    pub protocol: Protocol,
+    /// Address of the socket of the guest.
+    pub local: SocketAddr,
+    /// Address of the remote socket.
+    pub peer: SocketAddr,
+    pub data: &'a [u8],
+}
+
+impl CapturedPacket<'_> {
+    /// Returns the source and destination addresses of the packet.
+    pub fn endpoints(&self) -> (SocketAddr, SocketAddr) {
+        match self.direction {
+            Direction::Inbound => (self.peer, self.local),
+            Direction::Outbound => (self.local, self.peer),
+        }
+    }
+}
+
+/// Receives the packets captured by a [`TapNetworking`].
+///
+/// The packets are captured on the thread doing the I/O, so the tap
+/// should be quick.
+pub trait PacketTap: Send + Sync + 'static {
+    fn capture(&self, packet: &CapturedPacket<'_>);
+}
+
+impl fmt::Debug for dyn PacketTap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PacketTap")
+    }
+}
+
+impl<F> PacketTap for F
+where
+    F: Fn(&CapturedPacket<'_>) + Send + Sync + 'static,
+{
+    fn capture(&self, packet: &CapturedPacket<'_>) {
+        self(packet)
+    }
+}
+
+/// Writes the captured packets in the pcap format.
+///
+/// The packets are written as raw IP packets; the write errors are
+/// ignored so that the capture never disturbs the guest.
+pub struct PcapWriter<W: Write + Send + 'static> {
+    state: Mutex<PcapState<W>>,
+}
+
+struct PcapState<W> {
+    writer: W,
+    /// Next TCP sequence number of each direction of each connection.
+    sequences: HashMap<(SocketAddr, SocketAddr), u32>,
+}
+
+impl<W: Write + Send + 'static> PcapWriter<W> {
+    /// Link type of the raw IPv4 and IPv6 packets.
+    const LINKTYPE_RAW: u32 = 101;
+    /// Payload carried by each synthesized packet at most.
+    const MAX_PAYLOAD: usize = 65000;
+
+    /// Creates a writer, writing the pcap header to `writer`.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&0i32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&65535u32.to_le_bytes());
+        header.extend_from_slice(&Self::LINKTYPE_RAW.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(Self {
+            state: Mutex::new(PcapState {
+                writer,
+                sequences: HashMap::new(),
+            }),
+        })
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.state.into_inner().unwrap().writer
+    }
+
+    fn write_packet(state: &mut PcapState<W>, packet: &CapturedPacket<'_>) -> io::Result<()> {
+        let (src, dst) = packet.endpoints();
+        let timestamp = packet
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        for payload in packet.data.chunks(Self::MAX_PAYLOAD) {
+            let transport = match packet.protocol {
+                Protocol::Tcp => {
+                    let seq = state.sequences.entry((src, dst)).or_insert(0);
+                    let header = tcp_header(src, dst, *seq);
+                    *seq = seq.wrapping_add(payload.len() as u32);
+                    header
+                }
+                Protocol::Udp => udp_header(src, dst, payload),
+            };
+            let ip = ip_packet(src.ip(), dst.ip(), packet.protocol, &transport, payload);
+
+            let mut record = Vec::with_capacity(16 + ip.len());
+            record.extend_from_slice(&(timestamp.as_secs() as u32).to_le_bytes());
+            record.extend_from_slice(&timestamp.subsec_micros().to_le_bytes());
+            record.extend_from_slice(&(ip.len() as u32).to_le_bytes());
+            record.extend_from_slice(&(ip.len() as u32).to_le_bytes());
+            record.extend_from_slice(&ip);
+            state.writer.write_all(&record)?;
+        }
+        state.writer.flush()
+    }
+}
+
+impl PcapWriter<std::fs::File> {
+    /// Creates a writer to a new pcap file at `path`.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(std::fs::File::create(path)?)
+    }
+}
+
+impl<W: Write + Send + 'static> PacketTap for PcapWriter<W> {
+    fn capture(&self, packet: &CapturedPacket<'_>) {
+        let mut state = self.state.lock().unwrap();
+        let _ = Self::write_packet(&mut state, packet);
+    }
+}
+
+impl<W: Write + Send + 'static> fmt::Debug for PcapWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PcapWriter").finish()
+    }
+}
+
+fn tcp_header(src: SocketAddr, dst: SocketAddr, seq: u32) -> Vec<u8> {
+    const PSH_ACK: u8 = 0x18;
+    let mut header = Vec::with_capacity(20);
+    header.extend_from_slice(&src.port().to_be_bytes());
+    header.extend_from_slice(&dst.port().to_be_bytes());
+    header.extend_from_slice(&seq.to_be_bytes());
+    // The acknowledgment number isn't tracked.
+    header.extend_from_slice(&0u32.to_be_bytes());
+    header.extend_from_slice(&[5 << 4, PSH_ACK]);
+    header.extend_from_slice(&u16::MAX.to_be_bytes());
+    header.extend_from_slice(&[0, 0, 0, 0]);
+    header
+}
+
+fn udp_header(src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(8);
+    header.extend_from_slice(&src.port().to_be_bytes());
+    header.extend_from_slice(&dst.port().to_be_bytes());
+    header.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+    header.extend_from_slice(&[0, 0]);
+    header
+}
+
+fn ip_packet(
+    src: IpAddr,
+    dst: IpAddr,
+    protocol: Protocol,
+    transport: &[u8],
+    payload: &[u8],
+) -> Vec<u8> {
+    let next_header = match protocol {
+        Protocol::Tcp => 6,
+        Protocol::Udp => 17,
+    };
+    let len = transport.len() + payload.len();
+    let mut packet = match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            let mut header = vec![0x45, 0];
+            header.extend_from_slice(&((20 + len) as u16).to_be_bytes());
+            // Identification, then the "don't fragment" flag.
+            header.extend_from_slice(&[0, 0, 0x40, 0, 64, next_header, 0, 0]);
+            header.extend_from_slice(&src.octets());
+            header.extend_from_slice(&dst.octets());
+            let checksum = ipv4_checksum(&header);
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+            header
+        }
+        (src, dst) => {
+            let mut header = vec![0x60, 0, 0, 0];
+            header.extend_from_slice(&(len as u16).to_be_bytes());
+            header.extend_from_slice(&[next_header, 64]);
+            header.extend_from_slice(&to_ipv6(src).octets());
+            header.extend_from_slice(&to_ipv6(dst).octets());
+            header
+        }
+    };
+    packet.extend_from_slice(transport);
+    packet.extend_from_slice(payload);
+    packet
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum = header
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]) as u32)
+        .sum::<u32>();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn unspecified_addr() -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0)
+}
+
+/// A [`VirtualNetworking`] mirroring the traffic of the TCP and UDP
+/// sockets of another one to a [`PacketTap`].
+///
+/// The other sockets, e.g. the raw sockets or the Unix domain sockets,
+/// aren't captured. The connections upgraded to TLS are captured
+/// encrypted, as they go through the network.
+pub struct TapNetworking<N: VirtualNetworking> {
+    inner: N,
+    tap: Arc<dyn PacketTap>,
+}
+
+impl<N: VirtualNetworking> TapNetworking<N> {
+    /// Captures the traffic of the sockets of `inner` to `tap`.
+    pub fn new(inner: N, tap: Arc<dyn PacketTap>) -> Self {
+        Self { inner, tap }
+    }
+
+    /// Returns the wrapped networking.
+    pub fn inner(&self) -> &N {
+        &self.inner
+    }
+}
+
+impl<N: VirtualNetworking> fmt::Debug for TapNetworking<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TapNetworking")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<N: VirtualNetworking> VirtualNetworking for TapNetworking<N> {
+    fn ws_connect(&self, url: &str) -> Result<Box<dyn VirtualWebSocket + Sync>> {
+        self.inner.ws_connect(url)
+    }
+
+    fn http_request(
+        &self,
+        url: &str,
+        method: &str,
+        headers: &str,
+        gzip: bool,
+    ) -> Result<SocketHttpRequest> {
+        self.inner.http_request(url, method, headers, gzip)
+    }
+
+    fn bridge(&self, network: &str, access_token: &str, security: StreamSecurity) -> Result<()> {
+        self.inner.bridge(network, access_token, security)
+    }
+
+    fn unbridge(&self) -> Result<()> {
+        self.inner.unbridge()
+    }
+
+    fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire()
+    }
+
+    fn ip_add(&self, ip: IpAddr, prefix: u8) -> Result<()> {
+        self.inner.ip_add(ip, prefix)
+    }
+
+    fn ip_remove(&self, ip: IpAddr) -> Result<()> {
+        self.inner.ip_remove(ip)
+    }
+
+    fn ip_clear(&self) -> Result<()> {
+        self.inner.ip_clear()
+    }
+
+    fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        self.inner.ip_list()
+    }
+
+    fn mac(&self) -> Result<[u8; 6]> {
+        self.inner.mac()
+    }
+
+    fn gateway_set(&self, ip: IpAddr) -> Result<()> {
+        self.inner.gateway_set(ip)
+    }
+
+    fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+    }
+
+    fn route_remove(&self, cidr: IpAddr) -> Result<()> {
+        self.inner.route_remove(cidr)
+    }
+
+    fn route_clear(&self) -> Result<()> {
+        self.inner.route_clear()
+    }
+
+    fn route_list(&self) -> Result<Vec<IpRoute>> {
+        self.inner.route_list()
+    }
+
+    fn bind_raw(&self) -> Result<Box<dyn VirtualRawSocket + Sync>> {
+        self.inner.bind_raw()
+    }
+
+    fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let inner = self
+            .inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)?;
+        Ok(Box::new(TapTcpListener {
+            inner,
+            tap: self.tap.clone(),
+        }))
+    }
+
+    fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        let inner = self.inner.bind_udp(addr, reuse_port, reuse_addr)?;
+        Ok(Box::new(TapUdpSocket {
+            inner,
+            tap: self.tap.clone(),
+        }))
+    }
+
+    fn bind_icmp(&self, addr: IpAddr) -> Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.inner.bind_icmp(addr)
+    }
+
+    fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let inner = self.inner.connect_tcp(addr, peer, timeout)?;
+        Ok(Box::new(TapTcpSocket::new(inner, self.tap.clone())))
+    }
+
+    fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        self.inner.resolve(host, port, dns_server)
+    }
+
+    fn listen_unix(&self, path: &Path) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        self.inner.listen_unix(path)
+    }
+
+    fn connect_unix(
+        &self,
+        path: &Path,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        self.inner.connect_unix(path, timeout)
+    }
+
+    fn upgrade_tls(
+        &self,
+        socket: Box<dyn VirtualTcpSocket + Sync>,
+        server_name: &str,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        self.inner.upgrade_tls(socket, server_name)
+    }
+}
+
+/// A TCP listener whose accepted sockets are captured.
+#[derive(Debug)]
+pub struct TapTcpListener {
+    inner: Box<dyn VirtualTcpListener + Sync>,
+    tap: Arc<dyn PacketTap>,
+}
+
+impl VirtualTcpListener for TapTcpListener {
+    fn accept(&self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        let (socket, addr) = self.inner.accept()?;
+        Ok((Box::new(TapTcpSocket::new(socket, self.tap.clone())), addr))
+    }
+
+    fn accept_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        let (socket, addr) = self.inner.accept_timeout(timeout)?;
+        Ok((Box::new(TapTcpSocket::new(socket, self.tap.clone())), addr))
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.inner.set_timeout(timeout)
+    }
+
+    fn timeout(&self) -> Result<Option<Duration>> {
+        self.inner.timeout()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn set_ttl(&mut self, ttl: u8) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        self.inner.ttl()
+    }
+}
+
+/// A TCP socket whose segments are captured.
+#[derive(Debug)]
+pub struct TapTcpSocket {
+    inner: Box<dyn VirtualTcpSocket + Sync>,
+    tap: Arc<dyn PacketTap>,
+    local: SocketAddr,
+    peer: SocketAddr,
+}
+
+impl TapTcpSocket {
+    fn new(inner: Box<dyn VirtualTcpSocket + Sync>, tap: Arc<dyn PacketTap>) -> Self {
+        let local = inner.addr_local().unwrap_or_else(|_| unspecified_addr());
+        let peer = inner.addr_peer().unwrap_or_else(|_| unspecified_addr());
+        Self {
+            inner,
+            tap,
+            local,
+            peer,
+        }
+    }
+
+    fn capture(&self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        self.tap.capture(&CapturedPacket {
+            timestamp: SystemTime::now(),
+            direction,
+            protocol: Protocol::Tcp,
+            local: self.local,
+            peer: self.peer,
+            data,
+        });
+    }
+}
+
+impl VirtualTcpSocket for TapTcpSocket {
+    fn set_opt_time(&mut self, ty: TimeType, timeout: Option<Duration>) -> Result<()> {
+        self.inner.set_opt_time(ty, timeout)
+    }
+
+    fn opt_time(&self, ty: TimeType) -> Result<Option<Duration>> {
+        self.inner.opt_time(ty)
+    }
+
+    fn set_recv_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_recv_buf_size(size)
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        self.inner.recv_buf_size()
+    }
+
+    fn set_send_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_send_buf_size(size)
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        self.inner.send_buf_size()
+    }
+
+    fn set_nodelay(&mut self, nodelay: bool) -> Result<()> {
+        self.inner.set_nodelay(nodelay)
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        self.inner.nodelay()
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        self.inner.addr_peer()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        VirtualTcpSocket::flush(self.inner.as_mut())
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        self.inner.shutdown(how)
+    }
+}
+
+impl VirtualConnectedSocket for TapTcpSocket {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    fn send(&mut self, data: Bytes) -> Result<usize> {
+        let sent = self.inner.send(data.clone())?;
+        self.capture(Direction::Outbound, &data[..sent.min(data.len())]);
+        Ok(sent)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        VirtualConnectedSocket::flush(self.inner.as_mut())
+    }
+
+    fn recv(&mut self) -> Result<SocketReceive> {
+        let received = self.inner.recv()?;
+        self.capture(Direction::Inbound, &received.data);
+        Ok(received)
+    }
+
+    fn peek(&mut self) -> Result<SocketReceive> {
+        self.inner.peek()
+    }
+}
+
+impl VirtualSocket for TapTcpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+}
+
+/// A UDP socket whose datagrams are captured.
+#[derive(Debug)]
+pub struct TapUdpSocket {
+    inner: Box<dyn VirtualUdpSocket + Sync>,
+    tap: Arc<dyn PacketTap>,
+}
+
+impl TapUdpSocket {
+    fn capture(&self, direction: Direction, peer: SocketAddr, data: &[u8]) {
+        self.tap.capture(&CapturedPacket {
+            timestamp: SystemTime::now(),
+            direction,
+            protocol: Protocol::Udp,
+            local: self
+                .inner
+                .addr_local()
+                .unwrap_or_else(|_| unspecified_addr()),
+            peer,
+            data,
+        });
+    }
+
+    fn connected_peer(&self) -> SocketAddr {
+        self.inner
+            .addr_peer()
+            .ok()
+            .flatten()
+            .unwrap_or_else(unspecified_addr)
+    }
+}
+
+impl VirtualUdpSocket for TapUdpSocket {
+    fn connect(&mut self, addr: SocketAddr) -> Result<()> {
+        self.inner.connect(addr)
+    }
+
+    fn set_broadcast(&mut self, broadcast: bool) -> Result<()> {
+        self.inner.set_broadcast(broadcast)
+    }
+
+    fn broadcast(&self) -> Result<bool> {
+        self.inner.broadcast()
+    }
+
+    fn set_multicast_loop_v4(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v4(val)
+    }
+
+    fn multicast_loop_v4(&self) -> Result<bool> {
+        self.inner.multicast_loop_v4()
+    }
+
+    fn set_multicast_loop_v6(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v6(val)
+    }
+
+    fn multicast_loop_v6(&self) -> Result<bool> {
+        self.inner.multicast_loop_v6()
+    }
+
+    fn set_multicast_ttl_v4(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_multicast_ttl_v4(ttl)
+    }
+
+    fn multicast_ttl_v4(&self) -> Result<u32> {
+        self.inner.multicast_ttl_v4()
+    }
+
+    fn join_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.join_multicast_v4(multiaddr, iface)
+    }
+
+    fn leave_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.leave_multicast_v4(multiaddr, iface)
+    }
+
+    fn join_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.join_multicast_v6(multiaddr, iface)
+    }
+
+    fn leave_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.leave_multicast_v6(multiaddr, iface)
+    }
+
+    fn addr_peer(&self) -> Result<Option<SocketAddr>> {
+        self.inner.addr_peer()
+    }
+}
+
+impl VirtualConnectedSocket for TapUdpSocket {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    fn send(&mut self, data: Bytes) -> Result<usize> {
+        let sent = VirtualConnectedSocket::send(self.inner.as_mut(), data.clone())?;
+        self.capture(Direction::Outbound, self.connected_peer(), &data);
+        Ok(sent)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        VirtualConnectedSocket::flush(self.inner.as_mut())
+    }
+
+    fn recv(&mut self) -> Result<SocketReceive> {
+        let received = VirtualConnectedSocket::recv(self.inner.as_mut())?;
+        self.capture(Direction::Inbound, self.connected_peer(), &received.data);
+        Ok(received)
+    }
+
+    fn peek(&mut self) -> Result<SocketReceive> {
+        self.inner.peek()
+    }
+}
+
+impl VirtualConnectionlessSocket for TapUdpSocket {
+    fn send_to(&mut self, data: Bytes, addr: SocketAddr) -> Result<usize> {
+        let sent = self.inner.send_to(data.clone(), addr)?;
+        self.capture(Direction::Outbound, addr, &data);
+        Ok(sent)
+    }
+
+    fn recv_from(&mut self) -> Result<SocketReceiveFrom> {
+        let received = self.inner.recv_from()?;
+        self.capture(Direction::Inbound, received.addr, &received.data);
+        Ok(received)
+    }
+
+    fn peek_from(&mut self) -> Result<SocketReceiveFrom> {
+        self.inner.peek_from()
+    }
+}
+
+impl VirtualSocket for TapUdpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VirtualSwitch;
+
+    #[test]
+    fn capture_tcp_segments() {
+        let switch = VirtualSwitch::new(IpCidr {
+            ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)),
+            prefix: 24,
+        });
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let tap = {
+            let captured = captured.clone();
+            move |packet: &CapturedPacket<'_>| {
+                captured.lock().unwrap().push((
+                    packet.direction,
+                    packet.endpoints(),
+                    packet.data.to_vec(),
+                ));
+            }
+        };
+        let client = TapNetworking::new(switch.join().unwrap(), Arc::new(tap));
+        let server = switch.join().unwrap();
+        let server_addr = SocketAddr::new(server.ip(), 80);
+
+        let listener = server.listen_tcp(server_addr, false, false, false).unwrap();
+        let mut stream = client
+            .connect_tcp(unspecified_addr(), server_addr, None)
+            .unwrap();
+        let client_addr = stream.addr_local().unwrap();
+        let (mut accepted, _) = listener.accept().unwrap();
+        stream.send(Bytes::from_static(b"ping")).unwrap();
+        accepted.recv().unwrap();
+        accepted.send(Bytes::from_static(b"pong")).unwrap();
+        stream.recv().unwrap();
+
+        assert_eq!(
+            *captured.lock().unwrap(),
+            vec![
+                (
+                    Direction::Outbound,
+                    (client_addr, server_addr),
+                    b"ping".to_vec()
+                ),
+                (
+                    Direction::Inbound,
+                    (server_addr, client_addr),
+                    b"pong".to_vec()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pcap_format() {
+        let pcap = PcapWriter::new(Vec::new()).unwrap();
+        pcap.capture(&CapturedPacket {
+            timestamp: UNIX_EPOCH + Duration::from_micros(1_000_002),
+            direction: Direction::Outbound,
+            protocol: Protocol::Udp,
+            local: ([10, 0, 0, 1], 5000).into(),
+            peer: ([10, 0, 0, 2], 53).into(),
+            data: b"query",
+        });
+        let bytes = pcap.into_inner();
+
+        assert_eq!(&bytes[..4], &[0xd4, 0xc3, 0xb2, 0xa1]);
+        assert_eq!(&bytes[20..24], &101u32.to_le_bytes());
+        let record = &bytes[24..];
+        assert_eq!(&record[..4], &1u32.to_le_bytes());
+        assert_eq!(&record[4..8], &2u32.to_le_bytes());
+        assert_eq!(&record[8..12], &33u32.to_le_bytes());
+        let ip = &record[16..];
+        assert_eq!(ip.len(), 33);
+        assert_eq!(ipv4_checksum(&ip[..20]), 0);
+        assert_eq!(&ip[12..20], &[10, 0, 0, 1, 10, 0, 0, 2]);
+        assert_eq!(&ip[20..24], &[0x13, 0x88, 0, 53]);
+        assert_eq!(&ip[28..], b"query");
+    }
+}
@@ -2,18 +2,95 @@
 use bytes::{Bytes, BytesMut};
 use std::io::{Read, Write};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
 use wasmer_vnet::{
-    io_err_into_net_error, IpCidr, IpRoute, NetworkError, Result, SocketHttpRequest, SocketReceive,
-    SocketReceiveFrom, SocketStatus, StreamSecurity, TimeType, VirtualConnectedSocket,
-    VirtualConnectionlessSocket, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
-    VirtualSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket, VirtualWebSocket,
+    io_err_into_net_error, DnsResolver, IpCidr, IpRoute, NetworkError, Result, SocketHttpRequest,
+    SocketReceive, SocketReceiveFrom, SocketStatus, StreamSecurity, SystemDnsResolver, TimeType,
+    VirtualConnectedSocket, VirtualConnectionlessSocket, VirtualIcmpSocket, VirtualNetworking,
+    VirtualRawSocket, VirtualSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+    VirtualWebSocket,
 };
 
-#[derive(Debug, Default)]
-pub struct LocalNetworking {}
+mod proxy;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(unix)]
+mod unix;
+
+pub use crate::proxy::{Proxy, ProxyKind};
+#[cfg(feature = "tls")]
+pub use crate::tls::TlsTcpStream;
+#[cfg(unix)]
+pub use crate::unix::{LocalUnixListener, LocalUnixStream};
+#[cfg(feature = "tls")]
+pub use rustls::ClientConfig as TlsClientConfig;
+
+/// Networking of the host.
+///
+/// The outgoing TCP connections go through the [`Proxy`], if any. The
+/// `ws_connect` and `http_request` calls aren't supported yet:
+/// implementations built on top of this one must open their
+/// connections with [`VirtualNetworking::connect_tcp`] so they go
+/// through the proxy as well.
+///
+/// With the `tls` feature, the TCP connections can be upgraded to TLS
+/// with [`VirtualNetworking::upgrade_tls`]. The certificates of the
+/// peers are checked against the Mozilla root certificates, unless
+/// another configuration is given with [`LocalNetworking::with_tls_config`].
+///
+/// On Unix, the guests can use the Unix domain sockets of the
+/// directories mapped with [`LocalNetworking::map_unix_socket_dir`].
+#[derive(Default)]
+pub struct LocalNetworking {
+    proxy: Option<Proxy>,
+    unix_socket_dirs: Vec<(PathBuf, PathBuf)>,
+    #[cfg(feature = "tls")]
+    tls_config: Option<std::sync::Arc<TlsClientConfig>>,
+}
+
+impl LocalNetworking {
+    /// Opens the outgoing TCP connections through `proxy`.
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Returns the proxy the outgoing TCP connections go through.
+    pub fn proxy(&self) -> Option<&Proxy> {
+        self.proxy.as_ref()
+    }
+
+    /// Lets the guests bind and connect to the Unix domain sockets of the
+    /// `host` directory, which they see as the `guest` directory.
+    pub fn map_unix_socket_dir(
+        mut self,
+        guest: impl Into<PathBuf>,
+        host: impl Into<PathBuf>,
+    ) -> Self {
+        self.unix_socket_dirs.push((guest.into(), host.into()));
+        self
+    }
+
+    /// Upgrades the TCP connections to TLS with `config`, e.g. to trust
+    /// other root certificates or to authenticate the client.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(mut self, config: std::sync::Arc<TlsClientConfig>) -> Self {
+        self.tls_config = Some(config);
+        self
+    }
+}
+
+impl std::fmt::Debug for LocalNetworking {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalNetworking")
+            .field("proxy", &self.proxy)
+            .field("unix_socket_dirs", &self.unix_socket_dirs)
+            .finish()
+    }
+}
 
 #[allow(unused_variables)]
 impl VirtualNetworking for LocalNetworking {
@@ -131,13 +208,18 @@ impl VirtualNetworking for LocalNetworking {
         peer: SocketAddr,
         timeout: Option<Duration>,
     ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
-        let stream = if let Some(timeout) = timeout {
-            std::net::TcpStream::connect_timeout(&peer, timeout)
-        } else {
-            std::net::TcpStream::connect(peer)
+        let stream = match &self.proxy {
+            Some(proxy) if proxy.applies_to(&peer) => proxy.connect(peer, timeout),
+            _ => {
+                if let Some(timeout) = timeout {
+                    std::net::TcpStream::connect_timeout(&peer, timeout)
+                } else {
+                    std::net::TcpStream::connect(peer)
+                }
+            }
         }
         .map_err(io_err_into_net_error)?;
-        let peer = stream.peer_addr().map_err(io_err_into_net_error)?;
+        // The guest sees the peer it connected to, even through a proxy.
         Ok(Box::new(LocalTcpStream {
             stream,
             addr: peer,
@@ -151,17 +233,42 @@ impl VirtualNetworking for LocalNetworking {
         port: Option<u16>,
         dns_server: Option<IpAddr>,
     ) -> Result<Vec<IpAddr>> {
-        use std::net::ToSocketAddrs;
-        Ok(if let Some(port) = port {
-            let host = format!("{}:{}", host, port);
-            host.to_socket_addrs()
-                .map(|a| a.map(|a| a.ip()).collect::<Vec<_>>())
-                .map_err(io_err_into_net_error)?
-        } else {
-            host.to_socket_addrs()
-                .map(|a| a.map(|a| a.ip()).collect::<Vec<_>>())
-                .map_err(io_err_into_net_error)?
-        })
+        SystemDnsResolver::default().resolve(host, port, dns_server)
+    }
+
+    #[cfg(unix)]
+    fn listen_unix(&self, path: &Path) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let path =
+            unix::map_path(&self.unix_socket_dirs, path).ok_or(NetworkError::PermissionDenied)?;
+        unix::listen(&path)
+    }
+
+    #[cfg(unix)]
+    fn connect_unix(
+        &self,
+        path: &Path,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let path =
+            unix::map_path(&self.unix_socket_dirs, path).ok_or(NetworkError::PermissionDenied)?;
+        unix::connect(&path, timeout)
+    }
+
+    #[cfg(feature = "tls")]
+    fn upgrade_tls(
+        &self,
+        socket: Box<dyn VirtualTcpSocket + Sync>,
+        server_name: &str,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let config = match &self.tls_config {
+            Some(config) => config.clone(),
+            None => tls::default_config(),
+        };
+        Ok(Box::new(TlsTcpStream::connect(
+            socket,
+            config,
+            server_name,
+        )?))
     }
 }
 
@@ -365,7 +472,7 @@ impl VirtualConnectedSocket for LocalTcpStream {
 
     fn recv(&mut self) -> Result<SocketReceive> {
         let buf_size = 8192;
-        let mut buf = BytesMut::with_capacity(buf_size);
+        let mut buf = vec![0u8; buf_size];
         let read = self
             .stream
             .read(&mut buf[..])
@@ -379,7 +486,7 @@ impl VirtualConnectedSocket for LocalTcpStream {
 
     fn peek(&mut self) -> Result<SocketReceive> {
         let buf_size = 8192;
-        let mut buf = BytesMut::with_capacity(buf_size);
+        let mut buf = vec![0u8; buf_size];
         let read = self
             .stream
             .peek(&mut buf[..])
@@ -408,6 +515,11 @@ impl VirtualSocket for LocalTcpStream {
     fn status(&self) -> Result<SocketStatus> {
         Ok(SocketStatus::Opened)
     }
+
+    #[cfg(unix)]
+    fn as_raw_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        Some(std::os::unix::io::AsRawFd::as_raw_fd(&self.stream))
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,351 @@
+//! Outgoing connections through a SOCKS5 or HTTP proxy.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+use wasmer_vnet::IpCidr;
+
+/// The protocol spoken with a proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyKind {
+    /// A SOCKS5 proxy (RFC 1928).
+    Socks5,
+    /// A HTTP proxy supporting the `CONNECT` method.
+    Http,
+}
+
+/// A proxy the outgoing TCP connections of a [`LocalNetworking`] go
+/// through.
+///
+/// [`LocalNetworking`]: crate::LocalNetworking
+#[derive(Clone)]
+pub struct Proxy {
+    kind: ProxyKind,
+    addr: SocketAddr,
+    credentials: Option<(String, String)>,
+    bypass: Vec<IpCidr>,
+}
+
+impl fmt::Debug for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The password stays out of the logs.
+        let credentials = self
+            .credentials
+            .as_ref()
+            .map(|(username, _)| (username, "<redacted>"));
+        f.debug_struct("Proxy")
+            .field("kind", &self.kind)
+            .field("addr", &self.addr)
+            .field("credentials", &credentials)
+            .field("bypass", &self.bypass)
+            .finish()
+    }
+}
+
+impl Proxy {
+    /// Creates a SOCKS5 proxy listening at `addr`.
+    pub fn socks5(addr: SocketAddr) -> Self {
+        Self::new(ProxyKind::Socks5, addr)
+    }
+
+    /// Creates a HTTP proxy listening at `addr`.
+    pub fn http(addr: SocketAddr) -> Self {
+        Self::new(ProxyKind::Http, addr)
+    }
+
+    fn new(kind: ProxyKind, addr: SocketAddr) -> Self {
+        Self {
+            kind,
+            addr,
+            credentials: None,
+            bypass: Vec::new(),
+        }
+    }
+
+    /// Authenticates to the proxy with a user name and a password.
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Connects directly to the addresses of `cidr`, e.g. to the
+    /// internal networks, instead of going through the proxy.
+    pub fn bypass(mut self, cidr: IpCidr) -> Self {
+        self.bypass.push(cidr);
+        self
+    }
+
+    /// Returns the protocol spoken with the proxy.
+    pub fn kind(&self) -> ProxyKind {
+        self.kind
+    }
+
+    /// Returns the address of the proxy.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns whether the connections to `peer` go through the proxy.
+    pub fn applies_to(&self, peer: &SocketAddr) -> bool {
+        !self
+            .bypass
+            .iter()
+            .any(|cidr| cidr_contains(cidr, peer.ip()))
+    }
+
+    /// Opens a TCP connection to `peer` through the proxy.
+    pub(crate) fn connect(
+        &self,
+        peer: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> io::Result<TcpStream> {
+        let mut stream = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&self.addr, timeout)?,
+            None => TcpStream::connect(self.addr)?,
+        };
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        match self.kind {
+            ProxyKind::Socks5 => self.socks5_handshake(&mut stream, peer)?,
+            ProxyKind::Http => self.http_handshake(&mut stream, peer)?,
+        }
+        stream.set_read_timeout(None)?;
+        stream.set_write_timeout(None)?;
+        Ok(stream)
+    }
+
+    fn socks5_handshake(&self, stream: &mut TcpStream, peer: SocketAddr) -> io::Result<()> {
+        const VERSION: u8 = 5;
+        const NO_AUTHENTICATION: u8 = 0;
+        const USERNAME_PASSWORD: u8 = 2;
+        const NO_ACCEPTABLE_METHOD: u8 = 0xff;
+        const CONNECT: u8 = 1;
+
+        if self.credentials.is_some() {
+            stream.write_all(&[VERSION, 2, NO_AUTHENTICATION, USERNAME_PASSWORD])?;
+        } else {
+            stream.write_all(&[VERSION, 1, NO_AUTHENTICATION])?;
+        }
+        let mut reply = [0u8; 2];
+        stream.read_exact(&mut reply)?;
+        match (reply[1], &self.credentials) {
+            (NO_AUTHENTICATION, _) => {}
+            (USERNAME_PASSWORD, Some((username, password))) => {
+                if username.len() > 255 || password.len() > 255 {
+                    return Err(io::ErrorKind::InvalidInput.into());
+                }
+                let mut request = vec![1, username.len() as u8];
+                request.extend_from_slice(username.as_bytes());
+                request.push(password.len() as u8);
+                request.extend_from_slice(password.as_bytes());
+                stream.write_all(&request)?;
+                stream.read_exact(&mut reply)?;
+                if reply[1] != 0 {
+                    return Err(io::ErrorKind::PermissionDenied.into());
+                }
+            }
+            (NO_ACCEPTABLE_METHOD, _) => return Err(io::ErrorKind::PermissionDenied.into()),
+            _ => return Err(io::ErrorKind::InvalidData.into()),
+        }
+
+        let mut request = vec![VERSION, CONNECT, 0];
+        match peer.ip() {
+            IpAddr::V4(ip) => {
+                request.push(1);
+                request.extend_from_slice(&ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                request.push(4);
+                request.extend_from_slice(&ip.octets());
+            }
+        }
+        request.extend_from_slice(&peer.port().to_be_bytes());
+        stream.write_all(&request)?;
+
+        let mut reply = [0u8; 4];
+        stream.read_exact(&mut reply)?;
+        match reply[1] {
+            0 => {}
+            2 => return Err(io::ErrorKind::PermissionDenied.into()),
+            3 | 4 => return Err(io::ErrorKind::AddrNotAvailable.into()),
+            5 => return Err(io::ErrorKind::ConnectionRefused.into()),
+            _ => return Err(io::ErrorKind::ConnectionAborted.into()),
+        }
+        // Skip the address the proxy bound to.
+        let addr_len = match reply[3] {
+            1 => 4,
+            4 => 16,
+            3 => {
+                let mut len = [0u8; 1];
+                stream.read_exact(&mut len)?;
+                len[0] as usize
+            }
+            _ => return Err(io::ErrorKind::InvalidData.into()),
+        };
+        let mut bound = vec![0u8; addr_len + 2];
+        stream.read_exact(&mut bound)?;
+        Ok(())
+    }
+
+    fn http_handshake(&self, stream: &mut TcpStream, peer: SocketAddr) -> io::Result<()> {
+        let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", peer);
+        if let Some((username, password)) = &self.credentials {
+            let token = base64_encode(format!("{}:{}", username, password).as_bytes());
+            request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes())?;
+
+        // Read the response byte per byte, so nothing sent by the peer
+        // after the headers is consumed.
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() > 8192 {
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            let mut byte = [0u8; 1];
+            stream.read_exact(&mut byte)?;
+            response.push(byte[0]);
+        }
+        let status = String::from_utf8_lossy(&response)
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or(io::ErrorKind::InvalidData)?;
+        match status {
+            200..=299 => Ok(()),
+            403 | 407 => Err(io::ErrorKind::PermissionDenied.into()),
+            _ => Err(io::ErrorKind::ConnectionRefused.into()),
+        }
+    }
+}
+
+fn cidr_contains(cidr: &IpCidr, ip: IpAddr) -> bool {
+    match (cidr.ip, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => {
+            let mask = u32::MAX
+                .checked_shl(32u32.saturating_sub(cidr.prefix as u32))
+                .unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(ip)) => {
+            let mask = u128::MAX
+                .checked_shl(128u32.saturating_sub(cidr.prefix as u32))
+                .unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::thread;
+
+    #[test]
+    fn encode_credentials() {
+        assert_eq!(base64_encode(b"user:pass"), "dXNlcjpwYXNz");
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_encode(b"a"), "YQ==");
+    }
+
+    #[test]
+    fn debug_redacts_password() {
+        let proxy = Proxy::http(([127, 0, 0, 1], 3128).into()).with_credentials("user", "hunter2");
+        let debug = format!("{:?}", proxy);
+        assert!(debug.contains("user"));
+        assert!(!debug.contains("hunter2"));
+    }
+
+    #[test]
+    fn bypass() {
+        let proxy = Proxy::http(([127, 0, 0, 1], 3128).into()).bypass(IpCidr {
+            ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)),
+            prefix: 8,
+        });
+        assert!(!proxy.applies_to(&([10, 1, 2, 3], 80).into()));
+        assert!(proxy.applies_to(&([192, 0, 2, 1], 80).into()));
+    }
+
+    #[test]
+    fn socks5_connect() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let proxy = Proxy::socks5(listener.local_addr()?).with_credentials("user", "pass");
+        let server = thread::spawn(move || -> io::Result<Vec<u8>> {
+            let (mut stream, _) = listener.accept()?;
+            let mut greeting = [0u8; 4];
+            stream.read_exact(&mut greeting)?;
+            stream.write_all(&[5, 2])?;
+            let mut auth = [0u8; 11];
+            stream.read_exact(&mut auth)?;
+            assert_eq!(&auth, b"\x01\x04user\x04pass");
+            stream.write_all(&[1, 0])?;
+            let mut connect = [0u8; 10];
+            stream.read_exact(&mut connect)?;
+            stream.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 80])?;
+            stream.write_all(b"hello")?;
+            Ok(connect.to_vec())
+        });
+
+        let mut stream = proxy.connect(([192, 0, 2, 1], 8080).into(), None)?;
+        let mut hello = [0u8; 5];
+        stream.read_exact(&mut hello)?;
+        assert_eq!(&hello, b"hello");
+        assert_eq!(
+            server.join().unwrap()?,
+            vec![5, 1, 0, 1, 192, 0, 2, 1, 0x1f, 0x90]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn http_connect() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let proxy = Proxy::http(listener.local_addr()?);
+        let server = thread::spawn(move || -> io::Result<String> {
+            let (mut stream, _) = listener.accept()?;
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                let mut byte = [0u8; 1];
+                stream.read_exact(&mut byte)?;
+                request.push(byte[0]);
+            }
+            stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\nhello")?;
+            Ok(String::from_utf8(request).unwrap())
+        });
+
+        let mut stream = proxy.connect(([192, 0, 2, 1], 443).into(), None)?;
+        let mut hello = [0u8; 5];
+        stream.read_exact(&mut hello)?;
+        assert_eq!(&hello, b"hello");
+        assert!(server
+            .join()
+            .unwrap()?
+            .starts_with("CONNECT 192.0.2.1:443 HTTP/1.1\r\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+//! TLS on top of the TCP connections of the guests, so they can talk to
+//! TLS servers without bundling a TLS implementation of their own.
+
+use bytes::Bytes;
+use rustls::{ClientConfig, ClientConnection, OwnedTrustAnchor, RootCertStore, ServerName};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use wasmer_vnet::{
+    io_err_into_net_error, NetworkError, Result, SocketReceive, SocketStatus, TimeType,
+    VirtualConnectedSocket, VirtualSocket, VirtualTcpSocket,
+};
+
+/// Creates a client configuration trusting the Mozilla root certificates.
+pub(crate) fn default_config() -> Arc<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+    Arc::new(
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    )
+}
+
+fn tls_err_into_net_error(err: rustls::Error) -> NetworkError {
+    match err {
+        rustls::Error::InvalidCertificateEncoding
+        | rustls::Error::InvalidCertificateSignatureType
+        | rustls::Error::InvalidCertificateSignature
+        | rustls::Error::InvalidCertificateData(_)
+        | rustls::Error::UnsupportedNameType => NetworkError::PermissionDenied,
+        _ => NetworkError::ConnectionAborted,
+    }
+}
+
+/// A TCP socket whose traffic is encrypted with TLS by the host.
+///
+/// The guest sends and receives the plaintext, while the wrapped socket
+/// carries the TLS records.
+pub struct TlsTcpStream {
+    inner: Box<dyn VirtualTcpSocket + Sync>,
+    conn: ClientConnection,
+    /// Plaintext peeked but not received yet.
+    peeked: Option<Bytes>,
+}
+
+impl TlsTcpStream {
+    /// Performs the client handshake with `server_name` over `inner`.
+    pub(crate) fn connect(
+        inner: Box<dyn VirtualTcpSocket + Sync>,
+        config: Arc<ClientConfig>,
+        server_name: &str,
+    ) -> Result<Self> {
+        let name = ServerName::try_from(server_name).map_err(|_| NetworkError::InvalidInput)?;
+        let conn = ClientConnection::new(config, name).map_err(tls_err_into_net_error)?;
+        let mut stream = Self {
+            inner,
+            conn,
+            peeked: None,
+        };
+        while stream.conn.is_handshaking() {
+            stream.write_tls()?;
+            if stream.conn.wants_read() && !stream.read_tls()? {
+                return Err(NetworkError::ConnectionAborted);
+            }
+        }
+        stream.write_tls()?;
+        Ok(stream)
+    }
+
+    /// Sends the pending TLS records to the peer.
+    fn write_tls(&mut self) -> Result<()> {
+        let mut records = Vec::new();
+        while self.conn.wants_write() {
+            self.conn
+                .write_tls(&mut records)
+                .map_err(io_err_into_net_error)?;
+        }
+        if !records.is_empty() {
+            self.inner.send(Bytes::from(records))?;
+        }
+        Ok(())
+    }
+
+    /// Receives TLS records from the peer and processes them. Returns
+    /// `false` once the peer closed the connection.
+    fn read_tls(&mut self) -> Result<bool> {
+        let received = self.inner.recv()?;
+        if received.data.is_empty() {
+            return Ok(false);
+        }
+        let mut records = &received.data[..];
+        while !records.is_empty() {
+            self.conn
+                .read_tls(&mut records)
+                .map_err(io_err_into_net_error)?;
+            if let Err(err) = self.conn.process_new_packets() {
+                // Let the peer know why the connection is aborted.
+                let _ = self.write_tls();
+                return Err(tls_err_into_net_error(err));
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the next chunk of plaintext, which is empty once the peer
+    /// closed the connection.
+    fn read_plaintext(&mut self) -> Result<Bytes> {
+        if let Some(peeked) = self.peeked.take() {
+            return Ok(peeked);
+        }
+        let mut buf = vec![0u8; 8192];
+        loop {
+            match self.conn.reader().read(&mut buf[..]) {
+                Ok(read) => return Ok(Bytes::from(buf).slice(..read)),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    // Sends the replies the records may require, e.g.
+                    // to a key update.
+                    self.write_tls()?;
+                    if !self.read_tls()? {
+                        return Ok(Bytes::new());
+                    }
+                }
+                Err(err) => return Err(io_err_into_net_error(err)),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for TlsTcpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsTcpStream")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl VirtualTcpSocket for TlsTcpStream {
+    fn set_opt_time(&mut self, ty: TimeType, timeout: Option<Duration>) -> Result<()> {
+        self.inner.set_opt_time(ty, timeout)
+    }
+
+    fn opt_time(&self, ty: TimeType) -> Result<Option<Duration>> {
+        self.inner.opt_time(ty)
+    }
+
+    fn set_recv_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_recv_buf_size(size)
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        self.inner.recv_buf_size()
+    }
+
+    fn set_send_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_send_buf_size(size)
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        self.inner.send_buf_size()
+    }
+
+    fn set_nodelay(&mut self, nodelay: bool) -> Result<()> {
+        self.inner.set_nodelay(nodelay)
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        self.inner.nodelay()
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        self.inner.addr_peer()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.write_tls()?;
+        VirtualTcpSocket::flush(self.inner.as_mut())
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        if how != Shutdown::Read {
+            self.conn.send_close_notify();
+            self.write_tls()?;
+        }
+        self.inner.shutdown(how)
+    }
+}
+
+impl VirtualConnectedSocket for TlsTcpStream {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    fn send(&mut self, data: Bytes) -> Result<usize> {
+        // The connection buffers a limited amount of plaintext, so it's
+        // encrypted and sent piecewise.
+        let mut sent = 0;
+        while sent < data.len() {
+            sent += self
+                .conn
+                .writer()
+                .write(&data[sent..])
+                .map_err(io_err_into_net_error)?;
+            self.write_tls()?;
+        }
+        Ok(sent)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.write_tls()?;
+        VirtualConnectedSocket::flush(self.inner.as_mut())
+    }
+
+    fn recv(&mut self) -> Result<SocketReceive> {
+        let data = self.read_plaintext()?;
+        Ok(SocketReceive {
+            data,
+            truncated: false,
+        })
+    }
+
+    fn peek(&mut self) -> Result<SocketReceive> {
+        let data = self.read_plaintext()?;
+        self.peeked = Some(data.clone());
+        Ok(SocketReceive {
+            data,
+            truncated: false,
+        })
+    }
+}
+
+impl VirtualSocket for TlsTcpStream {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalNetworking;
+    use std::net::TcpListener;
+    use std::thread;
+    use wasmer_vnet::VirtualNetworking;
+
+    fn connect_local() -> io::Result<(Box<dyn VirtualTcpSocket + Sync>, TcpListener)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let socket = LocalNetworking::default()
+            .connect_tcp(
+                ([0, 0, 0, 0], 0).into(),
+                listener.local_addr()?,
+                Some(Duration::from_secs(5)),
+            )
+            .unwrap();
+        Ok((socket, listener))
+    }
+
+    #[test]
+    fn invalid_server_name() -> io::Result<()> {
+        let (socket, _listener) = connect_local()?;
+        assert_eq!(
+            LocalNetworking::default()
+                .upgrade_tls(socket, "not a host name")
+                .unwrap_err(),
+            NetworkError::InvalidInput
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn peer_closing_during_handshake() -> io::Result<()> {
+        let (socket, listener) = connect_local()?;
+        let server = thread::spawn(move || -> io::Result<Vec<u8>> {
+            let (mut stream, _) = listener.accept()?;
+            let mut header = [0u8; 5];
+            stream.read_exact(&mut header)?;
+            // Read the whole record, so closing the connection doesn't
+            // reset it.
+            let mut client_hello = vec![0u8; u16::from_be_bytes([header[3], header[4]]) as usize];
+            stream.read_exact(&mut client_hello)?;
+            Ok(header.to_vec())
+        });
+
+        assert_eq!(
+            LocalNetworking::default()
+                .upgrade_tls(socket, "example.com")
+                .unwrap_err(),
+            NetworkError::ConnectionAborted
+        );
+        // A handshake record of TLS.
+        assert_eq!(server.join().unwrap()?[0], 0x16);
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+//! Unix domain sockets of the host, so the guests can talk to the local
+//! daemons, e.g. a database listening on `/run/postgresql`.
+
+use bytes::Bytes;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use wasmer_vnet::{
+    io_err_into_net_error, NetworkError, Result, SocketReceive, SocketStatus, TimeType,
+    VirtualConnectedSocket, VirtualSocket, VirtualTcpListener, VirtualTcpSocket,
+};
+
+/// Unix domain sockets have no IP address: this is the address reported
+/// as their local and peer addresses.
+fn unspecified_addr() -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0)
+}
+
+/// Maps the path of a socket in the sandbox of the guest to the path of
+/// the host, or returns `None` if the path is outside of the mapped
+/// directories.
+pub(crate) fn map_path(mappings: &[(PathBuf, PathBuf)], path: &Path) -> Option<PathBuf> {
+    if path
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return None;
+    }
+    mappings.iter().find_map(|(guest, host)| {
+        path.strip_prefix(guest)
+            .ok()
+            .map(|relative| host.join(relative))
+    })
+}
+
+pub(crate) fn listen(path: &Path) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+    let listener = UnixListener::bind(path).map_err(io_err_into_net_error)?;
+    Ok(Box::new(LocalUnixListener {
+        listener,
+        timeout: None,
+    }))
+}
+
+pub(crate) fn connect(
+    path: &Path,
+    timeout: Option<Duration>,
+) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+    // Connecting to a local socket doesn't wait for the peer, so there's
+    // no timeout to apply.
+    let stream = UnixStream::connect(path).map_err(io_err_into_net_error)?;
+    Ok(Box::new(LocalUnixStream {
+        stream,
+        connect_timeout: timeout,
+    }))
+}
+
+#[derive(Debug)]
+pub struct LocalUnixListener {
+    listener: UnixListener,
+    timeout: Option<Duration>,
+}
+
+impl VirtualTcpListener for LocalUnixListener {
+    fn accept(&self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        if let Some(timeout) = &self.timeout {
+            return self.accept_timeout(*timeout);
+        }
+        let (stream, _) = self.listener.accept().map_err(io_err_into_net_error)?;
+        Ok((
+            Box::new(LocalUnixStream {
+                stream,
+                connect_timeout: None,
+            }),
+            unspecified_addr(),
+        ))
+    }
+
+    fn accept_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        // There's no accept with a timeout for Unix domain sockets, so
+        // the listener is polled until the deadline.
+        let deadline = Instant::now() + timeout;
+        self.listener
+            .set_nonblocking(true)
+            .map_err(io_err_into_net_error)?;
+        let accepted = loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => break Ok(stream),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if Instant::now() >= deadline {
+                        break Err(NetworkError::TimedOut);
+                    }
+                    thread::sleep(Duration::from_millis(5));
+                }
+                Err(err) => break Err(io_err_into_net_error(err)),
+            }
+        };
+        self.listener
+            .set_nonblocking(false)
+            .map_err(io_err_into_net_error)?;
+        let stream = accepted?;
+        stream
+            .set_nonblocking(false)
+            .map_err(io_err_into_net_error)?;
+        Ok((
+            Box::new(LocalUnixStream {
+                stream,
+                connect_timeout: None,
+            }),
+            unspecified_addr(),
+        ))
+    }
+
+    /// Sets the accept timeout
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// Gets the accept timeout
+    fn timeout(&self) -> Result<Option<Duration>> {
+        Ok(self.timeout)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        Ok(unspecified_addr())
+    }
+
+    fn set_ttl(&mut self, _ttl: u8) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        Err(NetworkError::Unsupported)
+    }
+}
+
+#[derive(Debug)]
+pub struct LocalUnixStream {
+    stream: UnixStream,
+    connect_timeout: Option<Duration>,
+}
+
+impl VirtualTcpSocket for LocalUnixStream {
+    fn set_opt_time(&mut self, ty: TimeType, timeout: Option<Duration>) -> Result<()> {
+        match ty {
+            TimeType::ReadTimeout => self
+                .stream
+                .set_read_timeout(timeout)
+                .map_err(io_err_into_net_error),
+            TimeType::WriteTimeout => self
+                .stream
+                .set_write_timeout(timeout)
+                .map_err(io_err_into_net_error),
+            TimeType::ConnectTimeout => {
+                self.connect_timeout = timeout;
+                Ok(())
+            }
+            _ => Err(NetworkError::InvalidInput),
+        }
+    }
+
+    fn opt_time(&self, ty: TimeType) -> Result<Option<Duration>> {
+        match ty {
+            TimeType::ReadTimeout => self.stream.read_timeout().map_err(io_err_into_net_error),
+            TimeType::WriteTimeout => self.stream.write_timeout().map_err(io_err_into_net_error),
+            TimeType::ConnectTimeout => Ok(self.connect_timeout),
+            _ => Err(NetworkError::InvalidInput),
+        }
+    }
+
+    fn set_recv_buf_size(&mut self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn set_send_buf_size(&mut self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn set_nodelay(&mut self, _nodelay: bool) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        Ok(unspecified_addr())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        self.stream.shutdown(how).map_err(io_err_into_net_error)
+    }
+}
+
+impl VirtualConnectedSocket for LocalUnixStream {
+    fn set_linger(&mut self, _linger: Option<Duration>) -> Result<()> {
+        Ok(())
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        Ok(None)
+    }
+
+    fn send(&mut self, data: Bytes) -> Result<usize> {
+        self.stream
+            .write_all(&data[..])
+            .map(|_| data.len())
+            .map_err(io_err_into_net_error)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.stream.flush().map_err(io_err_into_net_error)
+    }
+
+    fn recv(&mut self) -> Result<SocketReceive> {
+        let buf_size = 8192;
+        let mut buf = vec![0u8; buf_size];
+        let read = self
+            .stream
+            .read(&mut buf[..])
+            .map_err(io_err_into_net_error)?;
+        let buf = Bytes::from(buf).slice(..read);
+        Ok(SocketReceive {
+            data: buf,
+            truncated: read == buf_size,
+        })
+    }
+
+    fn peek(&mut self) -> Result<SocketReceive> {
+        Err(NetworkError::Unsupported)
+    }
+}
+
+impl VirtualSocket for LocalUnixStream {
+    fn set_ttl(&mut self, _ttl: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        Ok(unspecified_addr())
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        Ok(SocketStatus::Opened)
+    }
+
+    fn as_raw_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        Some(std::os::unix::io::AsRawFd::as_raw_fd(&self.stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalNetworking;
+    use wasmer_vnet::VirtualNetworking;
+
+    #[test]
+    fn sandbox_paths() {
+        let mappings = vec![(
+            PathBuf::from("/run/postgresql"),
+            PathBuf::from("/var/run/postgresql"),
+        )];
+        assert_eq!(
+            map_path(&mappings, Path::new("/run/postgresql/.s.PGSQL.5432")),
+            Some(PathBuf::from("/var/run/postgresql/.s.PGSQL.5432"))
+        );
+        assert_eq!(map_path(&mappings, Path::new("/run/docker.sock")), None);
+        assert_eq!(
+            map_path(&mappings, Path::new("/run/postgresql/../docker.sock")),
+            None
+        );
+    }
+
+    #[test]
+    fn connect_through_mapping() {
+        let dir = std::env::temp_dir().join(format!("wasmer-unix-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let _ = std::fs::remove_file(dir.join("echo.sock"));
+        let net = LocalNetworking::default().map_unix_socket_dir("/run/echo", &dir);
+
+        let listener = net.listen_unix(Path::new("/run/echo/echo.sock")).unwrap();
+        let mut client = net
+            .connect_unix(Path::new("/run/echo/echo.sock"), None)
+            .unwrap();
+        let (mut server, addr) = listener.accept().unwrap();
+        assert_eq!(addr, unspecified_addr());
+
+        client.send(Bytes::from_static(b"hello")).unwrap();
+        assert_eq!(&server.recv().unwrap().data[..], b"hello");
+        assert_eq!(
+            net.connect_unix(Path::new("/tmp/echo.sock"), None)
+                .unwrap_err(),
+            NetworkError::PermissionDenied
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
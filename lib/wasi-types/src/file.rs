@@ -28,6 +28,25 @@ pub const __WASI_FDFLAG_SYNC: __wasi_fdflags_t = 1 << 4;
 pub type __wasi_eventfdflags = u16;
 pub const __WASI_EVENTFDFLAGS_SEMAPHORE: __wasi_eventfdflags = 1 << 0;
 
+pub type __wasi_notifyflags_t = u8;
+pub const __WASI_NOTIFY_CREATE: __wasi_notifyflags_t = 1 << 0;
+pub const __WASI_NOTIFY_MODIFY: __wasi_notifyflags_t = 1 << 1;
+pub const __WASI_NOTIFY_REMOVE: __wasi_notifyflags_t = 1 << 2;
+/// Reported whatever the mask, once changes were dropped because too
+/// many were waiting to be read. Its name is empty.
+pub const __WASI_NOTIFY_OVERFLOW: __wasi_notifyflags_t = 1 << 3;
+
+/// A change read from a file descriptor returned by `path_notify`. It
+/// is followed by the `name_len` bytes of the name of the entry which
+/// changed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueType)]
+#[repr(C)]
+pub struct __wasi_notify_event_t {
+    /// One of the `__WASI_NOTIFY_*` flags.
+    pub kind: __wasi_notifyflags_t,
+    pub name_len: u32,
+}
+
 pub type __wasi_preopentype_t = u8;
 pub const __WASI_PREOPENTYPE_DIR: __wasi_preopentype_t = 0;
 
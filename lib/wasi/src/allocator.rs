@@ -0,0 +1,87 @@
+//! The allocator of the guest, with which the host allocates the
+//! buffers it passes to the program, see [`GuestAllocator`].
+
+use crate::WasiEnv;
+use wasmer::RuntimeError;
+
+/// Allocates and frees buffers in the memory of the guest, e.g. to pass
+/// it data the host produces, see [`WasiEnv::allocate_guest_buffer`].
+///
+/// The offsets and the lengths are the ones of the memory of the
+/// instance, whether it's a 32-bit or a 64-bit memory.
+pub trait GuestAllocator: Send + Sync {
+    /// Allocates a buffer of `len` bytes, and returns its offset in the
+    /// memory.
+    fn allocate(&self, len: u64) -> Result<u64, RuntimeError>;
+
+    /// Frees the buffer of `len` bytes at `ptr`, allocated with
+    /// [`GuestAllocator::allocate`].
+    fn free(&self, ptr: u64, len: u64) -> Result<(), RuntimeError>;
+}
+
+/// The allocator the instance exports, detected from the common export
+/// names, in this order:
+///
+/// - `_malloc` and `_free`, of WASIX, in 64-bit memories;
+/// - `canonical_abi_realloc` and `canonical_abi_free`, of the component
+///   model;
+/// - `malloc`, or `__wbindgen_malloc` of wasm-bindgen, with `free`, or
+///   `__wbindgen_free`.
+///
+/// A buffer allocated without an exported function to free it is never
+/// freed.
+impl GuestAllocator for WasiEnv {
+    fn allocate(&self, len: u64) -> Result<u64, RuntimeError> {
+        if let Some(malloc) = self.malloc.get_ref() {
+            return malloc.call(len);
+        }
+        if let Some(realloc) = self.canonical_abi_realloc.get_ref() {
+            return realloc
+                .call(0, 0, 1, i32_len(len)?)
+                .map(|ptr| ptr as u32 as u64);
+        }
+        if let Some(malloc) = self.malloc32.get_ref() {
+            return malloc.call(i32_len(len)?).map(|ptr| ptr as u32 as u64);
+        }
+
+        Err(RuntimeError::new(
+            "the instance doesn't export an allocator: expected `_malloc`, \
+             `canonical_abi_realloc`, `malloc` or `__wbindgen_malloc`",
+        ))
+    }
+
+    fn free(&self, ptr: u64, len: u64) -> Result<(), RuntimeError> {
+        if self.malloc.get_ref().is_some() {
+            return match self.free.get_ref() {
+                Some(free) => free.call(ptr, len),
+                None => Ok(()),
+            };
+        }
+        if self.canonical_abi_realloc.get_ref().is_some() {
+            return match self.canonical_abi_free.get_ref() {
+                Some(free) => free.call(ptr as i32, i32_len(len)?, 1),
+                None => Ok(()),
+            };
+        }
+
+        if let Some(free) = self.free32.get_ref() {
+            free.call(ptr as i32)
+        } else if let Some(free) = self.wbindgen_free.get_ref() {
+            free.call(ptr as i32, i32_len(len)?)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The length `len`, passed to the allocator of a 32-bit memory.
+fn i32_len(len: u64) -> Result<i32, RuntimeError> {
+    if len > i32::MAX as u64 {
+        return Err(RuntimeError::new(format!(
+            "a buffer of {} bytes doesn't fit in a 32-bit memory",
+            len
+        )));
+    }
+
+    Ok(len as i32)
+}
@@ -0,0 +1,211 @@
+//! Fault injection, to test how guests cope with failing syscalls.
+//!
+//! ```rust,ignore
+//! use std::time::Duration;
+//! use wasmer_wasi::types::__WASI_ENOSPC;
+//! use wasmer_wasi::{Fault, FaultInjector, WasiState};
+//!
+//! let mut faults = FaultInjector::new();
+//! faults
+//!     .inject_every("fd_write", 3, Fault::Fail(__WASI_ENOSPC))?
+//!     .inject("sock_recv", Fault::Delay(Duration::from_millis(50)))?;
+//!
+//! let env = WasiState::new("program").fault_injector(faults).finalize()?;
+//! ```
+//!
+//! Faults can be injected into the syscalls which do I/O: `fd_read`,
+//! `fd_write` and their positional variants, the other `fd_*`
+//! syscalls touching the contents of files, the `path_*` syscalls
+//! modifying the file system, `poll_oneoff`, and the `sock_*`
+//! syscalls transferring data or connections, see [`FAULTABLE_SYSCALLS`].
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::syscalls::types::*;
+
+/// The syscalls faults can be injected into.
+pub const FAULTABLE_SYSCALLS: &[&str] = &[
+    "fd_allocate",
+    "fd_close",
+    "fd_datasync",
+    "fd_pread",
+    "fd_pwrite",
+    "fd_read",
+    "fd_readdir",
+    "fd_seek",
+    "fd_sync",
+    "fd_write",
+    "path_create_directory",
+    "path_notify",
+    "path_open",
+    "path_remove_directory",
+    "path_rename",
+    "path_unlink_file",
+    "poll_oneoff",
+    "proc_fork",
+    "shm_open",
+    "shm_unlink",
+    "sock_accept",
+    "sock_connect",
+    "sock_connect_unix",
+    "sock_recv",
+    "sock_recv_from",
+    "sock_send",
+    "sock_send_file",
+    "sock_send_to",
+    "sock_upgrade_tls",
+];
+
+/// An error while configuring a [`FaultInjector`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FaultInjectorError {
+    /// The syscall doesn't exist, or faults can't be injected into it.
+    #[error("faults can't be injected into `{0}`, see `FAULTABLE_SYSCALLS`")]
+    UnknownSyscall(String),
+}
+
+/// A fault injected into a syscall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The syscall fails with the given errno, without doing anything.
+    Fail(__wasi_errno_t),
+    /// The syscall is delayed by the given duration, then runs
+    /// normally.
+    Delay(Duration),
+}
+
+#[derive(Debug)]
+struct FaultRule {
+    fault: Fault,
+    every: u64,
+    calls: AtomicU64,
+}
+
+/// Injects faults into the syscalls of a guest.
+///
+/// The faults are deterministic: a fault is injected every `n`th call
+/// of a syscall, counting from the instantiation.
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    rules: HashMap<String, Vec<FaultRule>>,
+}
+
+impl FaultInjector {
+    /// Creates an injector without any fault.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Injects `fault` into every call of `syscall`.
+    ///
+    /// Fails if `syscall` isn't one of [`FAULTABLE_SYSCALLS`].
+    pub fn inject(&mut self, syscall: &str, fault: Fault) -> Result<&mut Self, FaultInjectorError> {
+        self.inject_every(syscall, 1, fault)
+    }
+
+    /// Injects `fault` into every `nth` call of `syscall`, i.e. the
+    /// `nth` call, the `2 * nth` call, and so on.
+    ///
+    /// Fails if `syscall` isn't one of [`FAULTABLE_SYSCALLS`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nth` is zero.
+    pub fn inject_every(
+        &mut self,
+        syscall: &str,
+        nth: u64,
+        fault: Fault,
+    ) -> Result<&mut Self, FaultInjectorError> {
+        assert!(
+            nth > 0,
+            "faults are injected every `nth` call, with `nth > 0`"
+        );
+        if !FAULTABLE_SYSCALLS.contains(&syscall) {
+            return Err(FaultInjectorError::UnknownSyscall(syscall.to_string()));
+        }
+
+        self.rules
+            .entry(syscall.to_string())
+            .or_default()
+            .push(FaultRule {
+                fault,
+                every: nth,
+                calls: AtomicU64::new(0),
+            });
+
+        Ok(self)
+    }
+
+    /// Counts a call of `syscall`, and returns the faults to inject
+    /// into it, in the order they were configured.
+    pub(crate) fn faults(&self, syscall: &str) -> Vec<Fault> {
+        self.rules
+            .get(syscall)
+            .map(|rules| {
+                rules
+                    .iter()
+                    .filter(|rule| {
+                        (rule.calls.fetch_add(1, Ordering::SeqCst) + 1) % rule.every == 0
+                    })
+                    .map(|rule| rule.fault)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inject_every() {
+        let mut faults = FaultInjector::new();
+        faults
+            .inject_every("fd_write", 2, Fault::Fail(__WASI_ENOSPC))
+            .unwrap()
+            .inject("fd_write", Fault::Delay(Duration::from_millis(1)))
+            .unwrap();
+
+        assert_eq!(
+            faults.faults("fd_write"),
+            vec![Fault::Delay(Duration::from_millis(1))]
+        );
+        assert_eq!(
+            faults.faults("fd_write"),
+            vec![
+                Fault::Fail(__WASI_ENOSPC),
+                Fault::Delay(Duration::from_millis(1))
+            ]
+        );
+        assert_eq!(faults.faults("fd_read"), vec![]);
+    }
+
+    #[test]
+    fn inject_unknown_syscall() {
+        let mut faults = FaultInjector::new();
+        assert_eq!(
+            faults.inject("fd_wrtie", Fault::Fail(__WASI_EIO)).err(),
+            Some(FaultInjectorError::UnknownSyscall("fd_wrtie".to_string()))
+        );
+        // Faults aren't injected into syscalls which don't do I/O.
+        assert!(faults.inject("args_get", Fault::Fail(__WASI_EIO)).is_err());
+        assert_eq!(faults.faults("fd_wrtie"), vec![]);
+    }
+
+    #[test]
+    fn faultable_syscalls_inject_faults() {
+        let syscalls = include_str!("syscalls/mod.rs");
+        for syscall in FAULTABLE_SYSCALLS {
+            assert!(
+                syscalls.contains(&format!("inject_fault(\"{}\")", syscall)),
+                "`{}` doesn't inject faults",
+                syscall
+            );
+        }
+    }
+}
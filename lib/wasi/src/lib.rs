@@ -6,7 +6,8 @@
 //!
 //! Use `generate_import_object` to create an [`Imports`].  This [`Imports`]
 //! can be combined with a module to create an `Instance` which can execute WASI
-//! Wasm functions.
+//! Wasm functions. Programs which only need their output captured can
+//! be run in one call with [`run_wasi`].
 //!
 //! See `state` for the experimental WASI FS API.  Also see the
 //! [WASI plugin example](https://github.com/wasmerio/wasmer/blob/master/examples/plugin.rs)
@@ -35,28 +36,64 @@ compile_error!(
 
 #[macro_use]
 mod macros;
+mod allocator;
+mod fault;
+#[cfg(feature = "manifest")]
+mod manifest;
+mod net_policy;
+mod reload;
+mod run;
 mod runtime;
+mod scheduler;
+mod shm;
+#[cfg(feature = "sys")]
+mod spawn;
 mod state;
+mod stats;
 mod syscalls;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod timer;
 mod utils;
 
 use crate::syscalls::*;
 
+pub use crate::allocator::GuestAllocator;
+pub use crate::fault::{Fault, FaultInjector, FaultInjectorError, FAULTABLE_SYSCALLS};
+#[cfg(feature = "manifest")]
+pub use crate::manifest::{Manifest, ManifestError};
+pub use crate::net_policy::NetPolicy;
+pub use crate::reload::{ReloadError, ReloadOptions};
+pub use crate::run::{run_wasi, RunError, RunOptions, RunOutput};
+#[cfg(feature = "sys")]
+pub use crate::scheduler::{DedicatedThreadScheduler, ThreadPoolScheduler};
+pub use crate::scheduler::{ThreadScheduler, ThreadTask};
+pub use crate::shm::{SharedMemory, SharedMemoryFile, SharedMemoryRegistry};
+#[cfg(feature = "sys")]
+pub use crate::spawn::{spawn, WasiInstanceHandle};
 pub use crate::state::{
-    Fd, Pipe, Stderr, Stdin, Stdout, WasiFs, WasiInodes, WasiState, WasiStateBuilder,
-    WasiStateCreationError, ALL_RIGHTS, VIRTUAL_ROOT_FD,
+    Fd, Pipe, Stderr, Stdin, Stdout, VirtualDevFs, WasiConfigDiagnostic, WasiConfigOption,
+    WasiFdReader, WasiFdWriter, WasiFs, WasiInodes, WasiState, WasiStateBuilder,
+    WasiStateCreationError, WasiStateView, ALL_RIGHTS, VIRTUAL_ROOT_FD,
 };
+pub use crate::stats::{SyscallCategory, WasiThreadStats};
 pub use crate::syscalls::types;
 pub use crate::utils::{
     get_wasi_version, get_wasi_versions, is_wasi_module, is_wasix_module, WasiVersion,
 };
-pub use wasmer_vbus::{UnsupportedVirtualBus, VirtualBus};
+pub use wasmer_vbus::{
+    BusError, BusSpawnedProcess, SpawnOptions, StdioMode, UnsupportedVirtualBus, VirtualBus,
+};
 #[deprecated(since = "2.1.0", note = "Please use `wasmer_vfs::FsError`")]
 pub use wasmer_vfs::FsError as WasiFsError;
 #[deprecated(since = "2.1.0", note = "Please use `wasmer_vfs::VirtualFile`")]
 pub use wasmer_vfs::VirtualFile as WasiFile;
 pub use wasmer_vfs::{FsError, VirtualFile};
-pub use wasmer_vnet::{UnsupportedVirtualNetworking, VirtualNetworking};
+pub use wasmer_vnet::{
+    CapturedPacket, DnsResolver, HostsDnsResolver, PacketTap, PcapWriter, SwitchNetworking,
+    SystemDnsResolver, TapNetworking, UnsupportedVirtualNetworking, VirtualNetworking,
+    VirtualSwitch,
+};
 use wasmer_wasi_types::__WASI_CLOCK_MONOTONIC;
 
 use derivative::*;
@@ -64,12 +101,14 @@ use std::ops::Deref;
 use thiserror::Error;
 use wasmer::{
     imports, Function, Imports, LazyInit, Memory, Memory32, MemoryAccessError, MemorySize, Module,
-    Store, TypedFunction, WasmerEnv,
+    RuntimeError, Store, TypedFunction, WasmerEnv,
 };
 
 pub use runtime::{
     PluggableRuntimeImplementation, WasiRuntimeImplementation, WasiThreadError, WasiTtyState,
 };
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{mpsc, Arc, Mutex, RwLockReadGuard, RwLockWriteGuard};
 use std::time::Duration;
 
@@ -79,10 +118,27 @@ use std::time::Duration;
 pub enum WasiError {
     #[error("WASI exited with code: {0}")]
     Exit(syscalls::types::__wasi_exitcode_t),
+    #[error("WASI was terminated by signal: {0}")]
+    Signaled(syscalls::types::__wasi_signal_t),
+    #[error("WASI was terminated after its timeout")]
+    TimedOut,
     #[error("The WASI version could not be determined")]
     UnknownWasiVersion,
 }
 
+/// How a WASI process terminated, see [`WasiEnv::wait`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasiExitStatus {
+    /// The process exited with a code, by calling `proc_exit` or by
+    /// returning from its entry point (code 0).
+    Exited(syscalls::types::__wasi_exitcode_t),
+    /// The process was terminated by a signal raised with `proc_raise`.
+    Signaled(syscalls::types::__wasi_signal_t),
+    /// The process was terminated by the watchdog when it yielded past
+    /// its deadline, see [`WasiEnv::set_timeout`].
+    TimedOut,
+}
+
 /// Represents the ID of a WASI thread
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct WasiThreadId(u32);
@@ -142,14 +198,18 @@ impl WasiThread {
     }
 }
 
+/// The value of `WasiEnv::deadline` when no timeout is set.
+const NO_DEADLINE: u64 = u64::MAX;
+
 /// The environment provided to the WASI imports.
 #[derive(Derivative, Clone, WasmerEnv)]
 #[derivative(Debug)]
 pub struct WasiEnv {
     /// ID of this thread (zero is the main thread)
     id: WasiThreadId,
-    /// Represents a reference to the memory
-    #[wasmer(export)]
+    /// Represents a reference to the memory, exported by the instance
+    /// unless it was set with [`WasiEnv::set_memory`]
+    #[wasmer(export(optional = true))]
     memory: LazyInit<Memory>,
     /// If the module has it then map the thread start
     #[derivative(Debug = "ignore")]
@@ -167,11 +227,50 @@ pub struct WasiEnv {
     #[derivative(Debug = "ignore")]
     #[wasmer(export(optional = true, name = "_free"))]
     free: LazyInit<TypedFunction<(u64, u64), ()>>,
+    #[derivative(Debug = "ignore")]
+    #[wasmer(export(optional = true, name = "canonical_abi_realloc"))]
+    canonical_abi_realloc: LazyInit<TypedFunction<(i32, i32, i32, i32), i32>>,
+    #[derivative(Debug = "ignore")]
+    #[wasmer(export(optional = true, name = "canonical_abi_free"))]
+    canonical_abi_free: LazyInit<TypedFunction<(i32, i32, i32), ()>>,
+    #[derivative(Debug = "ignore")]
+    #[wasmer(export(optional = true, name = "malloc", alias = "__wbindgen_malloc"))]
+    malloc32: LazyInit<TypedFunction<i32, i32>>,
+    #[derivative(Debug = "ignore")]
+    #[wasmer(export(optional = true, name = "free"))]
+    free32: LazyInit<TypedFunction<i32, ()>>,
+    #[derivative(Debug = "ignore")]
+    #[wasmer(export(optional = true, name = "__wbindgen_free"))]
+    wbindgen_free: LazyInit<TypedFunction<(i32, i32), ()>>,
+    /// The allocator of the guest used instead of the exported one, if
+    /// any.
+    #[derivative(Debug = "ignore")]
+    guest_allocator: Option<Arc<dyn GuestAllocator>>,
     /// Shared state of the WASI system. Manages all the data that the
     /// executing WASI program can see.
     pub state: Arc<WasiState>,
     /// Implementation of the WASI runtime.
     pub(crate) runtime: Arc<dyn WasiRuntimeImplementation + Send + Sync + 'static>,
+    /// Faults to inject into the syscalls, if any.
+    pub(crate) fault_injector: Option<Arc<FaultInjector>>,
+    /// Policy restricting the `http_request` and `ws_connect` syscalls,
+    /// if any.
+    pub(crate) net_policy: Option<Arc<NetPolicy>>,
+    /// Resolver of the `resolve` syscall, instead of the one of the
+    /// networking implementation.
+    pub(crate) dns_resolver: Option<Arc<dyn DnsResolver>>,
+    /// Time of the monotonic clock, in nanoseconds, after which the
+    /// watchdog terminates the process, or `NO_DEADLINE`. It's shared by
+    /// the clones of the environment, e.g. the ones of the imports, and
+    /// by the environments created from it, e.g. by `reload`.
+    deadline: Arc<AtomicU64>,
+    /// The module the imports have been generated for, which
+    /// `proc_fork` instantiates again for the child.
+    #[derivative(Debug = "ignore")]
+    module: Option<Module>,
+    /// The standard streams and the current directory of this instance,
+    /// if they differ from the ones of the shared state.
+    view: Option<Arc<WasiStateView>>,
 }
 
 impl WasiEnv {
@@ -185,10 +284,83 @@ impl WasiEnv {
             reactor_finish: LazyInit::new(),
             malloc: LazyInit::new(),
             free: LazyInit::new(),
+            canonical_abi_realloc: LazyInit::new(),
+            canonical_abi_free: LazyInit::new(),
+            malloc32: LazyInit::new(),
+            free32: LazyInit::new(),
+            wbindgen_free: LazyInit::new(),
+            guest_allocator: None,
             runtime: Arc::new(PluggableRuntimeImplementation::default()),
+            fault_injector: None,
+            net_policy: None,
+            dns_resolver: None,
+            deadline: Arc::new(AtomicU64::new(NO_DEADLINE)),
+            module: None,
+            view: None,
         }
     }
 
+    /// Creates the environment of a process forked from this one, see
+    /// `proc_fork`. Its exports are set when the child is instantiated.
+    pub(crate) fn fork(&self) -> Self {
+        self.with_state(Arc::new(self.state.fork()))
+    }
+
+    /// Creates an environment like this one, with `state`, whose exports
+    /// are set when its instance is created.
+    fn with_state(&self, state: Arc<WasiState>) -> Self {
+        Self {
+            id: self.id,
+            state,
+            memory: LazyInit::new(),
+            thread_start: LazyInit::new(),
+            reactor_work: LazyInit::new(),
+            reactor_finish: LazyInit::new(),
+            malloc: LazyInit::new(),
+            free: LazyInit::new(),
+            canonical_abi_realloc: LazyInit::new(),
+            canonical_abi_free: LazyInit::new(),
+            malloc32: LazyInit::new(),
+            free32: LazyInit::new(),
+            wbindgen_free: LazyInit::new(),
+            guest_allocator: self.guest_allocator.clone(),
+            runtime: self.runtime.clone(),
+            fault_injector: self.fault_injector.clone(),
+            net_policy: self.net_policy.clone(),
+            dns_resolver: self.dns_resolver.clone(),
+            deadline: self.deadline.clone(),
+            module: self.module.clone(),
+            view: self.view.clone(),
+        }
+    }
+
+    /// Creates the environment of another instance, e.g. a reactor next
+    /// to a command, sharing the state of this one but with the standard
+    /// streams and the current directory of `view`, see
+    /// [`WasiStateView`].
+    ///
+    /// ```ignore
+    /// let mut view = WasiStateView::new();
+    /// view.stdout(Box::new(reactor_stdout)).current_dir("/srv");
+    /// let reactor_env = wasi_env.with_view(view);
+    /// ```
+    pub fn with_view(&self, view: WasiStateView) -> Self {
+        let mut env = self.with_state(self.state.clone());
+        env.view = Some(Arc::new(view));
+        env
+    }
+
+    /// The view this environment has of the shared state, if any.
+    pub fn view(&self) -> Option<&WasiStateView> {
+        self.view.as_deref()
+    }
+
+    /// The current directory of the view, overriding the one of the
+    /// shared file system.
+    pub(crate) fn view_current_dir(&self) -> Option<String> {
+        self.view.as_ref().and_then(|view| view.get_current_dir())
+    }
+
     /// Returns a copy of the current runtime implementation for this environment
     pub fn runtime(&self) -> &(dyn WasiRuntimeImplementation) {
         self.runtime.deref()
@@ -202,6 +374,153 @@ impl WasiEnv {
         self.runtime = Arc::new(runtime);
     }
 
+    /// Injects faults into the syscalls of this environment, see
+    /// [`FaultInjector`].
+    pub fn set_fault_injector(&mut self, fault_injector: FaultInjector) {
+        self.fault_injector = Some(Arc::new(fault_injector));
+    }
+
+    /// Restricts the `http_request` and `ws_connect` syscalls of this
+    /// environment, see [`NetPolicy`].
+    pub fn set_net_policy(&mut self, net_policy: NetPolicy) {
+        self.net_policy = Some(Arc::new(net_policy));
+    }
+
+    /// Resolves the host names of the `resolve` syscall with `resolver`
+    /// instead of the resolver of the networking implementation.
+    pub fn set_dns_resolver<R>(&mut self, resolver: R)
+    where
+        R: DnsResolver,
+    {
+        self.dns_resolver = Some(Arc::new(resolver));
+    }
+
+    /// Allocates the buffers of the guest with `allocator` instead of
+    /// the allocator the instance exports, see
+    /// [`WasiEnv::allocate_guest_buffer`].
+    pub fn set_guest_allocator<A>(&mut self, allocator: A)
+    where
+        A: GuestAllocator + 'static,
+    {
+        self.guest_allocator = Some(Arc::new(allocator));
+    }
+
+    /// Allocates a buffer of `len` bytes in the memory of the guest, e.g.
+    /// to pass it data the host produces, and returns its offset.
+    ///
+    /// The allocator set with [`WasiEnv::set_guest_allocator`] is used,
+    /// otherwise the one the instance exports, e.g. `malloc`, see
+    /// [`GuestAllocator`].
+    pub fn allocate_guest_buffer(&self, len: u64) -> Result<u64, RuntimeError> {
+        match &self.guest_allocator {
+            Some(allocator) => allocator.allocate(len),
+            None => GuestAllocator::allocate(self, len),
+        }
+    }
+
+    /// Frees the buffer of `len` bytes at `ptr`, allocated with
+    /// [`WasiEnv::allocate_guest_buffer`].
+    pub fn free_guest_buffer(&self, ptr: u64, len: u64) -> Result<(), RuntimeError> {
+        match &self.guest_allocator {
+            Some(allocator) => allocator.free(ptr, len),
+            None => GuestAllocator::free(self, ptr, len),
+        }
+    }
+
+    /// Returns the statistics of the thread `id` of this process, see
+    /// [`WasiThreadStats`], or `None` if it hasn't called any syscall
+    /// or has exited.
+    pub fn thread_stats(&self, id: WasiThreadId) -> Option<WasiThreadStats> {
+        self.state.stats.get(id)
+    }
+
+    /// Counts a call to `syscall` by the current thread.
+    pub(crate) fn record_syscall(&self, syscall: &str) {
+        self.state.stats.record_syscall(self.id, syscall);
+    }
+
+    /// Counts the bytes read by the current thread from a file or a
+    /// socket.
+    pub(crate) fn record_read(&self, bytes: usize) {
+        self.state.stats.record_read(self.id, bytes);
+    }
+
+    /// Counts the bytes written by the current thread to a file or a
+    /// socket.
+    pub(crate) fn record_written(&self, bytes: usize) {
+        self.state.stats.record_written(self.id, bytes);
+    }
+
+    /// Applies the faults to inject into `syscall`: waits for the
+    /// delays, and returns the errno `syscall` must fail with, if any.
+    pub(crate) fn inject_fault(&self, syscall: &str) -> Result<(), types::__wasi_errno_t> {
+        let fault_injector = match &self.fault_injector {
+            Some(fault_injector) => fault_injector,
+            None => return Ok(()),
+        };
+
+        for fault in fault_injector.faults(syscall) {
+            match fault {
+                Fault::Delay(duration) => self.sleep(duration).map_err(|_| types::__WASI_EINTR)?,
+                Fault::Fail(errno) => return Err(errno),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Terminates the process with [`WasiExitStatus::TimedOut`] once
+    /// `timeout` has elapsed.
+    ///
+    /// The timeout applies to every clone of this environment, so it
+    /// can be set before or after the imports are generated.
+    ///
+    /// # Limitations
+    ///
+    /// The watchdog doesn't interrupt the Wasm code: the deadline is
+    /// only checked when the process yields, e.g. in `sched_yield`,
+    /// `poll_oneoff`, `thread_sleep` or while a syscall blocks. A
+    /// process computing without such syscalls, e.g. stuck in a loop,
+    /// runs past its deadline, possibly forever. To bound the time
+    /// spent in Wasm code, compile the module with the `Metering`
+    /// middleware of `wasmer-middlewares`, which traps once its points
+    /// are exhausted.
+    pub fn set_timeout(&self, timeout: Duration) {
+        let now = platform_clock_time_get(__WASI_CLOCK_MONOTONIC, 1_000_000).unwrap() as u64;
+        let timeout = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+        self.deadline
+            .store(now.saturating_add(timeout), Ordering::Release);
+    }
+
+    /// Turns the outcome of the entry point of the process, e.g. of
+    /// `_start`, into its exit status.
+    ///
+    /// Errors that didn't terminate the process, like traps, are
+    /// returned as is.
+    ///
+    /// ```ignore
+    /// let start = instance.exports.get_function("_start")?;
+    /// match wasi_env.wait(start.call(&[]))? {
+    ///     WasiExitStatus::Exited(code) => println!("exited with {}", code),
+    ///     WasiExitStatus::Signaled(signal) => println!("killed by signal {}", signal),
+    ///     WasiExitStatus::TimedOut => println!("timed out"),
+    /// }
+    /// ```
+    pub fn wait<T>(&self, result: Result<T, RuntimeError>) -> Result<WasiExitStatus, RuntimeError> {
+        let error = match result {
+            Ok(_) => return Ok(WasiExitStatus::Exited(0)),
+            Err(error) => error,
+        };
+
+        match error.downcast::<WasiError>() {
+            Ok(WasiError::Exit(code)) => Ok(WasiExitStatus::Exited(code)),
+            Ok(WasiError::Signaled(signal)) => Ok(WasiExitStatus::Signaled(signal)),
+            Ok(WasiError::TimedOut) => Ok(WasiExitStatus::TimedOut),
+            Ok(error) => Err(RuntimeError::user(Box::new(error))),
+            Err(error) => Err(error),
+        }
+    }
+
     /// Returns the current thread ID
     pub fn current_thread_id(&self) -> WasiThreadId {
         self.id
@@ -226,6 +545,70 @@ impl WasiEnv {
         thread
     }
 
+    /// Spawns a process on the bus of the runtime, like the program
+    /// does with `process_spawn`. `configure` sets its options, e.g.
+    /// its arguments, its environment variables, or where its stdio
+    /// goes.
+    ///
+    /// ```ignore
+    /// let pid = wasi_env.spawn_process("python", |options| {
+    ///     options
+    ///         .args(vec!["-c".to_string(), "print(42)".to_string()])
+    ///         .env("PYTHONUNBUFFERED".to_string(), "1".to_string())
+    ///         .stdout_file("/tmp/out.txt".to_string());
+    /// })?;
+    /// let exit_code = wasi_env.wait_process(pid)?;
+    /// ```
+    pub fn spawn_process<F>(&self, name: &str, configure: F) -> Result<WasiBusProcessId, BusError>
+    where
+        F: FnOnce(&mut SpawnOptions),
+    {
+        let mut options = self.runtime.bus().new_spawn();
+        configure(&mut options);
+        let process = options.spawn(name)?;
+
+        Ok(self.add_process(process))
+    }
+
+    /// Terminates a process spawned by the program or with
+    /// [`WasiEnv::spawn_process`].
+    pub fn kill_process(&self, pid: WasiBusProcessId) -> Result<(), BusError> {
+        let guard = self.state.threading.lock().unwrap();
+        let process = guard.processes.get(&pid).ok_or(BusError::BadHandle)?;
+
+        process.inst.kill()
+    }
+
+    /// Waits for a process spawned by the program or with
+    /// [`WasiEnv::spawn_process`] to exit, and returns its exit code.
+    /// The process is then released.
+    pub fn wait_process(&self, pid: WasiBusProcessId) -> Result<u32, BusError> {
+        loop {
+            {
+                let mut guard = self.state.threading.lock().unwrap();
+                let process = guard.processes.get(&pid).ok_or(BusError::BadHandle)?;
+
+                if let Some(exit_code) = process.inst.exit_code() {
+                    guard.processes.remove(&pid);
+                    return Ok(exit_code);
+                }
+            }
+
+            self.sleep(Duration::from_millis(5))
+                .map_err(|_| BusError::Aborted)?;
+        }
+    }
+
+    /// Registers a spawned process, and returns its ID.
+    pub(crate) fn add_process(&self, process: BusSpawnedProcess) -> WasiBusProcessId {
+        let mut guard = self.state.threading.lock().unwrap();
+        guard.process_seed += 1;
+        let pid: WasiBusProcessId = guard.process_seed.into();
+        guard.processes.insert(pid, process);
+
+        pid
+    }
+
     /// Get the WASI state
     ///
     /// Be careful when using this in host functions that call into Wasm:
@@ -239,7 +622,19 @@ impl WasiEnv {
     pub fn memory(&self) -> &Memory {
         self.memory
             .get_ref()
-            .expect("Memory should be set on `WasiEnv` first")
+            .expect("Memory should be exported by the instance or set on `WasiEnv` first")
+    }
+
+    /// Sets the memory of this environment, for modules which import
+    /// their memory instead of exporting it.
+    ///
+    /// It replaces the memory set by a previous instantiation, if any.
+    /// The copies of the environment made before, e.g. by
+    /// [`WasiEnv::import_object`], keep their own memory, so it must be
+    /// set before the imports are generated.
+    pub fn set_memory(&mut self, memory: Memory) {
+        self.memory = LazyInit::new();
+        self.memory.initialize(memory);
     }
 
     /// Copy the lazy reference so that when it's initialized during the
@@ -251,6 +646,7 @@ impl WasiEnv {
     /// Get an `Imports` for a specific version of WASI detected in the module.
     pub fn import_object(&mut self, module: &Module) -> Result<Imports, WasiError> {
         let wasi_version = get_wasi_version(module, false).ok_or(WasiError::UnknownWasiVersion)?;
+        self.module = Some(module.clone());
         Ok(generate_import_object_from_env(
             module.store(),
             self.clone(),
@@ -266,6 +662,7 @@ impl WasiEnv {
     ) -> Result<Imports, WasiError> {
         let wasi_versions =
             get_wasi_versions(module, false).ok_or(WasiError::UnknownWasiVersion)?;
+        self.module = Some(module.clone());
 
         let mut resolver = Imports::new();
         for version in wasi_versions.iter() {
@@ -288,6 +685,16 @@ impl WasiEnv {
 
     // Yields execution
     pub fn yield_now(&self) -> Result<(), WasiError> {
+        if self.state.killed.load(std::sync::atomic::Ordering::Acquire) {
+            return Err(WasiError::Signaled(types::__WASI_SIGKILL));
+        }
+        let deadline = self.deadline.load(Ordering::Acquire);
+        if deadline != NO_DEADLINE {
+            let now = platform_clock_time_get(__WASI_CLOCK_MONOTONIC, 1_000_000).unwrap() as u64;
+            if now >= deadline {
+                return Err(WasiError::TimedOut);
+            }
+        }
         self.runtime.yield_now(self.id)?;
         Ok(())
     }
@@ -527,9 +934,13 @@ fn generate_import_object_wasix32_v1(store: &Store, env: WasiEnv) -> Imports {
             "path_rename" => Function::new_native_with_env(store, env.clone(), path_rename),
             "path_symlink" => Function::new_native_with_env(store, env.clone(), path_symlink),
             "path_unlink_file" => Function::new_native_with_env(store, env.clone(), path_unlink_file),
+            "path_notify" => Function::new_native_with_env(store, env.clone(), path_notify),
+            "shm_open" => Function::new_native_with_env(store, env.clone(), shm_open),
+            "shm_unlink" => Function::new_native_with_env(store, env.clone(), shm_unlink),
             "poll_oneoff" => Function::new_native_with_env(store, env.clone(), poll_oneoff),
             "proc_exit" => Function::new_native_with_env(store, env.clone(), proc_exit),
             "proc_raise" => Function::new_native_with_env(store, env.clone(), proc_raise),
+            "proc_fork" => Function::new_native_with_env(store, env.clone(), proc_fork),
             "random_get" => Function::new_native_with_env(store, env.clone(), random_get),
             "tty_get" => Function::new_native_with_env(store, env.clone(), tty_get),
             "tty_set" => Function::new_native_with_env(store, env.clone(), tty_set),
@@ -584,9 +995,12 @@ fn generate_import_object_wasix32_v1(store: &Store, env: WasiEnv) -> Imports {
             "sock_join_multicast_v6" => Function::new_native_with_env(store, env.clone(), sock_join_multicast_v6),
             "sock_leave_multicast_v6" => Function::new_native_with_env(store, env.clone(), sock_leave_multicast_v6),
             "sock_bind" => Function::new_native_with_env(store, env.clone(), sock_bind),
+            "sock_bind_unix" => Function::new_native_with_env(store, env.clone(), sock_bind_unix),
             "sock_listen" => Function::new_native_with_env(store, env.clone(), sock_listen),
             "sock_accept" => Function::new_native_with_env(store, env.clone(), sock_accept),
             "sock_connect" => Function::new_native_with_env(store, env.clone(), sock_connect),
+            "sock_connect_unix" => Function::new_native_with_env(store, env.clone(), sock_connect_unix),
+            "sock_upgrade_tls" => Function::new_native_with_env(store, env.clone(), sock_upgrade_tls),
             "sock_recv" => Function::new_native_with_env(store, env.clone(), sock_recv),
             "sock_recv_from" => Function::new_native_with_env(store, env.clone(), sock_recv_from),
             "sock_send" => Function::new_native_with_env(store, env.clone(), sock_send),
@@ -642,9 +1056,13 @@ fn generate_import_object_wasix64_v1(store: &Store, env: WasiEnv) -> Imports {
             "path_rename" => Function::new_native_with_env(store, env.clone(), path_rename),
             "path_symlink" => Function::new_native_with_env(store, env.clone(), path_symlink),
             "path_unlink_file" => Function::new_native_with_env(store, env.clone(), path_unlink_file),
+            "path_notify" => Function::new_native_with_env(store, env.clone(), path_notify),
+            "shm_open" => Function::new_native_with_env(store, env.clone(), shm_open),
+            "shm_unlink" => Function::new_native_with_env(store, env.clone(), shm_unlink),
             "poll_oneoff" => Function::new_native_with_env(store, env.clone(), poll_oneoff),
             "proc_exit" => Function::new_native_with_env(store, env.clone(), proc_exit),
             "proc_raise" => Function::new_native_with_env(store, env.clone(), proc_raise),
+            "proc_fork" => Function::new_native_with_env(store, env.clone(), proc_fork),
             "random_get" => Function::new_native_with_env(store, env.clone(), random_get),
             "tty_get" => Function::new_native_with_env(store, env.clone(), tty_get),
             "tty_set" => Function::new_native_with_env(store, env.clone(), tty_set),
@@ -699,9 +1117,12 @@ fn generate_import_object_wasix64_v1(store: &Store, env: WasiEnv) -> Imports {
             "sock_join_multicast_v6" => Function::new_native_with_env(store, env.clone(), sock_join_multicast_v6),
             "sock_leave_multicast_v6" => Function::new_native_with_env(store, env.clone(), sock_leave_multicast_v6),
             "sock_bind" => Function::new_native_with_env(store, env.clone(), sock_bind),
+            "sock_bind_unix" => Function::new_native_with_env(store, env.clone(), sock_bind_unix),
             "sock_listen" => Function::new_native_with_env(store, env.clone(), sock_listen),
             "sock_accept" => Function::new_native_with_env(store, env.clone(), sock_accept),
             "sock_connect" => Function::new_native_with_env(store, env.clone(), sock_connect),
+            "sock_connect_unix" => Function::new_native_with_env(store, env.clone(), sock_connect_unix),
+            "sock_upgrade_tls" => Function::new_native_with_env(store, env.clone(), sock_upgrade_tls),
             "sock_recv" => Function::new_native_with_env(store, env.clone(), sock_recv),
             "sock_recv_from" => Function::new_native_with_env(store, env.clone(), sock_recv_from),
             "sock_send" => Function::new_native_with_env(store, env.clone(), sock_send),
@@ -0,0 +1,208 @@
+//! Manifests describing how to run a WASI program, in TOML or YAML, so
+//! that the embedders and the tools share one format.
+//!
+//! ```toml
+//! # The module, relative to the manifest.
+//! module = "app.wasm"
+//! args = ["--verbose"]
+//! preopens = ["data"]
+//!
+//! [env]
+//! LOG = "debug"
+//!
+//! # The host directories, by their path for the program.
+//! [mounts]
+//! "/cache" = "/var/cache/app"
+//! ```
+//!
+//! The relative host paths, of the module, the preopened directories
+//! and the mounts, are relative to the directory of the manifest.
+
+use crate::{WasiState, WasiStateBuilder, WasiStateCreationError};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// An error while loading a [`Manifest`].
+#[derive(Error, Debug)]
+pub enum ManifestError {
+    /// The manifest couldn't be read.
+    #[error("failed to read the manifest `{}`: {}", path.display(), source)]
+    Io {
+        /// The path of the manifest.
+        path: PathBuf,
+        /// The reason the manifest couldn't be read.
+        source: io::Error,
+    },
+    /// The extension of the manifest is neither `.toml`, `.yaml` nor
+    /// `.yml`.
+    #[error("the format of the manifest `{}` is unknown, expected `.toml`, `.yaml` or `.yml`", .0.display())]
+    UnknownFormat(PathBuf),
+    /// The TOML manifest is invalid.
+    #[error("invalid TOML manifest: {0}")]
+    Toml(#[from] toml::de::Error),
+    /// The YAML manifest is invalid.
+    #[error("invalid YAML manifest: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+}
+
+/// How to run a WASI program: its module, its arguments, its
+/// environment variables and the host directories it can access.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The path of the module.
+    pub module: PathBuf,
+    /// The name of the program, its `argv[0]`, which defaults to the
+    /// name of the module, without its extension.
+    #[serde(default)]
+    pub program: Option<String>,
+    /// The arguments passed to the program, after its name.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The environment variables of the program.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// The host directories the program can access, at the same path.
+    #[serde(default)]
+    pub preopens: Vec<PathBuf>,
+    /// The host directories the program can access, by their path for
+    /// the program.
+    #[serde(default)]
+    pub mounts: BTreeMap<String, PathBuf>,
+}
+
+impl Manifest {
+    /// Loads the manifest at `path`, in TOML or YAML according to its
+    /// extension, and resolves its relative paths against its
+    /// directory.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ManifestError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|source| ManifestError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let manifest = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&source)?,
+            Some("yaml") | Some("yml") => Self::from_yaml_str(&source)?,
+            _ => return Err(ManifestError::UnknownFormat(path.to_path_buf())),
+        };
+
+        Ok(match path.parent() {
+            Some(base_dir) => manifest.resolve(base_dir),
+            None => manifest,
+        })
+    }
+
+    /// Parses a TOML manifest, whose relative paths are kept as is.
+    pub fn from_toml_str(source: &str) -> Result<Self, ManifestError> {
+        Ok(toml::from_str(source)?)
+    }
+
+    /// Parses a YAML manifest, whose relative paths are kept as is.
+    pub fn from_yaml_str(source: &str) -> Result<Self, ManifestError> {
+        Ok(serde_yaml::from_str(source)?)
+    }
+
+    /// Makes the relative host paths relative to `base_dir`.
+    pub fn resolve(mut self, base_dir: &Path) -> Self {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = base_dir.join(&*path);
+            }
+        };
+        resolve(&mut self.module);
+        self.preopens.iter_mut().for_each(resolve);
+        self.mounts.values_mut().for_each(resolve);
+
+        self
+    }
+
+    /// The name of the program, its `argv[0]`.
+    pub fn program_name(&self) -> String {
+        self.program.clone().unwrap_or_else(|| {
+            self.module
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
+
+    /// Creates a [`WasiStateBuilder`] with the arguments, the
+    /// environment variables and the directories of the manifest, to
+    /// which the embedder can add e.g. the standard streams before
+    /// finalizing it.
+    pub fn state_builder(&self) -> Result<WasiStateBuilder, WasiStateCreationError> {
+        let mut builder = WasiState::new(&self.program_name());
+        builder
+            .args(&self.args)
+            .envs(&self.env)
+            .preopen_dirs(&self.preopens)?;
+        for (alias, host_path) in &self.mounts {
+            builder.map_dir(alias, host_path)?;
+        }
+
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn toml_and_yaml_manifests() {
+        let toml = Manifest::from_toml_str(
+            r#"
+            module = "bin/app.wasm"
+            args = ["--verbose"]
+            preopens = ["data"]
+
+            [env]
+            LOG = "debug"
+
+            [mounts]
+            "/cache" = "/var/cache/app"
+            "#,
+        )
+        .unwrap();
+        let yaml = Manifest::from_yaml_str(
+            r#"
+            module: bin/app.wasm
+            args: [--verbose]
+            preopens: [data]
+            env:
+              LOG: debug
+            mounts:
+              /cache: /var/cache/app
+            "#,
+        )
+        .unwrap();
+        assert_eq!(toml, yaml);
+        assert_eq!(toml.program_name(), "app");
+
+        let resolved = toml.resolve(Path::new("/srv"));
+        assert_eq!(resolved.module, Path::new("/srv/bin/app.wasm"));
+        assert_eq!(resolved.preopens, [Path::new("/srv/data")]);
+        assert_eq!(resolved.mounts["/cache"], Path::new("/var/cache/app"));
+    }
+
+    #[test]
+    fn invalid_manifests() {
+        assert!(matches!(
+            Manifest::from_toml_str("args = []"),
+            Err(ManifestError::Toml(_))
+        ));
+        assert!(matches!(
+            Manifest::from_toml_str("module = \"app.wasm\"\nargz = []"),
+            Err(ManifestError::Toml(_))
+        ));
+        assert!(matches!(
+            Manifest::from_path("app.json"),
+            Err(ManifestError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+//! Policies restricting the remote resources a guest can reach with
+//! the `http_request` and `ws_connect` syscalls.
+//!
+//! ```rust,ignore
+//! use wasmer_wasi::{NetPolicy, WasiState};
+//!
+//! let mut policy = NetPolicy::new();
+//! policy
+//!     .allow_host("api.example.com")
+//!     .allow_host("*.cdn.example.com")
+//!     .allow_scheme("https")
+//!     .allow_scheme("wss")
+//!     .max_request_body_size(64 * 1024);
+//!
+//! let env = WasiState::new("program").net_policy(policy).finalize()?;
+//! ```
+//!
+//! The policy is checked by the syscalls before the request reaches the
+//! [`VirtualNetworking`] implementation: a request to a denied URL fails
+//! with `EACCES`, and a body exceeding its maximum size with `EMSGSIZE`.
+//!
+//! [`VirtualNetworking`]: crate::VirtualNetworking
+
+use crate::syscalls::types::*;
+use url::Url;
+
+/// Restricts the URLs a guest can request and the size of the bodies
+/// it can exchange.
+///
+/// A new policy allows everything; each restriction is opt-in.
+#[derive(Debug, Clone, Default)]
+pub struct NetPolicy {
+    allowed_hosts: Option<Vec<(String, Option<u16>)>>,
+    allowed_schemes: Option<Vec<String>>,
+    max_request_body_size: Option<usize>,
+    max_response_body_size: Option<usize>,
+}
+
+impl NetPolicy {
+    /// Creates a policy allowing everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `host` to the allowlist of hosts. Once a host is allowed,
+    /// the requests to the hosts absent from the allowlist are denied.
+    ///
+    /// A host starting with `*.` allows all its subdomains, e.g.
+    /// `*.example.com` allows `api.example.com` but not `example.com`.
+    /// A host ending with a port, e.g. `example.com:8443` or `[::1]:443`,
+    /// only allows that port, the URLs without one using the default
+    /// port of their scheme. Hosts are compared case-insensitively.
+    pub fn allow_host(&mut self, host: &str) -> &mut Self {
+        let (host, port) = split_port(host);
+        self.allowed_hosts
+            .get_or_insert_with(Vec::new)
+            .push((host.to_ascii_lowercase(), port));
+
+        self
+    }
+
+    /// Adds `scheme` to the allowlist of URL schemes, e.g. `https` or
+    /// `wss`. Once a scheme is allowed, the requests using the schemes
+    /// absent from the allowlist are denied.
+    pub fn allow_scheme(&mut self, scheme: &str) -> &mut Self {
+        self.allowed_schemes
+            .get_or_insert_with(Vec::new)
+            .push(scheme.to_ascii_lowercase());
+
+        self
+    }
+
+    /// Limits the size of the body a guest sends in a HTTP request.
+    pub fn max_request_body_size(&mut self, size: usize) -> &mut Self {
+        self.max_request_body_size = Some(size);
+
+        self
+    }
+
+    /// Limits the size of the body a guest receives in a HTTP response.
+    pub fn max_response_body_size(&mut self, size: usize) -> &mut Self {
+        self.max_response_body_size = Some(size);
+
+        self
+    }
+
+    pub(crate) fn request_body_limit(&self) -> Option<usize> {
+        self.max_request_body_size
+    }
+
+    pub(crate) fn response_body_limit(&self) -> Option<usize> {
+        self.max_response_body_size
+    }
+
+    /// Checks that the policy allows a request to `url`.
+    pub(crate) fn check_url(&self, url: &str) -> Result<(), __wasi_errno_t> {
+        let url = Url::parse(url.trim()).map_err(|_| __WASI_EINVAL)?;
+        let host = url.host_str().ok_or(__WASI_EINVAL)?;
+        // The IPv6 addresses are bracketed, and the hosts of the URLs of
+        // unknown schemes aren't lowercased.
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_ascii_lowercase();
+        let port = url.port_or_known_default();
+
+        if let Some(schemes) = &self.allowed_schemes {
+            if !schemes.iter().any(|allowed| allowed == url.scheme()) {
+                return Err(__WASI_EACCES);
+            }
+        }
+        if let Some(hosts) = &self.allowed_hosts {
+            let allowed = hosts.iter().any(|(allowed_host, allowed_port)| {
+                host_matches(allowed_host, &host) && allowed_port.map_or(true, |p| Some(p) == port)
+            });
+            if !allowed {
+                return Err(__WASI_EACCES);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits the port off `host`, if it ends with one.
+fn split_port(host: &str) -> (&str, Option<u16>) {
+    if let Some(ipv6) = host.strip_prefix('[') {
+        return match ipv6.split_once("]:") {
+            Some((ip, port)) => (ip, port.parse().ok()),
+            None => (ipv6.trim_end_matches(']'), None),
+        };
+    }
+    match host.split_once(':') {
+        // Unbracketed IPv6 addresses have no port.
+        Some((name, port)) if !port.contains(':') => (name, port.parse().ok()),
+        _ => (host, None),
+    }
+}
+
+fn host_matches(allowed: &str, host: &str) -> bool {
+    match allowed.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .map_or(false, |subdomain| subdomain.ends_with('.')),
+        None => allowed == host,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn allow_everything_by_default() {
+        let policy = NetPolicy::new();
+
+        assert_eq!(policy.check_url("http://example.com/"), Ok(()));
+        assert_eq!(policy.check_url("not a url"), Err(__WASI_EINVAL));
+    }
+
+    #[test]
+    fn allowlists() {
+        let mut policy = NetPolicy::new();
+        policy
+            .allow_host("example.com")
+            .allow_host("*.cdn.example.com")
+            .allow_host("::1")
+            .allow_scheme("https")
+            .allow_scheme("wss");
+
+        assert_eq!(policy.check_url("https://Example.com:8443/path"), Ok(()));
+        assert_eq!(policy.check_url("wss://user@example.com?q=1"), Ok(()));
+        assert_eq!(policy.check_url("https://a.b.cdn.example.com"), Ok(()));
+        assert_eq!(policy.check_url("https://[::1]:443/"), Ok(()));
+        assert_eq!(policy.check_url("http://example.com"), Err(__WASI_EACCES));
+        assert_eq!(
+            policy.check_url("https://cdn.example.com"),
+            Err(__WASI_EACCES)
+        );
+        assert_eq!(
+            policy.check_url("https://evilexample.com"),
+            Err(__WASI_EACCES)
+        );
+        assert_eq!(
+            policy.check_url("https://example.com@evil.com/"),
+            Err(__WASI_EACCES)
+        );
+        assert_eq!(
+            policy.check_url("https://evil.com\\@example.com/"),
+            Err(__WASI_EACCES)
+        );
+    }
+
+    #[test]
+    fn allow_ports() {
+        let mut policy = NetPolicy::new();
+        policy
+            .allow_host("example.com:443")
+            .allow_host("[::1]:8080");
+
+        assert_eq!(policy.check_url("https://example.com/"), Ok(()));
+        assert_eq!(policy.check_url("wss://example.com:443/"), Ok(()));
+        assert_eq!(policy.check_url("http://[::1]:8080/"), Ok(()));
+        assert_eq!(policy.check_url("http://example.com/"), Err(__WASI_EACCES));
+        assert_eq!(
+            policy.check_url("https://example.com:8443/"),
+            Err(__WASI_EACCES)
+        );
+        assert_eq!(policy.check_url("http://[::1]/"), Err(__WASI_EACCES));
+    }
+}
@@ -0,0 +1,85 @@
+//! Hot reloading of a WASI program: a new version of its module is
+//! instantiated against the state of the running one, so that its open
+//! files and sockets, its preopened directories and its current
+//! directory survive the reload.
+
+use crate::{WasiEnv, WasiError};
+use thiserror::Error;
+use wasmer::{HostEnvInitError, Instance, InstantiationError, Module, RuntimeError, WasmerEnv};
+
+/// How [`WasiEnv::reload`] carries the previous instance over to the new
+/// one, besides the state of WASI.
+#[derive(Debug, Clone, Default)]
+pub struct ReloadOptions {
+    /// The exported globals whose values are copied from the previous
+    /// instance to the new one, by name. They must be mutable in the
+    /// new instance.
+    pub migrate_globals: Vec<String>,
+}
+
+/// An error while reloading a WASI program, see [`WasiEnv::reload`].
+#[derive(Error, Debug)]
+pub enum ReloadError {
+    /// The new module doesn't import WASI.
+    #[error(transparent)]
+    Imports(#[from] WasiError),
+    /// The new module couldn't be instantiated.
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+    /// The environment couldn't be initialized with the new instance.
+    #[error(transparent)]
+    HostEnvInitialization(#[from] HostEnvInitError),
+    /// A global to migrate isn't exported by one of the instances.
+    #[error("the global `{0}` to migrate isn't exported by both instances")]
+    MissingGlobal(String),
+    /// A global couldn't be migrated, e.g. because it's immutable in
+    /// the new instance or its type changed.
+    #[error("failed to migrate the global `{name}`: {source}")]
+    Global {
+        /// The name of the global.
+        name: String,
+        /// Why its value couldn't be set.
+        source: RuntimeError,
+    },
+}
+
+impl WasiEnv {
+    /// Instantiates `module`, a new version of the program running in
+    /// `previous`, against the state of this environment, and returns
+    /// the environment of the new instance along with it.
+    ///
+    /// The file descriptors, the preopened directories and the current
+    /// directory are shared with the previous instance, which should no
+    /// longer run. The linear memory isn't: the new instance starts
+    /// with its own, and only the globals listed in `options` are
+    /// copied over.
+    ///
+    /// ```ignore
+    /// let (wasi_env, instance) = wasi_env.reload(&instance, &new_module, &ReloadOptions {
+    ///     migrate_globals: vec!["requests_served".to_string()],
+    /// })?;
+    /// ```
+    pub fn reload(
+        &self,
+        previous: &Instance,
+        module: &Module,
+        options: &ReloadOptions,
+    ) -> Result<(WasiEnv, Instance), ReloadError> {
+        let mut env = self.with_state(self.state.clone());
+        let imports = env.import_object_for_all_wasi_versions(module)?;
+        let instance = Instance::new(module, &imports)?;
+        env.init_with_instance(&instance)?;
+
+        for name in &options.migrate_globals {
+            let missing = || ReloadError::MissingGlobal(name.clone());
+            let old = previous.exports.get_global(name).map_err(|_| missing())?;
+            let new = instance.exports.get_global(name).map_err(|_| missing())?;
+            new.set(old.get()).map_err(|source| ReloadError::Global {
+                name: name.clone(),
+                source,
+            })?;
+        }
+
+        Ok((env, instance))
+    }
+}
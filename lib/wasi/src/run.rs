@@ -0,0 +1,124 @@
+//! Running a WASI program in one call, for the embedders which only need
+//! its output and its exit status.
+
+use crate::{Pipe, WasiExitStatus, WasiState, WasiStateCreationError};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use thiserror::Error;
+use wasmer::{
+    CompileError, ExportError, Instance, InstantiationError, Module, RuntimeError, Store,
+};
+
+/// The options of [`run_wasi`].
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// The name of the program, its `argv[0]`.
+    pub program: String,
+    /// The arguments passed to the program, after its name.
+    pub args: Vec<String>,
+    /// The environment variables of the program.
+    pub envs: Vec<(String, String)>,
+    /// The host directories the program can access.
+    pub preopens: Vec<PathBuf>,
+    /// What the program reads from its standard input.
+    pub stdin: Vec<u8>,
+}
+
+/// The output of a program run by [`run_wasi`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunOutput {
+    /// How the program terminated.
+    pub status: WasiExitStatus,
+    /// What the program wrote to its standard output.
+    pub stdout: Vec<u8>,
+    /// What the program wrote to its standard error.
+    pub stderr: Vec<u8>,
+}
+
+/// An error preventing [`run_wasi`] from running a program to its end,
+/// or [`spawn`](crate::spawn()) from starting it.
+#[derive(Error, Debug)]
+pub enum RunError {
+    /// The module couldn't be compiled.
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+    /// The module doesn't import WASI.
+    #[error("the module doesn't import WASI")]
+    NotWasi,
+    /// The WASI environment couldn't be created, e.g. because of a missing
+    /// preopened directory.
+    #[error(transparent)]
+    State(#[from] WasiStateCreationError),
+    /// The module couldn't be instantiated.
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+    /// The module doesn't export a `_start` function.
+    #[error(transparent)]
+    Export(#[from] ExportError),
+    /// The program trapped.
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+    /// The thread running the program couldn't be spawned.
+    #[error("failed to spawn the thread of the program: {0}")]
+    Thread(std::io::Error),
+}
+
+/// Compiles the WASI program `bytes`, in the binary or, with the `wat`
+/// feature, the text format, and runs its `_start` function in `store`,
+/// with its standard output and error captured.
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::Store;
+/// # use wasmer_wasi::{run_wasi, RunOptions, WasiExitStatus};
+/// let store = Store::default();
+/// let wat = r#"
+///     (module
+///         (import "wasi_unstable" "proc_exit" (func $proc_exit (param i32)))
+///         (memory (export "memory") 1)
+///         (func (export "_start")
+///             (call $proc_exit (i32.const 3))))
+/// "#;
+/// let output = run_wasi(&store, wat, RunOptions::default()).unwrap();
+/// assert_eq!(output.status, WasiExitStatus::Exited(3));
+/// ```
+pub fn run_wasi(
+    store: &Store,
+    bytes: impl AsRef<[u8]>,
+    options: RunOptions,
+) -> Result<RunOutput, RunError> {
+    let module = Module::new(store, bytes)?;
+    if !crate::is_wasi_module(&module) {
+        return Err(RunError::NotWasi);
+    }
+
+    let mut stdin = Pipe::new();
+    stdin.write_all(&options.stdin).unwrap();
+    let mut stdout = Pipe::new();
+    let mut stderr = Pipe::new();
+    let mut wasi_env = WasiState::new(options.program)
+        .args(options.args)
+        .envs(options.envs)
+        .preopen_dirs(options.preopens)?
+        .stdin(Box::new(stdin))
+        .stdout(Box::new(stdout.clone()))
+        .stderr(Box::new(stderr.clone()))
+        .finalize()?;
+
+    let import_object = wasi_env
+        .import_object_for_all_wasi_versions(&module)
+        .map_err(|_| RunError::NotWasi)?;
+    let instance = Instance::new(&module, &import_object)?;
+    let start = instance.exports.get_function("_start")?;
+    let status = wasi_env.wait(start.call(&[]))?;
+
+    let mut output = RunOutput {
+        status,
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+    };
+    stdout.read_to_end(&mut output.stdout).unwrap();
+    stderr.read_to_end(&mut output.stderr).unwrap();
+    Ok(output)
+}
@@ -1,11 +1,18 @@
 use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
 use std::ops::Deref;
+#[cfg(feature = "async")]
+use std::pin::Pin;
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
 use thiserror::Error;
 use wasmer_vbus::{UnsupportedVirtualBus, VirtualBus};
 use wasmer_vnet::VirtualNetworking;
 
 use super::types::*;
+use super::SharedMemoryRegistry;
+use super::ThreadScheduler;
 use super::WasiError;
 use super::WasiThreadId;
 
@@ -39,6 +46,22 @@ pub struct WasiTtyState {
     pub line_buffered: bool,
 }
 
+impl Default for WasiTtyState {
+    fn default() -> Self {
+        Self {
+            rows: 25,
+            cols: 80,
+            width: 800,
+            height: 600,
+            stdin_tty: false,
+            stdout_tty: false,
+            stderr_tty: false,
+            echo: true,
+            line_buffered: true,
+        }
+    }
+}
+
 /// Represents an implementation of the WASI runtime - by default everything is
 /// unimplemented.
 pub trait WasiRuntimeImplementation: fmt::Debug + Sync {
@@ -57,33 +80,36 @@ pub trait WasiRuntimeImplementation: fmt::Debug + Sync {
 
     /// Gets the TTY state
     fn tty_get(&self) -> WasiTtyState {
-        WasiTtyState {
-            rows: 25,
-            cols: 80,
-            width: 800,
-            height: 600,
-            stdin_tty: false,
-            stdout_tty: false,
-            stderr_tty: false,
-            echo: true,
-            line_buffered: true,
-        }
+        WasiTtyState::default()
     }
 
     /// Sets the TTY state
     fn tty_set(&self, _tty_state: WasiTtyState) {}
 
+    /// The scheduler running the threads spawned by the guest, if any.
+    /// By default multithreading is not implemented.
+    fn thread_scheduler(&self) -> Option<&(dyn ThreadScheduler)> {
+        None
+    }
+
     /// Spawns a new thread by invoking the
+    /// callback on the thread scheduler
     fn thread_spawn(
         &self,
-        _callback: Box<dyn FnOnce() + Send + 'static>,
+        callback: Box<dyn FnOnce() + Send + 'static>,
     ) -> Result<(), WasiThreadError> {
-        Err(WasiThreadError::Unsupported)
+        match self.thread_scheduler() {
+            Some(scheduler) => scheduler.schedule(callback),
+            None => Err(WasiThreadError::Unsupported),
+        }
     }
 
     /// Returns the amount of parallelism that is possible on this platform
     fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
-        Err(WasiThreadError::Unsupported)
+        match self.thread_scheduler() {
+            Some(scheduler) => scheduler.parallelism(),
+            None => Err(WasiThreadError::Unsupported),
+        }
     }
 
     /// Invokes whenever a WASM thread goes idle. In some runtimes (like singlethreaded
@@ -98,6 +124,36 @@ pub trait WasiRuntimeImplementation: fmt::Debug + Sync {
     fn getpid(&self) -> Option<u32> {
         None
     }
+
+    /// The registry of the named shared memory objects opened by
+    /// `shm_open`. The runtimes returning the same registry share its
+    /// objects. By default shared memory is not implemented.
+    fn shared_memory(&self) -> Option<&SharedMemoryRegistry> {
+        None
+    }
+
+    /// Reads the clock `clock_id` on behalf of the `clock_time_get`
+    /// syscall. Runtimes can override it to control the time seen by
+    /// the guest, e.g. in tests.
+    fn clock_time_get(
+        &self,
+        clock_id: __wasi_clockid_t,
+        precision: __wasi_timestamp_t,
+    ) -> Result<i64, __wasi_errno_t> {
+        crate::syscalls::platform_clock_time_get(clock_id, precision)
+    }
+
+    /// Runs `task` to completion on behalf of a syscall doing I/O on an
+    /// asynchronous file, e.g. `fd_read` on a `wasmer_vfs::SyncFile`.
+    ///
+    /// By default the current thread is parked while the task is pending.
+    /// Runtimes embedded in an executor run the task on it instead, e.g.
+    /// `tokio::task::block_in_place(|| handle.block_on(task))` on tokio,
+    /// which is also required for files needing tokio's reactor.
+    #[cfg(feature = "async")]
+    fn block_on<'a>(&self, task: Pin<Box<dyn Future<Output = ()> + Send + 'a>>) {
+        wasmer_vfs::block_on(task)
+    }
 }
 
 #[derive(Debug)]
@@ -105,6 +161,14 @@ pub struct PluggableRuntimeImplementation {
     pub bus: Box<dyn VirtualBus + Sync>,
     pub networking: Box<dyn VirtualNetworking + Sync>,
     pub thread_id_seed: AtomicU32,
+    pub shared_memory: SharedMemoryRegistry,
+    pub thread_scheduler: Option<Box<dyn ThreadScheduler>>,
+    /// The state of the terminal seen by the guest, unless it is given
+    /// the one of the host.
+    pub tty: Mutex<WasiTtyState>,
+    /// The terminal of the host, if the guest is allowed to change it.
+    #[cfg(feature = "sys")]
+    pub host_tty: Option<crate::syscalls::HostTty>,
 }
 
 impl PluggableRuntimeImplementation {
@@ -121,6 +185,34 @@ impl PluggableRuntimeImplementation {
     {
         self.networking = Box::new(net)
     }
+
+    /// Shares the objects of `registry`, e.g. with the runtime of
+    /// another environment, or with the host.
+    pub fn set_shared_memory_registry(&mut self, registry: SharedMemoryRegistry) {
+        self.shared_memory = registry
+    }
+
+    /// Runs the threads spawned by the guest on `scheduler`, e.g. a
+    /// `ThreadPoolScheduler`. Without a scheduler, spawning threads is
+    /// not supported.
+    pub fn set_thread_scheduler<S>(&mut self, scheduler: S)
+    where
+        S: ThreadScheduler + 'static,
+    {
+        self.thread_scheduler = Some(Box::new(scheduler))
+    }
+
+    /// Gives the guest the terminal of the host, which `tty_set` can
+    /// then put in raw mode. The terminal is restored when the runtime
+    /// is dropped. Without it, the guest sees a virtual terminal.
+    #[cfg(feature = "sys")]
+    pub fn set_host_tty(&mut self, enabled: bool) {
+        self.host_tty = if enabled {
+            Some(Default::default())
+        } else {
+            None
+        };
+    }
 }
 
 impl Default for PluggableRuntimeImplementation {
@@ -132,6 +224,11 @@ impl Default for PluggableRuntimeImplementation {
             networking: Box::new(wasmer_wasi_local_networking::LocalNetworking::default()),
             bus: Box::new(UnsupportedVirtualBus::default()),
             thread_id_seed: Default::default(),
+            shared_memory: Default::default(),
+            thread_scheduler: None,
+            tty: Default::default(),
+            #[cfg(feature = "sys")]
+            host_tty: None,
         }
     }
 }
@@ -148,4 +245,55 @@ impl WasiRuntimeImplementation for PluggableRuntimeImplementation {
     fn thread_generate_id(&self) -> WasiThreadId {
         self.thread_id_seed.fetch_add(1, Ordering::Relaxed).into()
     }
+
+    fn shared_memory(&self) -> Option<&SharedMemoryRegistry> {
+        Some(&self.shared_memory)
+    }
+
+    fn thread_scheduler(&self) -> Option<&(dyn ThreadScheduler)> {
+        self.thread_scheduler.as_deref()
+    }
+
+    fn tty_get(&self) -> WasiTtyState {
+        #[cfg(feature = "sys")]
+        if let Some(host_tty) = self.host_tty.as_ref() {
+            return host_tty.get();
+        }
+        self.tty.lock().unwrap().clone()
+    }
+
+    /// Toggles the echo and the line buffering of the terminal of the
+    /// host, if the guest is given it, e.g. to put it in raw mode; its
+    /// size can't be set
+    fn tty_set(&self, tty_state: WasiTtyState) {
+        #[cfg(feature = "sys")]
+        if let Some(host_tty) = self.host_tty.as_ref() {
+            return host_tty.set(&tty_state);
+        }
+        *self.tty.lock().unwrap() = tty_state;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_tty() {
+        let runtime = PluggableRuntimeImplementation::default();
+        let raw = WasiTtyState {
+            echo: false,
+            line_buffered: false,
+            ..Default::default()
+        };
+
+        // Without the host terminal, the guest only changes its own.
+        assert_eq!(runtime.tty_get(), WasiTtyState::default());
+        runtime.tty_set(raw.clone());
+        assert_eq!(runtime.tty_get(), raw);
+        assert_eq!(
+            PluggableRuntimeImplementation::default().tty_get(),
+            WasiTtyState::default()
+        );
+    }
 }
@@ -0,0 +1,158 @@
+//! Scheduling the threads spawned by the guest, see [`ThreadScheduler`].
+
+use std::fmt;
+
+use crate::WasiThreadError;
+
+/// A thread of the guest, run to completion by a [`ThreadScheduler`].
+pub type ThreadTask = Box<dyn FnOnce() + Send + 'static>;
+
+/// Decides on which host executor the threads spawned by the guest,
+/// with `thread_spawn` or `proc_fork`, run: a dedicated host thread,
+/// a thread pool, a task of an async runtime, etc.
+///
+/// A task runs a guest thread until it exits, blocking meanwhile when
+/// the guest waits, e.g. in `thread_join` or `poll_oneoff`. Executors
+/// with a bounded number of workers must so run the tasks on workers
+/// allowed to block, e.g. with `tokio::task::spawn_blocking` on
+/// tokio, and can deadlock when more guest threads wait on each other
+/// than there are workers.
+///
+/// The runtimes use it through
+/// [`WasiRuntimeImplementation::thread_scheduler`].
+///
+/// [`WasiRuntimeImplementation::thread_scheduler`]: crate::WasiRuntimeImplementation::thread_scheduler
+pub trait ThreadScheduler: fmt::Debug + Send + Sync {
+    /// Runs `task` on an executor, without waiting for it.
+    fn schedule(&self, task: ThreadTask) -> Result<(), WasiThreadError>;
+
+    /// Returns how many tasks can run in parallel, which the guest gets
+    /// from `thread_parallelism`.
+    fn parallelism(&self) -> Result<usize, WasiThreadError> {
+        Err(WasiThreadError::Unsupported)
+    }
+}
+
+#[cfg(feature = "sys")]
+pub use self::sys::{DedicatedThreadScheduler, ThreadPoolScheduler};
+
+#[cfg(feature = "sys")]
+mod sys {
+    use super::{ThreadScheduler, ThreadTask};
+    use crate::WasiThreadError;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+
+    /// Runs each task on a new host thread.
+    #[derive(Debug, Default)]
+    pub struct DedicatedThreadScheduler;
+
+    impl ThreadScheduler for DedicatedThreadScheduler {
+        fn schedule(&self, task: ThreadTask) -> Result<(), WasiThreadError> {
+            thread::Builder::new()
+                .name("wasi-thread".to_string())
+                .spawn(task)
+                .map(|_| ())
+                .map_err(|_| WasiThreadError::Unsupported)
+        }
+
+        fn parallelism(&self) -> Result<usize, WasiThreadError> {
+            thread::available_parallelism()
+                .map(usize::from)
+                .map_err(|_| WasiThreadError::Unsupported)
+        }
+    }
+
+    /// Runs the tasks on a fixed number of host threads, queuing them
+    /// while all the threads are busy.
+    ///
+    /// A guest thread occupies its worker until it exits, so the pool
+    /// suits the short-lived threads of many guests. A guest waiting on
+    /// more threads than there are workers deadlocks.
+    #[derive(Debug)]
+    pub struct ThreadPoolScheduler {
+        workers: usize,
+        queue: Mutex<mpsc::Sender<ThreadTask>>,
+    }
+
+    impl ThreadPoolScheduler {
+        /// Creates a pool of `workers` host threads, at least one. The
+        /// threads exit once the pool is dropped and its queue is
+        /// drained.
+        pub fn new(workers: usize) -> Self {
+            let workers = workers.max(1);
+            let (sender, receiver) = mpsc::channel::<ThreadTask>();
+            let receiver = Arc::new(Mutex::new(receiver));
+
+            for index in 0..workers {
+                let receiver = receiver.clone();
+
+                thread::Builder::new()
+                    .name(format!("wasi-worker-{}", index))
+                    .spawn(move || loop {
+                        // The lock is released before the task runs, so
+                        // that the other workers can take the next ones.
+                        let task = receiver.lock().unwrap().recv();
+                        match task {
+                            Ok(task) => task(),
+                            Err(_) => break,
+                        }
+                    })
+                    .expect("failed to spawn a worker thread");
+            }
+
+            Self {
+                workers,
+                queue: Mutex::new(sender),
+            }
+        }
+    }
+
+    impl ThreadScheduler for ThreadPoolScheduler {
+        fn schedule(&self, task: ThreadTask) -> Result<(), WasiThreadError> {
+            self.queue
+                .lock()
+                .unwrap()
+                .send(task)
+                .map_err(|_| WasiThreadError::Unsupported)
+        }
+
+        fn parallelism(&self) -> Result<usize, WasiThreadError> {
+            Ok(self.workers)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sys"))]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_thread_pool_scheduler() {
+        let scheduler = ThreadPoolScheduler::new(2);
+        assert_eq!(scheduler.parallelism().unwrap(), 2);
+
+        let (sender, receiver) = mpsc::channel();
+        for task in 0..10 {
+            let sender = sender.clone();
+            scheduler
+                .schedule(Box::new(move || sender.send(task).unwrap()))
+                .unwrap();
+        }
+
+        let mut done = receiver.iter().take(10).collect::<Vec<_>>();
+        done.sort_unstable();
+        assert_eq!(done, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_dedicated_thread_scheduler() {
+        let (sender, receiver) = mpsc::channel();
+        DedicatedThreadScheduler
+            .schedule(Box::new(move || sender.send(()).unwrap()))
+            .unwrap();
+
+        receiver.recv().unwrap();
+    }
+}
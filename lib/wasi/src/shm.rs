@@ -0,0 +1,260 @@
+//! Named shared memory objects, like POSIX's `shm_open`.
+//!
+//! The objects live in a [`SharedMemoryRegistry`] owned by the runtime,
+//! see [`WasiRuntimeImplementation::shared_memory`], so the instances
+//! whose runtimes share a registry share its objects:
+//!
+//! ```rust,ignore
+//! use wasmer_wasi::{PluggableRuntimeImplementation, SharedMemoryRegistry, WasiState};
+//!
+//! let registry = SharedMemoryRegistry::default();
+//!
+//! let mut runtime = PluggableRuntimeImplementation::default();
+//! runtime.set_shared_memory_registry(registry.clone());
+//! let mut env = WasiState::new("program").finalize()?;
+//! env.set_runtime(runtime);
+//!
+//! // The host sees the writes of the guest to the object named `frame`.
+//! let frame = registry.create("frame", 4096);
+//! ```
+//!
+//! A WebAssembly memory can't alias another buffer, so a guest doesn't
+//! map an object into its memory: `shm_open` returns a file descriptor
+//! on the object, read and written with `fd_pread` and `fd_pwrite`.
+//!
+//! [`WasiRuntimeImplementation::shared_memory`]: crate::WasiRuntimeImplementation::shared_memory
+
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, Write};
+use std::sync::{Arc, Mutex, RwLock};
+
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use wasmer_vfs::{FsError, VirtualFile};
+
+/// The named shared memory objects of one or more runtimes.
+///
+/// Cloning a registry gives another handle on the same objects.
+#[derive(Debug, Clone, Default)]
+pub struct SharedMemoryRegistry {
+    objects: Arc<Mutex<HashMap<String, SharedMemory>>>,
+}
+
+impl SharedMemoryRegistry {
+    /// Returns the object named `name`, or creates it with `size`
+    /// zeroed bytes if it doesn't exist. An existing object keeps its
+    /// size.
+    pub fn create(&self, name: &str, size: usize) -> SharedMemory {
+        self.objects
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| SharedMemory::new(size))
+            .clone()
+    }
+
+    /// Returns the object named `name`, if it exists.
+    pub fn get(&self, name: &str) -> Option<SharedMemory> {
+        self.objects.lock().unwrap().get(name).cloned()
+    }
+
+    /// Removes the name `name`, returning whether it existed. Like
+    /// with `shm_unlink`, the object lives on until its last handle is
+    /// dropped, and a new object can be created with the same name.
+    pub fn remove(&self, name: &str) -> bool {
+        self.objects.lock().unwrap().remove(name).is_some()
+    }
+
+    /// The names of the objects.
+    pub fn names(&self) -> Vec<String> {
+        self.objects.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+/// A handle on a shared memory object.
+///
+/// Cloning a handle gives another handle on the same bytes.
+#[derive(Debug, Clone, Default)]
+pub struct SharedMemory {
+    data: Arc<RwLock<Vec<u8>>>,
+}
+
+impl SharedMemory {
+    /// Creates an object, not in any registry, of `size` zeroed bytes.
+    pub fn new(size: usize) -> Self {
+        Self {
+            data: Arc::new(RwLock::new(vec![0; size])),
+        }
+    }
+
+    /// The size of the object in bytes.
+    pub fn len(&self) -> usize {
+        self.data.read().unwrap().len()
+    }
+
+    /// Whether the object is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Resizes the object to `size` bytes, zeroing the new bytes.
+    pub fn set_len(&self, size: usize) {
+        self.data.write().unwrap().resize(size, 0);
+    }
+
+    /// Reads the bytes at `offset` into `buf`, returning how many were
+    /// read, which is less than `buf.len()` past the end of the object.
+    pub fn read(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let data = self.data.read().unwrap();
+        let start = offset.min(data.len());
+        let read = buf.len().min(data.len() - start);
+        buf[..read].copy_from_slice(&data[start..start + read]);
+
+        read
+    }
+
+    /// Writes `buf` at `offset`, growing the object if needed.
+    pub fn write(&self, offset: usize, buf: &[u8]) {
+        let mut data = self.data.write().unwrap();
+        let end = offset + buf.len();
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[offset..end].copy_from_slice(buf);
+    }
+
+    /// Calls `f` with the bytes of the object, which the other handles
+    /// can't access meanwhile.
+    pub fn with_bytes<R>(&self, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        f(self.data.write().unwrap().as_mut_slice())
+    }
+}
+
+/// The files returned by `shm_open`, through which a program reads and
+/// writes a shared memory object.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct SharedMemoryFile {
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    memory: SharedMemory,
+    position: u64,
+}
+
+impl SharedMemoryFile {
+    pub fn new(memory: SharedMemory) -> Self {
+        Self {
+            memory,
+            position: 0,
+        }
+    }
+}
+
+impl Read for SharedMemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.memory.read(self.position as usize, buf);
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Write for SharedMemoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.memory.write(self.position as usize, buf);
+        self.position += buf.len() as u64;
+
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for SharedMemoryFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            io::SeekFrom::Start(offset) => Some(offset),
+            io::SeekFrom::End(offset) => offset_by(self.memory.len() as u64, offset),
+            io::SeekFrom::Current(offset) => offset_by(self.position, offset),
+        };
+
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )
+        })?;
+
+        Ok(self.position)
+    }
+}
+
+fn offset_by(position: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        position.checked_add(offset as u64)
+    } else {
+        position.checked_sub(offset.unsigned_abs())
+    }
+}
+
+#[cfg_attr(feature = "enable-serde", typetag::serde)]
+impl VirtualFile for SharedMemoryFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        self.memory.len() as u64
+    }
+    fn set_len(&mut self, new_size: u64) -> Result<(), FsError> {
+        self.memory.set_len(new_size as usize);
+        Ok(())
+    }
+    fn unlink(&mut self) -> Result<(), FsError> {
+        Ok(())
+    }
+    fn bytes_available_read(&self) -> Result<Option<usize>, FsError> {
+        Ok(Some(
+            (self.memory.len() as u64).saturating_sub(self.position) as usize,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_between_handles() {
+        let registry = SharedMemoryRegistry::default();
+        let memory = registry.create("object", 4);
+
+        let mut file = SharedMemoryFile::new(registry.get("object").unwrap());
+        file.seek(io::SeekFrom::Start(2)).unwrap();
+        file.write_all(b"abcd").unwrap();
+
+        // The object grew, and the other handles see the write.
+        assert_eq!(memory.len(), 6);
+        let mut buf = [0; 6];
+        assert_eq!(memory.read(0, &mut buf), 6);
+        assert_eq!(&buf, b"\0\0abcd");
+
+        // An existing object keeps its size.
+        assert_eq!(registry.create("object", 1).len(), 6);
+
+        // The object outlives its name.
+        assert!(registry.remove("object"));
+        assert!(!registry.remove("object"));
+        assert!(registry.get("object").is_none());
+        memory.write(0, b"z");
+        file.seek(io::SeekFrom::Start(0)).unwrap();
+        let mut buf = [0; 1];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"z");
+    }
+}
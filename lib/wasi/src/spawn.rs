@@ -0,0 +1,169 @@
+//! Running the entry point of a WASI instance in the background, for
+//! the hosts which need to cancel it or to do other work meanwhile.
+
+use crate::{RunError, WasiEnv, WasiExitStatus};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+use wasmer::{Instance, RuntimeError};
+
+/// How long the threads spawned by the program have to exit once its
+/// entry point has returned, after which they are left behind.
+const THREADS_EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Runs the `_start` function of `instance` on a thread of its own,
+/// with the environment `env` its imports have been generated with,
+/// and returns a handle to cancel it or to wait for its exit status.
+///
+/// Once `_start` returns, the threads spawned by the program are
+/// killed, since they can't outlive their process, and are joined
+/// before the exit status is reported. The watchdog of the environment
+/// still applies, with its limitations, see [`WasiEnv::set_timeout`].
+///
+/// ```ignore
+/// let import_object = wasi_env.import_object(&module)?;
+/// let instance = Instance::new(&module, &import_object)?;
+/// let handle = wasmer_wasi::spawn(instance, wasi_env)?;
+/// // ...
+/// handle.cancel();
+/// assert_eq!(
+///     handle.join_blocking()?,
+///     WasiExitStatus::Signaled(__WASI_SIGKILL)
+/// );
+/// ```
+pub fn spawn(instance: Instance, env: WasiEnv) -> Result<WasiInstanceHandle, RunError> {
+    let start = instance.exports.get_function("_start")?.clone();
+    let completion = Arc::new(Completion::default());
+
+    {
+        let env = env.clone();
+        let completion = completion.clone();
+        std::thread::Builder::new()
+            .name("wasi-start".to_string())
+            .spawn(move || {
+                let result = env.wait(start.call(&[]));
+                // The instance is kept alive until the threads are gone.
+                exit_threads(&env);
+                drop(instance);
+                completion.complete(result);
+            })
+            .map_err(RunError::Thread)?;
+    }
+
+    Ok(WasiInstanceHandle { env, completion })
+}
+
+/// Kills the threads of the process of `env`, and waits for them to
+/// exit.
+fn exit_threads(env: &WasiEnv) {
+    env.state.killed.store(true, Ordering::Release);
+
+    let threads = env
+        .state
+        .threading
+        .lock()
+        .unwrap()
+        .threads
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    let deadline = Instant::now() + THREADS_EXIT_TIMEOUT;
+    for thread in threads {
+        if !thread.join(deadline.saturating_duration_since(Instant::now())) {
+            warn!("a thread of the process didn't exit after it was killed");
+        }
+    }
+}
+
+/// The outcome of the entry point, once it has returned.
+#[derive(Debug, Default)]
+struct Completion {
+    result: Mutex<Option<Result<WasiExitStatus, RuntimeError>>>,
+    condvar: Condvar,
+    #[cfg(feature = "async")]
+    notify: tokio::sync::Notify,
+}
+
+impl Completion {
+    fn complete(&self, result: Result<WasiExitStatus, RuntimeError>) {
+        *self.result.lock().unwrap() = Some(result);
+        self.condvar.notify_all();
+        #[cfg(feature = "async")]
+        self.notify.notify_one();
+    }
+}
+
+/// A WASI instance running in the background, see [`spawn`].
+///
+/// Dropping the handle detaches the instance, which keeps running.
+#[derive(Debug)]
+pub struct WasiInstanceHandle {
+    env: WasiEnv,
+    completion: Arc<Completion>,
+}
+
+impl WasiInstanceHandle {
+    /// The environment of the instance.
+    pub fn env(&self) -> &WasiEnv {
+        &self.env
+    }
+
+    /// Kills the process, which then exits with
+    /// `WasiExitStatus::Signaled(__WASI_SIGKILL)`.
+    ///
+    /// Like the watchdog, this is cooperative: its threads exit the
+    /// next time they yield, e.g. in `sched_yield`, `poll_oneoff` or
+    /// while a syscall blocks.
+    pub fn cancel(&self) {
+        self.env.state.killed.store(true, Ordering::Release);
+    }
+
+    /// Whether the process has exited, so that joining it doesn't
+    /// block.
+    pub fn is_finished(&self) -> bool {
+        self.completion.result.lock().unwrap().is_some()
+    }
+
+    /// Waits for the process to exit, for at most `timeout`, returning
+    /// whether it has exited.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let result = self.completion.result.lock().unwrap();
+        let (result, _) = self
+            .completion
+            .condvar
+            .wait_timeout_while(result, timeout, |result| result.is_none())
+            .unwrap();
+
+        result.is_some()
+    }
+
+    /// Waits for the process to exit, and returns its exit status, see
+    /// [`WasiEnv::wait`].
+    pub fn join_blocking(self) -> Result<WasiExitStatus, RuntimeError> {
+        let result = self.completion.result.lock().unwrap();
+        let mut result = self
+            .completion
+            .condvar
+            .wait_while(result, |result| result.is_none())
+            .unwrap();
+
+        result.take().unwrap()
+    }
+
+    /// Waits asynchronously for the process to exit, and returns its
+    /// exit status, see [`WasiEnv::wait`].
+    #[cfg(feature = "async")]
+    pub async fn join(self) -> Result<WasiExitStatus, RuntimeError> {
+        loop {
+            // Created before checking, so that the notification of a
+            // completion in between isn't missed.
+            let notified = self.completion.notify.notified();
+            let result = self.completion.result.lock().unwrap().take();
+            if let Some(result) = result {
+                return result;
+            }
+            notified.await;
+        }
+    }
+}
@@ -1,10 +1,10 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
-use crate::state::{default_fs_backing, WasiFs, WasiState};
+use crate::state::{default_fs_backing, VirtualDevFs, WasiFs, WasiState};
 use crate::syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO};
-use crate::{WasiEnv, WasiInodes};
+use crate::{DnsResolver, FaultInjector, NetPolicy, WasiEnv, WasiInodes};
 use generational_arena::Arena;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -44,6 +44,8 @@ pub struct WasiStateBuilder {
     envs: Vec<(Vec<u8>, Vec<u8>)>,
     preopens: Vec<PreopenedDir>,
     vfs_preopens: Vec<String>,
+    dev_fs: bool,
+    rights_audit: bool,
     #[allow(clippy::type_complexity)]
     setup_fs_fn: Option<Box<dyn Fn(&mut WasiInodes, &mut WasiFs) -> Result<(), String> + Send>>,
     stdout_override: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
@@ -51,6 +53,9 @@ pub struct WasiStateBuilder {
     stdin_override: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     fs_override: Option<Box<dyn wasmer_vfs::FileSystem>>,
     runtime_override: Option<Arc<dyn crate::WasiRuntimeImplementation + Send + Sync + 'static>>,
+    fault_injector: Option<Arc<FaultInjector>>,
+    net_policy: Option<Arc<NetPolicy>>,
+    dns_resolver: Option<Arc<dyn DnsResolver>>,
 }
 
 impl std::fmt::Debug for WasiStateBuilder {
@@ -60,11 +65,16 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("args", &self.args)
             .field("envs", &self.envs)
             .field("preopens", &self.preopens)
+            .field("dev_fs", &self.dev_fs)
+            .field("rights_audit", &self.rights_audit)
             .field("setup_fs_fn exists", &self.setup_fs_fn.is_some())
             .field("stdout_override exists", &self.stdout_override.is_some())
             .field("stderr_override exists", &self.stderr_override.is_some())
             .field("stdin_override exists", &self.stdin_override.is_some())
             .field("runtime_override_exists", &self.runtime_override.is_some())
+            .field("fault_injector", &self.fault_injector)
+            .field("net_policy", &self.net_policy)
+            .field("dns_resolver", &self.dns_resolver)
             .finish()
     }
 }
@@ -88,6 +98,63 @@ pub enum WasiStateCreationError {
     WasiFsSetupError(String),
     #[error(transparent)]
     FileSystemError(FsError),
+    #[error("preopened directory name is used more than once: `{0}`")]
+    PreopenedDirectoryNameCollision(String),
+    #[error("standard stream is overridden but also reachable through preopened directory `{0}`")]
+    StdioConflict(String),
+    #[error("invalid configuration: {}", display_problems(.0))]
+    InvalidConfiguration(Vec<WasiConfigDiagnostic>),
+}
+
+/// The option of a [`WasiStateBuilder`] a [`WasiConfigDiagnostic`] is
+/// about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WasiConfigOption {
+    /// The argument at this index, the program name being the argument 0.
+    Arg(usize),
+    /// The environment variable with this key.
+    Env(String),
+    /// The preopened directory with this path, or this alias if it's
+    /// mapped.
+    Preopen(String),
+    /// The standard input.
+    Stdin,
+    /// The standard output.
+    Stdout,
+    /// The standard error.
+    Stderr,
+}
+
+impl std::fmt::Display for WasiConfigOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Arg(index) => write!(f, "argument {}", index),
+            Self::Env(key) => write!(f, "environment variable `{}`", key),
+            Self::Preopen(name) => write!(f, "preopened directory `{}`", name),
+            Self::Stdin => write!(f, "stdin"),
+            Self::Stdout => write!(f, "stdout"),
+            Self::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+/// A configuration problem found by [`WasiStateBuilder::validate`],
+/// along with the option it was found in.
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("{option}: {error}")]
+pub struct WasiConfigDiagnostic {
+    /// The offending option.
+    pub option: WasiConfigOption,
+    /// The problem.
+    pub error: WasiStateCreationError,
+}
+
+fn display_problems(problems: &[WasiConfigDiagnostic]) -> String {
+    problems
+        .iter()
+        .map(|problem| problem.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 fn validate_mapped_dir_alias(alias: &str) -> Result<(), WasiStateCreationError> {
@@ -308,13 +375,40 @@ impl WasiStateBuilder {
 
     /// Sets the FileSystem to be used with this WASI instance.
     ///
-    /// This is usually used in case a custom `wasmer_vfs::FileSystem` is needed.
+    /// This is usually used in case a custom `wasmer_vfs::FileSystem` is needed,
+    /// e.g. a `wasmer_vfs::overlay_fs::FileSystem` to let the instance write
+    /// into an in-memory layer while reading through to the host.
     pub fn set_fs(&mut self, fs: Box<dyn wasmer_vfs::FileSystem>) -> &mut Self {
         self.fs_override = Some(fs);
 
         self
     }
 
+    /// Emulates the `/dev` and `/proc` special directories.
+    ///
+    /// The guest gets `/dev/null`, `/dev/zero`, `/dev/random`,
+    /// `/dev/urandom`, as well as `/proc/self/cmdline` and
+    /// `/proc/self/environ` reflecting the configured arguments and
+    /// environment. See [`VirtualDevFs`] for more details.
+    pub fn with_dev_fs(&mut self) -> &mut Self {
+        self.dev_fs = true;
+
+        self
+    }
+
+    /// Audits the rights of the file descriptors instead of enforcing
+    /// them: a syscall lacking rights proceeds anyway, and the
+    /// violation is recorded in the report returned by
+    /// [`WasiState::rights_audit_report`].
+    ///
+    /// This is meant to find out which rights a guest needs, and must
+    /// not be used to run untrusted guests.
+    pub fn with_rights_audit(&mut self) -> &mut Self {
+        self.rights_audit = true;
+
+        self
+    }
+
     /// Configure the WASI filesystem before running.
     // TODO: improve ergonomics on this function
     pub fn setup_fs(
@@ -336,30 +430,58 @@ impl WasiStateBuilder {
         self
     }
 
-    /// Consumes the [`WasiStateBuilder`] and produces a [`WasiState`]
-    ///
-    /// Returns the error from `WasiFs::new` if there's an error
-    ///
-    /// # Calling `build` multiple times
+    /// Injects faults into the syscalls of the [`WasiEnv`] produced by
+    /// [Self::finalize], see [`FaultInjector`].
+    pub fn fault_injector(&mut self, fault_injector: FaultInjector) -> &mut Self {
+        self.fault_injector = Some(Arc::new(fault_injector));
+
+        self
+    }
+
+    /// Restricts the `http_request` and `ws_connect` syscalls of the
+    /// [`WasiEnv`] produced by [Self::finalize], see [`NetPolicy`].
+    pub fn net_policy(&mut self, net_policy: NetPolicy) -> &mut Self {
+        self.net_policy = Some(Arc::new(net_policy));
+
+        self
+    }
+
+    /// Resolves the host names of the `resolve` syscall of the
+    /// [`WasiEnv`] produced by [Self::finalize] with `resolver`, e.g.
+    /// a [`HostsDnsResolver`] to sandbox the name resolution.
     ///
-    /// Calling this method multiple times might not produce a
-    /// determinisic result. This method is changing the builder's
-    /// internal state. The values set with the following methods are
-    /// reset to their defaults:
+    /// [`HostsDnsResolver`]: crate::HostsDnsResolver
+    pub fn dns_resolver<R>(&mut self, resolver: R) -> &mut Self
+    where
+        R: DnsResolver,
+    {
+        self.dns_resolver = Some(Arc::new(resolver));
+
+        self
+    }
+
+    /// Checks the configuration without building anything, and returns
+    /// every problem found along with the option it was found in, e.g.
+    /// invalid arguments or environment variables, preopened directories
+    /// which don't exist in the file system, preopened directories
+    /// exposed under the same name, or overridden standard streams
+    /// which a preopened directory also exposes as `/dev/stdout` and
+    /// alike.
     ///
-    /// * [Self::set_fs],
-    /// * [Self::stdin],
-    /// * [Self::stdout],
-    /// * [Self::stderr].
+    /// Setting a standard stream more than once isn't a conflict: the
+    /// last call overrides the previous ones.
     ///
-    /// Ideally, the builder must be refactord to update `&mut self`
-    /// to `mut self` for every _builder method_, but it will break
-    /// existing code. It will be addressed in a next major release.
-    pub fn build(&mut self) -> Result<WasiState, WasiStateCreationError> {
+    /// An empty list means the configuration is valid. [Self::build]
+    /// runs this validation first.
+    pub fn validate(&self) -> Vec<WasiConfigDiagnostic> {
+        let mut problems = Vec::new();
+        let mut report = |option, error| problems.push(WasiConfigDiagnostic { option, error });
+
         for (i, arg) in self.args.iter().enumerate() {
-            for b in arg.iter() {
-                if *b == 0 {
-                    return Err(WasiStateCreationError::ArgumentContainsNulByte(
+            if arg.contains(&0) {
+                report(
+                    WasiConfigOption::Arg(i),
+                    WasiStateCreationError::ArgumentContainsNulByte(
                         std::str::from_utf8(arg)
                             .unwrap_or(if i == 0 {
                                 "Inner error: program name is invalid utf8!"
@@ -367,58 +489,160 @@ impl WasiStateBuilder {
                                 "Inner error: arg is invalid utf8!"
                             })
                             .to_string(),
-                    ));
-                }
+                    ),
+                );
             }
         }
 
-        enum InvalidCharacter {
-            Nul,
-            Equal,
+        for (env_key, env_value) in self.envs.iter() {
+            let option = || WasiConfigOption::Env(String::from_utf8_lossy(env_key).into_owned());
+            if env_key.contains(&0) {
+                report(
+                    option(),
+                    WasiStateCreationError::EnvironmentVariableFormatError(format!(
+                        "found nul byte in env var key \"{}\" (key=value)",
+                        String::from_utf8_lossy(env_key)
+                    )),
+                );
+            } else if env_key.contains(&b'=') {
+                report(
+                    option(),
+                    WasiStateCreationError::EnvironmentVariableFormatError(format!(
+                        "found equal sign in env var key \"{}\" (key=value)",
+                        String::from_utf8_lossy(env_key)
+                    )),
+                );
+            }
+
+            if env_value.contains(&0) {
+                report(
+                    option(),
+                    WasiStateCreationError::EnvironmentVariableFormatError(format!(
+                        "found nul byte in env var value \"{}\" (key=value)",
+                        String::from_utf8_lossy(env_value)
+                    )),
+                );
+            }
         }
 
-        for (env_key, env_value) in self.envs.iter() {
-            match env_key.iter().find_map(|&ch| {
-                if ch == 0 {
-                    Some(InvalidCharacter::Nul)
-                } else if ch == b'=' {
-                    Some(InvalidCharacter::Equal)
-                } else {
-                    None
-                }
-            }) {
-                Some(InvalidCharacter::Nul) => {
-                    return Err(WasiStateCreationError::EnvironmentVariableFormatError(
-                        format!(
-                            "found nul byte in env var key \"{}\" (key=value)",
-                            String::from_utf8_lossy(env_key)
-                        ),
-                    ))
-                }
+        let preopen_name = |preopen: &PreopenedDir| match &preopen.alias {
+            Some(alias) => alias.clone(),
+            None => preopen.path.to_string_lossy().into_owned(),
+        };
 
-                Some(InvalidCharacter::Equal) => {
-                    return Err(WasiStateCreationError::EnvironmentVariableFormatError(
-                        format!(
-                            "found equal sign in env var key \"{}\" (key=value)",
-                            String::from_utf8_lossy(env_key)
-                        ),
-                    ))
+        if !self.preopens.is_empty() {
+            let default_fs;
+            let fs = match &self.fs_override {
+                Some(fs) => fs.deref(),
+                None => {
+                    default_fs = default_fs_backing();
+                    default_fs.deref()
                 }
+            };
+
+            for preopen in &self.preopens {
+                if fs.metadata(&preopen.path).is_err() {
+                    report(
+                        WasiConfigOption::Preopen(preopen_name(preopen)),
+                        WasiStateCreationError::PreopenedDirectoryNotFound(preopen.path.clone()),
+                    );
+                }
+            }
+        }
 
-                None => (),
+        let mut names = HashSet::new();
+        let dev_fs_preopens = if self.dev_fs {
+            VirtualDevFs::preopens()
+        } else {
+            Vec::new()
+        };
+        let preopen_names = self
+            .preopens
+            .iter()
+            .chain(dev_fs_preopens.iter())
+            .map(preopen_name)
+            .chain(self.vfs_preopens.iter().cloned());
+        for name in preopen_names {
+            if !names.insert(name.clone()) {
+                report(
+                    WasiConfigOption::Preopen(name.clone()),
+                    WasiStateCreationError::PreopenedDirectoryNameCollision(name),
+                );
             }
+        }
 
-            if env_value.iter().any(|&ch| ch == 0) {
-                return Err(WasiStateCreationError::EnvironmentVariableFormatError(
-                    format!(
-                        "found nul byte in env var value \"{}\" (key=value)",
-                        String::from_utf8_lossy(env_value)
-                    ),
-                ));
+        // A guest opening e.g. `/dev/stdout` would reach the preopened
+        // directory instead of the stream the host overrode.
+        let streams = [
+            (&self.stdin_override, WasiConfigOption::Stdin, "stdin"),
+            (&self.stdout_override, WasiConfigOption::Stdout, "stdout"),
+            (&self.stderr_override, WasiConfigOption::Stderr, "stderr"),
+        ];
+        for (stream, option, name) in streams.iter() {
+            if stream.is_none() {
+                continue;
+            }
+            let device = Path::new("dev").join(name);
+            for preopen in &self.preopens {
+                let preopen_name = preopen_name(preopen);
+                if Path::new(preopen_name.trim_start_matches('/')) == device {
+                    report(
+                        option.clone(),
+                        WasiStateCreationError::StdioConflict(preopen_name),
+                    );
+                }
             }
         }
 
-        let fs_backing = self.fs_override.take().unwrap_or_else(default_fs_backing);
+        problems
+    }
+
+    /// Consumes the [`WasiStateBuilder`] and produces a [`WasiState`]
+    ///
+    /// Returns the error from `WasiFs::new` if there's an error
+    ///
+    /// # Calling `build` multiple times
+    ///
+    /// Calling this method multiple times might not produce a
+    /// determinisic result. This method is changing the builder's
+    /// internal state. The values set with the following methods are
+    /// reset to their defaults:
+    ///
+    /// * [Self::set_fs],
+    /// * [Self::stdin],
+    /// * [Self::stdout],
+    /// * [Self::stderr].
+    ///
+    /// Ideally, the builder must be refactord to update `&mut self`
+    /// to `mut self` for every _builder method_, but it will break
+    /// existing code. It will be addressed in a next major release.
+    pub fn build(&mut self) -> Result<WasiState, WasiStateCreationError> {
+        let mut problems = self.validate();
+        match problems.len() {
+            0 => {}
+            1 => return Err(problems.remove(0).error),
+            _ => return Err(WasiStateCreationError::InvalidConfiguration(problems)),
+        }
+
+        let envs = self
+            .envs
+            .iter()
+            .map(|(key, value)| {
+                let mut env = Vec::with_capacity(key.len() + value.len() + 1);
+                env.extend_from_slice(key);
+                env.push(b'=');
+                env.extend_from_slice(value);
+
+                env
+            })
+            .collect::<Vec<_>>();
+
+        let mut fs_backing = self.fs_override.take().unwrap_or_else(default_fs_backing);
+        let mut preopens = self.preopens.clone();
+        if self.dev_fs {
+            fs_backing = Box::new(VirtualDevFs::new(fs_backing, &self.args, &envs));
+            preopens.extend(VirtualDevFs::preopens());
+        }
 
         // self.preopens are checked in [`PreopenDirBuilder::build`]
         let inodes = RwLock::new(crate::state::WasiInodes {
@@ -431,12 +655,16 @@ impl WasiStateBuilder {
             // self.preopens are checked in [`PreopenDirBuilder::build`]
             let mut wasi_fs = WasiFs::new_with_preopen(
                 inodes.deref_mut(),
-                &self.preopens,
+                &preopens,
                 &self.vfs_preopens,
                 fs_backing,
             )
             .map_err(WasiStateCreationError::WasiFsCreationError)?;
 
+            if self.rights_audit {
+                wasi_fs.rights_audit = Some(Default::default());
+            }
+
             // set up the file system, overriding base files and calling the setup function
             if let Some(stdin_override) = self.stdin_override.take() {
                 wasi_fs
@@ -468,18 +696,11 @@ impl WasiStateBuilder {
             inodes: Arc::new(inodes),
             args: self.args.clone(),
             threading: Default::default(),
-            envs: self
-                .envs
-                .iter()
-                .map(|(key, value)| {
-                    let mut env = Vec::with_capacity(key.len() + value.len() + 1);
-                    env.extend_from_slice(key);
-                    env.push(b'=');
-                    env.extend_from_slice(value);
-
-                    env
-                })
-                .collect(),
+            envs,
+            signals: Default::default(),
+            stats: Default::default(),
+            timers: Default::default(),
+            killed: Default::default(),
         })
     }
 
@@ -500,6 +721,9 @@ impl WasiStateBuilder {
         if let Some(runtime) = self.runtime_override.as_ref() {
             env.runtime = runtime.clone();
         }
+        env.fault_injector = self.fault_injector.clone();
+        env.net_policy = self.net_policy.clone();
+        env.dns_resolver = self.dns_resolver.clone();
         Ok(env)
     }
 }
@@ -515,7 +739,7 @@ pub struct PreopenDirBuilder {
 }
 
 /// The built version of `PreopenDirBuilder`
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub(crate) struct PreopenedDir {
     pub(crate) path: PathBuf,
     pub(crate) alias: Option<String>,
@@ -653,6 +877,179 @@ mod test {
         );
     }
 
+    #[test]
+    fn validate_reports_every_problem() {
+        let mut builder = create_wasi_state("test_prog");
+        builder
+            .arg("--h\0elp")
+            .env("HOM=E", "/home/home")
+            .map_dir("data", "/")
+            .unwrap()
+            .map_dir("data", "/")
+            .unwrap();
+
+        assert_eq!(
+            builder.validate(),
+            vec![
+                WasiConfigDiagnostic {
+                    option: WasiConfigOption::Arg(1),
+                    error: WasiStateCreationError::ArgumentContainsNulByte("--h\0elp".to_string()),
+                },
+                WasiConfigDiagnostic {
+                    option: WasiConfigOption::Env("HOM=E".to_string()),
+                    error: WasiStateCreationError::EnvironmentVariableFormatError(
+                        "found equal sign in env var key \"HOM=E\" (key=value)".to_string()
+                    ),
+                },
+                WasiConfigDiagnostic {
+                    option: WasiConfigOption::Preopen("data".to_string()),
+                    error: WasiStateCreationError::PreopenedDirectoryNameCollision(
+                        "data".to_string()
+                    ),
+                },
+            ]
+        );
+        assert!(matches!(
+            builder.build(),
+            Err(WasiStateCreationError::InvalidConfiguration(problems)) if problems.len() == 3
+        ));
+    }
+
+    #[test]
+    fn validate_preopened_directory_not_found() {
+        let mut builder = create_wasi_state("test_prog");
+        builder.preopen_dir("/does-not-exist").unwrap();
+
+        assert_eq!(
+            builder.validate(),
+            vec![WasiConfigDiagnostic {
+                option: WasiConfigOption::Preopen("/does-not-exist".to_string()),
+                error: WasiStateCreationError::PreopenedDirectoryNotFound(PathBuf::from(
+                    "/does-not-exist"
+                )),
+            }]
+        );
+    }
+
+    #[test]
+    fn stdio_last_call_wins() {
+        let mut builder = create_wasi_state("test_prog");
+        builder
+            .stdout(Box::new(crate::Pipe::new()))
+            .stderr(Box::new(crate::Pipe::new()))
+            .stdout(Box::new(crate::Pipe::new()));
+
+        assert_eq!(builder.validate(), vec![]);
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn validate_stdio_conflict() {
+        let mut builder = create_wasi_state("test_prog");
+        builder
+            .stdout(Box::new(crate::Pipe::new()))
+            .map_dir("/dev/stdout", "/")
+            .unwrap()
+            .map_dir("/dev/stderr", "/")
+            .unwrap();
+
+        let problems = builder.validate();
+        assert_eq!(
+            problems,
+            vec![WasiConfigDiagnostic {
+                option: WasiConfigOption::Stdout,
+                error: WasiStateCreationError::StdioConflict("dev/stdout".to_string()),
+            }]
+        );
+        assert_eq!(
+            problems[0].to_string(),
+            "stdout: standard stream is overridden but also reachable through preopened directory `dev/stdout`"
+        );
+        assert!(matches!(
+            builder.build(),
+            Err(WasiStateCreationError::StdioConflict(_))
+        ));
+    }
+
+    #[test]
+    fn dev_fs() {
+        let state = create_wasi_state("test_prog")
+            .arg("--help")
+            .with_dev_fs()
+            .build()
+            .unwrap();
+        let mut inodes = state.inodes.write().unwrap();
+        for path in &["dev/null", "dev/zero", "dev/urandom", "proc/self/cmdline"] {
+            assert!(
+                state
+                    .fs
+                    .get_inode_at_path(inodes.deref_mut(), crate::VIRTUAL_ROOT_FD, None, path, true)
+                    .is_ok(),
+                "{} must be present",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn rights_audit() {
+        use crate::syscalls::types::{__WASI_RIGHT_FD_READ, __WASI_RIGHT_FD_WRITE};
+
+        let state = create_wasi_state("test_prog").build().unwrap();
+        assert!(!state.fs.has_rights(3, 0, __WASI_RIGHT_FD_READ, "fd_read"));
+        assert_eq!(state.rights_audit_report(), None);
+
+        let state = create_wasi_state("test_prog")
+            .with_rights_audit()
+            .build()
+            .unwrap();
+        assert!(state
+            .fs
+            .has_rights(3, __WASI_RIGHT_FD_READ, __WASI_RIGHT_FD_READ, "fd_read"));
+        assert!(state
+            .fs
+            .has_rights(3, __WASI_RIGHT_FD_READ, __WASI_RIGHT_FD_WRITE, "fd_write"));
+
+        let report = state.rights_audit_report().unwrap();
+        assert_eq!(
+            report.violations,
+            vec![crate::state::RightsViolation {
+                fd: 3,
+                syscall: "fd_write",
+                required: __WASI_RIGHT_FD_WRITE,
+                granted: __WASI_RIGHT_FD_READ,
+            }]
+        );
+        assert_eq!(
+            report.missing_rights().into_iter().collect::<Vec<_>>(),
+            vec![(3, __WASI_RIGHT_FD_WRITE)]
+        );
+    }
+
+    #[test]
+    fn rights_audit_is_bounded() {
+        use crate::syscalls::types::{__WASI_RIGHT_FD_READ, __WASI_RIGHT_FD_WRITE};
+
+        let state = create_wasi_state("test_prog")
+            .with_rights_audit()
+            .build()
+            .unwrap();
+        assert!(state.fs.has_rights(3, 0, __WASI_RIGHT_FD_READ, "fd_read"));
+        for _ in 0..2000 {
+            assert!(state.fs.has_rights(4, 0, __WASI_RIGHT_FD_WRITE, "fd_write"));
+        }
+
+        // The oldest violations are dropped, but the rights they lacked
+        // are still reported.
+        let report = state.rights_audit_report().unwrap();
+        assert_eq!(report.violations.len() as u64 + report.dropped, 2001);
+        assert!(report.violations.iter().all(|violation| violation.fd == 4));
+        assert_eq!(
+            report.missing_rights().into_iter().collect::<Vec<_>>(),
+            vec![(3, __WASI_RIGHT_FD_READ), (4, __WASI_RIGHT_FD_WRITE)]
+        );
+    }
+
     #[test]
     fn nul_character_in_args() {
         let output = create_wasi_state("test_prog").arg("--h\0elp").build();
@@ -0,0 +1,558 @@
+//! Emulation of the `/dev` and `/proc` special directories.
+//!
+//! Many programs ported to WASI expect a handful of well-known special
+//! files to exist, such as `/dev/null` or `/proc/self/cmdline`. The
+//! [`VirtualDevFs`] synthesizes them on top of any other
+//! [`FileSystem`], which keeps serving every other path.
+
+use std::io::{self, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use wasmer_vfs::{
+    DirEntry, FileOpener, FileSystem, FileType, FsError, FsEventSink, FsWatch, Metadata,
+    OpenOptions, OpenOptionsConfig, ReadDir, VirtualFile,
+};
+
+use super::PreopenedDir;
+
+/// The special files and directories served by [`VirtualDevFs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DevNode {
+    Dev,
+    Null,
+    Zero,
+    Random,
+    Proc,
+    ProcSelf,
+    Cmdline,
+    Environ,
+}
+
+impl DevNode {
+    fn lookup(path: &Path) -> Option<Self> {
+        if !path.has_root() {
+            return None;
+        }
+        let mut components = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::RootDir | Component::CurDir => (),
+                Component::Normal(name) => components.push(name.to_str()?),
+                Component::Prefix(_) | Component::ParentDir => return None,
+            }
+        }
+        Some(match components.as_slice() {
+            ["dev"] => Self::Dev,
+            ["dev", "null"] => Self::Null,
+            ["dev", "zero"] => Self::Zero,
+            ["dev", "random"] | ["dev", "urandom"] => Self::Random,
+            ["proc"] => Self::Proc,
+            ["proc", "self"] => Self::ProcSelf,
+            ["proc", "self", "cmdline"] => Self::Cmdline,
+            ["proc", "self", "environ"] => Self::Environ,
+            _ => return None,
+        })
+    }
+
+    fn children(&self) -> &'static [&'static str] {
+        match self {
+            Self::Dev => &["null", "random", "urandom", "zero"],
+            Self::Proc => &["self"],
+            Self::ProcSelf => &["cmdline", "environ"],
+            _ => &[],
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        matches!(self, Self::Dev | Self::Proc | Self::ProcSelf)
+    }
+
+    fn metadata(&self, proc_self: &ProcSelf) -> Metadata {
+        let len = match self {
+            Self::Cmdline => proc_self.cmdline.len() as u64,
+            Self::Environ => proc_self.environ.len() as u64,
+            _ => 0,
+        };
+        Metadata {
+            ft: FileType {
+                dir: self.is_dir(),
+                file: !self.is_dir(),
+                ..Default::default()
+            },
+            len,
+            ..Default::default()
+        }
+    }
+}
+
+/// The contents of the `/proc/self` files, captured when the state is built.
+#[derive(Debug, Default)]
+struct ProcSelf {
+    cmdline: Vec<u8>,
+    environ: Vec<u8>,
+}
+
+/// A [`FileSystem`] layer that serves `/dev/null`, `/dev/zero`,
+/// `/dev/random`, `/dev/urandom` and a minimal `/proc/self`, and
+/// delegates every other path to the inner file system.
+///
+/// It is usually registered with [`WasiStateBuilder::with_dev_fs`],
+/// which also preopens `/dev` and `/proc` for the guest.
+///
+/// [`WasiStateBuilder::with_dev_fs`]: crate::WasiStateBuilder::with_dev_fs
+#[derive(Debug)]
+pub struct VirtualDevFs {
+    inner: Arc<dyn FileSystem>,
+    proc_self: Arc<ProcSelf>,
+}
+
+impl VirtualDevFs {
+    /// Creates a new `VirtualDevFs` on top of `inner`.
+    ///
+    /// `args` and `envs` (as `key=value` pairs) are exposed respectively
+    /// through `/proc/self/cmdline` and `/proc/self/environ`.
+    pub fn new(inner: Box<dyn FileSystem>, args: &[Vec<u8>], envs: &[Vec<u8>]) -> Self {
+        let nul_terminated = |items: &[Vec<u8>]| {
+            items.iter().fold(Vec::new(), |mut acc, item| {
+                acc.extend_from_slice(item);
+                acc.push(0);
+                acc
+            })
+        };
+
+        Self {
+            inner: Arc::from(inner),
+            proc_self: Arc::new(ProcSelf {
+                cmdline: nul_terminated(args),
+                environ: nul_terminated(envs),
+            }),
+        }
+    }
+
+    /// The directories that must be preopened to make the special files
+    /// reachable by the guest.
+    pub(crate) fn preopens() -> Vec<PreopenedDir> {
+        ["dev", "proc"]
+            .iter()
+            .map(|name| PreopenedDir {
+                path: PathBuf::from(format!("/{}", name)),
+                alias: Some(name.to_string()),
+                read: true,
+                write: true,
+                create: false,
+            })
+            .collect()
+    }
+}
+
+impl FileSystem for VirtualDevFs {
+    fn read_dir(&self, path: &Path) -> wasmer_vfs::Result<ReadDir> {
+        match DevNode::lookup(path) {
+            Some(node) if node.is_dir() => {
+                let entries = node
+                    .children()
+                    .iter()
+                    .map(|name| {
+                        let path = path.join(name);
+                        let metadata = DevNode::lookup(&path)
+                            .map(|child| child.metadata(&self.proc_self))
+                            .ok_or(FsError::EntityNotFound);
+                        DirEntry { path, metadata }
+                    })
+                    .collect();
+                Ok(ReadDir::new(entries))
+            }
+            Some(_) => Err(FsError::BaseNotDirectory),
+            None => self.inner.read_dir(path),
+        }
+    }
+
+    fn create_dir(&self, path: &Path) -> wasmer_vfs::Result<()> {
+        match DevNode::lookup(path) {
+            Some(_) => Err(FsError::AlreadyExists),
+            None => self.inner.create_dir(path),
+        }
+    }
+
+    fn remove_dir(&self, path: &Path) -> wasmer_vfs::Result<()> {
+        match DevNode::lookup(path) {
+            Some(_) => Err(FsError::PermissionDenied),
+            None => self.inner.remove_dir(path),
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> wasmer_vfs::Result<()> {
+        if DevNode::lookup(from).is_some() || DevNode::lookup(to).is_some() {
+            return Err(FsError::PermissionDenied);
+        }
+        self.inner.rename(from, to)
+    }
+
+    fn metadata(&self, path: &Path) -> wasmer_vfs::Result<Metadata> {
+        match DevNode::lookup(path) {
+            Some(node) => Ok(node.metadata(&self.proc_self)),
+            None => self.inner.metadata(path),
+        }
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> wasmer_vfs::Result<Metadata> {
+        match DevNode::lookup(path) {
+            Some(node) => Ok(node.metadata(&self.proc_self)),
+            None => self.inner.symlink_metadata(path),
+        }
+    }
+
+    fn remove_file(&self, path: &Path) -> wasmer_vfs::Result<()> {
+        match DevNode::lookup(path) {
+            Some(_) => Err(FsError::PermissionDenied),
+            None => self.inner.remove_file(path),
+        }
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(DevFileOpener {
+            inner: self.inner.clone(),
+            proc_self: self.proc_self.clone(),
+        }))
+    }
+
+    fn watch(&self, path: &Path, sink: FsEventSink) -> wasmer_vfs::Result<Box<dyn FsWatch>> {
+        match DevNode::lookup(path) {
+            // The devices never change.
+            Some(_) => Err(FsError::UnknownError),
+            None => self.inner.watch(path, sink),
+        }
+    }
+}
+
+struct DevFileOpener {
+    inner: Arc<dyn FileSystem>,
+    proc_self: Arc<ProcSelf>,
+}
+
+impl FileOpener for DevFileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> wasmer_vfs::Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let node = match DevNode::lookup(path) {
+            Some(node) => node,
+            None => {
+                return self
+                    .inner
+                    .new_open_options()
+                    .options(conf.clone())
+                    .open(path)
+            }
+        };
+        if conf.create_new() {
+            return Err(FsError::AlreadyExists);
+        }
+        let read_only =
+            |data: &[u8]| -> wasmer_vfs::Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+                if conf.write() || conf.append() || conf.truncate() {
+                    Err(FsError::PermissionDenied)
+                } else {
+                    Ok(Box::new(ProcFile::new(data.to_vec())))
+                }
+            };
+        match node {
+            DevNode::Null => Ok(Box::new(NullFile)),
+            DevNode::Zero => Ok(Box::new(ZeroFile)),
+            DevNode::Random => Ok(Box::new(RandomFile)),
+            DevNode::Cmdline => read_only(&self.proc_self.cmdline),
+            DevNode::Environ => read_only(&self.proc_self.environ),
+            DevNode::Dev | DevNode::Proc | DevNode::ProcSelf => Err(FsError::NotAFile),
+        }
+    }
+}
+
+macro_rules! impl_device_metadata {
+    () => {
+        fn last_accessed(&self) -> u64 {
+            0
+        }
+        fn last_modified(&self) -> u64 {
+            0
+        }
+        fn created_time(&self) -> u64 {
+            0
+        }
+        fn size(&self) -> u64 {
+            0
+        }
+        fn set_len(&mut self, _new_size: u64) -> Result<(), FsError> {
+            Ok(())
+        }
+        fn unlink(&mut self) -> Result<(), FsError> {
+            Err(FsError::PermissionDenied)
+        }
+    };
+}
+
+/// `/dev/null`: reads are always at end of file, writes are discarded.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct NullFile;
+
+impl Read for NullFile {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for NullFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for NullFile {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+#[cfg_attr(feature = "enable-serde", typetag::serde)]
+impl VirtualFile for NullFile {
+    impl_device_metadata!();
+}
+
+/// `/dev/zero`: reads return zeroed bytes, writes are discarded.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ZeroFile;
+
+impl Read for ZeroFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        buf.iter_mut().for_each(|byte| *byte = 0);
+        Ok(buf.len())
+    }
+}
+
+impl Write for ZeroFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ZeroFile {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+#[cfg_attr(feature = "enable-serde", typetag::serde)]
+impl VirtualFile for ZeroFile {
+    impl_device_metadata!();
+}
+
+/// `/dev/random` and `/dev/urandom`: reads return bytes from the host
+/// entropy source, writes are discarded.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct RandomFile;
+
+impl Read for RandomFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        getrandom::getrandom(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+        Ok(buf.len())
+    }
+}
+
+impl Write for RandomFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for RandomFile {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+#[cfg_attr(feature = "enable-serde", typetag::serde)]
+impl VirtualFile for RandomFile {
+    impl_device_metadata!();
+}
+
+/// A read-only file of a fixed content, used for the `/proc/self` files.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ProcFile {
+    data: Vec<u8>,
+    pos: u64,
+}
+
+impl ProcFile {
+    fn new(data: Vec<u8>) -> Self {
+        Self { data, pos: 0 }
+    }
+}
+
+impl Read for ProcFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = (self.pos as usize).min(self.data.len());
+        let read = (&self.data[start..]).read(buf)?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Write for ProcFile {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "proc files are read-only",
+        ))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ProcFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            io::SeekFrom::Start(offset) => offset as i64,
+            io::SeekFrom::End(offset) => self.data.len() as i64 + offset,
+            io::SeekFrom::Current(offset) => self.pos as i64 + offset,
+        };
+        if new_pos < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            ));
+        }
+        self.pos = new_pos as u64;
+        Ok(self.pos)
+    }
+}
+
+#[cfg_attr(feature = "enable-serde", typetag::serde)]
+impl VirtualFile for ProcFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+    fn set_len(&mut self, _new_size: u64) -> Result<(), FsError> {
+        Err(FsError::PermissionDenied)
+    }
+    fn unlink(&mut self) -> Result<(), FsError> {
+        Err(FsError::PermissionDenied)
+    }
+    fn bytes_available_read(&self) -> Result<Option<usize>, FsError> {
+        Ok(Some(self.data.len().saturating_sub(self.pos as usize)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::default_fs_backing;
+
+    fn dev_fs() -> VirtualDevFs {
+        VirtualDevFs::new(
+            default_fs_backing(),
+            &[b"prog".to_vec(), b"--flag".to_vec()],
+            &[b"HOME=/home".to_vec()],
+        )
+    }
+
+    #[test]
+    fn devices() {
+        let fs = dev_fs();
+
+        let mut zero = fs.new_open_options().read(true).open("/dev/zero").unwrap();
+        let mut buf = [1u8; 16];
+        assert_eq!(zero.read(&mut buf).unwrap(), 16);
+        assert_eq!(buf, [0u8; 16]);
+
+        let mut null = fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .open("/dev/null")
+            .unwrap();
+        assert_eq!(null.write(b"hello").unwrap(), 5);
+        assert_eq!(null.read(&mut buf).unwrap(), 0);
+
+        let mut random = fs
+            .new_open_options()
+            .read(true)
+            .open("/dev/urandom")
+            .unwrap();
+        assert_eq!(random.read(&mut buf).unwrap(), 16);
+    }
+
+    #[test]
+    fn proc_self() {
+        let fs = dev_fs();
+
+        let mut cmdline = fs
+            .new_open_options()
+            .read(true)
+            .open("/proc/self/cmdline")
+            .unwrap();
+        let mut content = Vec::new();
+        cmdline.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"prog\0--flag\0");
+
+        assert_eq!(
+            fs.metadata(Path::new("/proc/self/environ")).unwrap().len(),
+            b"HOME=/home\0".len() as u64
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open("/proc/self/environ")
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+    }
+
+    #[test]
+    fn directories() {
+        let fs = dev_fs();
+
+        let mut names = fs
+            .read_dir(Path::new("/dev"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["null", "random", "urandom", "zero"]);
+
+        assert!(fs.metadata(Path::new("/proc/self")).unwrap().is_dir());
+        assert!(fs.metadata(Path::new("/dev/null")).unwrap().is_file());
+        assert_eq!(
+            fs.remove_file(Path::new("/dev/null")),
+            Err(FsError::PermissionDenied)
+        );
+        // Relative paths are never part of the special directories.
+        assert!(DevNode::lookup(Path::new("dev/null")).is_none());
+    }
+}
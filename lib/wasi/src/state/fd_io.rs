@@ -0,0 +1,187 @@
+use super::*;
+use std::io::{self, Read, SeekFrom, Write};
+
+/// Reads from a file descriptor of the guest, the same way the guest
+/// would with `fd_read`: the offset of the file descriptor is used and
+/// advanced.
+///
+/// Obtained with [`WasiFs::get_fd_reader`].
+#[derive(Debug)]
+pub struct WasiFdReader<'a> {
+    fs: &'a WasiFs,
+    inodes: &'a RwLock<WasiInodes>,
+    fd: __wasi_fd_t,
+}
+
+/// Writes to a file descriptor of the guest, the same way the guest
+/// would with `fd_write`: the offset of the file descriptor is used and
+/// advanced.
+///
+/// Obtained with [`WasiFs::get_fd_writer`].
+#[derive(Debug)]
+pub struct WasiFdWriter<'a> {
+    fs: &'a WasiFs,
+    inodes: &'a RwLock<WasiInodes>,
+    fd: __wasi_fd_t,
+}
+
+impl WasiFs {
+    /// Returns a reader the host can use to pump data out of the file
+    /// descriptor `fd` of the guest, e.g. a file or a pipe.
+    ///
+    /// The file descriptor must have the `__WASI_RIGHT_FD_READ` right.
+    pub fn get_fd_reader<'a>(
+        &'a self,
+        inodes: &'a RwLock<WasiInodes>,
+        fd: __wasi_fd_t,
+    ) -> Result<WasiFdReader<'a>, __wasi_errno_t> {
+        if self.get_fd(fd)?.rights & __WASI_RIGHT_FD_READ == 0 {
+            return Err(__WASI_EACCES);
+        }
+
+        Ok(WasiFdReader {
+            fs: self,
+            inodes,
+            fd,
+        })
+    }
+
+    /// Returns a writer the host can use to pump data into the file
+    /// descriptor `fd` of the guest, e.g. a file or a pipe.
+    ///
+    /// The file descriptor must have the `__WASI_RIGHT_FD_WRITE` right.
+    pub fn get_fd_writer<'a>(
+        &'a self,
+        inodes: &'a RwLock<WasiInodes>,
+        fd: __wasi_fd_t,
+    ) -> Result<WasiFdWriter<'a>, __wasi_errno_t> {
+        if self.get_fd(fd)?.rights & __WASI_RIGHT_FD_WRITE == 0 {
+            return Err(__WASI_EACCES);
+        }
+
+        Ok(WasiFdWriter {
+            fs: self,
+            inodes,
+            fd,
+        })
+    }
+
+    /// Runs `op` on the inode behind `fd`, with the current offset of
+    /// `fd`, and advances the offset by the number of bytes `op`
+    /// transferred.
+    ///
+    /// The standard streams have no offset, as in `fd_read` and
+    /// `fd_write`.
+    fn transfer_fd<F>(
+        &self,
+        inodes: &RwLock<WasiInodes>,
+        fd: __wasi_fd_t,
+        op: F,
+    ) -> io::Result<usize>
+    where
+        F: FnOnce(&mut Kind, Option<u64>) -> io::Result<usize>,
+    {
+        let inodes = inodes.read().unwrap();
+        let fd_entry = self.get_fd(fd).map_err(wasi_err_into_io_err)?;
+        let offset = match fd {
+            __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => None,
+            _ => Some(fd_entry.offset.load(Ordering::Acquire)),
+        };
+
+        let transferred = {
+            let mut guard = inodes.arena[fd_entry.inode].write();
+            op(guard.deref_mut(), offset)?
+        };
+
+        if offset.is_some() {
+            let mut fd_map = self.fd_map.write().unwrap();
+            if let Some(fd_entry) = fd_map.get_mut(&fd) {
+                fd_entry
+                    .offset
+                    .fetch_add(transferred as u64, Ordering::AcqRel);
+            }
+        }
+
+        Ok(transferred)
+    }
+}
+
+impl Read for WasiFdReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fs
+            .transfer_fd(self.inodes, self.fd, |kind, offset| match kind {
+                Kind::File {
+                    handle: Some(handle),
+                    ..
+                } => {
+                    if let Some(offset) = offset {
+                        handle.seek(SeekFrom::Start(offset))?;
+                    }
+                    handle.read(buf)
+                }
+                Kind::Pipe { pipe } => pipe.read(buf),
+                Kind::Buffer { buffer } => {
+                    let offset = (offset.unwrap_or(0) as usize).min(buffer.len());
+                    (&buffer[offset..]).read(buf)
+                }
+                Kind::Dir { .. } | Kind::Root { .. } => Err(wasi_err_into_io_err(__WASI_EISDIR)),
+                _ => Err(io::ErrorKind::Unsupported.into()),
+            })
+    }
+}
+
+impl Write for WasiFdWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self
+            .fs
+            .transfer_fd(self.inodes, self.fd, |kind, offset| match kind {
+                Kind::File {
+                    handle: Some(handle),
+                    ..
+                } => {
+                    if let Some(offset) = offset {
+                        handle.seek(SeekFrom::Start(offset))?;
+                    }
+                    handle.write(buf)
+                }
+                Kind::Pipe { pipe } => pipe.write(buf),
+                Kind::Buffer { buffer } => {
+                    let offset = (offset.unwrap_or(0) as usize).min(buffer.len());
+                    (&mut buffer[offset..]).write(buf)
+                }
+                Kind::Dir { .. } | Kind::Root { .. } => Err(wasi_err_into_io_err(__WASI_EISDIR)),
+                _ => Err(io::ErrorKind::Unsupported.into()),
+            })?;
+
+        // Only files have a size to keep in sync.
+        let _ = self
+            .fs
+            .filestat_resync_size(self.inodes.read().unwrap().deref(), self.fd);
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.fs
+            .transfer_fd(self.inodes, self.fd, |kind, _offset| match kind {
+                Kind::File {
+                    handle: Some(handle),
+                    ..
+                } => handle.flush().map(|()| 0),
+                _ => Ok(0),
+            })
+            .map(|_| ())
+    }
+}
+
+fn wasi_err_into_io_err(errno: __wasi_errno_t) -> io::Error {
+    let kind = match errno {
+        __WASI_EACCES | __WASI_EPERM => io::ErrorKind::PermissionDenied,
+        __WASI_EAGAIN => io::ErrorKind::WouldBlock,
+        __WASI_EINVAL => io::ErrorKind::InvalidInput,
+        __WASI_EPIPE => io::ErrorKind::BrokenPipe,
+        _ => io::ErrorKind::Other,
+    };
+
+    io::Error::new(kind, format!("WASI error code {}", errno))
+}
@@ -16,17 +16,31 @@
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
 mod builder;
+mod dev_fs;
+mod fd_io;
 mod guard;
 mod pipe;
+mod rights_audit;
+mod signal;
 mod socket;
 mod types;
+mod view;
+mod watch;
 
 pub use self::builder::*;
+pub use self::dev_fs::*;
+pub use self::fd_io::*;
 pub use self::guard::*;
 pub use self::pipe::*;
+pub use self::rights_audit::*;
+pub use self::signal::*;
 pub use self::socket::*;
 pub use self::types::*;
+pub use self::view::*;
+pub use self::watch::*;
+use crate::stats::WasiStats;
 use crate::syscalls::types::*;
+use crate::timer::TimerWheel;
 use crate::utils::map_io_err;
 use crate::WasiBusProcessId;
 use crate::WasiThread;
@@ -171,12 +185,42 @@ pub struct Fd {
     pub rights: __wasi_rights_t,
     pub rights_inheriting: __wasi_rights_t,
     pub flags: __wasi_fdflags_t,
-    pub offset: u64,
+    /// The offset of the reads and the writes, shared with the [`Fd`]s
+    /// duplicated with `fd_dup` or inherited by a forked process, like
+    /// in POSIX.
+    #[cfg_attr(feature = "enable-serde", serde(with = "shared_offset"))]
+    pub offset: Arc<AtomicU64>,
     /// Flags that determine how the [`Fd`] can be used.
     ///
     /// Used when reopening a [`VirtualFile`] during [`WasiState`] deserialization.
     pub open_flags: u16,
     pub inode: Inode,
+    /// The number of [`Fd`]s sharing the open file, duplicated with
+    /// `fd_dup` or inherited by a forked process. The file is closed
+    /// with the last of them.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub(crate) open_count: Arc<AtomicU32>,
+}
+
+/// Serializes the shared offset of an [`Fd`] as its value.
+#[cfg(feature = "enable-serde")]
+mod shared_offset {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    pub fn serialize<S: Serializer>(
+        offset: &Arc<AtomicU64>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        offset.load(Ordering::Acquire).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Arc<AtomicU64>, D::Error> {
+        Ok(Arc::new(AtomicU64::new(u64::deserialize(deserializer)?)))
+    }
 }
 
 impl Fd {
@@ -331,8 +375,13 @@ pub struct WasiFs {
     inode_counter: AtomicU64,
     pub current_dir: Mutex<String>,
     pub is_wasix: AtomicBool,
-    #[cfg_attr(feature = "enable-serde", serde(skip, default = "default_fs_backing"))]
-    pub fs_backing: Box<dyn FileSystem>,
+    #[cfg_attr(
+        feature = "enable-serde",
+        serde(skip, default = "default_shared_fs_backing")
+    )]
+    pub fs_backing: Arc<dyn FileSystem>,
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    rights_audit: Option<Mutex<RightsAudit>>,
 }
 
 /// Returns the default filesystem backing
@@ -348,6 +397,11 @@ pub(crate) fn default_fs_backing() -> Box<dyn wasmer_vfs::FileSystem> {
     }
 }
 
+#[cfg(feature = "enable-serde")]
+fn default_shared_fs_backing() -> Arc<dyn wasmer_vfs::FileSystem> {
+    default_fs_backing().into()
+}
+
 #[derive(Debug, Default)]
 pub struct FallbackFileSystem;
 
@@ -580,7 +634,8 @@ impl WasiFs {
             inode_counter: AtomicU64::new(1024),
             current_dir: Mutex::new("/".to_string()),
             is_wasix: AtomicBool::new(false),
-            fs_backing,
+            fs_backing: fs_backing.into(),
+            rights_audit: None,
         };
         wasi_fs.create_stdin(inodes);
         wasi_fs.create_stdout(inodes);
@@ -835,19 +890,20 @@ impl WasiFs {
         inodes: &mut WasiInodes,
         base: __wasi_fd_t,
     ) -> Result<(Inode, String), __wasi_errno_t> {
-        self.get_current_dir_inner(inodes, base, 0)
+        let current_dir = {
+            let guard = self.current_dir.lock().unwrap();
+            guard.clone()
+        };
+        self.get_current_dir_inner(inodes, base, current_dir, 0)
     }
 
     pub(crate) fn get_current_dir_inner(
         &self,
         inodes: &mut WasiInodes,
         base: __wasi_fd_t,
+        current_dir: String,
         symlink_count: u32,
     ) -> Result<(Inode, String), __wasi_errno_t> {
-        let current_dir = {
-            let guard = self.current_dir.lock().unwrap();
-            guard.clone()
-        };
         let cur_inode = self.get_fd_inode(base)?;
         let inode = self.get_inode_at_path_inner(
             inodes,
@@ -1225,15 +1281,24 @@ impl WasiFs {
     // even if it's false, it still follows symlinks, just not the last
     // symlink so
     // This will be resolved when we have tests asserting the correct behavior
+    //
+    // `current_dir` overrides the current directory of the file system,
+    // with the one of a `WasiStateView`.
     pub(crate) fn get_inode_at_path(
         &self,
         inodes: &mut WasiInodes,
         base: __wasi_fd_t,
+        current_dir: Option<&str>,
         path: &str,
         follow_symlinks: bool,
     ) -> Result<Inode, __wasi_errno_t> {
         let start_inode = if !path.starts_with('/') && self.is_wasix.load(Ordering::Acquire) {
-            let (cur_inode, _) = self.get_current_dir(inodes, base)?;
+            let (cur_inode, _) = match current_dir {
+                Some(current_dir) => {
+                    self.get_current_dir_inner(inodes, base, current_dir.to_string(), 0)?
+                }
+                None => self.get_current_dir(inodes, base)?,
+            };
             cur_inode
         } else {
             self.get_fd_inode(base)?
@@ -1248,6 +1313,7 @@ impl WasiFs {
         &self,
         inodes: &mut WasiInodes,
         base: __wasi_fd_t,
+        current_dir: Option<&str>,
         path: &Path,
         follow_symlinks: bool,
     ) -> Result<(Inode, String), __wasi_errno_t> {
@@ -1262,8 +1328,14 @@ impl WasiFs {
         for comp in components.rev() {
             parent_dir.push(comp);
         }
-        self.get_inode_at_path(inodes, base, &parent_dir.to_string_lossy(), follow_symlinks)
-            .map(|v| (v, new_entity_name))
+        self.get_inode_at_path(
+            inodes,
+            base,
+            current_dir,
+            &parent_dir.to_string_lossy(),
+            follow_symlinks,
+        )
+        .map(|v| (v, new_entity_name))
     }
 
     pub fn get_fd(&self, fd: __wasi_fd_t) -> Result<Fd, __wasi_errno_t> {
@@ -1477,9 +1549,10 @@ impl WasiFs {
                 rights,
                 rights_inheriting,
                 flags,
-                offset: 0,
+                offset: Arc::new(AtomicU64::new(0)),
                 open_flags,
                 inode,
+                open_count: Arc::new(AtomicU32::new(1)),
             },
         );
         Ok(idx)
@@ -1487,6 +1560,7 @@ impl WasiFs {
 
     pub fn clone_fd(&self, fd: __wasi_fd_t) -> Result<__wasi_fd_t, __wasi_errno_t> {
         let fd = self.get_fd(fd)?;
+        fd.open_count.fetch_add(1, Ordering::AcqRel);
         let idx = self.next_fd.fetch_add(1, Ordering::AcqRel);
         self.fd_map.write().unwrap().insert(
             idx,
@@ -1497,11 +1571,41 @@ impl WasiFs {
                 offset: fd.offset,
                 open_flags: fd.open_flags,
                 inode: fd.inode,
+                open_count: fd.open_count,
             },
         );
         Ok(idx)
     }
 
+    /// Creates the file system of a process forked from this one. It
+    /// shares the inodes and the backing file system, and gets a copy
+    /// of the file descriptors, whose open files and offsets are shared
+    /// like with [`WasiFs::clone_fd`].
+    pub(crate) fn fork(&self) -> Self {
+        let fd_map = self
+            .fd_map
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(idx, fd)| {
+                fd.open_count.fetch_add(1, Ordering::AcqRel);
+                (*idx, fd.clone())
+            })
+            .collect();
+
+        Self {
+            preopen_fds: RwLock::new(self.preopen_fds.read().unwrap().clone()),
+            name_map: self.name_map.clone(),
+            fd_map: RwLock::new(fd_map),
+            next_fd: AtomicU32::new(self.next_fd.load(Ordering::Acquire)),
+            inode_counter: AtomicU64::new(self.inode_counter.load(Ordering::Acquire)),
+            current_dir: Mutex::new(self.current_dir.lock().unwrap().clone()),
+            is_wasix: AtomicBool::new(self.is_wasix.load(Ordering::Acquire)),
+            fs_backing: self.fs_backing.clone(),
+            rights_audit: self.rights_audit.as_ref().map(|_| Default::default()),
+        }
+    }
+
     /// Low level function to remove an inode, that is it deletes the WASI FS's
     /// knowledge of a file.
     ///
@@ -1598,8 +1702,9 @@ impl WasiFs {
                 flags: fd_flags,
                 // since we're not calling open on this, we don't need open flags
                 open_flags: 0,
-                offset: 0,
+                offset: Arc::new(AtomicU64::new(0)),
                 inode,
+                open_count: Arc::new(AtomicU32::new(1)),
             },
         );
     }
@@ -1677,6 +1782,26 @@ impl WasiFs {
         fd: __wasi_fd_t,
     ) -> Result<(), __wasi_errno_t> {
         let inode = self.get_fd_inode(fd)?;
+
+        // The open file stays open while other descriptors share it.
+        {
+            let mut fd_map = self.fd_map.write().unwrap();
+            let previous_count = fd_map
+                .get(&fd)
+                .ok_or(__WASI_EBADF)?
+                .open_count
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                    Some(count.saturating_sub(1))
+                })
+                .unwrap();
+
+            if previous_count > 1 {
+                fd_map.remove(&fd);
+
+                return Ok(());
+            }
+        }
+
         let inodeval = inodes.get_inodeval(inode)?;
         let is_preopened = inodeval.is_preopened;
 
@@ -1844,6 +1969,19 @@ pub struct WasiState {
     pub(crate) threading: Mutex<WasiStateThreading>,
     pub args: Vec<Vec<u8>>,
     pub envs: Vec<Vec<u8>>,
+    /// The handlers of the signals raised with `proc_raise`.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub signals: WasiSignals,
+    /// The statistics of the threads, see `WasiEnv::thread_stats`.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub(crate) stats: WasiStats,
+    /// The timers of the clock subscriptions of `poll_oneoff`.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub(crate) timers: TimerWheel,
+    /// Whether the process has been killed by the host, see
+    /// `WasiInstanceHandle::cancel`. Its threads exit when they yield.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub(crate) killed: AtomicBool,
 }
 
 impl WasiState {
@@ -1854,6 +1992,24 @@ impl WasiState {
         create_wasi_state(program_name.as_ref())
     }
 
+    /// Creates the state of a process forked from this one, see
+    /// `proc_fork`. The inodes are shared, the file descriptors and the
+    /// signal handlers are copied, and the child has no thread, nor
+    /// statistics, nor timers, and isn't killed with its parent.
+    pub(crate) fn fork(&self) -> Self {
+        Self {
+            fs: self.fs.fork(),
+            inodes: self.inodes.clone(),
+            threading: Default::default(),
+            args: self.args.clone(),
+            envs: self.envs.clone(),
+            signals: self.signals.fork(),
+            stats: Default::default(),
+            timers: Default::default(),
+            killed: Default::default(),
+        }
+    }
+
     /// Turn the WasiState into bytes
     #[cfg(feature = "enable-serde")]
     pub fn freeze(&self) -> Option<Vec<u8>> {
@@ -1866,6 +2022,12 @@ impl WasiState {
         bincode::deserialize(bytes).ok()
     }
 
+    /// Returns the rights violations recorded so far, or `None` if the
+    /// rights audit isn't enabled, see [`WasiStateBuilder::with_rights_audit`].
+    pub fn rights_audit_report(&self) -> Option<RightsAuditReport> {
+        self.fs.rights_audit_report()
+    }
+
     /// Get the `VirtualFile` object at stdout
     pub fn stdout(&self) -> Result<Option<Box<dyn VirtualFile + Send + Sync + 'static>>, FsError> {
         self.std_dev_get(__WASI_STDOUT_FILENO)
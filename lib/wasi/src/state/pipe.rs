@@ -2,7 +2,7 @@ use crate::syscalls::types::*;
 use crate::syscalls::{read_bytes, write_bytes};
 use bytes::{Buf, Bytes};
 use std::convert::TryInto;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::ops::DerefMut;
 use std::sync::mpsc;
 use std::sync::Mutex;
@@ -116,3 +116,20 @@ impl Read for WasiPipe {
         }
     }
 }
+
+impl Write for WasiPipe {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let tx = self.tx.lock().unwrap();
+        tx.send(buf.to_vec()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the wasi pipe is not connected".to_string(),
+            )
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
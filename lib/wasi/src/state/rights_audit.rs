@@ -0,0 +1,124 @@
+//! Auditing of the rights a guest uses.
+//!
+//! When the audit is enabled with [`WasiStateBuilder::with_rights_audit`],
+//! a syscall which lacks rights on a file descriptor doesn't fail:
+//! the violation is recorded and the syscall proceeds as if the rights
+//! were granted. Running a guest this way, with a restrictive set of
+//! rights, tells which rights it actually needs.
+
+use super::*;
+use std::collections::{BTreeMap, VecDeque};
+
+/// The number of violations kept by the rights audit, above which the
+/// oldest ones are dropped. The rights missing on every file descriptor
+/// are still accounted for.
+const MAX_RECORDED_VIOLATIONS: usize = 1024;
+
+/// A rights check which failed while the rights audit was enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RightsViolation {
+    /// The file descriptor whose rights were checked.
+    pub fd: __wasi_fd_t,
+    /// The syscall which checked the rights.
+    pub syscall: &'static str,
+    /// The rights required by the syscall.
+    pub required: __wasi_rights_t,
+    /// The rights the file descriptor had.
+    pub granted: __wasi_rights_t,
+}
+
+impl RightsViolation {
+    /// The rights required by the syscall which the file descriptor
+    /// didn't have.
+    pub fn missing(&self) -> __wasi_rights_t {
+        self.required & !self.granted
+    }
+}
+
+/// The rights violations recorded by the rights audit.
+#[derive(Debug, Default)]
+pub(crate) struct RightsAudit {
+    /// The latest violations, at most `MAX_RECORDED_VIOLATIONS`.
+    violations: VecDeque<RightsViolation>,
+    /// The number of violations dropped from `violations`.
+    dropped: u64,
+    /// The union of the rights missing on every file descriptor.
+    missing_rights: BTreeMap<__wasi_fd_t, __wasi_rights_t>,
+}
+
+impl RightsAudit {
+    fn record(&mut self, violation: RightsViolation) {
+        *self.missing_rights.entry(violation.fd).or_insert(0) |= violation.missing();
+
+        if self.violations.len() == MAX_RECORDED_VIOLATIONS {
+            self.violations.pop_front();
+            self.dropped += 1;
+        }
+        self.violations.push_back(violation);
+    }
+}
+
+/// The rights violations recorded by the rights audit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RightsAuditReport {
+    /// The latest violations, in the order they happened.
+    pub violations: Vec<RightsViolation>,
+    /// The number of older violations which were dropped to bound the
+    /// memory used by the audit.
+    pub dropped: u64,
+    missing_rights: BTreeMap<__wasi_fd_t, __wasi_rights_t>,
+}
+
+impl RightsAuditReport {
+    /// Returns, for every file descriptor with violations, the union
+    /// of the rights it lacked, including in the dropped violations.
+    pub fn missing_rights(&self) -> BTreeMap<__wasi_fd_t, __wasi_rights_t> {
+        self.missing_rights.clone()
+    }
+}
+
+impl WasiFs {
+    /// Checks that `granted`, the rights of the file descriptor `fd`,
+    /// contain the `required` rights.
+    ///
+    /// When the rights audit is enabled, a failed check is recorded as
+    /// a violation of `syscall`, and passes.
+    pub(crate) fn has_rights(
+        &self,
+        fd: __wasi_fd_t,
+        granted: __wasi_rights_t,
+        required: __wasi_rights_t,
+        syscall: &'static str,
+    ) -> bool {
+        if granted | required == granted {
+            return true;
+        }
+
+        match &self.rights_audit {
+            Some(audit) => {
+                audit.lock().unwrap().record(RightsViolation {
+                    fd,
+                    syscall,
+                    required,
+                    granted,
+                });
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the violations recorded so far, or `None` if the
+    /// rights audit isn't enabled.
+    pub fn rights_audit_report(&self) -> Option<RightsAuditReport> {
+        self.rights_audit.as_ref().map(|audit| {
+            let audit = audit.lock().unwrap();
+            RightsAuditReport {
+                violations: audit.violations.iter().copied().collect(),
+                dropped: audit.dropped,
+                missing_rights: audit.missing_rights.clone(),
+            }
+        })
+    }
+}
@@ -0,0 +1,156 @@
+//! Dispatching of the signals a guest raises with `proc_raise`.
+//!
+//! The host registers handlers for the signals it wants to observe in
+//! [`WasiState::signals`]. A signal without a handler gets its default
+//! action, which is the one of POSIX.
+
+use super::*;
+use std::fmt;
+
+/// What happens to the process when it raises a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalAction {
+    /// The process is terminated, with [`crate::WasiExitStatus::Signaled`].
+    Terminate,
+    /// The signal is ignored, and `proc_raise` returns.
+    Ignore,
+}
+
+impl SignalAction {
+    /// The default action of `signal`, as defined by POSIX.
+    ///
+    /// Signals which stop or continue the process are ignored, as
+    /// processes can't be stopped.
+    pub fn default_for(signal: __wasi_signal_t) -> Self {
+        match signal {
+            __WASI_SIGCHLD | __WASI_SIGURG | __WASI_SIGWINCH => Self::Ignore,
+            __WASI_SIGCONT | __WASI_SIGSTOP | __WASI_SIGTSTP | __WASI_SIGTTIN | __WASI_SIGTTOU => {
+                Self::Ignore
+            }
+            _ => Self::Terminate,
+        }
+    }
+}
+
+/// A host callback handling a signal, returning what happens to the
+/// process.
+pub type SignalHandler = Arc<dyn Fn(__wasi_signal_t) -> SignalAction + Send + Sync + 'static>;
+
+/// The handlers of the signals raised by the process.
+#[derive(Default)]
+pub struct WasiSignals {
+    handlers: RwLock<HashMap<__wasi_signal_t, SignalHandler>>,
+}
+
+impl WasiSignals {
+    /// Calls `handler` whenever the process raises `signal`, instead of
+    /// applying the default action of the signal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `signal` is `SIGKILL` or `SIGSTOP`, which can't be
+    /// handled.
+    pub fn register<F>(&self, signal: __wasi_signal_t, handler: F)
+    where
+        F: Fn(__wasi_signal_t) -> SignalAction + Send + Sync + 'static,
+    {
+        assert!(
+            signal != __WASI_SIGKILL && signal != __WASI_SIGSTOP,
+            "signal {} can't be handled",
+            signal
+        );
+
+        self.handlers
+            .write()
+            .unwrap()
+            .insert(signal, Arc::new(handler));
+    }
+
+    /// Removes the handler of `signal`, restoring its default action.
+    pub fn unregister(&self, signal: __wasi_signal_t) {
+        self.handlers.write().unwrap().remove(&signal);
+    }
+
+    /// Whether a handler is registered for `signal`.
+    pub fn is_registered(&self, signal: __wasi_signal_t) -> bool {
+        self.handlers.read().unwrap().contains_key(&signal)
+    }
+
+    /// Dispatches `signal` to its handler, or applies its default action.
+    pub(crate) fn dispatch(&self, signal: __wasi_signal_t) -> SignalAction {
+        // The lock isn't held while the handler runs, so that the
+        // handler can register handlers itself.
+        let handler = self.handlers.read().unwrap().get(&signal).cloned();
+        match handler {
+            Some(handler) => handler(signal),
+            None => SignalAction::default_for(signal),
+        }
+    }
+
+    /// The handlers of a process forked from this one, which inherits
+    /// them.
+    pub(crate) fn fork(&self) -> Self {
+        Self {
+            handlers: RwLock::new(self.handlers.read().unwrap().clone()),
+        }
+    }
+}
+
+impl fmt::Debug for WasiSignals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut signals = self
+            .handlers
+            .read()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        signals.sort_unstable();
+
+        f.debug_struct("WasiSignals")
+            .field("handled", &signals)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test_signals {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn default_actions() {
+        let signals = WasiSignals::default();
+        assert_eq!(signals.dispatch(__WASI_SIGTERM), SignalAction::Terminate);
+        assert_eq!(signals.dispatch(__WASI_SIGABRT), SignalAction::Terminate);
+        assert_eq!(signals.dispatch(__WASI_SIGCHLD), SignalAction::Ignore);
+        assert_eq!(signals.dispatch(__WASI_SIGWINCH), SignalAction::Ignore);
+    }
+
+    #[test]
+    fn handlers_override_default_actions() {
+        let signals = WasiSignals::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        {
+            let calls = calls.clone();
+            signals.register(__WASI_SIGUSR1, move |signal| {
+                assert_eq!(signal, __WASI_SIGUSR1);
+                calls.fetch_add(1, Ordering::SeqCst);
+                SignalAction::Ignore
+            });
+        }
+
+        assert_eq!(signals.dispatch(__WASI_SIGUSR1), SignalAction::Ignore);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        signals.unregister(__WASI_SIGUSR1);
+        assert_eq!(signals.dispatch(__WASI_SIGUSR1), SignalAction::Terminate);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sigkill_cant_be_handled() {
+        WasiSignals::default().register(__WASI_SIGKILL, |_| SignalAction::Ignore);
+    }
+}
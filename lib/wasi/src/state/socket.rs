@@ -6,6 +6,7 @@ use std::convert::TryInto;
 use std::io::{self, Read};
 use std::mem::transmute;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::Duration;
 #[allow(unused_imports)]
@@ -39,6 +40,9 @@ pub enum InodeSocketKind {
         ty: __wasi_socktype_t,
         pt: __wasi_sockproto_t,
         addr: Option<SocketAddr>,
+        /// Path a Unix domain socket is bound to, in the sandbox of the
+        /// networking implementation.
+        unix_path: Option<PathBuf>,
         only_v6: bool,
         reuse_port: bool,
         reuse_addr: bool,
@@ -147,6 +151,9 @@ pub struct InodeSocket {
     kind: InodeSocketKind,
     read_buffer: Option<Bytes>,
     read_addr: Option<SocketAddr>,
+    /// Number of bytes of HTTP body that can still be transferred
+    /// through this socket, see [`crate::NetPolicy`].
+    body_limit: Option<usize>,
 }
 
 impl InodeSocket {
@@ -155,9 +162,24 @@ impl InodeSocket {
             kind,
             read_buffer: None,
             read_addr: None,
+            body_limit: None,
         }
     }
 
+    /// Limits the number of bytes of body transferred through this socket.
+    pub fn with_body_limit(mut self, limit: Option<usize>) -> InodeSocket {
+        self.body_limit = limit;
+        self
+    }
+
+    /// Counts `len` bytes of body against the limit of this socket.
+    fn consume_body_limit(&mut self, len: usize) -> Result<(), __wasi_errno_t> {
+        if let Some(remaining) = self.body_limit.as_mut() {
+            *remaining = remaining.checked_sub(len).ok_or(__WASI_EMSGSIZE)?;
+        }
+        Ok(())
+    }
+
     pub fn bind(
         &mut self,
         net: &(dyn VirtualNetworking),
@@ -217,8 +239,10 @@ impl InodeSocket {
     ) -> Result<Option<InodeSocket>, __wasi_errno_t> {
         match &self.kind {
             InodeSocketKind::PreSocket {
+                family,
                 ty,
                 addr,
+                unix_path,
                 only_v6,
                 reuse_port,
                 reuse_addr,
@@ -226,13 +250,14 @@ impl InodeSocket {
                 ..
             } => Ok(match *ty {
                 __WASI_SOCK_TYPE_STREAM => {
-                    if addr.is_none() {
-                        return Err(__WASI_EINVAL);
-                    }
-                    let addr = *addr.as_ref().unwrap();
-                    let mut socket = net
-                        .listen_tcp(addr, *only_v6, *reuse_port, *reuse_addr)
-                        .map_err(net_error_into_wasi_err)?;
+                    let mut socket = if *family == __WASI_ADDRESS_FAMILY_UNIX {
+                        let path = unix_path.as_ref().ok_or(__WASI_EINVAL)?;
+                        net.listen_unix(path).map_err(net_error_into_wasi_err)?
+                    } else {
+                        let addr = (*addr).ok_or(__WASI_EINVAL)?;
+                        net.listen_tcp(addr, *only_v6, *reuse_port, *reuse_addr)
+                            .map_err(net_error_into_wasi_err)?
+                    };
                     if let Some(accept_timeout) = accept_timeout {
                         socket
                             .set_timeout(Some(*accept_timeout))
@@ -276,6 +301,90 @@ impl InodeSocket {
         Ok((sock, addr))
     }
 
+    /// Upgrades a connected TCP socket to TLS, the handshake being done by
+    /// the networking of the host.
+    pub fn upgrade_tls(
+        &mut self,
+        net: &(dyn VirtualNetworking),
+        server_name: &str,
+    ) -> Result<Option<InodeSocket>, __wasi_errno_t> {
+        match &self.kind {
+            InodeSocketKind::TcpStream(_) => {}
+            InodeSocketKind::PreSocket { .. } => return Err(__WASI_ENOTCONN),
+            InodeSocketKind::Closed => return Err(__WASI_EIO),
+            _ => return Err(__WASI_ENOTSUP),
+        }
+        let socket = match std::mem::replace(&mut self.kind, InodeSocketKind::Closed) {
+            InodeSocketKind::TcpStream(socket) => socket,
+            _ => unreachable!(),
+        };
+        // The plain connection can't be used anymore if the handshake
+        // fails, so the socket stays closed.
+        let socket = net
+            .upgrade_tls(socket, server_name)
+            .map_err(net_error_into_wasi_err)?;
+        Ok(Some(InodeSocket::new(InodeSocketKind::TcpStream(socket))))
+    }
+
+    /// Binds a Unix domain socket to `path`.
+    pub fn bind_unix(&mut self, path: &Path) -> Result<Option<InodeSocket>, __wasi_errno_t> {
+        match &mut self.kind {
+            InodeSocketKind::PreSocket {
+                family, unix_path, ..
+            } => {
+                if *family != __WASI_ADDRESS_FAMILY_UNIX {
+                    return Err(__WASI_EAFNOSUPPORT);
+                }
+                // The socket is created when it starts listening.
+                unix_path.replace(path.to_path_buf());
+                Ok(None)
+            }
+            InodeSocketKind::Closed => Err(__WASI_EIO),
+            _ => Err(__WASI_ENOTSUP),
+        }
+    }
+
+    /// Connects a Unix domain socket to the socket bound to `path`.
+    pub fn connect_unix(
+        &mut self,
+        net: &(dyn VirtualNetworking),
+        path: &Path,
+    ) -> Result<Option<InodeSocket>, __wasi_errno_t> {
+        match &self.kind {
+            InodeSocketKind::PreSocket {
+                family,
+                ty,
+                send_timeout,
+                recv_timeout,
+                connect_timeout,
+                ..
+            } => {
+                if *family != __WASI_ADDRESS_FAMILY_UNIX {
+                    return Err(__WASI_EAFNOSUPPORT);
+                }
+                if *ty != __WASI_SOCK_TYPE_STREAM {
+                    return Err(__WASI_ENOTSUP);
+                }
+                let mut socket = net
+                    .connect_unix(path, *connect_timeout)
+                    .map_err(net_error_into_wasi_err)?;
+                if let Some(timeout) = send_timeout {
+                    socket
+                        .set_opt_time(TimeType::WriteTimeout, Some(*timeout))
+                        .map_err(net_error_into_wasi_err)?;
+                }
+                if let Some(timeout) = recv_timeout {
+                    socket
+                        .set_opt_time(TimeType::ReadTimeout, Some(*timeout))
+                        .map_err(net_error_into_wasi_err)?;
+                }
+                Ok(Some(InodeSocket::new(InodeSocketKind::TcpStream(socket))))
+            }
+            InodeSocketKind::Closed => Err(__WASI_EIO),
+            _ => Err(__WASI_ENOTSUP),
+        }
+    }
+
     pub fn connect(
         &mut self,
         net: &(dyn VirtualNetworking),
@@ -283,6 +392,7 @@ impl InodeSocket {
     ) -> Result<Option<InodeSocket>, __wasi_errno_t> {
         match &mut self.kind {
             InodeSocketKind::PreSocket {
+                family,
                 ty,
                 addr,
                 send_timeout,
@@ -290,6 +400,7 @@ impl InodeSocket {
                 connect_timeout,
                 ..
             } => Ok(match *ty {
+                _ if *family == __WASI_ADDRESS_FAMILY_UNIX => return Err(__WASI_EAFNOSUPPORT),
                 __WASI_SOCK_TYPE_STREAM => {
                     let addr = match addr {
                         Some(a) => *a,
@@ -777,6 +888,7 @@ impl InodeSocket {
             .map(|a| a.buf_len)
             .sum();
         let buf_len: usize = buf_len.try_into().map_err(|_| __WASI_EINVAL)?;
+        self.consume_body_limit(buf_len)?;
         let mut buf = Vec::with_capacity(buf_len);
         write_bytes(&mut buf, memory, iov)?;
         match &mut self.kind {
@@ -817,6 +929,7 @@ impl InodeSocket {
 
     pub fn send_bytes<M: MemorySize>(&mut self, buf: Bytes) -> Result<usize, __wasi_errno_t> {
         let buf_len = buf.len();
+        self.consume_body_limit(buf_len)?;
         match &mut self.kind {
             InodeSocketKind::HttpRequest(sock, ty) => {
                 let sock = sock.get_mut().unwrap();
@@ -850,6 +963,21 @@ impl InodeSocket {
         .map(|_| buf_len)
     }
 
+    /// Returns the descriptor of the host socket of a stream, which
+    /// files can be sent to with `sendfile(2)`, unless the data sent
+    /// through it is limited.
+    #[cfg(unix)]
+    pub(crate) fn as_raw_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        if self.body_limit.is_some() {
+            return None;
+        }
+
+        match &self.kind {
+            InodeSocketKind::TcpStream(sock) => sock.as_raw_fd(),
+            _ => None,
+        }
+    }
+
     pub fn send_to<M: MemorySize>(
         &mut self,
         memory: &Memory,
@@ -944,6 +1072,7 @@ impl InodeSocket {
                 InodeSocketKind::Closed => return Err(__WASI_EIO),
                 _ => return Err(__WASI_ENOTSUP),
             };
+            self.consume_body_limit(data.len())?;
             self.read_buffer.replace(data);
             self.read_addr.take();
         }
@@ -1103,6 +1232,12 @@ impl Read for InodeSocket {
                     ))
                 }
             };
+            self.consume_body_limit(data.len()).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    "the HTTP body exceeds its maximum size".to_string(),
+                )
+            })?;
             self.read_buffer.replace(data);
             self.read_addr.take();
         }
@@ -0,0 +1,96 @@
+use std::sync::{Mutex, MutexGuard};
+use wasmer_vfs::VirtualFile;
+
+type StdioFile = Box<dyn VirtualFile + Send + Sync + 'static>;
+
+/// What an instance sees differently from the other instances sharing
+/// the same [`WasiState`]: its standard streams and its current
+/// directory.
+///
+/// The instances sharing a state, e.g. a command and a reactor or the
+/// processes of a WASIX process group, share its file descriptors and
+/// its file system the way the threads of a process do; a view gives
+/// each of them its own standard streams and current directory on top
+/// of them, see [`WasiEnv::with_view`].
+///
+/// What isn't set in the view is taken from the shared state. The
+/// streams of the view are used by the reads and writes on the file
+/// descriptors 0, 1 and 2 as long as they are open in the shared state.
+///
+/// [`WasiState`]: crate::WasiState
+/// [`WasiEnv::with_view`]: crate::WasiEnv::with_view
+#[derive(Debug, Default)]
+pub struct WasiStateView {
+    stdin: Mutex<Option<StdioFile>>,
+    stdout: Mutex<Option<StdioFile>>,
+    stderr: Mutex<Option<StdioFile>>,
+    current_dir: Option<Mutex<String>>,
+}
+
+impl WasiStateView {
+    /// Creates a view showing the shared state as is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `stdin` of the instance.
+    pub fn stdin(&mut self, file: StdioFile) -> &mut Self {
+        self.stdin = Mutex::new(Some(file));
+
+        self
+    }
+
+    /// Sets the `stdout` of the instance.
+    pub fn stdout(&mut self, file: StdioFile) -> &mut Self {
+        self.stdout = Mutex::new(Some(file));
+
+        self
+    }
+
+    /// Sets the `stderr` of the instance.
+    pub fn stderr(&mut self, file: StdioFile) -> &mut Self {
+        self.stderr = Mutex::new(Some(file));
+
+        self
+    }
+
+    /// Sets the initial current directory of the instance, which
+    /// `chdir` then changes without affecting the other instances.
+    pub fn current_dir(&mut self, path: &str) -> &mut Self {
+        self.current_dir = Some(Mutex::new(path.to_string()));
+
+        self
+    }
+
+    pub(crate) fn stdin_mut(&self) -> MutexGuard<Option<StdioFile>> {
+        self.stdin.lock().unwrap()
+    }
+
+    pub(crate) fn stdout_mut(&self) -> MutexGuard<Option<StdioFile>> {
+        self.stdout.lock().unwrap()
+    }
+
+    pub(crate) fn stderr_mut(&self) -> MutexGuard<Option<StdioFile>> {
+        self.stderr.lock().unwrap()
+    }
+
+    /// Returns the current directory of the instance, if the view has
+    /// its own.
+    pub fn get_current_dir(&self) -> Option<String> {
+        self.current_dir
+            .as_ref()
+            .map(|current_dir| current_dir.lock().unwrap().clone())
+    }
+
+    /// Changes the current directory of the instance, returning `false`
+    /// if the view doesn't have its own.
+    pub(crate) fn set_current_dir(&self, path: &str) -> bool {
+        match self.current_dir.as_ref() {
+            Some(current_dir) => {
+                *current_dir.lock().unwrap() = path.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! The files returned by `path_notify`, from which a program reads the
+//! changes of a watched path.
+
+use crate::syscalls::types::*;
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use wasmer_vfs::{FileSystem, FsError, FsEvent, FsEventKind, FsEventSink, FsWatch, VirtualFile};
+
+/// The number of changes waiting to be read above which the next ones
+/// are dropped, and a `__WASI_NOTIFY_OVERFLOW` is reported instead.
+const MAX_QUEUED_EVENTS: usize = 4096;
+
+/// The changes waiting to be read, as whole records.
+#[derive(Debug, Default)]
+struct EventQueue {
+    records: VecDeque<Vec<u8>>,
+    /// The total length of the records.
+    len: usize,
+}
+
+impl EventQueue {
+    fn push(&mut self, kind: __wasi_notifyflags_t, name: &str) {
+        if self.records.len() < MAX_QUEUED_EVENTS {
+            self.push_record(kind, name);
+            return;
+        }
+        // The overflow is reported once, until the changes are read.
+        let overflowed = self
+            .records
+            .back()
+            .map_or(false, |record| record[0] == __WASI_NOTIFY_OVERFLOW);
+        if !overflowed {
+            self.push_record(__WASI_NOTIFY_OVERFLOW, "");
+        }
+    }
+
+    fn push_record(&mut self, kind: __wasi_notifyflags_t, name: &str) {
+        // The layout of `__wasi_notify_event_t`: the kind, 3 padding
+        // bytes, and the length of the name.
+        let mut record = Vec::with_capacity(8 + name.len());
+        record.push(kind);
+        record.extend([0; 3]);
+        record.extend((name.len() as u32).to_le_bytes());
+        record.extend(name.as_bytes());
+        self.len += record.len();
+        self.records.push_back(record);
+    }
+}
+
+/// A read-only file receiving the changes of a watched path, as
+/// `__wasi_notify_event_t` records each followed by the name of the
+/// entry which changed.
+///
+/// It's readable, when polled, as soon as a change is waiting. Reads
+/// only return whole records, and fail if the first one doesn't fit.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct WatchFile {
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    events: Arc<Mutex<EventQueue>>,
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    watch: Option<Box<dyn FsWatch>>,
+}
+
+impl WatchFile {
+    /// Watches `path` in `fs`, keeping the changes whose kind is in
+    /// `mask`, a set of `__WASI_NOTIFY_*` flags, until too many are
+    /// waiting to be read.
+    pub fn new(
+        fs: &dyn FileSystem,
+        path: &Path,
+        mask: __wasi_notifyflags_t,
+    ) -> Result<Self, FsError> {
+        let events = Arc::new(Mutex::new(EventQueue::default()));
+        let sink: FsEventSink = {
+            let events = events.clone();
+
+            Arc::new(move |event: FsEvent| {
+                let kind = match event.kind {
+                    FsEventKind::Create => __WASI_NOTIFY_CREATE,
+                    FsEventKind::Modify => __WASI_NOTIFY_MODIFY,
+                    FsEventKind::Remove => __WASI_NOTIFY_REMOVE,
+                };
+                if mask & kind == 0 {
+                    return;
+                }
+
+                let name = event
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                events.lock().unwrap().push(kind, &name);
+            })
+        };
+        let watch = fs.watch(path, sink)?;
+
+        Ok(Self {
+            events,
+            watch: Some(watch),
+        })
+    }
+}
+
+impl Read for WatchFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut guard = self.events.lock().unwrap();
+        let events = &mut *guard;
+        if let Some(record) = events.records.front() {
+            if record.len() > buf.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the buffer is too small for the next change",
+                ));
+            }
+        }
+
+        let mut read = 0;
+        while let Some(record) = events.records.front() {
+            if read + record.len() > buf.len() {
+                break;
+            }
+            buf[read..read + record.len()].copy_from_slice(record);
+            read += record.len();
+            events.len -= record.len();
+            events.records.pop_front();
+        }
+
+        Ok(read)
+    }
+}
+
+impl Write for WatchFile {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "watch files are read-only",
+        ))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for WatchFile {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        // The changes are a stream, which is always read from its start.
+        Ok(0)
+    }
+}
+
+#[cfg_attr(feature = "enable-serde", typetag::serde)]
+impl VirtualFile for WatchFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        self.events.lock().unwrap().len as u64
+    }
+    fn set_len(&mut self, _new_size: u64) -> Result<(), FsError> {
+        Err(FsError::PermissionDenied)
+    }
+    fn unlink(&mut self) -> Result<(), FsError> {
+        self.watch.take();
+        Ok(())
+    }
+    fn bytes_available_read(&self) -> Result<Option<usize>, FsError> {
+        match self.events.lock().unwrap().len {
+            0 => Ok(None),
+            available => Ok(Some(available)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_file_overflow() {
+        let mut file = WatchFile::default();
+        for _ in 0..MAX_QUEUED_EVENTS + 10 {
+            file.events
+                .lock()
+                .unwrap()
+                .push(__WASI_NOTIFY_CREATE, "file");
+        }
+        let record_len = 8 + "file".len();
+        assert_eq!(file.size(), (MAX_QUEUED_EVENTS * record_len + 8) as u64);
+
+        // Only whole records are read.
+        let mut buf = [0; 20];
+        assert!(file.read(&mut buf[..record_len - 1]).is_err());
+        assert_eq!(file.read(&mut buf).unwrap(), record_len);
+        assert_eq!(&buf[..record_len], b"\x01\0\0\0\x04\0\0\0file");
+
+        let mut buf = vec![0; file.size() as usize];
+        assert_eq!(file.read(&mut buf).unwrap(), buf.len());
+        assert_eq!(
+            &buf[buf.len() - 8..],
+            &[__WASI_NOTIFY_OVERFLOW, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(file.bytes_available_read().unwrap(), None);
+    }
+}
@@ -0,0 +1,260 @@
+//! Statistics of the threads of a process, see [`WasiEnv::thread_stats`],
+//! e.g. for a scheduler to find the threads hogging a runtime shared by
+//! several tenants.
+//!
+//! The statistics are collected by the syscalls: a thread spinning
+//! without calling any syscall isn't noticed until its next syscall.
+//!
+//! [`WasiEnv::thread_stats`]: crate::WasiEnv::thread_stats
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::WasiThreadId;
+
+/// The categories in which the syscalls are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyscallCategory {
+    /// The `fd_*` syscalls.
+    Fd,
+    /// The `path_*` syscalls, `chdir` and `getcwd`.
+    Path,
+    /// `poll_oneoff`.
+    Poll,
+    /// The `proc_*` syscalls, `process_spawn` and `getpid`.
+    Process,
+    /// The `thread_*` syscalls and `sched_yield`.
+    Thread,
+    /// The `sock_*`, `port_*` and `http_*` syscalls, `ws_connect` and
+    /// `resolve`.
+    Network,
+    /// The `bus_*` and `call_*` syscalls.
+    Bus,
+    /// The other syscalls, e.g. `clock_time_get` or `random_get`.
+    Other,
+}
+
+impl SyscallCategory {
+    /// The number of categories.
+    pub const COUNT: usize = 8;
+
+    /// All the categories.
+    pub const ALL: [SyscallCategory; Self::COUNT] = [
+        SyscallCategory::Fd,
+        SyscallCategory::Path,
+        SyscallCategory::Poll,
+        SyscallCategory::Process,
+        SyscallCategory::Thread,
+        SyscallCategory::Network,
+        SyscallCategory::Bus,
+        SyscallCategory::Other,
+    ];
+
+    /// The category of the syscall `name`.
+    pub fn of(name: &str) -> Self {
+        match name {
+            "chdir" | "getcwd" => Self::Path,
+            "poll_oneoff" => Self::Poll,
+            "process_spawn" | "getpid" => Self::Process,
+            "sched_yield" => Self::Thread,
+            "ws_connect" | "resolve" => Self::Network,
+            _ if name.starts_with("fd_") => Self::Fd,
+            _ if name.starts_with("path_") => Self::Path,
+            _ if name.starts_with("proc_") => Self::Process,
+            _ if name.starts_with("thread_") => Self::Thread,
+            _ if name.starts_with("sock_")
+                || name.starts_with("port_")
+                || name.starts_with("http_") =>
+            {
+                Self::Network
+            }
+            _ if name.starts_with("bus_") || name.starts_with("call_") => Self::Bus,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The statistics of a thread, returned by [`WasiEnv::thread_stats`].
+///
+/// [`WasiEnv::thread_stats`]: crate::WasiEnv::thread_stats
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WasiThreadStats {
+    /// The CPU time used by the host thread running the thread, as of
+    /// its last syscall. It's `None` where it can't be measured, e.g.
+    /// on the web.
+    pub cpu_time: Option<Duration>,
+    /// The number of syscalls called, by category. Every category is
+    /// present.
+    pub syscalls: HashMap<SyscallCategory, u64>,
+    /// The number of bytes read from files and sockets.
+    pub bytes_read: u64,
+    /// The number of bytes written to files and sockets.
+    pub bytes_written: u64,
+}
+
+impl WasiThreadStats {
+    /// The number of syscalls called, in all the categories.
+    pub fn total_syscalls(&self) -> u64 {
+        self.syscalls.values().sum()
+    }
+}
+
+/// The statistics of the threads of a process, which are forgotten
+/// when a thread exits.
+#[derive(Debug, Default)]
+pub(crate) struct WasiStats {
+    threads: RwLock<HashMap<WasiThreadId, Arc<ThreadCounters>>>,
+}
+
+/// The statistics of a thread, updated without locking so that the
+/// threads don't contend on them.
+#[derive(Debug)]
+struct ThreadCounters {
+    syscalls: [AtomicU64; SyscallCategory::COUNT],
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    /// In nanoseconds, or `NOT_MEASURED`.
+    cpu_time: AtomicU64,
+}
+
+const NOT_MEASURED: u64 = u64::MAX;
+
+impl Default for ThreadCounters {
+    fn default() -> Self {
+        Self {
+            syscalls: Default::default(),
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            cpu_time: AtomicU64::new(NOT_MEASURED),
+        }
+    }
+}
+
+impl WasiStats {
+    fn counters(&self, id: WasiThreadId) -> Arc<ThreadCounters> {
+        if let Some(counters) = self.threads.read().unwrap().get(&id) {
+            return counters.clone();
+        }
+
+        self.threads.write().unwrap().entry(id).or_default().clone()
+    }
+
+    /// Counts a call to the syscall `name` by the thread `id`, which is
+    /// the current thread.
+    pub(crate) fn record_syscall(&self, id: WasiThreadId, name: &str) {
+        let counters = self.counters(id);
+        counters.syscalls[SyscallCategory::of(name) as usize].fetch_add(1, Ordering::Relaxed);
+
+        if let Some(cpu_time) = current_thread_cpu_time() {
+            counters
+                .cpu_time
+                .store(cpu_time.as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_read(&self, id: WasiThreadId, bytes: usize) {
+        self.counters(id)
+            .bytes_read
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_written(&self, id: WasiThreadId, bytes: usize) {
+        self.counters(id)
+            .bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Forgets the statistics of the thread `id`, which has exited.
+    pub(crate) fn remove(&self, id: WasiThreadId) {
+        self.threads.write().unwrap().remove(&id);
+    }
+
+    pub(crate) fn get(&self, id: WasiThreadId) -> Option<WasiThreadStats> {
+        let counters = self.threads.read().unwrap().get(&id)?.clone();
+
+        Some(WasiThreadStats {
+            cpu_time: match counters.cpu_time.load(Ordering::Relaxed) {
+                NOT_MEASURED => None,
+                nanos => Some(Duration::from_nanos(nanos)),
+            },
+            syscalls: SyscallCategory::ALL
+                .iter()
+                .map(|category| {
+                    let count = counters.syscalls[*category as usize].load(Ordering::Relaxed);
+                    (*category, count)
+                })
+                .collect(),
+            bytes_read: counters.bytes_read.load(Ordering::Relaxed),
+            bytes_written: counters.bytes_written.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[cfg(any(
+    target_os = "freebsd",
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "windows"
+))]
+fn current_thread_cpu_time() -> Option<Duration> {
+    use crate::syscalls::types::__WASI_CLOCK_THREAD_CPUTIME_ID;
+
+    let nanos = crate::syscalls::platform_clock_time_get(__WASI_CLOCK_THREAD_CPUTIME_ID, 1).ok()?;
+    Some(Duration::from_nanos(nanos as u64))
+}
+
+#[cfg(not(any(
+    target_os = "freebsd",
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "windows"
+)))]
+fn current_thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syscall_category() {
+        assert_eq!(SyscallCategory::of("fd_write"), SyscallCategory::Fd);
+        assert_eq!(SyscallCategory::of("path_open"), SyscallCategory::Path);
+        assert_eq!(SyscallCategory::of("getcwd"), SyscallCategory::Path);
+        assert_eq!(SyscallCategory::of("sched_yield"), SyscallCategory::Thread);
+        assert_eq!(SyscallCategory::of("sock_send"), SyscallCategory::Network);
+        assert_eq!(SyscallCategory::of("call_reply"), SyscallCategory::Bus);
+        assert_eq!(SyscallCategory::of("random_get"), SyscallCategory::Other);
+    }
+
+    #[test]
+    fn test_thread_stats() {
+        let stats = WasiStats::default();
+        let (main, other) = (WasiThreadId::from(0u32), WasiThreadId::from(1u32));
+        assert_eq!(stats.get(main), None);
+
+        stats.record_syscall(main, "fd_write");
+        stats.record_written(main, 12);
+        stats.record_syscall(main, "fd_read");
+        stats.record_read(main, 3);
+        stats.record_syscall(other, "poll_oneoff");
+
+        let main_stats = stats.get(main).unwrap();
+        assert_eq!(main_stats.syscalls[&SyscallCategory::Fd], 2);
+        assert_eq!(main_stats.syscalls[&SyscallCategory::Poll], 0);
+        assert_eq!(main_stats.total_syscalls(), 2);
+        assert_eq!(main_stats.bytes_read, 3);
+        assert_eq!(main_stats.bytes_written, 12);
+        #[cfg(target_os = "linux")]
+        assert!(main_stats.cpu_time.is_some());
+
+        assert_eq!(stats.get(other).unwrap().total_syscalls(), 1);
+        stats.remove(other);
+        assert_eq!(stats.get(other), None);
+    }
+}
@@ -23,6 +23,7 @@ pub mod wasix64;
 
 use self::types::*;
 use crate::state::{bus_error_into_wasi_err, wasi_error_into_bus_err, InodeHttpSocketType};
+use crate::timer::monotonic_now;
 use crate::utils::map_io_err;
 use crate::WasiBusProcessId;
 use crate::{
@@ -30,23 +31,27 @@ use crate::{
     state::{
         self, fs_error_into_wasi_err, iterate_poll_events, net_error_into_wasi_err, poll,
         virtual_file_type_to_wasi_file_type, Fd, Inode, InodeSocket, InodeSocketKind, InodeVal,
-        Kind, PollEvent, PollEventBuilder, WasiPipe, WasiState, MAX_SYMLINKS,
+        Kind, PollEvent, PollEventBuilder, SignalAction, WasiPipe, WasiState, WasiStateView,
+        WatchFile, MAX_SYMLINKS,
     },
-    WasiEnv, WasiError, WasiThread, WasiThreadId,
+    SharedMemoryFile, WasiEnv, WasiError, WasiThread, WasiThreadId,
 };
 use bytes::Bytes;
 use std::borrow::{Borrow, Cow};
-use std::convert::{Infallible, TryInto};
+use std::convert::{Infallible, TryFrom, TryInto};
 use std::io::{self, Read, Seek, Write};
 use std::mem::transmute;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::ops::{Deref, DerefMut};
 use std::sync::atomic::AtomicU64;
-use std::sync::{atomic::Ordering, Mutex};
+use std::sync::{atomic::Ordering, Mutex, MutexGuard};
 use std::sync::{mpsc, Arc};
 use std::time::Duration;
 use tracing::{debug, error, trace, warn};
-use wasmer::{Memory, Memory32, Memory64, MemorySize, RuntimeError, Value, WasmPtr, WasmSlice};
+use wasmer::{
+    Instance, Memory, Memory32, Memory64, MemorySize, Module, RuntimeError, TypedFunction, Value,
+    WasmPtr, WasmSlice,
+};
 use wasmer_vbus::{FileDescriptor, StdioMode};
 use wasmer_vfs::{FsError, VirtualFile};
 use wasmer_vnet::{SocketHttpRequest, StreamSecurity};
@@ -75,11 +80,33 @@ fn from_offset<M: MemorySize>(offset: M::Offset) -> Result<usize, __wasi_errno_t
     Ok(ret)
 }
 
+/// Adds the length of a guest buffer to a total, as the guest may pass
+/// the same buffer many times, which can't be more than `usize::MAX`.
+fn add_buf_len(total: usize, len: usize) -> Result<usize, __wasi_errno_t> {
+    total.checked_add(len).ok_or(__WASI_EOVERFLOW)
+}
+
+/// The room left in a guest buffer of `buf_len` bytes, once `used` are,
+/// which doesn't fit in a `usize` for a 64-bit memory on a 32-bit host.
+fn remaining_buf_len(buf_len: u64, used: usize) -> usize {
+    let remaining = buf_len.saturating_sub(used as u64);
+    usize::try_from(remaining).unwrap_or(usize::MAX)
+}
+
 fn write_bytes_inner<T: Write, M: MemorySize>(
     mut write_loc: T,
     memory: &Memory,
     iovs_arr_cell: WasmSlice<__wasi_ciovec_t<M>>,
 ) -> Result<usize, __wasi_errno_t> {
+    #[cfg(feature = "sys")]
+    {
+        if let Some(bytes_written) =
+            write_bytes_vectored::<_, M>(&mut write_loc, memory, iovs_arr_cell)?
+        {
+            return Ok(bytes_written);
+        }
+    }
+
     let mut bytes_written = 0usize;
     for iov in iovs_arr_cell.iter() {
         let iov_inner = iov.read().map_err(mem_error_to_wasi)?;
@@ -89,11 +116,92 @@ fn write_bytes_inner<T: Write, M: MemorySize>(
         let bytes = bytes.read_to_vec().map_err(mem_error_to_wasi)?;
         write_loc.write_all(&bytes).map_err(map_io_err)?;
 
-        bytes_written += from_offset::<M>(iov_inner.buf_len)?;
+        bytes_written = add_buf_len(bytes_written, from_offset::<M>(iov_inner.buf_len)?)?;
     }
     Ok(bytes_written)
 }
 
+/// Writes the buffers straight from the guest memory with
+/// `write_vectored`, instead of copying them one by one. Returns `None`
+/// if the buffers can't be leased, see [`lease_guest_buffers`].
+#[cfg(feature = "sys")]
+fn write_bytes_vectored<T: Write, M: MemorySize>(
+    mut write_loc: T,
+    memory: &Memory,
+    iovs_arr_cell: WasmSlice<__wasi_ciovec_t<M>>,
+) -> Result<Option<usize>, __wasi_errno_t> {
+    let iovs = iovs_arr_cell.read_to_vec().map_err(mem_error_to_wasi)?;
+    let leases =
+        match lease_guest_buffers::<M>(memory, iovs.iter().map(|iov| (iov.buf, iov.buf_len)))? {
+            Some(leases) => leases,
+            None => return Ok(None),
+        };
+    // The leases keep the memory in place, and nothing else runs on it.
+    let mut buffers = leases
+        .iter()
+        .map(|lease| unsafe { lease.as_slice() })
+        .collect::<Vec<_>>();
+    let bytes_written = buffers
+        .iter()
+        .try_fold(0, |total, buffer| add_buf_len(total, buffer.len()))?;
+
+    // Like `Write::write_all_vectored`, which isn't stable
+    let mut first = 0;
+    while first < buffers.len() {
+        let slices = buffers[first..]
+            .iter()
+            .map(|buffer| io::IoSlice::new(buffer))
+            .collect::<Vec<_>>();
+        let mut written = match write_loc.write_vectored(&slices) {
+            Ok(0) => return Err(__WASI_EIO),
+            Ok(written) => written,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(map_io_err(err)),
+        };
+        while written > 0 {
+            let buffer = buffers[first];
+            if written < buffer.len() {
+                buffers[first] = &buffer[written..];
+                break;
+            }
+            written -= buffer.len();
+            first += 1;
+        }
+    }
+
+    Ok(Some(bytes_written))
+}
+
+/// Leases the non-empty guest buffers, given as their offset and length,
+/// so they can be borrowed during the syscall: the memory can't move
+/// while they are leased.
+///
+/// Returns `None` if the memory is shared, as the other threads of the
+/// guest may access the buffers meanwhile, or if it can't be pinned. The
+/// buffers are then copied instead.
+#[cfg(feature = "sys")]
+fn lease_guest_buffers<M: MemorySize>(
+    memory: &Memory,
+    buffers: impl Iterator<Item = (M::Offset, M::Offset)>,
+) -> Result<Option<Vec<wasmer::MemoryLease>>, __wasi_errno_t> {
+    if memory.ty().shared {
+        return Ok(None);
+    }
+    let mut leases = Vec::new();
+    for (buf, buf_len) in buffers {
+        if buf_len.into() == 0 {
+            continue;
+        }
+        match memory.lease(buf.into(), buf_len.into()) {
+            Ok(lease) => leases.push(lease),
+            Err(wasmer::MemoryAccessError::NotPinnable) => return Ok(None),
+            Err(err) => return Err(mem_error_to_wasi(err)),
+        }
+    }
+
+    Ok(Some(leases))
+}
+
 pub(crate) fn write_bytes<T: Write, M: MemorySize>(
     mut write_loc: T,
     memory: &Memory,
@@ -104,11 +212,148 @@ pub(crate) fn write_bytes<T: Write, M: MemorySize>(
     result
 }
 
+/// Runs `f` with the standard stream `fd`, the one of the view of `env`
+/// if it has its own, or else the one of the shared state.
+fn with_stdio<T>(
+    env: &WasiEnv,
+    inodes: &crate::WasiInodes,
+    fd: __wasi_fd_t,
+    f: impl FnOnce(&mut Box<dyn VirtualFile + Send + Sync + 'static>) -> Result<T, __wasi_errno_t>,
+) -> Result<T, __wasi_errno_t> {
+    if let Some(view) = env.view() {
+        if let Some(file) = lock_view_stdio(view, fd).as_mut() {
+            return f(file);
+        }
+    }
+
+    let fd_map = &env.state().fs.fd_map;
+    let mut guard = match fd {
+        __WASI_STDIN_FILENO => inodes.stdin_mut(fd_map),
+        __WASI_STDOUT_FILENO => inodes.stdout_mut(fd_map),
+        _ => inodes.stderr_mut(fd_map),
+    }
+    .map_err(fs_error_into_wasi_err)?;
+    match guard.deref_mut() {
+        Some(file) => f(file),
+        None => Err(__WASI_EBADF),
+    }
+}
+
+/// Locks the standard stream `fd` of `view`, which is `None` if the
+/// view doesn't have its own.
+fn lock_view_stdio(
+    view: &WasiStateView,
+    fd: __wasi_fd_t,
+) -> MutexGuard<'_, Option<Box<dyn VirtualFile + Send + Sync + 'static>>> {
+    match fd {
+        __WASI_STDIN_FILENO => view.stdin_mut(),
+        __WASI_STDOUT_FILENO => view.stdout_mut(),
+        _ => view.stderr_mut(),
+    }
+}
+
+/// A file polled by `poll_oneoff`: a standard stream of the view of the
+/// instance, locked once for all its subscriptions, or a file of the
+/// shared state.
+enum PolledFile<'a> {
+    View(__wasi_fd_t),
+    Inode(crate::state::InodeValFileReadGuard<'a>),
+}
+
+/// Whether the standard stream `fd` of `env` is the default one, rather
+/// than one set by the embedder or by the view of the instance.
+fn is_default_stdio(env: &WasiEnv, inodes: &crate::WasiInodes, fd: __wasi_fd_t) -> bool {
+    with_stdio(env, inodes, fd, |file| {
+        let file = (**file).upcast_any_ref();
+        Ok(match fd {
+            __WASI_STDIN_FILENO => file.is::<state::Stdin>(),
+            __WASI_STDOUT_FILENO => file.is::<state::Stdout>(),
+            _ => file.is::<state::Stderr>(),
+        })
+    })
+    .unwrap_or(false)
+}
+
+/// A file whose I/O is driven by the executor of the runtime when the
+/// file is asynchronous, instead of blocking the thread.
+#[cfg(feature = "async")]
+pub(crate) struct DrivenFile<'a> {
+    runtime: &'a dyn crate::WasiRuntimeImplementation,
+    file: &'a mut (dyn VirtualFile + Send + Sync + 'static),
+}
+
+#[cfg(feature = "async")]
+impl<'a> DrivenFile<'a> {
+    pub(crate) fn new(
+        runtime: &'a dyn crate::WasiRuntimeImplementation,
+        file: &'a mut (dyn VirtualFile + Send + Sync + 'static),
+    ) -> Self {
+        Self { runtime, file }
+    }
+}
+
+#[cfg(feature = "async")]
+fn block_on<T: Send>(
+    runtime: &dyn crate::WasiRuntimeImplementation,
+    task: impl std::future::Future<Output = T> + Send,
+) -> T {
+    let mut output = None;
+    let output_ref = &mut output;
+    runtime.block_on(Box::pin(async move {
+        *output_ref = Some(task.await);
+    }));
+    output.expect("the runtime didn't run the task to completion")
+}
+
+#[cfg(feature = "async")]
+impl Read for DrivenFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.file.as_async_mut() {
+            Some(file) => block_on(self.runtime, tokio::io::AsyncReadExt::read(file, buf)),
+            None => self.file.read(buf),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Write for DrivenFile<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.file.as_async_mut() {
+            Some(file) => block_on(self.runtime, tokio::io::AsyncWriteExt::write(file, buf)),
+            None => self.file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_async_mut() {
+            Some(file) => block_on(self.runtime, tokio::io::AsyncWriteExt::flush(file)),
+            None => self.file.flush(),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Seek for DrivenFile<'_> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        match self.file.as_async_mut() {
+            Some(file) => block_on(self.runtime, tokio::io::AsyncSeekExt::seek(file, pos)),
+            None => self.file.seek(pos),
+        }
+    }
+}
+
 pub(crate) fn read_bytes<T: Read, M: MemorySize>(
     mut reader: T,
     memory: &Memory,
     iovs_arr: WasmSlice<__wasi_iovec_t<M>>,
 ) -> Result<usize, __wasi_errno_t> {
+    #[cfg(feature = "sys")]
+    {
+        if let Some(bytes_read) = read_bytes_vectored::<_, M>(&mut reader, memory, iovs_arr)? {
+            return Ok(bytes_read);
+        }
+    }
+
     let mut bytes_read = 0usize;
 
     // We allocate the raw_bytes first once instead of
@@ -117,27 +362,94 @@ pub(crate) fn read_bytes<T: Read, M: MemorySize>(
 
     for iov in iovs_arr.iter() {
         let iov_inner = iov.read().map_err(mem_error_to_wasi)?;
+        let buf_len = from_offset::<M>(iov_inner.buf_len)?;
         raw_bytes.clear();
-        raw_bytes.resize(from_offset::<M>(iov_inner.buf_len)?, 0);
-        bytes_read += reader.read(&mut raw_bytes).map_err(map_io_err)?;
+        raw_bytes.resize(buf_len, 0);
+        let read = reader.read(&mut raw_bytes).map_err(map_io_err)?;
+        bytes_read = add_buf_len(bytes_read, read)?;
 
         let buf = WasmPtr::<u8, M>::new(iov_inner.buf)
             .slice(memory, iov_inner.buf_len)
             .map_err(mem_error_to_wasi)?;
-        buf.write_slice(&raw_bytes).map_err(mem_error_to_wasi)?;
+        buf.subslice(0..read as u64)
+            .write_slice(&raw_bytes[..read])
+            .map_err(mem_error_to_wasi)?;
+
+        // Like `readv`, a short read leaves the next buffers alone
+        if read < buf_len {
+            break;
+        }
     }
     Ok(bytes_read)
 }
 
-/// checks that `rights_check_set` is a subset of `rights_set`
-fn has_rights(rights_set: __wasi_rights_t, rights_check_set: __wasi_rights_t) -> bool {
-    rights_set | rights_check_set == rights_set
+/// Reads straight into the guest memory with `read_vectored`, instead
+/// of copying the buffers one by one. Returns `None` if the buffers
+/// overlap, as they can't be borrowed mutably together, or if they
+/// can't be leased, see [`lease_guest_buffers`].
+#[cfg(feature = "sys")]
+fn read_bytes_vectored<T: Read, M: MemorySize>(
+    mut reader: T,
+    memory: &Memory,
+    iovs_arr: WasmSlice<__wasi_iovec_t<M>>,
+) -> Result<Option<usize>, __wasi_errno_t> {
+    let iovs = iovs_arr.read_to_vec().map_err(mem_error_to_wasi)?;
+    let mut ranges = Vec::with_capacity(iovs.len());
+    for iov in iovs.iter() {
+        let start: u64 = iov.buf.into();
+        let end = start
+            .checked_add(iov.buf_len.into())
+            .ok_or_else(|| mem_error_to_wasi(wasmer::MemoryAccessError::Overflow))?;
+        if end > start {
+            ranges.push(start..end);
+        }
+    }
+    ranges.sort_unstable_by_key(|range| range.start);
+    if ranges.windows(2).any(|pair| pair[0].end > pair[1].start) {
+        return Ok(None);
+    }
+
+    let mut leases =
+        match lease_guest_buffers::<M>(memory, iovs.iter().map(|iov| (iov.buf, iov.buf_len)))? {
+            Some(leases) => leases,
+            None => return Ok(None),
+        };
+    // The leases keep the memory in place, nothing else runs on it, and
+    // they don't overlap.
+    let mut slices = leases
+        .iter_mut()
+        .map(|lease| io::IoSliceMut::new(unsafe { lease.as_mut_slice() }))
+        .collect::<Vec<_>>();
+
+    // Readers may only read into the first buffer, so like when the
+    // buffers are read one by one, the reads go on until one is short.
+    let mut bytes_read = 0;
+    let mut first = 0;
+    while first < slices.len() {
+        let mut read = match reader.read_vectored(&mut slices[first..]) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(map_io_err(err)),
+        };
+        bytes_read = add_buf_len(bytes_read, read)?;
+        while first < slices.len() && read >= slices[first].len() {
+            read -= slices[first].len();
+            first += 1;
+        }
+        if read > 0 {
+            break;
+        }
+    }
+
+    Ok(Some(bytes_read))
 }
 
 fn __sock_actor<T, F>(
     env: &WasiEnv,
     sock: __wasi_fd_t,
     rights: __wasi_rights_t,
+    syscall: &'static str,
     actor: F,
 ) -> Result<T, __wasi_errno_t>
 where
@@ -147,7 +459,7 @@ where
 
     let fd_entry = state.fs.get_fd(sock)?;
     let ret = {
-        if rights != 0 && !has_rights(fd_entry.rights, rights) {
+        if rights != 0 && !state.fs.has_rights(sock, fd_entry.rights, rights, syscall) {
             return Err(__WASI_EACCES);
         }
 
@@ -170,6 +482,7 @@ fn __sock_actor_mut<T, F>(
     env: &WasiEnv,
     sock: __wasi_fd_t,
     rights: __wasi_rights_t,
+    syscall: &'static str,
     actor: F,
 ) -> Result<T, __wasi_errno_t>
 where
@@ -179,7 +492,7 @@ where
 
     let fd_entry = state.fs.get_fd(sock)?;
     let ret = {
-        if rights != 0 && !has_rights(fd_entry.rights, rights) {
+        if rights != 0 && !state.fs.has_rights(sock, fd_entry.rights, rights, syscall) {
             return Err(__WASI_EACCES);
         }
 
@@ -202,6 +515,7 @@ fn __sock_upgrade<F>(
     env: &WasiEnv,
     sock: __wasi_fd_t,
     rights: __wasi_rights_t,
+    syscall: &'static str,
     actor: F,
 ) -> Result<(), __wasi_errno_t>
 where
@@ -212,7 +526,7 @@ where
     let (_, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
 
     let fd_entry = state.fs.get_fd(sock)?;
-    if rights != 0 && !has_rights(fd_entry.rights, rights) {
+    if rights != 0 && !state.fs.has_rights(sock, fd_entry.rights, rights, syscall) {
         return Err(__WASI_EACCES);
     }
 
@@ -290,6 +604,7 @@ pub fn args_get<M: MemorySize>(
     argv_buf: WasmPtr<u8, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::args_get");
+    env.record_syscall("args_get");
     let (memory, state) = env.get_memory_and_wasi_state(0);
 
     let result = write_buffer_array(memory, &*state.args, argv, argv_buf);
@@ -321,6 +636,7 @@ pub fn args_sizes_get<M: MemorySize>(
     argv_buf_size: WasmPtr<M::Offset, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::args_sizes_get");
+    env.record_syscall("args_sizes_get");
     let (memory, state) = env.get_memory_and_wasi_state(0);
 
     let argc = argc.deref(memory);
@@ -352,6 +668,7 @@ pub fn clock_res_get<M: MemorySize>(
     resolution: WasmPtr<__wasi_timestamp_t, M>,
 ) -> __wasi_errno_t {
     trace!("wasi::clock_res_get");
+    env.record_syscall("clock_res_get");
     let memory = env.memory();
 
     let out_addr = resolution.deref(memory);
@@ -380,9 +697,10 @@ pub fn clock_time_get<M: MemorySize>(
         "wasi::clock_time_get clock_id: {}, precision: {}",
         clock_id, precision
     );
+    env.record_syscall("clock_time_get");
     let memory = env.memory();
 
-    let t_out = wasi_try!(platform_clock_time_get(clock_id, precision));
+    let t_out = wasi_try!(env.runtime.clock_time_get(clock_id, precision));
     wasi_try_mem!(time.write(memory, t_out as __wasi_timestamp_t));
 
     let result = __WASI_ESUCCESS;
@@ -411,6 +729,7 @@ pub fn environ_get<M: MemorySize>(
         "wasi::environ_get. Environ: {:?}, environ_buf: {:?}",
         environ, environ_buf
     );
+    env.record_syscall("environ_get");
     let (memory, state) = env.get_memory_and_wasi_state(0);
     trace!(" -> State envs: {:?}", state.envs);
 
@@ -430,6 +749,7 @@ pub fn environ_sizes_get<M: MemorySize>(
     environ_buf_size: WasmPtr<M::Offset, M>,
 ) -> __wasi_errno_t {
     trace!("wasi::environ_sizes_get");
+    env.record_syscall("environ_sizes_get");
     let (memory, state) = env.get_memory_and_wasi_state(0);
 
     let environ_count = environ_count.deref(memory);
@@ -470,6 +790,7 @@ pub fn fd_advise(
     advice: __wasi_advice_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_advise: fd={}", fd);
+    env.record_syscall("fd_advise");
 
     // this is used for our own benefit, so just returning success is a valid
     // implementation for now
@@ -492,11 +813,16 @@ pub fn fd_allocate(
     len: __wasi_filesize_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_allocate");
+    env.record_syscall("fd_allocate");
+    wasi_try!(env.inject_fault("fd_allocate"));
     let (_, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     let inode = fd_entry.inode;
 
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_ALLOCATE) {
+    if !state
+        .fs
+        .has_rights(fd, fd_entry.rights, __WASI_RIGHT_FD_ALLOCATE, "fd_allocate")
+    {
         return __WASI_EACCES;
     }
     let new_size = wasi_try!(offset.checked_add(len).ok_or(__WASI_EINVAL));
@@ -538,6 +864,8 @@ pub fn fd_allocate(
 ///     If `fd` is invalid or not open
 pub fn fd_close(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_close: fd={}", fd);
+    env.record_syscall("fd_close");
+    wasi_try!(env.inject_fault("fd_close"));
     let (_, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
 
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
@@ -554,13 +882,24 @@ pub fn fd_close(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
 ///     The file descriptor to sync
 pub fn fd_datasync(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_datasync");
+    env.record_syscall("fd_datasync");
+    wasi_try!(env.inject_fault("fd_datasync"));
     let (_, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_DATASYNC) {
+    if !state
+        .fs
+        .has_rights(fd, fd_entry.rights, __WASI_RIGHT_FD_DATASYNC, "fd_datasync")
+    {
         return __WASI_EACCES;
     }
 
-    if let Err(e) = state.fs.flush(inodes.deref(), fd) {
+    let result = match fd {
+        __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => {
+            with_stdio(env, &inodes, fd, |file| file.flush().map_err(map_io_err))
+        }
+        _ => state.fs.flush(inodes.deref(), fd),
+    };
+    if let Err(e) = result {
         e
     } else {
         __WASI_ESUCCESS
@@ -585,6 +924,7 @@ pub fn fd_fdstat_get<M: MemorySize>(
         fd,
         buf_ptr.offset()
     );
+    env.record_syscall("fd_fdstat_get");
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let stat = wasi_try!(state.fs.fdstat(inodes.deref(), fd));
 
@@ -606,11 +946,17 @@ pub fn fd_fdstat_set_flags(
     flags: __wasi_fdflags_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_fdstat_set_flags");
+    env.record_syscall("fd_fdstat_set_flags");
     let (_, state) = env.get_memory_and_wasi_state(0);
     let mut fd_map = state.fs.fd_map.write().unwrap();
     let fd_entry = wasi_try!(fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
 
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FDSTAT_SET_FLAGS) {
+    if !state.fs.has_rights(
+        fd,
+        fd_entry.rights,
+        __WASI_RIGHT_FD_FDSTAT_SET_FLAGS,
+        "fd_fdstat_set_flags",
+    ) {
         return __WASI_EACCES;
     }
 
@@ -634,6 +980,7 @@ pub fn fd_fdstat_set_rights(
     fs_rights_inheriting: __wasi_rights_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_fdstat_set_rights");
+    env.record_syscall("fd_fdstat_set_rights");
     let (_, state) = env.get_memory_and_wasi_state(0);
     let mut fd_map = state.fs.fd_map.write().unwrap();
     let fd_entry = wasi_try!(fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
@@ -665,9 +1012,15 @@ pub fn fd_filestat_get<M: MemorySize>(
     buf: WasmPtr<__wasi_filestat_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_get");
+    env.record_syscall("fd_filestat_get");
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FILESTAT_GET) {
+    if !state.fs.has_rights(
+        fd,
+        fd_entry.rights,
+        __WASI_RIGHT_FD_FILESTAT_GET,
+        "fd_filestat_get",
+    ) {
         return __WASI_EACCES;
     }
 
@@ -691,11 +1044,17 @@ pub fn fd_filestat_set_size(
     st_size: __wasi_filesize_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_set_size");
+    env.record_syscall("fd_filestat_set_size");
     let (_, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     let inode = fd_entry.inode;
 
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FILESTAT_SET_SIZE) {
+    if !state.fs.has_rights(
+        fd,
+        fd_entry.rights,
+        __WASI_RIGHT_FD_FILESTAT_SET_SIZE,
+        "fd_filestat_set_size",
+    ) {
         return __WASI_EACCES;
     }
 
@@ -741,10 +1100,16 @@ pub fn fd_filestat_set_times(
     fst_flags: __wasi_fstflags_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_set_times");
+    env.record_syscall("fd_filestat_set_times");
     let (_, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
 
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FILESTAT_SET_TIMES) {
+    if !state.fs.has_rights(
+        fd,
+        fd_entry.rights,
+        __WASI_RIGHT_FD_FILESTAT_SET_TIMES,
+        "fd_filestat_set_times",
+    ) {
         return __WASI_EACCES;
     }
 
@@ -803,6 +1168,8 @@ pub fn fd_pread<M: MemorySize>(
     nread: WasmPtr<M::Offset, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     trace!("wasi::fd_pread: fd={}, offset={}", fd, offset);
+    env.record_syscall("fd_pread");
+    wasi_try_ok!(env.inject_fault("fd_pread"));
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
 
     let iovs = wasi_try_mem_ok!(iovs.slice(memory, iovs_len));
@@ -810,27 +1177,21 @@ pub fn fd_pread<M: MemorySize>(
 
     let fd_entry = wasi_try_ok!(state.fs.get_fd(fd));
     let bytes_read = match fd {
-        __WASI_STDIN_FILENO => {
-            let mut guard = wasi_try_ok!(
-                inodes
-                    .stdin_mut(&state.fs.fd_map)
-                    .map_err(fs_error_into_wasi_err),
-                env
-            );
-            if let Some(ref mut stdin) = guard.deref_mut() {
-                wasi_try_ok!(read_bytes(stdin, memory, iovs), env)
-            } else {
-                return Ok(__WASI_EBADF);
-            }
-        }
+        __WASI_STDIN_FILENO => wasi_try_ok!(
+            with_stdio(env, &inodes, fd, |stdin| read_bytes(stdin, memory, iovs)),
+            env
+        ),
         __WASI_STDOUT_FILENO => return Ok(__WASI_EINVAL),
         __WASI_STDERR_FILENO => return Ok(__WASI_EINVAL),
         _ => {
             let inode = fd_entry.inode;
 
-            if !(has_rights(fd_entry.rights, __WASI_RIGHT_FD_READ)
-                && has_rights(fd_entry.rights, __WASI_RIGHT_FD_SEEK))
-            {
+            if !state.fs.has_rights(
+                fd,
+                fd_entry.rights,
+                __WASI_RIGHT_FD_READ | __WASI_RIGHT_FD_SEEK,
+                "fd_pread",
+            ) {
                 debug!(
                     "Invalid rights on {:X}: expected READ and SEEK",
                     fd_entry.rights
@@ -867,6 +1228,7 @@ pub fn fd_pread<M: MemorySize>(
         }
     };
 
+    env.record_read(bytes_read);
     let bytes_read: M::Offset = wasi_try_ok!(bytes_read.try_into().map_err(|_| __WASI_EOVERFLOW));
     wasi_try_mem_ok!(nread_ref.write(bytes_read));
     debug!("Success: {} bytes read", bytes_read);
@@ -887,6 +1249,7 @@ pub fn fd_prestat_get<M: MemorySize>(
     buf: WasmPtr<__wasi_prestat_t, M>,
 ) -> __wasi_errno_t {
     trace!("wasi::fd_prestat_get: fd={}", fd);
+    env.record_syscall("fd_prestat_get");
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
 
     wasi_try_mem!(buf.write(memory, wasi_try!(state.fs.prestat_fd(inodes.deref(), fd))));
@@ -905,6 +1268,7 @@ pub fn fd_prestat_dir_name<M: MemorySize>(
         fd,
         path_len
     );
+    env.record_syscall("fd_prestat_dir_name");
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let path_chars = wasi_try_mem!(path.slice(memory, path_len));
 
@@ -964,6 +1328,8 @@ pub fn fd_pwrite<M: MemorySize>(
     nwritten: WasmPtr<M::Offset, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     trace!("wasi::fd_pwrite");
+    env.record_syscall("fd_pwrite");
+    wasi_try_ok!(env.inject_fault("fd_pwrite"));
     // TODO: refactor, this is just copied from `fd_write`...
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let iovs_arr = wasi_try_mem_ok!(iovs.slice(memory, iovs_len));
@@ -972,36 +1338,17 @@ pub fn fd_pwrite<M: MemorySize>(
     let fd_entry = wasi_try_ok!(state.fs.get_fd(fd));
     let bytes_written = match fd {
         __WASI_STDIN_FILENO => return Ok(__WASI_EINVAL),
-        __WASI_STDOUT_FILENO => {
-            let mut guard = wasi_try_ok!(
-                inodes
-                    .stdout_mut(&state.fs.fd_map)
-                    .map_err(fs_error_into_wasi_err),
-                env
-            );
-            if let Some(ref mut stdout) = guard.deref_mut() {
-                wasi_try_ok!(write_bytes(stdout, memory, iovs_arr), env)
-            } else {
-                return Ok(__WASI_EBADF);
-            }
-        }
-        __WASI_STDERR_FILENO => {
-            let mut guard = wasi_try_ok!(
-                inodes
-                    .stderr_mut(&state.fs.fd_map)
-                    .map_err(fs_error_into_wasi_err),
-                env
-            );
-            if let Some(ref mut stderr) = guard.deref_mut() {
-                wasi_try_ok!(write_bytes(stderr, memory, iovs_arr), env)
-            } else {
-                return Ok(__WASI_EBADF);
-            }
-        }
+        __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => wasi_try_ok!(
+            with_stdio(env, &inodes, fd, |file| write_bytes(file, memory, iovs_arr)),
+            env
+        ),
         _ => {
-            if !(has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE)
-                && has_rights(fd_entry.rights, __WASI_RIGHT_FD_SEEK))
-            {
+            if !state.fs.has_rights(
+                fd,
+                fd_entry.rights,
+                __WASI_RIGHT_FD_WRITE | __WASI_RIGHT_FD_SEEK,
+                "fd_pwrite",
+            ) {
                 return Ok(__WASI_EACCES);
             }
 
@@ -1045,6 +1392,7 @@ pub fn fd_pwrite<M: MemorySize>(
         }
     };
 
+    env.record_written(bytes_written);
     let bytes_written: M::Offset =
         wasi_try_ok!(bytes_written.try_into().map_err(|_| __WASI_EOVERFLOW));
     wasi_try_mem_ok!(nwritten_ref.write(bytes_written));
@@ -1073,6 +1421,8 @@ pub fn fd_read<M: MemorySize>(
     nread: WasmPtr<M::Offset, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     trace!("wasi::fd_read: fd={}", fd);
+    env.record_syscall("fd_read");
+    wasi_try_ok!(env.inject_fault("fd_read"));
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
 
     let iovs_arr = wasi_try_mem_ok!(iovs.slice(memory, iovs_len));
@@ -1080,28 +1430,24 @@ pub fn fd_read<M: MemorySize>(
 
     let fd_entry = wasi_try_ok!(state.fs.get_fd(fd));
     let bytes_read = match fd {
-        __WASI_STDIN_FILENO => {
-            let mut guard = wasi_try_ok!(
-                inodes
-                    .stdin_mut(&state.fs.fd_map)
-                    .map_err(fs_error_into_wasi_err),
-                env
-            );
-            if let Some(ref mut stdin) = guard.deref_mut() {
-                wasi_try_ok!(read_bytes(stdin, memory, iovs_arr), env)
-            } else {
-                return Ok(__WASI_EBADF);
-            }
-        }
+        __WASI_STDIN_FILENO => wasi_try_ok!(
+            with_stdio(env, &inodes, fd, |stdin| read_bytes(
+                stdin, memory, iovs_arr
+            )),
+            env
+        ),
         __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => return Ok(__WASI_EINVAL),
         _ => {
-            if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_READ) {
+            if !state
+                .fs
+                .has_rights(fd, fd_entry.rights, __WASI_RIGHT_FD_READ, "fd_read")
+            {
                 // TODO: figure out the error to return when lacking rights
                 return Ok(__WASI_EACCES);
             }
 
             let is_non_blocking = fd_entry.flags & __WASI_FDFLAG_NONBLOCK != 0;
-            let offset = fd_entry.offset as usize;
+            let offset = fd_entry.offset.load(Ordering::Acquire) as usize;
             let inode_idx = fd_entry.inode;
             let inode = &inodes.arena[inode_idx];
 
@@ -1110,6 +1456,8 @@ pub fn fd_read<M: MemorySize>(
                 match guard.deref_mut() {
                     Kind::File { handle, .. } => {
                         if let Some(handle) = handle {
+                            #[cfg(feature = "async")]
+                            let handle = &mut DrivenFile::new(env.runtime(), &mut **handle);
                             wasi_try_ok!(
                                 handle
                                     .seek(std::io::SeekFrom::Start(offset as u64))
@@ -1196,12 +1544,15 @@ pub fn fd_read<M: MemorySize>(
             // reborrow
             let mut fd_map = state.fs.fd_map.write().unwrap();
             let fd_entry = wasi_try_ok!(fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
-            fd_entry.offset += bytes_read as u64;
+            fd_entry
+                .offset
+                .fetch_add(bytes_read as u64, Ordering::AcqRel);
 
             bytes_read
         }
     };
 
+    env.record_read(bytes_read);
     let bytes_read: M::Offset = wasi_try_ok!(bytes_read.try_into().map_err(|_| __WASI_EOVERFLOW));
     wasi_try_mem_ok!(nread_ref.write(bytes_read));
 
@@ -1232,6 +1583,8 @@ pub fn fd_readdir<M: MemorySize>(
     bufused: WasmPtr<M::Offset, M>,
 ) -> __wasi_errno_t {
     trace!("wasi::fd_readdir");
+    env.record_syscall("fd_readdir");
+    wasi_try!(env.inject_fault("fd_readdir"));
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     // TODO: figure out how this is supposed to work;
     // is it supposed to pack the buffer full every time until it can't? or do one at a time?
@@ -1306,20 +1659,23 @@ pub fn fd_readdir<M: MemorySize>(
         }
     };
 
-    for (entry_path_str, wasi_file_type, ino) in entries.iter().skip(cookie as usize) {
+    // A cookie past the entries, even one which doesn't fit in a `usize`,
+    // reads none of them.
+    let skipped = usize::try_from(cookie).unwrap_or(usize::MAX);
+    for (entry_path_str, wasi_file_type, ino) in entries.iter().skip(skipped) {
         cur_cookie += 1;
         let namlen = entry_path_str.len();
         debug!("Returning dirent for {}", entry_path_str);
         let dirent = __wasi_dirent_t {
             d_next: cur_cookie,
             d_ino: *ino,
-            d_namlen: namlen as u32,
+            d_namlen: wasi_try!(u32::try_from(namlen).map_err(|_| __WASI_EOVERFLOW)),
             d_type: *wasi_file_type,
         };
         let dirent_bytes = dirent_to_le_bytes(&dirent);
         let buf_len: u64 = buf_len.into();
         let upper_limit = std::cmp::min(
-            (buf_len - buf_idx as u64) as usize,
+            remaining_buf_len(buf_len, buf_idx),
             std::mem::size_of::<__wasi_dirent_t>(),
         );
         for (i, b) in dirent_bytes.iter().enumerate().take(upper_limit) {
@@ -1329,7 +1685,7 @@ pub fn fd_readdir<M: MemorySize>(
         if upper_limit != std::mem::size_of::<__wasi_dirent_t>() {
             break;
         }
-        let upper_limit = std::cmp::min((buf_len - buf_idx as u64) as usize, namlen);
+        let upper_limit = std::cmp::min(remaining_buf_len(buf_len, buf_idx), namlen);
         for (i, b) in entry_path_str.bytes().take(upper_limit).enumerate() {
             wasi_try_mem!(buf_arr.index((i + buf_idx) as u64).write(b));
         }
@@ -1353,6 +1709,7 @@ pub fn fd_readdir<M: MemorySize>(
 ///     Location to copy file descriptor to
 pub fn fd_renumber(env: &WasiEnv, from: __wasi_fd_t, to: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_renumber: from={}, to={}", from, to);
+    env.record_syscall("fd_renumber");
     let (_, state) = env.get_memory_and_wasi_state(0);
 
     let mut fd_map = state.fs.fd_map.write().unwrap();
@@ -1361,7 +1718,7 @@ pub fn fd_renumber(env: &WasiEnv, from: __wasi_fd_t, to: __wasi_fd_t) -> __wasi_
     let new_fd_entry = Fd {
         // TODO: verify this is correct
         rights: fd_entry.rights_inheriting,
-        ..*fd_entry
+        ..fd_entry.clone()
     };
 
     fd_map.insert(to, new_fd_entry);
@@ -1383,6 +1740,7 @@ pub fn fd_dup<M: MemorySize>(
     ret_fd: WasmPtr<__wasi_fd_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_dup");
+    env.record_syscall("fd_dup");
 
     let (memory, state) = env.get_memory_and_wasi_state(0);
     let fd = wasi_try!(state.fs.clone_fd(fd));
@@ -1401,6 +1759,7 @@ pub fn fd_event<M: MemorySize>(
     ret_fd: WasmPtr<__wasi_fd_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_event");
+    env.record_syscall("fd_event");
 
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
 
@@ -1444,11 +1803,16 @@ pub fn fd_seek<M: MemorySize>(
     newoffset: WasmPtr<__wasi_filesize_t, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     trace!("wasi::fd_seek: fd={}, offset={}", fd, offset);
+    env.record_syscall("fd_seek");
+    wasi_try_ok!(env.inject_fault("fd_seek"));
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let new_offset_ref = newoffset.deref(memory);
     let fd_entry = wasi_try_ok!(state.fs.get_fd(fd));
 
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_SEEK) {
+    if !state
+        .fs
+        .has_rights(fd, fd_entry.rights, __WASI_RIGHT_FD_SEEK, "fd_seek")
+    {
         return Ok(__WASI_EACCES);
     }
 
@@ -1457,7 +1821,10 @@ pub fn fd_seek<M: MemorySize>(
         __WASI_WHENCE_CUR => {
             let mut fd_map = state.fs.fd_map.write().unwrap();
             let fd_entry = wasi_try_ok!(fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
-            fd_entry.offset = (fd_entry.offset as i64 + offset) as u64
+            let current = fd_entry.offset.load(Ordering::Acquire);
+            fd_entry
+                .offset
+                .store((current as i64 + offset) as u64, Ordering::Release);
         }
         __WASI_WHENCE_END => {
             use std::io::SeekFrom;
@@ -1473,7 +1840,9 @@ pub fn fd_seek<M: MemorySize>(
                         drop(guard);
                         let mut fd_map = state.fs.fd_map.write().unwrap();
                         let fd_entry = wasi_try_ok!(fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
-                        fd_entry.offset = (end as i64 + offset) as u64;
+                        fd_entry
+                            .offset
+                            .store((end as i64 + offset) as u64, Ordering::Release);
                     } else {
                         return Ok(__WASI_EINVAL);
                     }
@@ -1499,13 +1868,13 @@ pub fn fd_seek<M: MemorySize>(
         __WASI_WHENCE_SET => {
             let mut fd_map = state.fs.fd_map.write().unwrap();
             let fd_entry = wasi_try_ok!(fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
-            fd_entry.offset = offset as u64
+            fd_entry.offset.store(offset as u64, Ordering::Release);
         }
         _ => return Ok(__WASI_EINVAL),
     }
     // reborrow
     let fd_entry = wasi_try_ok!(state.fs.get_fd(fd));
-    wasi_try_mem_ok!(new_offset_ref.write(fd_entry.offset));
+    wasi_try_mem_ok!(new_offset_ref.write(fd_entry.offset.load(Ordering::Acquire)));
 
     Ok(__WASI_ESUCCESS)
 }
@@ -1521,10 +1890,15 @@ pub fn fd_seek<M: MemorySize>(
 /// - `__WASI_ENOTCAPABLE`
 pub fn fd_sync(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_sync");
+    env.record_syscall("fd_sync");
+    wasi_try!(env.inject_fault("fd_sync"));
     debug!("=> fd={}", fd);
     let (_, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_SYNC) {
+    if !state
+        .fs
+        .has_rights(fd, fd_entry.rights, __WASI_RIGHT_FD_SYNC, "fd_sync")
+    {
         return __WASI_EACCES;
     }
     let inode = fd_entry.inode;
@@ -1566,16 +1940,20 @@ pub fn fd_tell<M: MemorySize>(
     offset: WasmPtr<__wasi_filesize_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_tell");
+    env.record_syscall("fd_tell");
     let (memory, state) = env.get_memory_and_wasi_state(0);
     let offset_ref = offset.deref(memory);
 
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
 
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_TELL) {
+    if !state
+        .fs
+        .has_rights(fd, fd_entry.rights, __WASI_RIGHT_FD_TELL, "fd_tell")
+    {
         return __WASI_EACCES;
     }
 
-    wasi_try_mem!(offset_ref.write(fd_entry.offset));
+    wasi_try_mem!(offset_ref.write(fd_entry.offset.load(Ordering::Acquire)));
 
     __WASI_ESUCCESS
 }
@@ -1602,6 +1980,8 @@ pub fn fd_write<M: MemorySize>(
     nwritten: WasmPtr<M::Offset, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     trace!("wasi::fd_write: fd={}", fd);
+    env.record_syscall("fd_write");
+    wasi_try_ok!(env.inject_fault("fd_write"));
     let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
     let iovs_arr = wasi_try_mem_ok!(iovs.slice(memory, iovs_len));
     let nwritten_ref = nwritten.deref(memory);
@@ -1609,38 +1989,19 @@ pub fn fd_write<M: MemorySize>(
     let fd_entry = wasi_try_ok!(state.fs.get_fd(fd));
     let bytes_written = match fd {
         __WASI_STDIN_FILENO => return Ok(__WASI_EINVAL),
-        __WASI_STDOUT_FILENO => {
-            let mut guard = wasi_try_ok!(
-                inodes
-                    .stdout_mut(&state.fs.fd_map)
-                    .map_err(fs_error_into_wasi_err),
-                env
-            );
-            if let Some(ref mut stdout) = guard.deref_mut() {
-                wasi_try_ok!(write_bytes(stdout, memory, iovs_arr), env)
-            } else {
-                return Ok(__WASI_EBADF);
-            }
-        }
-        __WASI_STDERR_FILENO => {
-            let mut guard = wasi_try_ok!(
-                inodes
-                    .stderr_mut(&state.fs.fd_map)
-                    .map_err(fs_error_into_wasi_err),
-                env
-            );
-            if let Some(ref mut stderr) = guard.deref_mut() {
-                wasi_try_ok!(write_bytes(stderr, memory, iovs_arr), env)
-            } else {
-                return Ok(__WASI_EBADF);
-            }
-        }
+        __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => wasi_try_ok!(
+            with_stdio(env, &inodes, fd, |file| write_bytes(file, memory, iovs_arr)),
+            env
+        ),
         _ => {
-            if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE) {
+            if !state
+                .fs
+                .has_rights(fd, fd_entry.rights, __WASI_RIGHT_FD_WRITE, "fd_write")
+            {
                 return Ok(__WASI_EACCES);
             }
 
-            let offset = fd_entry.offset as usize;
+            let offset = fd_entry.offset.load(Ordering::Acquire) as usize;
             let inode_idx = fd_entry.inode;
             let inode = &inodes.arena[inode_idx];
 
@@ -1649,6 +2010,8 @@ pub fn fd_write<M: MemorySize>(
                 match guard.deref_mut() {
                     Kind::File { handle, .. } => {
                         if let Some(handle) = handle {
+                            #[cfg(feature = "async")]
+                            let handle = &mut DrivenFile::new(env.runtime(), &mut **handle);
                             wasi_try_ok!(
                                 handle
                                     .seek(std::io::SeekFrom::Start(offset as u64))
@@ -1703,7 +2066,9 @@ pub fn fd_write<M: MemorySize>(
             {
                 let mut fd_map = state.fs.fd_map.write().unwrap();
                 let fd_entry = wasi_try_ok!(fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
-                fd_entry.offset += bytes_written as u64;
+                fd_entry
+                    .offset
+                    .fetch_add(bytes_written as u64, Ordering::AcqRel);
             }
             wasi_try_ok!(state.fs.filestat_resync_size(inodes.deref(), fd), env);
 
@@ -1711,6 +2076,7 @@ pub fn fd_write<M: MemorySize>(
         }
     };
 
+    env.record_written(bytes_written);
     let bytes_written: M::Offset =
         wasi_try_ok!(bytes_written.try_into().map_err(|_| __WASI_EOVERFLOW));
     wasi_try_mem_ok!(nwritten_ref.write(bytes_written));
@@ -1731,6 +2097,7 @@ pub fn fd_pipe<M: MemorySize>(
     ro_fd2: WasmPtr<__wasi_fd_t, M>,
 ) -> __wasi_errno_t {
     trace!("wasi::fd_pipe");
+    env.record_syscall("fd_pipe");
 
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
 
@@ -1779,7 +2146,10 @@ pub fn path_create_directory<M: MemorySize>(
     path_len: M::Offset,
 ) -> __wasi_errno_t {
     debug!("wasi::path_create_directory");
+    env.record_syscall("path_create_directory");
+    wasi_try!(env.inject_fault("path_create_directory"));
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
 
     let working_dir = wasi_try!(state.fs.get_fd(fd));
     {
@@ -1788,7 +2158,12 @@ pub fn path_create_directory<M: MemorySize>(
             return __WASI_EACCES;
         }
     }
-    if !has_rights(working_dir.rights, __WASI_RIGHT_PATH_CREATE_DIRECTORY) {
+    if !state.fs.has_rights(
+        fd,
+        working_dir.rights,
+        __WASI_RIGHT_PATH_CREATE_DIRECTORY,
+        "path_create_directory",
+    ) {
         return __WASI_EACCES;
     }
     let path_string = unsafe { get_input_str!(memory, path, path_len) };
@@ -1843,6 +2218,7 @@ pub fn path_create_directory<M: MemorySize>(
                         state,
                         inodes.deref_mut(),
                         fd,
+                        current_dir.as_deref(),
                         0,
                         &adjusted_path.to_string_lossy(),
                     ) {
@@ -1908,7 +2284,9 @@ pub fn path_filestat_get<M: MemorySize>(
     buf: WasmPtr<__wasi_filestat_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::path_filestat_get (fd={})", fd);
+    env.record_syscall("path_filestat_get");
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
 
     let path_string = unsafe { get_input_str!(memory, path, path_len) };
 
@@ -1917,6 +2295,7 @@ pub fn path_filestat_get<M: MemorySize>(
         state,
         inodes.deref_mut(),
         fd,
+        current_dir.as_deref(),
         flags,
         &path_string
     ));
@@ -1945,12 +2324,18 @@ pub fn path_filestat_get_internal(
     state: &WasiState,
     inodes: &mut crate::WasiInodes,
     fd: __wasi_fd_t,
+    current_dir: Option<&str>,
     flags: __wasi_lookupflags_t,
     path_string: &str,
 ) -> Result<__wasi_filestat_t, __wasi_errno_t> {
     let root_dir = state.fs.get_fd(fd)?;
 
-    if !has_rights(root_dir.rights, __WASI_RIGHT_PATH_FILESTAT_GET) {
+    if !state.fs.has_rights(
+        fd,
+        root_dir.rights,
+        __WASI_RIGHT_PATH_FILESTAT_GET,
+        "path_filestat_get",
+    ) {
         return Err(__WASI_EACCES);
     }
     debug!("=> base_fd: {}, path: {}", fd, path_string);
@@ -1958,6 +2343,7 @@ pub fn path_filestat_get_internal(
     let file_inode = state.fs.get_inode_at_path(
         inodes,
         fd,
+        current_dir,
         path_string,
         flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     )?;
@@ -1997,10 +2383,17 @@ pub fn path_filestat_set_times<M: MemorySize>(
     fst_flags: __wasi_fstflags_t,
 ) -> __wasi_errno_t {
     debug!("wasi::path_filestat_set_times");
+    env.record_syscall("path_filestat_set_times");
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     let fd_inode = fd_entry.inode;
-    if !has_rights(fd_entry.rights, __WASI_RIGHT_PATH_FILESTAT_SET_TIMES) {
+    if !state.fs.has_rights(
+        fd,
+        fd_entry.rights,
+        __WASI_RIGHT_PATH_FILESTAT_SET_TIMES,
+        "path_filestat_set_times",
+    ) {
         return __WASI_EACCES;
     }
     if (fst_flags & __WASI_FILESTAT_SET_ATIM != 0 && fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0)
@@ -2016,6 +2409,7 @@ pub fn path_filestat_set_times<M: MemorySize>(
     let file_inode = wasi_try!(state.fs.get_inode_at_path(
         inodes.deref_mut(),
         fd,
+        current_dir.as_deref(),
         &path_string,
         flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     ));
@@ -2074,10 +2468,12 @@ pub fn path_link<M: MemorySize>(
     new_path_len: M::Offset,
 ) -> __wasi_errno_t {
     debug!("wasi::path_link");
+    env.record_syscall("path_link");
     if old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         debug!("  - will follow symlinks when opening path");
     }
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
     let old_path_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
     let new_path_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
     let source_fd = wasi_try!(state.fs.get_fd(old_fd));
@@ -2087,15 +2483,24 @@ pub fn path_link<M: MemorySize>(
         old_fd, &old_path_str, new_fd, new_path_str
     );
 
-    if !(has_rights(source_fd.rights, __WASI_RIGHT_PATH_LINK_SOURCE)
-        && has_rights(target_fd.rights, __WASI_RIGHT_PATH_LINK_TARGET))
-    {
+    if !(state.fs.has_rights(
+        old_fd,
+        source_fd.rights,
+        __WASI_RIGHT_PATH_LINK_SOURCE,
+        "path_link",
+    ) && state.fs.has_rights(
+        new_fd,
+        target_fd.rights,
+        __WASI_RIGHT_PATH_LINK_TARGET,
+        "path_link",
+    )) {
         return __WASI_EACCES;
     }
 
     let source_inode = wasi_try!(state.fs.get_inode_at_path(
         inodes.deref_mut(),
         old_fd,
+        current_dir.as_deref(),
         &old_path_str,
         old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     ));
@@ -2103,6 +2508,7 @@ pub fn path_link<M: MemorySize>(
     let (target_parent_inode, new_entry_name) = wasi_try!(state.fs.get_parent_inode_at_path(
         inodes.deref_mut(),
         new_fd,
+        current_dir.as_deref(),
         &target_path_arg,
         false
     ));
@@ -2171,10 +2577,13 @@ pub fn path_open<M: MemorySize>(
     fd: WasmPtr<__wasi_fd_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::path_open");
+    env.record_syscall("path_open");
+    wasi_try!(env.inject_fault("path_open"));
     if dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         debug!("  - will follow symlinks when opening path");
     }
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
     /* TODO: find actual upper bound on name size (also this is a path, not a name :think-fish:) */
     let path_len64: u64 = path_len.into();
     if path_len64 > 1024u64 * 1024u64 {
@@ -2193,7 +2602,12 @@ pub fn path_open<M: MemorySize>(
     let working_dir_rights_inheriting = working_dir.rights_inheriting;
 
     // ASSUMPTION: open rights apply recursively
-    if !has_rights(working_dir.rights, __WASI_RIGHT_PATH_OPEN) {
+    if !state.fs.has_rights(
+        dirfd,
+        working_dir.rights,
+        __WASI_RIGHT_PATH_OPEN,
+        "path_open",
+    ) {
         return __WASI_EACCES;
     }
     let path_string = unsafe { get_input_str!(memory, path, path_len) };
@@ -2204,6 +2618,7 @@ pub fn path_open<M: MemorySize>(
     let maybe_inode = state.fs.get_inode_at_path(
         inodes.deref_mut(),
         dirfd,
+        current_dir.as_deref(),
         &path_string,
         dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     );
@@ -2299,6 +2714,7 @@ pub fn path_open<M: MemorySize>(
             let (parent_inode, new_entity_name) = wasi_try!(state.fs.get_parent_inode_at_path(
                 inodes.deref_mut(),
                 dirfd,
+                current_dir.as_deref(),
                 &path_arg,
                 dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0
             ));
@@ -2410,16 +2826,27 @@ pub fn path_readlink<M: MemorySize>(
     buf_used: WasmPtr<M::Offset, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::path_readlink");
+    env.record_syscall("path_readlink");
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
 
     let base_dir = wasi_try!(state.fs.get_fd(dir_fd));
-    if !has_rights(base_dir.rights, __WASI_RIGHT_PATH_READLINK) {
+    if !state.fs.has_rights(
+        dir_fd,
+        base_dir.rights,
+        __WASI_RIGHT_PATH_READLINK,
+        "path_readlink",
+    ) {
         return __WASI_EACCES;
     }
     let path_str = unsafe { get_input_str!(memory, path, path_len) };
-    let inode = wasi_try!(state
-        .fs
-        .get_inode_at_path(inodes.deref_mut(), dir_fd, &path_str, false));
+    let inode = wasi_try!(state.fs.get_inode_at_path(
+        inodes.deref_mut(),
+        dir_fd,
+        current_dir.as_deref(),
+        &path_str,
+        false
+    ));
 
     {
         let guard = inodes.arena[inode].read();
@@ -2455,19 +2882,27 @@ pub fn path_remove_directory<M: MemorySize>(
     path: WasmPtr<u8, M>,
     path_len: M::Offset,
 ) -> __wasi_errno_t {
+    wasi_try!(env.inject_fault("path_remove_directory"));
     // TODO check if fd is a dir, ensure it's within sandbox, etc.
     debug!("wasi::path_remove_directory");
+    env.record_syscall("path_remove_directory");
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
 
     let base_dir = wasi_try!(state.fs.get_fd(fd));
     let path_str = unsafe { get_input_str!(memory, path, path_len) };
 
-    let inode = wasi_try!(state
-        .fs
-        .get_inode_at_path(inodes.deref_mut(), fd, &path_str, false));
+    let inode = wasi_try!(state.fs.get_inode_at_path(
+        inodes.deref_mut(),
+        fd,
+        current_dir.as_deref(),
+        &path_str,
+        false
+    ));
     let (parent_inode, childs_name) = wasi_try!(state.fs.get_parent_inode_at_path(
         inodes.deref_mut(),
         fd,
+        current_dir.as_deref(),
         std::path::Path::new(&path_str),
         false
     ));
@@ -2546,7 +2981,10 @@ pub fn path_rename<M: MemorySize>(
         "wasi::path_rename: old_fd = {}, new_fd = {}",
         old_fd, new_fd
     );
+    env.record_syscall("path_rename");
+    wasi_try!(env.inject_fault("path_rename"));
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
     let source_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
     let source_path = std::path::Path::new(&source_str);
     let target_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
@@ -2555,23 +2993,39 @@ pub fn path_rename<M: MemorySize>(
 
     {
         let source_fd = wasi_try!(state.fs.get_fd(old_fd));
-        if !has_rights(source_fd.rights, __WASI_RIGHT_PATH_RENAME_SOURCE) {
+        if !state.fs.has_rights(
+            old_fd,
+            source_fd.rights,
+            __WASI_RIGHT_PATH_RENAME_SOURCE,
+            "path_rename",
+        ) {
             return __WASI_EACCES;
         }
         let target_fd = wasi_try!(state.fs.get_fd(new_fd));
-        if !has_rights(target_fd.rights, __WASI_RIGHT_PATH_RENAME_TARGET) {
+        if !state.fs.has_rights(
+            new_fd,
+            target_fd.rights,
+            __WASI_RIGHT_PATH_RENAME_TARGET,
+            "path_rename",
+        ) {
             return __WASI_EACCES;
         }
     }
 
-    let (source_parent_inode, source_entry_name) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(inodes.deref_mut(), old_fd, source_path, true));
-    let (target_parent_inode, target_entry_name) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(inodes.deref_mut(), new_fd, target_path, true));
+    let (source_parent_inode, source_entry_name) = wasi_try!(state.fs.get_parent_inode_at_path(
+        inodes.deref_mut(),
+        old_fd,
+        current_dir.as_deref(),
+        source_path,
+        true
+    ));
+    let (target_parent_inode, target_entry_name) = wasi_try!(state.fs.get_parent_inode_at_path(
+        inodes.deref_mut(),
+        new_fd,
+        current_dir.as_deref(),
+        target_path,
+        true
+    ));
 
     let host_adjusted_target_path = {
         let guard = inodes.arena[target_parent_inode].read();
@@ -2705,30 +3159,43 @@ pub fn path_symlink<M: MemorySize>(
     new_path_len: M::Offset,
 ) -> __wasi_errno_t {
     debug!("wasi::path_symlink");
+    env.record_syscall("path_symlink");
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
     let old_path_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
     let new_path_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
     let base_fd = wasi_try!(state.fs.get_fd(fd));
-    if !has_rights(base_fd.rights, __WASI_RIGHT_PATH_SYMLINK) {
+    if !state.fs.has_rights(
+        fd,
+        base_fd.rights,
+        __WASI_RIGHT_PATH_SYMLINK,
+        "path_symlink",
+    ) {
         return __WASI_EACCES;
     }
 
     // get the depth of the parent + 1 (UNDER INVESTIGATION HMMMMMMMM THINK FISH ^ THINK FISH)
     let old_path_path = std::path::Path::new(&old_path_str);
-    let (source_inode, _) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(inodes.deref_mut(), fd, old_path_path, true));
+    let (source_inode, _) = wasi_try!(state.fs.get_parent_inode_at_path(
+        inodes.deref_mut(),
+        fd,
+        current_dir.as_deref(),
+        old_path_path,
+        true
+    ));
     let depth = wasi_try!(state
         .fs
         .path_depth_from_fd(inodes.deref(), fd, source_inode))
         - 1;
 
     let new_path_path = std::path::Path::new(&new_path_str);
-    let (target_parent_inode, entry_name) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(inodes.deref_mut(), fd, new_path_path, true));
+    let (target_parent_inode, entry_name) = wasi_try!(state.fs.get_parent_inode_at_path(
+        inodes.deref_mut(),
+        fd,
+        current_dir.as_deref(),
+        new_path_path,
+        true
+    ));
 
     // short circuit if anything is wrong, before we create an inode
     {
@@ -2802,21 +3269,34 @@ pub fn path_unlink_file<M: MemorySize>(
     path_len: M::Offset,
 ) -> __wasi_errno_t {
     debug!("wasi::path_unlink_file");
+    env.record_syscall("path_unlink_file");
+    wasi_try!(env.inject_fault("path_unlink_file"));
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
 
     let base_dir = wasi_try!(state.fs.get_fd(fd));
-    if !has_rights(base_dir.rights, __WASI_RIGHT_PATH_UNLINK_FILE) {
+    if !state.fs.has_rights(
+        fd,
+        base_dir.rights,
+        __WASI_RIGHT_PATH_UNLINK_FILE,
+        "path_unlink_file",
+    ) {
         return __WASI_EACCES;
     }
     let path_str = unsafe { get_input_str!(memory, path, path_len) };
     debug!("Requested file: {}", path_str);
 
-    let inode = wasi_try!(state
-        .fs
-        .get_inode_at_path(inodes.deref_mut(), fd, &path_str, false));
+    let inode = wasi_try!(state.fs.get_inode_at_path(
+        inodes.deref_mut(),
+        fd,
+        current_dir.as_deref(),
+        &path_str,
+        false
+    ));
     let (parent_inode, childs_name) = wasi_try!(state.fs.get_parent_inode_at_path(
         inodes.deref_mut(),
         fd,
+        current_dir.as_deref(),
         std::path::Path::new(&path_str),
         false
     ));
@@ -2893,63 +3373,261 @@ pub fn path_unlink_file<M: MemorySize>(
     __WASI_ESUCCESS
 }
 
-/// ### `poll_oneoff()`
-/// Concurrently poll for a set of events
+/// ### `path_notify()`
+/// Watch a file or a directory for changes, like `inotify` on Linux
+/// The paths of the host file system are only watched with the
+/// `host-fs-notify` feature.
 /// Inputs:
-/// - `const __wasi_subscription_t *in`
-///     The events to subscribe to
-/// - `__wasi_event_t *out`
-///     The events that have occured
-/// - `u32 nsubscriptions`
-///     The number of subscriptions and the number of events
+/// - `__wasi_fd_t fd`
+///     The base file descriptor from which the path is understood
+/// - `const char *path`
+///     Array of UTF-8 bytes representing the path
+/// - `u32 path_len`
+///     The number of bytes in the `path` array
+/// - `__wasi_notifyflags_t mask`
+///     The kinds of changes to report
 /// Output:
-/// - `u32 nevents`
-///     The number of events seen
-pub fn poll_oneoff<M: MemorySize>(
+/// - `__wasi_fd_t *fd`
+///     A file descriptor from which the changes are read, as
+///     `__wasi_notify_event_t` records each followed by the name of
+///     the entry which changed. It's readable in `poll_oneoff` as
+///     soon as a change is waiting. Reads only return whole records,
+///     and a `__WASI_NOTIFY_OVERFLOW` one once too many are waiting.
+pub fn path_notify<M: MemorySize>(
     env: &WasiEnv,
-    in_: WasmPtr<__wasi_subscription_t, M>,
-    out_: WasmPtr<__wasi_event_t, M>,
-    nsubscriptions: M::Offset,
-    nevents: WasmPtr<M::Offset, M>,
-) -> Result<__wasi_errno_t, WasiError> {
-    trace!("wasi::poll_oneoff");
-    trace!("  => nsubscriptions = {}", nsubscriptions);
-    let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
+    fd: __wasi_fd_t,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+    mask: __wasi_notifyflags_t,
+    ret_fd: WasmPtr<__wasi_fd_t, M>,
+) -> __wasi_errno_t {
+    debug!("wasi::path_notify");
+    env.record_syscall("path_notify");
+    wasi_try!(env.inject_fault("path_notify"));
+    let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    let current_dir = env.view_current_dir();
 
-    let subscription_array = wasi_try_mem_ok!(in_.slice(memory, nsubscriptions));
-    let event_array = wasi_try_mem_ok!(out_.slice(memory, nsubscriptions));
-    let mut events_seen: u32 = 0;
-    let out_ptr = nevents.deref(memory);
+    let base_dir = wasi_try!(state.fs.get_fd(fd));
+    if !state.fs.has_rights(
+        fd,
+        base_dir.rights,
+        __WASI_RIGHT_PATH_FILESTAT_GET,
+        "path_notify",
+    ) {
+        return __WASI_EACCES;
+    }
+    let path_str = unsafe { get_input_str!(memory, path, path_len) };
+    debug!("=> watching: {}", path_str);
 
-    let mut fd_guards = vec![];
-    let mut clock_subs = vec![];
-    let mut in_events = vec![];
-    let mut time_to_sleep = Duration::from_millis(5);
+    let inode = wasi_try!(state.fs.get_inode_at_path(
+        inodes.deref_mut(),
+        fd,
+        current_dir.as_deref(),
+        &path_str,
+        true
+    ));
+    let path = {
+        let guard = inodes.arena[inode].read();
+        match guard.deref() {
+            Kind::File { path, .. } | Kind::Dir { path, .. } => path.clone(),
+            _ => return __WASI_EINVAL,
+        }
+    };
 
-    for sub in subscription_array.iter() {
-        let s: WasiSubscription = wasi_try_ok!(wasi_try_mem_ok!(sub.read()).try_into());
-        let mut peb = PollEventBuilder::new();
+    let watch_file =
+        wasi_try!(WatchFile::new(state.fs.fs_backing.as_ref(), &path, mask)
+            .map_err(fs_error_into_wasi_err));
+    let kind = Kind::File {
+        handle: Some(Box::new(watch_file)),
+        path,
+        fd: None,
+    };
+    let inode = state.fs.create_inode_with_default_stat(
+        inodes.deref_mut(),
+        kind,
+        false,
+        "notify".to_string(),
+    );
+    let rights = __WASI_RIGHT_FD_READ | __WASI_RIGHT_POLL_FD_READWRITE;
+    let fd = wasi_try!(state.fs.create_fd(rights, rights, 0, 0, inode));
 
-        let fd = match s.event_type {
-            EventType::Read(__wasi_subscription_fs_readwrite_t { fd }) => {
-                match fd {
-                    __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
-                    _ => {
-                        let fd_entry = wasi_try_ok!(state.fs.get_fd(fd), env);
-                        if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_READ) {
-                            return Ok(__WASI_EACCES);
-                        }
-                    }
-                }
-                in_events.push(peb.add(PollEvent::PollIn).build());
-                Some(fd)
+    wasi_try_mem!(ret_fd.write(memory, fd));
+
+    __WASI_ESUCCESS
+}
+
+/// ### `shm_open()`
+/// Opens the shared memory object `name`, shared with the other
+/// instances, and the host, using the same registry of objects.
+/// A WebAssembly memory can't map the object, which is read and
+/// written through the returned file descriptor instead, e.g. with
+/// `fd_pread` and `fd_pwrite`.
+/// Inputs:
+/// - `const char *name`
+///     The name of the object
+/// - `u32 name_len`
+///     The length of the name
+/// - `__wasi_filesize_t size`
+///     The size of the object if it's created, or `0` to only open an
+///     existing object
+/// Output:
+/// - `__wasi_fd_t *fd`
+///     The file descriptor of the object
+pub fn shm_open<M: MemorySize>(
+    env: &WasiEnv,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+    size: __wasi_filesize_t,
+    ret_fd: WasmPtr<__wasi_fd_t, M>,
+) -> __wasi_errno_t {
+    debug!("wasi::shm_open");
+    env.record_syscall("shm_open");
+    wasi_try!(env.inject_fault("shm_open"));
+    let registry = wasi_try!(env.runtime.shared_memory().ok_or(__WASI_ENOTSUP));
+    let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+
+    let name = unsafe { get_input_str!(memory, name, name_len) };
+    if name.is_empty() || name.contains('/') {
+        return __WASI_EINVAL;
+    }
+    debug!("=> opening: {}", name);
+
+    let object = if size == 0 {
+        wasi_try!(registry.get(&name).ok_or(__WASI_ENOENT))
+    } else {
+        let size: usize = wasi_try!(size.try_into().map_err(|_| __WASI_EFBIG));
+        registry.create(&name, size)
+    };
+
+    let kind = Kind::File {
+        handle: Some(Box::new(SharedMemoryFile::new(object))),
+        path: std::path::PathBuf::from(format!("/dev/shm/{}", name)),
+        fd: None,
+    };
+    let inode = state
+        .fs
+        .create_inode_with_default_stat(inodes.deref_mut(), kind, false, name);
+    let rights = __WASI_RIGHT_FD_READ
+        | __WASI_RIGHT_FD_WRITE
+        | __WASI_RIGHT_FD_SEEK
+        | __WASI_RIGHT_FD_TELL
+        | __WASI_RIGHT_FD_FILESTAT_GET
+        | __WASI_RIGHT_FD_FILESTAT_SET_SIZE
+        | __WASI_RIGHT_POLL_FD_READWRITE;
+    let fd = wasi_try!(state.fs.create_fd(rights, rights, 0, 0, inode));
+
+    wasi_try_mem!(ret_fd.write(memory, fd));
+
+    __WASI_ESUCCESS
+}
+
+/// ### `shm_unlink()`
+/// Removes the name of the shared memory object `name`. The object
+/// lives on until its file descriptors are closed, and `shm_open` can
+/// create a new object with the same name.
+/// Inputs:
+/// - `const char *name`
+///     The name of the object
+/// - `u32 name_len`
+///     The length of the name
+pub fn shm_unlink<M: MemorySize>(
+    env: &WasiEnv,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+) -> __wasi_errno_t {
+    debug!("wasi::shm_unlink");
+    env.record_syscall("shm_unlink");
+    wasi_try!(env.inject_fault("shm_unlink"));
+    let registry = wasi_try!(env.runtime.shared_memory().ok_or(__WASI_ENOTSUP));
+    let memory = env.memory();
+
+    let name = unsafe { get_input_str!(memory, name, name_len) };
+    debug!("=> unlinking: {}", name);
+
+    if !registry.remove(&name) {
+        return __WASI_ENOENT;
+    }
+
+    __WASI_ESUCCESS
+}
+
+/// ### `poll_oneoff()`
+/// Concurrently poll for a set of events
+/// Inputs:
+/// - `const __wasi_subscription_t *in`
+///     The events to subscribe to
+/// - `__wasi_event_t *out`
+///     The events that have occured
+/// - `u32 nsubscriptions`
+///     The number of subscriptions and the number of events
+/// Output:
+/// - `u32 nevents`
+///     The number of events seen
+pub fn poll_oneoff<M: MemorySize>(
+    env: &WasiEnv,
+    in_: WasmPtr<__wasi_subscription_t, M>,
+    out_: WasmPtr<__wasi_event_t, M>,
+    nsubscriptions: M::Offset,
+    nevents: WasmPtr<M::Offset, M>,
+) -> Result<__wasi_errno_t, WasiError> {
+    trace!("wasi::poll_oneoff");
+    env.record_syscall("poll_oneoff");
+    wasi_try_ok!(env.inject_fault("poll_oneoff"));
+    trace!("  => nsubscriptions = {}", nsubscriptions);
+    let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
+
+    let subscription_array = wasi_try_mem_ok!(in_.slice(memory, nsubscriptions));
+    let event_array = wasi_try_mem_ok!(out_.slice(memory, nsubscriptions));
+    let mut events_seen: u32 = 0;
+    let out_ptr = nevents.deref(memory);
+
+    let mut view_stdio = [None, None, None];
+    let mut fd_guards = vec![];
+    let mut clock_subs = vec![];
+    let mut in_events = vec![];
+    // The clocks are the ones of the runtime, which may not be the
+    // clocks of the host, e.g. the virtual clock of the tests.
+    let clock_now = || {
+        env.runtime
+            .clock_time_get(__WASI_CLOCK_MONOTONIC, 1)
+            .map(|now| now as u64)
+    };
+    let now = wasi_try_ok!(clock_now());
+
+    for sub in subscription_array.iter() {
+        let s: WasiSubscription = wasi_try_ok!(wasi_try_mem_ok!(sub.read()).try_into());
+        let mut peb = PollEventBuilder::new();
+
+        let fd = match s.event_type {
+            EventType::Read(__wasi_subscription_fs_readwrite_t { fd }) => {
+                match fd {
+                    __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
+                    _ => {
+                        let fd_entry = wasi_try_ok!(state.fs.get_fd(fd), env);
+                        if !state.fs.has_rights(
+                            fd,
+                            fd_entry.rights,
+                            __WASI_RIGHT_FD_READ,
+                            "poll_oneoff",
+                        ) {
+                            return Ok(__WASI_EACCES);
+                        }
+                    }
+                }
+                in_events.push(peb.add(PollEvent::PollIn).build());
+                Some(fd)
             }
             EventType::Write(__wasi_subscription_fs_readwrite_t { fd }) => {
                 match fd {
                     __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
                     _ => {
                         let fd_entry = wasi_try_ok!(state.fs.get_fd(fd), env);
-                        if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE) {
+                        if !state.fs.has_rights(
+                            fd,
+                            fd_entry.rights,
+                            __WASI_RIGHT_FD_WRITE,
+                            "poll_oneoff",
+                        ) {
                             return Ok(__WASI_EACCES);
                         }
                     }
@@ -2961,10 +3639,19 @@ pub fn poll_oneoff<M: MemorySize>(
                 if clock_info.clock_id == __WASI_CLOCK_REALTIME
                     || clock_info.clock_id == __WASI_CLOCK_MONOTONIC
                 {
-                    // this is a hack
-                    // TODO: do this properly
-                    time_to_sleep = Duration::from_nanos(clock_info.timeout);
-                    clock_subs.push((clock_info, s.user_data));
+                    // The deadline on the monotonic clock, which the
+                    // timers are based on.
+                    let deadline = if clock_info.flags & __WASI_SUBSCRIPTION_CLOCK_ABSTIME == 0 {
+                        now.saturating_add(clock_info.timeout)
+                    } else if clock_info.clock_id == __WASI_CLOCK_MONOTONIC {
+                        clock_info.timeout
+                    } else {
+                        let realtime =
+                            wasi_try_ok!(env.runtime.clock_time_get(__WASI_CLOCK_REALTIME, 1_000))
+                                as u64;
+                        now.saturating_add(clock_info.timeout.saturating_sub(realtime))
+                    };
+                    clock_subs.push((deadline, s.user_data));
                     None
                 } else {
                     unimplemented!("Polling not implemented for clocks yet");
@@ -2973,6 +3660,22 @@ pub fn poll_oneoff<M: MemorySize>(
         };
 
         if let Some(fd) = fd {
+            // The standard streams of the view, if it has its own, are
+            // polled instead of the ones of the shared state.
+            if let Some(view) = env.view().filter(|_| fd <= __WASI_STDERR_FILENO) {
+                let stdio = &mut view_stdio[fd as usize];
+                if stdio.is_none() {
+                    let guard = lock_view_stdio(view, fd);
+                    if guard.is_some() {
+                        *stdio = Some(guard);
+                    }
+                }
+                if stdio.is_some() {
+                    fd_guards.push(PolledFile::View(fd));
+                    continue;
+                }
+            }
+
             let wasi_file_ref = match fd {
                 __WASI_STDERR_FILENO => {
                     wasi_try_ok!(
@@ -3001,7 +3704,12 @@ pub fn poll_oneoff<M: MemorySize>(
                 _ => {
                     let fd_entry = wasi_try_ok!(state.fs.get_fd(fd), env);
                     let inode = fd_entry.inode;
-                    if !has_rights(fd_entry.rights, __WASI_RIGHT_POLL_FD_READWRITE) {
+                    if !state.fs.has_rights(
+                        fd,
+                        fd_entry.rights,
+                        __WASI_RIGHT_POLL_FD_READWRITE,
+                        "poll_oneoff",
+                    ) {
                         return Ok(__WASI_EACCES);
                     }
 
@@ -3030,28 +3738,55 @@ pub fn poll_oneoff<M: MemorySize>(
                     }
                 }
             };
-            fd_guards.push(wasi_file_ref);
+            fd_guards.push(PolledFile::Inode(wasi_file_ref));
         }
     }
 
     let fds = {
         let mut f = vec![];
         for fd in fd_guards.iter() {
-            f.push(wasi_try_ok!(fd.as_ref().ok_or(__WASI_EBADF)).deref());
+            let file = match fd {
+                PolledFile::View(fd) => view_stdio[*fd as usize]
+                    .as_ref()
+                    .and_then(|guard| guard.as_ref()),
+                PolledFile::Inode(guard) => guard.as_ref(),
+            };
+            f.push(wasi_try_ok!(file.ok_or(__WASI_EBADF)).deref());
         }
         f
     };
 
     let mut seen_events = vec![Default::default(); in_events.len()];
 
-    let start = platform_clock_time_get(__WASI_CLOCK_MONOTONIC, 1_000_000).unwrap() as u128;
+    // A single timer for the earliest clock subscription, on the wheel
+    // shared by the threads, and polling for 5ms without any.
+    let deadline = clock_subs.iter().map(|(deadline, _)| *deadline).min();
+    let timeout = match deadline {
+        Some(deadline) => deadline.saturating_sub(now),
+        None => 5_000_000,
+    };
+    let timer = state.timers.insert(monotonic_now().saturating_add(timeout));
+    // The timer runs on the clock of the host, which the clock of the
+    // runtime may lag behind.
+    let deadline_reached = || match deadline {
+        Some(deadline) => clock_now().map_or(true, |now| now >= deadline),
+        None => true,
+    };
+
     let mut triggered = 0;
     while triggered == 0 {
-        let now = platform_clock_time_get(__WASI_CLOCK_MONOTONIC, 1_000_000).unwrap() as u128;
-        let delta = match now.checked_sub(start) {
-            Some(a) => Duration::from_nanos(a as u64),
-            None => Duration::ZERO,
-        };
+        if fds.is_empty() {
+            // Woken up regularly to handle the signals of the thread.
+            if timer.wait_timeout(Duration::from_millis(10)) {
+                if deadline_reached() {
+                    break;
+                }
+                env.sleep(Duration::from_millis(1))?;
+            }
+            env.yield_now()?;
+            continue;
+        }
+
         match poll(
             fds.as_slice(),
             in_events.as_slice(),
@@ -3071,10 +3806,11 @@ pub fn poll_oneoff<M: MemorySize>(
                 return Ok(fs_error_into_wasi_err(err));
             }
         };
-        if delta > time_to_sleep {
+        if timer.is_fired() && deadline_reached() {
             break;
         }
     }
+    drop(timer);
 
     for (i, seen_event) in seen_events.into_iter().enumerate() {
         let mut flags = 0;
@@ -3125,7 +3861,11 @@ pub fn poll_oneoff<M: MemorySize>(
         events_seen += 1;
     }
     if triggered == 0 {
-        for (clock_info, userdata) in clock_subs {
+        let now = wasi_try_ok!(clock_now());
+        for (_, userdata) in clock_subs
+            .into_iter()
+            .filter(|(deadline, _)| *deadline <= now)
+        {
             let event = __wasi_event_t {
                 userdata,
                 error: __WASI_ESUCCESS,
@@ -3157,24 +3897,147 @@ pub fn poll_oneoff<M: MemorySize>(
 ///   Exit code to return to the operating system
 pub fn proc_exit(env: &WasiEnv, code: __wasi_exitcode_t) -> Result<(), WasiError> {
     debug!("wasi::proc_exit, {}", code);
+    env.record_syscall("proc_exit");
     Err(WasiError::Exit(code))
 }
 
 /// ### `proc_raise()`
 /// Send a signal to the process of the calling thread.
 /// Note: This is similar to `raise` in POSIX.
+/// The signal is dispatched to the handler registered in
+/// `WasiState::signals`, or gets its default action.
 /// Inputs:
 /// - `__wasi_signal_t`
 ///   Signal to be raised for this process
-pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
-    debug!("wasi::proc_raise");
-    unimplemented!("wasi::proc_raise")
+pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> Result<__wasi_errno_t, WasiError> {
+    debug!("wasi::proc_raise {}", sig);
+    env.record_syscall("proc_raise");
+    // Like with `kill`, the signal 0 isn't delivered
+    if sig == 0 {
+        return Ok(__WASI_ESUCCESS);
+    }
+    if sig > __WASI_SIGSYS {
+        return Ok(__WASI_EINVAL);
+    }
+
+    match env.state().signals.dispatch(sig) {
+        SignalAction::Terminate => Err(WasiError::Signaled(sig)),
+        SignalAction::Ignore => Ok(__WASI_ESUCCESS),
+    }
+}
+
+/// ### `proc_fork()`
+/// Forks the process of the calling thread. The child runs on its own
+/// thread, with a copy of the memory and of the file descriptors of
+/// the parent. The open files are shared like with `fd_dup`: they are
+/// closed with the last descriptor, of the parent or of the child, and
+/// the reads and writes of either move their offset.
+/// Forking a module without a `_fork_start` export fails with
+/// `__WASI_ENOTSUP`.
+/// Note: A WebAssembly call can't return twice, so unlike `fork` in
+/// POSIX the child doesn't return from `proc_fork`: it calls the
+/// `_fork_start` export of its own instance of the module. It starts
+/// with the mutable globals of the parent, like the stack pointer, and
+/// the start function of the module isn't called again. Its tables have
+/// their initial values.
+/// Inputs:
+/// - `u64 user_data`
+///   Passed to `_fork_start` in the child
+/// Output:
+/// - `__wasi_pid_t *pid`
+///   The ID of the child, which `thread_join` waits for
+pub fn proc_fork<M: MemorySize>(
+    env: &WasiEnv,
+    user_data: u64,
+    ret_pid: WasmPtr<__wasi_pid_t, M>,
+) -> __wasi_errno_t {
+    debug!("wasi::proc_fork");
+    env.record_syscall("proc_fork");
+    wasi_try!(env.inject_fault("proc_fork"));
+    let memory = env.memory();
+
+    // The child is a new instance of the module, which must own its
+    // memory to get a copy of the one of the parent.
+    let module = wasi_try!(env.module.clone().ok_or(__WASI_ENOTSUP));
+    if module.imports().memories().next().is_some() {
+        return __WASI_ENOTSUP;
+    }
+
+    let mut child_env = env.fork();
+    let child_thread = env.new_thread();
+    let pid = child_thread.id;
+    child_env.id = pid;
+
+    let (instance, start) = match fork_instance(&mut child_env, &module, memory) {
+        Ok(forked) => forked,
+        Err(err) => {
+            env.state.threading.lock().unwrap().threads.remove(&pid);
+            return err;
+        }
+    };
+
+    let parent_state = env.state.clone();
+    let spawned = env.runtime.thread_spawn(Box::new(move || {
+        // The instance owns the exports of the child.
+        let _instance = instance;
+
+        match child_env.wait(start.call(user_data)) {
+            Ok(status) => debug!("forked process {:?} ended: {:?}", pid, status),
+            Err(err) => warn!("forked process {:?} failed: {}", pid, err),
+        }
+
+        // Wake up the joiners of the child.
+        let thread = parent_state.threading.lock().unwrap().threads.remove(&pid);
+        if let Some(thread) = thread {
+            thread.exit.lock().unwrap().take();
+        }
+        drop(child_thread);
+    }));
+    if let Err(err) = spawned {
+        env.state.threading.lock().unwrap().threads.remove(&pid);
+        return err.into();
+    }
+
+    let pid: __wasi_pid_t = pid.into();
+    wasi_try_mem!(ret_pid.write(memory, pid));
+
+    __WASI_ESUCCESS
+}
+
+/// Instantiates the module again for a process forked by `proc_fork`,
+/// from a snapshot of the parent, and returns its `_fork_start` export.
+///
+/// The child starts with the memory and the mutable globals of the
+/// parent, like the stack pointer, as they are now, and without
+/// running the start function again. The memory of the parent is
+/// copied once into the snapshot, which the memory of the child maps
+/// copy-on-write on Linux.
+fn fork_instance(
+    child_env: &mut WasiEnv,
+    module: &Module,
+    memory: &Memory,
+) -> Result<(Instance, TypedFunction<u64, ()>), __wasi_errno_t> {
+    let module = module.snapshot_of(memory).ok_or(__WASI_ENOTSUP)?;
+    let imports = child_env
+        .import_object_for_all_wasi_versions(&module)
+        .map_err(|_| __WASI_ENOEXEC)?;
+    let instance = Instance::new(&module, &imports).map_err(|err| {
+        warn!("failed to instantiate the forked process: {}", err);
+        __WASI_ENOEXEC
+    })?;
+    let start = instance
+        .exports
+        .get_native_function::<u64, ()>("_fork_start")
+        .map_err(|_| __WASI_ENOTSUP)?;
+
+    Ok((instance, start))
 }
 
 /// ### `sched_yield()`
 /// Yields execution of the thread
 pub fn sched_yield(env: &WasiEnv) -> Result<__wasi_errno_t, WasiError> {
     trace!("wasi::sched_yield");
+    env.record_syscall("sched_yield");
     env.yield_now()?;
     Ok(__WASI_ESUCCESS)
 }
@@ -3192,6 +4055,7 @@ pub fn random_get<M: MemorySize>(
     buf_len: M::Offset,
 ) -> __wasi_errno_t {
     trace!("wasi::random_get buf_len: {}", buf_len);
+    env.record_syscall("random_get");
     let memory = env.memory();
     let buf_len64: u64 = buf_len.into();
     let mut u8_buffer = vec![0; buf_len64 as usize];
@@ -3213,8 +4077,17 @@ pub fn tty_get<M: MemorySize>(
     tty_state: WasmPtr<__wasi_tty_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::tty_stdin");
+    env.record_syscall("tty_get");
 
-    let state = env.runtime.tty_get();
+    let mut state = env.runtime.tty_get();
+    {
+        // The streams overridden by the embedder, or by the view of the
+        // instance, aren't the terminal of the runtime.
+        let (_, _, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
+        state.stdin_tty &= is_default_stdio(env, &inodes, __WASI_STDIN_FILENO);
+        state.stdout_tty &= is_default_stdio(env, &inodes, __WASI_STDOUT_FILENO);
+        state.stderr_tty &= is_default_stdio(env, &inodes, __WASI_STDERR_FILENO);
+    }
     let state = __wasi_tty_t {
         cols: state.cols,
         rows: state.rows,
@@ -3255,6 +4128,7 @@ pub fn tty_set<M: MemorySize>(
     tty_state: WasmPtr<__wasi_tty_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::tty_stdout");
+    env.record_syscall("tty_set");
 
     let memory = env.memory();
     let state = wasi_try_mem!(tty_state.read(memory));
@@ -3305,11 +4179,13 @@ pub fn getcwd<M: MemorySize>(
     path_len: WasmPtr<M::Offset, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::getpwd");
-    let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
+    env.record_syscall("getcwd");
+    let (memory, state) = env.get_memory_and_wasi_state(0);
 
-    let (_, cur_dir) = wasi_try!(state
-        .fs
-        .get_current_dir(inodes.deref_mut(), crate::VIRTUAL_ROOT_FD,));
+    // `chdir` checked the directory exists.
+    let cur_dir = env
+        .view_current_dir()
+        .unwrap_or_else(|| state.fs.current_dir.lock().unwrap().clone());
 
     let max_path_len = wasi_try_mem!(path_len.read(memory));
     let path_slice = wasi_try_mem!(path.slice(memory, max_path_len));
@@ -3338,18 +4214,38 @@ pub fn getcwd<M: MemorySize>(
 }
 
 /// ### `chdir()`
-/// Sets the current working directory
+/// Sets the current working directory, which must be an existing
+/// directory. A relative path is relative to the current one.
 pub fn chdir<M: MemorySize>(
     env: &WasiEnv,
     path: WasmPtr<u8, M>,
     path_len: M::Offset,
 ) -> __wasi_errno_t {
     debug!("wasi::chdir");
+    env.record_syscall("chdir");
 
     let (memory, state) = env.get_memory_and_wasi_state(0);
     let path = unsafe { get_input_str!(memory, path, path_len) };
 
-    state.fs.set_current_dir(path.as_str());
+    // A relative path is relative to the current directory.
+    let current_dir = env
+        .view_current_dir()
+        .unwrap_or_else(|| state.fs.current_dir.lock().unwrap().clone());
+    let path = std::path::Path::new(&current_dir).join(&path);
+    match state.fs.fs_backing.metadata(&path) {
+        Ok(metadata) if metadata.is_dir() => {}
+        Ok(_) => return __WASI_ENOTDIR,
+        Err(err) => return fs_error_into_wasi_err(err),
+    }
+    let path = path.to_string_lossy().into_owned();
+
+    let changed_in_view = env
+        .view()
+        .map(|view| view.set_current_dir(path.as_str()))
+        .unwrap_or(false);
+    if !changed_in_view {
+        state.fs.set_current_dir(path.as_str());
+    }
     __WASI_ESUCCESS
 }
 
@@ -3380,6 +4276,7 @@ pub fn thread_spawn<M: MemorySize>(
     ret_tid: WasmPtr<__wasi_tid_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::thread_spawn");
+    env.record_syscall("thread_spawn");
     let memory = env.memory();
     let method = unsafe { get_input_str!(memory, method, method_len) };
 
@@ -3411,15 +4308,13 @@ pub fn thread_spawn<M: MemorySize>(
             .runtime
             .thread_spawn(Box::new(move || {
                 if let Some(funct) = sub_env.thread_start_ref() {
+                    // The thread is torn down even when it fails, e.g.
+                    // when the process is killed, so it can be joined.
                     if let Err(err) = funct.call(user_data) {
                         warn!("thread failed: {}", err);
-                        std::mem::forget(sub_thread);
-                        return;
                     }
                 } else {
                     warn!("failed to start thread: missing callback '__wasix_thread_start'");
-                    std::mem::forget(sub_thread);
-                    return;
                 }
 
                 let thread = {
@@ -3428,6 +4323,7 @@ pub fn thread_spawn<M: MemorySize>(
                     drop(guard);
                     thread
                 };
+                sub_env.state.stats.remove(id);
 
                 if let Some(thread) = thread {
                     let mut thread_guard = thread.exit.lock().unwrap();
@@ -3458,6 +4354,7 @@ pub fn thread_sleep(
     duration: __wasi_timestamp_t,
 ) -> Result<__wasi_errno_t, WasiError> {
     debug!("wasi::thread_sleep");
+    env.record_syscall("thread_sleep");
 
     let duration = Duration::from_nanos(duration as u64);
     env.sleep(duration)?;
@@ -3472,6 +4369,7 @@ pub fn thread_id<M: MemorySize>(
     ret_tid: WasmPtr<__wasi_tid_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::thread_id");
+    env.record_syscall("thread_id");
 
     let tid: __wasi_tid_t = env.id.into();
     wasi_try_mem!(ret_tid.write(env.memory(), tid));
@@ -3487,6 +4385,7 @@ pub fn thread_id<M: MemorySize>(
 /// * `tid` - Handle of the thread to wait on
 pub fn thread_join(env: &WasiEnv, tid: __wasi_tid_t) -> Result<__wasi_errno_t, WasiError> {
     debug!("wasi::thread_join");
+    env.record_syscall("thread_join");
 
     let tid: WasiThreadId = tid.into();
     let other_thread = {
@@ -3514,6 +4413,7 @@ pub fn thread_parallelism<M: MemorySize>(
     ret_parallelism: WasmPtr<M::Offset, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::thread_parallelism");
+    env.record_syscall("thread_parallelism");
 
     let parallelism = wasi_try!(env.runtime().thread_parallelism().map_err(|err| {
         let err: __wasi_errno_t = err.into();
@@ -3528,6 +4428,7 @@ pub fn thread_parallelism<M: MemorySize>(
 /// Returns the handle of the current process
 pub fn getpid<M: MemorySize>(env: &WasiEnv, ret_pid: WasmPtr<__wasi_pid_t, M>) -> __wasi_errno_t {
     debug!("wasi::getpid");
+    env.record_syscall("getpid");
 
     let pid = env.runtime().getpid();
     if let Some(pid) = pid {
@@ -3552,6 +4453,7 @@ pub fn thread_exit(
     exitcode: __wasi_exitcode_t,
 ) -> Result<__wasi_errno_t, WasiError> {
     debug!("wasi::thread_exit");
+    env.record_syscall("thread_exit");
     Err(WasiError::Exit(exitcode))
 }
 
@@ -3598,6 +4500,7 @@ pub fn process_spawn<M: MemorySize>(
     let working_dir = unsafe { get_input_str_bus!(memory, working_dir, working_dir_len) };
     let chroot = chroot == __WASI_BOOL_TRUE;
     debug!("wasi::process_spawn (name={})", name);
+    env.record_syscall("process_spawn");
 
     let args: Vec<_> = args.split(&['\n', '\r']).map(|a| a.to_string()).collect();
 
@@ -3642,16 +4545,10 @@ pub fn process_spawn<M: MemorySize>(
     let stderr = conv_stdio_fd(process.inst.stderr_fd());
 
     // Add the process to the environment state
-    let bid = {
-        let mut guard = env.state.threading.lock().unwrap();
-        guard.process_seed += 1;
-        let bid = guard.process_seed;
-        guard.processes.insert(bid.into(), process);
-        bid
-    };
+    let bid = env.add_process(process);
 
     let handles = __wasi_bus_handles_t {
-        bid,
+        bid: bid.into(),
         stdin,
         stdout,
         stderr,
@@ -3686,6 +4583,7 @@ pub fn bus_open_local<M: MemorySize>(
     let name = unsafe { get_input_str_bus!(memory, name, name_len) };
     let reuse = reuse == __WASI_BOOL_TRUE;
     debug!("wasi::bus_open_local (name={}, reuse={})", name, reuse);
+    env.record_syscall("bus_open_local");
 
     bus_open_local_internal(env, name, reuse, None, None, ret_bid)
 }
@@ -3725,6 +4623,7 @@ pub fn bus_open_remote<M: MemorySize>(
         "wasi::bus_open_remote (name={}, reuse={}, instance={})",
         name, reuse, instance
     );
+    env.record_syscall("bus_open_remote");
 
     bus_open_local_internal(env, name, reuse, Some(instance), Some(token), ret_bid)
 }
@@ -3793,6 +4692,7 @@ fn bus_open_local_internal<M: MemorySize>(
 /// * `bid` - Handle of the bus process handle to be closed
 pub fn bus_close(env: &WasiEnv, bid: __wasi_bid_t) -> __bus_errno_t {
     trace!("wasi::bus_close (bid={})", bid);
+    env.record_syscall("bus_close");
     let bid: WasiBusProcessId = bid.into();
 
     let mut guard = env.state.threading.lock().unwrap();
@@ -3833,6 +4733,7 @@ pub fn bus_call<M: MemorySize>(
         topic,
         buf_len
     );
+    env.record_syscall("bus_call");
 
     __BUS_EUNSUPPORTED
 }
@@ -3869,6 +4770,7 @@ pub fn bus_subcall<M: MemorySize>(
         topic,
         buf_len
     );
+    env.record_syscall("bus_subcall");
 
     __BUS_EUNSUPPORTED
 }
@@ -3900,6 +4802,7 @@ pub fn bus_poll<M: MemorySize>(
     let memory = env.memory();
     let malloc = unsafe { get_input_str_bus!(memory, malloc, malloc_len) };
     trace!("wasi::bus_poll (timeout={}, malloc={})", timeout, malloc);
+    env.record_syscall("bus_poll");
 
     __BUS_EUNSUPPORTED
 }
@@ -3928,6 +4831,7 @@ pub fn call_reply<M: MemorySize>(
         format,
         buf_len
     );
+    env.record_syscall("call_reply");
 
     __BUS_EUNSUPPORTED
 }
@@ -3943,6 +4847,7 @@ pub fn call_reply<M: MemorySize>(
 pub fn call_fault(env: &WasiEnv, cid: __wasi_cid_t, fault: __bus_errno_t) -> __bus_errno_t {
     let bus = env.runtime.bus();
     debug!("wasi::call_fault (cid={}, fault={})", cid, fault);
+    env.record_syscall("call_fault");
 
     __BUS_EUNSUPPORTED
 }
@@ -3955,6 +4860,7 @@ pub fn call_fault(env: &WasiEnv, cid: __wasi_cid_t, fault: __bus_errno_t) -> __b
 pub fn call_close(env: &WasiEnv, cid: __wasi_cid_t) -> __bus_errno_t {
     let bus = env.runtime.bus();
     trace!("wasi::call_close (cid={})", cid);
+    env.record_syscall("call_close");
 
     __BUS_EUNSUPPORTED
 }
@@ -3976,8 +4882,12 @@ pub fn ws_connect<M: MemorySize>(
     ret_sock: WasmPtr<__wasi_fd_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::ws_connect");
+    env.record_syscall("ws_connect");
     let memory = env.memory();
     let url = unsafe { get_input_str!(memory, url, url_len) };
+    if let Some(net_policy) = env.net_policy.as_ref() {
+        wasi_try!(net_policy.check_url(url.as_str()));
+    }
 
     let socket = wasi_try!(env
         .net()
@@ -4032,6 +4942,7 @@ pub fn http_request<M: MemorySize>(
     ret_handles: WasmPtr<__wasi_http_handles_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::http_request");
+    env.record_syscall("http_request");
     let memory = env.memory();
     let url = unsafe { get_input_str!(memory, url, url_len) };
     let method = unsafe { get_input_str!(memory, method, method_len) };
@@ -4042,6 +4953,16 @@ pub fn http_request<M: MemorySize>(
         __WASI_BOOL_TRUE => true,
         _ => return __WASI_EINVAL,
     };
+    let (request_body_limit, response_body_limit) = match env.net_policy.as_ref() {
+        Some(net_policy) => {
+            wasi_try!(net_policy.check_url(url.as_str()));
+            (
+                net_policy.request_body_limit(),
+                net_policy.response_body_limit(),
+            )
+        }
+        None => (None, None),
+    };
 
     let socket = wasi_try!(env
         .net()
@@ -4072,13 +4993,15 @@ pub fn http_request<M: MemorySize>(
         socket: InodeSocket::new(InodeSocketKind::HttpRequest(
             Mutex::new(socket_req),
             InodeHttpSocketType::Request,
-        )),
+        ))
+        .with_body_limit(request_body_limit),
     };
     let kind_res = Kind::Socket {
         socket: InodeSocket::new(InodeSocketKind::HttpRequest(
             Mutex::new(socket_res),
             InodeHttpSocketType::Response,
-        )),
+        ))
+        .with_body_limit(response_body_limit),
     };
     let kind_hdr = Kind::Socket {
         socket: InodeSocket::new(InodeSocketKind::HttpRequest(
@@ -4132,11 +5055,12 @@ pub fn http_status<M: MemorySize>(
     status: WasmPtr<__wasi_http_status_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::http_status");
+    env.record_syscall("http_status");
 
     let memory = env.memory();
     let ref_status = status.deref(memory);
 
-    let http_status = wasi_try!(__sock_actor(env, sock, 0, |socket| {
+    let http_status = wasi_try!(__sock_actor(env, sock, 0, "http_status", |socket| {
         socket.http_status()
     }));
 
@@ -4173,6 +5097,7 @@ pub fn port_bridge<M: MemorySize>(
     security: __wasi_streamsecurity_t,
 ) -> __wasi_errno_t {
     debug!("wasi::port_bridge");
+    env.record_syscall("port_bridge");
     let memory = env.memory();
     let network = unsafe { get_input_str!(memory, network, network_len) };
     let token = unsafe { get_input_str!(memory, token, token_len) };
@@ -4195,6 +5120,7 @@ pub fn port_bridge<M: MemorySize>(
 /// Disconnects from a remote network
 pub fn port_unbridge(env: &WasiEnv) -> __wasi_errno_t {
     debug!("wasi::port_unbridge");
+    env.record_syscall("port_unbridge");
     wasi_try!(env.net().unbridge().map_err(net_error_into_wasi_err));
     __WASI_ESUCCESS
 }
@@ -4203,6 +5129,7 @@ pub fn port_unbridge(env: &WasiEnv) -> __wasi_errno_t {
 /// Acquires a set of IP addresses using DHCP
 pub fn port_dhcp_acquire(env: &WasiEnv) -> __wasi_errno_t {
     debug!("wasi::port_dhcp_acquire");
+    env.record_syscall("port_dhcp_acquire");
     wasi_try!(env.net().dhcp_acquire().map_err(net_error_into_wasi_err));
     __WASI_ESUCCESS
 }
@@ -4218,6 +5145,7 @@ pub fn port_addr_add<M: MemorySize>(
     ip: WasmPtr<__wasi_cidr_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::port_addr_add");
+    env.record_syscall("port_addr_add");
     let memory = env.memory();
     let cidr = wasi_try!(super::state::read_cidr(memory, ip));
     wasi_try!(env
@@ -4238,6 +5166,7 @@ pub fn port_addr_remove<M: MemorySize>(
     ip: WasmPtr<__wasi_addr_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::port_addr_remove");
+    env.record_syscall("port_addr_remove");
     let memory = env.memory();
     let ip = wasi_try!(super::state::read_ip(memory, ip));
     wasi_try!(env.net().ip_remove(ip).map_err(net_error_into_wasi_err));
@@ -4248,6 +5177,7 @@ pub fn port_addr_remove<M: MemorySize>(
 /// Clears all the addresses on the local port
 pub fn port_addr_clear(env: &WasiEnv) -> __wasi_errno_t {
     debug!("wasi::port_addr_clear");
+    env.record_syscall("port_addr_clear");
     wasi_try!(env.net().ip_clear().map_err(net_error_into_wasi_err));
     __WASI_ESUCCESS
 }
@@ -4259,6 +5189,7 @@ pub fn port_mac<M: MemorySize>(
     ret_mac: WasmPtr<__wasi_hardwareaddress_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::port_mac");
+    env.record_syscall("port_mac");
     let memory = env.memory();
     let mac = wasi_try!(env.net().mac().map_err(net_error_into_wasi_err));
     let mac = __wasi_hardwareaddress_t { octs: mac };
@@ -4285,6 +5216,7 @@ pub fn port_addr_list<M: MemorySize>(
     naddrs: WasmPtr<M::Offset, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::port_addr_list");
+    env.record_syscall("port_addr_list");
     let memory = env.memory();
     let max_addrs = wasi_try_mem!(naddrs.read(memory));
     let max_addrs: u64 = wasi_try!(max_addrs.try_into().map_err(|_| __WASI_EOVERFLOW));
@@ -4318,6 +5250,7 @@ pub fn port_gateway_set<M: MemorySize>(
     ip: WasmPtr<__wasi_addr_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::port_gateway_set");
+    env.record_syscall("port_gateway_set");
     let memory = env.memory();
     let ip = wasi_try!(super::state::read_ip(memory, ip));
 
@@ -4335,6 +5268,7 @@ pub fn port_route_add<M: MemorySize>(
     expires_at: WasmPtr<__wasi_option_timestamp_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::port_route_add");
+    env.record_syscall("port_route_add");
     let memory = env.memory();
     let cidr = wasi_try!(super::state::read_cidr(memory, cidr));
     let via_router = wasi_try!(super::state::read_ip(memory, via_router));
@@ -4365,6 +5299,7 @@ pub fn port_route_remove<M: MemorySize>(
     ip: WasmPtr<__wasi_addr_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::port_route_remove");
+    env.record_syscall("port_route_remove");
     let memory = env.memory();
     let ip = wasi_try!(super::state::read_ip(memory, ip));
     wasi_try!(env.net().route_remove(ip).map_err(net_error_into_wasi_err));
@@ -4375,6 +5310,7 @@ pub fn port_route_remove<M: MemorySize>(
 /// Clears all the routes in the local port
 pub fn port_route_clear(env: &WasiEnv) -> __wasi_errno_t {
     debug!("wasi::port_route_clear");
+    env.record_syscall("port_route_clear");
     wasi_try!(env.net().route_clear().map_err(net_error_into_wasi_err));
     __WASI_ESUCCESS
 }
@@ -4394,6 +5330,7 @@ pub fn port_route_list<M: MemorySize>(
     nroutes: WasmPtr<M::Offset, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::port_route_list");
+    env.record_syscall("port_route_list");
     let memory = env.memory();
     let nroutes = nroutes.deref(memory);
     let max_routes: usize = wasi_try!(wasi_try_mem!(nroutes.read())
@@ -4426,6 +5363,7 @@ pub fn port_route_list<M: MemorySize>(
 /// * `how` - Which channels on the socket to shut down.
 pub fn sock_shutdown(env: &WasiEnv, sock: __wasi_fd_t, how: __wasi_sdflags_t) -> __wasi_errno_t {
     debug!("wasi::sock_shutdown");
+    env.record_syscall("sock_shutdown");
 
     let both = __WASI_SHUT_RD | __WASI_SHUT_WR;
     let how = match how {
@@ -4439,6 +5377,7 @@ pub fn sock_shutdown(env: &WasiEnv, sock: __wasi_fd_t, how: __wasi_sdflags_t) ->
         env,
         sock,
         __WASI_RIGHT_SOCK_SHUTDOWN,
+        "sock_shutdown",
         |socket| { socket.shutdown(how) }
     ));
 
@@ -4453,8 +5392,11 @@ pub fn sock_status<M: MemorySize>(
     ret_status: WasmPtr<__wasi_sockstatus_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_status");
+    env.record_syscall("sock_status");
 
-    let status = wasi_try!(__sock_actor(env, sock, 0, |socket| { socket.status() }));
+    let status = wasi_try!(__sock_actor(env, sock, 0, "sock_status", |socket| {
+        socket.status()
+    }));
 
     use super::state::WasiSocketStatus;
     let status = match status {
@@ -4486,8 +5428,11 @@ pub fn sock_addr_local<M: MemorySize>(
     ret_addr: WasmPtr<__wasi_addr_port_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_addr_local");
+    env.record_syscall("sock_addr_local");
 
-    let addr = wasi_try!(__sock_actor(env, sock, 0, |socket| { socket.addr_local() }));
+    let addr = wasi_try!(__sock_actor(env, sock, 0, "sock_addr_local", |socket| {
+        socket.addr_local()
+    }));
     wasi_try!(super::state::write_ip_port(
         env.memory(),
         ret_addr,
@@ -4514,8 +5459,11 @@ pub fn sock_addr_peer<M: MemorySize>(
     ro_addr: WasmPtr<__wasi_addr_port_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_addr_peer");
+    env.record_syscall("sock_addr_peer");
 
-    let addr = wasi_try!(__sock_actor(env, sock, 0, |socket| { socket.addr_peer() }));
+    let addr = wasi_try!(__sock_actor(env, sock, 0, "sock_addr_peer", |socket| {
+        socket.addr_peer()
+    }));
     wasi_try!(super::state::write_ip_port(
         env.memory(),
         ro_addr,
@@ -4552,9 +5500,15 @@ pub fn sock_open<M: MemorySize>(
     ro_sock: WasmPtr<__wasi_fd_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_open");
+    env.record_syscall("sock_open");
 
     let (memory, state, mut inodes) = env.get_memory_and_wasi_state_and_inodes_mut(0);
 
+    // Only the stream sockets are supported in the Unix domain.
+    if af == __WASI_ADDRESS_FAMILY_UNIX && ty != __WASI_SOCK_TYPE_STREAM {
+        return __WASI_ENOTSUP;
+    }
+
     let kind = match ty {
         __WASI_SOCK_TYPE_STREAM | __WASI_SOCK_TYPE_DGRAM => Kind::Socket {
             socket: InodeSocket::new(InodeSocketKind::PreSocket {
@@ -4562,6 +5516,7 @@ pub fn sock_open<M: MemorySize>(
                 ty,
                 pt,
                 addr: None,
+                unix_path: None,
                 only_v6: false,
                 reuse_port: false,
                 reuse_addr: false,
@@ -4606,6 +5561,7 @@ pub fn sock_set_opt_flag(
     flag: __wasi_bool_t,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_set_opt_flag(ty={})", opt);
+    env.record_syscall("sock_set_opt_flag");
 
     let flag = match flag {
         __WASI_BOOL_FALSE => false,
@@ -4614,9 +5570,13 @@ pub fn sock_set_opt_flag(
     };
 
     let option: super::state::WasiSocketOption = opt.into();
-    wasi_try!(__sock_actor_mut(env, sock, 0, |socket| {
-        socket.set_opt_flag(option, flag)
-    }));
+    wasi_try!(__sock_actor_mut(
+        env,
+        sock,
+        0,
+        "sock_set_opt_flag",
+        |socket| { socket.set_opt_flag(option, flag) }
+    ));
     __WASI_ESUCCESS
 }
 
@@ -4635,10 +5595,11 @@ pub fn sock_get_opt_flag<M: MemorySize>(
     ret_flag: WasmPtr<__wasi_bool_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_get_opt_flag(ty={})", opt);
+    env.record_syscall("sock_get_opt_flag");
     let memory = env.memory();
 
     let option: super::state::WasiSocketOption = opt.into();
-    let flag = wasi_try!(__sock_actor(env, sock, 0, |socket| {
+    let flag = wasi_try!(__sock_actor(env, sock, 0, "sock_get_opt_flag", |socket| {
         socket.get_opt_flag(option)
     }));
     let flag = match flag {
@@ -4666,6 +5627,7 @@ pub fn sock_set_opt_time<M: MemorySize>(
     time: WasmPtr<__wasi_option_timestamp_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_set_opt_time(ty={})", opt);
+    env.record_syscall("sock_set_opt_time");
 
     let memory = env.memory();
     let time = wasi_try_mem!(time.read(memory));
@@ -4685,9 +5647,13 @@ pub fn sock_set_opt_time<M: MemorySize>(
     };
 
     let option: super::state::WasiSocketOption = opt.into();
-    wasi_try!(__sock_actor_mut(env, sock, 0, |socket| {
-        socket.set_opt_time(ty, time)
-    }));
+    wasi_try!(__sock_actor_mut(
+        env,
+        sock,
+        0,
+        "sock_set_opt_time",
+        |socket| { socket.set_opt_time(ty, time) }
+    ));
     __WASI_ESUCCESS
 }
 
@@ -4705,6 +5671,7 @@ pub fn sock_get_opt_time<M: MemorySize>(
     ret_time: WasmPtr<__wasi_option_timestamp_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_get_opt_time(ty={})", opt);
+    env.record_syscall("sock_get_opt_time");
     let memory = env.memory();
 
     let ty = match opt {
@@ -4716,7 +5683,9 @@ pub fn sock_get_opt_time<M: MemorySize>(
         _ => return __WASI_EINVAL,
     };
 
-    let time = wasi_try!(__sock_actor(env, sock, 0, |socket| { socket.opt_time(ty) }));
+    let time = wasi_try!(__sock_actor(env, sock, 0, "sock_get_opt_time", |socket| {
+        socket.opt_time(ty)
+    }));
     let time = match time {
         None => __wasi_option_timestamp_t {
             tag: __WASI_OPTION_NONE,
@@ -4749,6 +5718,7 @@ pub fn sock_set_opt_size(
     size: __wasi_filesize_t,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_set_opt_size(ty={})", opt);
+    env.record_syscall("sock_set_opt_size");
 
     let ty = match opt {
         __WASI_SOCK_OPTION_RECV_TIMEOUT => wasmer_vnet::TimeType::ReadTimeout,
@@ -4760,15 +5730,21 @@ pub fn sock_set_opt_size(
     };
 
     let option: super::state::WasiSocketOption = opt.into();
-    wasi_try!(__sock_actor_mut(env, sock, 0, |socket| {
-        match opt {
-            __WASI_SOCK_OPTION_RECV_BUF_SIZE => socket.set_recv_buf_size(size as usize),
-            __WASI_SOCK_OPTION_SEND_BUF_SIZE => socket.set_send_buf_size(size as usize),
-            __WASI_SOCK_OPTION_TTL => socket.set_ttl(size as u32),
-            __WASI_SOCK_OPTION_MULTICAST_TTL_V4 => socket.set_multicast_ttl_v4(size as u32),
-            _ => Err(__WASI_EINVAL),
+    wasi_try!(__sock_actor_mut(
+        env,
+        sock,
+        0,
+        "sock_set_opt_size",
+        |socket| {
+            match opt {
+                __WASI_SOCK_OPTION_RECV_BUF_SIZE => socket.set_recv_buf_size(size as usize),
+                __WASI_SOCK_OPTION_SEND_BUF_SIZE => socket.set_send_buf_size(size as usize),
+                __WASI_SOCK_OPTION_TTL => socket.set_ttl(size as u32),
+                __WASI_SOCK_OPTION_MULTICAST_TTL_V4 => socket.set_multicast_ttl_v4(size as u32),
+                _ => Err(__WASI_EINVAL),
+            }
         }
-    }));
+    ));
     __WASI_ESUCCESS
 }
 
@@ -4787,9 +5763,10 @@ pub fn sock_get_opt_size<M: MemorySize>(
     ret_size: WasmPtr<__wasi_filesize_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_get_opt_size(ty={})", opt);
+    env.record_syscall("sock_get_opt_size");
     let memory = env.memory();
 
-    let size = wasi_try!(__sock_actor(env, sock, 0, |socket| {
+    let size = wasi_try!(__sock_actor(env, sock, 0, "sock_get_opt_size", |socket| {
         match opt {
             __WASI_SOCK_OPTION_RECV_BUF_SIZE => {
                 socket.recv_buf_size().map(|a| a as __wasi_filesize_t)
@@ -4824,13 +5801,18 @@ pub fn sock_join_multicast_v4<M: MemorySize>(
     iface: WasmPtr<__wasi_addr_ip4_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_join_multicast_v4");
+    env.record_syscall("sock_join_multicast_v4");
 
     let memory = env.memory();
     let multiaddr = wasi_try!(super::state::read_ip_v4(memory, multiaddr));
     let iface = wasi_try!(super::state::read_ip_v4(memory, iface));
-    wasi_try!(__sock_actor_mut(env, sock, 0, |socket| {
-        socket.join_multicast_v4(multiaddr, iface)
-    }));
+    wasi_try!(__sock_actor_mut(
+        env,
+        sock,
+        0,
+        "sock_join_multicast_v4",
+        |socket| { socket.join_multicast_v4(multiaddr, iface) }
+    ));
     __WASI_ESUCCESS
 }
 
@@ -4849,13 +5831,18 @@ pub fn sock_leave_multicast_v4<M: MemorySize>(
     iface: WasmPtr<__wasi_addr_ip4_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_leave_multicast_v4");
+    env.record_syscall("sock_leave_multicast_v4");
 
     let memory = env.memory();
     let multiaddr = wasi_try!(super::state::read_ip_v4(memory, multiaddr));
     let iface = wasi_try!(super::state::read_ip_v4(memory, iface));
-    wasi_try!(__sock_actor_mut(env, sock, 0, |socket| {
-        socket.leave_multicast_v4(multiaddr, iface)
-    }));
+    wasi_try!(__sock_actor_mut(
+        env,
+        sock,
+        0,
+        "sock_leave_multicast_v4",
+        |socket| { socket.leave_multicast_v4(multiaddr, iface) }
+    ));
     __WASI_ESUCCESS
 }
 
@@ -4874,12 +5861,17 @@ pub fn sock_join_multicast_v6<M: MemorySize>(
     iface: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_join_multicast_v6");
+    env.record_syscall("sock_join_multicast_v6");
 
     let memory = env.memory();
     let multiaddr = wasi_try!(super::state::read_ip_v6(memory, multiaddr));
-    wasi_try!(__sock_actor_mut(env, sock, 0, |socket| {
-        socket.join_multicast_v6(multiaddr, iface)
-    }));
+    wasi_try!(__sock_actor_mut(
+        env,
+        sock,
+        0,
+        "sock_join_multicast_v6",
+        |socket| { socket.join_multicast_v6(multiaddr, iface) }
+    ));
     __WASI_ESUCCESS
 }
 
@@ -4898,12 +5890,17 @@ pub fn sock_leave_multicast_v6<M: MemorySize>(
     iface: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_leave_multicast_v6");
+    env.record_syscall("sock_leave_multicast_v6");
 
     let memory = env.memory();
     let multiaddr = wasi_try!(super::state::read_ip_v6(memory, multiaddr));
-    wasi_try!(__sock_actor_mut(env, sock, 0, |socket| {
-        socket.leave_multicast_v6(multiaddr, iface)
-    }));
+    wasi_try!(__sock_actor_mut(
+        env,
+        sock,
+        0,
+        "sock_leave_multicast_v6",
+        |socket| { socket.leave_multicast_v6(multiaddr, iface) }
+    ));
     __WASI_ESUCCESS
 }
 
@@ -4921,6 +5918,7 @@ pub fn sock_bind<M: MemorySize>(
     addr: WasmPtr<__wasi_addr_port_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_bind");
+    env.record_syscall("sock_bind");
 
     let addr = wasi_try!(super::state::read_ip_port(env.memory(), addr));
     let addr = SocketAddr::new(addr.0, addr.1);
@@ -4928,11 +5926,44 @@ pub fn sock_bind<M: MemorySize>(
         env,
         sock,
         __WASI_RIGHT_SOCK_BIND,
+        "sock_bind",
         |socket| { socket.bind(env.net(), addr) }
     ));
     __WASI_ESUCCESS
 }
 
+/// ### `sock_bind_unix()`
+/// Bind a Unix domain socket to a path
+/// Note: This is similar to `bind` in POSIX using PF_UNIX
+///
+/// The path is resolved by the networking implementation, which decides
+/// which paths of the host the guest can bind to.
+///
+/// ## Parameters
+///
+/// * `fd` - File descriptor of the socket to be bind
+/// * `path` - Path to bind the socket to
+pub fn sock_bind_unix<M: MemorySize>(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+) -> __wasi_errno_t {
+    debug!("wasi::sock_bind_unix");
+    env.record_syscall("sock_bind_unix");
+
+    let memory = env.memory();
+    let path = unsafe { get_input_str!(memory, path, path_len) };
+    wasi_try!(__sock_upgrade(
+        env,
+        sock,
+        __WASI_RIGHT_SOCK_BIND,
+        "sock_bind_unix",
+        |socket| { socket.bind_unix(std::path::Path::new(&path)) }
+    ));
+    __WASI_ESUCCESS
+}
+
 /// ### `sock_listen()`
 /// Listen for connections on a socket
 ///
@@ -4951,12 +5982,14 @@ pub fn sock_listen<M: MemorySize>(
     backlog: M::Offset,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_listen");
+    env.record_syscall("sock_listen");
 
     let backlog: usize = wasi_try!(backlog.try_into().map_err(|_| __WASI_EINVAL));
     wasi_try!(__sock_upgrade(
         env,
         sock,
         __WASI_RIGHT_SOCK_BIND,
+        "sock_listen",
         |socket| { socket.listen(env.net(), backlog) }
     ));
     __WASI_ESUCCESS
@@ -4982,30 +6015,34 @@ pub fn sock_accept<M: MemorySize>(
     ro_addr: WasmPtr<__wasi_addr_port_t, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     debug!("wasi::sock_accept");
+    env.record_syscall("sock_accept");
+    wasi_try_ok!(env.inject_fault("sock_accept"));
 
     let (child, addr) = {
         let mut ret;
         let (_, state) = env.get_memory_and_wasi_state(0);
         loop {
-            wasi_try_ok!(
-                match __sock_actor(env, sock, __WASI_RIGHT_SOCK_ACCEPT, |socket| socket
-                    .accept_timeout(fd_flags, Duration::from_millis(5)))
-                {
-                    Ok(a) => {
-                        ret = a;
-                        break;
-                    }
-                    Err(__WASI_ETIMEDOUT) => {
-                        env.yield_now()?;
-                        continue;
-                    }
-                    Err(__WASI_EAGAIN) => {
-                        env.sleep(Duration::from_millis(5))?;
-                        continue;
-                    }
-                    Err(err) => Err(err),
+            wasi_try_ok!(match __sock_actor(
+                env,
+                sock,
+                __WASI_RIGHT_SOCK_ACCEPT,
+                "sock_accept",
+                |socket| socket.accept_timeout(fd_flags, Duration::from_millis(5))
+            ) {
+                Ok(a) => {
+                    ret = a;
+                    break;
                 }
-            );
+                Err(__WASI_ETIMEDOUT) => {
+                    env.yield_now()?;
+                    continue;
+                }
+                Err(__WASI_EAGAIN) => {
+                    env.sleep(Duration::from_millis(5))?;
+                    continue;
+                }
+                Err(err) => Err(err),
+            });
         }
         ret
     };
@@ -5054,6 +6091,8 @@ pub fn sock_connect<M: MemorySize>(
     addr: WasmPtr<__wasi_addr_port_t, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::sock_connect");
+    env.record_syscall("sock_connect");
+    wasi_try!(env.inject_fault("sock_connect"));
 
     let addr = wasi_try!(super::state::read_ip_port(env.memory(), addr));
     let addr = SocketAddr::new(addr.0, addr.1);
@@ -5061,11 +6100,78 @@ pub fn sock_connect<M: MemorySize>(
         env,
         sock,
         __WASI_RIGHT_SOCK_CONNECT,
+        "sock_connect",
         |socket| { socket.connect(env.net(), addr) }
     ));
     __WASI_ESUCCESS
 }
 
+/// ### `sock_connect_unix()`
+/// Initiate a connection on a Unix domain socket to the socket bound to
+/// a path
+///
+/// Note: This is similar to `connect` in POSIX using PF_UNIX
+///
+/// ## Parameters
+///
+/// * `fd` - Socket descriptor
+/// * `path` - Path of the socket to connect to
+pub fn sock_connect_unix<M: MemorySize>(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+) -> __wasi_errno_t {
+    debug!("wasi::sock_connect_unix");
+    env.record_syscall("sock_connect_unix");
+    wasi_try!(env.inject_fault("sock_connect_unix"));
+
+    let memory = env.memory();
+    let path = unsafe { get_input_str!(memory, path, path_len) };
+    wasi_try!(__sock_upgrade(
+        env,
+        sock,
+        __WASI_RIGHT_SOCK_CONNECT,
+        "sock_connect_unix",
+        |socket| { socket.connect_unix(env.net(), std::path::Path::new(&path)) }
+    ));
+    __WASI_ESUCCESS
+}
+
+/// ### `sock_upgrade_tls()`
+/// Upgrades a connected socket to TLS, the handshake and the encryption
+/// being done by the host
+///
+/// Once upgraded, the data sent and received on the socket is the
+/// plaintext. The socket is closed if the handshake fails.
+///
+/// ## Parameters
+///
+/// * `fd` - Socket descriptor
+/// * `server_name` - Name of the server the certificate of the peer is
+///   checked against
+pub fn sock_upgrade_tls<M: MemorySize>(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    server_name: WasmPtr<u8, M>,
+    server_name_len: M::Offset,
+) -> __wasi_errno_t {
+    debug!("wasi::sock_upgrade_tls");
+    env.record_syscall("sock_upgrade_tls");
+    wasi_try!(env.inject_fault("sock_upgrade_tls"));
+
+    let memory = env.memory();
+    let server_name = unsafe { get_input_str!(memory, server_name, server_name_len) };
+    wasi_try!(__sock_upgrade(
+        env,
+        sock,
+        __WASI_RIGHT_SOCK_CONNECT,
+        "sock_upgrade_tls",
+        |socket| { socket.upgrade_tls(env.net(), server_name.as_str()) }
+    ));
+    __WASI_ESUCCESS
+}
+
 /// ### `sock_recv()`
 /// Receive a message from a socket.
 /// Note: This is similar to `recv` in POSIX, though it also supports reading
@@ -5089,6 +6195,8 @@ pub fn sock_recv<M: MemorySize>(
     ro_flags: WasmPtr<__wasi_roflags_t, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     debug!("wasi::sock_recv");
+    env.record_syscall("sock_recv");
+    wasi_try_ok!(env.inject_fault("sock_recv"));
 
     let memory = env.memory();
     let iovs_arr = wasi_try_mem_ok!(ri_data.slice(memory, ri_data_len));
@@ -5097,8 +6205,10 @@ pub fn sock_recv<M: MemorySize>(
         env,
         sock,
         __WASI_RIGHT_SOCK_RECV,
+        "sock_recv",
         |socket| { socket.recv(memory, iovs_arr) }
     ));
+    env.record_read(bytes_read);
     let bytes_read: M::Offset = wasi_try_ok!(bytes_read.try_into().map_err(|_| __WASI_EOVERFLOW));
 
     wasi_try_mem_ok!(ro_flags.write(memory, 0));
@@ -5131,6 +6241,8 @@ pub fn sock_recv_from<M: MemorySize>(
     ro_addr: WasmPtr<__wasi_addr_port_t, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     debug!("wasi::sock_recv_from");
+    env.record_syscall("sock_recv_from");
+    wasi_try_ok!(env.inject_fault("sock_recv_from"));
 
     let memory = env.memory();
     let iovs_arr = wasi_try_mem_ok!(ri_data.slice(memory, ri_data_len));
@@ -5139,8 +6251,10 @@ pub fn sock_recv_from<M: MemorySize>(
         env,
         sock,
         __WASI_RIGHT_SOCK_RECV_FROM,
+        "sock_recv_from",
         |socket| { socket.recv_from(memory, iovs_arr, ro_addr) }
     ));
+    env.record_read(bytes_read);
     let bytes_read: M::Offset = wasi_try_ok!(bytes_read.try_into().map_err(|_| __WASI_EOVERFLOW));
 
     wasi_try_mem_ok!(ro_flags.write(memory, 0));
@@ -5171,6 +6285,8 @@ pub fn sock_send<M: MemorySize>(
     ret_data_len: WasmPtr<M::Offset, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     debug!("wasi::sock_send");
+    env.record_syscall("sock_send");
+    wasi_try_ok!(env.inject_fault("sock_send"));
 
     let memory = env.memory();
     let iovs_arr = wasi_try_mem_ok!(si_data.slice(memory, si_data_len));
@@ -5179,9 +6295,11 @@ pub fn sock_send<M: MemorySize>(
         env,
         sock,
         __WASI_RIGHT_SOCK_SEND,
+        "sock_send",
         |socket| { socket.send(memory, iovs_arr) }
     ));
 
+    env.record_written(bytes_written);
     let bytes_written: M::Offset =
         wasi_try_ok!(bytes_written.try_into().map_err(|_| __WASI_EOVERFLOW));
     wasi_try_mem_ok!(ret_data_len.write(memory, bytes_written));
@@ -5213,6 +6331,8 @@ pub fn sock_send_to<M: MemorySize>(
     ret_data_len: WasmPtr<M::Offset, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     debug!("wasi::sock_send_to");
+    env.record_syscall("sock_send_to");
+    wasi_try_ok!(env.inject_fault("sock_send_to"));
 
     let memory = env.memory();
     let iovs_arr = wasi_try_mem_ok!(si_data.slice(memory, si_data_len));
@@ -5221,9 +6341,11 @@ pub fn sock_send_to<M: MemorySize>(
         env,
         sock,
         __WASI_RIGHT_SOCK_SEND_TO,
+        "sock_send_to",
         |socket| { socket.send_to::<M>(memory, iovs_arr, addr) }
     ));
 
+    env.record_written(bytes_written);
     let bytes_written: M::Offset =
         wasi_try_ok!(bytes_written.try_into().map_err(|_| __WASI_EOVERFLOW));
     wasi_try_mem_ok!(ret_data_len.write(memory, bytes_written as M::Offset));
@@ -5232,7 +6354,15 @@ pub fn sock_send_to<M: MemorySize>(
 }
 
 /// ### `sock_send_file()`
-/// Sends the entire contents of a file down a socket
+/// Sends the contents of a file down a socket, without copying them
+/// through the memory of the guest. The file is streamed in chunks, or
+/// sent by the kernel with `sendfile(2)` where both descriptors are
+/// host descriptors.
+///
+/// The send stops early, and returns the number of bytes sent so far,
+/// at the end of the file or when the socket can't take more data
+/// without blocking. The offset of `in_fd` is left after the bytes
+/// sent.
 ///
 /// ## Parameters
 ///
@@ -5248,115 +6378,263 @@ pub unsafe fn sock_send_file<M: MemorySize>(
     sock: __wasi_fd_t,
     in_fd: __wasi_fd_t,
     offset: __wasi_filesize_t,
-    mut count: __wasi_filesize_t,
+    count: __wasi_filesize_t,
     ret_sent: WasmPtr<__wasi_filesize_t, M>,
 ) -> Result<__wasi_errno_t, WasiError> {
     debug!("wasi::send_file");
-    let (memory, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
+    env.record_syscall("sock_send_file");
+    wasi_try_ok!(env.inject_fault("sock_send_file"));
+    let memory = env.memory();
+    let state = env.state();
 
-    // Set the offset of the file
-    {
-        let mut fd_map = state.fs.fd_map.write().unwrap();
-        let fd_entry = wasi_try_ok!(fd_map.get_mut(&in_fd).ok_or(__WASI_EBADF));
-        fd_entry.offset = offset as u64;
+    let fd_entry = wasi_try_ok!(state.fs.get_fd(in_fd));
+    if in_fd == __WASI_STDOUT_FILENO || in_fd == __WASI_STDERR_FILENO {
+        return Ok(__WASI_EINVAL);
+    }
+    if !state.fs.has_rights(
+        in_fd,
+        fd_entry.rights,
+        __WASI_RIGHT_FD_READ,
+        "sock_send_file",
+    ) {
+        // TODO: figure out the error to return when lacking rights
+        return Ok(__WASI_EACCES);
     }
 
-    // Enter a loop that will process all the data
-    let mut total_written: __wasi_filesize_t = 0;
-    while (count > 0) {
-        let mut buf = [0; 4096];
-        let sub_count = count.min(4096);
-        count -= sub_count;
-
-        let fd_entry = wasi_try_ok!(state.fs.get_fd(in_fd));
-        let bytes_read = match in_fd {
-            __WASI_STDIN_FILENO => {
-                let mut guard = wasi_try_ok!(
-                    inodes
-                        .stdin_mut(&state.fs.fd_map)
-                        .map_err(fs_error_into_wasi_err),
-                    env
-                );
-                if let Some(ref mut stdin) = guard.deref_mut() {
-                    wasi_try_ok!(stdin.read(&mut buf).map_err(map_io_err))
-                } else {
-                    return Ok(__WASI_EBADF);
-                }
-            }
-            __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => return Ok(__WASI_EINVAL),
-            _ => {
-                if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_READ) {
-                    // TODO: figure out the error to return when lacking rights
-                    return Ok(__WASI_EACCES);
-                }
-
-                let offset = fd_entry.offset as usize;
-                let inode_idx = fd_entry.inode;
-                let inode = &inodes.arena[inode_idx];
-
-                let bytes_read = {
-                    let mut guard = inode.write();
-                    match guard.deref_mut() {
-                        Kind::File { handle, .. } => {
-                            if let Some(handle) = handle {
-                                wasi_try_ok!(
-                                    handle
-                                        .seek(std::io::SeekFrom::Start(offset as u64))
-                                        .map_err(map_io_err),
-                                    env
-                                );
-                                wasi_try_ok!(handle.read(&mut buf).map_err(map_io_err))
-                            } else {
-                                return Ok(__WASI_EINVAL);
-                            }
-                        }
-                        Kind::Socket { socket } => {
-                            wasi_try_ok!(socket.read(&mut buf).map_err(map_io_err))
-                        }
-                        Kind::Pipe { pipe } => {
-                            wasi_try_ok!(pipe.read(&mut buf).map_err(map_io_err))
-                        }
-                        Kind::Dir { .. } | Kind::Root { .. } => {
-                            return Ok(__WASI_EISDIR);
-                        }
-                        Kind::EventNotifications { .. } => {
-                            return Ok(__WASI_EINVAL);
-                        }
-                        Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_read"),
-                        Kind::Buffer { buffer } => {
-                            let mut buf_read = &buffer[offset..];
-                            wasi_try_ok!(buf_read.read(&mut buf).map_err(map_io_err))
-                        }
+    let total_written =
+        match wasi_try_ok!(sock_send_file_zero_copy(env, sock, in_fd, offset, count)) {
+            Some(sent) => sent,
+            None => {
+                let mut total_written: __wasi_filesize_t = 0;
+                let mut buf = vec![0; SEND_FILE_CHUNK_SIZE];
+
+                while total_written < count {
+                    let sub_count = (count - total_written).min(buf.len() as u64) as usize;
+                    let bytes_read = wasi_try_ok!(
+                        sock_send_file_read(
+                            env,
+                            in_fd,
+                            offset + total_written,
+                            &mut buf[..sub_count]
+                        ),
+                        env
+                    );
+                    // The end of the file.
+                    if bytes_read == 0 {
+                        break;
                     }
-                };
 
-                // reborrow
-                let mut fd_map = state.fs.fd_map.write().unwrap();
-                let fd_entry = wasi_try_ok!(fd_map.get_mut(&in_fd).ok_or(__WASI_EBADF));
-                fd_entry.offset += bytes_read as u64;
+                    // Write it down to the socket
+                    let bytes_written = match __sock_actor_mut(
+                        env,
+                        sock,
+                        __WASI_RIGHT_SOCK_SEND,
+                        "sock_send_file",
+                        |socket| socket.send_bytes::<M>(Bytes::copy_from_slice(&buf[..bytes_read])),
+                    ) {
+                        Ok(bytes_written) => bytes_written,
+                        // The socket is full: the guest sends the rest later.
+                        Err(__WASI_EAGAIN) if total_written > 0 => break,
+                        Err(err) => return Ok(err),
+                    };
+                    total_written += bytes_written as u64;
 
-                bytes_read
+                    if bytes_written < bytes_read {
+                        break;
+                    }
+                }
+
+                total_written
             }
         };
 
-        // Write it down to the socket
-        let bytes_written = wasi_try_ok!(__sock_actor_mut(
-            env,
-            sock,
-            __WASI_RIGHT_SOCK_SEND,
-            |socket| {
-                let buf = (&buf[..]).to_vec();
-                socket.send_bytes::<M>(Bytes::from(buf))
-            }
-        ));
-        total_written += bytes_written as u64;
+    // Set the offset of the file after what has been sent
+    {
+        let mut fd_map = state.fs.fd_map.write().unwrap();
+        let fd_entry = wasi_try_ok!(fd_map.get_mut(&in_fd).ok_or(__WASI_EBADF));
+        fd_entry
+            .offset
+            .store(offset + total_written, Ordering::Release);
     }
 
+    env.record_written(total_written as usize);
     wasi_try_mem_ok!(ret_sent.write(memory, total_written as __wasi_filesize_t));
 
     Ok(__WASI_ESUCCESS)
 }
 
+/// The size of the chunks in which `sock_send_file` streams a file.
+const SEND_FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads the chunk of `in_fd` at `offset` sent by `sock_send_file`.
+/// The streams, like stdin or pipes, are read from where they are.
+fn sock_send_file_read(
+    env: &WasiEnv,
+    in_fd: __wasi_fd_t,
+    offset: __wasi_filesize_t,
+    buf: &mut [u8],
+) -> Result<usize, __wasi_errno_t> {
+    let (_, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
+
+    if in_fd == __WASI_STDIN_FILENO {
+        return with_stdio(env, &inodes, in_fd, |stdin| {
+            stdin.read(buf).map_err(map_io_err)
+        });
+    }
+
+    let fd_entry = state.fs.get_fd(in_fd)?;
+    let mut guard = inodes.arena[fd_entry.inode].write();
+    match guard.deref_mut() {
+        Kind::File { handle, .. } => {
+            let handle = handle.as_mut().ok_or(__WASI_EINVAL)?;
+            handle
+                .seek(std::io::SeekFrom::Start(offset))
+                .map_err(map_io_err)?;
+            handle.read(buf).map_err(map_io_err)
+        }
+        Kind::Socket { socket } => socket.read(buf).map_err(map_io_err),
+        Kind::Pipe { pipe } => pipe.read(buf).map_err(map_io_err),
+        Kind::Dir { .. } | Kind::Root { .. } => Err(__WASI_EISDIR),
+        Kind::EventNotifications { .. } => Err(__WASI_EINVAL),
+        Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::sock_send_file"),
+        Kind::Buffer { buffer } => {
+            let mut buf_read = buffer.get(offset as usize..).unwrap_or_default();
+            buf_read.read(buf).map_err(map_io_err)
+        }
+    }
+}
+
+/// Sends `count` bytes of the file `in_fd` from `offset` with
+/// `sendfile(2)`, when `in_fd` is a host file and `sock` a host
+/// socket. Returns `None` when they aren't, or when the kernel can't
+/// send the file, for the caller to stream it instead.
+#[cfg(all(feature = "host-fs", any(target_os = "linux", target_os = "android")))]
+fn sock_send_file_zero_copy(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    in_fd: __wasi_fd_t,
+    offset: __wasi_filesize_t,
+    count: __wasi_filesize_t,
+) -> Result<Option<__wasi_filesize_t>, __wasi_errno_t> {
+    use std::os::unix::io::AsRawFd;
+    use wasmer_vfs::Upcastable;
+
+    let (_, state, inodes) = env.get_memory_and_wasi_state_and_inodes(0);
+    let sock_entry = state.fs.get_fd(sock)?;
+    if !state.fs.has_rights(
+        sock,
+        sock_entry.rights,
+        __WASI_RIGHT_SOCK_SEND,
+        "sock_send_file",
+    ) {
+        return Err(__WASI_EACCES);
+    }
+    let in_entry = state.fs.get_fd(in_fd)?;
+    if sock_entry.inode == in_entry.inode {
+        return Ok(None);
+    }
+
+    let mut sock_guard = inodes.arena[sock_entry.inode].write();
+    let out_raw_fd = match sock_guard.deref_mut() {
+        Kind::Socket { socket } => match socket.as_raw_fd() {
+            Some(fd) => fd,
+            None => return Ok(None),
+        },
+        _ => return Err(__WASI_ENOTSOCK),
+    };
+    let in_guard = inodes.arena[in_entry.inode].read();
+    let in_raw_fd = match in_guard.deref() {
+        Kind::File {
+            handle: Some(handle),
+            ..
+        } => match (**handle)
+            .upcast_any_ref()
+            .downcast_ref::<wasmer_vfs::host_fs::File>()
+        {
+            Some(file) => file.inner.as_raw_fd(),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    sock_send_file_chunks(offset, count, |offset, sub_count| {
+        let mut file_offset = offset as libc::off_t;
+        let ret =
+            unsafe { libc::sendfile(out_raw_fd, in_raw_fd, &mut file_offset, sub_count as usize) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ret as u64)
+    })
+}
+
+/// The most `sendfile(2)` sends in one call.
+#[cfg(all(feature = "host-fs", any(target_os = "linux", target_os = "android")))]
+const MAX_SEND_FILE: u64 = 0x7fff_f000;
+
+/// Sends `count` bytes from `offset` with `send_chunk`, which sends at
+/// most the given number of bytes from the given offset with
+/// `sendfile(2)`. Returns `None` when the kernel can't send the file at
+/// all, for the caller to stream it instead.
+#[cfg(all(feature = "host-fs", any(target_os = "linux", target_os = "android")))]
+fn sock_send_file_chunks(
+    offset: __wasi_filesize_t,
+    count: __wasi_filesize_t,
+    mut send_chunk: impl FnMut(__wasi_filesize_t, u64) -> io::Result<u64>,
+) -> Result<Option<__wasi_filesize_t>, __wasi_errno_t> {
+    let mut sent: __wasi_filesize_t = 0;
+    while sent < count {
+        let sub_count = (count - sent).min(MAX_SEND_FILE);
+        match send_chunk(offset + sent, sub_count) {
+            // The end of the file.
+            Ok(0) => break,
+            Ok(chunk) => {
+                sent += chunk;
+                // The socket is full: the guest sends the rest later.
+                if chunk < sub_count {
+                    break;
+                }
+            }
+            Err(err) => match err.raw_os_error() {
+                Some(libc::EINVAL) | Some(libc::ENOSYS) if sent == 0 => return Ok(None),
+                _ if sent > 0 => break,
+                _ => return Err(map_send_file_err(err)),
+            },
+        }
+    }
+
+    Ok(Some(sent))
+}
+
+/// Maps the errors of `sendfile(2)` to their errno, which the kind of
+/// the error doesn't always tell, e.g. for `EBADF`.
+#[cfg(all(feature = "host-fs", any(target_os = "linux", target_os = "android")))]
+fn map_send_file_err(err: io::Error) -> __wasi_errno_t {
+    match err.raw_os_error() {
+        Some(libc::EAGAIN) => __WASI_EAGAIN,
+        Some(libc::EBADF) => __WASI_EBADF,
+        Some(libc::EFAULT) => __WASI_EFAULT,
+        Some(libc::EINVAL) => __WASI_EINVAL,
+        Some(libc::EIO) => __WASI_EIO,
+        Some(libc::ENOMEM) => __WASI_ENOMEM,
+        Some(libc::EOVERFLOW) => __WASI_EOVERFLOW,
+        Some(libc::ESPIPE) => __WASI_ESPIPE,
+        Some(libc::EPIPE) => __WASI_EPIPE,
+        _ => map_io_err(err),
+    }
+}
+
+#[cfg(not(all(feature = "host-fs", any(target_os = "linux", target_os = "android"))))]
+fn sock_send_file_zero_copy(
+    _env: &WasiEnv,
+    _sock: __wasi_fd_t,
+    _in_fd: __wasi_fd_t,
+    _offset: __wasi_filesize_t,
+    _count: __wasi_filesize_t,
+) -> Result<Option<__wasi_filesize_t>, __wasi_errno_t> {
+    Ok(None)
+}
+
 /// ### `resolve()`
 /// Resolves a hostname and a port to one or more IP addresses.
 ///
@@ -5385,6 +6663,7 @@ pub fn resolve<M: MemorySize>(
     ret_naddrs: WasmPtr<M::Offset, M>,
 ) -> __wasi_errno_t {
     debug!("wasi::resolve");
+    env.record_syscall("resolve");
 
     let naddrs: usize = wasi_try!(naddrs.try_into().map_err(|_| __WASI_EINVAL));
     let memory = env.memory();
@@ -5393,10 +6672,11 @@ pub fn resolve<M: MemorySize>(
 
     let port = if port > 0 { Some(port) } else { None };
 
-    let found_ips = wasi_try!(env
-        .net()
-        .resolve(host_str.as_str(), port, None)
-        .map_err(net_error_into_wasi_err));
+    let found_ips = match env.dns_resolver.as_ref() {
+        Some(resolver) => resolver.resolve(host_str.as_str(), port, None),
+        None => env.net().resolve(host_str.as_str(), port, None),
+    };
+    let found_ips = wasi_try!(found_ips.map_err(net_error_into_wasi_err));
 
     let mut idx = 0;
     for found_ip in found_ips.iter().take(naddrs) {
@@ -5409,3 +6689,339 @@ pub fn resolve<M: MemorySize>(
 
     __WASI_ESUCCESS
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn buffer_lengths_dont_overflow() {
+        assert_eq!(add_buf_len(1, 2), Ok(3));
+        assert_eq!(add_buf_len(usize::MAX, 1), Err(__WASI_EOVERFLOW));
+
+        assert_eq!(remaining_buf_len(10, 4), 6);
+        assert_eq!(remaining_buf_len(4, 10), 0);
+        if usize::BITS < 64 {
+            assert_eq!(remaining_buf_len(u64::MAX, 0), usize::MAX);
+        }
+    }
+
+    /// A socket taking the data sent to it until it holds `capacity`
+    /// bytes, and recording the size of each send.
+    #[derive(Debug, Clone)]
+    struct TestSocket {
+        sent: Arc<Mutex<Vec<u8>>>,
+        sends: Arc<Mutex<Vec<usize>>>,
+        capacity: usize,
+    }
+
+    impl TestSocket {
+        fn new(capacity: usize) -> Self {
+            Self {
+                sent: Default::default(),
+                sends: Default::default(),
+                capacity,
+            }
+        }
+    }
+
+    impl wasmer_vnet::VirtualSocket for TestSocket {
+        fn set_ttl(&mut self, _ttl: u32) -> wasmer_vnet::Result<()> {
+            Ok(())
+        }
+
+        fn ttl(&self) -> wasmer_vnet::Result<u32> {
+            Ok(64)
+        }
+
+        fn addr_local(&self) -> wasmer_vnet::Result<SocketAddr> {
+            Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+        }
+
+        fn status(&self) -> wasmer_vnet::Result<wasmer_vnet::SocketStatus> {
+            Ok(wasmer_vnet::SocketStatus::Opened)
+        }
+    }
+
+    impl wasmer_vnet::VirtualRawSocket for TestSocket {
+        fn send(&mut self, data: Bytes) -> wasmer_vnet::Result<usize> {
+            let mut sent = self.sent.lock().unwrap();
+            if sent.len() + data.len() > self.capacity {
+                return Err(wasmer_vnet::NetworkError::WouldBlock);
+            }
+            sent.extend_from_slice(&data);
+            self.sends.lock().unwrap().push(data.len());
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> wasmer_vnet::Result<()> {
+            Ok(())
+        }
+
+        fn recv(&mut self) -> wasmer_vnet::Result<wasmer_vnet::SocketReceive> {
+            Err(wasmer_vnet::NetworkError::WouldBlock)
+        }
+
+        fn set_promiscuous(&mut self, _promiscuous: bool) -> wasmer_vnet::Result<()> {
+            Ok(())
+        }
+
+        fn promiscuous(&self) -> wasmer_vnet::Result<bool> {
+            Ok(false)
+        }
+    }
+
+    /// Creates an environment with the file `contents` open in a memory
+    /// file system, and `socket` open, returning their descriptors.
+    fn send_file_env(contents: &[u8], socket: InodeSocket) -> (WasiEnv, __wasi_fd_t, __wasi_fd_t) {
+        use wasmer_vfs::{mem_fs, FileSystem};
+
+        let fs = mem_fs::FileSystem::default();
+        let path = std::path::Path::new("/data.bin");
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open(path)
+            .unwrap()
+            .write_all(contents)
+            .unwrap();
+        let file = fs.new_open_options().read(true).open(path).unwrap();
+
+        let mut env = WasiState::new("test")
+            .set_fs(Box::new(fs))
+            .finalize()
+            .unwrap();
+        let store = wasmer::Store::default();
+        env.set_memory(Memory::new(&store, wasmer::MemoryType::new(1, None, false)).unwrap());
+
+        let state = env.state();
+        let mut inodes = state.inodes.write().unwrap();
+        let kind = Kind::File {
+            handle: Some(file),
+            path: path.to_owned(),
+            fd: None,
+        };
+        let inode = state.fs.create_inode_with_default_stat(
+            inodes.deref_mut(),
+            kind,
+            false,
+            "data.bin".to_string(),
+        );
+        let in_fd = state
+            .fs
+            .create_fd(__WASI_RIGHT_FD_READ, 0, 0, 0, inode)
+            .unwrap();
+        let inode = state.fs.create_inode_with_default_stat(
+            inodes.deref_mut(),
+            Kind::Socket { socket },
+            false,
+            "socket".to_string(),
+        );
+        let sock = state
+            .fs
+            .create_fd(__WASI_RIGHT_SOCK_SEND, 0, 0, 0, inode)
+            .unwrap();
+        drop(inodes);
+
+        (env, in_fd, sock)
+    }
+
+    /// Sends the file with `sock_send_file`, returning its errno, how
+    /// many bytes it sent and the offset of the file after it.
+    fn send_file(
+        env: &WasiEnv,
+        sock: __wasi_fd_t,
+        in_fd: __wasi_fd_t,
+        offset: __wasi_filesize_t,
+        count: __wasi_filesize_t,
+    ) -> (__wasi_errno_t, __wasi_filesize_t, __wasi_filesize_t) {
+        let ret_sent = WasmPtr::<__wasi_filesize_t, Memory32>::new(8);
+        let errno = unsafe { sock_send_file(env, sock, in_fd, offset, count, ret_sent) }.unwrap();
+        let sent = ret_sent.deref(env.memory()).read().unwrap();
+        let file_offset = env
+            .state()
+            .fs
+            .get_fd(in_fd)
+            .unwrap()
+            .offset
+            .load(Ordering::Acquire);
+        (errno, sent, file_offset)
+    }
+
+    fn test_contents(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn send_file_streams_in_chunks() {
+        let contents = test_contents(2 * SEND_FILE_CHUNK_SIZE + 1000);
+        let socket = TestSocket::new(usize::MAX);
+        let (env, in_fd, sock) = send_file_env(
+            &contents,
+            InodeSocket::new(InodeSocketKind::Raw(Box::new(socket.clone()))),
+        );
+
+        // The send stops at the end of the file.
+        let len = contents.len() as u64;
+        assert_eq!(
+            send_file(&env, sock, in_fd, 0, len + 10),
+            (__WASI_ESUCCESS, len, len)
+        );
+        assert_eq!(*socket.sent.lock().unwrap(), contents);
+        assert_eq!(
+            *socket.sends.lock().unwrap(),
+            vec![SEND_FILE_CHUNK_SIZE, SEND_FILE_CHUNK_SIZE, 1000]
+        );
+    }
+
+    #[test]
+    fn send_file_starts_at_the_offset() {
+        let contents = test_contents(10_000);
+        let socket = TestSocket::new(usize::MAX);
+        let (env, in_fd, sock) = send_file_env(
+            &contents,
+            InodeSocket::new(InodeSocketKind::Raw(Box::new(socket.clone()))),
+        );
+
+        assert_eq!(
+            send_file(&env, sock, in_fd, 1000, 5000),
+            (__WASI_ESUCCESS, 5000, 6000)
+        );
+        assert_eq!(*socket.sent.lock().unwrap(), &contents[1000..6000]);
+    }
+
+    #[test]
+    fn send_file_stops_when_the_socket_is_full() {
+        let contents = test_contents(4 * SEND_FILE_CHUNK_SIZE);
+        let socket = TestSocket::new(2 * SEND_FILE_CHUNK_SIZE + 10);
+        let (env, in_fd, sock) = send_file_env(
+            &contents,
+            InodeSocket::new(InodeSocketKind::Raw(Box::new(socket.clone()))),
+        );
+
+        // The bytes sent before the socket got full are reported.
+        let sent = 2 * SEND_FILE_CHUNK_SIZE as u64;
+        assert_eq!(
+            send_file(&env, sock, in_fd, 0, contents.len() as u64),
+            (__WASI_ESUCCESS, sent, sent)
+        );
+        assert_eq!(
+            *socket.sent.lock().unwrap(),
+            &contents[..2 * SEND_FILE_CHUNK_SIZE]
+        );
+
+        // Without any progress, the socket would block.
+        let (errno, _, file_offset) = send_file(&env, sock, in_fd, sent, contents.len() as u64);
+        assert_eq!((errno, file_offset), (__WASI_EAGAIN, sent));
+    }
+
+    #[cfg(all(feature = "host-fs", any(target_os = "linux", target_os = "android")))]
+    #[test]
+    fn send_file_chunks_fall_back_when_sendfile_is_unsupported() {
+        let error = |errno| Err(io::Error::from_raw_os_error(errno));
+
+        // The file is streamed instead.
+        assert_eq!(
+            sock_send_file_chunks(0, 10, |_, _| error(libc::EINVAL)),
+            Ok(None)
+        );
+        assert_eq!(
+            sock_send_file_chunks(0, 10, |_, _| error(libc::ENOSYS)),
+            Ok(None)
+        );
+        assert_eq!(
+            sock_send_file_chunks(0, 10, |_, _| error(libc::EBADF)),
+            Err(__WASI_EBADF)
+        );
+        assert_eq!(
+            sock_send_file_chunks(0, 10, |_, _| error(libc::EAGAIN)),
+            Err(__WASI_EAGAIN)
+        );
+
+        // After a first chunk, the bytes already sent are reported.
+        let mut chunks = Vec::new();
+        let sent = sock_send_file_chunks(5, 3 * MAX_SEND_FILE, |offset, sub_count| {
+            chunks.push((offset, sub_count));
+            match chunks.len() {
+                1 => Ok(sub_count),
+                _ => error(libc::EINVAL),
+            }
+        });
+        assert_eq!(sent, Ok(Some(MAX_SEND_FILE)));
+        assert_eq!(
+            chunks,
+            vec![(5, MAX_SEND_FILE), (5 + MAX_SEND_FILE, MAX_SEND_FILE)]
+        );
+
+        // A short chunk means the socket is full, no chunk the end of
+        // the file.
+        assert_eq!(sock_send_file_chunks(0, 10, |_, _| Ok(4)), Ok(Some(4)));
+        assert_eq!(sock_send_file_chunks(0, 10, |_, _| Ok(0)), Ok(Some(0)));
+    }
+
+    #[cfg(all(
+        feature = "host-fs",
+        feature = "host-vnet",
+        any(target_os = "linux", target_os = "android")
+    ))]
+    #[test]
+    fn send_file_sends_host_files_to_host_sockets() {
+        use std::net::TcpListener;
+        use wasmer_vnet::VirtualNetworking;
+
+        let contents = test_contents(3 * SEND_FILE_CHUNK_SIZE);
+        let mut host_file = tempfile::NamedTempFile::new().unwrap();
+        host_file.write_all(&contents).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let peer = listener.local_addr().unwrap();
+        let socket = wasmer_wasi_local_networking::LocalNetworking::default()
+            .connect_tcp(peer, peer, None)
+            .unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let receiver = std::thread::spawn(move || {
+            let mut received = vec![0; contents.len() - 100];
+            stream.read_exact(&mut received).unwrap();
+            received
+        });
+
+        let (env, _, sock) =
+            send_file_env(b"", InodeSocket::new(InodeSocketKind::TcpStream(socket)));
+        let file = wasmer_vfs::host_fs::File::new(
+            host_file.reopen().unwrap(),
+            host_file.path().to_owned(),
+            true,
+            false,
+            false,
+        );
+        let in_fd = {
+            let state = env.state();
+            let mut inodes = state.inodes.write().unwrap();
+            let kind = Kind::File {
+                handle: Some(Box::new(file)),
+                path: host_file.path().to_owned(),
+                fd: None,
+            };
+            let inode = state.fs.create_inode_with_default_stat(
+                inodes.deref_mut(),
+                kind,
+                false,
+                "host.bin".to_string(),
+            );
+            state
+                .fs
+                .create_fd(__WASI_RIGHT_FD_READ, 0, 0, 0, inode)
+                .unwrap()
+        };
+
+        let len = 3 * SEND_FILE_CHUNK_SIZE as u64;
+        assert_eq!(
+            send_file(&env, sock, in_fd, 100, len),
+            (__WASI_ESUCCESS, len - 100, len)
+        );
+        assert_eq!(
+            receiver.join().unwrap(),
+            &test_contents(len as usize)[100..]
+        );
+    }
+}
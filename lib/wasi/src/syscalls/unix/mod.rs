@@ -1,9 +1,12 @@
 use crate::syscalls::types::*;
+use crate::WasiTtyState;
 use libc::{
     clock_getres, clock_gettime, timespec, CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID,
     CLOCK_REALTIME, CLOCK_THREAD_CPUTIME_ID,
 };
+use std::fmt;
 use std::mem;
+use std::sync::Mutex;
 use wasmer::WasmRef;
 
 pub fn platform_clock_res_get(
@@ -25,6 +28,9 @@ pub fn platform_clock_res_get(
         };
         (clock_getres(unix_clock_id, &mut timespec_out), timespec_out)
     };
+    if output != 0 {
+        return Err(__WASI_EINVAL);
+    }
 
     let t_out = (timespec_out.tv_sec * 1_000_000_000).wrapping_add(timespec_out.tv_nsec);
     Ok(t_out)
@@ -52,7 +58,123 @@ pub fn platform_clock_time_get(
             timespec_out,
         )
     };
+    if output != 0 {
+        return Err(__WASI_EINVAL);
+    }
 
     let t_out = (timespec_out.tv_sec * 1_000_000_000).wrapping_add(timespec_out.tv_nsec);
     Ok(t_out)
 }
+
+/// Reads the state of the terminal of the host: the size of the one
+/// of stdout, and the echo and line buffering of the one of stdin.
+pub fn platform_tty_get() -> WasiTtyState {
+    let mut tty = WasiTtyState::default();
+    unsafe {
+        tty.stdin_tty = libc::isatty(libc::STDIN_FILENO) == 1;
+        tty.stdout_tty = libc::isatty(libc::STDOUT_FILENO) == 1;
+        tty.stderr_tty = libc::isatty(libc::STDERR_FILENO) == 1;
+
+        let mut size: libc::winsize = mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) == 0 && size.ws_col > 0 {
+            tty.cols = size.ws_col as u32;
+            tty.rows = size.ws_row as u32;
+            // Most terminals don't report their size in pixels
+            if size.ws_xpixel > 0 {
+                tty.width = size.ws_xpixel as u32;
+                tty.height = size.ws_ypixel as u32;
+            }
+        }
+
+        let mut termios: libc::termios = mem::zeroed();
+        if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) == 0 {
+            tty.echo = termios.c_lflag & libc::ECHO != 0;
+            tty.line_buffered = termios.c_lflag & libc::ICANON != 0;
+        }
+    }
+
+    tty
+}
+
+/// The terminal of the host, changed on behalf of the guest.
+///
+/// The state the terminal was in before the first change is restored
+/// when this is dropped, so the guest can't leave it in raw mode.
+#[derive(Default)]
+pub struct HostTty {
+    original: Mutex<Option<libc::termios>>,
+}
+
+impl HostTty {
+    /// Reads the state of the terminal of the host.
+    pub fn get(&self) -> WasiTtyState {
+        platform_tty_get()
+    }
+
+    /// Sets the echo and line buffering of the terminal of stdin, if it
+    /// is a terminal.
+    pub fn set(&self, tty: &WasiTtyState) {
+        let mut original = self.original.lock().unwrap();
+        unsafe {
+            let mut termios: libc::termios = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return;
+            }
+
+            let mut c_lflag = termios.c_lflag & !(libc::ECHO | libc::ICANON);
+            if tty.echo {
+                c_lflag |= libc::ECHO;
+            }
+            if tty.line_buffered {
+                c_lflag |= libc::ICANON;
+            }
+            if c_lflag != termios.c_lflag {
+                if original.is_none() {
+                    *original = Some(termios);
+                }
+                termios.c_lflag = c_lflag;
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            }
+        }
+    }
+}
+
+impl Drop for HostTty {
+    fn drop(&mut self) {
+        if let Ok(Some(termios)) = self.original.get_mut().map(Option::take) {
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for HostTty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changed = self
+            .original
+            .lock()
+            .map(|original| original.is_some())
+            .unwrap_or_default();
+        f.debug_struct("HostTty")
+            .field("changed", &changed)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_time_clocks() {
+        for clock_id in [
+            __WASI_CLOCK_PROCESS_CPUTIME_ID,
+            __WASI_CLOCK_THREAD_CPUTIME_ID,
+        ] {
+            let before = platform_clock_time_get(clock_id, 1).unwrap();
+            let after = platform_clock_time_get(clock_id, 1).unwrap();
+            assert!(before > 0 && after >= before, "clock {}", clock_id);
+        }
+    }
+}
@@ -373,6 +373,35 @@ pub(crate) fn path_unlink_file(
     super::path_unlink_file::<MemoryType>(env, fd, path, path_len)
 }
 
+pub(crate) fn path_notify(
+    env: &WasiEnv,
+    fd: __wasi_fd_t,
+    path: WasmPtr<u8, MemoryType>,
+    path_len: MemoryOffset,
+    mask: __wasi_notifyflags_t,
+    ret_fd: WasmPtr<__wasi_fd_t, MemoryType>,
+) -> __wasi_errno_t {
+    super::path_notify::<MemoryType>(env, fd, path, path_len, mask, ret_fd)
+}
+
+pub(crate) fn shm_open(
+    env: &WasiEnv,
+    name: WasmPtr<u8, MemoryType>,
+    name_len: MemoryOffset,
+    size: __wasi_filesize_t,
+    ret_fd: WasmPtr<__wasi_fd_t, MemoryType>,
+) -> __wasi_errno_t {
+    super::shm_open::<MemoryType>(env, name, name_len, size, ret_fd)
+}
+
+pub(crate) fn shm_unlink(
+    env: &WasiEnv,
+    name: WasmPtr<u8, MemoryType>,
+    name_len: MemoryOffset,
+) -> __wasi_errno_t {
+    super::shm_unlink::<MemoryType>(env, name, name_len)
+}
+
 pub(crate) fn poll_oneoff(
     env: &WasiEnv,
     in_: WasmPtr<__wasi_subscription_t, MemoryType>,
@@ -387,7 +416,15 @@ pub(crate) fn proc_exit(env: &WasiEnv, code: __wasi_exitcode_t) -> Result<(), Wa
     super::proc_exit(env, code)
 }
 
-pub(crate) fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
+pub(crate) fn proc_fork(
+    env: &WasiEnv,
+    user_data: u64,
+    ret_pid: WasmPtr<__wasi_pid_t, MemoryType>,
+) -> __wasi_errno_t {
+    super::proc_fork::<MemoryType>(env, user_data, ret_pid)
+}
+
+pub(crate) fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> Result<__wasi_errno_t, WasiError> {
     super::proc_raise(env, sig)
 }
 
@@ -912,6 +949,15 @@ pub(crate) fn sock_bind(
     super::sock_bind::<MemoryType>(env, sock, addr)
 }
 
+pub(crate) fn sock_bind_unix(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    path: WasmPtr<u8, MemoryType>,
+    path_len: MemoryOffset,
+) -> __wasi_errno_t {
+    super::sock_bind_unix::<MemoryType>(env, sock, path, path_len)
+}
+
 pub(crate) fn sock_listen(
     env: &WasiEnv,
     sock: __wasi_fd_t,
@@ -938,6 +984,24 @@ pub(crate) fn sock_connect(
     super::sock_connect::<MemoryType>(env, sock, addr)
 }
 
+pub(crate) fn sock_connect_unix(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    path: WasmPtr<u8, MemoryType>,
+    path_len: MemoryOffset,
+) -> __wasi_errno_t {
+    super::sock_connect_unix::<MemoryType>(env, sock, path, path_len)
+}
+
+pub(crate) fn sock_upgrade_tls(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    server_name: WasmPtr<u8, MemoryType>,
+    server_name_len: MemoryOffset,
+) -> __wasi_errno_t {
+    super::sock_upgrade_tls::<MemoryType>(env, sock, server_name, server_name_len)
+}
+
 pub(crate) fn sock_recv(
     env: &WasiEnv,
     sock: __wasi_fd_t,
@@ -1,8 +1,17 @@
 use crate::syscalls::types::*;
 use chrono::prelude::*;
 use std::mem;
+use wasm_bindgen::prelude::*;
 use wasmer::WasmRef;
 
+#[wasm_bindgen]
+extern "C" {
+    /// `performance.now()`, the milliseconds elapsed since the start of
+    /// the page or of the worker, which is missing from old runtimes.
+    #[wasm_bindgen(catch, js_namespace = performance, js_name = now)]
+    fn performance_now() -> Result<f64, JsValue>;
+}
+
 pub fn platform_clock_res_get(
     clock_id: __wasi_clockid_t,
     resolution: WasmRef<__wasi_timestamp_t>,
@@ -10,8 +19,10 @@ pub fn platform_clock_res_get(
     let t_out = match clock_id {
         __WASI_CLOCK_MONOTONIC => 10_000_000,
         __WASI_CLOCK_REALTIME => 1,
-        __WASI_CLOCK_PROCESS_CPUTIME_ID => 1,
-        __WASI_CLOCK_THREAD_CPUTIME_ID => 1,
+        // The browsers coarsen `performance.now()` to 100µs when the
+        // page isn't isolated.
+        __WASI_CLOCK_PROCESS_CPUTIME_ID => 100_000,
+        __WASI_CLOCK_THREAD_CPUTIME_ID => 100_000,
         _ => return Err(__WASI_EINVAL),
     };
     Ok(t_out)
@@ -21,6 +32,17 @@ pub fn platform_clock_time_get(
     clock_id: __wasi_clockid_t,
     precision: __wasi_timestamp_t,
 ) -> Result<i64, __wasi_errno_t> {
-    let new_time: DateTime<Local> = Local::now();
-    Ok(new_time.timestamp_nanos() as i64)
+    match clock_id {
+        // JavaScript doesn't expose the CPU time. The best estimate is
+        // the time elapsed since the start of the page or of the worker
+        // running the instance, whose thread is busy running it.
+        __WASI_CLOCK_PROCESS_CPUTIME_ID | __WASI_CLOCK_THREAD_CPUTIME_ID => {
+            let millis = performance_now().map_err(|_| __WASI_ENOTSUP)?;
+            Ok((millis * 1_000_000.0) as i64)
+        }
+        _ => {
+            let new_time: DateTime<Local> = Local::now();
+            Ok(new_time.timestamp_nanos() as i64)
+        }
+    }
 }
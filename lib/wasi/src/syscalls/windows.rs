@@ -1,6 +1,9 @@
 use crate::syscalls::types::*;
+use crate::WasiTtyState;
+use std::sync::Mutex;
 use tracing::debug;
 use wasmer::WasmRef;
+use winapi::shared::minwindef::FILETIME;
 
 pub fn platform_clock_res_get(
     clock_id: __wasi_clockid_t,
@@ -12,12 +15,9 @@ pub fn platform_clock_res_get(
         __WASI_CLOCK_MONOTONIC => 10_000_000,
         // TODO: verify or compute this
         __WASI_CLOCK_REALTIME => 1,
-        __WASI_CLOCK_PROCESS_CPUTIME_ID => {
-            return Err(__WASI_EINVAL);
-        }
-        __WASI_CLOCK_THREAD_CPUTIME_ID => {
-            return Err(__WASI_EINVAL);
-        }
+        // The unit of the CPU times, which Windows updates at every tick
+        // of the scheduler, typically every 15.6ms.
+        __WASI_CLOCK_PROCESS_CPUTIME_ID | __WASI_CLOCK_THREAD_CPUTIME_ID => 100,
         _ => return Err(__WASI_EINVAL),
     };
     Ok(resolution_val)
@@ -42,12 +42,151 @@ pub fn platform_clock_time_get(
             duration.as_nanos() as u64
         }
         __WASI_CLOCK_PROCESS_CPUTIME_ID => {
-            unimplemented!("wasi::platform_clock_time_get(__WASI_CLOCK_PROCESS_CPUTIME_ID, ..)")
+            use winapi::um::processthreadsapi::{GetCurrentProcess, GetProcessTimes};
+
+            cpu_time(|creation, exit, kernel, user| unsafe {
+                GetProcessTimes(GetCurrentProcess(), creation, exit, kernel, user)
+            })?
         }
         __WASI_CLOCK_THREAD_CPUTIME_ID => {
-            unimplemented!("wasi::platform_clock_time_get(__WASI_CLOCK_THREAD_CPUTIME_ID, ..)")
+            use winapi::um::processthreadsapi::{GetCurrentThread, GetThreadTimes};
+
+            cpu_time(|creation, exit, kernel, user| unsafe {
+                GetThreadTimes(GetCurrentThread(), creation, exit, kernel, user)
+            })?
         }
         _ => return Err(__WASI_EINVAL),
     };
     Ok(nanos as i64)
 }
+
+/// Returns the CPU time, in nanoseconds, spent in kernel and user mode
+/// as reported by `get_times`, i.e. `GetProcessTimes` or
+/// `GetThreadTimes`.
+fn cpu_time(
+    get_times: impl FnOnce(
+        &mut FILETIME,
+        &mut FILETIME,
+        &mut FILETIME,
+        &mut FILETIME,
+    ) -> winapi::shared::minwindef::BOOL,
+) -> Result<u64, __wasi_errno_t> {
+    let (mut creation, mut exit, mut kernel, mut user) = unsafe {
+        (
+            std::mem::zeroed(),
+            std::mem::zeroed(),
+            std::mem::zeroed(),
+            std::mem::zeroed(),
+        )
+    };
+    if get_times(&mut creation, &mut exit, &mut kernel, &mut user) == 0 {
+        debug!("Error in wasi::platform_clock_time_get: {}", unsafe {
+            winapi::um::errhandlingapi::GetLastError()
+        });
+        return Err(__WASI_EIO);
+    }
+
+    // The times are counted in units of 100 nanoseconds.
+    let hundreds =
+        |time: FILETIME| (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime);
+    Ok((hundreds(kernel) + hundreds(user)) * 100)
+}
+
+/// Reads the state of the console of the host: the size of the window
+/// of stdout, and the echo and line buffering of stdin.
+pub fn platform_tty_get() -> WasiTtyState {
+    use winapi::um::consoleapi::GetConsoleMode;
+    use winapi::um::processenv::GetStdHandle;
+    use winapi::um::winbase::{STD_ERROR_HANDLE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE};
+    use winapi::um::wincon::{
+        GetConsoleScreenBufferInfo, CONSOLE_SCREEN_BUFFER_INFO, ENABLE_ECHO_INPUT,
+        ENABLE_LINE_INPUT,
+    };
+
+    let mut tty = WasiTtyState::default();
+    unsafe {
+        let stdin = GetStdHandle(STD_INPUT_HANDLE);
+        let stdout = GetStdHandle(STD_OUTPUT_HANDLE);
+        let stderr = GetStdHandle(STD_ERROR_HANDLE);
+
+        let mut mode = 0;
+        tty.stdout_tty = GetConsoleMode(stdout, &mut mode) != 0;
+        tty.stderr_tty = GetConsoleMode(stderr, &mut mode) != 0;
+        tty.stdin_tty = GetConsoleMode(stdin, &mut mode) != 0;
+        if tty.stdin_tty {
+            tty.echo = mode & ENABLE_ECHO_INPUT != 0;
+            tty.line_buffered = mode & ENABLE_LINE_INPUT != 0;
+        }
+
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+        if GetConsoleScreenBufferInfo(stdout, &mut info) != 0 {
+            tty.cols = (info.srWindow.Right - info.srWindow.Left + 1) as u32;
+            tty.rows = (info.srWindow.Bottom - info.srWindow.Top + 1) as u32;
+        }
+    }
+
+    tty
+}
+
+/// The console of the host, changed on behalf of the guest.
+///
+/// The mode the console was in before the first change is restored
+/// when this is dropped, so the guest can't leave it in raw mode.
+#[derive(Debug, Default)]
+pub struct HostTty {
+    original: Mutex<Option<u32>>,
+}
+
+impl HostTty {
+    /// Reads the state of the console of the host.
+    pub fn get(&self) -> WasiTtyState {
+        platform_tty_get()
+    }
+
+    /// Sets the echo and line buffering of the console of stdin, if it
+    /// is a console.
+    pub fn set(&self, tty: &WasiTtyState) {
+        use winapi::um::consoleapi::{GetConsoleMode, SetConsoleMode};
+        use winapi::um::processenv::GetStdHandle;
+        use winapi::um::winbase::STD_INPUT_HANDLE;
+        use winapi::um::wincon::{ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT};
+
+        let mut original = self.original.lock().unwrap();
+        unsafe {
+            let stdin = GetStdHandle(STD_INPUT_HANDLE);
+            let mut mode = 0;
+            if GetConsoleMode(stdin, &mut mode) == 0 {
+                return;
+            }
+
+            let mut new_mode = mode & !(ENABLE_ECHO_INPUT | ENABLE_LINE_INPUT);
+            if tty.line_buffered {
+                new_mode |= ENABLE_LINE_INPUT;
+                // The console only echoes line buffered input
+                if tty.echo {
+                    new_mode |= ENABLE_ECHO_INPUT;
+                }
+            }
+            if new_mode != mode {
+                if original.is_none() {
+                    *original = Some(mode);
+                }
+                SetConsoleMode(stdin, new_mode);
+            }
+        }
+    }
+}
+
+impl Drop for HostTty {
+    fn drop(&mut self) {
+        use winapi::um::consoleapi::SetConsoleMode;
+        use winapi::um::processenv::GetStdHandle;
+        use winapi::um::winbase::STD_INPUT_HANDLE;
+
+        if let Ok(Some(mode)) = self.original.get_mut().map(Option::take) {
+            unsafe {
+                SetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), mode);
+            }
+        }
+    }
+}
@@ -0,0 +1,427 @@
+//! Helpers to write integration tests of WASI guest modules.
+//!
+//! A [`WasiTestFixture`] builds a throwaway [`WasiEnv`] whose file
+//! system lives in memory, whose standard input is scripted, whose
+//! standard output and error are captured, and whose clocks only move
+//! when the test says so.
+//!
+//! ```rust,ignore
+//! use wasmer_wasi::testing::WasiTestFixture;
+//!
+//! let mut fixture = WasiTestFixture::new("greet");
+//! fixture.file("/data/name.txt", "Ferris")?.stdin("hello\n");
+//! fixture.builder().arg("/data/name.txt");
+//! fixture.clock().set(1_000_000_000);
+//!
+//! let mut test_env = fixture.finalize()?;
+//! // Instantiate and run the module with `test_env.env_mut()`...
+//! test_env.assert_stdout("hello Ferris\n");
+//! ```
+//!
+//! This module is only available with the `testing` feature.
+
+use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use wasmer_vbus::VirtualBus;
+use wasmer_vfs::{mem_fs, FileSystem, FsError};
+use wasmer_vnet::VirtualNetworking;
+
+use crate::syscalls::types::*;
+use crate::{
+    Pipe, PluggableRuntimeImplementation, SharedMemoryRegistry, ThreadScheduler, WasiEnv,
+    WasiError, WasiRuntimeImplementation, WasiState, WasiStateBuilder, WasiStateCreationError,
+    WasiThreadError, WasiThreadId, WasiTtyState,
+};
+
+/// A clock controlled by the test, shared by every clock of the guest.
+///
+/// The time is expressed in nanoseconds, and starts at zero.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl VirtualClock {
+    /// Creates a clock set to zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current time, in nanoseconds.
+    pub fn now(&self) -> u64 {
+        self.nanos.load(Ordering::SeqCst)
+    }
+
+    /// Sets the current time, in nanoseconds.
+    pub fn set(&self, nanos: u64) {
+        self.nanos.store(nanos, Ordering::SeqCst);
+    }
+
+    /// Moves the clock forward by `nanos` nanoseconds.
+    pub fn advance(&self, nanos: u64) {
+        self.nanos.fetch_add(nanos, Ordering::SeqCst);
+    }
+}
+
+/// A runtime which behaves like the [`PluggableRuntimeImplementation`],
+/// except that the guest reads the time from a [`VirtualClock`].
+#[derive(Debug)]
+struct TestRuntime {
+    inner: PluggableRuntimeImplementation,
+    clock: VirtualClock,
+}
+
+impl WasiRuntimeImplementation for TestRuntime {
+    fn bus(&self) -> &(dyn VirtualBus) {
+        self.inner.bus()
+    }
+
+    fn networking(&self) -> &(dyn VirtualNetworking) {
+        self.inner.networking()
+    }
+
+    fn thread_generate_id(&self) -> WasiThreadId {
+        self.inner.thread_generate_id()
+    }
+
+    fn tty_get(&self) -> WasiTtyState {
+        self.inner.tty_get()
+    }
+
+    fn tty_set(&self, tty_state: WasiTtyState) {
+        self.inner.tty_set(tty_state)
+    }
+
+    fn thread_scheduler(&self) -> Option<&(dyn ThreadScheduler)> {
+        self.inner.thread_scheduler()
+    }
+
+    fn thread_spawn(
+        &self,
+        callback: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.inner.thread_spawn(callback)
+    }
+
+    fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
+        self.inner.thread_parallelism()
+    }
+
+    fn yield_now(&self, id: WasiThreadId) -> Result<(), WasiError> {
+        self.inner.yield_now(id)
+    }
+
+    fn getpid(&self) -> Option<u32> {
+        self.inner.getpid()
+    }
+
+    fn shared_memory(&self) -> Option<&SharedMemoryRegistry> {
+        self.inner.shared_memory()
+    }
+
+    fn clock_time_get(
+        &self,
+        clock_id: __wasi_clockid_t,
+        _precision: __wasi_timestamp_t,
+    ) -> Result<i64, __wasi_errno_t> {
+        match clock_id {
+            __WASI_CLOCK_REALTIME
+            | __WASI_CLOCK_MONOTONIC
+            | __WASI_CLOCK_PROCESS_CPUTIME_ID
+            | __WASI_CLOCK_THREAD_CPUTIME_ID => Ok(self.clock.now() as i64),
+            _ => Err(__WASI_EINVAL),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn block_on<'a>(&self, task: Pin<Box<dyn Future<Output = ()> + Send + 'a>>) {
+        self.inner.block_on(task)
+    }
+}
+
+/// Builds a [`WasiTestEnv`].
+///
+/// The guest sees the in-memory file system preopened at `/`.
+pub struct WasiTestFixture {
+    builder: WasiStateBuilder,
+    fs: mem_fs::FileSystem,
+    stdin: Pipe,
+    clock: VirtualClock,
+}
+
+impl fmt::Debug for WasiTestFixture {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("WasiTestFixture")
+            .field("fs", &self.fs)
+            .field("clock", &self.clock)
+            .finish()
+    }
+}
+
+impl WasiTestFixture {
+    /// Creates a fixture for a program named `program_name`, with an
+    /// empty file system, an empty standard input, and the clock set
+    /// to zero.
+    pub fn new(program_name: &str) -> Self {
+        Self {
+            builder: WasiState::new(program_name),
+            fs: mem_fs::FileSystem::default(),
+            stdin: Pipe::new(),
+            clock: VirtualClock::new(),
+        }
+    }
+
+    /// Gives access to the underlying [`WasiStateBuilder`], e.g. to
+    /// set the arguments or the environment variables.
+    ///
+    /// The file system, the standard streams and the runtime are
+    /// overridden by [`Self::finalize`].
+    pub fn builder(&mut self) -> &mut WasiStateBuilder {
+        &mut self.builder
+    }
+
+    /// Creates the directory `path`, and all its missing ancestors.
+    pub fn dir<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, FsError> {
+        create_dir_all(&self.fs, &absolute(path.as_ref()))?;
+
+        Ok(self)
+    }
+
+    /// Creates the file `path` with the given contents, and all its
+    /// missing ancestors. An existing file is overwritten.
+    pub fn file<P, C>(&mut self, path: P, contents: C) -> Result<&mut Self, FsError>
+    where
+        P: AsRef<Path>,
+        C: AsRef<[u8]>,
+    {
+        let path = absolute(path.as_ref());
+        if let Some(parent) = path.parent() {
+            create_dir_all(&self.fs, parent)?;
+        }
+
+        let mut file = self
+            .fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        file.write_all(contents.as_ref())?;
+
+        Ok(self)
+    }
+
+    /// Appends `data` to the standard input of the guest.
+    pub fn stdin<D: AsRef<[u8]>>(&mut self, data: D) -> &mut Self {
+        // Writing to a pipe never fails.
+        let _ = self.stdin.write_all(data.as_ref());
+
+        self
+    }
+
+    /// Returns the clock the guest reads the time from.
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    /// Produces the [`WasiTestEnv`].
+    pub fn finalize(&mut self) -> Result<WasiTestEnv, WasiStateCreationError> {
+        let stdout = Pipe::new();
+        let stderr = Pipe::new();
+
+        let env = self
+            .builder
+            .set_fs(Box::new(self.fs.clone()))
+            .preopen_dir("/")?
+            .stdin(Box::new(self.stdin.clone()))
+            .stdout(Box::new(stdout.clone()))
+            .stderr(Box::new(stderr.clone()))
+            .runtime(TestRuntime {
+                inner: PluggableRuntimeImplementation::default(),
+                clock: self.clock.clone(),
+            })
+            .finalize()?;
+
+        Ok(WasiTestEnv {
+            env,
+            fs: self.fs.clone(),
+            stdin: self.stdin.clone(),
+            stdout,
+            stderr,
+            clock: self.clock.clone(),
+        })
+    }
+}
+
+/// A [`WasiEnv`] built by a [`WasiTestFixture`], along with handles
+/// to inspect what the guest did.
+#[derive(Debug)]
+pub struct WasiTestEnv {
+    env: WasiEnv,
+    fs: mem_fs::FileSystem,
+    stdin: Pipe,
+    stdout: Pipe,
+    stderr: Pipe,
+    clock: VirtualClock,
+}
+
+impl WasiTestEnv {
+    /// The environment to instantiate the guest module with.
+    pub fn env(&self) -> &WasiEnv {
+        &self.env
+    }
+
+    /// The environment to instantiate the guest module with.
+    pub fn env_mut(&mut self) -> &mut WasiEnv {
+        &mut self.env
+    }
+
+    /// Appends `data` to the standard input of the guest.
+    pub fn write_stdin<D: AsRef<[u8]>>(&mut self, data: D) {
+        let _ = self.stdin.write_all(data.as_ref());
+    }
+
+    /// Takes everything the guest wrote to its standard output since
+    /// the last call.
+    pub fn stdout(&mut self) -> String {
+        drain(&mut self.stdout)
+    }
+
+    /// Takes everything the guest wrote to its standard error since
+    /// the last call.
+    pub fn stderr(&mut self) -> String {
+        drain(&mut self.stderr)
+    }
+
+    /// Asserts that the guest wrote exactly `expected` to its standard
+    /// output since the last check.
+    #[track_caller]
+    pub fn assert_stdout(&mut self, expected: &str) {
+        assert_eq!(self.stdout(), expected, "unexpected standard output");
+    }
+
+    /// Asserts that the guest wrote exactly `expected` to its standard
+    /// error since the last check.
+    #[track_caller]
+    pub fn assert_stderr(&mut self, expected: &str) {
+        assert_eq!(self.stderr(), expected, "unexpected standard error");
+    }
+
+    /// Reads the file `path` of the in-memory file system.
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, FsError> {
+        let mut file = self
+            .fs
+            .new_open_options()
+            .read(true)
+            .open(absolute(path.as_ref()))?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        Ok(contents)
+    }
+
+    /// The in-memory file system seen by the guest.
+    pub fn fs(&self) -> &mem_fs::FileSystem {
+        &self.fs
+    }
+
+    /// Returns the clock the guest reads the time from.
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+}
+
+/// Paths of the in-memory file system must be absolute.
+fn absolute(path: &Path) -> PathBuf {
+    Path::new("/").join(path)
+}
+
+fn create_dir_all(fs: &mem_fs::FileSystem, path: &Path) -> Result<(), FsError> {
+    match fs.metadata(path) {
+        Ok(metadata) if metadata.is_dir() => return Ok(()),
+        Ok(_) => return Err(FsError::BaseNotDirectory),
+        Err(_) => {}
+    }
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(fs, parent)?;
+    }
+
+    fs.create_dir(path)
+}
+
+fn drain(pipe: &mut Pipe) -> String {
+    let mut output = Vec::new();
+    // Reading from a pipe never fails.
+    let _ = pipe.read_to_end(&mut output);
+
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fixture() {
+        let mut fixture = WasiTestFixture::new("test");
+        fixture
+            .file("data/input.txt", "input")
+            .unwrap()
+            .dir("/output")
+            .unwrap()
+            .stdin("hello");
+        fixture.clock().set(42);
+
+        let mut test_env = fixture.finalize().unwrap();
+        let state = test_env.env().state.clone();
+
+        let mut stdin = String::new();
+        state
+            .fs
+            .get_fd_reader(&state.inodes, __WASI_STDIN_FILENO)
+            .unwrap()
+            .read_to_string(&mut stdin)
+            .unwrap();
+        assert_eq!(stdin, "hello");
+
+        state
+            .fs
+            .get_fd_writer(&state.inodes, __WASI_STDOUT_FILENO)
+            .unwrap()
+            .write_all(b"world")
+            .unwrap();
+        test_env.assert_stdout("world");
+        test_env.assert_stdout("");
+
+        assert_eq!(test_env.read_file("/data/input.txt").unwrap(), b"input");
+        assert!(test_env
+            .fs()
+            .metadata(Path::new("/output"))
+            .unwrap()
+            .is_dir());
+
+        let runtime = test_env.env().runtime();
+        assert_eq!(runtime.clock_time_get(__WASI_CLOCK_MONOTONIC, 1), Ok(42));
+        test_env.clock().advance(8);
+        assert_eq!(runtime.clock_time_get(__WASI_CLOCK_REALTIME, 1), Ok(50));
+
+        // The other hooks are the ones of the default runtime.
+        let raw = WasiTtyState {
+            echo: false,
+            ..Default::default()
+        };
+        runtime.tty_set(raw.clone());
+        assert_eq!(runtime.tty_get(), raw);
+        assert!(runtime.shared_memory().is_some());
+    }
+}
@@ -0,0 +1,318 @@
+//! The timers of the clock subscriptions of `poll_oneoff`, see
+//! [`TimerWheel`].
+
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::Duration;
+
+use crate::syscalls::platform_clock_time_get;
+use crate::syscalls::types::__WASI_CLOCK_MONOTONIC;
+
+/// The number of slots of a wheel.
+const SLOTS: u64 = 1024;
+
+/// The default duration of a tick of a wheel, which is the resolution
+/// of its timers.
+pub(crate) const DEFAULT_TICK: Duration = Duration::from_micros(100);
+
+/// The current time of the monotonic clock, in nanoseconds.
+pub(crate) fn monotonic_now() -> u64 {
+    platform_clock_time_get(__WASI_CLOCK_MONOTONIC, 1_000).unwrap_or(0) as u64
+}
+
+/// A hashed timer wheel shared by the threads of a process, so that
+/// thousands of pending timers, e.g. the ones of an async runtime
+/// compiled to WASIX, cost a slot in a wheel each rather than a
+/// sleeping loop each.
+///
+/// The deadlines are rounded up to the next tick, and the timers of a
+/// tick all fire together, in a single wake up of the thread driving
+/// the wheel. That thread is only spawned once a timer is inserted,
+/// and exits with the wheel.
+///
+/// Where no thread can be spawned, i.e. on the web, the timers expire
+/// by themselves when they're checked after their deadline.
+#[derive(Debug)]
+pub(crate) struct TimerWheel {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    tick: u64,
+    state: Mutex<State>,
+    /// Wakes the driver when a timer may fire before it planned to
+    /// wake up, or when the wheel is dropped.
+    driver: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The timers, in the slot of their tick modulo `SLOTS`.
+    slots: Vec<Vec<Entry>>,
+    /// The first tick whose timers haven't fired yet.
+    current_tick: u64,
+    /// The tick the driver is sleeping until, if it's sleeping.
+    wake_tick: Option<u64>,
+    next_id: u64,
+    driver_spawned: bool,
+    dropped: bool,
+}
+
+#[derive(Debug)]
+struct Entry {
+    id: u64,
+    tick: u64,
+    signal: Arc<Signal>,
+}
+
+/// Raised when a timer fires.
+#[derive(Debug, Default)]
+struct Signal {
+    fired: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Signal {
+    fn raise(&self) {
+        *self.fired.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+}
+
+impl Default for TimerWheel {
+    fn default() -> Self {
+        Self::with_tick(DEFAULT_TICK)
+    }
+}
+
+impl TimerWheel {
+    /// Creates a wheel whose timers have a resolution of `tick`.
+    pub(crate) fn with_tick(tick: Duration) -> Self {
+        let tick = (tick.as_nanos() as u64).max(1);
+
+        Self {
+            shared: Arc::new(Shared {
+                tick,
+                state: Mutex::new(State {
+                    slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+                    current_tick: monotonic_now() / tick,
+                    ..Default::default()
+                }),
+                driver: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Inserts a timer firing once the monotonic clock reaches
+    /// `deadline`, in nanoseconds. The timer is cancelled when it's
+    /// dropped.
+    pub(crate) fn insert(&self, deadline: u64) -> Timer {
+        let shared = &self.shared;
+        // Rounded up, so that a timer never fires early.
+        let tick = deadline.saturating_add(shared.tick - 1) / shared.tick;
+        let signal = Arc::new(Signal::default());
+
+        let mut state = shared.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+
+        if tick < state.current_tick {
+            signal.raise();
+        } else {
+            state.slots[(tick % SLOTS) as usize].push(Entry {
+                id,
+                tick,
+                signal: signal.clone(),
+            });
+
+            if state.wake_tick.map_or(true, |wake_tick| tick < wake_tick) {
+                shared.driver.notify_one();
+            }
+            if !state.driver_spawned {
+                state.driver_spawned = spawn_driver(Arc::downgrade(shared));
+            }
+        }
+
+        Timer {
+            wheel: Arc::downgrade(shared),
+            id,
+            tick,
+            deadline,
+            signal,
+        }
+    }
+}
+
+impl Drop for TimerWheel {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().dropped = true;
+        self.shared.driver.notify_one();
+    }
+}
+
+impl Shared {
+    /// Fires the timers whose tick is before `now_tick`, and returns
+    /// the tick of the next timer to fire, if any.
+    fn advance(&self, state: &mut State, now_tick: u64) -> Option<u64> {
+        // After a whole turn, every slot has been visited.
+        let end_tick = now_tick.min(state.current_tick + SLOTS);
+
+        for tick in state.current_tick..end_tick {
+            let slot = &mut state.slots[(tick % SLOTS) as usize];
+            slot.retain(|entry| {
+                let due = entry.tick < now_tick;
+                if due {
+                    entry.signal.raise();
+                }
+                !due
+            });
+        }
+        state.current_tick = state.current_tick.max(now_tick);
+
+        // The next timer is most likely in the next turn, otherwise it
+        // is searched in all the slots.
+        (state.current_tick..state.current_tick + SLOTS)
+            .find(|tick| {
+                state.slots[(tick % SLOTS) as usize]
+                    .iter()
+                    .any(|entry| entry.tick == *tick)
+            })
+            .or_else(|| state.slots.iter().flatten().map(|entry| entry.tick).min())
+    }
+
+    /// Runs the wheel until it's dropped.
+    fn drive(self: Arc<Self>) {
+        let mut state = self.state.lock().unwrap();
+
+        while !state.dropped {
+            let now = monotonic_now();
+            let next_tick = self.advance(&mut state, now / self.tick + 1);
+            state.wake_tick = next_tick;
+
+            state = match next_tick {
+                Some(tick) => {
+                    let timeout =
+                        Duration::from_nanos(tick.saturating_mul(self.tick).saturating_sub(now));
+                    self.driver.wait_timeout(state, timeout).unwrap().0
+                }
+                None => self.driver.wait(state).unwrap(),
+            };
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_driver(shared: Weak<Shared>) -> bool {
+    std::thread::Builder::new()
+        .name("wasi-timers".to_string())
+        .spawn(move || {
+            if let Some(shared) = shared.upgrade() {
+                shared.drive();
+            }
+        })
+        .is_ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn spawn_driver(_shared: Weak<Shared>) -> bool {
+    false
+}
+
+/// A timer of a [`TimerWheel`].
+#[derive(Debug)]
+pub(crate) struct Timer {
+    wheel: Weak<Shared>,
+    id: u64,
+    tick: u64,
+    deadline: u64,
+    signal: Arc<Signal>,
+}
+
+impl Timer {
+    /// Whether the timer has fired.
+    pub(crate) fn is_fired(&self) -> bool {
+        *self.signal.fired.lock().unwrap() || monotonic_now() >= self.deadline
+    }
+
+    /// Waits until the timer fires, for at most `timeout`, returning
+    /// whether it has fired.
+    pub(crate) fn wait_timeout(&self, timeout: Duration) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let fired = self.signal.fired.lock().unwrap();
+            if !*fired {
+                let _ = self.signal.condvar.wait_timeout(fired, timeout).unwrap();
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = timeout;
+
+        self.is_fired()
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(shared) = self.wheel.upgrade() {
+            let mut state = shared.state.lock().unwrap();
+            let slot = &mut state.slots[(self.tick % SLOTS) as usize];
+            slot.retain(|entry| entry.id != self.id);
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_accuracy() {
+        let wheel = TimerWheel::default();
+
+        for millis in [1, 5, 20] {
+            let start = monotonic_now();
+            let timer = wheel.insert(start + millis * 1_000_000);
+            while !timer.wait_timeout(Duration::from_secs(1)) {}
+
+            let elapsed = monotonic_now() - start;
+            assert!(elapsed >= millis * 1_000_000, "fired early: {}ns", elapsed);
+            assert!(
+                elapsed < (millis + 50) * 1_000_000,
+                "fired late: {}ns",
+                elapsed
+            );
+        }
+    }
+
+    #[test]
+    fn test_coalescing() {
+        let wheel = TimerWheel::with_tick(Duration::from_millis(5));
+        // The end of a tick, so that the deadlines before it are
+        // rounded up to it.
+        let deadline = (monotonic_now() / 5_000_000 + 3) * 5_000_000;
+
+        let timers = (0..1000)
+            .map(|i| wheel.insert(deadline - i * 1_000))
+            .collect::<Vec<_>>();
+        let later = wheel.insert(deadline + 100_000_000);
+
+        while !timers[0].wait_timeout(Duration::from_secs(1)) {}
+        // The timers of a tick are fired while the wheel is locked, so
+        // they have all fired once it's unlocked.
+        drop(wheel.shared.state.lock().unwrap());
+        assert!(timers
+            .iter()
+            .all(|timer| *timer.signal.fired.lock().unwrap()));
+        assert!(!later.is_fired());
+    }
+
+    #[test]
+    fn test_cancel() {
+        let wheel = TimerWheel::default();
+        let timer = wheel.insert(monotonic_now() + 1_000_000_000);
+        drop(timer);
+
+        let state = wheel.shared.state.lock().unwrap();
+        assert!(state.slots.iter().all(|slot| slot.is_empty()));
+    }
+}
@@ -0,0 +1,89 @@
+use wasmer::{Instance, Module, RuntimeError, Store, WasmerEnv};
+use wasmer_wasi::{
+    generate_import_object_from_env, GuestAllocator, WasiEnv, WasiState, WasiVersion,
+};
+
+mod sys {
+    #[test]
+    fn test_exported_allocator() {
+        super::test_exported_allocator()
+    }
+
+    #[test]
+    fn test_custom_allocator() {
+        super::test_custom_allocator()
+    }
+}
+
+#[cfg(feature = "js")]
+mod js {
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_exported_allocator() {
+        super::test_exported_allocator()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_custom_allocator() {
+        super::test_custom_allocator()
+    }
+}
+
+fn instantiate(wat: &str) -> (WasiEnv, Instance) {
+    let store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+    // The modules don't import anything from WASI to detect its version.
+    let import_object =
+        generate_import_object_from_env(&store, wasi_env.clone(), WasiVersion::Snapshot1);
+    let instance = Instance::new(&module, &import_object).unwrap();
+    wasi_env.init_with_instance(&instance).unwrap();
+
+    (wasi_env, instance)
+}
+
+fn test_exported_allocator() {
+    let (wasi_env, instance) = instantiate(
+        r#"
+        (module
+            (memory (export "memory") 1)
+            (global $next (mut i32) (i32.const 1024))
+            (global $freed (export "freed") (mut i32) (i32.const 0))
+            (func (export "__wbindgen_malloc") (param $len i32) (result i32)
+                (global.get $next)
+                (global.set $next (i32.add (global.get $next) (local.get $len))))
+            (func (export "__wbindgen_free") (param i32 i32)
+                (global.set $freed (i32.add (global.get $freed) (local.get 1)))))
+        "#,
+    );
+
+    assert_eq!(wasi_env.allocate_guest_buffer(16).unwrap(), 1024);
+    assert_eq!(wasi_env.allocate_guest_buffer(8).unwrap(), 1040);
+    wasi_env.free_guest_buffer(1024, 16).unwrap();
+
+    let freed = instance.exports.get_global("freed").unwrap();
+    assert_eq!(freed.get().unwrap_i32(), 16);
+
+    let (wasi_env, _) = instantiate(r#"(module (memory (export "memory") 1))"#);
+    assert!(wasi_env.allocate_guest_buffer(16).is_err());
+}
+
+fn test_custom_allocator() {
+    struct FixedAllocator;
+
+    impl GuestAllocator for FixedAllocator {
+        fn allocate(&self, len: u64) -> Result<u64, RuntimeError> {
+            Ok(65536 - len)
+        }
+
+        fn free(&self, _ptr: u64, _len: u64) -> Result<(), RuntimeError> {
+            Ok(())
+        }
+    }
+
+    let (mut wasi_env, _) = instantiate(r#"(module (memory (export "memory") 1))"#);
+    wasi_env.set_guest_allocator(FixedAllocator);
+    assert_eq!(wasi_env.allocate_guest_buffer(16).unwrap(), 65520);
+    wasi_env.free_guest_buffer(65520, 16).unwrap();
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use wasmer::{Instance, Module, Store};
+use wasmer_wasi::{ReloadOptions, WasiEnv, WasiExitStatus, WasiState};
+
+mod sys {
+    #[test]
+    fn test_exit_status() {
+        super::test_exit_status()
+    }
+
+    #[test]
+    fn test_timeout() {
+        super::test_timeout()
+    }
+
+    #[test]
+    fn test_timeout_after_imports() {
+        super::test_timeout_after_imports()
+    }
+
+    #[test]
+    fn test_timeout_after_reload() {
+        super::test_timeout_after_reload()
+    }
+}
+
+#[cfg(feature = "js")]
+mod js {
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_exit_status() {
+        super::test_exit_status()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_timeout() {
+        super::test_timeout()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_timeout_after_imports() {
+        super::test_timeout_after_imports()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_timeout_after_reload() {
+        super::test_timeout_after_reload()
+    }
+}
+
+fn run(wasi_env: &mut WasiEnv, wat: &str) -> WasiExitStatus {
+    let store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    let import_object = wasi_env.import_object(&module).unwrap();
+    let instance = Instance::new(&module, &import_object).unwrap();
+
+    let start = instance.exports.get_function("_start").unwrap();
+    wasi_env.wait(start.call(&[])).unwrap()
+}
+
+fn test_exit_status() {
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+
+    let returns = r#"
+    (module
+        (import "wasi_unstable" "proc_exit" (func (param i32)))
+        (memory (export "memory") 1)
+        (func (export "_start")))
+    "#;
+    assert_eq!(run(&mut wasi_env, returns), WasiExitStatus::Exited(0));
+
+    let exits = r#"
+    (module
+        (import "wasi_unstable" "proc_exit" (func $proc_exit (param i32)))
+        (memory (export "memory") 1)
+        (func (export "_start")
+            (call $proc_exit (i32.const 42))))
+    "#;
+    assert_eq!(run(&mut wasi_env, exits), WasiExitStatus::Exited(42));
+
+    let raises = r#"
+    (module
+        (import "wasi_unstable" "proc_raise" (func $proc_raise (param i32) (result i32)))
+        (memory (export "memory") 1)
+        (func (export "_start")
+            (drop (call $proc_raise (i32.const 6)))))
+    "#;
+    assert_eq!(run(&mut wasi_env, raises), WasiExitStatus::Signaled(6));
+}
+
+fn test_timeout() {
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+    wasi_env.set_timeout(Duration::from_millis(10));
+
+    assert_eq!(run(&mut wasi_env, SPINS), WasiExitStatus::TimedOut);
+}
+
+fn test_timeout_after_imports() {
+    let store = Store::default();
+    let module = Module::new(&store, SPINS).unwrap();
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+    let import_object = wasi_env.import_object(&module).unwrap();
+    let instance = Instance::new(&module, &import_object).unwrap();
+
+    // The imports hold clones of the environment, which see the timeout.
+    wasi_env.set_timeout(Duration::from_millis(10));
+    let start = instance.exports.get_function("_start").unwrap();
+    assert_eq!(
+        wasi_env.wait(start.call(&[])).unwrap(),
+        WasiExitStatus::TimedOut
+    );
+}
+
+fn test_timeout_after_reload() {
+    let store = Store::default();
+    let module = Module::new(&store, SPINS).unwrap();
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+    let import_object = wasi_env.import_object(&module).unwrap();
+    let instance = Instance::new(&module, &import_object).unwrap();
+    let (new_env, new_instance) = wasi_env
+        .reload(&instance, &module, &ReloadOptions::default())
+        .unwrap();
+
+    // The reloaded environment shares the deadline of the previous one.
+    wasi_env.set_timeout(Duration::from_millis(10));
+    let start = new_instance.exports.get_function("_start").unwrap();
+    assert_eq!(
+        new_env.wait(start.call(&[])).unwrap(),
+        WasiExitStatus::TimedOut
+    );
+}
+
+const SPINS: &str = r#"
+(module
+    (import "wasi_unstable" "sched_yield" (func $sched_yield (result i32)))
+    (memory (export "memory") 1)
+    (func (export "_start")
+        (loop $spin
+            (drop (call $sched_yield))
+            (br $spin))))
+"#;
@@ -0,0 +1,148 @@
+use std::io::Read;
+
+use wasmer::{Instance, Module, Store};
+use wasmer_wasi::types::__WASI_ENOTSUP;
+use wasmer_wasi::{
+    Pipe, PluggableRuntimeImplementation, VirtualBus, VirtualNetworking, WasiExitStatus,
+    WasiRuntimeImplementation, WasiState, WasiThreadError, WasiThreadId,
+};
+
+mod sys {
+    #[test]
+    fn test_proc_fork() {
+        super::test_proc_fork()
+    }
+
+    #[test]
+    fn test_proc_fork_without_fork_start() {
+        super::test_proc_fork_without_fork_start()
+    }
+}
+
+/// A runtime running the threads, and so the forked processes, on
+/// threads of the host.
+#[derive(Debug, Default)]
+struct ThreadedRuntime(PluggableRuntimeImplementation);
+
+impl WasiRuntimeImplementation for ThreadedRuntime {
+    fn bus(&self) -> &(dyn VirtualBus) {
+        self.0.bus()
+    }
+
+    fn networking(&self) -> &(dyn VirtualNetworking) {
+        self.0.networking()
+    }
+
+    fn thread_generate_id(&self) -> WasiThreadId {
+        self.0.thread_generate_id()
+    }
+
+    fn thread_spawn(
+        &self,
+        callback: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        std::thread::spawn(callback);
+        Ok(())
+    }
+}
+
+fn test_proc_fork() {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+        (import "wasix_32v1" "proc_fork" (func $proc_fork (param i64 i32) (result i32)))
+        (import "wasix_32v1" "thread_join" (func $thread_join (param i32) (result i32)))
+        (import "wasix_32v1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+        (memory (export "memory") 1)
+
+        ;; Incremented by the start function, and by 10 before forking,
+        ;; like the stack pointer would be.
+        (global $state (mut i32) (i32.const 0))
+        (func $init
+            (global.set $state (i32.add (global.get $state) (i32.const 1))))
+        (start $init)
+
+        ;; The iovec of "child\n".
+        (data (i32.const 32) "\28\00\00\00\06\00\00\00")
+        (data (i32.const 40) "child\n")
+
+        (func (export "_fork_start") (param $user_data i64)
+            ;; The child gets the memory and the globals of the parent at
+            ;; the time of the fork, without running the start function
+            ;; again, and its stdout.
+            (if (i32.and
+                    (i32.and
+                        (i64.eq (local.get $user_data) (i64.const 7))
+                        (i32.eq (i32.load (i32.const 16)) (i32.const 42)))
+                    (i32.eq (global.get $state) (i32.const 11)))
+                (then
+                    (drop (call $fd_write (i32.const 1) (i32.const 32) (i32.const 1) (i32.const 48)))))
+
+            ;; Which is a copy.
+            (i32.store (i32.const 16) (i32.const 0)))
+
+        (func (export "_start")
+            (i32.store (i32.const 16) (i32.const 42))
+            (global.set $state (i32.add (global.get $state) (i32.const 10)))
+
+            (if (call $proc_fork (i64.const 7) (i32.const 0))
+                (then unreachable))
+            (drop (call $thread_join (i32.load (i32.const 0))))
+
+            (call $proc_exit (i32.load (i32.const 16)))))
+    "#,
+    )
+    .unwrap();
+
+    let mut stdout = Pipe::new();
+    let mut wasi_env = WasiState::new("command-name")
+        .stdout(Box::new(stdout.clone()))
+        .finalize()
+        .unwrap();
+    wasi_env.set_runtime(ThreadedRuntime::default());
+
+    let import_object = wasi_env.import_object(&module).unwrap();
+    let instance = Instance::new(&module, &import_object).unwrap();
+
+    let start = instance.exports.get_function("_start").unwrap();
+    assert_eq!(
+        wasi_env.wait(start.call(&[])).unwrap(),
+        WasiExitStatus::Exited(42)
+    );
+
+    let mut stdout_str = String::new();
+    stdout.read_to_string(&mut stdout_str).unwrap();
+    assert_eq!(stdout_str, "child\n");
+}
+
+fn test_proc_fork_without_fork_start() {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+        (import "wasix_32v1" "proc_fork" (func $proc_fork (param i64 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+        (memory (export "memory") 1)
+
+        (func (export "_start")
+            (call $proc_exit (call $proc_fork (i64.const 0) (i32.const 0)))))
+    "#,
+    )
+    .unwrap();
+
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+    wasi_env.set_runtime(ThreadedRuntime::default());
+
+    let import_object = wasi_env.import_object(&module).unwrap();
+    let instance = Instance::new(&module, &import_object).unwrap();
+
+    let start = instance.exports.get_function("_start").unwrap();
+    assert_eq!(
+        wasi_env.wait(start.call(&[])).unwrap(),
+        WasiExitStatus::Exited(__WASI_ENOTSUP as u32)
+    );
+}
@@ -0,0 +1,154 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use wasmer_vbus::{
+    BusDataFormat, BusError, BusSpawnedProcess, FileDescriptor, SpawnOptions, SpawnOptionsConfig,
+    StdioMode, VirtualBus, VirtualBusInvocation, VirtualBusInvokable, VirtualBusListener,
+    VirtualBusProcess, VirtualBusScope, VirtualBusSpawner,
+};
+use wasmer_wasi::{PluggableRuntimeImplementation, WasiState};
+
+mod sys {
+    #[test]
+    fn test_spawn_process() {
+        super::test_spawn_process()
+    }
+}
+
+type Spawned = Arc<Mutex<Vec<(String, SpawnOptionsConfig)>>>;
+
+/// A bus recording the processes it spawns, which run until they are
+/// killed.
+#[derive(Debug, Default)]
+struct RecordingBus {
+    spawned: Spawned,
+}
+
+impl VirtualBus for RecordingBus {
+    fn new_spawn(&self) -> SpawnOptions {
+        SpawnOptions::new(Box::new(RecordingSpawner {
+            spawned: self.spawned.clone(),
+        }))
+    }
+
+    fn listen(&self) -> Result<Box<dyn VirtualBusListener + Sync>, BusError> {
+        Err(BusError::Unsupported)
+    }
+}
+
+struct RecordingSpawner {
+    spawned: Spawned,
+}
+
+impl VirtualBusSpawner for RecordingSpawner {
+    fn spawn(
+        &mut self,
+        name: &str,
+        config: &SpawnOptionsConfig,
+    ) -> Result<BusSpawnedProcess, BusError> {
+        self.spawned
+            .lock()
+            .unwrap()
+            .push((name.to_string(), config.clone()));
+
+        Ok(BusSpawnedProcess {
+            inst: Box::new(RunningProcess::default()),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct RunningProcess {
+    exit_code: Mutex<Option<u32>>,
+}
+
+impl VirtualBusScope for RunningProcess {
+    fn poll_finished(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        match *self.exit_code.lock().unwrap() {
+            Some(_) => Poll::Ready(()),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl VirtualBusInvokable for RunningProcess {
+    fn invoke(
+        &self,
+        _topic: String,
+        _format: BusDataFormat,
+        _buf: &[u8],
+    ) -> Result<Box<dyn VirtualBusInvocation + Sync>, BusError> {
+        Err(BusError::Unsupported)
+    }
+}
+
+impl VirtualBusProcess for RunningProcess {
+    fn exit_code(&self) -> Option<u32> {
+        *self.exit_code.lock().unwrap()
+    }
+
+    fn stdin_fd(&self) -> Option<FileDescriptor> {
+        None
+    }
+
+    fn stdout_fd(&self) -> Option<FileDescriptor> {
+        None
+    }
+
+    fn stderr_fd(&self) -> Option<FileDescriptor> {
+        None
+    }
+
+    fn kill(&self) -> Result<(), BusError> {
+        *self.exit_code.lock().unwrap() = Some(137);
+        Ok(())
+    }
+}
+
+fn test_spawn_process() {
+    let bus = RecordingBus::default();
+    let spawned = bus.spawned.clone();
+    let mut runtime = PluggableRuntimeImplementation::default();
+    runtime.set_bus_implementation(bus);
+
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+    wasi_env.set_runtime(runtime);
+
+    let pid = wasi_env
+        .spawn_process("tool", |options| {
+            options
+                .args(vec!["--flag".to_string()])
+                .env("KEY".to_string(), "VALUE".to_string())
+                .working_dir("/work".to_string())
+                .stdin_mode(StdioMode::Inherit)
+                .stdout_file("/out.txt".to_string());
+        })
+        .unwrap();
+
+    {
+        let spawned = spawned.lock().unwrap();
+        assert_eq!(spawned.len(), 1);
+
+        let (name, config) = &spawned[0];
+        assert_eq!(name, "tool");
+        assert_eq!(config.args(), &vec!["--flag".to_string()]);
+        assert_eq!(
+            config.envs(),
+            &vec![("KEY".to_string(), "VALUE".to_string())]
+        );
+        assert_eq!(config.working_dir(), "/work");
+        assert_eq!(config.stdin_mode(), StdioMode::Inherit);
+        assert_eq!(config.stdin_file(), None);
+        assert_eq!(config.stdout_mode(), StdioMode::File);
+        assert_eq!(config.stdout_file(), Some("/out.txt"));
+        assert_eq!(config.stderr_mode(), StdioMode::Null);
+    }
+
+    assert_eq!(wasi_env.kill_process(pid), Ok(()));
+    assert_eq!(wasi_env.wait_process(pid), Ok(137));
+
+    // The process has been released.
+    assert_eq!(wasi_env.wait_process(pid), Err(BusError::BadHandle));
+    assert_eq!(wasi_env.kill_process(pid), Err(BusError::BadHandle));
+}
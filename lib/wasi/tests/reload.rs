@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use wasmer::{Instance, Module, Store, Value};
+use wasmer_wasi::{ReloadError, ReloadOptions, WasiState};
+
+mod sys {
+    #[test]
+    fn test_reload() {
+        super::test_reload()
+    }
+}
+
+#[cfg(feature = "js")]
+mod js {
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_reload() {
+        super::test_reload()
+    }
+}
+
+fn test_reload() {
+    let store = Store::default();
+    let v1 = Module::new(
+        &store,
+        r#"
+        (module
+            (import "wasi_unstable" "fd_close" (func $fd_close (param i32) (result i32)))
+            (memory (export "memory") 1)
+            (global (export "counter") (mut i32) (i32.const 41))
+            (func (export "close_stdout") (result i32)
+                (call $fd_close (i32.const 1))))
+        "#,
+    )
+    .unwrap();
+    let v2 = Module::new(
+        &store,
+        r#"
+        (module
+            (import "wasi_unstable" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (global (export "counter") (mut i32) (i32.const 0))
+            (global (export "version") i32 (i32.const 2))
+            (func (export "write_stdout") (result i32)
+                (call $fd_write (i32.const 1) (i32.const 0) (i32.const 0) (i32.const 8))))
+        "#,
+    )
+    .unwrap();
+
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+    let import_object = wasi_env.import_object(&v1).unwrap();
+    let instance = Instance::new(&v1, &import_object).unwrap();
+    let close_stdout = instance.exports.get_function("close_stdout").unwrap();
+    assert_eq!(close_stdout.call(&[]).unwrap()[0], Value::I32(0));
+
+    let options = ReloadOptions {
+        migrate_globals: vec!["counter".to_string()],
+    };
+    let (new_env, new_instance) = wasi_env.reload(&instance, &v2, &options).unwrap();
+    assert!(Arc::ptr_eq(&new_env.state, &wasi_env.state));
+
+    // The global is migrated, and stdout is still closed.
+    let counter = new_instance.exports.get_global("counter").unwrap();
+    assert_eq!(counter.get(), Value::I32(41));
+    let write_stdout = new_instance.exports.get_function("write_stdout").unwrap();
+    assert_eq!(write_stdout.call(&[]).unwrap()[0], Value::I32(8));
+
+    let options = ReloadOptions {
+        migrate_globals: vec!["version".to_string()],
+    };
+    assert!(matches!(
+        wasi_env.reload(&instance, &v2, &options),
+        Err(ReloadError::MissingGlobal(name)) if name == "version"
+    ));
+    // The global is immutable in the new instance.
+    assert!(matches!(
+        new_env.reload(&new_instance, &v2, &options),
+        Err(ReloadError::Global { name, .. }) if name == "version"
+    ));
+}
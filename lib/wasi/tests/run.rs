@@ -0,0 +1,76 @@
+use wasmer::Store;
+use wasmer_wasi::{run_wasi, RunError, RunOptions, WasiExitStatus};
+
+mod sys {
+    #[test]
+    fn test_run_wasi() {
+        super::test_run_wasi()
+    }
+
+    #[test]
+    fn test_run_not_wasi() {
+        super::test_run_not_wasi()
+    }
+}
+
+#[cfg(feature = "js")]
+mod js {
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_run_wasi() {
+        super::test_run_wasi()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_run_not_wasi() {
+        super::test_run_not_wasi()
+    }
+}
+
+fn test_run_wasi() {
+    // Copies its standard input to its standard output, writes "oops" to
+    // its standard error, and exits with 7.
+    let wat = r#"
+    (module
+        (import "wasi_unstable" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_unstable" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_unstable" "proc_exit" (func $proc_exit (param i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 64) "oops")
+        (func (export "_start")
+            (i32.store (i32.const 0) (i32.const 128))
+            (i32.store (i32.const 4) (i32.const 32))
+            (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (i32.store (i32.const 4) (i32.load (i32.const 8)))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 4))
+            (drop (call $fd_write (i32.const 2) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (call $proc_exit (i32.const 7))))
+    "#;
+
+    let store = Store::default();
+    let output = run_wasi(
+        &store,
+        wat,
+        RunOptions {
+            program: "echo".to_string(),
+            stdin: b"hello".to_vec(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(output.status, WasiExitStatus::Exited(7));
+    assert_eq!(output.stdout, b"hello");
+    assert_eq!(output.stderr, b"oops");
+}
+
+fn test_run_not_wasi() {
+    let store = Store::default();
+    let wat = r#"(module (func (export "_start")))"#;
+    assert!(matches!(
+        run_wasi(&store, wat, RunOptions::default()),
+        Err(RunError::NotWasi)
+    ));
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "sys")]
+
+use std::time::Duration;
+
+use wasmer::{Instance, Module, Store};
+use wasmer_wasi::types::__WASI_SIGKILL;
+use wasmer_wasi::{spawn, WasiExitStatus, WasiInstanceHandle, WasiState};
+
+mod sys {
+    #[test]
+    fn test_spawn_exit_status() {
+        super::test_spawn_exit_status()
+    }
+
+    #[test]
+    fn test_spawn_cancel() {
+        super::test_spawn_cancel()
+    }
+
+    #[test]
+    fn test_spawn_timeout() {
+        super::test_spawn_timeout()
+    }
+}
+
+fn start(wat: &str, timeout: Option<Duration>) -> WasiInstanceHandle {
+    let store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+    if let Some(timeout) = timeout {
+        wasi_env.set_timeout(timeout);
+    }
+    let import_object = wasi_env.import_object(&module).unwrap();
+    let instance = Instance::new(&module, &import_object).unwrap();
+
+    spawn(instance, wasi_env).unwrap()
+}
+
+const SPINS: &str = r#"
+(module
+    (import "wasi_unstable" "sched_yield" (func $sched_yield (result i32)))
+    (memory (export "memory") 1)
+    (func (export "_start")
+        (loop $spin
+            (drop (call $sched_yield))
+            (br $spin))))
+"#;
+
+fn test_spawn_exit_status() {
+    let exits = r#"
+    (module
+        (import "wasi_unstable" "proc_exit" (func $proc_exit (param i32)))
+        (memory (export "memory") 1)
+        (func (export "_start")
+            (call $proc_exit (i32.const 42))))
+    "#;
+    let handle = start(exits, None);
+    assert!(handle.wait_timeout(Duration::from_secs(10)));
+    assert!(handle.is_finished());
+    assert_eq!(handle.join_blocking().unwrap(), WasiExitStatus::Exited(42));
+}
+
+fn test_spawn_cancel() {
+    let handle = start(SPINS, None);
+    assert!(!handle.wait_timeout(Duration::from_millis(50)));
+    assert!(!handle.is_finished());
+
+    handle.cancel();
+    assert_eq!(
+        handle.join_blocking().unwrap(),
+        WasiExitStatus::Signaled(__WASI_SIGKILL)
+    );
+}
+
+fn test_spawn_timeout() {
+    let handle = start(SPINS, Some(Duration::from_millis(10)));
+    assert_eq!(handle.join_blocking().unwrap(), WasiExitStatus::TimedOut);
+}
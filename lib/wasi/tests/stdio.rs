@@ -18,6 +18,16 @@ mod sys {
     fn test_env() {
         super::test_env()
     }
+
+    #[test]
+    fn test_fd_reader_writer() {
+        super::test_fd_reader_writer()
+    }
+
+    #[test]
+    fn test_vectored_io() {
+        super::test_vectored_io()
+    }
 }
 
 #[cfg(feature = "js")]
@@ -38,6 +48,16 @@ mod js {
     fn test_env() {
         super::test_env()
     }
+
+    #[wasm_bindgen_test]
+    fn test_fd_reader_writer() {
+        super::test_fd_reader_writer()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_vectored_io() {
+        super::test_vectored_io()
+    }
 }
 
 fn test_stdout() {
@@ -168,3 +188,98 @@ fn test_stdin() {
     stdin.read_to_end(&mut buf).unwrap();
     assert_eq!(buf.len(), 0);
 }
+
+fn test_fd_reader_writer() {
+    let mut stdin = Pipe::new();
+    let mut stdout = Pipe::new();
+    let state = WasiState::new("command-name")
+        .stdin(Box::new(stdin.clone()))
+        .stdout(Box::new(stdout.clone()))
+        .build()
+        .unwrap();
+
+    // The host reads what is waiting on the stdin of the guest.
+    stdin.write_all(b"Hello, stdin!").unwrap();
+    let mut buf = String::new();
+    state
+        .fs
+        .get_fd_reader(&state.inodes, 0)
+        .unwrap()
+        .read_to_string(&mut buf)
+        .unwrap();
+    assert_eq!(buf, "Hello, stdin!");
+
+    // The host writes to the stdout of the guest.
+    state
+        .fs
+        .get_fd_writer(&state.inodes, 1)
+        .unwrap()
+        .write_all(b"Hello, stdout!")
+        .unwrap();
+    let mut buf = String::new();
+    stdout.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "Hello, stdout!");
+
+    // The rights of the file descriptors are enforced.
+    assert!(state.fs.get_fd_writer(&state.inodes, 0).is_err());
+    assert!(state.fs.get_fd_reader(&state.inodes, 1).is_err());
+    assert!(state.fs.get_fd_reader(&state.inodes, 42).is_err());
+}
+
+fn test_vectored_io() {
+    let store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_unstable" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_unstable" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; Read stdin into 3 bytes at 100, then 16 bytes at 200
+            (i32.store (i32.const 0) (i32.const 100))
+            (i32.store (i32.const 4) (i32.const 3))
+            (i32.store (i32.const 8) (i32.const 200))
+            (i32.store (i32.const 12) (i32.const 16))
+            (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 2) (i32.const 64)))
+
+            ;; Write the 5 bytes at 200, then the 3 bytes at 100, to stdout
+            (i32.store (i32.const 32) (i32.const 200))
+            (i32.store (i32.const 36) (i32.const 5))
+            (i32.store (i32.const 40) (i32.const 100))
+            (i32.store (i32.const 44) (i32.const 3))
+            (drop (call $fd_write (i32.const 1) (i32.const 32) (i32.const 2) (i32.const 68)))
+        )
+    )
+    "#).unwrap();
+
+    // Create the `WasiEnv`.
+    let mut stdin = Pipe::new();
+    let mut stdout = Pipe::new();
+    let mut wasi_env = WasiState::new("command-name")
+        .stdin(Box::new(stdin.clone()))
+        .stdout(Box::new(stdout.clone()))
+        .finalize()
+        .unwrap();
+    stdin.write_all(b"abcdefgh").unwrap();
+
+    // Generate an `ImportObject`.
+    let import_object = wasi_env.import_object(&module).unwrap();
+
+    // Let's instantiate the module with the imports.
+    let instance = Instance::new(&module, &import_object).unwrap();
+
+    // Let's call the `_start` function, which is our `main` function in Rust.
+    let start = instance.exports.get_function("_start").unwrap();
+    start.call(&[]).unwrap();
+
+    let memory = instance.exports.get_memory("memory").unwrap();
+    let mut nread = [0; 4];
+    memory.read(64, &mut nread).unwrap();
+    assert_eq!(u32::from_le_bytes(nread), 8);
+
+    let mut stdout_str = String::new();
+    stdout.read_to_string(&mut stdout_str).unwrap();
+    assert_eq!(stdout_str, "defghabc");
+}
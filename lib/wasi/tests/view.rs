@@ -0,0 +1,224 @@
+use std::io::Read;
+use std::sync::Arc;
+use wasmer::{Instance, Module, Store, Value};
+use wasmer_wasi::types::__WASI_ENOENT;
+use wasmer_wasi::{Pipe, WasiState, WasiStateView};
+
+mod sys {
+    #[test]
+    fn test_view() {
+        super::test_view()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_view_poll_stdin() {
+        super::test_view_poll_stdin()
+    }
+}
+
+#[cfg(feature = "js")]
+mod js {
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_view() {
+        super::test_view()
+    }
+}
+
+fn test_view() {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+        (module
+            (import "wasix_32v1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "getcwd" (func $getcwd (param i32 i32) (result i32)))
+            (import "wasix_32v1" "chdir" (func $chdir (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 64) "/dev")
+            (data (i32.const 80) "/missing")
+            ;; Writes the current directory to stdout.
+            (func (export "print_cwd") (result i32)
+                (local $errno i32)
+                (i32.store (i32.const 4) (i32.const 32))
+                (local.set $errno (call $getcwd (i32.const 128) (i32.const 4)))
+                (if (local.get $errno) (then (return (local.get $errno))))
+                (i32.store (i32.const 8) (i32.const 128))
+                (i32.store (i32.const 12) (i32.load (i32.const 4)))
+                (call $fd_write (i32.const 1) (i32.const 8) (i32.const 1) (i32.const 16)))
+            (func (export "chdir_dev") (result i32)
+                (call $chdir (i32.const 64) (i32.const 4)))
+            (func (export "chdir_missing") (result i32)
+                (call $chdir (i32.const 80) (i32.const 8))))
+        "#,
+    )
+    .unwrap();
+
+    let wasi_env = WasiState::new("command-name")
+        .with_dev_fs()
+        .finalize()
+        .unwrap();
+
+    // The command only has its own stdout, the reactor its own current
+    // directory too.
+    let mut command_stdout = Pipe::new();
+    let mut command_view = WasiStateView::new();
+    command_view.stdout(Box::new(command_stdout.clone()));
+    let mut command_env = wasi_env.with_view(command_view);
+    let mut reactor_stdout = Pipe::new();
+    let mut reactor_view = WasiStateView::new();
+    reactor_view
+        .stdout(Box::new(reactor_stdout.clone()))
+        .current_dir("/");
+    let mut reactor_env = wasi_env.with_view(reactor_view);
+    assert!(Arc::ptr_eq(&command_env.state, &reactor_env.state));
+
+    let import_object = command_env.import_object(&module).unwrap();
+    let command = Instance::new(&module, &import_object).unwrap();
+    let import_object = reactor_env.import_object(&module).unwrap();
+    let reactor = Instance::new(&module, &import_object).unwrap();
+    let call = |instance: &Instance, name: &str| {
+        let function = instance.exports.get_function(name).unwrap();
+        function.call(&[]).unwrap()[0].clone()
+    };
+
+    // Only existing directories can become the current one.
+    assert_eq!(
+        call(&command, "chdir_missing"),
+        Value::I32(__WASI_ENOENT as i32)
+    );
+    assert_eq!(*wasi_env.state.fs.current_dir.lock().unwrap(), "/");
+
+    // The command changes the current directory of the shared state,
+    // which the reactor doesn't see.
+    assert_eq!(call(&command, "chdir_dev"), Value::I32(0));
+    assert_eq!(*wasi_env.state.fs.current_dir.lock().unwrap(), "/dev");
+    assert_eq!(call(&command, "print_cwd"), Value::I32(0));
+    assert_eq!(call(&reactor, "print_cwd"), Value::I32(0));
+
+    let mut output = String::new();
+    command_stdout.read_to_string(&mut output).unwrap();
+    assert_eq!(output, "/dev");
+    output.clear();
+    reactor_stdout.read_to_string(&mut output).unwrap();
+    assert_eq!(output, "/");
+    assert_eq!(reactor_env.view().unwrap().get_current_dir().unwrap(), "/");
+}
+
+/// A file of the host, which `poll_oneoff` polls with its file
+/// descriptor.
+#[cfg(unix)]
+#[derive(Debug)]
+struct HostFile(std::fs::File);
+
+#[cfg(unix)]
+impl std::io::Read for HostFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(unix)]
+impl std::io::Write for HostFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(unix)]
+impl std::io::Seek for HostFile {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+#[cfg(unix)]
+impl wasmer_vfs::VirtualFile for HostFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        self.0.metadata().unwrap().len()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> wasmer_vfs::Result<()> {
+        self.0
+            .set_len(new_size)
+            .map_err(|_| wasmer_vfs::FsError::IOError)
+    }
+
+    fn unlink(&mut self) -> wasmer_vfs::Result<()> {
+        Ok(())
+    }
+
+    fn bytes_available_read(&self) -> wasmer_vfs::Result<Option<usize>> {
+        Ok(Some(self.size() as usize))
+    }
+
+    fn get_fd(&self) -> Option<wasmer_vfs::FileDescriptor> {
+        use std::os::unix::io::AsRawFd;
+
+        Some((self.0.as_raw_fd() as u32).into())
+    }
+}
+
+#[cfg(unix)]
+fn test_view_poll_stdin() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+        (module
+            (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            ;; Reading stdin, with the user data 7.
+            (data (i32.const 0) "\07\00\00\00\00\00\00\00\01")
+            ;; Or a second on the monotonic clock, with the user data 9.
+            (data (i32.const 48) "\09\00\00\00\00\00\00\00\00")
+            (data (i32.const 64) "\01\00\00\00\00\00\00\00\00\ca\9a\3b")
+            ;; Returns the user data of the single event seen, or -1.
+            (func (export "poll_stdin") (result i64)
+                (if (call $poll_oneoff (i32.const 0) (i32.const 128) (i32.const 2) (i32.const 256))
+                    (then (return (i64.const -1))))
+                (if (i32.ne (i32.load (i32.const 256)) (i32.const 1))
+                    (then (return (i64.const -1))))
+                (i64.load (i32.const 128))))
+        "#,
+    )
+    .unwrap();
+
+    // The stdin of the shared state is empty, the one of the view
+    // isn't.
+    let wasi_env = WasiState::new("command-name")
+        .stdin(Box::new(Pipe::new()))
+        .finalize()
+        .unwrap();
+    let mut stdin = tempfile::tempfile().unwrap();
+    stdin.write_all(b"hello").unwrap();
+    stdin.seek(SeekFrom::Start(0)).unwrap();
+    let mut view = WasiStateView::new();
+    view.stdin(Box::new(HostFile(stdin)));
+    let mut env = wasi_env.with_view(view);
+
+    let import_object = env.import_object(&module).unwrap();
+    let instance = Instance::new(&module, &import_object).unwrap();
+    let poll_stdin = instance.exports.get_function("poll_stdin").unwrap();
+    assert_eq!(poll_stdin.call(&[]).unwrap()[0], Value::I64(7));
+}
@@ -74,3 +74,30 @@ fn test_deserialize(config: crate::Config) -> Result<()> {
     assert_eq!(result.to_vec(), vec![Value::I64(1500)]);
     Ok(())
 }
+
+#[compiler_test(serialize)]
+fn test_deserialize_incompatible_version(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, "(module)")?;
+    let mut serialized_bytes = module.serialize()?;
+
+    // Overwrite the format version following the metadata magic.
+    let magic = b"WASMER\0\0";
+    let offset = serialized_bytes
+        .windows(magic.len())
+        .position(|window| window == magic)
+        .expect("metadata header not found")
+        + magic.len();
+    let found = ArtifactVersion::new(0, 0, 2);
+    serialized_bytes[offset..offset + 4].copy_from_slice(&found.to_u32().to_ne_bytes());
+
+    let headless_store = config.headless_store();
+    match unsafe { Module::deserialize(&headless_store, &serialized_bytes) } {
+        Err(DeserializeError::IncompatibleVersion {
+            found: err_found, ..
+        }) => assert_eq!(err_found, found),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("module with an incompatible version was deserialized"),
+    }
+    Ok(())
+}
@@ -254,6 +254,7 @@ fn trap_start_function_import(config: crate::Config) -> Result<()> {
     .unwrap();
     match err {
         InstantiationError::Link(_)
+        | InstantiationError::Compile(_)
         | InstantiationError::HostEnvInitialization(_)
         | InstantiationError::CpuFeature(_) => {
             panic!("It should be a start error")
@@ -1,6 +1,7 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Read;
-use wasmer_wast::{WasiFileSystemKind, WasiTest};
+use std::path::Path;
+use wasmer_wast::{ConformanceMatrix, WasiFileSystemKind, WasiTest};
 
 // The generated tests (from build.rs) look like:
 // #[cfg(test)]
@@ -40,3 +41,52 @@ pub fn run_wasi(
 
     Ok(())
 }
+
+/// Runs the WASI testsuite fetched by `make download-wasi-testsuite`, and
+/// prints the conformance matrix. The tests listed in
+/// `tests/wasi-testsuite.ignores` are known not to conform, any other
+/// failure is a regression.
+///
+/// The testsuite isn't vendored, so this only runs with `--ignored`, as
+/// `make test-wasi-testsuite` does after fetching it.
+#[compiler_test(wasi)]
+#[ignore = "needs the WASI testsuite, run `make test-wasi-testsuite`"]
+fn wasi_testsuite(config: crate::Config) -> anyhow::Result<()> {
+    let root = Path::new("tests/wasi-testsuite");
+    anyhow::ensure!(
+        root.exists(),
+        "the WASI testsuite is missing: run `make download-wasi-testsuite` to fetch it"
+    );
+
+    let matrix = ConformanceMatrix::run(&config.store(), root)?;
+    println!("{}", matrix);
+
+    // Each line is a test, optionally followed by the file system it
+    // doesn't conform on, `Host` or `InMemory`.
+    let ignores = fs::read_to_string("tests/wasi-testsuite.ignores")?;
+    let ignored = |name: &str, kind: WasiFileSystemKind| {
+        ignores
+            .lines()
+            .map(|line| line.split('#').next().unwrap().trim())
+            .filter(|line| !line.is_empty())
+            .any(|line| {
+                let mut words = line.split_whitespace();
+                words.next() == Some(name)
+                    && words
+                        .next()
+                        .map_or(true, |ignored| ignored == format!("{:?}", kind))
+            })
+    };
+    let regressions = matrix
+        .failures()
+        .filter(|(name, kind, _)| !ignored(name, *kind))
+        .map(|(name, kind, reason)| format!("{} ({:?}): {}", name, kind, reason))
+        .collect::<Vec<_>>();
+    assert!(
+        regressions.is_empty(),
+        "WASI testsuite regressions:\n{}",
+        regressions.join("\n")
+    );
+
+    Ok(())
+}
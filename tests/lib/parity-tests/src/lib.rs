@@ -0,0 +1,62 @@
+//! Tests run against every backend from the very same source.
+//!
+//! A crate built for both the `sys` and `js` backends declares its
+//! tests with [`parity_tests!`]: the `sys` backend runs them with
+//! `cargo test`, the `js` backend with `wasm-pack test`, depending on
+//! the `sys` and `js` features of the crate. The crate needs
+//! `wasm-bindgen-test` as a development dependency on `wasm32`.
+//!
+//! A behavior which is known to differ on a backend is not left out:
+//! the test is marked with `#[known_failure("<feature>", "<reason>")]`
+//! and is ignored on that backend only, with its reason, so the parity
+//! gap stays visible.
+
+#![deny(missing_docs, unused_extern_crates)]
+
+/// Declares tests run on both the `sys` and `js` backends.
+///
+/// ```ignore
+/// parity_tests! {
+///     fn memory_grow() {
+///         // …
+///     }
+///
+///     #[known_failure("js", "Table::grow is not implemented")]
+///     fn table_grow() {
+///         // …
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! parity_tests {
+    ($(
+        $(#[known_failure($backend:tt, $reason:tt)])*
+        fn $name:ident() $body:block
+    )*) => {
+        $(
+            #[cfg_attr(feature = "sys", test)]
+            #[cfg_attr(feature = "js", wasm_bindgen_test::wasm_bindgen_test)]
+            $(#[cfg_attr(all(feature = "sys", feature = $backend), ignore = $reason)])*
+            fn $name() {
+                // `wasm-bindgen-test` can't ignore a test: it passes
+                // without running its body instead.
+                $(
+                    if cfg!(all(feature = "js", feature = $backend)) {
+                        $crate::ignored(stringify!($name), $reason);
+                        return;
+                    }
+                )*
+                $body
+            }
+        )*
+    };
+}
+
+/// Reports the test `name` as ignored because of `reason`.
+#[doc(hidden)]
+pub fn ignored(name: &str, reason: &str) {
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_test::console_log!("test {} ... ignored, {}", name, reason);
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("test {} ... ignored, {}", name, reason);
+}
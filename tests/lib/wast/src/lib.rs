@@ -19,11 +19,15 @@
 
 mod error;
 mod spectest;
+mod wasi_testsuite;
 mod wasi_wast;
 mod wast;
 
 pub use crate::error::{DirectiveError, DirectiveErrors};
 pub use crate::spectest::spectest_importobject;
+pub use crate::wasi_testsuite::{
+    wasi_testsuite_cases, ConformanceMatrix, WasiTestsuiteCase, FILE_SYSTEM_KINDS,
+};
 pub use crate::wasi_wast::{WasiFileSystemKind, WasiTest};
 pub use crate::wast::Wast;
 
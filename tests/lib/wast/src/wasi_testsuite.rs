@@ -0,0 +1,263 @@
+//! Runs the binaries of the [WASI testsuite], against every kind of
+//! file system, and reports which of them conform.
+//!
+//! The testsuite isn't vendored: `make download-wasi-testsuite` fetches
+//! it into `tests/wasi-testsuite`. Each test is a `.wasm` command, along
+//! with an optional `.json` file of the same name describing how to run
+//! it and what it must output.
+//!
+//! [WASI testsuite]: https://github.com/WebAssembly/wasi-testsuite
+
+use crate::wasi_wast::{get_stdio_output, map_host_fs_to_mem_fs, OutputCapturerer};
+use crate::WasiFileSystemKind;
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, read_dir};
+use std::path::{Path, PathBuf};
+use wasmer::{Instance, Module, Store};
+use wasmer_vfs::{host_fs, mem_fs, FileSystem};
+use wasmer_wasi::{generate_import_object_from_env, get_wasi_version, WasiError, WasiState};
+
+/// The file systems every test runs against, in the columns of the
+/// [`ConformanceMatrix`].
+pub const FILE_SYSTEM_KINDS: [WasiFileSystemKind; 2] =
+    [WasiFileSystemKind::Host, WasiFileSystemKind::InMemory];
+
+/// How to run a test, and what it must output, from its `.json` file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TestSpec {
+    args: Vec<String>,
+    dirs: Vec<String>,
+    env: BTreeMap<String, String>,
+    exit_code: u32,
+    stdout: Option<String>,
+}
+
+/// A test of the WASI testsuite.
+#[derive(Debug)]
+pub struct WasiTestsuiteCase {
+    name: String,
+    wasm_path: PathBuf,
+    spec: TestSpec,
+}
+
+impl WasiTestsuiteCase {
+    /// Loads the test `wasm_path`, named after its path relative to
+    /// `root`, along with its `.json` file, if any.
+    pub fn load(root: &Path, wasm_path: &Path) -> anyhow::Result<Self> {
+        let spec_path = wasm_path.with_extension("json");
+        let spec = if spec_path.exists() {
+            let spec = fs::read_to_string(&spec_path)?;
+            serde_json::from_str(&spec)
+                .with_context(|| format!("invalid test spec `{}`", spec_path.display()))?
+        } else {
+            TestSpec::default()
+        };
+        let name = wasm_path
+            .strip_prefix(root)
+            .unwrap_or(wasm_path)
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        Ok(Self {
+            name,
+            wasm_path: wasm_path.to_path_buf(),
+            spec,
+        })
+    }
+
+    /// The path of the test, relative to the root of the testsuite,
+    /// without the extension.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Runs the test against `filesystem_kind`, and returns why it
+    /// doesn't conform, if it doesn't.
+    pub fn run(&self, store: &Store, filesystem_kind: WasiFileSystemKind) -> anyhow::Result<()> {
+        let base_dir = self.wasm_path.parent().unwrap_or_else(|| Path::new("."));
+        let module = Module::from_file(store, &self.wasm_path)?;
+
+        let mut builder = WasiState::new(&self.name);
+        builder.args(&self.spec.args);
+        for (name, value) in &self.spec.env {
+            builder.env(name, value);
+        }
+        match filesystem_kind {
+            WasiFileSystemKind::Host => {
+                for dir in &self.spec.dirs {
+                    builder.map_dir(dir, base_dir.join(dir))?;
+                }
+                builder.set_fs(Box::new(host_fs::FileSystem::default()));
+            }
+            WasiFileSystemKind::InMemory => {
+                // The tests may write to their directories, which are
+                // copied so that the next runs see them untouched.
+                let fs = mem_fs::FileSystem::default();
+                for dir in &self.spec.dirs {
+                    let path = Path::new("/").join(dir);
+                    fs.create_dir(&path)?;
+                    map_host_fs_to_mem_fs(&fs, read_dir(base_dir.join(dir))?, &path)?;
+                    builder.map_dir(dir, path)?;
+                }
+                builder.set_fs(Box::new(fs));
+            }
+        }
+        let (stdout, stdout_rx) = OutputCapturerer::new();
+        let (stderr, stderr_rx) = OutputCapturerer::new();
+        let env = builder
+            .stdout(Box::new(stdout))
+            .stderr(Box::new(stderr))
+            .finalize()?;
+
+        let version = get_wasi_version(&module, true)
+            .with_context(|| "failed to detect a version of WASI from the module")?;
+        let imports = generate_import_object_from_env(store, env, version);
+        let instance = Instance::new(&module, &imports)?;
+        let start = instance.exports.get_function("_start")?;
+
+        let exit_code = match start.call(&[]) {
+            Ok(_) => 0,
+            Err(error) => match error.downcast::<WasiError>() {
+                Ok(WasiError::Exit(code)) => code,
+                Ok(error) => return Err(error.into()),
+                Err(error) => {
+                    return Err(error).with_context(|| {
+                        format!(
+                            "`_start` failed, with stderr: \"{}\"",
+                            get_stdio_output(&stderr_rx).unwrap_or_default()
+                        )
+                    })
+                }
+            },
+        };
+        if exit_code != self.spec.exit_code {
+            anyhow::bail!(
+                "exited with {}, expected {}, with stderr: \"{}\"",
+                exit_code,
+                self.spec.exit_code,
+                get_stdio_output(&stderr_rx)?
+            );
+        }
+        if let Some(expected) = &self.spec.stdout {
+            let stdout = get_stdio_output(&stdout_rx)?;
+            if &stdout != expected {
+                anyhow::bail!("printed \"{}\", expected \"{}\"", stdout, expected);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the tests of the testsuite in `root`, sorted by name.
+pub fn wasi_testsuite_cases(root: &Path) -> anyhow::Result<Vec<WasiTestsuiteCase>> {
+    fn visit(root: &Path, dir: &Path, cases: &mut Vec<WasiTestsuiteCase>) -> anyhow::Result<()> {
+        for entry in read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                visit(root, &path, cases)?;
+            } else if path.extension().map_or(false, |ext| ext == "wasm") {
+                cases.push(WasiTestsuiteCase::load(root, &path)?);
+            }
+        }
+        Ok(())
+    }
+
+    let mut cases = Vec::new();
+    visit(root, root, &mut cases)?;
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+/// Whether the tests of the testsuite conform, on each kind of file
+/// system. It displays as a table, one row per test.
+#[derive(Debug, Default)]
+pub struct ConformanceMatrix {
+    rows: Vec<(String, Vec<Result<(), String>>)>,
+}
+
+impl ConformanceMatrix {
+    /// Runs all the tests of the testsuite in `root`, against each of
+    /// the [`FILE_SYSTEM_KINDS`].
+    pub fn run(store: &Store, root: &Path) -> anyhow::Result<Self> {
+        let rows = wasi_testsuite_cases(root)?
+            .into_iter()
+            .map(|case| {
+                let outcomes = FILE_SYSTEM_KINDS
+                    .iter()
+                    .map(|kind| case.run(store, *kind).map_err(|e| format!("{:#}", e)))
+                    .collect();
+                (case.name, outcomes)
+            })
+            .collect();
+
+        Ok(Self { rows })
+    }
+
+    /// The number of tests which conform on each kind of file system,
+    /// out of the number of tests.
+    pub fn passed(&self) -> (Vec<usize>, usize) {
+        let passed = (0..FILE_SYSTEM_KINDS.len())
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .filter(|(_, outcomes)| outcomes[column].is_ok())
+                    .count()
+            })
+            .collect();
+        (passed, self.rows.len())
+    }
+
+    /// The tests which don't conform on at least one kind of file
+    /// system, with why, by kind of file system.
+    pub fn failures(&self) -> impl Iterator<Item = (&str, WasiFileSystemKind, &str)> {
+        self.rows.iter().flat_map(|(name, outcomes)| {
+            outcomes
+                .iter()
+                .zip(FILE_SYSTEM_KINDS.iter())
+                .filter_map(move |(outcome, kind)| {
+                    outcome
+                        .as_ref()
+                        .err()
+                        .map(|reason| (name.as_str(), *kind, reason.as_str()))
+                })
+        })
+    }
+}
+
+impl fmt::Display for ConformanceMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .rows
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("test".len());
+        write!(f, "{:width$}", "test", width = width)?;
+        for kind in FILE_SYSTEM_KINDS.iter() {
+            write!(f, " | {:8}", format!("{:?}", kind))?;
+        }
+        writeln!(f)?;
+
+        for (name, outcomes) in &self.rows {
+            write!(f, "{:width$}", name, width = width)?;
+            for outcome in outcomes {
+                write!(f, " | {:8}", if outcome.is_ok() { "ok" } else { "FAIL" })?;
+            }
+            writeln!(f)?;
+        }
+
+        let (passed, total) = self.passed();
+        write!(f, "{:width$}", "passed", width = width)?;
+        for passed in passed {
+            write!(f, " | {:8}", format!("{}/{}", passed, total))?;
+        }
+        writeln!(f)
+    }
+}
@@ -13,7 +13,7 @@ use wasmer_wasi::{
 use wast::parser::{self, Parse, ParseBuffer, Parser};
 
 /// The kind of filesystem `WasiTest` is going to use.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WasiFileSystemKind {
     /// Instruct the test runner to use `wasmer_vfs::host_fs`.
     Host,
@@ -40,7 +40,7 @@ pub struct WasiTest<'a> {
 // TODO: add `test_fs` here to sandbox better
 const BASE_TEST_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../wasi-wast/wasi/");
 
-fn get_stdio_output(rx: &mpsc::Receiver<Vec<u8>>) -> anyhow::Result<String> {
+pub(crate) fn get_stdio_output(rx: &mpsc::Receiver<Vec<u8>>) -> anyhow::Result<String> {
     let mut stdio = Vec::new();
     while let Ok(mut buf) = rx.try_recv() {
         stdio.append(&mut buf);
@@ -520,12 +520,12 @@ mod test {
 }
 
 #[derive(Debug, Clone)]
-struct OutputCapturerer {
+pub(crate) struct OutputCapturerer {
     output: Arc<Mutex<mpsc::Sender<Vec<u8>>>>,
 }
 
 impl OutputCapturerer {
-    fn new() -> (Self, mpsc::Receiver<Vec<u8>>) {
+    pub(crate) fn new() -> (Self, mpsc::Receiver<Vec<u8>>) {
         let (tx, rx) = mpsc::channel();
         (
             Self {
@@ -629,7 +629,7 @@ impl VirtualFile for OutputCapturerer {
 /// When using `wasmer_vfs::mem_fs`, we cannot rely on `BASE_TEST_DIR`
 /// because the host filesystem cannot be used. Instead, we are
 /// copying `BASE_TEST_DIR` to the `mem_fs`.
-fn map_host_fs_to_mem_fs(
+pub(crate) fn map_host_fs_to_mem_fs(
     fs: &mem_fs::FileSystem,
     directory_reader: ReadDir,
     path_prefix: &Path,